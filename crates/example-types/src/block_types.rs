@@ -14,19 +14,20 @@ use async_trait::async_trait;
 use committable::{Commitment, Committable, RawCommitmentBuilder};
 use hotshot_types::{
     data::{BlockError, Leaf2},
+    hlc::HybridTimestamp,
     traits::{
         block_contents::{BlockHeader, BuilderFee, EncodeBytes, TestableBlock, Transaction},
         node_implementation::NodeType,
         BlockPayload, ValidatedState,
     },
     utils::BuilderCommitment,
+    validator_ops::OperationTransaction,
     vid::{VidCommitment, VidCommon},
 };
 use rand::{thread_rng, Rng};
 use serde::{Deserialize, Serialize};
 use sha3::{Digest, Keccak256};
 use thiserror::Error;
-use time::OffsetDateTime;
 use vbs::version::Version;
 
 use crate::{
@@ -129,6 +130,16 @@ impl Transaction for TestTransaction {
     }
 }
 
+impl OperationTransaction for TestTransaction {
+    fn from_operation_bytes(bytes: Vec<u8>) -> Option<Self> {
+        Self::try_new(bytes)
+    }
+
+    fn operation_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
 /// A [`BlockPayload`] that contains a list of `TestTransaction`.
 #[derive(PartialEq, Eq, Hash, Serialize, Deserialize, Clone, Debug)]
 pub struct TestBlockPayload {
@@ -265,7 +276,7 @@ pub struct TestBlockHeader {
     /// block metadata
     pub metadata: TestMetadata,
     /// Timestamp when this header was created.
-    pub timestamp: u64,
+    pub timestamp: HybridTimestamp,
     /// random
     pub random: u64,
 }
@@ -279,12 +290,6 @@ impl TestBlockHeader {
     ) -> Self {
         let parent = parent_leaf.block_header();
 
-        let mut timestamp = OffsetDateTime::now_utc().unix_timestamp() as u64;
-        if timestamp < parent.timestamp {
-            // Prevent decreasing timestamps.
-            timestamp = parent.timestamp;
-        }
-
         let random = thread_rng().gen_range(0..=u64::MAX);
 
         Self {
@@ -292,7 +297,7 @@ impl TestBlockHeader {
             payload_commitment,
             builder_commitment,
             metadata,
-            timestamp,
+            timestamp: HybridTimestamp::successor(parent.timestamp),
             random,
         }
     }
@@ -301,11 +306,12 @@ impl TestBlockHeader {
 impl<
         TYPES: NodeType<
             BlockHeader = Self,
-            BlockPayload = TestBlockPayload,
             InstanceState = TestInstanceState,
             AuctionResult = TestAuctionResult,
         >,
     > BlockHeader<TYPES> for TestBlockHeader
+where
+    TYPES::BlockPayload: BlockPayload<TYPES, Metadata = TestMetadata>,
 {
     type Error = std::convert::Infallible;
 
@@ -366,7 +372,7 @@ impl<
             payload_commitment,
             builder_commitment,
             metadata,
-            timestamp: 0,
+            timestamp: HybridTimestamp::default(),
             random: 0,
         }
     }
@@ -390,6 +396,10 @@ impl<
     fn get_auction_results(&self) -> Option<TYPES::AuctionResult> {
         Some(TYPES::AuctionResult { urls: vec![] })
     }
+
+    fn timestamp(&self) -> Option<HybridTimestamp> {
+        Some(self.timestamp)
+    }
 }
 
 impl Committable for TestBlockHeader {
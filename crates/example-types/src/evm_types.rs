@@ -0,0 +1,574 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A reference EVM-compatible state machine, built on revm.
+//!
+//! [`EvmTypes`] plugs EVM transactions, blocks and state into the standard traits. Replicas only
+//! see block headers while voting, so [`EvmState`] is advanced by header during consensus and
+//! blocks are executed once decided, by [`EvmApplication`]. The executed state is committed to
+//! by its state root.
+
+use std::{collections::BTreeMap, fmt::Display, sync::Arc};
+
+use async_lock::RwLock;
+use async_trait::async_trait;
+use bincode::Options;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use hotshot::traits::election::static_committee::StaticCommittee;
+use hotshot_types::{
+    data::{BlockError, EpochNumber, Leaf2, ViewNumber},
+    signature_key::{BLSPubKey, BuilderKey},
+    traits::{
+        application::Application,
+        block_contents::{BlockHeader, EncodeBytes, Transaction},
+        commitment::KeccakCommitments,
+        node_implementation::NodeType,
+        states::StateDelta,
+        BlockPayload, ValidatedState,
+    },
+    utils::{bincode_opts, BuilderCommitment},
+    vid::VidCommon,
+};
+use revm::{
+    db::{AccountState, CacheDB, EmptyDB},
+    primitives::{AccountInfo, Address, Bytecode, Bytes, TxKind, KECCAK_EMPTY, U256},
+    Evm,
+};
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
+use vbs::version::Version;
+
+use crate::{
+    auction_results_provider_types::TestAuctionResult,
+    block_types::{TestBlockHeader, TestMetadata},
+    state_types::TestInstanceState,
+};
+
+/// Gas limit of a single block
+pub const EVM_BLOCK_GAS_LIMIT: u64 = 30_000_000;
+
+/// An EVM transaction.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EvmTransaction {
+    /// Sender of the transaction
+    pub caller: Address,
+    /// Callee, or `None` to create a contract
+    pub to: Option<Address>,
+    /// Value transferred to the callee
+    pub value: U256,
+    /// Call data, or init code for contract creation
+    pub data: Bytes,
+    /// Maximum gas the transaction may use
+    pub gas_limit: u64,
+    /// Nonce of the sender
+    pub nonce: u64,
+}
+
+impl Committable for EvmTransaction {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("EVM Txn Comm")
+            .fixed_size_bytes(&self.caller.into_array())
+            .var_size_bytes(self.to.as_ref().map_or(&[][..], |to| to.as_slice()))
+            .fixed_size_bytes(&self.value.to_be_bytes::<32>())
+            .var_size_bytes(&self.data)
+            .u64(self.gas_limit)
+            .u64(self.nonce)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "EVM_TXN".to_string()
+    }
+}
+
+impl Transaction for EvmTransaction {
+    fn minimum_block_size(&self) -> u64 {
+        bincode_opts()
+            .serialized_size(self)
+            .expect("EVM transactions are serializable")
+    }
+
+    fn gas(&self) -> u64 {
+        self.gas_limit
+    }
+}
+
+/// A block of EVM transactions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EvmBlockPayload {
+    /// Transactions, in execution order
+    pub transactions: Vec<EvmTransaction>,
+}
+
+impl Display for EvmBlockPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EvmBlockPayload #txns={}", self.transactions.len())
+    }
+}
+
+impl EncodeBytes for EvmBlockPayload {
+    fn encode(&self) -> Arc<[u8]> {
+        bincode_opts()
+            .serialize(&self.transactions)
+            .expect("EVM transactions are serializable")
+            .into()
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> BlockPayload<TYPES> for EvmBlockPayload {
+    type Error = BlockError;
+    type Instance = TestInstanceState;
+    type Transaction = EvmTransaction;
+    type Metadata = TestMetadata;
+    type ValidatedState = EvmState;
+
+    async fn from_transactions(
+        transactions: impl IntoIterator<Item = Self::Transaction> + Send,
+        _validated_state: &Self::ValidatedState,
+        _instance_state: &Self::Instance,
+    ) -> Result<(Self, Self::Metadata), Self::Error> {
+        // Pack transactions until the block gas limit is reached.
+        let mut gas = 0u64;
+        let transactions: Vec<_> = transactions
+            .into_iter()
+            .filter(|txn| {
+                let fits = gas.saturating_add(txn.gas_limit) <= EVM_BLOCK_GAS_LIMIT;
+                if fits {
+                    gas += txn.gas_limit;
+                }
+                fits
+            })
+            .collect();
+        let metadata = TestMetadata {
+            num_transactions: transactions.len() as u64,
+        };
+
+        Ok((Self { transactions }, metadata))
+    }
+
+    fn from_bytes(encoded_transactions: &[u8], _metadata: &Self::Metadata) -> Self {
+        let transactions = bincode_opts()
+            .deserialize(encoded_transactions)
+            .unwrap_or_else(|e| {
+                tracing::warn!("Failed to decode EVM block payload: {e}");
+                Vec::new()
+            });
+
+        Self { transactions }
+    }
+
+    fn empty() -> (Self, Self::Metadata) {
+        (
+            Self::default(),
+            TestMetadata {
+                num_transactions: 0,
+            },
+        )
+    }
+
+    fn builder_commitment(&self, _metadata: &Self::Metadata) -> BuilderCommitment {
+        let mut digest = sha2::Sha256::new();
+        digest.update(self.encode());
+        BuilderCommitment::from_raw_digest(digest.finalize())
+    }
+
+    fn transactions<'a>(
+        &'a self,
+        _metadata: &'a Self::Metadata,
+    ) -> impl 'a + Iterator<Item = Self::Transaction> {
+        self.transactions.iter().cloned()
+    }
+}
+
+/// An account in the EVM state.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EvmAccount {
+    /// Balance in wei
+    pub balance: U256,
+    /// Number of transactions sent from this account
+    pub nonce: u64,
+    /// Contract code, empty for externally owned accounts
+    pub code: Bytes,
+    /// Contract storage
+    pub storage: BTreeMap<U256, U256>,
+}
+
+/// Result of executing a block.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EvmDelta {
+    /// Total gas used by the block
+    pub gas_used: u64,
+    /// Transactions which failed or reverted, with the reason
+    pub failed: Vec<(Commitment<EvmTransaction>, String)>,
+}
+
+impl StateDelta for EvmDelta {}
+
+/// Errors applying a block to the EVM state
+#[derive(Debug, Error)]
+pub enum EvmError {
+    /// The block does not extend the state
+    #[error("Block {block} does not extend state at height {height}")]
+    WrongHeight {
+        /// Number of the block
+        block: u64,
+        /// Height of the state
+        height: u64,
+    },
+    /// The transaction can never be executed
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+}
+
+/// The EVM world state.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EvmState {
+    /// Number of blocks applied to this state
+    pub block_height: u64,
+    /// All accounts
+    pub accounts: BTreeMap<Address, EvmAccount>,
+}
+
+impl EvmState {
+    /// A state with the given account balances, e.g. for a genesis allocation.
+    #[must_use]
+    pub fn with_balances(balances: impl IntoIterator<Item = (Address, U256)>) -> Self {
+        Self {
+            block_height: 0,
+            accounts: balances
+                .into_iter()
+                .map(|(address, balance)| {
+                    (
+                        address,
+                        EvmAccount {
+                            balance,
+                            ..EvmAccount::default()
+                        },
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Keccak root over all accounts, their code and storage.
+    #[must_use]
+    pub fn state_root(&self) -> [u8; 32] {
+        let mut hasher = Keccak256::new();
+        for (address, account) in &self.accounts {
+            hasher.update(address);
+            hasher.update(account.balance.to_be_bytes::<32>());
+            hasher.update(account.nonce.to_be_bytes());
+            hasher.update(Keccak256::digest(&account.code));
+            for (slot, value) in &account.storage {
+                hasher.update(slot.to_be_bytes::<32>());
+                hasher.update(value.to_be_bytes::<32>());
+            }
+        }
+        hasher.finalize().into()
+    }
+
+    /// Execute `transactions` as block `block_number`, returning the new state.
+    ///
+    /// Transactions which fail or revert use gas and are reported in the delta, but don't stop
+    /// the block.
+    ///
+    /// # Errors
+    /// If `block_number` does not directly follow this state.
+    pub fn execute(
+        &self,
+        block_number: u64,
+        transactions: &[EvmTransaction],
+    ) -> Result<(Self, EvmDelta), EvmError> {
+        if block_number != self.block_height + 1 {
+            return Err(EvmError::WrongHeight {
+                block: block_number,
+                height: self.block_height,
+            });
+        }
+
+        let mut db = CacheDB::new(EmptyDB::default());
+        for (address, account) in &self.accounts {
+            let code = (!account.code.is_empty()).then(|| Bytecode::new_raw(account.code.clone()));
+            let info = AccountInfo {
+                balance: account.balance,
+                nonce: account.nonce,
+                code_hash: code.as_ref().map_or(KECCAK_EMPTY, Bytecode::hash_slow),
+                code,
+            };
+            db.insert_account_info(*address, info);
+            for (slot, value) in &account.storage {
+                db.insert_account_storage(*address, *slot, *value)
+                    .expect("the empty database is infallible");
+            }
+        }
+
+        let mut evm = Evm::builder()
+            .with_db(db)
+            .modify_block_env(|block| {
+                block.number = U256::from(block_number);
+                block.gas_limit = U256::from(EVM_BLOCK_GAS_LIMIT);
+            })
+            .build();
+
+        let mut delta = EvmDelta::default();
+        for txn in transactions {
+            let env = evm.tx_mut();
+            env.caller = txn.caller;
+            env.transact_to = txn.to.map_or(TxKind::Create, TxKind::Call);
+            env.value = txn.value;
+            env.data = txn.data.clone();
+            env.gas_limit = txn.gas_limit;
+            env.nonce = Some(txn.nonce);
+
+            match evm.transact_commit() {
+                Ok(result) => {
+                    delta.gas_used += result.gas_used();
+                    if !result.is_success() {
+                        delta
+                            .failed
+                            .push((txn.commit(), format!("{:?}", result.output())));
+                    }
+                }
+                Err(e) => delta.failed.push((txn.commit(), e.to_string())),
+            }
+        }
+
+        let (db, _) = evm.into_db_and_env_with_handler_cfg();
+        let accounts = db
+            .accounts
+            .into_iter()
+            // Accounts which were only looked up don't belong in the state.
+            .filter(|(_, account)| account.account_state != AccountState::NotExisting)
+            .map(|(address, account)| {
+                (
+                    address,
+                    EvmAccount {
+                        balance: account.info.balance,
+                        nonce: account.info.nonce,
+                        code: account
+                            .info
+                            .code
+                            .map(|code| code.original_bytes())
+                            .unwrap_or_default(),
+                        storage: account.storage.into_iter().collect(),
+                    },
+                )
+            })
+            .collect();
+
+        Ok((
+            Self {
+                block_height: block_number,
+                accounts,
+            },
+            delta,
+        ))
+    }
+}
+
+impl Committable for EvmState {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("EVM State Comm")
+            .u64_field("block_height", self.block_height)
+            .fixed_size_field("state_root", &self.state_root())
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "EVM_STATE".to_string()
+    }
+}
+
+impl<TYPES: NodeType> ValidatedState<TYPES> for EvmState {
+    type Error = EvmError;
+    type Instance = TestInstanceState;
+    type Delta = EvmDelta;
+    type Time = ViewNumber;
+
+    async fn validate_and_apply_header(
+        &self,
+        _instance: &Self::Instance,
+        _parent_leaf: &Leaf2<TYPES>,
+        proposed_header: &TYPES::BlockHeader,
+        _vid_common: VidCommon,
+        _version: Version,
+        _view_number: u64,
+    ) -> Result<(Self, Self::Delta), Self::Error> {
+        let block = proposed_header.block_number();
+        if block != self.block_height + 1 {
+            return Err(EvmError::WrongHeight {
+                block,
+                height: self.block_height,
+            });
+        }
+
+        // The payload is executed on decide, see `EvmApplication`.
+        Ok((
+            Self {
+                block_height: block,
+                accounts: self.accounts.clone(),
+            },
+            EvmDelta::default(),
+        ))
+    }
+
+    fn from_header(block_header: &TYPES::BlockHeader) -> Self {
+        Self {
+            block_height: block_header.block_number(),
+            ..Self::default()
+        }
+    }
+
+    fn on_commit(&self) {}
+
+    fn genesis(_instance: &Self::Instance) -> (Self, Self::Delta) {
+        (Self::default(), EvmDelta::default())
+    }
+}
+
+/// Executes decided EVM blocks and keeps the canonical executed state.
+#[derive(Debug, Default)]
+pub struct EvmApplication {
+    /// State after the last finalized block
+    state: RwLock<EvmState>,
+}
+
+impl EvmApplication {
+    /// Start executing on top of `genesis`.
+    #[must_use]
+    pub fn new(genesis: EvmState) -> Self {
+        Self {
+            state: RwLock::new(genesis),
+        }
+    }
+
+    /// The state after the last finalized block.
+    pub async fn state(&self) -> EvmState {
+        self.state.read().await.clone()
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType<Transaction = EvmTransaction>> Application<TYPES> for EvmApplication {
+    type Error = EvmError;
+
+    async fn check_tx(&self, transaction: &EvmTransaction) -> Result<(), EvmError> {
+        if transaction.gas_limit > EVM_BLOCK_GAS_LIMIT {
+            return Err(EvmError::InvalidTransaction(format!(
+                "gas limit {} exceeds the block gas limit",
+                transaction.gas_limit
+            )));
+        }
+
+        let state = self.state.read().await;
+        let nonce = state
+            .accounts
+            .get(&transaction.caller)
+            .map_or(0, |account| account.nonce);
+        if transaction.nonce < nonce {
+            return Err(EvmError::InvalidTransaction(format!(
+                "nonce {} was already used",
+                transaction.nonce
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn finalize_block(
+        &self,
+        leaf: &Leaf2<TYPES>,
+        transactions: Vec<EvmTransaction>,
+    ) -> Result<(), EvmError> {
+        let mut state = self.state.write().await;
+        // Genesis carries no transactions.
+        if leaf.height() == 0 {
+            return Ok(());
+        }
+
+        let (new_state, delta) = state.execute(leaf.height(), &transactions)?;
+        tracing::info!(
+            "Executed EVM block {} with {} transactions using {} gas, {} failed",
+            leaf.height(),
+            transactions.len(),
+            delta.gas_used,
+            delta.failed.len()
+        );
+        *state = new_state;
+
+        Ok(())
+    }
+}
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// Node types for a chain of EVM blocks
+pub struct EvmTypes;
+impl NodeType for EvmTypes {
+    const EPOCH_HEIGHT: u64 = 10;
+
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = TestBlockHeader;
+    type BlockPayload = EvmBlockPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = EvmTransaction;
+    type ValidatedState = EvmState;
+    type InstanceState = TestInstanceState;
+    type Membership = StaticCommittee<EvmTypes>;
+    type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
+}
+
+#[cfg(test)]
+mod test {
+    use revm::primitives::address;
+
+    use super::*;
+
+    #[test]
+    fn transfer_updates_balances_and_root() {
+        let alice = address!("1000000000000000000000000000000000000001");
+        let bob = address!("2000000000000000000000000000000000000002");
+        let genesis = EvmState::with_balances([(alice, U256::from(1_000_000))]);
+
+        let transfer = EvmTransaction {
+            caller: alice,
+            to: Some(bob),
+            value: U256::from(10),
+            data: Bytes::new(),
+            gas_limit: 21_000,
+            nonce: 0,
+        };
+        let (state, delta) = genesis.execute(1, &[transfer.clone()]).unwrap();
+
+        assert_eq!(delta.gas_used, 21_000);
+        assert!(delta.failed.is_empty());
+        assert_eq!(state.accounts[&bob].balance, U256::from(10));
+        assert_eq!(state.accounts[&alice].nonce, 1);
+        assert_ne!(state.state_root(), genesis.state_root());
+
+        // Replaying the transaction fails on its nonce, without aborting the block.
+        let (_, delta) = state.execute(2, &[transfer]).unwrap();
+        assert_eq!(delta.failed.len(), 1);
+
+        assert!(genesis.execute(2, &[]).is_err());
+    }
+}
@@ -13,6 +13,10 @@ pub mod state_types;
 /// node types
 pub mod node_types;
 
+/// reference EVM state machine
+#[cfg(feature = "evm")]
+pub mod evm_types;
+
 /// storage types for hotshot storage
 pub mod storage_types;
 
@@ -22,8 +22,14 @@ use hotshot::traits::{
 };
 use hotshot_types::{
     data::{EpochNumber, ViewNumber},
+    ordering::{
+        OpaqueTransaction, OrderedPayload, OrderingHeader, OrderingInstanceState, OrderingState,
+    },
     signature_key::{BLSPubKey, BuilderKey},
-    traits::node_implementation::{NodeType, Versions},
+    traits::{
+        commitment::KeccakCommitments,
+        node_implementation::{NodeType, Versions},
+    },
 };
 use serde::{Deserialize, Serialize};
 use vbs::version::StaticVersion;
@@ -65,6 +71,39 @@ impl NodeType for TestTypes {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommittee<TestTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
+}
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// Node types for an ordering-only sequencer, whose blocks are opaque payloads
+pub struct OrderingTypes;
+impl NodeType for OrderingTypes {
+    const EPOCH_HEIGHT: u64 = 10;
+
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = OrderingHeader;
+    type BlockPayload = OrderedPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = OpaqueTransaction;
+    type ValidatedState = OrderingState;
+    type InstanceState = OrderingInstanceState;
+    type Membership = StaticCommittee<OrderingTypes>;
+    type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 #[derive(
@@ -97,6 +136,7 @@ impl NodeType for TestTypesRandomizedLeader {
     type InstanceState = TestInstanceState;
     type Membership = RandomizedCommittee<TestTypesRandomizedLeader>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 #[derive(
@@ -133,6 +173,7 @@ impl<CONFIG: QuorumFilterConfig> NodeType for TestTypesRandomizedCommitteeMember
     type Membership =
         RandomizedCommitteeMembers<TestTypesRandomizedCommitteeMembers<CONFIG>, CONFIG>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 #[derive(
@@ -165,6 +206,7 @@ impl NodeType for TestConsecutiveLeaderTypes {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommitteeLeaderForTwoViews<TestConsecutiveLeaderTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 #[derive(
@@ -197,6 +239,7 @@ impl NodeType for TestTwoStakeTablesTypes {
     type InstanceState = TestInstanceState;
     type Membership = TwoStaticCommittees<TestTwoStakeTablesTypes>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 /// The Push CDN implementation
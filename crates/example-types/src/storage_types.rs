@@ -13,7 +13,7 @@ use anyhow::{bail, Result};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use hotshot_types::{
-    consensus::CommitmentMap,
+    consensus::{CommitmentMap, LeafMap},
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
         VidDisperseShare2,
@@ -23,7 +23,7 @@ use hotshot_types::{
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
     traits::{
         node_implementation::{ConsensusTime, NodeType},
-        storage::Storage,
+        storage::{Storage, StorageKey, StoredValue, ViewUndo, ViewWrites},
     },
     utils::View,
     vid::VidSchemeType,
@@ -54,6 +54,8 @@ pub struct TestStorageState<TYPES: NodeType> {
     high_qc2: Option<hotshot_types::simple_certificate::QuorumCertificate2<TYPES>>,
     action: TYPES::View,
     epoch: TYPES::Epoch,
+    undo_log: BTreeMap<TYPES::View, ViewUndo<TYPES>>,
+    decided_leaf: Option<Leaf2<TYPES>>,
 }
 
 impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
@@ -69,6 +71,8 @@ impl<TYPES: NodeType> Default for TestStorageState<TYPES> {
             high_qc2: None,
             action: TYPES::View::genesis(),
             epoch: TYPES::Epoch::genesis(),
+            undo_log: BTreeMap::new(),
+            decided_leaf: None,
         }
     }
 }
@@ -121,6 +125,34 @@ impl<TYPES: NodeType> TestStorage<TYPES> {
     pub async fn last_actioned_epoch(&self) -> TYPES::Epoch {
         self.inner.read().await.epoch
     }
+    /// The newest leaf recorded with [`Self::record_decided_leaf`].
+    pub async fn decided_leaf(&self) -> Option<Leaf2<TYPES>> {
+        self.inner.read().await.decided_leaf.clone()
+    }
+    /// Persist `leaf` as decided, as an application does on a decide event, so that the node can
+    /// be rebuilt from its storage.
+    pub async fn record_decided_leaf(&self, leaf: Leaf2<TYPES>) {
+        let mut inner = self.inner.write().await;
+        if inner
+            .decided_leaf
+            .as_ref()
+            .map_or(true, |decided| leaf.view_number() > decided.view_number())
+        {
+            inner.decided_leaf = Some(leaf);
+        }
+    }
+    /// A copy of this storage which shares nothing with it, as a restarted node gets when it
+    /// opens the storage it persisted to.
+    pub async fn reopen(&self) -> Self {
+        Self {
+            inner: Arc::new(RwLock::new(self.inner.read().await.clone())),
+            should_return_err: self.should_return_err,
+            delay_config: self.delay_config.clone(),
+            decided_upgrade_certificate: Arc::new(RwLock::new(
+                self.decided_upgrade_certificate.read().await.clone(),
+            )),
+        }
+    }
 }
 
 #[async_trait]
@@ -281,7 +313,7 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
     }
     async fn update_undecided_state2(
         &self,
-        _leaves: CommitmentMap<Leaf2<TYPES>>,
+        _leaves: LeafMap<TYPES>,
         _state: BTreeMap<TYPES::View, View<TYPES>>,
     ) -> Result<()> {
         if self.should_return_err {
@@ -290,6 +322,54 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
         Self::run_delay_settings_from_config(&self.delay_config).await;
         Ok(())
     }
+    async fn write_view(&self, writes: ViewWrites<TYPES>) -> Result<()> {
+        if self.should_return_err {
+            bail!("Failed to write view to storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        let mut undo = writes.proposal.as_ref().map(ViewUndo::new);
+        let mut record = |key: StorageKey<TYPES>, old: Option<StoredValue<TYPES>>| {
+            if let Some(undo) = &mut undo {
+                match old {
+                    Some(old) => undo.removed.push(old),
+                    None => undo.added.push(key),
+                }
+            }
+        };
+        if let Some(proposal) = writes.proposal {
+            let view = proposal.data.view_number;
+            let old = inner.proposals2.insert(view, proposal);
+            record(StorageKey::Proposal(view), old.map(StoredValue::Proposal));
+        }
+        if let Some(vid_share) = writes.vid_share {
+            let view = vid_share.data.view_number;
+            let recipient = vid_share.data.recipient_key.clone();
+            let old = inner
+                .vid2
+                .entry(view)
+                .or_default()
+                .insert(recipient.clone(), vid_share);
+            record(
+                StorageKey::VidShare(view, recipient),
+                old.map(StoredValue::VidShare),
+            );
+        }
+        if let Some(new_high_qc) = writes.high_qc {
+            if !inner
+                .high_qc2
+                .as_ref()
+                .is_some_and(|high_qc| new_high_qc.view_number() <= high_qc.view_number())
+            {
+                let old = inner.high_qc2.replace(new_high_qc);
+                record(StorageKey::HighQc, old.map(StoredValue::HighQc));
+            }
+        }
+        if let Some(undo) = undo {
+            inner.undo_log.insert(undo.view, undo);
+        }
+        Ok(())
+    }
     async fn update_decided_upgrade_certificate(
         &self,
         decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
@@ -316,4 +396,107 @@ impl<TYPES: NodeType> Storage<TYPES> for TestStorage<TYPES> {
 
         Ok(())
     }
+
+    async fn prune(&self, view: TYPES::View) -> Result<()> {
+        let mut inner = self.inner.write().await;
+        inner.vids.retain(|v, _| *v >= view);
+        inner.vid2.retain(|v, _| *v >= view);
+        inner.das.retain(|v, _| *v >= view);
+        inner.da2s.retain(|v, _| *v >= view);
+        inner.proposals = inner.proposals.split_off(&view);
+        inner.proposals2 = inner.proposals2.split_off(&view);
+        inner.undo_log = inner.undo_log.split_off(&view);
+
+        Ok(())
+    }
+
+    async fn revert_to(&self, view: TYPES::View) -> Result<Vec<ViewUndo<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to revert storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let mut inner = self.inner.write().await;
+        let reverted = inner.undo_log.split_off(&(view + 1));
+        for undo in reverted.values().rev() {
+            for key in &undo.added {
+                match key {
+                    StorageKey::Proposal(view) => {
+                        inner.proposals2.remove(view);
+                    }
+                    StorageKey::VidShare(view, recipient) => {
+                        if let Some(shares) = inner.vid2.get_mut(view) {
+                            shares.remove(recipient);
+                        }
+                    }
+                    StorageKey::HighQc => inner.high_qc2 = None,
+                }
+            }
+            for old in undo.removed.iter().cloned() {
+                match old {
+                    StoredValue::Proposal(proposal) => {
+                        inner.proposals2.insert(proposal.data.view_number, proposal);
+                    }
+                    StoredValue::VidShare(share) => {
+                        inner
+                            .vid2
+                            .entry(share.data.view_number)
+                            .or_default()
+                            .insert(share.data.recipient_key.clone(), share);
+                    }
+                    StoredValue::HighQc(high_qc) => inner.high_qc2 = Some(high_qc),
+                }
+            }
+        }
+
+        Ok(reverted.into_values().rev().collect())
+    }
+
+    async fn load(&self, key: &StorageKey<TYPES>) -> Result<Option<StoredValue<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to read from storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let inner = self.inner.read().await;
+        Ok(match key {
+            StorageKey::Proposal(view) => inner
+                .proposals2
+                .get(view)
+                .cloned()
+                .map(StoredValue::Proposal),
+            StorageKey::VidShare(view, recipient) => inner
+                .vid2
+                .get(view)
+                .and_then(|shares| shares.get(recipient))
+                .cloned()
+                .map(StoredValue::VidShare),
+            StorageKey::HighQc => inner.high_qc2.clone().map(StoredValue::HighQc),
+        })
+    }
+
+    async fn load_before(&self, view: TYPES::View) -> Result<Vec<StoredValue<TYPES>>> {
+        if self.should_return_err {
+            bail!("Failed to read from storage");
+        }
+        Self::run_delay_settings_from_config(&self.delay_config).await;
+        let inner = self.inner.read().await;
+        let mut values: Vec<_> = inner
+            .proposals2
+            .range(..view)
+            .map(|(view, proposal)| (*view, StoredValue::Proposal(proposal.clone())))
+            .collect();
+        values.extend(
+            inner
+                .vid2
+                .iter()
+                .filter(|(share_view, _)| **share_view < view)
+                .flat_map(|(view, shares)| {
+                    shares
+                        .values()
+                        .map(|share| (*view, StoredValue::VidShare(share.clone())))
+                }),
+        );
+        // The sort is stable, so each view's proposal stays ahead of its VID shares
+        values.sort_by_key(|(view, _)| *view);
+        Ok(values.into_iter().map(|(_, value)| value).collect())
+    }
 }
@@ -0,0 +1,232 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A replicated counter, run as a local network of HotShot nodes.
+//!
+//! This is an end-to-end reference for integrators: it defines an application (see [`types`]),
+//! then starts `--nodes` validators in this process, each with its own libp2p network stack,
+//! storage and copy of the application, together with a builder serving them blocks. It submits
+//! `--increments` transactions, round-robin across the nodes, and waits until every node has
+//! finalized all of them and agrees on the counter.
+//!
+//! ```text
+//! just example all-counter -- --nodes 5 --increments 100
+//! ```
+//!
+//! The steps a real deployment would take are the same, except that each node runs in its own
+//! process, with keys and peers taken from its configuration rather than derived from an index.
+
+/// types used for this example
+pub mod types;
+
+use std::{collections::HashMap, num::NonZeroUsize, sync::Arc, time::Duration};
+
+use clap::Parser;
+use futures::future::join_all;
+use hotshot::{
+    helpers::initialize_logging, remote_signer::configured_signer,
+    traits::implementations::Libp2pNetwork, types::SystemContextHandle, HotShotInitializer,
+    MarketplaceConfig, SystemContext,
+};
+use hotshot_example_types::{
+    auction_results_provider_types::TestAuctionResultsProvider, node_types::TestVersions,
+    storage_types::TestStorage,
+};
+use hotshot_testing::block_builder::{SimpleBuilderImplementation, TestBuilderImplementation};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::EpochNumber,
+    hotshot_config_file::HotShotConfigFile,
+    ordering::OrderingInstanceState,
+    traits::{
+        election::Membership,
+        network::{ConnectedNetwork, TestableNetworkingImplementation},
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    HotShotConfig, ValidatorConfig,
+};
+use tokio::time::{sleep, Instant};
+use url::Url;
+
+use crate::types::{Counter, CounterImpl, CounterTypes, Increment};
+
+/// Handle to a running counter node
+type Handle = SystemContextHandle<CounterTypes, CounterImpl, TestVersions>;
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "Replicated counter",
+    about = "Runs a replicated counter on a local network of HotShot nodes"
+)]
+/// Arguments of the counter demo
+struct Args {
+    /// Number of validators to run
+    #[arg(long, default_value_t = 5)]
+    nodes: usize,
+    /// Number of increments to submit
+    #[arg(long, default_value_t = 100)]
+    increments: u64,
+    /// How long to wait for every increment to be finalized, in seconds
+    #[arg(long, default_value_t = 120)]
+    timeout: u64,
+}
+
+/// The configuration shared by all nodes: every node is staked and in the DA committee, and
+/// blocks come from the builder at `builder_url`.
+fn config(
+    nodes: usize,
+    builder_url: Url,
+) -> HotShotConfig<<CounterTypes as NodeType>::SignatureKey> {
+    let peers: Vec<_> = (0..nodes as u64)
+        .map(|node_id| {
+            ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, true)
+                .public_config()
+        })
+        .collect();
+
+    let mut config = HotShotConfigFile::hotshot_config_5_nodes_10_da();
+    config.num_nodes_with_stake = NonZeroUsize::new(nodes).expect("At least one node");
+    config.known_nodes_with_stake.clone_from(&peers);
+    config.known_da_nodes = peers;
+    config.staked_da_nodes = nodes;
+    config.num_bootstrap = nodes;
+    config.builder_urls = vec![builder_url]
+        .try_into()
+        .expect("Non-empty by construction");
+    config.into()
+}
+
+/// Start a node with the given network, which is not yet connected to its peers.
+async fn start_node(
+    node_id: u64,
+    config: HotShotConfig<<CounterTypes as NodeType>::SignatureKey>,
+    network: Arc<Libp2pNetwork<CounterTypes>>,
+) -> Handle {
+    // Keys are derived from the node index, as the libp2p networks derive theirs
+    let validator = ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, true);
+
+    let (known_nodes_with_stake, known_da_nodes) = config.stake_tables(*EpochNumber::genesis());
+    let memberships =
+        <CounterTypes as NodeType>::Membership::new(known_nodes_with_stake, known_da_nodes);
+
+    let initializer = HotShotInitializer::from_genesis::<TestVersions>(OrderingInstanceState)
+        .await
+        .expect("Failed to build the genesis state");
+    let marketplace_config = MarketplaceConfig {
+        auction_results_provider: TestAuctionResultsProvider::default().into(),
+        fallback_builder_url: config.builder_urls.first().clone(),
+    };
+
+    let signer = configured_signer(
+        config.remote_signer.as_ref(),
+        validator.public_key,
+        validator.private_key,
+    );
+
+    SystemContext::init(
+        signer,
+        node_id,
+        config,
+        memberships,
+        network,
+        initializer,
+        ConsensusMetricsValue::default(),
+        TestStorage::default(),
+        marketplace_config,
+    )
+    .await
+    .expect("Failed to initialize the node")
+    .0
+}
+
+#[tokio::main]
+async fn main() {
+    initialize_logging();
+    let args = Args::parse();
+    assert!(args.nodes > 0, "The network needs at least one node");
+
+    // The builder assembles blocks from the transactions it sees on the network
+    let builder_port = portpicker::pick_unused_port().expect("No free ports");
+    let builder_url = Url::parse(&format!("http://localhost:{builder_port}")).unwrap();
+    let builder = <SimpleBuilderImplementation as TestBuilderImplementation<CounterTypes>>::start(
+        args.nodes,
+        builder_url.clone(),
+        (),
+        HashMap::new(),
+    )
+    .await;
+
+    // Every node gets its own libp2p stack, listening on a free local port
+    let config = config(args.nodes, builder_url);
+    let network_generator = Libp2pNetwork::<CounterTypes>::generator(
+        args.nodes,
+        args.nodes,
+        0,
+        args.nodes,
+        None,
+        Duration::ZERO,
+    );
+    let mut networks = Vec::new();
+    for node_id in 0..args.nodes as u64 {
+        networks.push(network_generator(node_id).await);
+    }
+    tracing::info!("Waiting for {} nodes to connect", args.nodes);
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    let mut handles: Vec<Handle> = Vec::new();
+    for (node_id, network) in (0..).zip(networks) {
+        handles.push(start_node(node_id, config.clone(), network).await);
+    }
+    builder.start(Box::new(handles[0].event_stream()));
+
+    // Each node applies decided blocks to its own copy of the counter
+    let counters: Vec<_> = handles
+        .iter()
+        .map(|_| Arc::new(Counter::default()))
+        .collect();
+    let _drivers: Vec<_> = handles
+        .iter()
+        .zip(&counters)
+        .map(|(handle, counter)| handle.spawn_application(Arc::clone(counter)))
+        .collect();
+
+    for handle in &handles {
+        handle.hotshot.start_consensus().await;
+    }
+
+    // Any node accepts transactions, after checking them against its copy of the application
+    for nonce in 0..args.increments {
+        let node = usize::try_from(nonce).unwrap() % handles.len();
+        let increment = Increment { amount: 1, nonce };
+        if let Err(e) = handles[node]
+            .submit_checked_transaction(&*counters[node], increment.to_transaction())
+            .await
+        {
+            tracing::error!("Node {node} did not accept increment {nonce}: {e}");
+        }
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(args.timeout);
+    let values = loop {
+        let values = join_all(counters.iter().map(|counter| counter.value())).await;
+        if values.iter().all(|value| *value == args.increments) || Instant::now() > deadline {
+            break values;
+        }
+        sleep(Duration::from_secs(1)).await;
+    };
+
+    for (node_id, value) in values.iter().enumerate() {
+        tracing::info!("Node {node_id} counted {value}");
+    }
+    for handle in &mut handles {
+        handle.shut_down().await;
+    }
+    assert!(
+        values.iter().all(|value| *value == args.increments),
+        "Not every node counted {} increments before the timeout",
+        args.increments
+    );
+}
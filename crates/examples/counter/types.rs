@@ -0,0 +1,194 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Types of the replicated counter.
+//!
+//! The counter is the smallest useful application: every transaction adds to a single `u64`,
+//! and all nodes agree on its value because they apply the same decided blocks in the same
+//! order. It is built from three pieces an integrator would write for their own application:
+//!
+//! * [`CounterTypes`], the [`NodeType`] tying together the block, header, transaction and state
+//!   types. Blocks are plain ordered lists of transactions, so it reuses the building blocks of
+//!   [`hotshot_types::ordering`] for them.
+//! * [`CounterImpl`], the [`NodeImplementation`] choosing the network and storage each node
+//!   runs with.
+//! * [`Counter`], the [`Application`] which checks transactions before they are submitted and
+//!   applies them once their block is decided.
+
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot::traits::{
+    election::static_committee::StaticCommittee, implementations::Libp2pNetwork, NodeImplementation,
+};
+use hotshot_example_types::{
+    auction_results_provider_types::{TestAuctionResult, TestAuctionResultsProvider},
+    storage_types::TestStorage,
+};
+use hotshot_types::{
+    data::{EpochNumber, Leaf2, ViewNumber},
+    ordering::{
+        OpaqueTransaction, OrderedPayload, OrderingHeader, OrderingInstanceState, OrderingState,
+    },
+    signature_key::{BLSPubKey, BuilderKey},
+    traits::{
+        application::Application, commitment::KeccakCommitments, node_implementation::NodeType,
+    },
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    Hash,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+/// Node type of the replicated counter
+pub struct CounterTypes;
+impl NodeType for CounterTypes {
+    const EPOCH_HEIGHT: u64 = 10;
+
+    type AuctionResult = TestAuctionResult;
+    type View = ViewNumber;
+    type Epoch = EpochNumber;
+    type BlockHeader = OrderingHeader;
+    type BlockPayload = OrderedPayload;
+    type SignatureKey = BLSPubKey;
+    type Transaction = OpaqueTransaction;
+    type ValidatedState = OrderingState;
+    type InstanceState = OrderingInstanceState;
+    type Membership = StaticCommittee<CounterTypes>;
+    type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
+}
+
+/// Node implementation of the replicated counter: libp2p networking and in-memory storage
+#[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq)]
+pub struct CounterImpl;
+
+impl NodeImplementation<CounterTypes> for CounterImpl {
+    type Network = Libp2pNetwork<CounterTypes>;
+    type Storage = TestStorage<CounterTypes>;
+    type AuctionResultsProvider = TestAuctionResultsProvider<CounterTypes>;
+}
+
+/// Why a counter transaction was rejected
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum CounterError {
+    /// The transaction is not an encoded [`Increment`]
+    #[error("malformed increment of {0} bytes")]
+    Malformed(usize),
+    /// The transaction would not change the counter
+    #[error("increment by zero")]
+    Zero,
+    /// Applying the transaction would overflow the counter
+    #[error("counter overflows at {0}")]
+    Overflow(u64),
+}
+
+/// A transaction adding `amount` to the counter.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Increment {
+    /// How much to add
+    pub amount: u64,
+    /// Distinguishes otherwise equal increments, which would be deduplicated as the same
+    /// transaction
+    pub nonce: u64,
+}
+
+impl Increment {
+    /// Length of an encoded increment
+    const ENCODED_LEN: usize = 16;
+
+    /// Encode the increment as a transaction: the amount then the nonce, both big-endian.
+    #[must_use]
+    pub fn to_transaction(self) -> OpaqueTransaction {
+        let mut bytes = Vec::with_capacity(Self::ENCODED_LEN);
+        bytes.extend_from_slice(&self.amount.to_be_bytes());
+        bytes.extend_from_slice(&self.nonce.to_be_bytes());
+        OpaqueTransaction(bytes)
+    }
+
+    /// Decode an increment from a transaction.
+    ///
+    /// # Errors
+    /// If the transaction is not an encoded increment.
+    pub fn from_transaction(transaction: &OpaqueTransaction) -> Result<Self, CounterError> {
+        let bytes = &transaction.0;
+        if bytes.len() != Self::ENCODED_LEN {
+            return Err(CounterError::Malformed(bytes.len()));
+        }
+        let (amount, nonce) = bytes.split_at(8);
+        Ok(Self {
+            amount: u64::from_be_bytes(amount.try_into().expect("8 bytes")),
+            nonce: u64::from_be_bytes(nonce.try_into().expect("8 bytes")),
+        })
+    }
+}
+
+/// The replicated counter, as seen by one node.
+#[derive(Debug, Default)]
+pub struct Counter {
+    /// Value after the last finalized block
+    value: RwLock<u64>,
+}
+
+impl Counter {
+    /// The value after the last finalized block.
+    pub async fn value(&self) -> u64 {
+        *self.value.read().await
+    }
+}
+
+#[async_trait]
+impl Application<CounterTypes> for Counter {
+    type Error = CounterError;
+
+    async fn check_tx(&self, transaction: &OpaqueTransaction) -> Result<(), CounterError> {
+        let increment = Increment::from_transaction(transaction)?;
+        if increment.amount == 0 {
+            return Err(CounterError::Zero);
+        }
+        Ok(())
+    }
+
+    async fn finalize_block(
+        &self,
+        leaf: &Leaf2<CounterTypes>,
+        transactions: Vec<OpaqueTransaction>,
+    ) -> Result<(), CounterError> {
+        let mut value = self.value.write().await;
+
+        // Anyone can send transactions straight to a builder, so the block may hold some which
+        // never went through `check_tx`. Malformed ones are skipped, and a block which would
+        // overflow the counter is not applied at all; every node does the same.
+        let mut new_value = *value;
+        let mut applied = 0;
+        for transaction in &transactions {
+            let Ok(increment) = Increment::from_transaction(transaction) else {
+                continue;
+            };
+            new_value = new_value
+                .checked_add(increment.amount)
+                .ok_or(CounterError::Overflow(new_value))?;
+            applied += 1;
+        }
+        *value = new_value;
+
+        tracing::info!(
+            "Block {} applied {applied} increments, counter is {new_value}",
+            leaf.height()
+        );
+        Ok(())
+    }
+}
@@ -21,6 +21,7 @@ use chrono::Utc;
 use clap::{value_parser, Arg, Command, Parser};
 use futures::StreamExt;
 use hotshot::{
+    remote_signer::configured_signer,
     traits::{
         implementations::{
             derive_libp2p_multiaddr, derive_libp2p_peer_id, CdnMetricsValue, CdnTopic,
@@ -368,18 +369,16 @@ pub trait RunDa<
         // Get KeyPair for certificate Aggregation
         let pk = validator_config.public_key.clone();
         let sk = validator_config.private_key.clone();
+        let signer = configured_signer(config.config.remote_signer.as_ref(), pk, sk);
 
         let network = self.network();
 
-        let all_nodes = if cfg!(feature = "fixed-leader-election") {
-            let mut vec = config.config.known_nodes_with_stake.clone();
-            vec.truncate(config.config.fixed_leader_for_gpuvid);
-            vec
-        } else {
-            config.config.known_nodes_with_stake.clone()
-        };
-
-        let da_nodes = config.config.known_da_nodes.clone();
+        let (mut all_nodes, da_nodes) = config
+            .config
+            .stake_tables(*<TYPES::Epoch as ConsensusTime>::genesis());
+        if cfg!(feature = "fixed-leader-election") {
+            all_nodes.truncate(config.config.fixed_leader_for_gpuvid);
+        }
 
         // Create the quorum membership from all nodes, specifying the committee
         // as the known da nodes
@@ -392,8 +391,7 @@ pub trait RunDa<
         };
 
         SystemContext::init(
-            pk,
-            sk,
+            signer,
             config.node_index,
             config.config,
             memberships,
@@ -518,8 +516,11 @@ pub trait RunDa<
                         EventType::ReplicaViewTimeout { view_number } => {
                             warn!("Timed out as a replicas in view {:?}", view_number);
                         }
-                        EventType::ViewTimeout { view_number } => {
-                            warn!("Timed out in view {:?}", view_number);
+                        EventType::ViewTimeout {
+                            view_number,
+                            diagnostics,
+                        } => {
+                            warn!("Timed out in view {:?}: {:?}", view_number, diagnostics);
                         }
                         _ => {} // mostly DA proposal
                     }
@@ -737,8 +738,9 @@ where
         };
 
         // Create the quorum membership from the list of known nodes
-        let all_nodes = config.config.known_nodes_with_stake.clone();
-        let da_nodes = config.config.known_da_nodes.clone();
+        let (all_nodes, da_nodes) = config
+            .config
+            .stake_tables(*<TYPES::Epoch as ConsensusTime>::genesis());
         let quorum_membership = TYPES::Membership::new(all_nodes, da_nodes);
 
         // Derive the bind address
@@ -751,7 +753,7 @@ where
             quorum_membership,
             GossipConfig::default(),
             RequestResponseConfig::default(),
-            bind_address,
+            vec![bind_address],
             public_key,
             private_key,
             Libp2pMetricsValue::default(),
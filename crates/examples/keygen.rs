@@ -0,0 +1,61 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Generates fresh validator keys and writes them to an encrypted keystore.
+//!
+//! ```text
+//! HOTSHOT_KEYSTORE_PASSWORD=... just example keygen -- --out validator.json
+//! ```
+//!
+//! The public keys are printed, for registering the validator in the stake table.
+
+use std::path::PathBuf;
+
+use clap::Parser;
+use hotshot_types::{
+    keystore::{KdfParams, Keystore},
+    signature_key::BLSPubKey,
+    ValidatorConfig,
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "Key generation",
+    about = "Generates validator keys into a keystore"
+)]
+/// Arguments of the key generator
+struct Args {
+    /// Where to write the keystore. An existing file is never overwritten.
+    #[arg(long)]
+    out: PathBuf,
+    /// Password to encrypt the keystore with
+    #[arg(long, env = "HOTSHOT_KEYSTORE_PASSWORD", hide_env_values = true)]
+    password: String,
+    /// Number of PBKDF2 iterations deriving the encryption key from the password, no fewer than
+    /// `MIN_KDF_ITERATIONS`
+    #[arg(long, default_value_t = KdfParams::default().iterations)]
+    kdf_iterations: u32,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let config = ValidatorConfig::<BLSPubKey>::generate(1, false);
+    let keystore = Keystore::encrypt(
+        &config,
+        &args.password,
+        KdfParams {
+            iterations: args.kdf_iterations,
+        },
+    )?;
+    keystore.write(&args.out)?;
+
+    let peer = keystore.peer_config(1);
+    println!("Wrote keystore to {}", args.out.display());
+    println!("Consensus public key: {}", keystore.public_key());
+    println!("State verification key: {}", peer.state_ver_key);
+    Ok(())
+}
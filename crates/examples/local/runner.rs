@@ -0,0 +1,491 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Runs a local network of HotShot nodes and reports their progress.
+//!
+//! All nodes share a genesis: their keys are generated from their index, every node is staked,
+//! and the first `--da-nodes` form the DA committee. A builder serves them blocks. The runner
+//! prints every node's decided height every `--report-interval` seconds, and stops once each has
+//! decided `--decides` blocks.
+//!
+//! By default the nodes run as tasks of this process, over any of the networking backends:
+//!
+//! ```text
+//! just example local-runner -- --nodes 4 --network libp2p
+//! ```
+//!
+//! With `--processes`, every node runs in a child process instead, connected over libp2p on local
+//! ports. The output of each child is streamed with the index of its node as a prefix, which
+//! keeps the logs of one node apart from the others'.
+//!
+//! ```text
+//! just example local-runner -- --nodes 4 --processes
+//! ```
+
+use std::{
+    io::{BufRead, BufReader, Read},
+    num::NonZeroUsize,
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use clap::{Parser, ValueEnum};
+use futures::{future::join_all, StreamExt};
+use hotshot::{
+    helpers::initialize_logging,
+    remote_signer::configured_signer,
+    traits::{
+        implementations::{
+            derive_libp2p_multiaddr, derive_libp2p_peer_id, GossipConfig, Libp2pMetricsValue,
+            Libp2pNetwork, RequestResponseConfig,
+        },
+        NodeImplementation,
+    },
+    types::{EventType, SystemContextHandle},
+    HotShotInitializer, MarketplaceConfig, SystemContext,
+};
+use hotshot_example_types::{
+    auction_results_provider_types::TestAuctionResultsProvider,
+    node_types::{CombinedImpl, Libp2pImpl, MemoryImpl, PushCdnImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+    storage_types::TestStorage,
+};
+use hotshot_testing::block_builder::{SimpleBuilderImplementation, TestBuilderImplementation};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::EpochNumber,
+    hotshot_config_file::HotShotConfigFile,
+    network::{Libp2pConfig, NetworkConfig},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership,
+        network::{ConnectedNetwork, TestableNetworkingImplementation},
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    HotShotConfig, ValidatorConfig,
+};
+use tokio::time::sleep;
+use url::Url;
+
+/// Marks the lines a child process reports its decides on
+const DECIDE_MARKER: &str = "#decided";
+
+/// Networking backend of the nodes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+enum Backend {
+    /// In-memory channels
+    Memory,
+    /// libp2p over QUIC on local ports
+    Libp2p,
+    /// The push CDN, run in-process
+    PushCdn,
+    /// The push CDN with libp2p as a fallback
+    Combined,
+}
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "Local runner",
+    about = "Runs a local network of HotShot nodes and reports their progress"
+)]
+/// Arguments of the local runner
+struct Args {
+    /// Number of nodes to run
+    #[arg(long, default_value_t = 4)]
+    nodes: usize,
+    /// Number of nodes in the DA committee, all of them by default
+    #[arg(long)]
+    da_nodes: Option<usize>,
+    /// Networking backend of the nodes. Child processes always use libp2p.
+    #[arg(long, value_enum, default_value_t = Backend::Memory)]
+    network: Backend,
+    /// Run every node in a child process
+    #[arg(long)]
+    processes: bool,
+    /// Stop once every node decided this many blocks, or never if zero
+    #[arg(long, default_value_t = 20)]
+    decides: u64,
+    /// How often to report progress, in seconds
+    #[arg(long, default_value_t = 5)]
+    report_interval: u64,
+    /// Run only this node, as a child process of the runner
+    #[arg(long, hide = true)]
+    node: Option<u64>,
+    /// Ports of the nodes, as assigned to child processes
+    #[arg(long, hide = true, value_delimiter = ',')]
+    ports: Vec<u16>,
+    /// Port of the builder, as assigned to child processes
+    #[arg(long, hide = true)]
+    builder_port: Option<u16>,
+}
+
+impl Args {
+    /// Size of the DA committee.
+    fn da_nodes(&self) -> usize {
+        self.da_nodes.unwrap_or(self.nodes).min(self.nodes)
+    }
+}
+
+/// Handle to a running node
+type Handle<I> = SystemContextHandle<TestTypes, I, TestVersions>;
+
+/// The decided height of every node.
+struct Progress {
+    /// Decided height, by node index
+    heights: Vec<AtomicU64>,
+}
+
+impl Progress {
+    /// No node has decided anything yet.
+    fn new(nodes: usize) -> Arc<Self> {
+        Arc::new(Self {
+            heights: (0..nodes).map(|_| AtomicU64::new(0)).collect(),
+        })
+    }
+
+    /// Record that `node` decided up to `height`.
+    fn record(&self, node: usize, height: u64) {
+        self.heights[node].fetch_max(height, Ordering::Relaxed);
+    }
+
+    /// Print the height of every node, and return whether all of them reached `target`.
+    fn report(&self, target: u64) -> bool {
+        let heights: Vec<_> = self
+            .heights
+            .iter()
+            .map(|height| height.load(Ordering::Relaxed))
+            .collect();
+        let summary: Vec<_> = heights
+            .iter()
+            .enumerate()
+            .map(|(node, height)| format!("[node {node}] {height}"))
+            .collect();
+        println!("Decided heights: {}", summary.join(" | "));
+        target > 0 && heights.iter().all(|height| *height >= target)
+    }
+}
+
+/// Keys of node `node_id`, derived from its index.
+fn validator(node_id: u64, da_nodes: usize) -> ValidatorConfig<BLSPubKey> {
+    let is_da = node_id < da_nodes as u64;
+    ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da)
+}
+
+/// The genesis configuration shared by all nodes.
+fn genesis(args: &Args, builder_url: Url) -> HotShotConfig<BLSPubKey> {
+    let validators: Vec<_> = (0..args.nodes as u64)
+        .map(|node_id| validator(node_id, args.da_nodes()))
+        .collect();
+
+    let mut config = HotShotConfigFile::hotshot_config_5_nodes_10_da();
+    config.num_nodes_with_stake = NonZeroUsize::new(args.nodes).expect("At least one node");
+    config.known_nodes_with_stake = validators
+        .iter()
+        .map(ValidatorConfig::public_config)
+        .collect();
+    config.known_da_nodes = validators
+        .iter()
+        .filter(|v| v.is_da)
+        .map(ValidatorConfig::public_config)
+        .collect();
+    config.staked_da_nodes = args.da_nodes();
+    config.num_bootstrap = args.nodes;
+    config.builder_urls = vec![builder_url]
+        .try_into()
+        .expect("Non-empty by construction");
+    config.into()
+}
+
+/// A free local URL for the builder.
+fn builder_url(port: Option<u16>) -> Url {
+    let port = port.unwrap_or_else(|| portpicker::pick_unused_port().expect("No free ports"));
+    Url::parse(&format!("http://localhost:{port}")).unwrap()
+}
+
+/// Start a builder on `url`, building blocks from the events of `handle`.
+async fn start_builder<I: NodeImplementation<TestTypes>>(
+    nodes: usize,
+    url: Url,
+    handle: &Handle<I>,
+) {
+    <SimpleBuilderImplementation as TestBuilderImplementation<TestTypes>>::start(
+        nodes,
+        url,
+        (),
+        Default::default(),
+    )
+    .await
+    .start(Box::new(handle.event_stream()));
+}
+
+/// Initialize node `node_id` on `network`, without starting consensus.
+async fn start_node<I>(
+    node_id: u64,
+    config: HotShotConfig<BLSPubKey>,
+    network: Arc<I::Network>,
+) -> Handle<I>
+where
+    I: NodeImplementation<
+        TestTypes,
+        Storage = TestStorage<TestTypes>,
+        AuctionResultsProvider = TestAuctionResultsProvider<TestTypes>,
+    >,
+{
+    let validator = validator(node_id, config.da_staked_committee_size);
+    let (known_nodes_with_stake, known_da_nodes) = config.stake_tables(*EpochNumber::genesis());
+    let memberships =
+        <TestTypes as NodeType>::Membership::new(known_nodes_with_stake, known_da_nodes);
+    let initializer =
+        HotShotInitializer::from_genesis::<TestVersions>(TestInstanceState::default())
+            .await
+            .expect("Failed to build the genesis state");
+    let marketplace_config = MarketplaceConfig {
+        auction_results_provider: TestAuctionResultsProvider::default().into(),
+        fallback_builder_url: config.builder_urls.first().clone(),
+    };
+
+    let signer = configured_signer(
+        config.remote_signer.as_ref(),
+        validator.public_key,
+        validator.private_key,
+    );
+
+    SystemContext::init(
+        signer,
+        node_id,
+        config,
+        memberships,
+        network,
+        initializer,
+        ConsensusMetricsValue::default(),
+        TestStorage::default(),
+        marketplace_config,
+    )
+    .await
+    .expect("Failed to initialize the node")
+    .0
+}
+
+/// Report every height `handle` decides to `on_decide`.
+fn watch_decides<I: NodeImplementation<TestTypes>>(
+    handle: &Handle<I>,
+    on_decide: impl Fn(u64, u64) + Send + 'static,
+) {
+    let mut events = handle.event_stream();
+    tokio::spawn(async move {
+        while let Some(event) = events.next().await {
+            if let EventType::Decide { leaf_chain, .. } = event.event {
+                if let Some(newest) = leaf_chain.first() {
+                    on_decide(newest.leaf.height(), *newest.leaf.view_number());
+                }
+            }
+        }
+    });
+}
+
+/// Print the progress of the nodes until every one of them reached the target.
+async fn report_until_done(args: &Args, progress: &Progress) {
+    loop {
+        sleep(Duration::from_secs(args.report_interval)).await;
+        if progress.report(args.decides) {
+            println!("Every node decided {} blocks", args.decides);
+            return;
+        }
+    }
+}
+
+/// Run all nodes as tasks of this process.
+async fn run_in_process<I>(args: &Args)
+where
+    I: NodeImplementation<
+        TestTypes,
+        Storage = TestStorage<TestTypes>,
+        AuctionResultsProvider = TestAuctionResultsProvider<TestTypes>,
+    >,
+    I::Network: TestableNetworkingImplementation<TestTypes>,
+{
+    let builder_url = builder_url(None);
+    let config = genesis(args, builder_url.clone());
+
+    let generator = I::Network::generator(
+        args.nodes,
+        args.nodes,
+        0,
+        args.da_nodes(),
+        None,
+        Duration::ZERO,
+    );
+    let mut networks = Vec::new();
+    for node_id in 0..args.nodes as u64 {
+        networks.push(generator(node_id).await);
+    }
+    println!("Waiting for {} nodes to connect", args.nodes);
+    join_all(networks.iter().map(|network| network.wait_for_ready())).await;
+
+    let mut handles: Vec<Handle<I>> = Vec::new();
+    for (node_id, network) in (0..).zip(networks) {
+        handles.push(start_node::<I>(node_id, config.clone(), network).await);
+    }
+    start_builder(args.nodes, builder_url, &handles[0]).await;
+
+    let progress = Progress::new(args.nodes);
+    for (node, handle) in handles.iter().enumerate() {
+        let progress = Arc::clone(&progress);
+        watch_decides(handle, move |height, view| {
+            tracing::info!("[node {node}] decided height {height} in view {view}");
+            progress.record(node, height);
+        });
+    }
+    for handle in &handles {
+        handle.hotshot.start_consensus().await;
+    }
+
+    report_until_done(args, &progress).await;
+    for handle in &mut handles {
+        handle.shut_down().await;
+    }
+}
+
+/// Run a single node over libp2p, as a child process of the runner. Decides are reported on
+/// stdout after [`DECIDE_MARKER`].
+async fn run_child(args: &Args, node_id: u64) {
+    let address = |port: u16| {
+        derive_libp2p_multiaddr(&format!("127.0.0.1:{port}")).expect("Valid local address")
+    };
+    let builder_url = builder_url(args.builder_port);
+    let config = genesis(args, builder_url.clone());
+
+    // Every node knows where every other one listens, so they all bootstrap off each other
+    let bootstrap_nodes = (0..)
+        .zip(&args.ports)
+        .map(|(peer, port)| {
+            let private_key = validator(peer, args.da_nodes()).private_key;
+            let peer_id =
+                derive_libp2p_peer_id::<BLSPubKey>(&private_key).expect("Valid private key");
+            (peer_id, address(*port))
+        })
+        .collect();
+    let network_config = NetworkConfig {
+        node_index: node_id,
+        libp2p_config: Some(Libp2pConfig { bootstrap_nodes }),
+        config: config.clone(),
+        ..NetworkConfig::default()
+    };
+
+    let validator = validator(node_id, args.da_nodes());
+    let (known_nodes_with_stake, known_da_nodes) = config.stake_tables(*EpochNumber::genesis());
+    let network = Libp2pNetwork::from_config(
+        network_config,
+        <TestTypes as NodeType>::Membership::new(known_nodes_with_stake, known_da_nodes),
+        GossipConfig::default(),
+        RequestResponseConfig::default(),
+        vec![address(args.ports[usize::try_from(node_id).unwrap()])],
+        &validator.public_key,
+        &validator.private_key,
+        Libp2pMetricsValue::default(),
+    )
+    .await
+    .expect("Failed to start libp2p");
+    network.wait_for_ready().await;
+
+    let handle = start_node::<Libp2pImpl>(node_id, config, Arc::new(network)).await;
+    if node_id == 0 {
+        start_builder(args.nodes, builder_url, &handle).await;
+    }
+    watch_decides(&handle, |height, view| {
+        println!("{DECIDE_MARKER} {height} {view}");
+    });
+    handle.hotshot.start_consensus().await;
+
+    // Run until the runner stops us
+    std::future::pending::<()>().await;
+}
+
+/// Forward the lines of a child's `output`, prefixed with its node index, and record the decides
+/// it reports.
+fn forward_output(node: usize, output: impl Read + Send + 'static, progress: Arc<Progress>) {
+    thread::spawn(move || {
+        for line in BufReader::new(output).lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if let Some(decide) = line.strip_prefix(DECIDE_MARKER) {
+                let height = decide
+                    .split_whitespace()
+                    .next()
+                    .and_then(|h| h.parse().ok());
+                if let Some(height) = height {
+                    progress.record(node, height);
+                }
+            }
+            println!("[node {node}] {line}");
+        }
+    });
+}
+
+/// Run every node in a child process of this one.
+async fn run_processes(args: &Args) {
+    assert!(args.nodes > 1, "libp2p needs at least two nodes");
+    let ports: Vec<_> = (0..args.nodes)
+        .map(|_| portpicker::pick_unused_port().expect("No free ports"))
+        .collect();
+    let ports = ports
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(",");
+    let builder_port = portpicker::pick_unused_port().expect("No free ports");
+    let executable = std::env::current_exe().expect("Failed to find the runner executable");
+
+    let progress = Progress::new(args.nodes);
+    let mut children: Vec<Child> = Vec::new();
+    for node in 0..args.nodes {
+        let mut child = Command::new(&executable)
+            .args(["--node", &node.to_string()])
+            .args(["--nodes", &args.nodes.to_string()])
+            .args(["--da-nodes", &args.da_nodes().to_string()])
+            .args(["--ports", &ports])
+            .args(["--builder-port", &builder_port.to_string()])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .expect("Failed to start a node");
+        forward_output(node, child.stdout.take().unwrap(), Arc::clone(&progress));
+        forward_output(node, child.stderr.take().unwrap(), Arc::clone(&progress));
+        children.push(child);
+    }
+
+    report_until_done(args, &progress).await;
+    for child in &mut children {
+        let _ = child.kill();
+        let _ = child.wait();
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    initialize_logging();
+    let args = Args::parse();
+    assert!(args.nodes > 0, "The network needs at least one node");
+
+    if let Some(node_id) = args.node {
+        run_child(&args, node_id).await;
+    } else if args.processes {
+        run_processes(&args).await;
+    } else {
+        match args.network {
+            Backend::Memory => run_in_process::<MemoryImpl>(&args).await,
+            Backend::Libp2p => run_in_process::<Libp2pImpl>(&args).await,
+            Backend::PushCdn => run_in_process::<PushCdnImpl>(&args).await,
+            Backend::Combined => run_in_process::<CombinedImpl>(&args).await,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Signs a validator operation with the keys of a keystore.
+//!
+//! ```text
+//! HOTSHOT_KEYSTORE_PASSWORD=... just example validator-ops -- \
+//!     --keystore validator.json --chain-id 1 --nonce 0 register --stake 100
+//! ```
+//!
+//! The transaction bytes carrying the operation are printed in hex, to be submitted to any node
+//! of the chain. The operation takes effect two epochs after the epoch it is decided in.
+
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use hotshot_types::{
+    keystore::Keystore,
+    signature_key::BLSPubKey,
+    traits::signer::{LocalSigner, Signer},
+    validator_ops::{SignedOperation, ValidatorOperation},
+};
+
+#[derive(Parser, Debug, Clone)]
+#[command(
+    name = "Validator operations",
+    about = "Signs operations registering, updating or removing a validator"
+)]
+/// Arguments of the operation signer
+struct Args {
+    /// The keystore of the validator
+    #[arg(long)]
+    keystore: PathBuf,
+    /// Password of the keystore
+    #[arg(long, env = "HOTSHOT_KEYSTORE_PASSWORD", hide_env_values = true)]
+    password: String,
+    /// The chain the operation is for
+    #[arg(long)]
+    chain_id: u64,
+    /// Number of previous operations of the validator
+    #[arg(long, default_value_t = 0)]
+    nonce: u64,
+    /// The operation
+    #[command(subcommand)]
+    operation: Operation,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+/// The operations a validator can sign
+enum Operation {
+    /// Join the stake table
+    Register {
+        /// Stake of the validator
+        #[arg(long)]
+        stake: u64,
+    },
+    /// Change the stake of the validator
+    UpdateStake {
+        /// New stake of the validator
+        #[arg(long)]
+        stake: u64,
+    },
+    /// Move the stake of the validator to the keys of another keystore
+    RotateKey {
+        /// The keystore of the new keys
+        #[arg(long)]
+        new_keystore: PathBuf,
+        /// Password of the new keystore
+        #[arg(long, env = "HOTSHOT_NEW_KEYSTORE_PASSWORD", hide_env_values = true)]
+        new_password: String,
+    },
+    /// Leave the stake table
+    Exit,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+
+    let keystore = Keystore::<BLSPubKey>::read(&args.keystore)?;
+    let (private_key, _) = keystore.decrypt(&args.password)?;
+    let key = *keystore.public_key();
+    let signer = LocalSigner::new(key, private_key);
+
+    let (operation, new_signer) = match args.operation {
+        Operation::Register { stake } => (
+            ValidatorOperation::Register {
+                peer: keystore.peer_config(stake),
+            },
+            None,
+        ),
+        Operation::UpdateStake { stake } => (ValidatorOperation::UpdateStake { key, stake }, None),
+        Operation::RotateKey {
+            new_keystore,
+            new_password,
+        } => {
+            let new_keystore = Keystore::<BLSPubKey>::read(&new_keystore)?;
+            let (new_private_key, new_state_key_pair) = new_keystore.decrypt(&new_password)?;
+            let new_key = *new_keystore.public_key();
+            (
+                ValidatorOperation::RotateKey {
+                    key,
+                    new_key,
+                    new_state_ver_key: new_state_key_pair.ver_key(),
+                },
+                Some(LocalSigner::new(new_key, new_private_key)),
+            )
+        }
+        Operation::Exit => (ValidatorOperation::Exit { key }, None),
+    };
+
+    let operation = SignedOperation::sign(
+        operation,
+        args.chain_id,
+        args.nonce,
+        &signer,
+        new_signer
+            .as_ref()
+            .map(|signer| signer as &dyn Signer<BLSPubKey>),
+    )
+    .await?;
+
+    let bytes: String = operation
+        .encode()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    println!("{bytes}");
+    Ok(())
+}
@@ -0,0 +1,339 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! C ABI for embedding a HotShot node in a non-Rust sequencer stack.
+//!
+//! A node is created from a JSON [`NetworkConfig`] file and an encrypted [`Keystore`] with
+//! [`hotshot_node_new`], started with [`hotshot_node_start`] and torn down with
+//! [`hotshot_node_shutdown`]. Transactions are opaque byte strings, and events are handed out as
+//! JSON-encoded [`Event`]s.
+//!
+//! Nodes use the example types with a libp2p network, since a C ABI cannot be generic over them.
+//! Every node owns its own tokio runtime, so the embedder doesn't need one. With the `test-keys`
+//! feature, `hotshot_node_new_from_seed` derives a node's keys from the config's seed instead,
+//! for local test networks where every node can derive everyone's keys.
+//!
+//! Functions returning `i32` return `0` on success and a negative value on failure; failures are
+//! logged.
+
+use std::{
+    ffi::{c_char, CStr},
+    path::Path,
+    ptr, slice,
+    sync::Arc,
+};
+
+use anyhow::{Context, Result};
+use async_broadcast::Receiver;
+use hotshot::{
+    remote_signer::configured_signer,
+    traits::implementations::{
+        derive_libp2p_multiaddrs, GossipConfig, Libp2pMetricsValue, Libp2pNetwork,
+        RequestResponseConfig,
+    },
+    types::{Event, SystemContextHandle},
+    HotShotInitializer, MarketplaceConfig, SystemContext,
+};
+use hotshot_example_types::{
+    auction_results_provider_types::TestAuctionResultsProvider,
+    block_types::TestTransaction,
+    node_types::{Libp2pImpl, TestTypes, TestVersions},
+    state_types::TestInstanceState,
+    storage_types::TestStorage,
+};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    data::EpochNumber,
+    keystore::Keystore,
+    network::NetworkConfig,
+    signature_key::{BLSPrivKey, BLSPubKey},
+    traits::{
+        election::Membership,
+        network::ConnectedNetwork,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+};
+use tokio::runtime::Runtime;
+
+/// Status returned on success
+const OK: i32 = 0;
+/// Status returned on failure
+const ERROR: i32 = -1;
+
+/// An embedded node, opaque to C.
+pub struct HotShotNode {
+    /// Runtime driving the node's tasks
+    runtime: Runtime,
+    /// Handle to the running node
+    handle: SystemContextHandle<TestTypes, Libp2pImpl, TestVersions>,
+    /// Receiver for events which have not been polled yet
+    events: Receiver<Event<TestTypes>>,
+}
+
+/// Read a C string argument.
+///
+/// # Safety
+/// `s` must be null or point to a valid nul-terminated string.
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str> {
+    anyhow::ensure!(!s.is_null(), "null string argument");
+    Ok(CStr::from_ptr(s).to_str()?)
+}
+
+/// Read the network config at `config_path`, for the node at `node_index`.
+fn read_config(config_path: &str, node_index: u64) -> Result<NetworkConfig<BLSPubKey>> {
+    let mut config = NetworkConfig::<BLSPubKey>::from_file(config_path.to_string())
+        .context("failed to read network config")?;
+    config.node_index = node_index;
+    Ok(config)
+}
+
+/// Decrypt the consensus keys of the keystore at `keystore_path` with `password`.
+fn read_keys(keystore_path: &str, password: &str) -> Result<(BLSPubKey, BLSPrivKey)> {
+    let keystore =
+        Keystore::<BLSPubKey>::read(Path::new(keystore_path)).context("failed to read keystore")?;
+    let (private_key, _) = keystore
+        .decrypt(password)
+        .context("failed to decrypt keystore")?;
+    Ok((BLSPubKey::from_private(&private_key), private_key))
+}
+
+/// Build a node from `config` which signs with the given keys. Does not start consensus.
+fn build_node(
+    config: NetworkConfig<BLSPubKey>,
+    public_key: BLSPubKey,
+    private_key: BLSPrivKey,
+    bind_address: &str,
+) -> Result<HotShotNode> {
+    let runtime = Runtime::new().context("failed to start runtime")?;
+
+    let node_index = config.node_index;
+    let bind_addresses = derive_libp2p_multiaddrs(bind_address)?;
+
+    let handle = runtime.block_on(async {
+        let (known_nodes_with_stake, known_da_nodes) =
+            config.config.stake_tables(*EpochNumber::genesis());
+        let memberships =
+            <TestTypes as NodeType>::Membership::new(known_nodes_with_stake, known_da_nodes);
+
+        let network = Libp2pNetwork::from_config(
+            config.clone(),
+            memberships.clone(),
+            GossipConfig::default(),
+            RequestResponseConfig::default(),
+            bind_addresses,
+            &public_key,
+            &private_key,
+            Libp2pMetricsValue::default(),
+        )
+        .await?;
+        network.wait_for_ready().await;
+
+        let initializer =
+            HotShotInitializer::from_genesis::<TestVersions>(TestInstanceState::default())
+                .await
+                .context("failed to build genesis")?;
+        let marketplace_config = MarketplaceConfig {
+            auction_results_provider: TestAuctionResultsProvider::default().into(),
+            fallback_builder_url: config.config.builder_urls.first().clone(),
+        };
+
+        let signer = configured_signer(
+            config.config.remote_signer.as_ref(),
+            public_key,
+            private_key,
+        );
+        let (handle, ..) = SystemContext::<TestTypes, Libp2pImpl, TestVersions>::init(
+            signer,
+            node_index,
+            config.config,
+            memberships,
+            Arc::new(network),
+            initializer,
+            ConsensusMetricsValue::default(),
+            TestStorage::default(),
+            marketplace_config,
+        )
+        .await?;
+
+        anyhow::Ok(handle)
+    })?;
+
+    let events = handle.event_stream_known_impl();
+    Ok(HotShotNode {
+        runtime,
+        handle,
+        events,
+    })
+}
+
+/// Hand a built node to C, or log why it could not be built.
+fn into_raw(node: Result<HotShotNode>) -> *mut HotShotNode {
+    match node {
+        Ok(node) => Box::into_raw(Box::new(node)),
+        Err(e) => {
+            tracing::error!("Failed to create node: {e:#}");
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Create the node at `node_index` of a JSON network config, with the keys of the keystore at
+/// `keystore_path` decrypted with `password`. The node listens on `bind_address` (`host:port`,
+/// or several separated by commas).
+///
+/// Returns null on failure. The node must be released with [`hotshot_node_shutdown`].
+///
+/// # Safety
+/// `config_path`, `keystore_path`, `password` and `bind_address` must be valid nul-terminated
+/// strings.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_node_new(
+    config_path: *const c_char,
+    node_index: u64,
+    keystore_path: *const c_char,
+    password: *const c_char,
+    bind_address: *const c_char,
+) -> *mut HotShotNode {
+    let node = read_str(keystore_path)
+        .and_then(|keystore_path| read_keys(keystore_path, read_str(password)?))
+        .and_then(|(public_key, private_key)| {
+            let config = read_config(read_str(config_path)?, node_index)?;
+            build_node(config, public_key, private_key, read_str(bind_address)?)
+        });
+
+    into_raw(node)
+}
+
+/// Create the node at `node_index` of a JSON network config, with keys derived from the config's
+/// seed and the index. Anyone can derive these keys, so this is only for test networks.
+///
+/// Returns null on failure. The node must be released with [`hotshot_node_shutdown`].
+///
+/// # Safety
+/// `config_path` and `bind_address` must be valid nul-terminated strings.
+#[cfg(feature = "test-keys")]
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_node_new_from_seed(
+    config_path: *const c_char,
+    node_index: u64,
+    bind_address: *const c_char,
+) -> *mut HotShotNode {
+    let node = read_str(config_path).and_then(|config_path| {
+        let config = read_config(config_path, node_index)?;
+        let (public_key, private_key) =
+            BLSPubKey::generated_from_seed_indexed(config.seed, node_index);
+        build_node(config, public_key, private_key, read_str(bind_address)?)
+    });
+
+    into_raw(node)
+}
+
+/// Start consensus on a node.
+///
+/// # Safety
+/// `node` must have been returned by [`hotshot_node_new`] and not shut down.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_node_start(node: *mut HotShotNode) -> i32 {
+    let Some(node) = node.as_ref() else {
+        return ERROR;
+    };
+    node.runtime.block_on(node.handle.hotshot.start_consensus());
+    OK
+}
+
+/// Submit a transaction of `len` bytes.
+///
+/// # Safety
+/// `node` must be a live node and `data` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_submit_transaction(
+    node: *mut HotShotNode,
+    data: *const u8,
+    len: usize,
+) -> i32 {
+    let Some(node) = node.as_ref() else {
+        return ERROR;
+    };
+    if data.is_null() {
+        return ERROR;
+    }
+
+    let transaction = TestTransaction::new(slice::from_raw_parts(data, len).to_vec());
+    match node
+        .runtime
+        .block_on(node.handle.submit_transaction(transaction))
+    {
+        Ok(()) => OK,
+        Err(e) => {
+            tracing::warn!("Failed to submit transaction: {e}");
+            ERROR
+        }
+    }
+}
+
+/// Poll the next event without blocking.
+///
+/// Returns `1` and stores a JSON-encoded event in `out`/`out_len` if there was one, `0` if there
+/// was none, and a negative value on failure. The event must be released with
+/// [`hotshot_bytes_free`].
+///
+/// # Safety
+/// `node` must be a live node, and `out` and `out_len` must be valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_poll_event(
+    node: *mut HotShotNode,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    let Some(node) = node.as_mut() else {
+        return ERROR;
+    };
+    if out.is_null() || out_len.is_null() {
+        return ERROR;
+    }
+
+    let Ok(event) = node.events.try_recv() else {
+        return 0;
+    };
+    match serde_json::to_vec(&event) {
+        Ok(bytes) => {
+            let bytes = bytes.into_boxed_slice();
+            *out_len = bytes.len();
+            *out = Box::into_raw(bytes).cast::<u8>();
+            1
+        }
+        Err(e) => {
+            tracing::error!("Failed to encode event: {e}");
+            ERROR
+        }
+    }
+}
+
+/// Release an event returned by [`hotshot_poll_event`].
+///
+/// # Safety
+/// `data` and `len` must be exactly as returned by [`hotshot_poll_event`], and not freed before.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_bytes_free(data: *mut u8, len: usize) {
+    if !data.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(data, len)));
+    }
+}
+
+/// Shut a node down and release it.
+///
+/// # Safety
+/// `node` must have been returned by [`hotshot_node_new`] and is invalid afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn hotshot_node_shutdown(node: *mut HotShotNode) {
+    if node.is_null() {
+        return;
+    }
+
+    let mut node = Box::from_raw(node);
+    node.runtime.block_on(node.handle.shut_down());
+}
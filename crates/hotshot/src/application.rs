@@ -0,0 +1,67 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Drives the `finalize_block` hook of an [`Application`] from decide events.
+
+use std::sync::Arc;
+
+use futures::{Stream, StreamExt};
+use hotshot_types::traits::{
+    application::{leaf_transactions, Application},
+    node_implementation::NodeType,
+};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Handle to a running application driver. The driver is stopped when the handle is dropped.
+pub struct ApplicationDriver {
+    /// The task finalizing decided blocks
+    task: JoinHandle<()>,
+}
+
+impl ApplicationDriver {
+    /// Spawn a task which calls [`Application::finalize_block`] for every leaf decided in
+    /// `events`, oldest first.
+    pub fn spawn<TYPES: NodeType, A: Application<TYPES>>(
+        app: Arc<A>,
+        mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let task = tokio::spawn(async move {
+            // Leaf chains of consecutive decides don't overlap, but a restarted node may see
+            // blocks again. Make sure each height is only finalized once.
+            let mut next_height = None;
+            while let Some(event) = events.next().await {
+                let EventType::Decide { leaf_chain, .. } = event.event else {
+                    continue;
+                };
+
+                for leaf_info in leaf_chain.iter().rev() {
+                    let leaf = &leaf_info.leaf;
+                    if next_height.is_some_and(|next| leaf.height() < next) {
+                        continue;
+                    }
+
+                    if let Err(e) = app.finalize_block(leaf, leaf_transactions(leaf)).await {
+                        tracing::error!(
+                            "Application failed to finalize block {}: {e}",
+                            leaf.height()
+                        );
+                    }
+                    next_height = Some(leaf.height() + 1);
+                }
+            }
+        });
+
+        Self { task }
+    }
+}
+
+impl Drop for ApplicationDriver {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
@@ -0,0 +1,307 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! The archival node role: keep the whole decided chain and serve it to others.
+//!
+//! An archival node runs the usual tasks without stake, so it follows the chain but never votes.
+//! Validators send it their DA proposals (see [`HotShotConfig::archival_nodes`]), which lets the
+//! [`Archiver`] fill in the payload of every decided leaf before storing it in an
+//! [`ArchiveStorage`]. The archiver also answers proposal requests from the archive, so nodes
+//! catching up can fetch proposals long after validators have garbage collected them.
+//!
+//! [`HotShotConfig::archival_nodes`]: hotshot_types::HotShotConfig::archival_nodes
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use async_broadcast::{Receiver, Sender};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_types::{
+    data::{DaProposal2, Leaf2, QuorumProposal2},
+    message::Proposal,
+    traits::{
+        archive::ArchiveStorage, block_contents::BlockHeader, election::Membership,
+        node_implementation::NodeType, signature_key::SignatureKey, BlockPayload,
+    },
+};
+use tokio::{sync::watch, task::JoinHandle};
+
+use crate::types::{Event, EventType};
+
+/// An [`ArchiveStorage`] kept in memory.
+#[derive(Debug)]
+pub struct MemoryArchive<TYPES: NodeType> {
+    /// Decided leaves, by height
+    leaves: RwLock<BTreeMap<u64, Leaf2<TYPES>>>,
+    /// Heights of decided leaves, by view
+    heights_by_view: RwLock<HashMap<TYPES::View, u64>>,
+    /// Heights of decided leaves, by commitment
+    heights_by_commitment: RwLock<HashMap<Commitment<Leaf2<TYPES>>, u64>>,
+    /// Heights of the leaves which included each transaction
+    heights_by_transaction: RwLock<HashMap<Commitment<TYPES::Transaction>, u64>>,
+    /// Validated proposals, by view
+    proposals: RwLock<BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>>,
+}
+
+impl<TYPES: NodeType> Default for MemoryArchive<TYPES> {
+    fn default() -> Self {
+        Self {
+            leaves: RwLock::new(BTreeMap::new()),
+            heights_by_view: RwLock::new(HashMap::new()),
+            heights_by_commitment: RwLock::new(HashMap::new()),
+            heights_by_transaction: RwLock::new(HashMap::new()),
+            proposals: RwLock::new(BTreeMap::new()),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> ArchiveStorage<TYPES> for MemoryArchive<TYPES> {
+    async fn append_leaf(&self, leaf: Leaf2<TYPES>) -> Result<()> {
+        let height = leaf.height();
+        self.heights_by_view
+            .write()
+            .await
+            .insert(leaf.view_number(), height);
+        self.heights_by_commitment
+            .write()
+            .await
+            .insert(leaf.commit(), height);
+        if let Some(payload) = leaf.block_payload() {
+            let metadata = leaf.block_header().metadata();
+            let mut heights_by_transaction = self.heights_by_transaction.write().await;
+            for transaction in payload.transactions(metadata) {
+                heights_by_transaction.insert(transaction.commit(), height);
+            }
+        }
+        self.leaves.write().await.insert(height, leaf);
+        Ok(())
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.proposals
+            .write()
+            .await
+            .insert(proposal.data.view_number, proposal);
+        Ok(())
+    }
+
+    async fn leaf(&self, height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        Ok(self.leaves.read().await.get(&height).cloned())
+    }
+
+    async fn leaves(&self, heights: Range<u64>) -> Result<Vec<Leaf2<TYPES>>> {
+        if heights.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self
+            .leaves
+            .read()
+            .await
+            .range(heights)
+            .map(|(_, leaf)| leaf.clone())
+            .collect())
+    }
+
+    async fn leaf_by_view(&self, view: TYPES::View) -> Result<Option<Leaf2<TYPES>>> {
+        let Some(height) = self.heights_by_view.read().await.get(&view).copied() else {
+            return Ok(None);
+        };
+        self.leaf(height).await
+    }
+
+    async fn leaf_by_commitment(
+        &self,
+        commitment: Commitment<Leaf2<TYPES>>,
+    ) -> Result<Option<Leaf2<TYPES>>> {
+        let Some(height) = self
+            .heights_by_commitment
+            .read()
+            .await
+            .get(&commitment)
+            .copied()
+        else {
+            return Ok(None);
+        };
+        self.leaf(height).await
+    }
+
+    async fn transaction_height(
+        &self,
+        commitment: Commitment<TYPES::Transaction>,
+    ) -> Result<Option<u64>> {
+        Ok(self
+            .heights_by_transaction
+            .read()
+            .await
+            .get(&commitment)
+            .copied())
+    }
+
+    async fn proposal(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<Proposal<TYPES, QuorumProposal2<TYPES>>>> {
+        Ok(self.proposals.read().await.get(&view).cloned())
+    }
+
+    async fn latest_height(&self) -> Result<Option<u64>> {
+        Ok(self.leaves.read().await.keys().next_back().copied())
+    }
+}
+
+/// Handle to the tasks of an archival node. The tasks are stopped when the handle is dropped.
+pub struct Archiver {
+    /// The task storing decided leaves
+    archive_task: JoinHandle<()>,
+    /// The task answering proposal requests
+    serve_task: JoinHandle<()>,
+    /// Height of the latest leaf archived by the archive task
+    archived: watch::Receiver<Option<u64>>,
+}
+
+impl Archiver {
+    /// Spawn the tasks archiving the chain seen in `events` to `archive`, and serving proposal
+    /// requests from `requests` by sending responses to `responses`.
+    pub fn spawn<TYPES: NodeType, A: ArchiveStorage<TYPES>>(
+        archive: Arc<A>,
+        memberships: Arc<TYPES::Membership>,
+        events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+        requests: Receiver<Arc<HotShotEvent<TYPES>>>,
+        responses: Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Self {
+        let (archived_sender, archived) = watch::channel(None);
+        let archive_task = tokio::spawn(archive_chain(
+            Arc::clone(&archive),
+            memberships,
+            events,
+            archived_sender,
+        ));
+        let serve_task = tokio::spawn(serve_proposals(archive, requests, responses));
+
+        Self {
+            archive_task,
+            serve_task,
+            archived,
+        }
+    }
+
+    /// Follow the height of the latest leaf archived since the archiver started, for
+    /// [`FinalizedLeaves`](crate::finalized_leaves::FinalizedLeaves) subscriptions.
+    #[must_use]
+    pub fn archived_height(&self) -> watch::Receiver<Option<u64>> {
+        self.archived.clone()
+    }
+}
+
+impl Drop for Archiver {
+    fn drop(&mut self) {
+        self.archive_task.abort();
+        self.serve_task.abort();
+    }
+}
+
+/// Store every proposal and decided leaf in `events`, filling in payloads from DA proposals, and
+/// publish the height of each leaf once it is stored to `archived`.
+async fn archive_chain<TYPES: NodeType, A: ArchiveStorage<TYPES>>(
+    archive: Arc<A>,
+    memberships: Arc<TYPES::Membership>,
+    mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin,
+    archived: watch::Sender<Option<u64>>,
+) {
+    // DA proposals of views which have not been decided yet
+    let mut payloads = BTreeMap::<TYPES::View, DaProposal2<TYPES>>::new();
+
+    while let Some(event) = events.next().await {
+        match event.event {
+            EventType::DaProposal { proposal, .. } => {
+                payloads.insert(proposal.data.view_number, proposal.data);
+            }
+            EventType::QuorumProposal { proposal, .. } => {
+                if let Err(e) = archive.append_proposal(proposal).await {
+                    tracing::error!("Failed to archive proposal: {e:#}");
+                }
+            }
+            EventType::Decide { leaf_chain, .. } => {
+                for leaf_info in leaf_chain.iter().rev() {
+                    let mut leaf = leaf_info.leaf.clone();
+                    let view = leaf.view_number();
+                    if leaf.block_payload().is_none() {
+                        if let Some(da_proposal) = payloads.get(&view) {
+                            let payload = <TYPES::BlockPayload as BlockPayload<TYPES>>::from_bytes(
+                                &da_proposal.encoded_transactions,
+                                &da_proposal.metadata,
+                            );
+                            let num_nodes = memberships.total_nodes(da_proposal.epoch);
+                            if let Err(e) = leaf.fill_block_payload(payload, num_nodes) {
+                                tracing::warn!(
+                                    "DA proposal for view {view:?} does not match the decided leaf: {e}"
+                                );
+                            }
+                        } else {
+                            tracing::warn!("Archiving leaf for view {view:?} without its payload");
+                        }
+                    }
+
+                    let height = leaf.height();
+                    match archive.append_leaf(leaf).await {
+                        Ok(()) => {
+                            archived.send_replace(Some(height));
+                        }
+                        Err(e) => {
+                            tracing::error!("Failed to archive leaf for view {view:?}: {e:#}");
+                        }
+                    }
+                    payloads = payloads.split_off(&(view + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Answer proposal requests from the archive.
+async fn serve_proposals<TYPES: NodeType, A: ArchiveStorage<TYPES>>(
+    archive: Arc<A>,
+    mut requests: Receiver<Arc<HotShotEvent<TYPES>>>,
+    responses: Sender<Arc<HotShotEvent<TYPES>>>,
+) {
+    while let Ok(event) = requests.recv_direct().await {
+        let HotShotEvent::QuorumProposalRequestRecv(req, signature) = event.as_ref() else {
+            continue;
+        };
+        if !req.key.validate(signature, req.commit().as_ref()) {
+            tracing::warn!("Invalid signature key on proposal request.");
+            continue;
+        }
+
+        match archive.proposal(req.view_number).await {
+            Ok(Some(proposal)) => {
+                broadcast_event(
+                    Arc::new(HotShotEvent::QuorumProposalResponseSend(
+                        req.key.clone(),
+                        proposal,
+                    )),
+                    &responses,
+                )
+                .await;
+            }
+            Ok(None) => {}
+            Err(e) => tracing::error!("Failed to read proposal from the archive: {e:#}"),
+        }
+    }
+}
@@ -0,0 +1,385 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Append-only audit log of what a node received, signed and decided.
+//!
+//! Every proposal received, proposal sent, vote cast and decide is written as one JSON line, with
+//! the signatures and commitments involved, so an operator can reconstruct exactly what their node
+//! signed. The log is rotated once it reaches a configured size: `audit.log` becomes
+//! `audit.log.1`, `audit.log.1` becomes `audit.log.2`, and so on, up to the configured number of
+//! files.
+//!
+//! The file is written from a thread of its own, so that slow disks never hold up the runtime.
+//! Lines are buffered, and only flushed when the log is rotated or closed.
+
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::{mpsc, Arc},
+    thread,
+};
+
+use anyhow::{Context, Result};
+use async_broadcast::Receiver;
+use chrono::Utc;
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_task_impls::events::HotShotEvent;
+use hotshot_types::{
+    data::Leaf2,
+    simple_certificate::QuorumCertificate2,
+    traits::{node_implementation::NodeType, signature_key::SignatureKey},
+    vote::{HasViewNumber, Vote},
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Configuration of the audit log.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogConfig {
+    /// File the log is written to
+    pub path: PathBuf,
+    /// Size in bytes at which the log is rotated
+    pub max_file_size: u64,
+    /// Number of rotated files kept besides the current one
+    pub max_rotated_files: usize,
+}
+
+impl AuditLogConfig {
+    /// Default rotation settings for a log at `path`.
+    #[must_use]
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            max_file_size: 100 * 1024 * 1024,
+            max_rotated_files: 10,
+        }
+    }
+}
+
+/// The signature type of `TYPES`
+type Signature<TYPES> =
+    <<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType;
+
+/// A line of the audit log.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct AuditEntry<TYPES: NodeType> {
+    /// Milliseconds since the Unix epoch when the entry was written
+    pub timestamp: i64,
+    /// What happened
+    #[serde(flatten)]
+    pub record: AuditRecord<TYPES>,
+}
+
+/// Something the node saw, signed or decided.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", bound(deserialize = ""))]
+pub enum AuditRecord<TYPES: NodeType> {
+    /// We received a quorum proposal
+    ProposalReceived {
+        /// View of the proposal
+        view: TYPES::View,
+        /// Leader who signed the proposal
+        sender: TYPES::SignatureKey,
+        /// Commitment of the proposed leaf
+        leaf: Commitment<Leaf2<TYPES>>,
+        /// The leader's signature
+        signature: Signature<TYPES>,
+    },
+    /// We signed and sent a quorum proposal
+    ProposalSent {
+        /// View of the proposal
+        view: TYPES::View,
+        /// Commitment of the proposed leaf
+        leaf: Commitment<Leaf2<TYPES>>,
+        /// Our signature
+        signature: Signature<TYPES>,
+    },
+    /// We signed and sent a vote
+    VoteSent {
+        /// Kind of vote, e.g. `Quorum` or `Timeout`
+        kind: String,
+        /// View of the vote
+        view: TYPES::View,
+        /// Commitment of the data we voted for
+        data: String,
+        /// Our signature
+        signature: Signature<TYPES>,
+    },
+    /// A leaf was decided
+    Decided {
+        /// View the leaf was proposed in
+        view: TYPES::View,
+        /// Height of the leaf
+        height: u64,
+        /// Commitment of the leaf
+        leaf: Commitment<Leaf2<TYPES>>,
+        /// The QC of the newest leaf in the decide, which certifies the whole chain
+        qc: QuorumCertificate2<TYPES>,
+    },
+}
+
+impl<TYPES: NodeType> AuditRecord<TYPES> {
+    /// The record for a vote we sent.
+    fn vote<V: Vote<TYPES> + HasViewNumber<TYPES>>(kind: &str, vote: &V) -> Self {
+        AuditRecord::VoteSent {
+            kind: kind.to_string(),
+            view: vote.view_number(),
+            data: vote.data_commitment().to_string(),
+            signature: vote.signature(),
+        }
+    }
+
+    /// The record for an internal event, if it is worth auditing.
+    fn from_internal(event: &HotShotEvent<TYPES>) -> Option<Self> {
+        Some(match event {
+            HotShotEvent::QuorumProposalRecv(proposal, sender) => AuditRecord::ProposalReceived {
+                view: proposal.data.view_number,
+                sender: sender.clone(),
+                leaf: Leaf2::from_quorum_proposal(&proposal.data).commit(),
+                signature: proposal.signature.clone(),
+            },
+            HotShotEvent::QuorumProposalSend(proposal, _) => AuditRecord::ProposalSent {
+                view: proposal.data.view_number,
+                leaf: Leaf2::from_quorum_proposal(&proposal.data).commit(),
+                signature: proposal.signature.clone(),
+            },
+            HotShotEvent::QuorumVoteSend(vote) => Self::vote("Quorum", vote),
+            HotShotEvent::DaVoteSend(vote) => Self::vote("Da", vote),
+            HotShotEvent::TimeoutVoteSend(vote) => Self::vote("Timeout", vote),
+            HotShotEvent::UpgradeVoteSend(vote) => Self::vote("Upgrade", vote),
+            HotShotEvent::ViewSyncPreCommitVoteSend(vote) => Self::vote("ViewSyncPreCommit", vote),
+            HotShotEvent::ViewSyncCommitVoteSend(vote) => Self::vote("ViewSyncCommit", vote),
+            HotShotEvent::ViewSyncFinalizeVoteSend(vote) => Self::vote("ViewSyncFinalize", vote),
+            _ => return None,
+        })
+    }
+}
+
+/// A file writer which rotates the file once it grows past a size.
+struct RotatingWriter {
+    /// Rotation settings
+    config: AuditLogConfig,
+    /// The current file
+    file: BufWriter<File>,
+    /// Bytes in the current file
+    size: u64,
+}
+
+impl RotatingWriter {
+    /// Open the log, appending to an existing file.
+    fn open(config: AuditLogConfig) -> Result<Self> {
+        if let Some(parent) = config.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&config.path)
+            .with_context(|| format!("Failed to open audit log {}", config.path.display()))?;
+        let size = file.metadata()?.len();
+
+        Ok(Self {
+            config,
+            file: BufWriter::new(file),
+            size,
+        })
+    }
+
+    /// Path of the `index`th rotated file.
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.config.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        path.into()
+    }
+
+    /// Shift the rotated files up by one and start a new file.
+    fn rotate(&mut self) -> Result<()> {
+        self.file.flush()?;
+        if self.config.max_rotated_files == 0 {
+            fs::remove_file(&self.config.path)?;
+        } else {
+            for index in (1..self.config.max_rotated_files).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.config.path, self.rotated_path(1))?;
+        }
+
+        *self = Self::open(self.config.clone())?;
+        Ok(())
+    }
+
+    /// Write `line`, followed by a newline. The line is buffered until the next flush.
+    fn write_line(&mut self, line: &[u8]) -> Result<()> {
+        if self.size > 0 && self.size + line.len() as u64 + 1 > self.config.max_file_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line)?;
+        self.file.write_all(b"\n")?;
+        self.size += line.len() as u64 + 1;
+        Ok(())
+    }
+
+    /// Write the buffered lines to the file.
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.file.flush()?)
+    }
+
+    /// Write every line received on `lines`, and flush once the sender is gone.
+    fn run(mut self, lines: &mpsc::Receiver<Vec<u8>>) {
+        for line in lines {
+            if let Err(e) = self.write_line(&line) {
+                tracing::error!("Failed to write to the audit log: {e:#}");
+            }
+        }
+        if let Err(e) = self.flush() {
+            tracing::error!("Failed to flush the audit log: {e:#}");
+        }
+    }
+}
+
+/// Appends entries to the audit log.
+struct AuditLogWriter<TYPES: NodeType> {
+    /// Lines for the thread writing the log file
+    lines: mpsc::Sender<Vec<u8>>,
+    /// Phantom for the node type
+    _types: std::marker::PhantomData<TYPES>,
+}
+
+impl<TYPES: NodeType> AuditLogWriter<TYPES> {
+    /// Write `record`, timestamped now.
+    fn append(&mut self, record: AuditRecord<TYPES>) {
+        let entry = AuditEntry {
+            timestamp: Utc::now().timestamp_millis(),
+            record,
+        };
+        let result = serde_json::to_vec(&entry)
+            .map_err(anyhow::Error::from)
+            .and_then(|line| Ok(self.lines.send(line)?));
+        if let Err(e) = result {
+            tracing::error!("Failed to write to the audit log: {e:#}");
+        }
+    }
+}
+
+/// Handle to the task writing the audit log. Logging stops when the handle is dropped, after
+/// which the writer thread flushes what is left and exits.
+pub struct AuditLog {
+    /// The task turning events into log lines
+    task: JoinHandle<()>,
+}
+
+impl AuditLog {
+    /// Start logging proposals and votes from the internal event stream, and decides from the
+    /// external one.
+    ///
+    /// # Errors
+    /// Fails if the log file cannot be opened, or the writer thread cannot be started.
+    pub fn spawn<TYPES: NodeType>(
+        config: AuditLogConfig,
+        mut internal_events: Receiver<Arc<HotShotEvent<TYPES>>>,
+        mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Result<Self> {
+        let writer = RotatingWriter::open(config)?;
+        let (lines, received) = mpsc::channel();
+        thread::Builder::new()
+            .name("audit-log".to_string())
+            .spawn(move || writer.run(&received))
+            .context("Failed to start the audit log writer")?;
+
+        let mut log = AuditLogWriter::<TYPES> {
+            lines,
+            _types: std::marker::PhantomData,
+        };
+
+        let task = tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    event = internal_events.recv_direct() => {
+                        let Ok(event) = event else { break };
+                        if let Some(record) = AuditRecord::from_internal(&event) {
+                            log.append(record);
+                        }
+                    }
+                    event = events.next() => {
+                        let Some(event) = event else { break };
+                        let EventType::Decide { leaf_chain, qc, .. } = event.event else {
+                            continue;
+                        };
+                        for leaf_info in leaf_chain.iter().rev() {
+                            log.append(AuditRecord::Decided {
+                                view: leaf_info.leaf.view_number(),
+                                height: leaf_info.leaf.height(),
+                                leaf: leaf_info.leaf.commit(),
+                                qc: QuorumCertificate2::clone(&qc),
+                            });
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { task })
+    }
+}
+
+impl Drop for AuditLog {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn log_is_rotated_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AuditLogConfig {
+            path: dir.path().join("audit.log"),
+            max_file_size: 10,
+            max_rotated_files: 2,
+        };
+        let mut writer = RotatingWriter::open(config.clone()).unwrap();
+
+        for line in ["first", "second", "third", "fourth"] {
+            writer.write_line(line.as_bytes()).unwrap();
+        }
+        writer.flush().unwrap();
+
+        let read = |path: PathBuf| fs::read_to_string(path).unwrap();
+        assert_eq!(read(config.path.clone()), "fourth\n");
+        assert_eq!(read(writer.rotated_path(1)), "third\n");
+        assert_eq!(read(writer.rotated_path(2)), "second\n");
+        // The oldest file is dropped once there are too many
+        assert!(!writer.rotated_path(3).exists());
+    }
+
+    #[test]
+    fn lines_are_flushed_once_the_sender_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = AuditLogConfig::new(dir.path().join("audit.log"));
+        let writer = RotatingWriter::open(config.clone()).unwrap();
+
+        let (lines, received) = mpsc::channel();
+        let thread = thread::spawn(move || writer.run(&received));
+        for line in ["first", "second"] {
+            lines.send(line.as_bytes().to_vec()).unwrap();
+        }
+        drop(lines);
+        thread.join().unwrap();
+
+        assert_eq!(fs::read_to_string(config.path).unwrap(), "first\nsecond\n");
+    }
+}
@@ -0,0 +1,522 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Read-through LRU cache in front of any [`Storage`] or [`ArchiveStorage`] backend.
+//!
+//! [`CachedStorage`] keeps a fixed number of the values most recently read from or written to its
+//! backend, so the values looked up again and again, such as recent leaves, proposals and QCs while
+//! serving nodes which are catching up, are only read from disk once. Writes go through to the
+//! backend before they update the cache, and hits and misses are exported as metrics so the
+//! capacity can be tuned by hit rate.
+
+use std::{collections::BTreeMap, num::NonZeroUsize, ops::Range, sync::Arc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use hotshot_types::{
+    consensus::{CommitmentMap, LeafMap},
+    data::{
+        DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
+        VidDisperseShare2,
+    },
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    traits::{
+        archive::ArchiveStorage,
+        metrics::{Counter, Gauge, Metrics},
+        node_implementation::NodeType,
+        storage::{Storage, StorageKey, StoredValue, ViewUndo, ViewWrites},
+    },
+    utils::View,
+    vid::VidSchemeType,
+};
+use jf_vid::VidScheme;
+use lru::LruCache;
+use parking_lot::Mutex;
+
+/// Cache metrics of a backend.
+#[derive(Debug)]
+pub struct CacheMetricsValue {
+    /// Lookups answered from the cache
+    pub hits: Box<dyn Counter>,
+    /// Lookups which went to the backend
+    pub misses: Box<dyn Counter>,
+    /// Entries in the cache
+    pub entries: Box<dyn Gauge>,
+}
+
+impl CacheMetricsValue {
+    /// Create the metrics of the cache in front of the backend called `backend`.
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics, backend: &str) -> Self {
+        let metrics = metrics.subgroup(format!("cache_{backend}"));
+        Self {
+            hits: metrics.create_counter(String::from("hits"), None),
+            misses: metrics.create_counter(String::from("misses"), None),
+            entries: metrics.create_gauge(String::from("entries"), None),
+        }
+    }
+}
+
+/// What a cache entry holds.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum CacheKey<TYPES: NodeType> {
+    /// A value of a [`Storage`] backend
+    Stored(StorageKey<TYPES>),
+    /// The archived leaf at a height
+    Leaf(u64),
+    /// Height of the archived leaf proposed in a view
+    HeightByView(TYPES::View),
+    /// Height of the archived leaf with a commitment
+    HeightByCommitment(Commitment<Leaf2<TYPES>>),
+    /// The archived proposal of a view
+    Proposal(TYPES::View),
+}
+
+/// A cached value.
+#[derive(Clone, Debug)]
+enum CachedValue<TYPES: NodeType> {
+    /// A value of a [`Storage`] backend
+    Stored(StoredValue<TYPES>),
+    /// An archived leaf
+    Leaf(Leaf2<TYPES>),
+    /// Height of an archived leaf
+    Height(u64),
+    /// An archived proposal
+    Proposal(Proposal<TYPES, QuorumProposal2<TYPES>>),
+}
+
+/// The cache entries of an archived leaf.
+fn leaf_entries<TYPES: NodeType>(
+    leaf: &Leaf2<TYPES>,
+) -> [(CacheKey<TYPES>, CachedValue<TYPES>); 3] {
+    let height = leaf.height();
+    [
+        (
+            CacheKey::HeightByView(leaf.view_number()),
+            CachedValue::Height(height),
+        ),
+        (
+            CacheKey::HeightByCommitment(leaf.commit()),
+            CachedValue::Height(height),
+        ),
+        (CacheKey::Leaf(height), CachedValue::Leaf(leaf.clone())),
+    ]
+}
+
+/// The cache entries and a count of the writes made through it.
+#[derive(Debug)]
+struct Cache<TYPES: NodeType> {
+    /// Cached values, least recently used first out
+    entries: LruCache<CacheKey<TYPES>, CachedValue<TYPES>>,
+    /// Bumped by every write, so a read which raced with a write does not cache what it read
+    generation: u64,
+}
+
+/// A [`Storage`] or [`ArchiveStorage`] backend with an LRU cache in front of it.
+#[derive(Clone, Debug)]
+pub struct CachedStorage<TYPES: NodeType, S> {
+    /// The wrapped backend
+    inner: S,
+    /// The cache
+    cache: Arc<Mutex<Cache<TYPES>>>,
+    /// Metrics of the cache
+    metrics: Arc<CacheMetricsValue>,
+}
+
+impl<TYPES: NodeType, S> CachedStorage<TYPES, S> {
+    /// Wrap `inner` with a cache of `capacity` entries, exporting its metrics under the name
+    /// `backend`.
+    #[must_use]
+    pub fn new(inner: S, capacity: NonZeroUsize, metrics: &dyn Metrics, backend: &str) -> Self {
+        Self {
+            inner,
+            cache: Arc::new(Mutex::new(Cache {
+                entries: LruCache::new(capacity),
+                generation: 0,
+            })),
+            metrics: Arc::new(CacheMetricsValue::new(metrics, backend)),
+        }
+    }
+
+    /// The wrapped backend.
+    #[must_use]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// The cached value of `key`, or the generation to pass to [`Self::fill`] with what the
+    /// backend has for it.
+    fn lookup(&self, key: &CacheKey<TYPES>) -> Result<CachedValue<TYPES>, u64> {
+        let mut cache = self.cache.lock();
+        match cache.entries.get(key) {
+            Some(value) => {
+                self.metrics.hits.add(1);
+                Ok(value.clone())
+            }
+            None => {
+                self.metrics.misses.add(1);
+                Err(cache.generation)
+            }
+        }
+    }
+
+    /// Cache values read from the backend, unless there was a write since the lookup in
+    /// `generation`, which may have changed them.
+    fn fill(
+        &self,
+        generation: u64,
+        entries: impl IntoIterator<Item = (CacheKey<TYPES>, CachedValue<TYPES>)>,
+    ) {
+        let mut cache = self.cache.lock();
+        if cache.generation != generation {
+            return;
+        }
+        for (key, value) in entries {
+            cache.entries.put(key, value);
+        }
+        self.metrics.entries.set(cache.entries.len());
+    }
+
+    /// Record a write to the backend which stored `entries` and may have changed the values of
+    /// the keys in `stale`.
+    fn update(
+        &self,
+        entries: impl IntoIterator<Item = (CacheKey<TYPES>, CachedValue<TYPES>)>,
+        stale: impl IntoIterator<Item = CacheKey<TYPES>>,
+    ) {
+        let mut cache = self.cache.lock();
+        cache.generation += 1;
+        for key in stale {
+            cache.entries.pop(&key);
+        }
+        for (key, value) in entries {
+            cache.entries.put(key, value);
+        }
+        self.metrics.entries.set(cache.entries.len());
+    }
+
+    /// Record a write to the backend which may have changed the values of the keys `stale`
+    /// selects.
+    fn invalidate(&self, stale: impl Fn(&CacheKey<TYPES>) -> bool) {
+        let mut cache = self.cache.lock();
+        cache.generation += 1;
+        let keys: Vec<_> = cache
+            .entries
+            .iter()
+            .map(|(key, _)| key)
+            .filter(|key| stale(key))
+            .cloned()
+            .collect();
+        for key in keys {
+            cache.entries.pop(&key);
+        }
+        self.metrics.entries.set(cache.entries.len());
+    }
+}
+
+/// The cache entry of a value stored by a [`Storage`] backend.
+fn stored_entry<TYPES: NodeType>(
+    value: StoredValue<TYPES>,
+) -> (CacheKey<TYPES>, CachedValue<TYPES>) {
+    (CacheKey::Stored(value.key()), CachedValue::Stored(value))
+}
+
+#[async_trait]
+impl<TYPES: NodeType, S: Storage<TYPES>> Storage<TYPES> for CachedStorage<TYPES, S> {
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        self.inner.append_vid(proposal).await
+    }
+
+    async fn append_vid2(
+        &self,
+        proposal: &Proposal<TYPES, VidDisperseShare2<TYPES>>,
+    ) -> Result<()> {
+        self.inner.append_vid2(proposal).await?;
+        self.update([stored_entry(StoredValue::VidShare(proposal.clone()))], []);
+        Ok(())
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        self.inner.append_da(proposal, vid_commit).await
+    }
+
+    async fn append_da2(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        self.inner.append_da2(proposal, vid_commit).await
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        self.inner.append_proposal(proposal).await
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.inner.append_proposal2(proposal).await?;
+        self.update([stored_entry(StoredValue::Proposal(proposal.clone()))], []);
+        Ok(())
+    }
+
+    async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()> {
+        self.inner.record_action(view, action).await
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        self.inner.update_high_qc(high_qc).await
+    }
+
+    async fn update_high_qc2(&self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        self.inner.update_high_qc2(high_qc).await?;
+        // The backend only keeps the QC if it is newer than the one it has
+        self.update([], [CacheKey::Stored(StorageKey::HighQc)]);
+        Ok(())
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leaves: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        self.inner.update_undecided_state(leaves, state).await
+    }
+
+    async fn update_undecided_state2(
+        &self,
+        leaves: LeafMap<TYPES>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        self.inner.update_undecided_state2(leaves, state).await
+    }
+
+    async fn write_view(&self, writes: ViewWrites<TYPES>) -> Result<()> {
+        let entries: Vec<_> = writes
+            .proposal
+            .clone()
+            .map(StoredValue::Proposal)
+            .into_iter()
+            .chain(writes.vid_share.clone().map(StoredValue::VidShare))
+            .map(stored_entry)
+            .collect();
+        let stale = writes
+            .high_qc
+            .is_some()
+            .then_some(CacheKey::Stored(StorageKey::HighQc));
+
+        self.inner.write_view(writes).await?;
+        self.update(entries, stale);
+        Ok(())
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<()> {
+        self.inner
+            .update_decided_upgrade_certificate(decided_upgrade_certificate)
+            .await
+    }
+
+    async fn migrate_consensus(
+        &self,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_proposal: fn(
+            Proposal<TYPES, QuorumProposal<TYPES>>,
+        ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.inner
+            .migrate_consensus(convert_leaf, convert_proposal)
+            .await?;
+        self.invalidate(|key| matches!(key, CacheKey::Stored(_)));
+        Ok(())
+    }
+
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        self.inner.storage_size().await
+    }
+
+    async fn prune(&self, view: TYPES::View) -> Result<()> {
+        self.inner.prune(view).await?;
+        self.invalidate(|key| {
+            matches!(
+                key,
+                CacheKey::Stored(StorageKey::Proposal(stored) | StorageKey::VidShare(stored, _))
+                    if *stored < view
+            )
+        });
+        Ok(())
+    }
+
+    async fn revert_to(&self, view: TYPES::View) -> Result<Vec<ViewUndo<TYPES>>> {
+        let reverted = self.inner.revert_to(view).await?;
+        let stale = reverted.iter().flat_map(|undo| {
+            undo.added
+                .iter()
+                .cloned()
+                .chain(undo.removed.iter().map(StoredValue::key))
+                .map(CacheKey::Stored)
+        });
+        self.update([], stale);
+        Ok(reverted)
+    }
+
+    async fn load(&self, key: &StorageKey<TYPES>) -> Result<Option<StoredValue<TYPES>>> {
+        let generation = match self.lookup(&CacheKey::Stored(key.clone())) {
+            Ok(CachedValue::Stored(value)) => return Ok(Some(value)),
+            Ok(_) => unreachable!("Storage keys only hold stored values"),
+            Err(generation) => generation,
+        };
+        let value = self.inner.load(key).await?;
+        if let Some(value) = &value {
+            self.fill(generation, [stored_entry(value.clone())]);
+        }
+        Ok(value)
+    }
+
+    async fn load_before(&self, view: TYPES::View) -> Result<Vec<StoredValue<TYPES>>> {
+        // Bulk reads would only push out the values worth caching
+        self.inner.load_before(view).await
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, S: ArchiveStorage<TYPES>> ArchiveStorage<TYPES> for CachedStorage<TYPES, S> {
+    async fn append_leaf(&self, leaf: Leaf2<TYPES>) -> Result<()> {
+        let entries = leaf_entries(&leaf);
+        self.inner.append_leaf(leaf).await?;
+        self.update(entries, []);
+        Ok(())
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        let entry = (
+            CacheKey::Proposal(proposal.data.view_number),
+            CachedValue::Proposal(proposal.clone()),
+        );
+        ArchiveStorage::append_proposal(&self.inner, proposal).await?;
+        self.update([entry], []);
+        Ok(())
+    }
+
+    async fn leaf(&self, height: u64) -> Result<Option<Leaf2<TYPES>>> {
+        let generation = match self.lookup(&CacheKey::Leaf(height)) {
+            Ok(CachedValue::Leaf(leaf)) => return Ok(Some(leaf)),
+            Ok(_) => unreachable!("Leaf keys only hold leaves"),
+            Err(generation) => generation,
+        };
+        let leaf = self.inner.leaf(height).await?;
+        if let Some(leaf) = &leaf {
+            self.fill(generation, leaf_entries(leaf));
+        }
+        Ok(leaf)
+    }
+
+    async fn leaves(&self, heights: Range<u64>) -> Result<Vec<Leaf2<TYPES>>> {
+        let generation = {
+            let mut cache = self.cache.lock();
+            let cached: Option<Vec<_>> = heights
+                .clone()
+                .map(|height| match cache.entries.get(&CacheKey::Leaf(height)) {
+                    Some(CachedValue::Leaf(leaf)) => Some(leaf.clone()),
+                    _ => None,
+                })
+                .collect();
+            if let Some(leaves) = cached {
+                self.metrics.hits.add(1);
+                return Ok(leaves);
+            }
+            self.metrics.misses.add(1);
+            cache.generation
+        };
+        // Read the whole range in one go rather than leaf by leaf
+        let leaves = self.inner.leaves(heights).await?;
+        self.fill(generation, leaves.iter().flat_map(leaf_entries));
+        Ok(leaves)
+    }
+
+    async fn leaf_by_view(&self, view: TYPES::View) -> Result<Option<Leaf2<TYPES>>> {
+        if let Ok(CachedValue::Height(height)) = self.lookup(&CacheKey::HeightByView(view)) {
+            if let Some(leaf) = self.leaf(height).await? {
+                return Ok(Some(leaf));
+            }
+        }
+        let generation = self.cache.lock().generation;
+        let leaf = self.inner.leaf_by_view(view).await?;
+        if let Some(leaf) = &leaf {
+            self.fill(generation, leaf_entries(leaf));
+        }
+        Ok(leaf)
+    }
+
+    async fn leaf_by_commitment(
+        &self,
+        commitment: Commitment<Leaf2<TYPES>>,
+    ) -> Result<Option<Leaf2<TYPES>>> {
+        if let Ok(CachedValue::Height(height)) =
+            self.lookup(&CacheKey::HeightByCommitment(commitment))
+        {
+            if let Some(leaf) = self.leaf(height).await? {
+                return Ok(Some(leaf));
+            }
+        }
+        let generation = self.cache.lock().generation;
+        let leaf = self.inner.leaf_by_commitment(commitment).await?;
+        if let Some(leaf) = &leaf {
+            self.fill(generation, leaf_entries(leaf));
+        }
+        Ok(leaf)
+    }
+
+    async fn transaction_height(
+        &self,
+        commitment: Commitment<TYPES::Transaction>,
+    ) -> Result<Option<u64>> {
+        // Transactions are rarely looked up twice, and there are many of them
+        self.inner.transaction_height(commitment).await
+    }
+
+    async fn proposal(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<Proposal<TYPES, QuorumProposal2<TYPES>>>> {
+        let generation = match self.lookup(&CacheKey::Proposal(view)) {
+            Ok(CachedValue::Proposal(proposal)) => return Ok(Some(proposal)),
+            Ok(_) => unreachable!("Proposal keys only hold proposals"),
+            Err(generation) => generation,
+        };
+        let proposal = self.inner.proposal(view).await?;
+        if let Some(proposal) = &proposal {
+            self.fill(
+                generation,
+                [(
+                    CacheKey::Proposal(view),
+                    CachedValue::Proposal(proposal.clone()),
+                )],
+            );
+        }
+        Ok(proposal)
+    }
+
+    async fn latest_height(&self) -> Result<Option<u64>> {
+        self.inner.latest_height().await
+    }
+}
@@ -0,0 +1,340 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Export of decided blocks as content-addressed CAR archives.
+//!
+//! Each decided leaf with a payload is written as a [CARv1] file holding three blocks: the
+//! encoded payload and the leaf without its payload, both as raw blocks, and a DAG-CBOR root
+//! block indexing them by their commitments:
+//!
+//! ```text
+//! { "leaf": <CID>, "height": <u64>, "payload": <CID>,
+//!   "leaf_commitment": <bytes>, "payload_commitment": <bytes> }
+//! ```
+//!
+//! Blocks are addressed by CIDv1 over their SHA-256, so the files can be imported into IPFS or
+//! sealed into Filecoin deals as they are, and anyone who knows a leaf commitment can find the
+//! archive, named after it, and check what they fetched against the root CID.
+//!
+//! [CARv1]: https://ipld.io/specs/transport/car/carv1/
+
+use std::{fmt, fs, path::PathBuf};
+
+use anyhow::{Context, Result};
+use bincode::Options;
+use committable::Committable;
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    data::Leaf2,
+    traits::{node_implementation::NodeType, BlockPayload},
+    utils::bincode_opts,
+};
+use sha2::{Digest, Sha256};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Multicodec of raw binary blocks
+pub const CODEC_RAW: u64 = 0x55;
+
+/// Multicodec of DAG-CBOR blocks
+pub const CODEC_DAG_CBOR: u64 = 0x71;
+
+/// Multihash code of SHA-256
+const MULTIHASH_SHA2_256: u64 = 0x12;
+
+/// CBOR tag of a CID in DAG-CBOR
+const CBOR_TAG_CID: u64 = 42;
+
+/// A CIDv1 addressing a block by its SHA-256.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Cid {
+    /// Multicodec of the block
+    codec: u64,
+    /// SHA-256 of the block
+    digest: [u8; 32],
+}
+
+impl Cid {
+    /// The CID of `data` encoded with `codec`.
+    #[must_use]
+    pub fn of(codec: u64, data: &[u8]) -> Self {
+        Self {
+            codec,
+            digest: Sha256::digest(data).into(),
+        }
+    }
+
+    /// Multicodec of the block.
+    #[must_use]
+    pub fn codec(&self) -> u64 {
+        self.codec
+    }
+
+    /// The binary form of the CID.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(36);
+        write_varint(&mut bytes, 1);
+        write_varint(&mut bytes, self.codec);
+        write_varint(&mut bytes, MULTIHASH_SHA2_256);
+        write_varint(&mut bytes, self.digest.len() as u64);
+        bytes.extend_from_slice(&self.digest);
+        bytes
+    }
+}
+
+impl fmt::Display for Cid {
+    /// The multibase form used by IPFS: `b` followed by the lowercase, unpadded base32 of the
+    /// binary form.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        const ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+        let mut encoded = String::from("b");
+        let (mut buffer, mut bits) = (0u16, 0);
+        for byte in self.to_bytes() {
+            buffer = (buffer << 8) | u16::from(byte);
+            bits += 8;
+            while bits >= 5 {
+                bits -= 5;
+                encoded.push(ALPHABET[usize::from((buffer >> bits) & 0x1f)] as char);
+            }
+        }
+        if bits > 0 {
+            encoded.push(ALPHABET[usize::from((buffer << (5 - bits)) & 0x1f)] as char);
+        }
+        f.write_str(&encoded)
+    }
+}
+
+/// A CAR archive held in memory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CarArchive {
+    /// CID of the root block
+    pub root: Cid,
+    /// The archive, ready to be written out
+    pub bytes: Vec<u8>,
+}
+
+impl CarArchive {
+    /// Build an archive of `blocks`, the first of which is the root.
+    fn new(blocks: &[(Cid, Vec<u8>)]) -> Self {
+        let root = blocks[0].0;
+
+        let mut header = Vec::new();
+        write_cbor_head(&mut header, 5, 2);
+        write_cbor_text(&mut header, "roots");
+        write_cbor_head(&mut header, 4, 1);
+        write_cbor_cid(&mut header, &root);
+        write_cbor_text(&mut header, "version");
+        write_cbor_head(&mut header, 0, 1);
+
+        let mut bytes = Vec::new();
+        write_varint(&mut bytes, header.len() as u64);
+        bytes.extend_from_slice(&header);
+        for (cid, data) in blocks {
+            let cid = cid.to_bytes();
+            write_varint(&mut bytes, (cid.len() + data.len()) as u64);
+            bytes.extend_from_slice(&cid);
+            bytes.extend_from_slice(data);
+        }
+
+        Self { root, bytes }
+    }
+
+    /// Archive `leaf` with its payload.
+    ///
+    /// # Errors
+    /// Fails if the leaf has no payload or cannot be serialized.
+    pub fn from_leaf<TYPES: NodeType>(leaf: &Leaf2<TYPES>) -> Result<Self> {
+        let mut leaf = leaf.clone();
+        let payload = leaf
+            .unfill_block_payload()
+            .context("Cannot archive a leaf without its payload")?;
+        let payload = payload.encode().to_vec();
+        let leaf_commitment = leaf.commit();
+        let payload_commitment = bincode_opts().serialize(&leaf.payload_commitment())?;
+        let height = leaf.height();
+        let leaf = bincode_opts().serialize(&leaf)?;
+
+        let leaf_cid = Cid::of(CODEC_RAW, &leaf);
+        let payload_cid = Cid::of(CODEC_RAW, &payload);
+
+        // DAG-CBOR sorts map keys by length first, then bytewise
+        let mut index = Vec::new();
+        write_cbor_head(&mut index, 5, 5);
+        write_cbor_text(&mut index, "leaf");
+        write_cbor_cid(&mut index, &leaf_cid);
+        write_cbor_text(&mut index, "height");
+        write_cbor_head(&mut index, 0, height);
+        write_cbor_text(&mut index, "payload");
+        write_cbor_cid(&mut index, &payload_cid);
+        write_cbor_text(&mut index, "leaf_commitment");
+        write_cbor_bytes(&mut index, leaf_commitment.as_ref());
+        write_cbor_text(&mut index, "payload_commitment");
+        write_cbor_bytes(&mut index, &payload_commitment);
+
+        Ok(Self::new(&[
+            (Cid::of(CODEC_DAG_CBOR, &index), index),
+            (leaf_cid, leaf),
+            (payload_cid, payload),
+        ]))
+    }
+}
+
+/// Write `value` as an unsigned LEB128 varint.
+#[allow(clippy::cast_possible_truncation)]
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+/// Write the head of a CBOR item of major type `major` with argument `value`.
+#[allow(clippy::cast_possible_truncation)]
+fn write_cbor_head(out: &mut Vec<u8>, major: u8, value: u64) {
+    let major = major << 5;
+    match value {
+        0..=23 => out.push(major | value as u8),
+        24..=0xff => out.extend_from_slice(&[major | 24, value as u8]),
+        0x100..=0xffff => {
+            out.push(major | 25);
+            out.extend_from_slice(&(value as u16).to_be_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.push(major | 26);
+            out.extend_from_slice(&(value as u32).to_be_bytes());
+        }
+        _ => {
+            out.push(major | 27);
+            out.extend_from_slice(&value.to_be_bytes());
+        }
+    }
+}
+
+/// Write a CBOR text string.
+fn write_cbor_text(out: &mut Vec<u8>, text: &str) {
+    write_cbor_head(out, 3, text.len() as u64);
+    out.extend_from_slice(text.as_bytes());
+}
+
+/// Write a CBOR byte string.
+fn write_cbor_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_cbor_head(out, 2, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
+
+/// Write a link to `cid` as DAG-CBOR does: a tagged byte string of the binary CID, after a zero
+/// byte for the identity multibase.
+fn write_cbor_cid(out: &mut Vec<u8>, cid: &Cid) {
+    write_cbor_head(out, 6, CBOR_TAG_CID);
+    let mut bytes = vec![0];
+    bytes.extend(cid.to_bytes());
+    write_cbor_bytes(out, &bytes);
+}
+
+/// Handle to the task exporting decided blocks. Exporting stops when the handle is dropped.
+pub struct CarExporter {
+    /// The exporting task
+    task: JoinHandle<()>,
+}
+
+impl CarExporter {
+    /// Start writing every decided leaf in `events` which has its payload to `dir`, as
+    /// `<leaf commitment>.car`. Leaves without their payload, as decided by nodes outside the DA
+    /// committee, are skipped.
+    ///
+    /// # Errors
+    /// Fails if `dir` cannot be created.
+    pub fn spawn<TYPES: NodeType>(
+        dir: PathBuf,
+        mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Result<Self> {
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create export directory {}", dir.display()))?;
+
+        let task = tokio::spawn(async move {
+            while let Some(event) = events.next().await {
+                let EventType::Decide { leaf_chain, .. } = event.event else {
+                    continue;
+                };
+                for leaf_info in leaf_chain.iter().rev() {
+                    let leaf = &leaf_info.leaf;
+                    if leaf.block_payload().is_none() {
+                        tracing::debug!("Not exporting leaf {} without its payload", leaf.height());
+                        continue;
+                    }
+                    let path = dir.join(format!("{}.car", leaf.commit()));
+                    let result = CarArchive::from_leaf(leaf).and_then(|car| {
+                        fs::write(&path, &car.bytes)?;
+                        Ok(car.root)
+                    });
+                    match result {
+                        Ok(root) => tracing::info!(
+                            "Exported leaf {} as {} with root {root}",
+                            leaf.height(),
+                            path.display()
+                        ),
+                        Err(e) => {
+                            tracing::error!("Failed to export leaf {}: {e:#}", leaf.height());
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self { task })
+    }
+}
+
+impl Drop for CarExporter {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cid_matches_ipfs() {
+        // The CID IPFS gives an empty file imported with raw leaves
+        assert_eq!(
+            Cid::of(CODEC_RAW, b"").to_string(),
+            "bafkreihdwdcefgh4dqkjv67uzcmw7ojee6xedzdetojuzjevtenxquvyku"
+        );
+    }
+
+    #[test]
+    fn archive_frames_header_and_blocks() {
+        let blocks = [
+            (Cid::of(CODEC_RAW, b"root"), b"root".to_vec()),
+            (Cid::of(CODEC_RAW, b"leaf"), b"leaf".to_vec()),
+        ];
+        let car = CarArchive::new(&blocks);
+        assert_eq!(car.root, blocks[0].0);
+
+        // The header is a map of the roots and the version
+        let header_len = usize::from(car.bytes[0]);
+        let header = &car.bytes[1..=header_len];
+        assert_eq!(header[0], 0xa2);
+        assert_eq!(header.last(), Some(&0x01));
+
+        // Each block is framed by its length, then its CID and data
+        let mut rest = &car.bytes[1 + header_len..];
+        for (cid, data) in &blocks {
+            let cid = cid.to_bytes();
+            assert_eq!(usize::from(rest[0]), cid.len() + data.len());
+            assert_eq!(&rest[1..=cid.len()], &cid[..]);
+            assert_eq!(&rest[1 + cid.len()..1 + cid.len() + data.len()], &data[..]);
+            rest = &rest[1 + cid.len() + data.len()..];
+        }
+        assert!(rest.is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Background task which periodically checkpoints the decided chain to a [`CheckpointSink`].
+
+use std::{num::NonZeroU64, sync::Arc};
+
+use async_lock::RwLock;
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    checkpoint::{Checkpoint, CheckpointError, CheckpointSink},
+    data::Leaf2,
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+    },
+};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Handle to a running checkpointing task. The task is stopped when the handle is dropped.
+pub struct Checkpointer<TYPES: NodeType> {
+    /// The last checkpoint accepted by the sink
+    latest: Arc<RwLock<Option<Checkpoint<TYPES>>>>,
+    /// Memberships used to look up the checkpointed stake table
+    memberships: Arc<TYPES::Membership>,
+    /// The checkpointing task
+    task: JoinHandle<()>,
+}
+
+impl<TYPES: NodeType> Checkpointer<TYPES> {
+    /// Spawn a task which checkpoints the newest decided leaf to `sink` every time the decided
+    /// block height crosses a multiple of `interval`.
+    pub fn spawn<S: CheckpointSink<TYPES>>(
+        sink: S,
+        interval: NonZeroU64,
+        memberships: Arc<TYPES::Membership>,
+        mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let latest = Arc::new(RwLock::new(None::<Checkpoint<TYPES>>));
+        let task = tokio::spawn({
+            let latest = Arc::clone(&latest);
+            let memberships = Arc::clone(&memberships);
+            async move {
+                while let Some(event) = events.next().await {
+                    let EventType::Decide { leaf_chain, qc, .. } = event.event else {
+                        continue;
+                    };
+                    let Some(newest) = leaf_chain.first() else {
+                        continue;
+                    };
+
+                    let last_height = latest
+                        .read()
+                        .await
+                        .as_ref()
+                        .map(|checkpoint| checkpoint.block_height);
+                    if !crosses_interval(last_height, newest.leaf.height(), interval) {
+                        continue;
+                    }
+
+                    let stake_table = memberships.stake_table(qc.data.epoch).into_owned();
+                    let checkpoint = match Checkpoint::new(&newest.leaf, (*qc).clone(), stake_table)
+                    {
+                        Ok(checkpoint) => checkpoint,
+                        Err(e) => {
+                            tracing::error!("Failed to build checkpoint: {e}");
+                            continue;
+                        }
+                    };
+
+                    match sink.submit(&checkpoint).await {
+                        Ok(()) => {
+                            tracing::info!(
+                                "Checkpointed block {} at view {:?}",
+                                checkpoint.block_height,
+                                checkpoint.view_number
+                            );
+                            *latest.write().await = Some(checkpoint);
+                        }
+                        Err(e) => tracing::warn!("Failed to submit checkpoint: {e:#}"),
+                    }
+                }
+            }
+        });
+
+        Self {
+            latest,
+            memberships,
+            task,
+        }
+    }
+
+    /// The last checkpoint accepted by the sink, if any.
+    pub async fn latest(&self) -> Option<Checkpoint<TYPES>> {
+        self.latest.read().await.clone()
+    }
+
+    /// Verify that `leaves` extend the last checkpoint and that `qc` certifies the last of them.
+    /// See [`Checkpoint::verify_certified_extension`].
+    ///
+    /// # Errors
+    /// If there is no checkpoint yet, or the extension is invalid.
+    pub async fn verify_certified_extension<V: Versions>(
+        &self,
+        leaves: &[Leaf2<TYPES>],
+        qc: &QuorumCertificate2<TYPES>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<(), CheckpointError> {
+        let checkpoint = self.latest().await.ok_or(CheckpointError::NoCheckpoint)?;
+        let epoch = checkpoint.qc.data.epoch;
+
+        checkpoint
+            .verify_certified_extension(
+                leaves,
+                qc,
+                self.memberships.stake_table(epoch).into_owned(),
+                self.memberships.success_threshold(epoch),
+                upgrade_lock,
+            )
+            .await
+    }
+}
+
+impl<TYPES: NodeType> Drop for Checkpointer<TYPES> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Whether moving the decided height from the last checkpoint to `height` crosses a multiple of
+/// `interval`. The first decide is always checkpointed.
+fn crosses_interval(last_height: Option<u64>, height: u64, interval: NonZeroU64) -> bool {
+    last_height.map_or(true, |last| height / interval.get() > last / interval.get())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn checkpoints_at_interval_boundaries() {
+        let interval = NonZeroU64::new(10).unwrap();
+        assert!(crosses_interval(None, 3, interval));
+        assert!(!crosses_interval(Some(3), 9, interval));
+        assert!(crosses_interval(Some(9), 10, interval));
+        // Decides can skip heights; any crossing counts.
+        assert!(crosses_interval(Some(12), 35, interval));
+        assert!(!crosses_interval(Some(20), 20, interval));
+    }
+}
@@ -0,0 +1,349 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Generation of the configuration files of a deployment.
+//!
+//! [`Deployment::generate`] creates fresh keys for every validator and derives from them
+//! everything the nodes must agree on: the genesis stake table and DA committee, and where every
+//! node listens for libp2p connections, with the peer ID it will present. [`Deployment::write`]
+//! lays out one directory per node:
+//!
+//! ```text
+//! genesis.json                 the stake table and DA committee
+//! node-<i>/keystore.json       the node's keys, encrypted with the deployment password
+//! node-<i>/network-config.json the shared configuration, with the node's index
+//! node-<i>/node.json           where the node listens, and its stake
+//! ```
+//!
+//! Each directory can be mounted into a container or copied to a host as it is. At startup a
+//! node calls [`DeployedNode::load`], which also checks that its keys are the ones the genesis
+//! stake table has at its index.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{ensure, Context, Result};
+use hotshot_types::{
+    hotshot_config_file::HotShotConfigFile,
+    keystore::{KdfParams, Keystore},
+    network::{Libp2pConfig, NetworkConfig},
+    traits::signature_key::SignatureKey,
+    PeerConfig, ValidatorConfig,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::traits::implementations::{derive_libp2p_multiaddr, derive_libp2p_peer_id};
+
+/// Parameters of a deployment.
+#[derive(Clone, Debug)]
+pub struct DeploymentParams<KEY: SignatureKey> {
+    /// Number of validators
+    pub validators: usize,
+    /// Number of validators in the DA committee, which are the first ones
+    pub da_validators: usize,
+    /// Stake of every validator
+    pub stake_value: u64,
+    /// Hosts the validators are reachable at, assigned round-robin
+    pub hosts: Vec<String>,
+    /// libp2p port of the first validator on each host. Validators sharing a host listen on
+    /// consecutive ports.
+    pub libp2p_base_port: u16,
+    /// Password the keystores are encrypted with
+    pub keystore_password: String,
+    /// Key derivation of the keystores
+    pub kdf: KdfParams,
+    /// Consensus parameters. The stake tables and committee sizes are replaced by the generated
+    /// ones.
+    pub config: HotShotConfigFile<KEY>,
+}
+
+impl<KEY: SignatureKey> DeploymentParams<KEY> {
+    /// `validators` validators, all in the DA committee, each on its own host named `node-<i>`
+    /// as in a container network, on port 9000.
+    #[must_use]
+    pub fn new(validators: usize, keystore_password: String) -> Self {
+        Self {
+            validators,
+            da_validators: validators,
+            stake_value: 1,
+            hosts: (0..validators).map(|i| format!("node-{i}")).collect(),
+            libp2p_base_port: 9000,
+            keystore_password,
+            kdf: KdfParams::default(),
+            config: HotShotConfigFile::hotshot_config_5_nodes_10_da(),
+        }
+    }
+}
+
+/// Where a node listens, and what it stakes.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeManifest {
+    /// Index of the node in the stake table
+    pub index: u64,
+    /// Address to bind the libp2p endpoint to, as `ip:port`
+    pub libp2p_bind_address: String,
+    /// Address other nodes reach the libp2p endpoint at, as `host:port`
+    pub libp2p_advertise_address: String,
+    /// Stake of the node
+    pub stake_value: u64,
+    /// Whether the node is in the DA committee
+    pub is_da: bool,
+}
+
+/// The genesis stake table and DA committee.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct Genesis<KEY: SignatureKey> {
+    /// All validators and their stake
+    pub stake_table: Vec<PeerConfig<KEY>>,
+    /// The DA committee
+    pub da_committee: Vec<PeerConfig<KEY>>,
+}
+
+/// The files of one node.
+#[derive(Clone, Debug)]
+pub struct NodeDeployment<KEY: SignatureKey> {
+    /// Where the node listens, and what it stakes
+    pub manifest: NodeManifest,
+    /// The node's encrypted keys
+    pub keystore: Keystore<KEY>,
+    /// The configuration shared by all nodes, with this node's index
+    pub network_config: NetworkConfig<KEY>,
+}
+
+/// The files of every node of a deployment.
+#[derive(Clone, Debug)]
+pub struct Deployment<KEY: SignatureKey> {
+    /// The genesis stake table and DA committee
+    pub genesis: Genesis<KEY>,
+    /// The nodes, by index
+    pub nodes: Vec<NodeDeployment<KEY>>,
+}
+
+impl<KEY: SignatureKey> Deployment<KEY> {
+    /// Generate keys for every validator, and the configuration of every node.
+    ///
+    /// # Errors
+    /// If the parameters are inconsistent, a host is not a valid address, or the key derivation
+    /// is too weak.
+    pub fn generate(params: DeploymentParams<KEY>) -> Result<Self> {
+        ensure!(params.validators > 0, "A deployment needs validators");
+        ensure!(
+            params.da_validators <= params.validators,
+            "The DA committee can't be larger than the set of validators"
+        );
+        ensure!(!params.hosts.is_empty(), "A deployment needs hosts");
+
+        let validators: Vec<_> = (0..params.validators)
+            .map(|i| ValidatorConfig::<KEY>::generate(params.stake_value, i < params.da_validators))
+            .collect();
+        let genesis = Genesis {
+            stake_table: validators
+                .iter()
+                .map(ValidatorConfig::public_config)
+                .collect(),
+            da_committee: validators
+                .iter()
+                .filter(|validator| validator.is_da)
+                .map(ValidatorConfig::public_config)
+                .collect(),
+        };
+
+        let manifests = (0..)
+            .zip(&validators)
+            .map(|(index, validator)| {
+                let i = usize::try_from(index)?;
+                let host = &params.hosts[i % params.hosts.len()];
+                let port = u16::try_from(i / params.hosts.len())
+                    .ok()
+                    .and_then(|offset| params.libp2p_base_port.checked_add(offset))
+                    .context("Too many validators per host for the port range")?;
+                Ok(NodeManifest {
+                    index,
+                    libp2p_bind_address: format!("0.0.0.0:{port}"),
+                    libp2p_advertise_address: format!("{host}:{port}"),
+                    stake_value: validator.stake_value,
+                    is_da: validator.is_da,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // Every node bootstraps off every other one
+        let bootstrap_nodes = validators
+            .iter()
+            .zip(&manifests)
+            .map(|(validator, manifest)| {
+                Ok((
+                    derive_libp2p_peer_id::<KEY>(&validator.private_key)?,
+                    derive_libp2p_multiaddr(&manifest.libp2p_advertise_address)?,
+                ))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut config = params.config;
+        config.num_nodes_with_stake = params
+            .validators
+            .try_into()
+            .context("A deployment needs validators")?;
+        config
+            .known_nodes_with_stake
+            .clone_from(&genesis.stake_table);
+        config.known_da_nodes.clone_from(&genesis.da_committee);
+        config.staked_da_nodes = params.da_validators;
+        config.num_bootstrap = params.validators;
+        let network_config = NetworkConfig {
+            libp2p_config: Some(Libp2pConfig { bootstrap_nodes }),
+            config: config.into(),
+            ..NetworkConfig::default()
+        };
+
+        let nodes = validators
+            .iter()
+            .zip(manifests)
+            .map(|(validator, manifest)| {
+                Ok(NodeDeployment {
+                    keystore: Keystore::encrypt(validator, &params.keystore_password, params.kdf)?,
+                    network_config: NetworkConfig {
+                        node_index: manifest.index,
+                        ..network_config.clone()
+                    },
+                    manifest,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { genesis, nodes })
+    }
+
+    /// Write the deployment to `dir`, with one directory per node.
+    ///
+    /// # Errors
+    /// If a file can't be written, or already exists.
+    pub fn write(&self, dir: &Path) -> Result<()> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        fs::write(
+            dir.join("genesis.json"),
+            serde_json::to_vec_pretty(&self.genesis)?,
+        )?;
+
+        for node in &self.nodes {
+            let node_dir = dir.join(format!("node-{}", node.manifest.index));
+            fs::create_dir_all(&node_dir)
+                .with_context(|| format!("Failed to create {}", node_dir.display()))?;
+            node.keystore.write(&node_dir.join("keystore.json"))?;
+            node.network_config
+                .to_file(node_dir.join("network-config.json").display().to_string())?;
+            fs::write(
+                node_dir.join("node.json"),
+                serde_json::to_vec_pretty(&node.manifest)?,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A node's share of a deployment, as loaded at startup.
+#[derive(Clone, Debug)]
+pub struct DeployedNode<KEY: SignatureKey> {
+    /// Where the node listens, and what it stakes
+    pub manifest: NodeManifest,
+    /// The node's keys
+    pub validator_config: ValidatorConfig<KEY>,
+    /// The configuration shared by all nodes, with this node's index
+    pub network_config: NetworkConfig<KEY>,
+}
+
+impl<KEY: SignatureKey> DeployedNode<KEY> {
+    /// Load the node directory `dir` written by [`Deployment::write`], decrypting the keys with
+    /// `password`.
+    ///
+    /// # Errors
+    /// If a file is missing or malformed, the password is wrong, or the keys are not the ones
+    /// the genesis stake table has at the node's index.
+    pub fn load(dir: &Path, password: &str) -> Result<Self> {
+        let path = |name: &str| -> PathBuf { dir.join(name) };
+        let manifest: NodeManifest = serde_json::from_slice(&fs::read(path("node.json"))?)
+            .context("Malformed node manifest")?;
+        let network_config =
+            NetworkConfig::<KEY>::from_file(path("network-config.json").display().to_string())?;
+        let validator_config = ValidatorConfig::from_keystore(
+            &path("keystore.json"),
+            password,
+            manifest.stake_value,
+            manifest.is_da,
+        )?;
+
+        ensure!(
+            network_config.node_index == manifest.index,
+            "Network config is for node {}, not node {}",
+            network_config.node_index,
+            manifest.index
+        );
+        let expected = network_config
+            .config
+            .known_nodes_with_stake
+            .get(usize::try_from(manifest.index)?)
+            .context("Node index is outside the stake table")?;
+        ensure!(
+            *expected == validator_config.public_config(),
+            "Keys do not match the genesis stake table entry of node {}",
+            manifest.index
+        );
+
+        Ok(Self {
+            manifest,
+            validator_config,
+            network_config,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hotshot_types::{keystore::MIN_KDF_ITERATIONS, signature_key::BLSPubKey};
+
+    use super::*;
+
+    #[test]
+    fn nodes_load_their_share_of_the_genesis() {
+        let dir = tempfile::tempdir().unwrap();
+        let params = DeploymentParams::<BLSPubKey> {
+            da_validators: 2,
+            hosts: vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()],
+            kdf: KdfParams {
+                iterations: MIN_KDF_ITERATIONS,
+            },
+            ..DeploymentParams::new(3, "password".to_string())
+        };
+        let deployment = Deployment::generate(params).unwrap();
+        deployment.write(dir.path()).unwrap();
+
+        let addresses: Vec<_> = deployment
+            .nodes
+            .iter()
+            .map(|node| node.manifest.libp2p_advertise_address.as_str())
+            .collect();
+        assert_eq!(
+            addresses,
+            ["10.0.0.1:9000", "10.0.0.2:9000", "10.0.0.1:9001"]
+        );
+        assert_eq!(deployment.genesis.da_committee.len(), 2);
+
+        for i in 0..3 {
+            let node =
+                DeployedNode::<BLSPubKey>::load(&dir.path().join(format!("node-{i}")), "password")
+                    .unwrap();
+            assert_eq!(node.network_config.node_index, i);
+            assert_eq!(
+                node.network_config.config.known_nodes_with_stake,
+                deployment.genesis.stake_table
+            );
+        }
+        assert!(DeployedNode::<BLSPubKey>::load(&dir.path().join("node-0"), "wrong").is_err());
+    }
+}
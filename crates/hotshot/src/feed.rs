@@ -0,0 +1,183 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A WebSocket feed of consensus progress, for real-time dashboards.
+//!
+//! The [`Feed`] condenses the event stream of a node into view changes (with the new leader), QC
+//! formation and decides, and [`define_api`] pushes them to every subscriber as JSON. Subscribers
+//! which fall behind lose the oldest events rather than slowing the node down.
+
+use std::sync::Arc;
+
+use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    data::Leaf2,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+};
+use serde::{Deserialize, Serialize};
+use tide_disco::{api::ApiError, Api};
+use tokio::task::JoinHandle;
+
+use crate::{
+    query_api::{Error, Version},
+    types::{Event, EventType},
+};
+
+/// Number of events buffered for each subscriber
+const FEED_BUFFER: usize = 1024;
+
+/// An event pushed to dashboards.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", bound(deserialize = ""))]
+pub enum FeedEvent<TYPES: NodeType> {
+    /// The node moved to a new view
+    ViewChange {
+        /// The new view
+        view: TYPES::View,
+        /// Leader of the new view, if known
+        leader: Option<TYPES::SignatureKey>,
+    },
+    /// A QC was formed for a proposal
+    QcFormed {
+        /// View of the certified proposal
+        view: TYPES::View,
+        /// Commitment of the certified leaf
+        leaf: Commitment<Leaf2<TYPES>>,
+    },
+    /// Leaves were decided
+    Decide {
+        /// View of the newest decided leaf
+        view: TYPES::View,
+        /// Height of the newest decided leaf
+        height: u64,
+        /// Commitment of the newest decided leaf
+        leaf: Commitment<Leaf2<TYPES>>,
+        /// Number of leaves decided at once
+        num_leaves: usize,
+        /// Size of the newest decided block, if known
+        block_size: Option<u64>,
+    },
+}
+
+/// Condenses the event stream of a node into [`FeedEvent`]s for subscribers.
+///
+/// The feed stops when it is dropped.
+pub struct Feed<TYPES: NodeType> {
+    /// Keeps the feed channel open while nobody is subscribed
+    receiver: InactiveReceiver<FeedEvent<TYPES>>,
+    /// The task condensing events
+    task: JoinHandle<()>,
+}
+
+impl<TYPES: NodeType> Feed<TYPES> {
+    /// Start condensing `events`.
+    pub fn spawn(
+        memberships: Arc<TYPES::Membership>,
+        events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let (mut sender, receiver) = broadcast(FEED_BUFFER);
+        // Drop the oldest events for slow subscribers, and don't wait for anyone to subscribe
+        sender.set_overflow(true);
+        sender.set_await_active(false);
+        let task = tokio::spawn(condense(sender, memberships, events));
+
+        Self {
+            receiver: receiver.deactivate(),
+            task,
+        }
+    }
+
+    /// Subscribe to the feed.
+    #[must_use]
+    pub fn subscribe(&self) -> Receiver<FeedEvent<TYPES>> {
+        self.receiver.activate_cloned()
+    }
+}
+
+impl<TYPES: NodeType> Drop for Feed<TYPES> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Turn `events` into feed events.
+async fn condense<TYPES: NodeType>(
+    sender: Sender<FeedEvent<TYPES>>,
+    memberships: Arc<TYPES::Membership>,
+    mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin,
+) {
+    let mut epoch = TYPES::Epoch::genesis();
+    let mut last_qc_view = None;
+
+    while let Some(event) = events.next().await {
+        let feed_event = match event.event {
+            EventType::ViewFinished { view_number } => {
+                let view = view_number + 1;
+                FeedEvent::ViewChange {
+                    view,
+                    leader: memberships.leader(view, epoch).ok(),
+                }
+            }
+            EventType::QuorumProposal { proposal, .. } => {
+                let qc = proposal.data.justify_qc;
+                epoch = epoch.max(qc.data.epoch);
+                if last_qc_view >= Some(qc.view_number) {
+                    continue;
+                }
+                last_qc_view = Some(qc.view_number);
+                FeedEvent::QcFormed {
+                    view: qc.view_number,
+                    leaf: qc.data.leaf_commit,
+                }
+            }
+            EventType::Decide {
+                leaf_chain,
+                block_size,
+                ..
+            } => {
+                let Some(newest) = leaf_chain.first() else {
+                    continue;
+                };
+                FeedEvent::Decide {
+                    view: newest.leaf.view_number(),
+                    height: newest.leaf.height(),
+                    leaf: newest.leaf.commit(),
+                    num_leaves: leaf_chain.len(),
+                    block_size,
+                }
+            }
+            _ => continue,
+        };
+
+        // Only fails if there are no subscribers, in which case there is nobody to tell
+        let _ = sender.broadcast_direct(feed_event).await;
+    }
+}
+
+/// Define the feed API, which streams [`FeedEvent`]s over a WebSocket at `events`.
+///
+/// # Errors
+/// Fails if the API specification is invalid.
+pub fn define_api<TYPES: NodeType>() -> Result<Api<Feed<TYPES>, Error, Version>, ApiError> {
+    let toml: toml::Value = toml::from_str(include_str!("../api/feed.toml")).map_err(|err| {
+        ApiError::CannotReadToml {
+            reason: err.to_string(),
+        }
+    })?;
+
+    let mut api = Api::new(toml)?;
+    api.with_version("0.1.0".parse().unwrap())
+        .stream("events", |_req, feed: &Feed<TYPES>| {
+            feed.subscribe().map(Ok).boxed()
+        })?;
+
+    Ok(api)
+}
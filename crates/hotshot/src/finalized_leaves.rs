@@ -0,0 +1,177 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Subscriptions to finalized leaves, with backpressure.
+//!
+//! The event stream drops events for consumers which fall too far behind, and buffering for them
+//! in memory grows without bound. A [`FinalizedLeaves`] subscription instead reads decided leaves
+//! from the [`ArchiveStorage`] the [`Archiver`](crate::archive::Archiver) writes to, and only when
+//! the consumer asks for the next one: a slow consumer leaves its backlog in storage, holding at
+//! most [`SUBSCRIPTION_BATCH`] leaves in memory, and a fast one waits for the archiver.
+//!
+//! Each subscription starts at a [`LeafCursor`]. A consumer which disconnects can resume from the
+//! view of the last leaf it processed, and receives every leaf after it exactly once.
+//!
+//! ```ignore
+//! let archiver = handle.spawn_archiver(Arc::clone(&archive));
+//! let leaves = FinalizedLeaves::new(archive, archiver.archived_height());
+//! let mut subscription = leaves.subscribe(LeafCursor::After(last_processed_view));
+//! while let Some(leaf) = subscription.next().await {
+//!     process(leaf?).await;
+//! }
+//! ```
+
+use std::{collections::VecDeque, sync::Arc};
+
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, BoxStream, StreamExt};
+use hotshot_types::{
+    data::Leaf2,
+    traits::{archive::ArchiveStorage, node_implementation::NodeType},
+};
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+/// Largest number of leaves a subscription reads from storage at once.
+pub const SUBSCRIPTION_BATCH: u64 = 64;
+
+/// Where a subscription to finalized leaves starts.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub enum LeafCursor<TYPES: NodeType> {
+    /// From the first leaf in the archive
+    Genesis,
+    /// From the first leaf archived after the subscription is first polled
+    Latest,
+    /// From the leaf after the one decided in this view
+    After(TYPES::View),
+}
+
+/// Source of subscriptions to the finalized leaves in an archive.
+pub struct FinalizedLeaves<A> {
+    /// The archive leaves are read from
+    archive: Arc<A>,
+    /// Height of the latest leaf written to the archive
+    archived: watch::Receiver<Option<u64>>,
+}
+
+impl<A> FinalizedLeaves<A> {
+    /// Subscriptions to the leaves in `archive`, woken up whenever `archived` changes.
+    #[must_use]
+    pub fn new(archive: Arc<A>, archived: watch::Receiver<Option<u64>>) -> Self {
+        Self { archive, archived }
+    }
+
+    /// Subscribe to finalized leaves in height order, starting at `cursor`.
+    ///
+    /// Leaves are only read from the archive when the stream is polled. The stream ends when the
+    /// archiver stops, and fails if the cursor is not a decided view or the archive has a gap.
+    pub fn subscribe<TYPES: NodeType>(
+        &self,
+        cursor: LeafCursor<TYPES>,
+    ) -> BoxStream<'static, Result<Leaf2<TYPES>>>
+    where
+        A: ArchiveStorage<TYPES>,
+    {
+        let subscription = Subscription {
+            archive: Arc::clone(&self.archive),
+            archived: self.archived.clone(),
+            cursor,
+            next: None,
+            batch: VecDeque::new(),
+        };
+        stream::try_unfold(subscription, |mut subscription| async move {
+            match subscription.next_leaf().await? {
+                Some(leaf) => Ok(Some((leaf, subscription))),
+                None => Ok(None),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Where a subscription is.
+struct Subscription<TYPES: NodeType, A> {
+    /// The archive leaves are read from
+    archive: Arc<A>,
+    /// Height of the latest leaf written to the archive
+    archived: watch::Receiver<Option<u64>>,
+    /// Where the subscription started
+    cursor: LeafCursor<TYPES>,
+    /// Height of the next leaf to read from the archive, once the cursor is resolved
+    next: Option<u64>,
+    /// Leaves read but not delivered yet, in height order
+    batch: VecDeque<Leaf2<TYPES>>,
+}
+
+impl<TYPES: NodeType, A: ArchiveStorage<TYPES>> Subscription<TYPES, A> {
+    /// The next leaf, waiting for it to be archived if needed. `None` once the archiver stopped.
+    async fn next_leaf(&mut self) -> Result<Option<Leaf2<TYPES>>> {
+        loop {
+            if let Some(leaf) = self.batch.pop_front() {
+                return Ok(Some(leaf));
+            }
+
+            let next = match self.next {
+                Some(next) => next,
+                None => self.resolve_cursor().await?,
+            };
+            self.next = Some(next);
+
+            // Mark the current height as seen before reading, so a leaf archived in between
+            // still wakes us up
+            self.archived.mark_unchanged();
+            let latest = self.archive.latest_height().await?;
+            if let Some(latest) = latest.filter(|latest| *latest >= next) {
+                self.read_batch(next, latest).await?;
+                continue;
+            }
+
+            if self.archived.changed().await.is_err() {
+                return Ok(None);
+            }
+        }
+    }
+
+    /// The height of the first leaf to deliver.
+    async fn resolve_cursor(&self) -> Result<u64> {
+        match &self.cursor {
+            LeafCursor::Genesis => Ok(0),
+            LeafCursor::Latest => Ok(self
+                .archive
+                .latest_height()
+                .await?
+                .map_or(0, |latest| latest + 1)),
+            LeafCursor::After(view) => {
+                let leaf = self
+                    .archive
+                    .leaf_by_view(*view)
+                    .await?
+                    .with_context(|| format!("No leaf was decided in view {view:?}"))?;
+                Ok(leaf.height() + 1)
+            }
+        }
+    }
+
+    /// Read the leaves from height `next` up to `latest` into the batch, a batch at a time.
+    async fn read_batch(&mut self, next: u64, latest: u64) -> Result<()> {
+        let end = latest.saturating_add(1).min(next + SUBSCRIPTION_BATCH);
+        let leaves = self.archive.leaves(next..end).await?;
+        let mut expected = next;
+        for leaf in leaves {
+            if leaf.height() != expected {
+                break;
+            }
+            self.batch.push_back(leaf);
+            expected += 1;
+        }
+        if expected == next {
+            bail!("The archive has no leaf at height {next}");
+        }
+        self.next = Some(expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,417 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Consensus-level health reporting.
+//!
+//! A process can be up while consensus is stuck. The [`HealthMonitor`] follows the event stream
+//! and reports whether the node is still deciding blocks, keeping up with the rest of the network,
+//! writing to storage in reasonable time and connected to enough peers to reach a quorum.
+//!
+//! [`network_status`] gives a per-peer view of the network: connection, stake, when we last heard
+//! from each peer, round-trip times and failure counts.
+//!
+//! With the `query-api` feature, [`define_api`] serves the report over HTTP, as the module's
+//! health check, alongside the network status.
+
+use std::{
+    collections::VecDeque,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_lock::RwLock;
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    consensus::Consensus,
+    traits::{
+        election::Membership,
+        network::{ConnectedNetwork, PeerStatus},
+        node_implementation::NodeType,
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+};
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Thresholds at which a node is reported as degraded or unhealthy.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthThresholds {
+    /// Window over which decides are counted
+    pub window: Duration,
+    /// Fewer decides than this in the window is degraded; none at all is unhealthy
+    pub min_decides: usize,
+    /// Being this many views behind the network is degraded
+    pub degraded_views_behind: u64,
+    /// Being this many views behind the network is unhealthy
+    pub unhealthy_views_behind: u64,
+    /// Storage writes slower than this are degraded
+    pub max_storage_write_latency: Duration,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        Self {
+            window: Duration::from_secs(60),
+            min_decides: 5,
+            degraded_views_behind: 3,
+            unhealthy_views_behind: 20,
+            max_storage_write_latency: Duration::from_secs(1),
+        }
+    }
+}
+
+/// Overall health of a node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum HealthStatus {
+    /// Everything is within thresholds
+    Healthy,
+    /// The node is making progress, but slowly or with warnings
+    Degraded,
+    /// The node is not taking part in consensus
+    Unhealthy,
+}
+
+/// A snapshot of a node's consensus health.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// Overall status, the worst of the individual checks
+    pub status: HealthStatus,
+    /// Why the node is not healthy, one entry per failed check
+    pub reasons: Vec<String>,
+    /// Number of decides in the last [`HealthThresholds::window`]
+    pub decides_in_window: usize,
+    /// The view this node is in
+    pub current_view: u64,
+    /// Highest view seen in a proposal from the network
+    pub network_view: u64,
+    /// How long our latest write to storage took
+    pub storage_write_latency: Option<Duration>,
+    /// Number of peers we are connected to, if the network can tell
+    pub connected_peers: Option<usize>,
+    /// Whether we are connected to enough peers to form a quorum, if the network can tell
+    pub quorum_reachable: Option<bool>,
+}
+
+/// The network status of a peer, with its stake.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct PeerNetworkStatus<K: SignatureKey> {
+    /// What the network knows about the peer
+    #[serde(flatten)]
+    pub status: PeerStatus<K>,
+    /// Stake of the peer in the current epoch, if it is staked
+    pub stake: Option<U256>,
+}
+
+/// A snapshot of a node's connections to its peers.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct NetworkStatus<K: SignatureKey> {
+    /// Every peer the network has dealt with
+    pub peers: Vec<PeerNetworkStatus<K>>,
+    /// Staked nodes the network has never dealt with, or cannot identify
+    pub unknown_staked_nodes: Vec<K>,
+    /// Stake of the peers we are connected to, including our own
+    pub connected_stake: U256,
+    /// Total stake in the current epoch
+    pub total_stake: U256,
+}
+
+/// Get the status of the connections of `network` to its peers, with their stake in `epoch`.
+///
+/// Returns `None` if the network does not track its peers.
+pub async fn network_status<TYPES: NodeType, NET: ConnectedNetwork<TYPES::SignatureKey>>(
+    network: &NET,
+    memberships: &TYPES::Membership,
+    public_key: &TYPES::SignatureKey,
+    epoch: TYPES::Epoch,
+) -> Option<NetworkStatus<TYPES::SignatureKey>> {
+    let peers = network.peer_status().await?;
+    let stake_of =
+        |key: &TYPES::SignatureKey| memberships.stake(key, epoch).map(|entry| entry.stake());
+
+    let mut connected_stake = stake_of(public_key).unwrap_or_default();
+    let peers: Vec<_> = peers
+        .into_iter()
+        .map(|status| {
+            let stake = status.key.as_ref().and_then(stake_of);
+            if status.connected {
+                connected_stake += stake.unwrap_or_default();
+            }
+            PeerNetworkStatus { status, stake }
+        })
+        .collect();
+
+    let stake_table = memberships.stake_table(epoch);
+    let total_stake = stake_table
+        .iter()
+        .fold(U256::zero(), |total, entry| total + entry.stake());
+    let unknown_staked_nodes = stake_table
+        .iter()
+        .map(|entry| entry.public_key())
+        .filter(|key| {
+            key != public_key
+                && !peers
+                    .iter()
+                    .any(|peer| peer.status.key.as_ref() == Some(key))
+        })
+        .collect();
+
+    Some(NetworkStatus {
+        peers,
+        unknown_staked_nodes,
+        connected_stake,
+        total_stake,
+    })
+}
+
+impl HealthReport {
+    /// Record a failed check.
+    fn fail(&mut self, status: HealthStatus, reason: String) {
+        self.status = self.status.max(status);
+        self.reasons.push(reason);
+    }
+}
+
+/// What the monitor has seen in the event stream.
+#[derive(Debug, Default)]
+struct Observed {
+    /// When recent decides happened, oldest first
+    decides: VecDeque<Instant>,
+    /// Highest view seen in a proposal
+    network_view: u64,
+}
+
+/// Follows the event stream of a node and reports on its health.
+///
+/// The monitor stops following events when it is dropped.
+pub struct HealthMonitor<TYPES: NodeType, NET> {
+    /// What we have seen so far
+    observed: Arc<RwLock<Observed>>,
+    /// Consensus state of the node
+    consensus: Arc<RwLock<Consensus<TYPES>>>,
+    /// The network of the node
+    network: Arc<NET>,
+    /// Stake tables
+    memberships: Arc<TYPES::Membership>,
+    /// Our public key
+    public_key: TYPES::SignatureKey,
+    /// Thresholds for the report
+    thresholds: HealthThresholds,
+    /// The task following events
+    task: JoinHandle<()>,
+}
+
+impl<TYPES: NodeType, NET: ConnectedNetwork<TYPES::SignatureKey>> HealthMonitor<TYPES, NET> {
+    /// Start following `events`.
+    pub fn spawn(
+        thresholds: HealthThresholds,
+        consensus: Arc<RwLock<Consensus<TYPES>>>,
+        network: Arc<NET>,
+        memberships: Arc<TYPES::Membership>,
+        public_key: TYPES::SignatureKey,
+        events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let observed = Arc::new(RwLock::new(Observed::default()));
+        let task = tokio::spawn(observe(Arc::clone(&observed), thresholds.window, events));
+
+        Self {
+            observed,
+            consensus,
+            network,
+            memberships,
+            public_key,
+            thresholds,
+            task,
+        }
+    }
+
+    /// Get the status of the node's connections to its peers, if the network tracks them.
+    pub async fn network_status(&self) -> Option<NetworkStatus<TYPES::SignatureKey>> {
+        let epoch = self.consensus.read().await.cur_epoch();
+        network_status::<TYPES, NET>(&self.network, &self.memberships, &self.public_key, epoch)
+            .await
+    }
+
+    /// Report the current health of the node.
+    pub async fn report(&self) -> HealthReport {
+        let (current_view, epoch, storage_write_latency) = {
+            let consensus = self.consensus.read().await;
+            (
+                *consensus.cur_view(),
+                consensus.cur_epoch(),
+                consensus.storage_write_latency(),
+            )
+        };
+        let (decides_in_window, network_view) = {
+            let mut observed = self.observed.write().await;
+            prune(&mut observed.decides, self.thresholds.window);
+            (
+                observed.decides.len(),
+                observed.network_view.max(current_view),
+            )
+        };
+        let connected_peers = self.network.connected_peers().await;
+        let total_nodes = self.memberships.total_nodes(epoch);
+        let quorum_reachable = connected_peers.map(|peers| (peers + 1) * 3 > total_nodes * 2);
+
+        let mut report = HealthReport {
+            status: HealthStatus::Healthy,
+            reasons: vec![],
+            decides_in_window,
+            current_view,
+            network_view,
+            storage_write_latency,
+            connected_peers,
+            quorum_reachable,
+        };
+
+        let window = self.thresholds.window;
+        if decides_in_window == 0 {
+            report.fail(
+                HealthStatus::Unhealthy,
+                format!("No decides in the last {window:?}"),
+            );
+        } else if decides_in_window < self.thresholds.min_decides {
+            report.fail(
+                HealthStatus::Degraded,
+                format!("Only {decides_in_window} decides in the last {window:?}"),
+            );
+        }
+
+        let behind = network_view - current_view;
+        if behind >= self.thresholds.unhealthy_views_behind {
+            report.fail(
+                HealthStatus::Unhealthy,
+                format!("{behind} views behind the network"),
+            );
+        } else if behind >= self.thresholds.degraded_views_behind {
+            report.fail(
+                HealthStatus::Degraded,
+                format!("{behind} views behind the network"),
+            );
+        }
+
+        if let Some(latency) = storage_write_latency {
+            if latency > self.thresholds.max_storage_write_latency {
+                report.fail(
+                    HealthStatus::Degraded,
+                    format!("Storage writes take {latency:?}"),
+                );
+            }
+        }
+
+        if quorum_reachable == Some(false) {
+            report.fail(
+                HealthStatus::Unhealthy,
+                format!(
+                    "Connected to {} of {total_nodes} nodes, not enough for a quorum",
+                    connected_peers.unwrap_or_default()
+                ),
+            );
+        }
+
+        report
+    }
+}
+
+impl<TYPES: NodeType, NET> Drop for HealthMonitor<TYPES, NET> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Forget decides older than `window`.
+fn prune(decides: &mut VecDeque<Instant>, window: Duration) {
+    while decides
+        .front()
+        .is_some_and(|decided| decided.elapsed() > window)
+    {
+        decides.pop_front();
+    }
+}
+
+/// Record decides and proposal views from `events`.
+async fn observe<TYPES: NodeType>(
+    observed: Arc<RwLock<Observed>>,
+    window: Duration,
+    mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin,
+) {
+    while let Some(event) = events.next().await {
+        match event.event {
+            EventType::Decide { .. } => {
+                let mut observed = observed.write().await;
+                observed.decides.push_back(Instant::now());
+                prune(&mut observed.decides, window);
+            }
+            EventType::QuorumProposal { proposal, .. } => {
+                let mut observed = observed.write().await;
+                observed.network_view = observed.network_view.max(*proposal.data.view_number);
+            }
+            EventType::DaProposal { proposal, .. } => {
+                let mut observed = observed.write().await;
+                observed.network_view = observed.network_view.max(*proposal.data.view_number);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(feature = "query-api")]
+impl tide_disco::healthcheck::HealthCheck for HealthReport {
+    fn status(&self) -> tide_disco::StatusCode {
+        match self.status {
+            HealthStatus::Healthy | HealthStatus::Degraded => tide_disco::StatusCode::OK,
+            HealthStatus::Unhealthy => tide_disco::StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
+/// Define the health API, serving the report of the [`HealthMonitor`] in `State`.
+///
+/// # Errors
+/// Fails if the API specification is invalid.
+#[cfg(feature = "query-api")]
+pub fn define_api<State, TYPES, NET>() -> Result<
+    tide_disco::Api<State, crate::query_api::Error, crate::query_api::Version>,
+    tide_disco::api::ApiError,
+>
+where
+    TYPES: NodeType,
+    NET: ConnectedNetwork<TYPES::SignatureKey>,
+    State: 'static + Send + Sync + tide_disco::method::ReadState<State = HealthMonitor<TYPES, NET>>,
+{
+    use futures::FutureExt;
+
+    let toml: toml::Value = toml::from_str(include_str!("../api/health.toml")).map_err(|err| {
+        tide_disco::api::ApiError::CannotReadToml {
+            reason: err.to_string(),
+        }
+    })?;
+
+    let mut api = tide_disco::Api::new(toml)?;
+    api.with_version("0.1.0".parse().unwrap())
+        .with_health_check(|state: &State| {
+            async move { state.read(|monitor| monitor.report().boxed()).await }.boxed()
+        })
+        .get("report", |_req, monitor| {
+            async move { Ok(monitor.report().await) }.boxed()
+        })?
+        .get("network", |_req, monitor| {
+            async move {
+                monitor
+                    .network_status()
+                    .await
+                    .ok_or_else(|| crate::query_api::Error::NotFound("Peer status".into()))
+            }
+            .boxed()
+        })?;
+
+    Ok(api)
+}
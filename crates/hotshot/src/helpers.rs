@@ -19,6 +19,13 @@ pub fn initialize_logging() {
         Err(_) => FmtSpan::NONE,
     };
 
+    // Also report tasks to tokio-console, if asked to
+    #[cfg(feature = "tokio-console")]
+    if std::env::var("TOKIO_CONSOLE_ENABLED") == Ok("true".to_string()) {
+        initialize_console_logging(span_event_filter);
+        return;
+    }
+
     // Conditionally initialize in `json` mode
     if std::env::var("RUST_LOG_FORMAT") == Ok("json".to_string()) {
         let _ = tracing_subscriber::fmt()
@@ -33,3 +40,25 @@ pub fn initialize_logging() {
             .try_init();
     };
 }
+
+/// Initializes logging, and a `console-subscriber` layer which serves task data to tokio-console.
+///
+/// The layer has its own filter, so tokio-console sees every task whatever `RUST_LOG` says.
+#[cfg(feature = "tokio-console")]
+fn initialize_console_logging(span_event_filter: FmtSpan) {
+    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, Layer};
+
+    let fmt_layer = if std::env::var("RUST_LOG_FORMAT") == Ok("json".to_string()) {
+        fmt::layer()
+            .with_span_events(span_event_filter)
+            .json()
+            .boxed()
+    } else {
+        fmt::layer().with_span_events(span_event_filter).boxed()
+    };
+
+    let _ = tracing_subscriber::registry()
+        .with(console_subscriber::spawn())
+        .with(fmt_layer.with_filter(EnvFilter::from_default_env()))
+        .try_init();
+}
@@ -27,6 +27,66 @@ pub mod types;
 
 pub mod tasks;
 
+/// Driver for ABCI-style applications
+pub mod application;
+
+/// Storage and serving of the full chain by archival nodes
+pub mod archive;
+
+/// Append-only audit log of proposals, votes and decides
+pub mod audit_log;
+
+/// Read-through LRU cache in front of storage backends
+pub mod cached_storage;
+
+/// Export of decided blocks as CAR archives for IPFS and Filecoin
+pub mod car_export;
+
+/// Periodic checkpointing of the decided chain to an external sink
+pub mod checkpoint;
+
+/// Generation of the per-node configuration files of a deployment
+pub mod deployment;
+
+/// WebSocket feed of consensus progress for dashboards
+#[cfg(feature = "query-api")]
+pub mod feed;
+
+/// Subscriptions to finalized leaves, with backpressure
+pub mod finalized_leaves;
+
+/// Consensus-level health reporting
+pub mod health;
+
+/// Relay of the broadcast stream to observer clients
+pub mod relay;
+
+/// Disk usage metrics and quota enforcement for storage backends
+pub mod metered_storage;
+
+/// Relay of decided QCs to an L1 endpoint
+pub mod qc_relay;
+
+/// Signing with a consensus key held by a remote signing service
+pub mod remote_signer;
+
+/// Hot and cold storage tiers behind one storage backend
+pub mod tiered_storage;
+
+/// Lifecycle tracking of submitted transactions
+pub mod transaction_status;
+
+/// Stake tables driven by validator operations
+pub mod validator_ops;
+
+/// Archival storage in S3-compatible object stores
+#[cfg(feature = "s3-archive")]
+pub mod s3_archive;
+
+/// HTTP API over the archive, for block explorers
+#[cfg(feature = "query-api")]
+pub mod query_api;
+
 /// Contains helper functions for the crate
 pub mod helpers;
 
@@ -40,14 +100,21 @@ use std::{
 use async_broadcast::{broadcast, InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::join;
-use hotshot_task::task::{ConsensusTaskRegistry, NetworkTaskRegistry};
-use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
+use hotshot_task::{
+    spawn::spawn_named,
+    task::{ConsensusTaskRegistry, NetworkTaskRegistry},
+};
+use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event, workers::WorkerPool};
 // Internal
 /// Reexport error type
 pub use hotshot_types::error::HotShotError;
 use hotshot_types::{
-    consensus::{Consensus, ConsensusMetricsValue, OuterConsensus, View, ViewInner},
+    consensus::{
+        CommitRule, Consensus, ConsensusMetricsValue, OuterConsensus, SavedPayloads, View,
+        ViewInner,
+    },
     constants::{EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE},
     data::{Leaf2, QuorumProposal, QuorumProposal2},
     event::{EventType, LeafInfo},
@@ -58,18 +125,19 @@ use hotshot_types::{
         election::Membership,
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signer::Signer,
         states::ValidatedState,
         storage::Storage,
         EncodeBytes,
     },
     utils::epoch_from_block_number,
-    HotShotConfig,
+    HotShotConfig, NodeRole,
 };
 /// Reexport rand crate
 pub use rand;
-use tokio::{spawn, time::sleep};
+use tokio::time::sleep;
 use tracing::{debug, instrument, trace};
+use vbs::version::StaticVersionType;
 
 // -- Rexports
 // External
@@ -98,8 +166,9 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
     /// The public key of this node
     public_key: TYPES::SignatureKey,
 
-    /// The private key of this node
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs everything we sign with our consensus key, so the private key itself need not be
+    /// held by the node
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Configuration items for this hotshot instance
     pub config: HotShotConfig<TYPES::SignatureKey>,
@@ -152,6 +221,12 @@ pub struct SystemContext<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
 
     /// Marketplace config for this instance of HotShot
     pub marketplace_config: MarketplaceConfig<TYPES, I>,
+
+    /// Threads which validate proposed block headers and apply them to the state
+    pub state_workers: Arc<WorkerPool>,
+
+    /// Threads which check the signatures on votes and proposals
+    pub verification_workers: Arc<WorkerPool>,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     for SystemContext<TYPES, I, V>
@@ -160,7 +235,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
     fn clone(&self) -> Self {
         Self {
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
+            signer: Arc::clone(&self.signer),
             config: self.config.clone(),
             network: Arc::clone(&self.network),
             memberships: Arc::clone(&self.memberships),
@@ -177,6 +252,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Clone
             storage: Arc::clone(&self.storage),
             upgrade_lock: self.upgrade_lock.clone(),
             marketplace_config: self.marketplace_config.clone(),
+            state_workers: Arc::clone(&self.state_workers),
+            verification_workers: Arc::clone(&self.verification_workers),
         }
     }
 }
@@ -195,8 +272,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     /// Panics if storage migration fails.
     #[allow(clippy::too_many_arguments)]
     pub async fn new(
-        public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         nonce: u64,
         config: HotShotConfig<TYPES::SignatureKey>,
         memberships: TYPES::Membership,
@@ -220,12 +296,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             }
         }
 
-        let internal_chan = broadcast(EVENT_CHANNEL_SIZE);
-        let external_chan = broadcast(EXTERNAL_EVENT_CHANNEL_SIZE);
+        let internal_chan = broadcast(config.channels.internal_capacity);
+        let external_chan = broadcast(config.channels.external_capacity);
 
         Self::new_from_channels(
-            public_key,
-            private_key,
+            signer,
             nonce,
             config,
             memberships,
@@ -246,10 +321,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ///
     /// Use this function if you want to use some preexisting channels and to spin up the tasks
     /// and start consensus manually.  Mostly useful for tests
+    ///
+    /// # Panics
+    ///
+    /// Panics if the worker threads cannot be started, or if the configuration asks for the
+    /// two-chain rule before the epochs upgrade is in effect.
     #[allow(clippy::too_many_arguments, clippy::type_complexity)]
     pub fn new_from_channels(
-        public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         nonce: u64,
         config: HotShotConfig<TYPES::SignatureKey>,
         memberships: TYPES::Membership,
@@ -266,6 +345,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ) -> Arc<Self> {
         debug!("Creating a new hotshot");
 
+        // Proposals from before the epochs upgrade cannot prove that they extend the highest QC,
+        // so the two-chain rule would decide on them without that check.
+        assert!(
+            config.commit_rule != CommitRule::TwoChain || V::Base::VERSION >= V::Epochs::VERSION,
+            "The two-chain commit rule needs the epochs upgrade to be in effect from genesis"
+        );
+
+        let public_key = signer.public_key().clone();
+
         let consensus_metrics = Arc::new(metrics);
         let anchored_leaf = initializer.inner;
         let instance_state = initializer.instance_state;
@@ -274,7 +362,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         let (mut external_tx, mut external_rx) = external_channel;
 
         let upgrade_lock =
-            UpgradeLock::<TYPES, V>::from_certificate(&initializer.decided_upgrade_certificate);
+            UpgradeLock::<TYPES, V>::from_certificate(&initializer.decided_upgrade_certificate)
+                .with_chain_id(config.chain_id);
 
         // Allow overflow on the external channel, otherwise sending to it may block.
         external_rx.set_overflow(true);
@@ -296,6 +385,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             anchored_leaf.height(),
             config.epoch_height,
         ));
+        if config.role == NodeRole::Archival
+            && (memberships.has_stake(&public_key, epoch)
+                || memberships.has_da_stake(&public_key, epoch))
+        {
+            tracing::warn!(
+                "Archival node has stake; it will vote like a validator. Archival nodes should be \
+                 left out of the stake table."
+            );
+        }
         // Insert the validated state to state map.
         let mut validated_state_map = BTreeMap::default();
         validated_state_map.insert(
@@ -315,10 +413,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
 
         let mut saved_leaves = HashMap::new();
         let mut saved_payloads = BTreeMap::new();
-        saved_leaves.insert(anchored_leaf.commit(), anchored_leaf.clone());
+        saved_leaves.insert(anchored_leaf.commit(), Arc::new(anchored_leaf.clone()));
 
         for leaf in initializer.undecided_leaves {
-            saved_leaves.insert(leaf.commit(), leaf.clone());
+            saved_leaves.insert(leaf.commit(), Arc::new(leaf));
         }
         if let Some(payload) = anchored_leaf.block_payload() {
             let encoded_txns = payload.encode();
@@ -342,7 +440,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             initializer.actioned_view,
             initializer.saved_proposals,
             saved_leaves,
-            saved_payloads,
+            SavedPayloads::new(saved_payloads),
             initializer.high_qc,
             Arc::clone(&consensus_metrics),
             config.epoch_height,
@@ -350,6 +448,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
 
         let consensus = Arc::new(RwLock::new(consensus));
 
+        let state_workers = WorkerPool::new("state", config.workers.state_threads)
+            .expect("Failed to start the state worker threads");
+        let verification_workers =
+            WorkerPool::new("verification", config.workers.verification_threads)
+                .expect("Failed to start the verification worker threads");
+
         // This makes it so we won't block on broadcasting if there is not a receiver
         // Our own copy of the receiver is inactive so it doesn't count.
         external_tx.set_await_active(false);
@@ -359,7 +463,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             consensus: OuterConsensus::new(consensus),
             instance_state: Arc::new(instance_state),
             public_key,
-            private_key,
+            signer,
             config,
             start_view: initializer.start_view,
             start_epoch: initializer.start_epoch,
@@ -373,6 +477,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             storage: Arc::new(RwLock::new(storage)),
             upgrade_lock,
             marketplace_config,
+            state_workers: Arc::new(state_workers),
+            verification_workers: Arc::new(verification_workers),
         });
 
         inner
@@ -413,7 +519,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
 
         // Spawn a task that will sleep for the next view timeout and then send a timeout event
         // if not cancelled
-        spawn({
+        spawn_named("initial view timeout", {
             async move {
                 sleep(Duration::from_millis(next_view_timeout)).await;
                 broadcast_event(
@@ -491,6 +597,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     ) -> Result<(), HotShotError<TYPES>> {
         trace!("Adding transaction to our own queue");
 
+        // With gossip enabled, the mempool task relays the transaction to all nodes.
+        if self.config.mempool_gossip.enabled {
+            broadcast_event(
+                Arc::new(HotShotEvent::TransactionsRecv(vec![transaction])),
+                &self.internal_event_stream.0,
+            )
+            .await;
+            return Ok(());
+        }
+
         let api = self.clone();
         let view_number = api.consensus.read().await.cur_view();
 
@@ -502,11 +618,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
             kind: MessageKind::from(message_kind),
         };
 
-        let serialized_message = self.upgrade_lock.serialize(&message).await.map_err(|err| {
-            HotShotError::FailedToSerialize(format!("failed to serialize transaction: {err}"))
-        })?;
+        let serialized_message = self
+            .upgrade_lock
+            .serialize_signed(&message, self.signer.as_ref())
+            .await
+            .map(Bytes::from)
+            .map_err(|err| {
+                HotShotError::FailedToSerialize(format!("failed to serialize transaction: {err}"))
+            })?;
 
-        spawn(async move {
+        spawn_named("publish transaction", async move {
             join! {
                 // TODO We should have a function that can return a network error if there is one
                 // but first we'd need to ensure our network implementations can support that
@@ -599,8 +720,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
     /// Can throw an error if `Self::new` fails.
     #[allow(clippy::too_many_arguments)]
     pub async fn init(
-        public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         node_id: u64,
         config: HotShotConfig<TYPES::SignatureKey>,
         memberships: TYPES::Membership,
@@ -618,8 +738,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> SystemContext<T
         HotShotError<TYPES>,
     > {
         let hotshot = Self::new(
-            public_key,
-            private_key,
+            signer,
             node_id,
             config,
             memberships,
@@ -716,7 +835,7 @@ where
         let (network_task_sender, mut receiver_from_network): Channel<HotShotEvent<TYPES>> =
             broadcast(EVENT_CHANNEL_SIZE);
 
-        let _recv_loop_handle = spawn(async move {
+        let _recv_loop_handle = spawn_named("twins network receive", async move {
             loop {
                 let msg = match select(left_receiver.recv(), right_receiver.recv()).await {
                     Either::Left(msg) => Either::Left(msg.0.unwrap().as_ref().clone()),
@@ -732,7 +851,7 @@ where
             }
         });
 
-        let _send_loop_handle = spawn(async move {
+        let _send_loop_handle = spawn_named("twins network send", async move {
             loop {
                 if let Ok(msg) = receiver_from_network.recv().await {
                     let mut state = send_state.write().await;
@@ -762,8 +881,7 @@ where
     /// For a list of which tasks are being spawned, see this module's documentation.
     async fn spawn_twin_handles(
         &'static mut self,
-        public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         nonce: u64,
         config: HotShotConfig<TYPES::SignatureKey>,
         memberships: TYPES::Membership,
@@ -778,8 +896,7 @@ where
     ) {
         let epoch_height = config.epoch_height;
         let left_system_context = SystemContext::new(
-            public_key.clone(),
-            private_key.clone(),
+            Arc::clone(&signer),
             nonce,
             config.clone(),
             memberships.clone(),
@@ -791,8 +908,7 @@ where
         )
         .await;
         let right_system_context = SystemContext::new(
-            public_key,
-            private_key,
+            signer,
             nonce,
             config,
             memberships,
@@ -964,8 +1080,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusApi<TY
         &self.hotshot.public_key
     }
 
-    fn private_key(&self) -> &<TYPES::SignatureKey as SignatureKey>::PrivateKey {
-        &self.hotshot.private_key
+    fn signer(&self) -> Arc<dyn Signer<TYPES::SignatureKey>> {
+        Arc::clone(&self.hotshot.signer)
     }
 }
 
@@ -0,0 +1,420 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Disk usage accounting and quota enforcement for any [`Storage`] backend.
+//!
+//! [`MeteredStorage`] wraps a backend, counts the bytes written for each view and exports them as
+//! metrics. Once the storage grows past its [`StorageQuota`], it prunes the views which are
+//! already decided. If that is not enough, it refuses to store VID shares and DA proposals, which a
+//! node can do without, while still accepting the writes it needs to stay safe across restarts,
+//! such as its last actions and high QC. This keeps a node running, if degraded, instead of
+//! letting it crash once the disk is full.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::{bail, Result};
+use async_lock::RwLock;
+use async_trait::async_trait;
+use bincode::Options;
+use hotshot_types::{
+    consensus::{CommitmentMap, LeafMap},
+    data::{
+        DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
+        VidDisperseShare2,
+    },
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    traits::{
+        metrics::{Counter, Gauge, Histogram, Metrics},
+        node_implementation::{ConsensusTime, NodeType},
+        storage::{Storage, StorageKey, StoredValue, ViewUndo, ViewWrites},
+    },
+    utils::{bincode_opts, View},
+    vid::VidSchemeType,
+};
+use jf_vid::VidScheme;
+use serde::{Deserialize, Serialize};
+
+/// How much a storage backend may hold.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageQuota {
+    /// Bytes the backend may hold before decided views are pruned and non-essential writes are
+    /// refused, or `None` for no limit
+    pub max_bytes: Option<u64>,
+}
+
+/// Storage metrics of a backend.
+#[derive(Debug)]
+pub struct StorageMetricsValue {
+    /// Bytes written to the backend
+    pub bytes_written: Box<dyn Counter>,
+    /// Bytes held by the backend, as reported by the backend or estimated from the bytes written
+    pub size: Box<dyn Gauge>,
+    /// Bytes written for each view, recorded once the view is decided
+    pub bytes_per_view: Box<dyn Histogram>,
+    /// Number of times decided views were pruned to stay under the quota
+    pub prunes: Box<dyn Counter>,
+    /// Number of writes refused because the backend was over its quota
+    pub refused_writes: Box<dyn Counter>,
+}
+
+impl StorageMetricsValue {
+    /// Create the metrics of the backend called `backend`.
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics, backend: &str) -> Self {
+        let metrics = metrics.subgroup(format!("storage_{backend}"));
+        Self {
+            bytes_written: metrics.create_counter(String::from("bytes_written"), None),
+            size: metrics.create_gauge(String::from("size_bytes"), None),
+            bytes_per_view: metrics.create_histogram(String::from("bytes_per_view"), None),
+            prunes: metrics.create_counter(String::from("prunes"), None),
+            refused_writes: metrics.create_counter(String::from("refused_writes"), None),
+        }
+    }
+}
+
+/// What we know about the bytes held by the backend.
+#[derive(Debug)]
+struct Usage<TYPES: NodeType> {
+    /// Bytes written for each view which may not be decided yet
+    bytes_per_view: BTreeMap<TYPES::View, u64>,
+    /// Estimate of the bytes held, from the bytes written and pruned
+    estimated_size: u64,
+    /// Views before this one are decided and can be pruned
+    decided_before: TYPES::View,
+    /// Views before this one have been pruned
+    pruned_before: TYPES::View,
+}
+
+/// A [`Storage`] backend which accounts for the bytes written to it and keeps under a quota.
+#[derive(Clone, Debug)]
+pub struct MeteredStorage<TYPES: NodeType, S> {
+    /// The wrapped backend
+    inner: S,
+    /// How much the backend may hold
+    quota: StorageQuota,
+    /// Metrics of the backend
+    metrics: Arc<StorageMetricsValue>,
+    /// Bytes held by the backend
+    usage: Arc<RwLock<Usage<TYPES>>>,
+}
+
+impl<TYPES: NodeType, S: Storage<TYPES>> MeteredStorage<TYPES, S> {
+    /// Wrap `inner`, exporting its metrics under the name `backend`.
+    #[must_use]
+    pub fn new(inner: S, quota: StorageQuota, metrics: &dyn Metrics, backend: &str) -> Self {
+        Self {
+            inner,
+            quota,
+            metrics: Arc::new(StorageMetricsValue::new(metrics, backend)),
+            usage: Arc::new(RwLock::new(Usage {
+                bytes_per_view: BTreeMap::new(),
+                estimated_size: 0,
+                decided_before: TYPES::View::genesis(),
+                pruned_before: TYPES::View::genesis(),
+            })),
+        }
+    }
+
+    /// The wrapped backend.
+    #[must_use]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Bytes held by the backend, as reported by the backend if it can tell.
+    async fn size(&self) -> u64 {
+        match self.inner.storage_size().await {
+            Ok(Some(size)) => size,
+            Ok(None) => self.usage.read().await.estimated_size,
+            Err(e) => {
+                tracing::warn!("Failed to get the storage size: {e:#}");
+                self.usage.read().await.estimated_size
+            }
+        }
+    }
+
+    /// Whether the backend holds more than its quota, after pruning decided views if it does.
+    async fn over_quota(&self) -> bool {
+        let Some(max_bytes) = self.quota.max_bytes else {
+            return false;
+        };
+        if self.size().await < max_bytes {
+            return false;
+        }
+
+        let prune_before = {
+            let usage = self.usage.read().await;
+            (usage.decided_before > usage.pruned_before).then_some(usage.decided_before)
+        };
+        if let Some(view) = prune_before {
+            match self.inner.prune(view).await {
+                Ok(()) => {
+                    self.metrics.prunes.add(1);
+                    let mut usage = self.usage.write().await;
+                    let pruned: u64 = usage.bytes_per_view.range(..view).map(|(_, b)| b).sum();
+                    usage.bytes_per_view = usage.bytes_per_view.split_off(&view);
+                    usage.estimated_size = usage.estimated_size.saturating_sub(pruned);
+                    usage.pruned_before = view;
+                }
+                Err(e) => tracing::error!("Failed to prune storage before view {view:?}: {e:#}"),
+            }
+        }
+
+        let size = self.size().await;
+        self.metrics
+            .size
+            .set(usize::try_from(size).unwrap_or(usize::MAX));
+        size >= max_bytes
+    }
+
+    /// Refuse a non-essential write if the backend is over its quota.
+    async fn check_quota(&self) -> Result<()> {
+        if self.over_quota().await {
+            self.metrics.refused_writes.add(1);
+            bail!("Storage is over its quota, refusing a non-essential write");
+        }
+        Ok(())
+    }
+
+    /// Warn about an essential write to a backend over its quota.
+    async fn warn_if_over_quota(&self) {
+        if self.over_quota().await {
+            tracing::error!("Storage is over its quota, but this write is needed for safety");
+        }
+    }
+
+    /// Account for `value` being written for `view`.
+    async fn record_write(&self, view: TYPES::View, value: &(impl Serialize + Sync)) {
+        let bytes = bincode_opts().serialized_size(value).unwrap_or(0);
+        self.record_bytes(view, bytes).await;
+    }
+
+    /// Account for `bytes` being written for `view`.
+    async fn record_bytes(&self, view: TYPES::View, bytes: u64) {
+        self.metrics
+            .bytes_written
+            .add(usize::try_from(bytes).unwrap_or(usize::MAX));
+
+        {
+            let mut usage = self.usage.write().await;
+            *usage.bytes_per_view.entry(view).or_default() += bytes;
+            usage.estimated_size += bytes;
+        }
+        let size = self.size().await;
+        self.metrics
+            .size
+            .set(usize::try_from(size).unwrap_or(usize::MAX));
+    }
+
+    /// Record that the views before `view` are decided.
+    async fn record_decided(&self, view: TYPES::View) {
+        let mut usage = self.usage.write().await;
+        if view <= usage.decided_before {
+            return;
+        }
+        for (_, bytes) in usage.bytes_per_view.range(usage.decided_before..view) {
+            #[allow(clippy::cast_precision_loss)]
+            self.metrics.bytes_per_view.add_point(*bytes as f64);
+        }
+        usage.decided_before = view;
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, S: Storage<TYPES>> Storage<TYPES> for MeteredStorage<TYPES, S> {
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        self.check_quota().await?;
+        self.inner.append_vid(proposal).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn append_vid2(
+        &self,
+        proposal: &Proposal<TYPES, VidDisperseShare2<TYPES>>,
+    ) -> Result<()> {
+        self.check_quota().await?;
+        self.inner.append_vid2(proposal).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        self.check_quota().await?;
+        self.inner.append_da(proposal, vid_commit).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn append_da2(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        self.check_quota().await?;
+        self.inner.append_da2(proposal, vid_commit).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        self.warn_if_over_quota().await;
+        self.inner.append_proposal(proposal).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.warn_if_over_quota().await;
+        self.inner.append_proposal2(proposal).await?;
+        self.record_write(proposal.data.view_number, proposal).await;
+        Ok(())
+    }
+
+    async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()> {
+        self.inner.record_action(view, action).await
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        self.inner.update_high_qc(high_qc).await
+    }
+
+    async fn update_high_qc2(&self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        self.inner.update_high_qc2(high_qc).await
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leaves: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        self.inner.update_undecided_state(leaves, state).await
+    }
+
+    async fn update_undecided_state2(
+        &self,
+        leaves: LeafMap<TYPES>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        // The oldest undecided view is the last decided one, so everything before it is decided
+        let oldest_undecided = state.keys().next().copied();
+        // The undecided state replaces the previous one, so it does not add up over views
+        let bytes = bincode_opts().serialized_size(&leaves).unwrap_or(0);
+
+        self.inner.update_undecided_state2(leaves, state).await?;
+
+        self.metrics
+            .bytes_written
+            .add(usize::try_from(bytes).unwrap_or(usize::MAX));
+        if let Some(view) = oldest_undecided {
+            self.record_decided(view).await;
+        }
+        Ok(())
+    }
+
+    async fn write_view(&self, writes: ViewWrites<TYPES>) -> Result<()> {
+        // A VID share is the only non-essential write in a batch, and we cannot vote without it
+        if writes.vid_share.is_some() {
+            self.check_quota().await?;
+        } else {
+            self.warn_if_over_quota().await;
+        }
+
+        let proposal_write = writes.proposal.as_ref().map(|proposal| {
+            let bytes = bincode_opts().serialized_size(proposal).unwrap_or(0);
+            (proposal.data.view_number, bytes)
+        });
+        let vid_share_write = writes.vid_share.as_ref().map(|vid_share| {
+            let bytes = bincode_opts().serialized_size(vid_share).unwrap_or(0);
+            (vid_share.data.view_number, bytes)
+        });
+        // The undecided state replaces the previous one, so it does not add up over views
+        let (oldest_undecided, undecided_bytes) =
+            writes
+                .undecided_state
+                .as_ref()
+                .map_or((None, 0), |(leaves, state)| {
+                    (
+                        state.keys().next().copied(),
+                        bincode_opts().serialized_size(leaves).unwrap_or(0),
+                    )
+                });
+
+        self.inner.write_view(writes).await?;
+
+        for (view, bytes) in proposal_write.into_iter().chain(vid_share_write) {
+            self.record_bytes(view, bytes).await;
+        }
+        self.metrics
+            .bytes_written
+            .add(usize::try_from(undecided_bytes).unwrap_or(usize::MAX));
+        if let Some(view) = oldest_undecided {
+            self.record_decided(view).await;
+        }
+        Ok(())
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<()> {
+        self.inner
+            .update_decided_upgrade_certificate(decided_upgrade_certificate)
+            .await
+    }
+
+    async fn migrate_consensus(
+        &self,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_proposal: fn(
+            Proposal<TYPES, QuorumProposal<TYPES>>,
+        ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.inner
+            .migrate_consensus(convert_leaf, convert_proposal)
+            .await
+    }
+
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        Ok(Some(self.size().await))
+    }
+
+    async fn prune(&self, view: TYPES::View) -> Result<()> {
+        self.inner.prune(view).await
+    }
+
+    async fn revert_to(&self, view: TYPES::View) -> Result<Vec<ViewUndo<TYPES>>> {
+        let reverted = self.inner.revert_to(view).await?;
+
+        // Reverting deletes the bulk of what the views wrote, their proposals and VID shares
+        let mut usage = self.usage.write().await;
+        for undo in &reverted {
+            let removed = usage.bytes_per_view.remove(&undo.view).unwrap_or(0);
+            usage.estimated_size = usage.estimated_size.saturating_sub(removed);
+        }
+        Ok(reverted)
+    }
+
+    async fn load(&self, key: &StorageKey<TYPES>) -> Result<Option<StoredValue<TYPES>>> {
+        self.inner.load(key).await
+    }
+
+    async fn load_before(&self, view: TYPES::View) -> Result<Vec<StoredValue<TYPES>>> {
+        self.inner.load_before(view).await
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Service relaying compact QCs of decided leaves to an L1 endpoint.
+//!
+//! Decide events are folded into the newest decided QC, which is posted to the endpoint once per
+//! configured cadence. Intermediate QCs are skipped: each QC attests to finality of the whole
+//! chain below it.
+
+use std::{sync::Arc, time::Duration};
+
+use async_lock::RwLock;
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    data::Leaf2,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        metrics::{Counter, Gauge, Histogram, Metrics},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+};
+use serde::{Deserialize, Serialize};
+use surf_disco::{error::ClientError, Client};
+use tokio::{task::JoinHandle, time::sleep};
+use url::Url;
+use vbs::version::StaticVersion;
+
+use crate::types::{Event, EventType};
+
+/// API version of the QC relay endpoint
+pub type QcRelayVersion = StaticVersion<0, 1>;
+
+/// Configuration of the QC relay.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QcRelayConfig {
+    /// Base URL of the endpoint. QCs are posted to `{url}/qc`.
+    pub url: Url,
+    /// How often the newest decided QC is posted
+    pub cadence: Duration,
+    /// Delay before the first retry of a failed post. Doubles with every attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between retries
+    pub max_backoff: Duration,
+    /// Number of attempts before a QC is given up on
+    pub max_attempts: u32,
+}
+
+impl QcRelayConfig {
+    /// Default relay settings for the endpoint at `url`.
+    #[must_use]
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            // One Ethereum slot
+            cadence: Duration::from_secs(12),
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: 5,
+        }
+    }
+}
+
+/// The part of a QC the L1 needs to attest to finality of a leaf.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct CompactQc<TYPES: NodeType> {
+    /// View of the QC
+    pub view_number: TYPES::View,
+    /// Epoch of the QC
+    pub epoch: TYPES::Epoch,
+    /// Height of the decided leaf
+    pub block_height: u64,
+    /// Commitment to the decided leaf
+    pub leaf_commit: Commitment<Leaf2<TYPES>>,
+    /// The assembled signature
+    pub signatures: Option<<TYPES::SignatureKey as SignatureKey>::QcType>,
+}
+
+impl<TYPES: NodeType> CompactQc<TYPES> {
+    /// Compact `qc`, which signs `leaf`.
+    #[must_use]
+    pub fn new(leaf: &Leaf2<TYPES>, qc: &QuorumCertificate2<TYPES>) -> Self {
+        debug_assert_eq!(qc.data.leaf_commit, leaf.commit());
+        Self {
+            view_number: qc.view_number,
+            epoch: qc.data.epoch,
+            block_height: leaf.height(),
+            leaf_commit: qc.data.leaf_commit,
+            signatures: qc.signatures.clone(),
+        }
+    }
+}
+
+/// Metrics of the QC relay
+pub struct QcRelayMetrics {
+    /// Number of QCs accepted by the endpoint
+    pub relayed: Box<dyn Counter>,
+    /// Number of failed post attempts
+    pub failed_attempts: Box<dyn Counter>,
+    /// Number of QCs given up on after exhausting all attempts
+    pub dropped: Box<dyn Counter>,
+    /// Block height of the last QC accepted by the endpoint
+    pub last_relayed_height: Box<dyn Gauge>,
+    /// Time taken to relay a QC, including retries
+    pub relay_duration: Box<dyn Histogram>,
+}
+
+impl QcRelayMetrics {
+    /// Create the relay metrics in a `qc_relay` subgroup of `metrics`.
+    #[must_use]
+    pub fn new(metrics: &dyn Metrics) -> Self {
+        let metrics = metrics.subgroup("qc_relay".into());
+        Self {
+            relayed: metrics.create_counter("relayed".into(), None),
+            failed_attempts: metrics.create_counter("failed_attempts".into(), None),
+            dropped: metrics.create_counter("dropped".into(), None),
+            last_relayed_height: metrics.create_gauge("last_relayed_height".into(), None),
+            relay_duration: metrics.create_histogram("relay_duration".into(), Some("s".into())),
+        }
+    }
+}
+
+/// Handle to the running relay. The relay stops when the handle is dropped.
+pub struct QcRelay {
+    /// Task folding decide events into the newest QC
+    watcher: JoinHandle<()>,
+    /// Task posting the newest QC at the configured cadence
+    poster: JoinHandle<()>,
+}
+
+impl QcRelay {
+    /// Spawn the relay, watching `events` for decides.
+    pub fn spawn<TYPES: NodeType>(
+        config: QcRelayConfig,
+        metrics: QcRelayMetrics,
+        mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let newest = Arc::new(RwLock::new(None::<CompactQc<TYPES>>));
+
+        let watcher = tokio::spawn({
+            let newest = Arc::clone(&newest);
+            async move {
+                while let Some(event) = events.next().await {
+                    if let EventType::Decide { leaf_chain, qc, .. } = event.event {
+                        if let Some(leaf_info) = leaf_chain.first() {
+                            *newest.write().await = Some(CompactQc::new(&leaf_info.leaf, &qc));
+                        }
+                    }
+                }
+            }
+        });
+
+        let poster = tokio::spawn(async move {
+            let client = Client::<ClientError, QcRelayVersion>::new(config.url.clone());
+            let mut last_relayed = None;
+            loop {
+                sleep(config.cadence).await;
+
+                let Some(qc) = newest.read().await.clone() else {
+                    continue;
+                };
+                if last_relayed.is_some_and(|height| height >= qc.block_height) {
+                    continue;
+                }
+
+                let start = std::time::Instant::now();
+                if relay(&client, &config, &metrics, &qc).await {
+                    metrics.relayed.add(1);
+                    metrics
+                        .last_relayed_height
+                        .set(usize::try_from(qc.block_height).unwrap_or(usize::MAX));
+                    metrics
+                        .relay_duration
+                        .add_point(start.elapsed().as_secs_f64());
+                    last_relayed = Some(qc.block_height);
+                } else {
+                    tracing::warn!(
+                        "Giving up on relaying QC for block {} after {} attempts",
+                        qc.block_height,
+                        config.max_attempts
+                    );
+                    metrics.dropped.add(1);
+                }
+            }
+        });
+
+        Self { watcher, poster }
+    }
+}
+
+impl Drop for QcRelay {
+    fn drop(&mut self) {
+        self.watcher.abort();
+        self.poster.abort();
+    }
+}
+
+/// Post `qc`, retrying with exponential backoff. Returns whether the endpoint accepted it.
+async fn relay<TYPES: NodeType>(
+    client: &Client<ClientError, QcRelayVersion>,
+    config: &QcRelayConfig,
+    metrics: &QcRelayMetrics,
+    qc: &CompactQc<TYPES>,
+) -> bool {
+    let mut backoff = config.initial_backoff;
+    for attempt in 1..=config.max_attempts {
+        let result = match client.post::<()>("qc").body_json(qc) {
+            Ok(request) => request.send().await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => return true,
+            Err(e) => {
+                tracing::debug!(
+                    "Attempt {attempt} to relay QC for block {} failed: {e}",
+                    qc.block_height
+                );
+                metrics.failed_attempts.add(1);
+            }
+        }
+
+        if attempt < config.max_attempts {
+            sleep(backoff).await;
+            backoff = (backoff * 2).min(config.max_backoff);
+        }
+    }
+    false
+}
@@ -0,0 +1,353 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A read-only HTTP API over an [`ArchiveStorage`], for block explorers.
+//!
+//! The API serves decided leaves, blocks, QCs and transactions, plus the stake table of any epoch,
+//! as JSON. Lists are paginated. The routes are defined in `api/query.toml`; to serve them, register
+//! the module returned by [`define_api`] with a `tide_disco::App` whose state wraps a
+//! [`QueryState`]:
+//!
+//! ```ignore
+//! let state = QueryState::new(archive, Arc::clone(&memberships));
+//! let mut app = App::<_, Error>::with_state(RwLock::new(state));
+//! app.register_module("query", define_api::<_, TYPES, _>(&Options::default())?)?;
+//! app.serve(url, Version::instance()).await
+//! ```
+
+use std::{path::PathBuf, sync::Arc};
+
+use committable::{Commitment, Committable};
+use futures::FutureExt;
+use hotshot_types::{
+    data::Leaf2,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        archive::ArchiveStorage,
+        block_contents::BlockHeader,
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+        BlockPayload,
+    },
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tide_disco::{api::ApiError, method::ReadState, Api, RequestError, StatusCode};
+
+/// Version of the query API
+pub type Version = vbs::version::StaticVersion<0, 1>;
+
+/// Largest page of leaves or blocks returned by a single request
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Options for the query API
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Load the API specification from this file instead of the built-in one
+    pub api_path: Option<PathBuf>,
+}
+
+/// Errors returned by the query API
+#[derive(Clone, Debug, Error, Serialize, Deserialize)]
+pub enum Error {
+    /// The request was malformed
+    #[error("Error processing request: {0}")]
+    Request(#[from] RequestError),
+    /// The requested object is not in the archive
+    #[error("{0} not found")]
+    NotFound(String),
+    /// The archive failed to answer
+    #[error("Error reading from the archive: {0}")]
+    Archive(String),
+    /// Any other error
+    #[error("Custom error {status}: {message}")]
+    Custom {
+        /// The error message
+        message: String,
+        /// The HTTP status to return
+        status: StatusCode,
+    },
+}
+
+impl tide_disco::error::Error for Error {
+    fn catch_all(status: StatusCode, message: String) -> Self {
+        Error::Custom { message, status }
+    }
+
+    fn status(&self) -> StatusCode {
+        match self {
+            Error::Request(_) => StatusCode::BAD_REQUEST,
+            Error::NotFound(_) => StatusCode::NOT_FOUND,
+            Error::Archive(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::Custom { status, .. } => *status,
+        }
+    }
+}
+
+impl From<anyhow::Error> for Error {
+    fn from(err: anyhow::Error) -> Self {
+        Error::Archive(format!("{err:#}"))
+    }
+}
+
+/// One page of a paginated list
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Page<T> {
+    /// The items on this page
+    pub items: Vec<T>,
+    /// Where the next page starts, if there is one
+    pub next: Option<u64>,
+}
+
+/// A decided block
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct BlockQueryData<TYPES: NodeType> {
+    /// Height of the block
+    pub height: u64,
+    /// View the block was proposed in
+    pub view: TYPES::View,
+    /// Commitment of the leaf the block belongs to
+    pub hash: Commitment<Leaf2<TYPES>>,
+    /// The block header
+    pub header: TYPES::BlockHeader,
+    /// The block payload, if the archive has it
+    pub payload: Option<TYPES::BlockPayload>,
+    /// Number of transactions in the payload, if the archive has it
+    pub num_transactions: Option<usize>,
+}
+
+impl<TYPES: NodeType> From<Leaf2<TYPES>> for BlockQueryData<TYPES> {
+    fn from(leaf: Leaf2<TYPES>) -> Self {
+        let payload = leaf.block_payload();
+        let num_transactions = payload
+            .as_ref()
+            .map(|payload| payload.num_transactions(leaf.block_header().metadata()));
+        Self {
+            height: leaf.height(),
+            view: leaf.view_number(),
+            hash: leaf.commit(),
+            header: leaf.block_header().clone(),
+            payload,
+            num_transactions,
+        }
+    }
+}
+
+/// A decided transaction
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct TransactionQueryData<TYPES: NodeType> {
+    /// Height of the block which included the transaction
+    pub height: u64,
+    /// Position of the transaction in its block
+    pub index: usize,
+    /// The transaction
+    pub transaction: TYPES::Transaction,
+}
+
+/// The validators of an epoch
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct StakeTableQueryData<TYPES: NodeType> {
+    /// The epoch
+    pub epoch: TYPES::Epoch,
+    /// Number of validators
+    pub total_nodes: usize,
+    /// Validators and their stake
+    pub validators: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+    /// Members of the DA committee and their stake
+    pub da_committee: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+}
+
+/// State of the query API: the archive to read from, and the membership for stake tables.
+pub struct QueryState<TYPES: NodeType, A: ArchiveStorage<TYPES>> {
+    /// The archive of the decided chain
+    archive: Arc<A>,
+    /// Stake tables
+    memberships: Arc<TYPES::Membership>,
+}
+
+impl<TYPES: NodeType, A: ArchiveStorage<TYPES>> QueryState<TYPES, A> {
+    /// Serve the chain stored in `archive`, and the stake tables of `memberships`.
+    #[must_use]
+    pub fn new(archive: Arc<A>, memberships: Arc<TYPES::Membership>) -> Self {
+        Self {
+            archive,
+            memberships,
+        }
+    }
+
+    /// The leaf at `height`, or a not found error.
+    async fn leaf(&self, height: u64) -> Result<Leaf2<TYPES>, Error> {
+        self.archive
+            .leaf(height)
+            .await?
+            .ok_or_else(|| Error::NotFound(format!("Leaf {height}")))
+    }
+
+    /// Up to `limit` leaves from `from`, capped at [`MAX_PAGE_SIZE`].
+    async fn leaves(&self, from: u64, limit: u64) -> Result<Page<Leaf2<TYPES>>, Error> {
+        let Some(latest) = self.archive.latest_height().await? else {
+            return Ok(Page {
+                items: vec![],
+                next: None,
+            });
+        };
+        let end = from
+            .saturating_add(limit.min(MAX_PAGE_SIZE))
+            .min(latest.saturating_add(1));
+
+        // The archive has gaps if it started following the chain late
+        let items = self.archive.leaves(from..end).await?;
+        let next = (end <= latest).then_some(end);
+
+        Ok(Page { items, next })
+    }
+}
+
+/// Define the query API.
+///
+/// # Errors
+/// Fails if the API specification cannot be read or is invalid.
+pub fn define_api<State, TYPES, A>(
+    options: &Options,
+) -> Result<Api<State, Error, Version>, ApiError>
+where
+    TYPES: NodeType,
+    A: ArchiveStorage<TYPES>,
+    State: 'static + Send + Sync + ReadState<State = QueryState<TYPES, A>>,
+{
+    let toml = match &options.api_path {
+        Some(path) => std::fs::read_to_string(path).map_err(|err| ApiError::CannotReadToml {
+            reason: err.to_string(),
+        })?,
+        None => include_str!("../api/query.toml").to_string(),
+    };
+    let toml: toml::Value = toml::from_str(&toml).map_err(|err| ApiError::CannotReadToml {
+        reason: err.to_string(),
+    })?;
+
+    let mut api = Api::<State, Error, Version>::new(toml)?;
+    api.with_version("0.1.0".parse().unwrap())
+        .get("block_height", |_req, state| {
+            async move {
+                Ok(state
+                    .archive
+                    .latest_height()
+                    .await?
+                    .map_or(0, |height| height + 1))
+            }
+            .boxed()
+        })?
+        .get("leaf", |req, state| {
+            async move {
+                if let Some(height) = req.opt_integer_param("height")? {
+                    return state.leaf(height).await;
+                }
+                let leaf = if let Some(view) = req.opt_integer_param("view")? {
+                    state.archive.leaf_by_view(TYPES::View::new(view)).await?
+                } else {
+                    let hash: Commitment<Leaf2<TYPES>> = req.blob_param("hash")?;
+                    state.archive.leaf_by_commitment(hash).await?
+                };
+                leaf.ok_or_else(|| Error::NotFound("Leaf".into()))
+            }
+            .boxed()
+        })?
+        .get("leaves", |req, state| {
+            async move {
+                let from = req.integer_param("from")?;
+                let limit = req.integer_param("limit")?;
+                state.leaves(from, limit).await
+            }
+            .boxed()
+        })?
+        .get("block", |req, state| {
+            async move {
+                let leaf = if let Some(height) = req.opt_integer_param("height")? {
+                    state.leaf(height).await?
+                } else {
+                    let hash: Commitment<Leaf2<TYPES>> = req.blob_param("hash")?;
+                    state
+                        .archive
+                        .leaf_by_commitment(hash)
+                        .await?
+                        .ok_or_else(|| Error::NotFound(format!("Block {hash}")))?
+                };
+                Ok(BlockQueryData::from(leaf))
+            }
+            .boxed()
+        })?
+        .get("blocks", |req, state| {
+            async move {
+                let from = req.integer_param("from")?;
+                let limit = req.integer_param("limit")?;
+                let page = state.leaves(from, limit).await?;
+                Ok(Page {
+                    items: page
+                        .items
+                        .into_iter()
+                        .map(BlockQueryData::from)
+                        .collect::<Vec<_>>(),
+                    next: page.next,
+                })
+            }
+            .boxed()
+        })?
+        .get("qc", |req, state| {
+            async move {
+                let height: u64 = req.integer_param("height")?;
+                let child = state
+                    .archive
+                    .leaf(height + 1)
+                    .await?
+                    .ok_or_else(|| Error::NotFound(format!("QC for leaf {height}")))?;
+                let qc: QuorumCertificate2<TYPES> = child.justify_qc();
+                Ok(qc)
+            }
+            .boxed()
+        })?
+        .get("transaction", |req, state| {
+            async move {
+                let hash: Commitment<TYPES::Transaction> = req.blob_param("hash")?;
+                let not_found = || Error::NotFound(format!("Transaction {hash}"));
+                let height = state
+                    .archive
+                    .transaction_height(hash)
+                    .await?
+                    .ok_or_else(not_found)?;
+                let leaf = state.leaf(height).await?;
+                let payload = leaf.block_payload().ok_or_else(not_found)?;
+                let (index, transaction) = payload
+                    .transactions(leaf.block_header().metadata())
+                    .enumerate()
+                    .find(|(_, transaction)| transaction.commit() == hash)
+                    .ok_or_else(not_found)?;
+                Ok(TransactionQueryData::<TYPES> {
+                    height,
+                    index,
+                    transaction,
+                })
+            }
+            .boxed()
+        })?
+        .get("stake_table", |req, state| {
+            async move {
+                let epoch = TYPES::Epoch::new(req.integer_param("epoch")?);
+                Ok(StakeTableQueryData::<TYPES> {
+                    epoch,
+                    total_nodes: state.memberships.total_nodes(epoch),
+                    validators: state.memberships.stake_table(epoch).into_owned(),
+                    da_committee: state.memberships.da_stake_table(epoch).into_owned(),
+                })
+            }
+            .boxed()
+        })?;
+
+    Ok(api)
+}
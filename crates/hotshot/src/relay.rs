@@ -0,0 +1,290 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Relay of the broadcast stream to observer clients.
+//!
+//! A [`Relay`] follows the event stream of a node and forwards quorum proposals, newly formed QCs
+//! and decides to clients connected over TCP, so that RPC providers can fan chain data out without
+//! joining the p2p network. Clients only receive: the relay never reads from them.
+//!
+//! The protocol is a version byte, [`RELAY_PROTOCOL_VERSION`], followed by frames of a 4-byte
+//! big-endian length and a bincode-serialized [`RelayMessage`]. [`read_message`] reads a frame on
+//! the client side. A client which falls more than [`RelayConfig::buffer`] messages behind is
+//! disconnected, rather than silently missing messages or slowing the node down.
+
+use std::{
+    io::{Error as IoError, ErrorKind},
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use async_broadcast::{broadcast, InactiveReceiver, Receiver, RecvError, Sender};
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    data::{Leaf2, QuorumProposal2},
+    message::Proposal,
+    simple_certificate::QuorumCertificate2,
+    traits::{node_implementation::NodeType, signature_key::ProposerId},
+};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::types::{Event, EventType};
+
+/// Version of the relay protocol, sent to clients when they connect
+pub const RELAY_PROTOCOL_VERSION: u8 = 1;
+
+/// Largest frame a client accepts
+pub const MAX_FRAME_SIZE: usize = 64 * 1024 * 1024;
+
+/// Configuration of the relay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RelayConfig {
+    /// Address clients connect to
+    pub bind_address: SocketAddr,
+    /// Maximum number of connected clients. Further clients are turned away
+    pub max_clients: usize,
+    /// Number of messages buffered for each client, beyond which it is disconnected
+    pub buffer: usize,
+}
+
+impl RelayConfig {
+    /// Default relay settings, listening on `bind_address`.
+    #[must_use]
+    pub fn new(bind_address: SocketAddr) -> Self {
+        Self {
+            bind_address,
+            max_clients: 256,
+            buffer: 1024,
+        }
+    }
+}
+
+/// A message of the broadcast stream, as relayed to clients.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+#[allow(clippy::large_enum_variant)]
+pub enum RelayMessage<TYPES: NodeType> {
+    /// A quorum proposal was received or sent by the node
+    QuorumProposal {
+        /// The signed proposal
+        proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
+        /// The leader which made it
+        sender: ProposerId<TYPES::SignatureKey>,
+    },
+    /// A QC newer than any before was formed, as seen in the proposal extending it
+    Qc(QuorumCertificate2<TYPES>),
+    /// Leaves were decided
+    Decide {
+        /// The decided leaves, newest first
+        leaves: Vec<Leaf2<TYPES>>,
+        /// The QC of the newest leaf
+        qc: QuorumCertificate2<TYPES>,
+    },
+}
+
+/// Forwards the broadcast stream of a node to observer clients.
+///
+/// The relay stops when it is dropped.
+pub struct Relay {
+    /// Address clients connect to
+    local_addr: SocketAddr,
+    /// The task turning events into frames
+    relay_task: JoinHandle<()>,
+    /// The task accepting clients
+    accept_task: JoinHandle<()>,
+}
+
+impl Relay {
+    /// Start relaying `events` to clients connecting on the configured address.
+    ///
+    /// # Errors
+    /// Fails if we cannot listen on the address.
+    pub async fn spawn<TYPES: NodeType>(
+        config: RelayConfig,
+        events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(config.bind_address).await?;
+        let local_addr = listener.local_addr()?;
+
+        let (mut sender, receiver) = broadcast(config.buffer.max(1));
+        // Lagging clients see an overflow and are disconnected, and nobody needs to be connected
+        sender.set_overflow(true);
+        sender.set_await_active(false);
+
+        let relay_task = tokio::spawn(relay(sender, events));
+        let accept_task = tokio::spawn(accept(listener, receiver.deactivate(), config.max_clients));
+        tracing::info!("Relaying the broadcast stream on {local_addr}");
+
+        Ok(Self {
+            local_addr,
+            relay_task,
+            accept_task,
+        })
+    }
+
+    /// The address clients connect to.
+    #[must_use]
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for Relay {
+    fn drop(&mut self) {
+        self.relay_task.abort();
+        self.accept_task.abort();
+    }
+}
+
+/// Turn `events` into frames for clients, serializing each message once for all of them.
+async fn relay<TYPES: NodeType>(
+    sender: Sender<Arc<Vec<u8>>>,
+    mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin,
+) {
+    let mut last_qc_view = None;
+
+    while let Some(event) = events.next().await {
+        let mut messages = Vec::new();
+        match event.event {
+            EventType::QuorumProposal { proposal, sender } => {
+                let qc = &proposal.data.justify_qc;
+                if last_qc_view < Some(qc.view_number) {
+                    last_qc_view = Some(qc.view_number);
+                    messages.push(RelayMessage::Qc(qc.clone()));
+                }
+                messages.push(RelayMessage::QuorumProposal { proposal, sender });
+            }
+            EventType::Decide { leaf_chain, qc, .. } => {
+                messages.push(RelayMessage::Decide {
+                    leaves: leaf_chain.iter().map(|info| info.leaf.clone()).collect(),
+                    qc: (*qc).clone(),
+                });
+            }
+            _ => continue,
+        }
+
+        for message in messages {
+            let frame = match encode_frame(&message) {
+                Ok(frame) => frame,
+                Err(err) => {
+                    tracing::warn!("Failed to serialize relay message: {err}");
+                    continue;
+                }
+            };
+            // Only fails if no client is connected, in which case there is nobody to tell
+            let _ = sender.broadcast_direct(Arc::new(frame)).await;
+        }
+    }
+}
+
+/// Serialize `message` into a length-prefixed frame.
+fn encode_frame<TYPES: NodeType>(message: &RelayMessage<TYPES>) -> bincode::Result<Vec<u8>> {
+    let body = bincode::serialize(message)?;
+    let len = u32::try_from(body.len()).map_err(|_| {
+        Box::new(bincode::ErrorKind::Custom(
+            "relay message too large".to_string(),
+        ))
+    })?;
+    let mut frame = Vec::with_capacity(4 + body.len());
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend(body);
+    Ok(frame)
+}
+
+/// Accept clients on `listener`, up to `max_clients` at once.
+async fn accept(
+    listener: TcpListener,
+    receiver: InactiveReceiver<Arc<Vec<u8>>>,
+    max_clients: usize,
+) {
+    let clients = Arc::new(AtomicUsize::new(0));
+
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(client) => client,
+            Err(err) => {
+                tracing::warn!("Failed to accept relay client: {err}");
+                continue;
+            }
+        };
+
+        if clients.fetch_add(1, Ordering::Relaxed) >= max_clients {
+            clients.fetch_sub(1, Ordering::Relaxed);
+            tracing::debug!("Turning relay client {addr} away: already serving {max_clients}");
+            continue;
+        }
+
+        let frames = receiver.activate_cloned();
+        let clients = Arc::clone(&clients);
+        tokio::spawn(async move {
+            if let Err(err) = serve(stream, frames).await {
+                tracing::debug!("Relay client {addr} disconnected: {err}");
+            }
+            clients.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
+
+/// Send `frames` to a client until it disconnects or falls behind.
+async fn serve(mut stream: TcpStream, mut frames: Receiver<Arc<Vec<u8>>>) -> std::io::Result<()> {
+    stream.set_nodelay(true)?;
+    stream.write_all(&[RELAY_PROTOCOL_VERSION]).await?;
+
+    loop {
+        match frames.recv_direct().await {
+            Ok(frame) => stream.write_all(&frame).await?,
+            Err(RecvError::Overflowed(missed)) => {
+                return Err(IoError::new(
+                    ErrorKind::Other,
+                    format!("fell {missed} messages behind"),
+                ));
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        }
+    }
+}
+
+/// Read the protocol version a relay sends when a client connects.
+///
+/// # Errors
+/// Fails if the stream fails, or the relay speaks another version of the protocol.
+pub async fn read_version<R: AsyncRead + Unpin>(stream: &mut R) -> std::io::Result<()> {
+    let version = stream.read_u8().await?;
+    if version != RELAY_PROTOCOL_VERSION {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            format!("relay speaks protocol version {version}, not {RELAY_PROTOCOL_VERSION}"),
+        ));
+    }
+    Ok(())
+}
+
+/// Read the next message from a relay, after [`read_version`].
+///
+/// # Errors
+/// Fails if the stream fails, or the frame is too large or malformed.
+pub async fn read_message<TYPES: NodeType, R: AsyncRead + Unpin>(
+    stream: &mut R,
+) -> std::io::Result<RelayMessage<TYPES>> {
+    let len = usize::try_from(stream.read_u32().await?).unwrap_or(usize::MAX);
+    if len > MAX_FRAME_SIZE {
+        return Err(IoError::new(
+            ErrorKind::InvalidData,
+            format!("relay frame of {len} bytes is too large"),
+        ));
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body).await?;
+    bincode::deserialize(&body).map_err(|err| IoError::new(ErrorKind::InvalidData, err))
+}
@@ -0,0 +1,132 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Signing with a consensus key held by a remote signing service, e.g. in front of an HSM.
+//!
+//! The [`RemoteSigner`] posts a [`SignRequest`] to `{url}/sign` and expects the signature back.
+//! Requests which time out or fail are retried up to [`RemoteSignerConfig::max_attempts`] times,
+//! and every signature is verified against our public key before it is used, so a misbehaving
+//! service cannot make us send invalid votes or proposals.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use hotshot_types::traits::{
+    signature_key::SignatureKey,
+    signer::{LocalSigner, RemoteSignerConfig, Signer, SignerError},
+};
+use serde::{Deserialize, Serialize};
+use surf_disco::{error::ClientError, Client};
+use tokio::time::timeout;
+use vbs::version::StaticVersion;
+
+/// API version of the signing service
+pub type RemoteSignerVersion = StaticVersion<0, 1>;
+
+/// The signer of the node with `public_key`: the signing service in `remote` if one is
+/// configured, and otherwise a [`LocalSigner`] holding `private_key`.
+///
+/// Nodes which sign remotely need not load their private key at all, and can build a
+/// [`RemoteSigner`] themselves instead.
+#[must_use]
+pub fn configured_signer<K: SignatureKey + 'static>(
+    remote: Option<&RemoteSignerConfig>,
+    public_key: K,
+    private_key: K::PrivateKey,
+) -> Arc<dyn Signer<K>> {
+    match remote {
+        Some(remote) => Arc::new(RemoteSigner::new(remote.clone(), public_key)),
+        None => Arc::new(LocalSigner::new(public_key, private_key)),
+    }
+}
+
+/// A request to the signing service.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct SignRequest<K: SignatureKey> {
+    /// The key to sign with, so one service can hold the keys of several nodes
+    pub public_key: K,
+    /// The data to sign
+    pub data: Vec<u8>,
+}
+
+/// A [`Signer`] asking a signing service for signatures.
+pub struct RemoteSigner<K: SignatureKey> {
+    /// Settings
+    config: RemoteSignerConfig,
+    /// Client of the service
+    client: Client<ClientError, RemoteSignerVersion>,
+    /// The public key of the key pair the service signs with
+    public_key: K,
+}
+
+impl<K: SignatureKey> RemoteSigner<K> {
+    /// A signer asking the service in `config` to sign with the private key of `public_key`.
+    #[must_use]
+    pub fn new(config: RemoteSignerConfig, public_key: K) -> Self {
+        Self {
+            client: Client::new(config.url.clone()),
+            config,
+            public_key,
+        }
+    }
+
+    /// Ask the service for a signature once.
+    async fn request(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, SignerError> {
+        let request = SignRequest {
+            public_key: self.public_key.clone(),
+            data: data.to_vec(),
+        };
+        let response = self
+            .client
+            .post::<K::PureAssembledSignatureType>("sign")
+            .body_json(&request)
+            .map_err(|err| SignerError::Failed(err.to_string()))?
+            .send();
+
+        match timeout(self.config.timeout, response).await {
+            Ok(Ok(signature)) => Ok(signature),
+            Ok(Err(err)) => Err(SignerError::Unavailable(err.to_string())),
+            Err(_) => Err(SignerError::Unavailable(format!(
+                "no answer within {:?}",
+                self.config.timeout
+            ))),
+        }
+    }
+}
+
+impl<K: SignatureKey> std::fmt::Debug for RemoteSigner<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RemoteSigner")
+            .field("config", &self.config)
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static> Signer<K> for RemoteSigner<K> {
+    fn public_key(&self) -> &K {
+        &self.public_key
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, SignerError> {
+        let mut result = Err(SignerError::Unavailable("no attempt was made".to_string()));
+        for attempt in 1..=self.config.max_attempts {
+            result = self.request(data).await;
+            match &result {
+                Ok(signature) => {
+                    if !self.public_key.validate(signature, data) {
+                        return Err(SignerError::InvalidSignature);
+                    }
+                    return result;
+                }
+                Err(err) => tracing::debug!("Attempt {attempt} to sign remotely failed: {err}"),
+            }
+        }
+        result
+    }
+}
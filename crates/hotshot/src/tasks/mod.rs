@@ -17,12 +17,17 @@ use futures::{
     future::{BoxFuture, FutureExt},
     stream, StreamExt,
 };
-use hotshot_task::task::Task;
+use hotshot_task::{spawn::spawn_named, task::Task};
 #[cfg(feature = "rewind")]
 use hotshot_task_impls::rewind::RewindTaskState;
 use hotshot_task_impls::{
+    anti_entropy::AntiEntropyTaskState,
     da::DaTaskState,
+    dag_mempool::DagMempoolTaskState,
     events::HotShotEvent,
+    fallback::FallbackTaskState,
+    helpers::broadcast_event,
+    mempool::{MempoolTaskState, PeerRateLimiter},
     network::{NetworkEventTaskState, NetworkMessageTaskState},
     request::NetworkRequestState,
     response::{run_response_task, NetworkResponseState},
@@ -30,23 +35,29 @@ use hotshot_task_impls::{
     upgrade::UpgradeTaskState,
     vid::VidTaskState,
     view_sync::ViewSyncTaskState,
+    vote_aggregator::VoteAggregatorTaskState,
 };
 use hotshot_types::{
+    channels::ViewChannels,
     consensus::{Consensus, OuterConsensus},
     constants::EVENT_CHANNEL_SIZE,
     message::{Message, UpgradeLock},
+    replay::ReplayGuard,
     traits::{
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
+        signer::Signer,
     },
+    utils::mnemonic,
 };
-use tokio::{spawn, time::sleep};
+use parking_lot::Mutex;
+use tokio::{sync::Notify, time::sleep};
 use vbs::version::StaticVersionType;
 
 use crate::{
     tasks::task_state::CreateTaskState, types::SystemContextHandle, ConsensusApi,
     ConsensusMetricsValue, ConsensusTaskRegistry, HotShotConfig, HotShotInitializer,
-    MarketplaceConfig, NetworkTaskRegistry, SignatureKey, SystemContext, Versions,
+    MarketplaceConfig, NetworkTaskRegistry, SystemContext, Versions,
 };
 
 /// event for global event stream
@@ -84,7 +95,7 @@ pub fn add_response_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versi
         handle.hotshot.consensus(),
         (*handle.hotshot.memberships).clone().into(),
         handle.public_key().clone(),
-        handle.private_key().clone(),
+        handle.signer(),
         handle.hotshot.id,
     );
     handle.network_registry.register(run_response_task::<TYPES>(
@@ -100,8 +111,9 @@ pub fn add_queue_len_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Vers
 ) {
     let consensus = handle.hotshot.consensus();
     let rx = handle.internal_event_stream.1.clone();
+    let external_rx = handle.output_event_stream.1.clone();
     let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
-    let task_handle = spawn(async move {
+    let task_handle = spawn_named("queue length metrics", async move {
         futures::pin_mut!(shutdown_signal);
         loop {
             futures::select! {
@@ -109,7 +121,33 @@ pub fn add_queue_len_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Vers
                     return;
                 },
                 () = sleep(Duration::from_millis(500)).fuse() => {
-                    consensus.read().await.metrics.internal_event_queue_len.set(rx.len());
+                    let metrics = Arc::clone(&consensus.read().await.metrics);
+                    metrics.internal_event_queue_len.set(rx.len());
+                    metrics.external_event_queue_len.set(external_rx.len());
+                }
+            }
+        }
+    });
+    handle.network_registry.register(task_handle);
+}
+
+/// Add a task which tells the anti-entropy task to send its digest at the configured interval
+pub fn add_anti_entropy_tick_task<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &mut SystemContextHandle<TYPES, I, V>,
+) {
+    let interval = Duration::from_millis(handle.hotshot.config.anti_entropy.interval_ms);
+    let event_stream = handle.internal_event_stream.0.clone();
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let task_handle = spawn_named("anti-entropy tick", async move {
+        futures::pin_mut!(shutdown_signal);
+        loop {
+            futures::select! {
+                () = shutdown_signal => {
+                    return;
+                },
+                () = sleep(interval).fuse() => {
+                    broadcast_event(Arc::new(HotShotEvent::AntiEntropyTick), &event_stream)
+                        .await;
                 }
             }
         }
@@ -133,15 +171,36 @@ pub fn add_network_message_task<
         external_event_stream: handle.output_event_stream.0.clone(),
         public_key: handle.public_key().clone(),
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        transaction_limiter: handle
+            .hotshot
+            .config
+            .mempool_gossip
+            .enabled
+            .then(|| PeerRateLimiter::new(&handle.hotshot.config.mempool_gossip)),
+        channels: handle.hotshot.config.channels,
+        consensus: OuterConsensus::new(handle.consensus()),
+        membership: Arc::clone(&handle.hotshot.memberships),
     };
 
     let upgrade_lock = handle.hotshot.upgrade_lock.clone();
 
+    // Received messages wait in one channel per view, so that the dispatcher can drop those of
+    // views consensus left without handling them one by one
+    let channels = Arc::new(Mutex::new(ViewChannels::new(
+        handle.hotshot.config.channels.view_channel_capacity,
+    )));
+    let queued = Arc::new(Notify::new());
+
     let network = Arc::clone(channel);
-    let mut state = network_state.clone();
+    let state = network_state.clone();
+    let receiver_channels = Arc::clone(&channels);
+    let receiver_queued = Arc::clone(&queued);
+    let verification_workers = Arc::clone(&handle.hotshot.verification_workers);
     let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
-    let task_handle = spawn(async move {
+    let task_handle = spawn_named("network message", async move {
         futures::pin_mut!(shutdown_signal);
+        // Nonces seen from each sender, so that recorded messages cannot be injected again
+        let replay = Arc::new(Mutex::new(ReplayGuard::default()));
 
         loop {
             // Wait for one of the following to resolve:
@@ -163,17 +222,103 @@ pub fn add_network_message_task<
                         }
                     };
 
-                    // Deserialize the message
-                    let deserialized_message: Message<TYPES> = match upgrade_lock.deserialize(&message).await {
-                        Ok(message) => message,
+                    // Take the message out of its envelope, and drop replays before spending a
+                    // signature check on them
+                    let unsealed = match upgrade_lock.open_signed(&message).await {
+                        Ok(unsealed) => unsealed,
                         Err(e) => {
                             tracing::error!("Failed to deserialize message: {:?}", e);
                             continue;
                         }
                     };
+                    if let Err(e) = unsealed.check_replay(&replay.lock()) {
+                        tracing::debug!("{e:?}");
+                        continue;
+                    }
 
-                    // Handle the message
-                    state.handle_message(deserialized_message).await;
+                    // Check the sender's signature on the verification workers, so that the
+                    // next messages are received meanwhile
+                    let verified = verification_workers
+                        .spawn_blocking(move || unsealed.verify().then_some(unsealed));
+                    let replay = Arc::clone(&replay);
+                    let state = state.clone();
+                    let network = Arc::clone(&network);
+                    let channels = Arc::clone(&receiver_channels);
+                    let queued = Arc::clone(&receiver_queued);
+                    tokio::spawn(async move {
+                        let unsealed = match verified.await {
+                            Ok(Some(unsealed)) => unsealed,
+                            Ok(None) => {
+                                tracing::warn!("Dropping message not signed by its sender");
+                                return;
+                            }
+                            // The workers shut down with the node
+                            Err(_) => return,
+                        };
+                        let deserialized_message = match unsealed.accept(&mut replay.lock()) {
+                            Ok(message) => message,
+                            Err(e) => {
+                                tracing::debug!("{e:?}");
+                                return;
+                            }
+                        };
+
+                        if !state.may_send(&deserialized_message) {
+                            tracing::warn!(
+                                "Dropping message from {}, who may not send it",
+                                mnemonic(&deserialized_message.sender)
+                            );
+                            return;
+                        }
+
+                        // Measure its propagation, which the network may tune its gossip by
+                        if let Some((class, latency)) =
+                            state.record_propagation(&deserialized_message).await
+                        {
+                            network.observe_propagation(class, latency);
+                        }
+
+                        // Queue the message for the dispatcher
+                        NetworkMessageTaskState::queue_message(
+                            &mut channels.lock(),
+                            deserialized_message,
+                        );
+                        queued.notify_one();
+                    });
+                }
+            }
+        }
+    });
+    handle.network_registry.register(task_handle);
+
+    let mut state = network_state;
+    let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
+    let task_handle = spawn_named("network message dispatch", async move {
+        futures::pin_mut!(shutdown_signal);
+
+        loop {
+            futures::select! {
+                () = shutdown_signal => {
+                    tracing::error!("Shutting down network message dispatch task");
+                    return;
+                }
+
+                () = queued.notified().fuse() => {
+                    // Handle the queued messages, dropping those of views consensus left first
+                    loop {
+                        let floor = state.channel_floor().await;
+                        let (dropped, message) = {
+                            let mut channels = channels.lock();
+                            (channels.advance(floor), channels.pop())
+                        };
+                        if dropped > 0 {
+                            state.record_stale_messages(dropped).await;
+                        }
+                        let Some(message) = message else {
+                            break;
+                        };
+                        state.handle_message(message).await;
+                    }
                 }
             }
         }
@@ -200,7 +345,10 @@ pub fn add_network_event_task<
         storage: Arc::clone(&handle.storage()),
         consensus: OuterConsensus::new(handle.consensus()),
         upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        signer: handle.signer(),
         transmit_tasks: BTreeMap::new(),
+        archival_nodes: handle.hotshot.config.archival_nodes.clone(),
+        aggregation: handle.hotshot.config.aggregation,
     };
     let task = Task::new(
         network_state,
@@ -218,6 +366,25 @@ pub async fn add_consensus_tasks<TYPES: NodeType, I: NodeImplementation<TYPES>,
     handle.add_task(VidTaskState::<TYPES, I>::create_from(handle).await);
     handle.add_task(DaTaskState::<TYPES, I, V>::create_from(handle).await);
     handle.add_task(TransactionTaskState::<TYPES, I, V>::create_from(handle).await);
+    if handle.hotshot.config.mempool_gossip.enabled {
+        handle.add_task(MempoolTaskState::<TYPES>::new(
+            handle.public_key().clone(),
+            handle.hotshot.config.mempool_gossip,
+        ));
+    }
+    if handle.hotshot.config.anti_entropy.enabled {
+        handle.add_task(AntiEntropyTaskState::<TYPES>::create_from(handle).await);
+        add_anti_entropy_tick_task(handle);
+    }
+    if handle.hotshot.config.dag_mempool.enabled {
+        handle.add_task(DagMempoolTaskState::<TYPES, V>::create_from(handle).await);
+    }
+    if handle.hotshot.config.fallback.enabled {
+        handle.add_task(FallbackTaskState::<TYPES, V>::create_from(handle).await);
+    }
+    if handle.hotshot.config.aggregation.enabled {
+        handle.add_task(VoteAggregatorTaskState::<TYPES, V>::create_from(handle).await);
+    }
 
     {
         let mut upgrade_certificate_lock = handle
@@ -307,8 +474,7 @@ where
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>>;
@@ -317,8 +483,7 @@ where
     /// Creates a `SystemContextHandle` with the given even transformer
     async fn spawn_handle(
         &'static mut self,
-        public_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         nonce: u64,
         config: HotShotConfig<TYPES::SignatureKey>,
         memberships: TYPES::Membership,
@@ -330,8 +495,7 @@ where
     ) -> SystemContextHandle<TYPES, I, V> {
         let epoch_height = config.epoch_height;
         let hotshot = SystemContext::new(
-            public_key,
-            private_key,
+            signer,
             nonce,
             config,
             memberships,
@@ -407,11 +571,10 @@ where
         // spawn a task to listen on the (original) internal event stream,
         // and broadcast the transformed events to the replacement event stream we just created.
         let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
-        let public_key = handle.public_key().clone();
-        let private_key = handle.private_key().clone();
+        let signer = handle.signer();
         let upgrade_lock = handle.hotshot.upgrade_lock.clone();
         let consensus = Arc::clone(&handle.hotshot.consensus());
-        let send_handle = spawn(async move {
+        let send_handle = spawn_named("network relay send", async move {
             futures::pin_mut!(shutdown_signal);
 
             let recv_stream = stream::unfold(original_receiver, |mut recv| async move {
@@ -439,8 +602,7 @@ where
                                 let mut state = state_out.write().await;
                                 let mut results = state.send_handler(
                                     &msg,
-                                    &public_key,
-                                    &private_key,
+                                    signer.as_ref(),
                                     &upgrade_lock,
                                     Arc::clone(&consensus)
                                 ).await;
@@ -465,7 +627,7 @@ where
         // spawn a task to listen on the newly created event stream,
         // and broadcast the transformed events to the original internal event stream
         let shutdown_signal = create_shutdown_event_monitor(handle).fuse();
-        let recv_handle = spawn(async move {
+        let recv_handle = spawn_named("network relay receive", async move {
             futures::pin_mut!(shutdown_signal);
 
             let network_recv_stream =
@@ -5,16 +5,19 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{BTreeMap, HashMap},
+    collections::{BTreeMap, BTreeSet, HashMap},
     sync::{atomic::AtomicBool, Arc},
 };
 
 use async_trait::async_trait;
 use chrono::Utc;
 use hotshot_task_impls::{
+    anti_entropy::AntiEntropyTaskState,
     builder::BuilderClient,
     consensus::ConsensusTaskState,
     da::DaTaskState,
+    dag_mempool::DagMempoolTaskState,
+    fallback::FallbackTaskState,
     quorum_proposal::QuorumProposalTaskState,
     quorum_proposal_recv::QuorumProposalRecvTaskState,
     quorum_vote::{drb_computations::DrbComputations, QuorumVoteTaskState},
@@ -24,9 +27,13 @@ use hotshot_task_impls::{
     upgrade::UpgradeTaskState,
     vid::VidTaskState,
     view_sync::ViewSyncTaskState,
+    vote_aggregator::VoteAggregatorTaskState,
 };
 use hotshot_types::{
+    anti_entropy::AntiEntropyStore,
     consensus::OuterConsensus,
+    dag_mempool::BatchStore,
+    participation::ParticipationTracker,
     traits::{
         consensus_api::ConsensusApi,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
@@ -60,7 +67,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             delay: handle.hotshot.config.data_request_delay,
             membership: (*handle.hotshot.memberships).clone(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             shutdown_flag: Arc::new(AtomicBool::new(false)),
             spawned_tasks: BTreeMap::new(),
@@ -81,7 +88,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             quorum_membership: (*handle.hotshot.memberships).clone().into(),
             vote_collectors: BTreeMap::default(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             start_proposing_view: handle.hotshot.config.start_proposing_view,
             stop_proposing_view: handle.hotshot.config.stop_proposing_view,
@@ -92,6 +99,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             start_voting_time: handle.hotshot.config.start_voting_time,
             stop_voting_time: handle.hotshot.config.stop_voting_time,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
         };
 
         #[cfg(feature = "example-upgrade")]
@@ -103,7 +111,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             network: Arc::clone(&handle.hotshot.network),
             vote_collector: None.into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             start_proposing_view: 5,
             stop_proposing_view: 10,
@@ -114,6 +122,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             start_voting_time: 0,
             stop_voting_time: u64::MAX,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
         };
     }
 }
@@ -130,7 +139,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             network: Arc::clone(&handle.hotshot.network),
             membership: (*handle.hotshot.memberships).clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
         }
     }
@@ -141,8 +150,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
     for DaTaskState<TYPES, I, V>
 {
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        let consensus = handle.hotshot.consensus();
+
+        // Share the saved payloads, which have their own lock
+        let saved_payloads = consensus.read().await.saved_payloads().clone();
+
         Self {
-            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            consensus: OuterConsensus::new(consensus),
+            saved_payloads,
             output_event_stream: handle.hotshot.external_event_stream.0.clone(),
             membership: (*handle.hotshot.memberships).clone().into(),
             network: Arc::clone(&handle.hotshot.network),
@@ -150,10 +165,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             vote_collectors: BTreeMap::default(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             id: handle.hotshot.id,
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            block_limits: handle.hotshot.config.block_limits,
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
+            payload_streaming: handle.hotshot.config.payload_streaming,
+            payload_streams: BTreeMap::new(),
         }
     }
 }
@@ -171,7 +190,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             membership: (*handle.hotshot.memberships).clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             num_timeouts_tracked: 0,
             replica_task_map: HashMap::default().into(),
             pre_commit_relay_map: HashMap::default().into(),
@@ -181,6 +200,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             last_garbage_collected_view: TYPES::View::new(0),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
         }
     }
 }
@@ -198,7 +218,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             cur_epoch: handle.cur_epoch().await,
             membership: (*handle.hotshot.memberships).clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             instance_state: handle.hotshot.instance_state(),
             id: handle.hotshot.id,
             builder_clients: handle
@@ -218,6 +238,94 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
                 .marketplace_config
                 .fallback_builder_url
                 .clone(),
+            block_limits: handle.hotshot.config.block_limits,
+            dag_mempool: handle.hotshot.config.dag_mempool,
+            prefetched: None,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for AntiEntropyTaskState<TYPES>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            public_key: handle.public_key().clone(),
+            membership: (*handle.hotshot.memberships).clone().into(),
+            config: handle.hotshot.config.anti_entropy,
+            cur_view: handle.cur_view().await,
+            cur_epoch: handle.cur_epoch().await,
+            store: AntiEntropyStore::default(),
+            requested: BTreeSet::new(),
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for FallbackTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        let cur_view = handle.cur_view().await;
+
+        Self {
+            public_key: handle.public_key().clone(),
+            signer: handle.signer(),
+            membership: (*handle.hotshot.memberships).clone().into(),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            config: handle.hotshot.config.fallback,
+            cur_view,
+            cur_epoch: handle.cur_epoch().await,
+            last_progress: cur_view,
+            accumulators: BTreeMap::new(),
+            elected: BTreeMap::new(),
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for VoteAggregatorTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            public_key: handle.public_key().clone(),
+            membership: (*handle.hotshot.memberships).clone().into(),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            config: handle.hotshot.config.aggregation,
+            cur_view: handle.cur_view().await,
+            cur_epoch: handle.cur_epoch().await,
+            pending: BTreeMap::new(),
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
+            id: handle.hotshot.id,
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState<TYPES, I, V>
+    for DagMempoolTaskState<TYPES, V>
+{
+    async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
+        Self {
+            public_key: handle.public_key().clone(),
+            signer: handle.signer(),
+            membership: (*handle.hotshot.memberships).clone().into(),
+            consensus: OuterConsensus::new(handle.hotshot.consensus()),
+            instance_state: handle.hotshot.instance_state(),
+            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+            config: handle.hotshot.config.dag_mempool,
+            cur_view: handle.cur_view().await,
+            cur_epoch: handle.cur_epoch().await,
+            pending: Vec::new(),
+            store: BatchStore::default(),
+            accumulators: HashMap::new(),
+            prefetched: None,
+            waiting: BTreeMap::new(),
+            id: handle.hotshot.id,
         }
     }
 }
@@ -232,9 +340,24 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
         // Clone the consensus metrics
         let consensus_metrics = Arc::clone(&consensus.read().await.metrics);
 
+        // Jails follow the decided chain, so pick up where it was left before a restart
+        let anchor = consensus.read().await.decided_leaf();
+        let participation = ParticipationTracker::restore(
+            &*handle.storage.read().await,
+            &anchor,
+            &handle.hotshot.memberships,
+            handle.hotshot.config.jail,
+            handle.hotshot.config.epoch_height,
+        )
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to rebuild validator participation from storage: {e}");
+            ParticipationTracker::default()
+        });
+
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             consensus: OuterConsensus::new(consensus),
             instance_state: handle.hotshot.instance_state(),
             latest_voted_view: handle.cur_view().await,
@@ -247,7 +370,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             storage: Arc::clone(&handle.storage),
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            participation,
+            jail_config: handle.hotshot.config.jail,
+            commit_rule: handle.hotshot.config.commit_rule,
             consensus_metrics,
+            state_workers: Arc::clone(&handle.hotshot.state_workers),
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
         }
     }
 }
@@ -267,14 +395,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             instance_state: handle.hotshot.instance_state(),
             quorum_membership: (*handle.hotshot.memberships).clone().into(),
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             storage: Arc::clone(&handle.storage),
             timeout: handle.hotshot.config.next_view_timeout,
             id: handle.hotshot.id,
             formed_upgrade_certificate: None,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            commit_rule: handle.hotshot.config.commit_rule,
+            pacing: handle.hotshot.config.pacing,
             highest_qc: handle.hotshot.consensus.read().await.high_qc().clone(),
+            high_qc_reports: BTreeMap::new(),
         }
     }
 }
@@ -288,7 +419,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
 
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             consensus: OuterConsensus::new(consensus),
             cur_view: handle.cur_view().await,
             cur_epoch: handle.cur_epoch().await,
@@ -300,6 +431,9 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            timestamp_rules: handle.hotshot.config.timestamp_rules,
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
+            commit_rule: handle.hotshot.config.commit_rule,
         }
     }
 }
@@ -311,9 +445,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
     async fn create_from(handle: &SystemContextHandle<TYPES, I, V>) -> Self {
         let consensus = handle.hotshot.consensus();
 
+        // Clone the consensus metrics
+        let consensus_metrics = Arc::clone(&consensus.read().await.metrics);
+
         Self {
             public_key: handle.public_key().clone(),
-            private_key: handle.private_key().clone(),
+            signer: handle.signer(),
             instance_state: handle.hotshot.instance_state(),
             network: Arc::clone(&handle.hotshot.network),
             membership: (*handle.hotshot.memberships).clone().into(),
@@ -326,9 +463,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> CreateTaskState
             timeout_task: spawn(async {}),
             timeout: handle.hotshot.config.next_view_timeout,
             consensus: OuterConsensus::new(consensus),
+            consensus_metrics,
+            verification_workers: Arc::clone(&handle.hotshot.verification_workers),
             id: handle.hotshot.id,
             upgrade_lock: handle.hotshot.upgrade_lock.clone(),
             epoch_height: handle.hotshot.config.epoch_height,
+            commit_rule: handle.hotshot.config.commit_rule,
         }
     }
 }
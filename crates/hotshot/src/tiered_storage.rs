@@ -0,0 +1,311 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Two-tier storage, keeping recent views on a fast backend and older ones on a cold one.
+//!
+//! [`TieredStorage`] writes everything to its hot tier, such as a local disk. Once a view has been
+//! decided for longer than [`StorageTiering::hot_views`] views, its proposal and VID shares are
+//! migrated to the cold tier, such as object storage or a slower disk, and pruned from the hot
+//! one. Reads of migrated views go through to the cold tier, so callers only see one [`Storage`].
+//!
+//! Both tiers must be able to read back what they store (see [`Storage::load`]).
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use anyhow::Result;
+use async_lock::RwLock;
+use async_trait::async_trait;
+use hotshot_types::{
+    consensus::{CommitmentMap, LeafMap},
+    data::{
+        DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
+        VidDisperseShare2,
+    },
+    event::HotShotAction,
+    message::Proposal,
+    simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        storage::{Storage, StorageKey, StoredValue, ViewUndo, ViewWrites},
+    },
+    utils::View,
+    vid::VidSchemeType,
+};
+use jf_vid::VidScheme;
+use serde::{Deserialize, Serialize};
+
+/// When views move from the hot tier to the cold one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StorageTiering {
+    /// Number of decided views kept in the hot tier behind the last decided view
+    pub hot_views: u64,
+}
+
+/// A [`Storage`] backend made of a hot tier for recent views and a cold tier for older ones.
+#[derive(Clone, Debug)]
+pub struct TieredStorage<TYPES: NodeType, H, C> {
+    /// The fast backend, holding recent views and everything which is not per view
+    hot: H,
+    /// The slow backend, holding the views migrated out of the hot tier
+    cold: C,
+    /// When views are migrated
+    tiering: StorageTiering,
+    /// Views before this one have been migrated to the cold tier. Writes hold it for reading,
+    /// so none of them lands in the hot tier while its view is being migrated.
+    migrated_before: Arc<RwLock<TYPES::View>>,
+}
+
+impl<TYPES: NodeType, H: Storage<TYPES>, C: Storage<TYPES>> TieredStorage<TYPES, H, C> {
+    /// Put `hot` in front of `cold`.
+    #[must_use]
+    pub fn new(hot: H, cold: C, tiering: StorageTiering) -> Self {
+        Self {
+            hot,
+            cold,
+            tiering,
+            migrated_before: Arc::new(RwLock::new(TYPES::View::genesis())),
+        }
+    }
+
+    /// The hot tier.
+    #[must_use]
+    pub fn hot(&self) -> &H {
+        &self.hot
+    }
+
+    /// The cold tier.
+    #[must_use]
+    pub fn cold(&self) -> &C {
+        &self.cold
+    }
+
+    /// Views before this one are read from and written to the cold tier.
+    pub async fn migrated_before(&self) -> TYPES::View {
+        *self.migrated_before.read().await
+    }
+
+    /// Migrate the views which have been decided for long enough, now that the views before
+    /// `decided` are decided.
+    async fn migrate(&self, decided: TYPES::View) -> Result<()> {
+        let cutoff = TYPES::View::new(decided.saturating_sub(self.tiering.hot_views));
+        let mut migrated_before = self.migrated_before.write().await;
+        if cutoff <= *migrated_before {
+            return Ok(());
+        }
+
+        // Copy before pruning, so every value is in one tier or the other for concurrent reads
+        for value in self.hot.load_before(cutoff).await? {
+            match value {
+                StoredValue::Proposal(proposal) => self.cold.append_proposal2(&proposal).await?,
+                StoredValue::VidShare(share) => self.cold.append_vid2(&share).await?,
+                StoredValue::HighQc(_) => {}
+            }
+        }
+        self.hot.prune(cutoff).await?;
+        tracing::debug!("Migrated views before {cutoff:?} to cold storage");
+        *migrated_before = cutoff;
+        Ok(())
+    }
+
+    /// Migrate what has been decided for long enough, if `state` shows that views were decided.
+    async fn migrate_decided(&self, state: &BTreeMap<TYPES::View, View<TYPES>>) {
+        // The oldest undecided view is the last decided one, so everything before it is decided
+        let Some(decided) = state.keys().next().copied() else {
+            return;
+        };
+        // The write itself succeeded, and migration is retried on the next decide
+        if let Err(e) = self.migrate(decided).await {
+            tracing::error!("Failed to migrate decided views to cold storage: {e:#}");
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, H: Storage<TYPES>, C: Storage<TYPES>> Storage<TYPES>
+    for TieredStorage<TYPES, H, C>
+{
+    async fn append_vid(&self, proposal: &Proposal<TYPES, VidDisperseShare<TYPES>>) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_vid(proposal).await
+        } else {
+            self.hot.append_vid(proposal).await
+        }
+    }
+
+    async fn append_vid2(
+        &self,
+        proposal: &Proposal<TYPES, VidDisperseShare2<TYPES>>,
+    ) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_vid2(proposal).await
+        } else {
+            self.hot.append_vid2(proposal).await
+        }
+    }
+
+    async fn append_da(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_da(proposal, vid_commit).await
+        } else {
+            self.hot.append_da(proposal, vid_commit).await
+        }
+    }
+
+    async fn append_da2(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        vid_commit: <VidSchemeType as VidScheme>::Commit,
+    ) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_da2(proposal, vid_commit).await
+        } else {
+            self.hot.append_da2(proposal, vid_commit).await
+        }
+    }
+
+    async fn append_proposal(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal<TYPES>>,
+    ) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_proposal(proposal).await
+        } else {
+            self.hot.append_proposal(proposal).await
+        }
+    }
+
+    async fn append_proposal2(
+        &self,
+        proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        let migrated_before = self.migrated_before.read().await;
+        if proposal.data.view_number < *migrated_before {
+            self.cold.append_proposal2(proposal).await
+        } else {
+            self.hot.append_proposal2(proposal).await
+        }
+    }
+
+    async fn record_action(&self, view: TYPES::View, action: HotShotAction) -> Result<()> {
+        self.hot.record_action(view, action).await
+    }
+
+    async fn update_high_qc(&self, high_qc: QuorumCertificate<TYPES>) -> Result<()> {
+        self.hot.update_high_qc(high_qc).await
+    }
+
+    async fn update_high_qc2(&self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        self.hot.update_high_qc2(high_qc).await
+    }
+
+    async fn update_undecided_state(
+        &self,
+        leaves: CommitmentMap<Leaf<TYPES>>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        self.hot.update_undecided_state(leaves, state).await
+    }
+
+    async fn update_undecided_state2(
+        &self,
+        leaves: LeafMap<TYPES>,
+        state: BTreeMap<TYPES::View, View<TYPES>>,
+    ) -> Result<()> {
+        self.hot
+            .update_undecided_state2(leaves, state.clone())
+            .await?;
+        self.migrate_decided(&state).await;
+        Ok(())
+    }
+
+    async fn write_view(&self, writes: ViewWrites<TYPES>) -> Result<()> {
+        // A view being voted on is never decided, so it is never migrated before this write
+        let state = writes
+            .undecided_state
+            .as_ref()
+            .map(|(_, state)| state.clone());
+        self.hot.write_view(writes).await?;
+        if let Some(state) = state {
+            self.migrate_decided(&state).await;
+        }
+        Ok(())
+    }
+
+    async fn update_decided_upgrade_certificate(
+        &self,
+        decided_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
+    ) -> Result<()> {
+        self.hot
+            .update_decided_upgrade_certificate(decided_upgrade_certificate)
+            .await
+    }
+
+    async fn migrate_consensus(
+        &self,
+        convert_leaf: fn(Leaf<TYPES>) -> Leaf2<TYPES>,
+        convert_proposal: fn(
+            Proposal<TYPES, QuorumProposal<TYPES>>,
+        ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()> {
+        self.hot
+            .migrate_consensus(convert_leaf, convert_proposal)
+            .await?;
+        self.cold
+            .migrate_consensus(convert_leaf, convert_proposal)
+            .await
+    }
+
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        let (Some(hot), Some(cold)) = (
+            self.hot.storage_size().await?,
+            self.cold.storage_size().await?,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(hot + cold))
+    }
+
+    async fn prune(&self, view: TYPES::View) -> Result<()> {
+        self.hot.prune(view).await?;
+        self.cold.prune(view).await
+    }
+
+    async fn revert_to(&self, view: TYPES::View) -> Result<Vec<ViewUndo<TYPES>>> {
+        // Only decided views are migrated, and those are never reverted
+        self.hot.revert_to(view).await
+    }
+
+    async fn load(&self, key: &StorageKey<TYPES>) -> Result<Option<StoredValue<TYPES>>> {
+        let view = match key {
+            StorageKey::Proposal(view) | StorageKey::VidShare(view, _) => *view,
+            StorageKey::HighQc => return self.hot.load(key).await,
+        };
+        if view < *self.migrated_before.read().await {
+            return self.cold.load(key).await;
+        }
+        // The view may be migrated since we checked, in which case it is in the cold tier now
+        match self.hot.load(key).await? {
+            Some(value) => Ok(Some(value)),
+            None => self.cold.load(key).await,
+        }
+    }
+
+    async fn load_before(&self, view: TYPES::View) -> Result<Vec<StoredValue<TYPES>>> {
+        let migrated_before = self.migrated_before.read().await;
+        let mut values = self.cold.load_before(view.min(*migrated_before)).await?;
+        values.extend(self.hot.load_before(view).await?);
+        Ok(values)
+    }
+}
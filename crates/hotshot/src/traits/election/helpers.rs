@@ -4,9 +4,24 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::BTreeSet, hash::Hash};
-
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    hash::Hash,
+    sync::Arc,
+};
+
+use hotshot_types::{
+    drb::{DrbResult, INITIAL_DRB_RESULT},
+    error::ConsensusError,
+    traits::{
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+};
+use parking_lot::RwLock;
 use rand::{rngs::StdRng, Rng, SeedableRng};
+use sha2::{Digest, Sha256};
 
 /// Helper which allows producing random numbers within a range and preventing duplicates
 /// If consumed as a regular iterator, will return a randomly ordered permutation of all
@@ -359,6 +374,156 @@ impl<
     }
 }
 
+/// The DRB results a membership has been given, shared between all of its clones.
+#[derive(Clone, Debug)]
+pub struct DrbResults<TYPES: NodeType> {
+    /// DRB result of each epoch
+    results: Arc<RwLock<BTreeMap<TYPES::Epoch, DrbResult>>>,
+}
+
+impl<TYPES: NodeType> Default for DrbResults<TYPES> {
+    fn default() -> Self {
+        Self {
+            results: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+}
+
+impl<TYPES: NodeType> DrbResults<TYPES> {
+    /// Record the DRB result for `epoch`.
+    pub fn insert(&self, epoch: TYPES::Epoch, drb_result: DrbResult) {
+        self.results.write().insert(epoch, drb_result);
+    }
+
+    /// The DRB result for `epoch`.
+    ///
+    /// The first epochs start before any DRB result can be computed, so they use
+    /// [`INITIAL_DRB_RESULT`], as does a network without epochs.
+    ///
+    /// # Errors
+    /// If the result for `epoch` is not known yet.
+    pub fn get(&self, epoch: TYPES::Epoch) -> Result<DrbResult, ConsensusError<TYPES>> {
+        if epoch.u64() <= 2 {
+            return Ok(INITIAL_DRB_RESULT);
+        }
+        self.results
+            .read()
+            .get(&epoch)
+            .copied()
+            .ok_or(ConsensusError::MissingDrbResult(epoch))
+    }
+}
+
+/// The validators a membership keeps from leading in each epoch, shared between all of its clones.
+#[derive(Clone, Debug)]
+pub struct Jail<TYPES: NodeType> {
+    /// Jailed validators of each epoch
+    jailed: Arc<RwLock<BTreeMap<TYPES::Epoch, BTreeSet<TYPES::SignatureKey>>>>,
+}
+
+impl<TYPES: NodeType> Default for Jail<TYPES> {
+    fn default() -> Self {
+        Self {
+            jailed: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+}
+
+impl<TYPES: NodeType> Jail<TYPES> {
+    /// Keep `jailed` from leading in `epoch`.
+    pub fn set(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jailed.write().insert(epoch, jailed);
+    }
+
+    /// The entries of `leaders` which may lead in `epoch`.
+    ///
+    /// If every leader is jailed, none is, so that the epoch still has leaders.
+    pub fn eligible<'a>(
+        &self,
+        epoch: TYPES::Epoch,
+        leaders: &'a [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+    ) -> Cow<'a, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        let jailed = self.jailed.read();
+        let Some(jailed) = jailed.get(&epoch).filter(|jailed| !jailed.is_empty()) else {
+            return Cow::Borrowed(leaders);
+        };
+
+        let eligible: Vec<_> = leaders
+            .iter()
+            .filter(|entry| !jailed.contains(&TYPES::SignatureKey::public_key(entry)))
+            .cloned()
+            .collect();
+        if eligible.is_empty() {
+            Cow::Borrowed(leaders)
+        } else {
+            Cow::Owned(eligible)
+        }
+    }
+}
+
+/// Number of views the leaders elected by the coin fallback are remembered for.
+const FALLBACK_LEADERS_KEPT: u64 = 1000;
+
+/// The leaders the coin fallback elected for particular views, shared between all clones
+/// of a membership.
+#[derive(Clone, Debug)]
+pub struct FallbackLeaders<TYPES: NodeType> {
+    /// Elected leader of each view
+    leaders: Arc<RwLock<BTreeMap<TYPES::View, TYPES::SignatureKey>>>,
+}
+
+impl<TYPES: NodeType> Default for FallbackLeaders<TYPES> {
+    fn default() -> Self {
+        Self {
+            leaders: Arc::new(RwLock::new(BTreeMap::new())),
+        }
+    }
+}
+
+impl<TYPES: NodeType> FallbackLeaders<TYPES> {
+    /// Have `leader` lead `view`, forgetting the leaders of views long past.
+    pub fn set(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        let mut leaders = self.leaders.write();
+        leaders.insert(view, leader);
+        let oldest = TYPES::View::new(view.u64().saturating_sub(FALLBACK_LEADERS_KEPT));
+        *leaders = leaders.split_off(&oldest);
+    }
+
+    /// The leader elected for `view`, if the fallback elected one.
+    pub fn get(&self, view: TYPES::View) -> Option<TYPES::SignatureKey> {
+        self.leaders.read().get(&view).cloned()
+    }
+}
+
+/// Domain tag of leader seeds, so they never coincide with another hash of the same DRB result
+const LEADER_SEED_DOMAIN: &[u8] = b"HotShot leader seed";
+
+/// RNG for picking the leader of `view`.
+///
+/// The seed is bound to the DRB result of the view's epoch, which derives from the QC of a block
+/// decided two epochs earlier, so neither the view number alone nor anything a proposer picks
+/// lets anyone work out future leaders before that block is decided.
+#[must_use]
+pub fn leader_rng(drb_result: &DrbResult, view: u64) -> StdRng {
+    let mut hasher = Sha256::new();
+    hasher.update(LEADER_SEED_DOMAIN);
+    hasher.update(drb_result);
+    hasher.update(view.to_le_bytes());
+    StdRng::from_seed(hasher.finalize().into())
+}
+
+/// Index of the leader of `view` among `candidates` eligible leaders, drawn from [`leader_rng`].
+///
+/// # Panics
+///
+/// panics if there are no candidates
+#[must_use]
+#[allow(clippy::cast_possible_truncation)]
+pub fn leader_index(drb_result: &DrbResult, view: u64, candidates: usize) -> usize {
+    let randomized_view_number: u64 = leader_rng(drb_result, view).gen_range(0..=u64::MAX);
+    randomized_view_number as usize % candidates
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
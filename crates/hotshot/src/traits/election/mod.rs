@@ -12,9 +12,12 @@ pub mod randomized_committee;
 /// quorum randomized every view, with configurable overlap
 pub mod randomized_committee_members;
 
-/// static (round robin) committee election
+/// static (round robin) committee election, in which every member votes with its full stake
 pub mod static_committee;
 
+/// static committee election over stake tables queried per epoch from a provider
+pub mod provided_committee;
+
 /// static (round robin leader for 2 consecutive views) committee election
 pub mod static_committee_leader_two_views;
 /// two static (round robin) committees for even and odd epochs
@@ -22,3 +25,6 @@ pub mod two_static_committees;
 
 /// general helpers
 pub mod helpers;
+
+/// golden test vectors for the randomness of elections
+pub mod test_vectors;
@@ -0,0 +1,273 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU64,
+    sync::Arc,
+};
+
+use hotshot_types::{
+    error::ConsensusError,
+    traits::{
+        election::{Membership, StakeTableProvider},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+    PeerConfig,
+};
+use parking_lot::RwLock;
+
+use super::{
+    helpers::{FallbackLeaders, Jail},
+    static_committee::StaticCommittee,
+};
+
+/// Stake tables fixed at startup, which hold for every epoch
+#[derive(Clone, Debug)]
+pub struct StaticStakeTables<T: NodeType> {
+    /// The quorum stake table
+    stake_table: Vec<PeerConfig<T::SignatureKey>>,
+
+    /// The DA stake table
+    da_stake_table: Vec<PeerConfig<T::SignatureKey>>,
+}
+
+impl<T: NodeType> StaticStakeTables<T> {
+    /// Provide `stake_table` and `da_stake_table` for every epoch.
+    #[must_use]
+    pub fn new(
+        stake_table: Vec<PeerConfig<T::SignatureKey>>,
+        da_stake_table: Vec<PeerConfig<T::SignatureKey>>,
+    ) -> Self {
+        Self {
+            stake_table,
+            da_stake_table,
+        }
+    }
+}
+
+impl<T: NodeType> StakeTableProvider<T> for StaticStakeTables<T> {
+    fn stake_tables(
+        &self,
+        _epoch: T::Epoch,
+    ) -> Option<(
+        Vec<PeerConfig<T::SignatureKey>>,
+        Vec<PeerConfig<T::SignatureKey>>,
+    )> {
+        Some((self.stake_table.clone(), self.da_stake_table.clone()))
+    }
+}
+
+/// An election which takes the stake tables of each epoch from a [`StakeTableProvider`], and
+/// otherwise behaves like a [`StaticCommittee`] within the epoch.
+#[derive(Clone, Debug)]
+pub struct ProvidedCommittee<T: NodeType> {
+    /// Where the stake tables come from
+    provider: Arc<dyn StakeTableProvider<T>>,
+
+    /// The committee of each epoch the provider has answered for, shared between clones so the
+    /// provider is queried once per epoch.
+    ///
+    /// Epochs are never forgotten, so each committee is leaked once when it is cached. This lets
+    /// the stake tables be borrowed rather than copied on every certificate and vote.
+    committees: Arc<RwLock<BTreeMap<T::Epoch, &'static StaticCommittee<T>>>>,
+
+    /// The committee of epochs the provider does not know yet
+    empty: Arc<StaticCommittee<T>>,
+
+    /// The validators kept from leading in each epoch, shared with every epoch's committee
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback, shared with every epoch's committee
+    fallback_leaders: FallbackLeaders<T>,
+}
+
+impl<T: NodeType> ProvidedCommittee<T> {
+    /// Create an election which queries `provider` for the stake tables of each epoch.
+    #[must_use]
+    pub fn with_provider(provider: Arc<dyn StakeTableProvider<T>>) -> Self {
+        Self {
+            provider,
+            committees: Arc::new(RwLock::new(BTreeMap::new())),
+            empty: Arc::new(StaticCommittee::new(vec![], vec![])),
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
+        }
+    }
+
+    /// The committee of `epoch`.
+    ///
+    /// Until the provider knows the tables of `epoch`, the committee is empty, so nothing can be
+    /// certified in it. The empty committee is not cached, so the provider is asked again later.
+    fn committee(&self, epoch: T::Epoch) -> &StaticCommittee<T> {
+        if let Some(committee) = self.committees.read().get(&epoch).copied() {
+            return committee;
+        }
+
+        let Some((stake_table, da_stake_table)) = self.provider.stake_tables(epoch) else {
+            return &self.empty;
+        };
+
+        *self.committees.write().entry(epoch).or_insert_with(|| {
+            Box::leak(Box::new(
+                StaticCommittee::new(stake_table, da_stake_table)
+                    .with_jail(self.jail.clone())
+                    .with_fallback_leaders(self.fallback_leaders.clone()),
+            ))
+        })
+    }
+}
+
+impl<TYPES: NodeType> Membership<TYPES> for ProvidedCommittee<TYPES> {
+    type Error = ConsensusError<TYPES>;
+
+    /// Create an election with the same stake tables in every epoch
+    fn new(
+        committee_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+        da_members: Vec<PeerConfig<<TYPES as NodeType>::SignatureKey>>,
+    ) -> Self {
+        Self::with_provider(Arc::new(StaticStakeTables::new(
+            committee_members,
+            da_members,
+        )))
+    }
+
+    /// Get the stake table for the epoch
+    fn stake_table(
+        &self,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        self.committee(epoch).stake_table(epoch)
+    }
+
+    /// Get the DA stake table for the epoch
+    fn da_stake_table(
+        &self,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        self.committee(epoch).da_stake_table(epoch)
+    }
+
+    /// Get all members of the committee for the epoch
+    fn committee_members(
+        &self,
+        view_number: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.committee(epoch).committee_members(view_number, epoch)
+    }
+
+    /// Get all members of the DA committee for the epoch
+    fn da_committee_members(
+        &self,
+        view_number: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.committee(epoch)
+            .da_committee_members(view_number, epoch)
+    }
+
+    /// Get all eligible leaders of the committee for the epoch
+    fn committee_leaders(
+        &self,
+        view_number: <TYPES as NodeType>::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
+        self.committee(epoch).committee_leaders(view_number, epoch)
+    }
+
+    /// Get the stake table entry for a public key
+    fn stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.committee(epoch).stake(pub_key, epoch)
+    }
+
+    /// Get the DA stake table entry for a public key
+    fn da_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+        self.committee(epoch).da_stake(pub_key, epoch)
+    }
+
+    /// Check if a node has stake in the committee
+    fn has_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.committee(epoch).has_stake(pub_key, epoch)
+    }
+
+    /// Check if a node has stake in the DA committee
+    fn has_da_stake(
+        &self,
+        pub_key: &<TYPES as NodeType>::SignatureKey,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> bool {
+        self.committee(epoch).has_da_stake(pub_key, epoch)
+    }
+
+    /// Index the epoch's leaders with the view number, as the static committee does
+    fn lookup_leader(
+        &self,
+        view_number: TYPES::View,
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        let committee = self.committee(epoch);
+        if committee.total_nodes(epoch) == 0 {
+            return Err(ConsensusError::MissingStakeTable(epoch));
+        }
+
+        committee.lookup_leader(view_number, epoch)
+    }
+
+    /// Skip `jailed` in the rotation of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
+
+    /// Let `leader` lead `view` rather than its turn in the rotation
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
+    }
+
+    /// Get the total number of nodes in the committee
+    fn total_nodes(&self, epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.committee(epoch).total_nodes(epoch)
+    }
+
+    /// Get the total number of DA nodes in the committee
+    fn da_total_nodes(&self, epoch: <TYPES as NodeType>::Epoch) -> usize {
+        self.committee(epoch).da_total_nodes(epoch)
+    }
+
+    /// Get the voting success threshold for the committee
+    fn success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
+        self.committee(epoch).success_threshold(epoch)
+    }
+
+    /// Get the voting success threshold for the DA committee
+    fn da_success_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
+        self.committee(epoch).da_success_threshold(epoch)
+    }
+
+    /// Get the voting failure threshold for the committee
+    fn failure_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
+        self.committee(epoch).failure_threshold(epoch)
+    }
+
+    /// Get the voting upgrade threshold for the committee
+    fn upgrade_threshold(&self, epoch: <TYPES as NodeType>::Epoch) -> NonZeroU64 {
+        self.committee(epoch).upgrade_threshold(epoch)
+    }
+}
@@ -4,9 +4,16 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::{
+    borrow::Cow,
+    cmp::max,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU64,
+};
 
 use hotshot_types::{
+    drb::DrbResult,
+    error::ConsensusError,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -15,10 +22,10 @@ use hotshot_types::{
     PeerConfig,
 };
 use primitive_types::U256;
-use rand::{rngs::StdRng, Rng};
-use utils::anytrace::Result;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+use crate::traits::election::helpers::{leader_index, DrbResults, FallbackLeaders, Jail};
+
+#[derive(Clone, Debug)]
 
 /// The static committee election
 
@@ -41,10 +48,19 @@ pub struct RandomizedCommittee<T: NodeType> {
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The DRB results which seed leader selection in each epoch
+    drb_results: DrbResults<T>,
+
+    /// The validators kept from leading in each epoch
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback
+    fallback_leaders: FallbackLeaders<T>,
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for RandomizedCommittee<TYPES> {
-    type Error = utils::anytrace::Error;
+    type Error = ConsensusError<TYPES>;
 
     /// Create a new election
     fn new(
@@ -98,6 +114,9 @@ impl<TYPES: NodeType> Membership<TYPES> for RandomizedCommittee<TYPES> {
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            drb_results: DrbResults::default(),
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
         }
     }
 
@@ -105,16 +124,16 @@ impl<TYPES: NodeType> Membership<TYPES> for RandomizedCommittee<TYPES> {
     fn stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.stake_table)
     }
 
     /// Get the stake table for the current view
     fn da_stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.da_stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.da_stake_table)
     }
 
     /// Get all members of the committee for the current view
@@ -204,19 +223,41 @@ impl<TYPES: NodeType> Membership<TYPES> for RandomizedCommittee<TYPES> {
     fn lookup_leader(
         &self,
         view_number: TYPES::View,
-        _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Result<TYPES::SignatureKey> {
-        let mut rng: StdRng = rand::SeedableRng::seed_from_u64(*view_number);
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        if let Some(leader) = self.fallback_leaders.get(view_number) {
+            return Ok(leader);
+        }
+
+        let drb_result = self.drb_results.get(epoch)?;
 
-        let randomized_view_number: u64 = rng.gen_range(0..=u64::MAX);
-        #[allow(clippy::cast_possible_truncation)]
-        let index = randomized_view_number as usize % self.eligible_leaders.len();
+        let eligible_leaders = self.jail.eligible(epoch, &self.eligible_leaders);
+        if eligible_leaders.is_empty() {
+            return Err(ConsensusError::NoEligibleLeaders(epoch));
+        }
+
+        let index = leader_index(&drb_result, *view_number, eligible_leaders.len());
 
-        let res = self.eligible_leaders[index].clone();
+        let res = eligible_leaders[index].clone();
 
         Ok(TYPES::SignatureKey::public_key(&res))
     }
 
+    /// Record the DRB result which seeds leader selection in `epoch`
+    fn add_drb_result(&self, epoch: TYPES::Epoch, drb_result: DrbResult) {
+        self.drb_results.insert(epoch, drb_result);
+    }
+
+    /// Pass over `jailed` when picking the leaders of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
+
+    /// Let `leader` lead `view` rather than the node the DRB result picks
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
+    }
+
     /// Get the total number of nodes in the committee
     fn total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
         self.stake_table.len()
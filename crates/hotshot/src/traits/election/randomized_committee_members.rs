@@ -5,6 +5,7 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
+    borrow::Cow,
     cmp::max,
     collections::{BTreeMap, BTreeSet},
     marker::PhantomData,
@@ -12,6 +13,8 @@ use std::{
 };
 
 use hotshot_types::{
+    drb::DrbResult,
+    error::ConsensusError,
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
@@ -20,12 +23,12 @@ use hotshot_types::{
     PeerConfig,
 };
 use primitive_types::U256;
-use rand::{rngs::StdRng, Rng};
-use utils::anytrace::Result;
 
-use crate::traits::election::helpers::QuorumFilterConfig;
+use crate::traits::election::helpers::{
+    leader_index, DrbResults, FallbackLeaders, Jail, QuorumFilterConfig,
+};
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug)]
 /// The static committee election
 pub struct RandomizedCommitteeMembers<T: NodeType, C: QuorumFilterConfig> {
     /// The nodes eligible for leadership.
@@ -47,6 +50,15 @@ pub struct RandomizedCommitteeMembers<T: NodeType, C: QuorumFilterConfig> {
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
 
+    /// The DRB results which seed leader selection in each epoch
+    drb_results: DrbResults<T>,
+
+    /// The validators kept from leading in each epoch
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback
+    fallback_leaders: FallbackLeaders<T>,
+
     /// Phantom
     _pd: PhantomData<C>,
 }
@@ -57,6 +69,19 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> RandomizedCommitteeMembers<TYP
         CONFIG::execute(epoch.u64(), self.stake_table.len())
     }
 
+    /// Whether `pub_key` is one of the entries of `table` picked out by `filter`.
+    ///
+    /// Only looks at the picked entries, rather than collecting their keys first.
+    fn selected(
+        table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+        filter: &BTreeSet<usize>,
+        pub_key: &TYPES::SignatureKey,
+    ) -> bool {
+        filter
+            .iter()
+            .any(|idx| TYPES::SignatureKey::public_key(&table[*idx]) == *pub_key)
+    }
+
     /// Creates a set of indices into the da_stake_table which reference the nodes selected for this epoch's da committee
     fn make_da_quorum_filter(&self, epoch: <TYPES as NodeType>::Epoch) -> BTreeSet<usize> {
         CONFIG::execute(epoch.u64(), self.da_stake_table.len())
@@ -66,7 +91,7 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> RandomizedCommitteeMembers<TYP
 impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
     for RandomizedCommitteeMembers<TYPES, CONFIG>
 {
-    type Error = utils::anytrace::Error;
+    type Error = ConsensusError<TYPES>;
 
     /// Create a new election
     fn new(
@@ -120,6 +145,9 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            drb_results: DrbResults::default(),
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
             _pd: PhantomData,
         }
     }
@@ -128,30 +156,28 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
     fn stake_table(
         &self,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
         let filter = self.make_quorum_filter(epoch);
-        //self.stake_table.clone()s
-        self.stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| v.clone())
-            .collect()
+        Cow::Owned(
+            filter
+                .iter()
+                .map(|member| self.stake_table[*member].clone())
+                .collect(),
+        )
     }
 
     /// Get the da stake table for the current view
     fn da_stake_table(
         &self,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
         let filter = self.make_da_quorum_filter(epoch);
-        //self.stake_table.clone()s
-        self.da_stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| v.clone())
-            .collect()
+        Cow::Owned(
+            filter
+                .iter()
+                .map(|member| self.da_stake_table[*member].clone())
+                .collect(),
+        )
     }
 
     /// Get all members of the committee for the current view
@@ -199,16 +225,7 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
         pub_key: &<TYPES as NodeType>::SignatureKey,
         epoch: <TYPES as NodeType>::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        let filter = self.make_quorum_filter(epoch);
-        let actual_members: BTreeSet<_> = self
-            .stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| TYPES::SignatureKey::public_key(v))
-            .collect();
-
-        if actual_members.contains(pub_key) {
+        if Self::selected(&self.stake_table, &self.make_quorum_filter(epoch), pub_key) {
             // Only return the stake if it is above zero
             self.indexed_stake_table.get(pub_key).cloned()
         } else {
@@ -223,16 +240,11 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
         pub_key: &<TYPES as NodeType>::SignatureKey,
         epoch: <TYPES as NodeType>::Epoch,
     ) -> Option<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
-        let filter = self.make_da_quorum_filter(epoch);
-        let actual_members: BTreeSet<_> = self
-            .da_stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| TYPES::SignatureKey::public_key(v))
-            .collect();
-
-        if actual_members.contains(pub_key) {
+        if Self::selected(
+            &self.da_stake_table,
+            &self.make_da_quorum_filter(epoch),
+            pub_key,
+        ) {
             // Only return the stake if it is above zero
             self.indexed_da_stake_table.get(pub_key).cloned()
         } else {
@@ -247,16 +259,7 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
         pub_key: &<TYPES as NodeType>::SignatureKey,
         epoch: <TYPES as NodeType>::Epoch,
     ) -> bool {
-        let filter = self.make_quorum_filter(epoch);
-        let actual_members: BTreeSet<_> = self
-            .stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| TYPES::SignatureKey::public_key(v))
-            .collect();
-
-        if actual_members.contains(pub_key) {
+        if Self::selected(&self.stake_table, &self.make_quorum_filter(epoch), pub_key) {
             self.indexed_stake_table
                 .get(pub_key)
                 .is_some_and(|x| x.stake() > U256::zero())
@@ -272,16 +275,11 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
         pub_key: &<TYPES as NodeType>::SignatureKey,
         epoch: <TYPES as NodeType>::Epoch,
     ) -> bool {
-        let filter = self.make_da_quorum_filter(epoch);
-        let actual_members: BTreeSet<_> = self
-            .da_stake_table
-            .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| TYPES::SignatureKey::public_key(v))
-            .collect();
-
-        if actual_members.contains(pub_key) {
+        if Self::selected(
+            &self.da_stake_table,
+            &self.make_da_quorum_filter(epoch),
+            pub_key,
+        ) {
             self.indexed_da_stake_table
                 .get(pub_key)
                 .is_some_and(|x| x.stake() > U256::zero())
@@ -296,25 +294,41 @@ impl<TYPES: NodeType, CONFIG: QuorumFilterConfig> Membership<TYPES>
         &self,
         view_number: TYPES::View,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Result<TYPES::SignatureKey> {
-        let filter = self.make_quorum_filter(epoch);
-        let leader_vec: Vec<_> = self
-            .stake_table
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        if let Some(leader) = self.fallback_leaders.get(view_number) {
+            return Ok(leader);
+        }
+
+        // The filter is ordered like the stake table, so the members keep its order
+        let members: Vec<_> = self
+            .make_quorum_filter(epoch)
             .iter()
-            .enumerate()
-            .filter(|(idx, _)| filter.contains(idx))
-            .map(|(_, v)| v.clone())
+            .map(|idx| self.stake_table[*idx].clone())
             .collect();
+        let eligible_leaders = self.jail.eligible(epoch, &members);
+        if eligible_leaders.is_empty() {
+            return Err(ConsensusError::NoEligibleLeaders(epoch));
+        }
 
-        let mut rng: StdRng = rand::SeedableRng::seed_from_u64(*view_number);
+        let drb_result = self.drb_results.get(epoch)?;
+        let index = leader_index(&drb_result, *view_number, eligible_leaders.len());
 
-        let randomized_view_number: u64 = rng.gen_range(0..=u64::MAX);
-        #[allow(clippy::cast_possible_truncation)]
-        let index = randomized_view_number as usize % leader_vec.len();
+        Ok(TYPES::SignatureKey::public_key(&eligible_leaders[index]))
+    }
 
-        let res = leader_vec[index].clone();
+    /// Record the DRB result which seeds leader selection in `epoch`
+    fn add_drb_result(&self, epoch: TYPES::Epoch, drb_result: DrbResult) {
+        self.drb_results.insert(epoch, drb_result);
+    }
+
+    /// Pass over `jailed` when picking the leaders of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
 
-        Ok(TYPES::SignatureKey::public_key(&res))
+    /// Let `leader` lead `view` rather than the node the DRB result picks
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
     }
 
     /// Get the total number of nodes in the committee
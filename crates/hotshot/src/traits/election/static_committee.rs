@@ -4,9 +4,15 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::{
+    borrow::Cow,
+    cmp::max,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU64,
+};
 
 use hotshot_types::{
+    error::ConsensusError,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -15,10 +21,15 @@ use hotshot_types::{
     PeerConfig,
 };
 use primitive_types::U256;
-use utils::anytrace::Result;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+use crate::traits::election::helpers::{FallbackLeaders, Jail};
+
+#[derive(Clone, Debug)]
 /// The static committee election
+///
+/// This is the fixed-weight mode: every member of the stake table votes in every view, weighted
+/// by its stake, and leaders take turns. Nothing is sampled, so the committee is public, but its
+/// quorum size is known in advance.
 pub struct StaticCommittee<T: NodeType> {
     /// The nodes eligible for leadership.
     /// NOTE: This is currently a hack because the DA leader needs to be the quorum
@@ -38,10 +49,31 @@ pub struct StaticCommittee<T: NodeType> {
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The validators kept from leading in each epoch
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback
+    fallback_leaders: FallbackLeaders<T>,
+}
+
+impl<T: NodeType> StaticCommittee<T> {
+    /// Share `jail` with another membership, which passes its jailing on to this one.
+    pub(crate) fn with_jail(mut self, jail: Jail<T>) -> Self {
+        self.jail = jail;
+        self
+    }
+
+    /// Share `fallback_leaders` with another membership, which passes the leaders the fallback
+    /// elects on to this one.
+    pub(crate) fn with_fallback_leaders(mut self, fallback_leaders: FallbackLeaders<T>) -> Self {
+        self.fallback_leaders = fallback_leaders;
+        self
+    }
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
-    type Error = utils::anytrace::Error;
+    type Error = ConsensusError<TYPES>;
 
     /// Create a new election
     fn new(
@@ -95,6 +127,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
         }
     }
 
@@ -102,16 +136,16 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     fn stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.stake_table)
     }
 
     /// Get the stake table for the current view
     fn da_stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.da_stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.da_stake_table)
     }
 
     /// Get all members of the committee for the current view
@@ -196,14 +230,32 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommittee<TYPES> {
     fn lookup_leader(
         &self,
         view_number: TYPES::View,
-        _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Result<TYPES::SignatureKey> {
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        if let Some(leader) = self.fallback_leaders.get(view_number) {
+            return Ok(leader);
+        }
+
+        let eligible_leaders = self.jail.eligible(epoch, &self.eligible_leaders);
+        if eligible_leaders.is_empty() {
+            return Err(ConsensusError::NoEligibleLeaders(epoch));
+        }
         #[allow(clippy::cast_possible_truncation)]
-        let index = *view_number as usize % self.eligible_leaders.len();
-        let res = self.eligible_leaders[index].clone();
+        let index = *view_number as usize % eligible_leaders.len();
+        let res = eligible_leaders[index].clone();
         Ok(TYPES::SignatureKey::public_key(&res))
     }
 
+    /// Skip `jailed` in the rotation of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
+
+    /// Let `leader` lead `view` rather than its turn in the rotation
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
+    }
+
     /// Get the total number of nodes in the committee
     fn total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
         self.stake_table.len()
@@ -4,9 +4,14 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::BTreeMap, num::NonZeroU64};
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU64,
+};
 
 use hotshot_types::{
+    error::ConsensusError,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -15,9 +20,10 @@ use hotshot_types::{
     PeerConfig,
 };
 use primitive_types::U256;
-use utils::anytrace::Result;
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+use crate::traits::election::helpers::{FallbackLeaders, Jail};
+
+#[derive(Clone, Debug)]
 
 /// The static committee election
 pub struct StaticCommitteeLeaderForTwoViews<T: NodeType> {
@@ -39,10 +45,16 @@ pub struct StaticCommitteeLeaderForTwoViews<T: NodeType> {
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table:
         BTreeMap<T::SignatureKey, <T::SignatureKey as SignatureKey>::StakeTableEntry>,
+
+    /// The validators kept from leading in each epoch
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback
+    fallback_leaders: FallbackLeaders<T>,
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYPES> {
-    type Error = utils::anytrace::Error;
+    type Error = ConsensusError<TYPES>;
 
     /// Create a new election
     fn new(
@@ -96,6 +108,8 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
             da_stake_table: da_members,
             indexed_stake_table,
             indexed_da_stake_table,
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
         }
     }
 
@@ -103,16 +117,16 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
     fn stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.stake_table)
     }
 
     /// Get the stake table for the current view
     fn da_stake_table(
         &self,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
-        self.da_stake_table.clone()
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
+        Cow::Borrowed(&self.da_stake_table)
     }
 
     /// Get all members of the committee for the current view
@@ -120,7 +134,7 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
         &self,
         _view_number: <TYPES as NodeType>::View,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         self.stake_table
             .iter()
             .map(TYPES::SignatureKey::public_key)
@@ -132,7 +146,7 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
         &self,
         _view_number: <TYPES as NodeType>::View,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         self.da_stake_table
             .iter()
             .map(TYPES::SignatureKey::public_key)
@@ -144,7 +158,7 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
         &self,
         _view_number: <TYPES as NodeType>::View,
         _epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         self.eligible_leaders
             .iter()
             .map(TYPES::SignatureKey::public_key)
@@ -197,15 +211,32 @@ impl<TYPES: NodeType> Membership<TYPES> for StaticCommitteeLeaderForTwoViews<TYP
     fn lookup_leader(
         &self,
         view_number: TYPES::View,
-        _epoch: <TYPES as NodeType>::Epoch,
-    ) -> Result<TYPES::SignatureKey> {
-        let index =
-            usize::try_from((*view_number / 2) % self.eligible_leaders.len() as u64).unwrap();
-        let res = self.eligible_leaders[index].clone();
+        epoch: <TYPES as NodeType>::Epoch,
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        if let Some(leader) = self.fallback_leaders.get(view_number) {
+            return Ok(leader);
+        }
+
+        let eligible_leaders = self.jail.eligible(epoch, &self.eligible_leaders);
+        if eligible_leaders.is_empty() {
+            return Err(ConsensusError::NoEligibleLeaders(epoch));
+        }
+        let index = usize::try_from((*view_number / 2) % eligible_leaders.len() as u64).unwrap();
+        let res = eligible_leaders[index].clone();
 
         Ok(TYPES::SignatureKey::public_key(&res))
     }
 
+    /// Skip `jailed` in the rotation of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
+
+    /// Let `leader` lead `view` rather than its turn in the rotation
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
+    }
+
     /// Get the total number of nodes in the committee
     fn total_nodes(&self, _epoch: <TYPES as NodeType>::Epoch) -> usize {
         self.stake_table.len()
@@ -0,0 +1,248 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Golden test vectors for the randomness of elections.
+//!
+//! Every node must derive the same DRB results, leaders and quorums from the same inputs, or the
+//! network forks. [`ElectionVectors::generate`] evaluates the election functions on fixed inputs,
+//! and the vectors generated when the functions were last changed on purpose are committed in
+//! `test_vectors.json`, returned by [`ElectionVectors::committed`]. A new version, or another
+//! implementation, is deterministic with this one if it reproduces the committed vectors, which
+//! [`ElectionVectors::verify`] checks.
+
+use std::collections::BTreeSet;
+
+use anyhow::{ensure, Result};
+use hotshot_types::{
+    drb::{compute_drb_result, DrbResult, DrbSeedInput},
+    traits::node_implementation::NodeType,
+};
+use serde::{Deserialize, Serialize};
+
+use super::helpers::{leader_index, random_overlap_quorum_filter, stable_quorum_filter};
+
+/// The committed vectors.
+const COMMITTED: &str = include_str!("test_vectors.json");
+
+/// A DRB result and the seed it is computed from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DrbVector {
+    /// Beacon output the DRB computation starts from
+    #[serde(with = "hex_bytes")]
+    pub seed_input: DrbSeedInput,
+    /// The DRB result
+    #[serde(with = "hex_bytes")]
+    pub result: DrbResult,
+}
+
+/// The leader of a view, as an index among the eligible leaders.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeaderVector {
+    /// DRB result of the view's epoch
+    #[serde(with = "hex_bytes")]
+    pub drb_result: DrbResult,
+    /// The view
+    pub view: u64,
+    /// Number of eligible leaders
+    pub candidates: usize,
+    /// Index of the leader
+    pub index: usize,
+}
+
+/// A quorum picked by [`stable_quorum_filter`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StableQuorumVector {
+    /// Seed of the filter
+    pub seed: u64,
+    /// Round, usually the epoch
+    pub round: u64,
+    /// Size of the stake table
+    pub count: usize,
+    /// Members carried over from the previous round
+    pub overlap: u64,
+    /// Indices of the members of the quorum
+    pub members: BTreeSet<usize>,
+}
+
+/// A quorum picked by [`random_overlap_quorum_filter`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RandomOverlapQuorumVector {
+    /// Seed of the filter
+    pub seed: u64,
+    /// Round, usually the epoch
+    pub round: u64,
+    /// Size of the stake table
+    pub count: usize,
+    /// Fewest members of the quorum
+    pub members_min: u64,
+    /// Most members of the quorum
+    pub members_max: u64,
+    /// Fewest members carried over from the previous round
+    pub overlap_min: u64,
+    /// Most members carried over from the previous round
+    pub overlap_max: u64,
+    /// Indices of the members of the quorum
+    pub members: BTreeSet<usize>,
+}
+
+/// Outputs of the election functions on fixed inputs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ElectionVectors {
+    /// Results of [`compute_drb_result`]
+    pub drb: Vec<DrbVector>,
+    /// Results of [`leader_index`]
+    pub leaders: Vec<LeaderVector>,
+    /// Results of [`stable_quorum_filter`]
+    pub stable_quorums: Vec<StableQuorumVector>,
+    /// Results of [`random_overlap_quorum_filter`]
+    pub random_overlap_quorums: Vec<RandomOverlapQuorumVector>,
+}
+
+impl ElectionVectors {
+    /// Evaluate the election functions on the fixed inputs.
+    #[must_use]
+    pub fn generate<TYPES: NodeType>() -> Self {
+        let mut counting = [0u8; 32];
+        for (byte, i) in counting.iter_mut().zip(0..) {
+            *byte = i;
+        }
+        let drb = [[0; 32], [1; 32], counting, [0xff; 32]]
+            .into_iter()
+            .map(|seed_input| DrbVector {
+                seed_input,
+                result: compute_drb_result::<TYPES>(seed_input),
+            })
+            .collect();
+
+        let mut leaders = vec![];
+        for drb_result in [[0; 32], [1; 32], counting] {
+            for view in [0, 1, 100, u64::MAX] {
+                for candidates in [1, 7, 100] {
+                    leaders.push(LeaderVector {
+                        drb_result,
+                        view,
+                        candidates,
+                        index: leader_index(&drb_result, view, candidates),
+                    });
+                }
+            }
+        }
+
+        let mut stable_quorums = vec![];
+        let mut random_overlap_quorums = vec![];
+        for seed in [0, 12345] {
+            for round in [1, 2, 3] {
+                for count in [10, 25] {
+                    stable_quorums.push(StableQuorumVector {
+                        seed,
+                        round,
+                        count,
+                        overlap: 2,
+                        members: stable_quorum_filter(seed, round, count, 2),
+                    });
+                }
+                random_overlap_quorums.push(RandomOverlapQuorumVector {
+                    seed,
+                    round,
+                    count: 20,
+                    members_min: 5,
+                    members_max: 10,
+                    overlap_min: 2,
+                    overlap_max: 3,
+                    members: random_overlap_quorum_filter(seed, round, 20, 5, 10, 2, 3),
+                });
+            }
+        }
+
+        Self {
+            drb,
+            leaders,
+            stable_quorums,
+            random_overlap_quorums,
+        }
+    }
+
+    /// The vectors committed alongside this module.
+    ///
+    /// # Panics
+    /// If the committed file is malformed.
+    #[must_use]
+    pub fn committed() -> Self {
+        serde_json::from_str(COMMITTED).expect("Committed election vectors are well-formed")
+    }
+
+    /// Check that the election functions reproduce every output from its inputs.
+    ///
+    /// # Errors
+    /// On the first output which is not reproduced.
+    pub fn verify<TYPES: NodeType>(&self) -> Result<()> {
+        for vector in &self.drb {
+            ensure!(
+                compute_drb_result::<TYPES>(vector.seed_input) == vector.result,
+                "DRB result differs for {vector:?}"
+            );
+        }
+        for vector in &self.leaders {
+            ensure!(
+                leader_index(&vector.drb_result, vector.view, vector.candidates) == vector.index,
+                "Leader differs for {vector:?}"
+            );
+        }
+        for vector in &self.stable_quorums {
+            ensure!(
+                stable_quorum_filter(vector.seed, vector.round, vector.count, vector.overlap)
+                    == vector.members,
+                "Stable quorum differs for {vector:?}"
+            );
+        }
+        for vector in &self.random_overlap_quorums {
+            let members = random_overlap_quorum_filter(
+                vector.seed,
+                vector.round,
+                vector.count,
+                vector.members_min,
+                vector.members_max,
+                vector.overlap_min,
+                vector.overlap_max,
+            );
+            ensure!(
+                members == vector.members,
+                "Random overlap quorum differs for {vector:?}"
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Serialization of 32-byte values as hex strings, which stay readable in the committed file.
+mod hex_bytes {
+    use std::fmt::Write;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serialize `bytes` as a hex string.
+    pub fn serialize<S: Serializer>(bytes: &[u8; 32], serializer: S) -> Result<S::Ok, S::Error> {
+        let hex = bytes.iter().fold(String::new(), |mut hex, byte| {
+            let _ = write!(hex, "{byte:02x}");
+            hex
+        });
+        serializer.serialize_str(&hex)
+    }
+
+    /// Deserialize 32 bytes from a hex string.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 32], D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(D::Error::custom("expected 32 hex-encoded bytes"));
+        }
+        let mut bytes = [0; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+            let pair = std::str::from_utf8(pair).map_err(D::Error::custom)?;
+            *byte = u8::from_str_radix(pair, 16).map_err(D::Error::custom)?;
+        }
+        Ok(bytes)
+    }
+}
@@ -4,9 +4,15 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{cmp::max, collections::BTreeMap, num::NonZeroU64};
+use std::{
+    borrow::Cow,
+    cmp::max,
+    collections::{BTreeMap, BTreeSet},
+    num::NonZeroU64,
+};
 
 use hotshot_types::{
+    error::ConsensusError,
     traits::{
         election::Membership,
         node_implementation::NodeType,
@@ -15,7 +21,8 @@ use hotshot_types::{
     PeerConfig,
 };
 use primitive_types::U256;
-use utils::anytrace::Result;
+
+use crate::traits::election::helpers::{FallbackLeaders, Jail};
 
 /// Tuple type for eligible leaders
 type EligibleLeaders<T> = (
@@ -41,7 +48,7 @@ type IndexedStakeTables<T> = (
     >,
 );
 
-#[derive(Clone, Debug, Eq, PartialEq, Hash)]
+#[derive(Clone, Debug)]
 /// The static committee election
 pub struct TwoStaticCommittees<T: NodeType> {
     /// The nodes eligible for leadership.
@@ -60,10 +67,16 @@ pub struct TwoStaticCommittees<T: NodeType> {
 
     /// The nodes on the committee and their stake, indexed by public key
     indexed_da_stake_table: IndexedStakeTables<T>,
+
+    /// The validators kept from leading in each epoch
+    jail: Jail<T>,
+
+    /// The leaders elected by the coin fallback
+    fallback_leaders: FallbackLeaders<T>,
 }
 
 impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
-    type Error = utils::anytrace::Error;
+    type Error = ConsensusError<TYPES>;
 
     /// Create a new election
     fn new(
@@ -172,6 +185,8 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
             da_stake_table: (da_members1, da_members2),
             indexed_stake_table: (indexed_stake_table1, indexed_stake_table2),
             indexed_da_stake_table: (indexed_da_stake_table1, indexed_da_stake_table2),
+            jail: Jail::default(),
+            fallback_leaders: FallbackLeaders::default(),
         }
     }
 
@@ -179,11 +194,11 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
     fn stake_table(
         &self,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
         if *epoch != 0 && *epoch % 2 == 0 {
-            self.stake_table.0.clone()
+            Cow::Borrowed(&self.stake_table.0)
         } else {
-            self.stake_table.1.clone()
+            Cow::Borrowed(&self.stake_table.1)
         }
     }
 
@@ -191,11 +206,11 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
     fn da_stake_table(
         &self,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Vec<<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<<TYPES as NodeType>::SignatureKey as SignatureKey>::StakeTableEntry]> {
         if *epoch != 0 && *epoch % 2 == 0 {
-            self.da_stake_table.0.clone()
+            Cow::Borrowed(&self.da_stake_table.0)
         } else {
-            self.da_stake_table.1.clone()
+            Cow::Borrowed(&self.da_stake_table.1)
         }
     }
 
@@ -204,7 +219,7 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
         &self,
         _view_number: <TYPES as NodeType>::View,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         if *epoch != 0 && *epoch % 2 == 0 {
             self.stake_table
                 .0
@@ -225,7 +240,7 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
         &self,
         _view_number: <TYPES as NodeType>::View,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         if *epoch != 0 && *epoch % 2 == 0 {
             self.da_stake_table
                 .0
@@ -246,7 +261,7 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
         &self,
         _view_number: <TYPES as NodeType>::View,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> std::collections::BTreeSet<<TYPES as NodeType>::SignatureKey> {
+    ) -> BTreeSet<<TYPES as NodeType>::SignatureKey> {
         if *epoch != 0 && *epoch % 2 == 0 {
             self.eligible_leaders
                 .0
@@ -333,18 +348,35 @@ impl<TYPES: NodeType> Membership<TYPES> for TwoStaticCommittees<TYPES> {
         &self,
         view_number: TYPES::View,
         epoch: <TYPES as NodeType>::Epoch,
-    ) -> Result<TYPES::SignatureKey> {
-        if *epoch != 0 && *epoch % 2 == 0 {
-            #[allow(clippy::cast_possible_truncation)]
-            let index = *view_number as usize % self.eligible_leaders.0.len();
-            let res = self.eligible_leaders.0[index].clone();
-            Ok(TYPES::SignatureKey::public_key(&res))
+    ) -> Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        if let Some(leader) = self.fallback_leaders.get(view_number) {
+            return Ok(leader);
+        }
+
+        let eligible_leaders = if *epoch != 0 && *epoch % 2 == 0 {
+            &self.eligible_leaders.0
         } else {
-            #[allow(clippy::cast_possible_truncation)]
-            let index = *view_number as usize % self.eligible_leaders.1.len();
-            let res = self.eligible_leaders.1[index].clone();
-            Ok(TYPES::SignatureKey::public_key(&res))
+            &self.eligible_leaders.1
+        };
+        let eligible_leaders = self.jail.eligible(epoch, eligible_leaders);
+        if eligible_leaders.is_empty() {
+            return Err(ConsensusError::NoEligibleLeaders(epoch));
         }
+
+        #[allow(clippy::cast_possible_truncation)]
+        let index = *view_number as usize % eligible_leaders.len();
+        let res = eligible_leaders[index].clone();
+        Ok(TYPES::SignatureKey::public_key(&res))
+    }
+
+    /// Skip `jailed` in the rotation of `epoch`
+    fn set_jailed(&self, epoch: TYPES::Epoch, jailed: BTreeSet<TYPES::SignatureKey>) {
+        self.jail.set(epoch, jailed);
+    }
+
+    /// Let `leader` lead `view` rather than its turn in the rotation
+    fn set_fallback_leader(&self, view: TYPES::View, leader: TYPES::SignatureKey) {
+        self.fallback_leaders.set(view, leader);
     }
 
     /// Get the total number of nodes in the committee
@@ -11,9 +11,11 @@
 //! - [`MemoryNetwork`](memory_network::MemoryNetwork), an in memory testing-only implementation
 //! - [`Libp2pNetwork`](libp2p_network::Libp2pNetwork), a production-ready networking implementation built on top of libp2p-rs.
 
+pub mod batching_network;
 pub mod combined_network;
 pub mod libp2p_network;
 pub mod memory_network;
+pub mod noise_network;
 /// The Push CDN network
 pub mod push_cdn_network;
 
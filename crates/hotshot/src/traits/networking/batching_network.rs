@@ -0,0 +1,336 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Networking implementation which coalesces small direct messages to the same peer.
+//!
+//! At high vote rates a node sends many small messages (votes, acks) to the same few peers, each
+//! paying for its own send and framing. [`BatchingNetwork`] wraps another network and holds the
+//! direct messages to a peer for a short window, sending all those which arrived in the meantime
+//! as one frame. On receipt, frames are split back into the messages they hold, so that consensus
+//! sees the same messages it would without batching.
+//!
+//! Every message a [`BatchingNetwork`] sends is framed, batched or not, so all nodes of a network
+//! must wrap it alike.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use hotshot_types::{
+    boxed_sync,
+    constants::{BATCHING_NETWORK_MAX_BATCH_SIZE, BATCHING_NETWORK_WINDOW},
+    data::ViewNumber,
+    gossip_fanout::MessageClass,
+    traits::{
+        network::{BroadcastDelay, ConnectedNetwork, PeerStatus, Topic},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+    BoxSyncFuture,
+};
+use parking_lot::Mutex;
+use tokio::{spawn, sync::mpsc::error::TrySendError, time::sleep};
+use tracing::warn;
+
+use super::NetworkError;
+
+/// Tag of a frame holding a single message, as it was sent
+const FRAME_SINGLE: u8 = 0;
+
+/// Tag of a frame holding a batch of messages, each preceded by its length
+const FRAME_BATCH: u8 = 1;
+
+/// Size of the length preceding each message of a batch
+const LENGTH_PREFIX: usize = 4;
+
+/// Direct messages to a peer waiting to be sent
+#[derive(Debug, Default)]
+struct PendingBatch {
+    /// The messages, in the order they were sent
+    messages: Vec<Bytes>,
+    /// Size of the batch frame holding the messages
+    size: usize,
+}
+
+impl PendingBatch {
+    /// Add `message` to the batch.
+    fn push(&mut self, message: Bytes) {
+        if self.messages.is_empty() {
+            self.size = 1;
+        }
+        self.size += LENGTH_PREFIX + message.len();
+        self.messages.push(message);
+    }
+
+    /// The frame holding the messages of the batch.
+    fn into_frame(mut self) -> Result<Bytes, NetworkError> {
+        if self.messages.len() == 1 {
+            return Ok(single_frame(&self.messages.remove(0)));
+        }
+        let mut frame = BytesMut::with_capacity(self.size);
+        frame.put_u8(FRAME_BATCH);
+        for message in self.messages {
+            let len = u32::try_from(message.len()).map_err(|_| {
+                NetworkError::FailedToSerialize("Message too large to batch".to_string())
+            })?;
+            frame.put_u32(len);
+            frame.put_slice(&message);
+        }
+        Ok(frame.freeze())
+    }
+}
+
+/// The frame holding `message` alone.
+fn single_frame(message: &[u8]) -> Bytes {
+    let mut frame = BytesMut::with_capacity(1 + message.len());
+    frame.put_u8(FRAME_SINGLE);
+    frame.put_slice(message);
+    frame.freeze()
+}
+
+/// Split a frame sent by a [`BatchingNetwork`] into the messages it holds, without copying them.
+///
+/// # Errors
+/// If the frame is malformed.
+pub fn unbatch(frame: Bytes) -> Result<Vec<Bytes>, NetworkError> {
+    let malformed = || NetworkError::FailedToDeserialize("Malformed message batch".to_string());
+    match frame.first() {
+        Some(&FRAME_SINGLE) => Ok(vec![frame.slice(1..)]),
+        Some(&FRAME_BATCH) => {
+            let mut messages = vec![];
+            let mut offset = 1;
+            while offset < frame.len() {
+                let prefix = frame
+                    .get(offset..offset + LENGTH_PREFIX)
+                    .ok_or_else(malformed)?;
+                let len = u32::from_be_bytes(prefix.try_into().map_err(|_| malformed())?);
+                let start = offset + LENGTH_PREFIX;
+                let end = usize::try_from(len)
+                    .ok()
+                    .and_then(|len| start.checked_add(len))
+                    .filter(|end| *end <= frame.len())
+                    .ok_or_else(malformed)?;
+                messages.push(frame.slice(start..end));
+                offset = end;
+            }
+            Ok(messages)
+        }
+        _ => Err(malformed()),
+    }
+}
+
+/// A network which batches the direct messages it sends over another network.
+#[derive(Clone)]
+pub struct BatchingNetwork<K: SignatureKey, N> {
+    /// The network the frames are sent over
+    network: N,
+
+    /// How long the first message of a batch waits for others
+    window: Duration,
+
+    /// Largest size of a batch frame. Messages too large to fit one are sent on their own.
+    max_batch_size: usize,
+
+    /// Direct messages waiting to be sent, by recipient
+    pending: Arc<Mutex<HashMap<K, PendingBatch>>>,
+
+    /// Messages of received batches not yet returned by `recv_message`
+    received: Arc<Mutex<VecDeque<Bytes>>>,
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> BatchingNetwork<K, N> {
+    /// Batch the direct messages sent over `network`, holding them for at most `window` and
+    /// sending frames of at most `max_batch_size` bytes. A zero window disables batching.
+    #[must_use]
+    pub fn new(network: N, window: Option<Duration>, max_batch_size: Option<usize>) -> Self {
+        Self {
+            network,
+            window: window.unwrap_or(Duration::from_millis(BATCHING_NETWORK_WINDOW)),
+            max_batch_size: max_batch_size.unwrap_or(BATCHING_NETWORK_MAX_BATCH_SIZE),
+            pending: Arc::default(),
+            received: Arc::default(),
+        }
+    }
+
+    /// Get a ref to the network the frames are sent over
+    #[must_use]
+    pub fn inner(&self) -> &N {
+        &self.network
+    }
+
+    /// Send the messages waiting for `recipient`, if any.
+    async fn flush(&self, recipient: K) -> Result<(), NetworkError> {
+        let Some(batch) = self.pending.lock().remove(&recipient) else {
+            return Ok(());
+        };
+        self.network
+            .direct_message(batch.into_frame()?, recipient)
+            .await
+    }
+
+    /// Send the messages waiting for every recipient.
+    async fn flush_all(&self) {
+        let recipients: Vec<_> = self.pending.lock().keys().cloned().collect();
+        for recipient in recipients {
+            if let Err(e) = self.flush(recipient).await {
+                warn!("Failed to send message batch: {e}");
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> ConnectedNetwork<K>
+    for BatchingNetwork<K, N>
+{
+    fn pause(&self) {
+        self.network.pause();
+    }
+
+    fn resume(&self) {
+        self.network.resume();
+    }
+
+    async fn wait_for_ready(&self) {
+        self.network.wait_for_ready().await;
+    }
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        let closure = async move {
+            self.flush_all().await;
+            self.network.shut_down().await;
+        };
+        boxed_sync(closure)
+    }
+
+    async fn broadcast_message(
+        &self,
+        message: Bytes,
+        topic: Topic,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        self.network
+            .broadcast_message(single_frame(&message), topic, broadcast_delay)
+            .await
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        message: Bytes,
+        recipients: Vec<K>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        self.network
+            .da_broadcast_message(single_frame(&message), recipients, broadcast_delay)
+            .await
+    }
+
+    async fn vid_broadcast_message(&self, messages: HashMap<K, Bytes>) -> Result<(), NetworkError> {
+        let messages = messages
+            .into_iter()
+            .map(|(recipient, message)| (recipient, single_frame(&message)))
+            .collect();
+        self.network.vid_broadcast_message(messages).await
+    }
+
+    /// Queue a direct message to be sent along with the others to `recipient` within the window.
+    ///
+    /// # Errors
+    /// If a message sent right away fails to send. Failures to send a batch later are logged.
+    async fn direct_message(&self, message: Bytes, recipient: K) -> Result<(), NetworkError> {
+        if self.window.is_zero() || 1 + LENGTH_PREFIX + message.len() > self.max_batch_size {
+            return self
+                .network
+                .direct_message(single_frame(&message), recipient)
+                .await;
+        }
+
+        let (full, first) = {
+            let mut pending = self.pending.lock();
+            let batch = pending.entry(recipient.clone()).or_default();
+            // Send what is waiting first rather than exceed the largest size
+            let full = (batch.size + LENGTH_PREFIX + message.len() > self.max_batch_size)
+                .then(|| std::mem::take(batch));
+            let first = batch.messages.is_empty();
+            batch.push(message);
+            (full, first)
+        };
+
+        if first {
+            let network = self.clone();
+            let recipient = recipient.clone();
+            spawn(async move {
+                sleep(network.window).await;
+                if let Err(e) = network.flush(recipient).await {
+                    warn!("Failed to send message batch: {e}");
+                }
+            });
+        }
+        match full {
+            Some(batch) => {
+                self.network
+                    .direct_message(batch.into_frame()?, recipient)
+                    .await
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Receive one message, splitting the batches the underlying network receives.
+    ///
+    /// # Errors
+    /// If there is a network-related failure, or a malformed frame is received.
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
+        loop {
+            if let Some(message) = self.received.lock().pop_front() {
+                return Ok(message);
+            }
+            let messages = unbatch(self.network.recv_message().await?)?;
+            self.received.lock().extend(messages);
+        }
+    }
+
+    fn queue_node_lookup(
+        &self,
+        view_number: ViewNumber,
+        pk: K,
+    ) -> Result<(), TrySendError<Option<(ViewNumber, K)>>> {
+        self.network.queue_node_lookup(view_number, pk)
+    }
+
+    async fn update_view<'a, TYPES>(&'a self, view: u64, epoch: u64, membership: &TYPES::Membership)
+    where
+        TYPES: NodeType<SignatureKey = K> + 'a,
+    {
+        self.network
+            .update_view::<TYPES>(view, epoch, membership)
+            .await;
+    }
+
+    fn is_primary_down(&self) -> bool {
+        self.network.is_primary_down()
+    }
+
+    async fn connected_peers(&self) -> Option<usize> {
+        self.network.connected_peers().await
+    }
+
+    async fn peer_status(&self) -> Option<Vec<PeerStatus<K>>> {
+        self.network.peer_status().await
+    }
+
+    fn observe_propagation(&self, class: MessageClass, latency: Duration) {
+        self.network.observe_propagation(class, latency);
+    }
+}
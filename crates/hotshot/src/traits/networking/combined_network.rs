@@ -21,6 +21,7 @@ use std::{
 use async_broadcast::{broadcast, InactiveReceiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
+use bytes::Bytes;
 use futures::{join, select, FutureExt};
 #[cfg(feature = "hotshot-testing")]
 use hotshot_types::traits::network::{
@@ -33,8 +34,9 @@ use hotshot_types::{
         COMBINED_NETWORK_MIN_PRIMARY_FAILURES, COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL,
     },
     data::ViewNumber,
+    gossip_fanout::MessageClass,
     traits::{
-        network::{BroadcastDelay, ConnectedNetwork, Topic},
+        network::{BroadcastDelay, ConnectedNetwork, PeerStatus, Topic},
         node_implementation::NodeType,
     },
     BoxSyncFuture,
@@ -131,7 +133,6 @@ impl<TYPES: NodeType> CombinedNetworks<TYPES> {
     /// a helper function to send messages through both networks (possibly delayed)
     async fn send_both_networks(
         &self,
-        _message: Vec<u8>,
         primary_future: impl Future<Output = Result<(), NetworkError>> + Send + 'static,
         secondary_future: impl Future<Output = Result<(), NetworkError>> + Send + 'static,
         broadcast_delay: BroadcastDelay,
@@ -356,17 +357,16 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
 
     async fn broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         topic: Topic,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
         let primary = self.primary().clone();
         let secondary = self.secondary().clone();
         let primary_message = message.clone();
-        let secondary_message = message.clone();
+        let secondary_message = message;
         let topic_clone = topic.clone();
         self.send_both_networks(
-            message,
             async move {
                 primary
                     .broadcast_message(primary_message, topic_clone, BroadcastDelay::None)
@@ -384,17 +384,16 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
 
     async fn da_broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipients: Vec<TYPES::SignatureKey>,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
         let primary = self.primary().clone();
         let secondary = self.secondary().clone();
         let primary_message = message.clone();
-        let secondary_message = message.clone();
+        let secondary_message = message;
         let primary_recipients = recipients.clone();
         self.send_both_networks(
-            message,
             async move {
                 primary
                     .da_broadcast_message(primary_message, primary_recipients, BroadcastDelay::None)
@@ -412,16 +411,15 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
 
     async fn direct_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipient: TYPES::SignatureKey,
     ) -> Result<(), NetworkError> {
         let primary = self.primary().clone();
         let secondary = self.secondary().clone();
         let primary_message = message.clone();
-        let secondary_message = message.clone();
+        let secondary_message = message;
         let primary_recipient = recipient.clone();
         self.send_both_networks(
-            message,
             async move {
                 primary
                     .direct_message(primary_message, primary_recipient)
@@ -435,7 +433,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
 
     async fn vid_broadcast_message(
         &self,
-        messages: HashMap<TYPES::SignatureKey, Vec<u8>>,
+        messages: HashMap<TYPES::SignatureKey, Bytes>,
     ) -> Result<(), NetworkError> {
         self.networks.0.vid_broadcast_message(messages).await
     }
@@ -444,7 +442,7 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     ///
     /// # Errors
     /// Does not error
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
         loop {
             // Receive from both networks
             let mut primary_fut = self.primary().recv_message().fuse();
@@ -505,4 +503,18 @@ impl<TYPES: NodeType> ConnectedNetwork<TYPES::SignatureKey> for CombinedNetworks
     fn is_primary_down(&self) -> bool {
         self.primary_down.load(Ordering::Relaxed)
     }
+
+    async fn connected_peers(&self) -> Option<usize> {
+        // The primary network connects through a broker, so only the secondary knows its peers
+        self.networks.1.connected_peers().await
+    }
+
+    async fn peer_status(&self) -> Option<Vec<PeerStatus<TYPES::SignatureKey>>> {
+        self.networks.1.peer_status().await
+    }
+
+    fn observe_propagation(&self, class: MessageClass, latency: Duration) {
+        // Only the secondary network gossips
+        self.networks.1.observe_propagation(class, latency);
+    }
 }
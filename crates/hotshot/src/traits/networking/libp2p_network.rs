@@ -11,7 +11,7 @@
 use std::str::FromStr;
 use std::{
     cmp::min,
-    collections::{BTreeSet, HashSet},
+    collections::{BTreeSet, HashMap, HashSet},
     fmt::Debug,
     net::{IpAddr, ToSocketAddrs},
     num::NonZeroUsize,
@@ -26,6 +26,7 @@ use anyhow::{anyhow, Context};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use bimap::BiHashMap;
+use bytes::Bytes;
 use futures::future::join_all;
 #[cfg(feature = "hotshot-testing")]
 use hotshot_types::traits::network::{
@@ -35,11 +36,12 @@ use hotshot_types::{
     boxed_sync,
     constants::LOOK_AHEAD,
     data::ViewNumber,
+    gossip_fanout::{FanoutTuner, MessageClass},
     network::NetworkConfig,
     traits::{
         election::Membership,
         metrics::{Counter, Gauge, Metrics, NoMetrics},
-        network::{ConnectedNetwork, NetworkError, Topic},
+        network::{ConnectedNetwork, NetworkError, PeerStatus, Topic},
         node_implementation::{ConsensusTime, NodeType},
         signature_key::{PrivateSignatureKey, SignatureKey},
     },
@@ -54,13 +56,14 @@ use libp2p_networking::{
     network::{
         behaviours::dht::record::{Namespace, RecordKey, RecordValue},
         spawn_network_node,
-        transport::construct_auth_message,
+        transport::{AuthKey, NetworkInfo, PROTOCOL_VERSION},
         NetworkEvent::{self, DirectRequest, DirectResponse, GossipMsg},
         NetworkNodeConfig, NetworkNodeConfigBuilder, NetworkNodeHandle, NetworkNodeReceiver,
         DEFAULT_REPLICATION_FACTOR,
     },
     reexport::Multiaddr,
 };
+use parking_lot::Mutex as PlMutex;
 use rand::{rngs::StdRng, seq::IteratorRandom, SeedableRng};
 use serde::Serialize;
 use tokio::{
@@ -84,6 +87,8 @@ pub struct Libp2pMetricsValue {
     pub num_failed_messages: Box<dyn Counter>,
     /// Whether or not the network is considered ready
     pub is_ready: Box<dyn Gauge>,
+    /// The number of peers outside the mesh we send all gossip to
+    pub extra_gossip_fanout: Box<dyn Gauge>,
 }
 
 impl Libp2pMetricsValue {
@@ -97,6 +102,7 @@ impl Libp2pMetricsValue {
             num_connected_peers: subgroup.create_gauge("num_connected_peers".into(), None),
             num_failed_messages: subgroup.create_counter("num_failed_messages".into(), None),
             is_ready: subgroup.create_gauge("is_ready".into(), None),
+            extra_gossip_fanout: subgroup.create_gauge("extra_gossip_fanout".into(), None),
         }
     }
 }
@@ -147,9 +153,9 @@ struct Libp2pNetworkInner<T: NodeType> {
     /// handle to control the network
     handle: Arc<NetworkNodeHandle<T>>,
     /// Message Receiver
-    receiver: Mutex<Receiver<Vec<u8>>>,
+    receiver: Mutex<Receiver<Bytes>>,
     /// Sender for broadcast messages
-    sender: Sender<Vec<u8>>,
+    sender: Sender<Bytes>,
     /// Sender for node lookup (relevant view number, key of node) (None for shutdown)
     node_lookup_send: Sender<Option<(ViewNumber, T::SignatureKey)>>,
     /// this is really cheating to enable local tests
@@ -174,6 +180,10 @@ struct Libp2pNetworkInner<T: NodeType> {
     reliability_config: Option<Box<dyn NetworkReliability>>,
     /// Killswitch sender
     kill_switch: Sender<()>,
+    /// Staking keys of the peers we have looked up
+    peer_keys: Arc<RwLock<HashMap<PeerId, T::SignatureKey>>>,
+    /// Tuner of the gossip fanout, if it adapts to how late proposals arrive
+    fanout_tuner: Option<PlMutex<FanoutTuner>>,
 }
 
 /// Networking implementation that uses libp2p
@@ -278,6 +288,7 @@ impl<T: NodeType> TestableNetworkingImplementation<T> for Libp2pNetwork<T> {
                             lookup_record_value,
                             bootstrap_addrs_ref,
                             usize::try_from(node_id).unwrap(),
+                            None,
                             #[cfg(feature = "hotshot-testing")]
                             reliability_config_dup,
                         )
@@ -330,7 +341,8 @@ pub fn derive_libp2p_peer_id<K: SignatureKey>(
 }
 
 /// Parse a Libp2p Multiaddr from a string. The input string should be in the format
-/// `hostname:port` or `ip:port`. This function derives a `Multiaddr` from the input string.
+/// `hostname:port`, `ip:port` or `[ipv6]:port`. This function derives a `Multiaddr` from the input
+/// string.
 ///
 /// This borrows from Rust's implementation of `to_socket_addrs` but will only warn if the domain
 /// does not yet resolve.
@@ -344,6 +356,12 @@ pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
         None => return Err(anyhow!("Invalid address format, no port supplied")),
     };
 
+    // IPv6 hosts may be bracketed, to tell them apart from the port
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+
     // Try parsing the host as an IP address
     let ip = host.parse::<IpAddr>();
 
@@ -378,12 +396,27 @@ pub fn derive_libp2p_multiaddr(addr: &String) -> anyhow::Result<Multiaddr> {
     })
 }
 
+/// Parse a comma-separated list of addresses in the format of [`derive_libp2p_multiaddr`], such
+/// as `0.0.0.0:9000,[::]:9000` to listen on both IPv4 and IPv6.
+///
+/// # Errors
+/// - If any address in the list is not in the correct format
+pub fn derive_libp2p_multiaddrs(addrs: &str) -> anyhow::Result<Vec<Multiaddr>> {
+    addrs
+        .split(',')
+        .map(|addr| derive_libp2p_multiaddr(&addr.trim().to_string()))
+        .collect()
+}
+
 impl<T: NodeType> Libp2pNetwork<T> {
     /// Create and return a Libp2p network from a network config file
     /// and various other configuration-specific values.
     ///
+    /// We listen on each of `bind_addresses`, and advertise all of them to peers. The first one is
+    /// our main address.
+    ///
     /// # Errors
-    /// If we are unable to parse a Multiaddress
+    /// If we are unable to parse a Multiaddress, or no bind address is supplied
     ///
     /// # Panics
     /// If we are unable to calculate the replication factor
@@ -393,7 +426,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
         quorum_membership: T::Membership,
         gossip_config: GossipConfig,
         request_response_config: RequestResponseConfig,
-        bind_address: Multiaddr,
+        bind_addresses: Vec<Multiaddr>,
         pub_key: &T::SignatureKey,
         priv_key: &<T::SignatureKey as SignatureKey>::PrivateKey,
         metrics: Libp2pMetricsValue,
@@ -410,19 +443,37 @@ impl<T: NodeType> Libp2pNetwork<T> {
         // Build our libp2p configuration
         let mut config_builder = NetworkNodeConfigBuilder::default();
 
+        // Fit the gossip mesh to the size of the network
+        let num_nodes = config.config.num_nodes_with_stake.get();
+        let gossip_fanout = config.config.gossip_fanout;
+        let gossip_config = gossip_config.with_mesh(gossip_fanout.mesh(num_nodes));
+        let fanout_tuner = gossip_fanout
+            .adaptive
+            .then(|| FanoutTuner::new(&gossip_fanout, num_nodes));
+
         // Set the gossip configuration
         config_builder.gossip_config(gossip_config.clone());
         config_builder.request_response_config(request_response_config);
 
-        // Construct the auth message
-        let auth_message =
-            construct_auth_message(pub_key, &keypair.public().to_peer_id(), priv_key)
-                .with_context(|| "Failed to construct auth message")?;
-
-        // Set the auth message and stake table
+        // Set the key we prove possession of to peers, and the stake table we check theirs against
         config_builder
             .stake_table(Some(quorum_membership))
-            .auth_message(Some(auth_message));
+            .auth_key(Some(AuthKey {
+                public_key: pub_key.clone(),
+                private_key: priv_key.clone(),
+            }));
+
+        // Only connect to peers of the same chain, which started from the same stake tables
+        let genesis_stake_tables = bincode::serialize(&(
+            &config.config.known_nodes_with_stake,
+            &config.config.known_da_nodes,
+        ))
+        .with_context(|| "Failed to serialize genesis stake tables")?;
+        config_builder.network_info(NetworkInfo {
+            protocol_version: PROTOCOL_VERSION,
+            chain_id: config.config.chain_id,
+            genesis: *blake3::hash(&genesis_stake_tables).as_bytes(),
+        });
 
         // The replication factor is the minimum of [the default and 2/3 the number of nodes]
         let Some(default_replication_factor) = DEFAULT_REPLICATION_FACTOR else {
@@ -444,10 +495,14 @@ impl<T: NodeType> Libp2pNetwork<T> {
         )
         .with_context(|| "Failed to sign DHT lookup record")?;
 
+        let Some((bind_address, extra_bind_addresses)) = bind_addresses.split_first() else {
+            return Err(anyhow!("No bind address supplied"));
+        };
         config_builder
             .keypair(keypair)
             .replication_factor(replication_factor)
-            .bind_address(Some(bind_address.clone()));
+            .bind_address(Some(bind_address.clone()))
+            .extra_bind_addresses(extra_bind_addresses.to_vec());
 
         // Choose `mesh_n` random nodes to connect to for bootstrap
         let bootstrap_nodes = libp2p_config
@@ -474,6 +529,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
             lookup_record_value,
             Arc::new(RwLock::new(bootstrap_nodes)),
             usize::try_from(config.node_index)?,
+            fanout_tuner,
             #[cfg(feature = "hotshot-testing")]
             None,
         )
@@ -501,6 +557,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
     /// * `config`: the configuration of the node
     /// * `pk`: public key associated with the node
     /// * `bootstrap_addrs`: rwlock containing the bootstrap addrs
+    /// * `fanout_tuner`: tuner of the gossip fanout, if it adapts to how late proposals arrive
     /// # Errors
     /// Returns error in the event that the underlying libp2p network
     /// is unable to create a network.
@@ -516,16 +573,21 @@ impl<T: NodeType> Libp2pNetwork<T> {
         lookup_record_value: RecordValue<T::SignatureKey>,
         bootstrap_addrs: BootstrapAddrs,
         id: usize,
+        fanout_tuner: Option<FanoutTuner>,
         #[cfg(feature = "hotshot-testing")] reliability_config: Option<Box<dyn NetworkReliability>>,
     ) -> Result<Libp2pNetwork<T>, NetworkError> {
         let (mut rx, network_handle) = spawn_network_node::<T>(config.clone(), id)
             .await
             .map_err(|e| NetworkError::ConfigError(format!("failed to spawn network node: {e}")))?;
 
-        // Add our own address to the bootstrap addresses
-        let addr = network_handle.listen_addr();
+        // Add our own addresses to the bootstrap addresses
         let pid = network_handle.peer_id();
-        bootstrap_addrs.write().await.push((pid, addr));
+        bootstrap_addrs.write().await.extend(
+            network_handle
+                .listen_addrs()
+                .iter()
+                .map(|addr| (pid, addr.clone())),
+        );
 
         let mut pubkey_pid_map = BiHashMap::new();
         pubkey_pid_map.insert(pk.clone(), network_handle.peer_id());
@@ -561,6 +623,8 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 #[cfg(feature = "hotshot-testing")]
                 reliability_config,
                 kill_switch: kill_tx,
+                peer_keys: Arc::new(RwLock::new(HashMap::new())),
+                fanout_tuner: fanout_tuner.map(PlMutex::new),
             }),
         };
 
@@ -583,6 +647,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
         let handle = Arc::clone(&self.inner.handle);
         let dht_timeout = self.inner.dht_timeout;
         let latest_seen_view = Arc::clone(&self.inner.latest_seen_view);
+        let peer_keys = Arc::clone(&self.inner.peer_keys);
 
         // deals with handling lookup queue. should be infallible
         spawn(async move {
@@ -597,8 +662,13 @@ impl<T: NodeType> Libp2pNetwork<T> {
                 // only run if we are not too close to the next view number
                 if latest_seen_view.load(Ordering::Relaxed) + THRESHOLD <= *view_number {
                     // look up
-                    if let Err(err) = handle.lookup_node(&pk.to_bytes(), dht_timeout).await {
-                        warn!("Failed to perform lookup for key {:?}: {}", pk, err);
+                    match handle.lookup_node(&pk.to_bytes(), dht_timeout).await {
+                        Ok(pid) => {
+                            peer_keys.write().await.insert(pid, pk);
+                        }
+                        Err(err) => {
+                            warn!("Failed to perform lookup for key {:?}: {}", pk, err);
+                        }
                     };
                 }
             }
@@ -664,16 +734,16 @@ impl<T: NodeType> Libp2pNetwork<T> {
     fn handle_recvd_events(
         &self,
         msg: NetworkEvent,
-        sender: &Sender<Vec<u8>>,
+        sender: &Sender<Bytes>,
     ) -> Result<(), NetworkError> {
         match msg {
             GossipMsg(msg) => {
-                sender.try_send(msg).map_err(|err| {
+                sender.try_send(msg.into()).map_err(|err| {
                     NetworkError::ChannelSendError(format!("failed to send gossip message: {err}"))
                 })?;
             }
             DirectRequest(msg, _pid, chan) => {
-                sender.try_send(msg).map_err(|err| {
+                sender.try_send(msg.into()).map_err(|err| {
                     NetworkError::ChannelSendError(format!(
                         "failed to send direct request message: {err}"
                     ))
@@ -705,7 +775,7 @@ impl<T: NodeType> Libp2pNetwork<T> {
 
     /// task to propagate messages to handlers
     /// terminates on shut down of network
-    fn handle_event_generator(&self, sender: Sender<Vec<u8>>, mut network_rx: NetworkNodeReceiver) {
+    fn handle_event_generator(&self, sender: Sender<Bytes>, mut network_rx: NetworkNodeReceiver) {
         let handle = self.clone();
         let is_bootstrapped = Arc::clone(&self.inner.is_bootstrapped);
         spawn(async move {
@@ -754,6 +824,46 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
         self.wait_for_ready().await;
     }
 
+    async fn connected_peers(&self) -> Option<usize> {
+        self.inner.handle.num_connected().await.ok()
+    }
+
+    async fn peer_status(&self) -> Option<Vec<PeerStatus<T::SignatureKey>>> {
+        let stats = self.inner.handle.peer_stats().await.ok()?;
+        let peer_keys = self.inner.peer_keys.read().await;
+
+        Some(
+            stats
+                .into_iter()
+                .map(|(pid, stats)| PeerStatus {
+                    key: peer_keys.get(&pid).cloned(),
+                    peer_id: pid.to_string(),
+                    connected: stats.connected,
+                    last_seen: stats.last_seen.map(|seen| seen.elapsed()),
+                    rtt: stats.rtt,
+                    failures: stats.failures,
+                })
+                .collect(),
+        )
+    }
+
+    fn observe_propagation(&self, class: MessageClass, latency: Duration) {
+        let Some(tuner) = &self.inner.fanout_tuner else {
+            return;
+        };
+        if class != MessageClass::Proposal {
+            return;
+        }
+        let Some(extra) = tuner.lock().observe_proposal(latency) else {
+            return;
+        };
+        info!("Gossiping to {extra} peers outside the mesh");
+        self.inner.metrics.extra_gossip_fanout.set(extra);
+        if let Err(e) = self.inner.handle.set_extra_fanout(extra) {
+            warn!("Failed to change the gossip fanout: {e}");
+        }
+    }
+
     fn pause(&self) {
         unimplemented!("Pausing not implemented for the Libp2p network");
     }
@@ -779,7 +889,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
     #[instrument(name = "Libp2pNetwork::broadcast_message", skip_all)]
     async fn broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         topic: Topic,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
@@ -808,12 +918,12 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
 
                 let fut = config.clone().chaos_send_msg(
                     message,
-                    Arc::new(move |msg: Vec<u8>| {
+                    Arc::new(move |msg: Bytes| {
                         let topic_2 = topic.clone();
                         let handle_2 = Arc::clone(&handle);
                         let metrics_2 = metrics.clone();
                         boxed_sync(async move {
-                            if let Err(e) = handle_2.gossip_no_serialize(topic_2, msg) {
+                            if let Err(e) = handle_2.gossip_no_serialize(topic_2, msg.into()) {
                                 metrics_2.num_failed_messages.add(1);
                                 warn!("Failed to broadcast to libp2p: {:?}", e);
                             }
@@ -825,7 +935,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             }
         }
 
-        if let Err(e) = self.inner.handle.gossip(topic, &message) {
+        if let Err(e) = self.inner.handle.gossip_no_serialize(topic, message.into()) {
             self.inner.metrics.num_failed_messages.add(1);
             return Err(e);
         }
@@ -836,7 +946,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
     #[instrument(name = "Libp2pNetwork::da_broadcast_message", skip_all)]
     async fn da_broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipients: Vec<T::SignatureKey>,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
@@ -869,7 +979,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
     #[instrument(name = "Libp2pNetwork::direct_message", skip_all)]
     async fn direct_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipient: T::SignatureKey,
     ) -> Result<(), NetworkError> {
         // If we're not ready, return an error
@@ -894,7 +1004,14 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             .lookup_node(&recipient.to_bytes(), self.inner.dht_timeout)
             .await
         {
-            Ok(pid) => pid,
+            Ok(pid) => {
+                self.inner
+                    .peer_keys
+                    .write()
+                    .await
+                    .insert(pid, recipient.clone());
+                pid
+            }
             Err(err) => {
                 self.inner.metrics.num_failed_messages.add(1);
                 return Err(NetworkError::LookupError(format!(
@@ -911,11 +1028,11 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
 
                 let fut = config.clone().chaos_send_msg(
                     message,
-                    Arc::new(move |msg: Vec<u8>| {
+                    Arc::new(move |msg: Bytes| {
                         let handle_2 = Arc::clone(&handle);
                         let metrics_2 = metrics.clone();
                         boxed_sync(async move {
-                            if let Err(e) = handle_2.direct_request_no_serialize(pid, msg) {
+                            if let Err(e) = handle_2.direct_request_no_serialize(pid, msg.into()) {
                                 metrics_2.num_failed_messages.add(1);
                                 warn!("Failed to broadcast to libp2p: {:?}", e);
                             }
@@ -927,7 +1044,11 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
             }
         }
 
-        match self.inner.handle.direct_request(pid, &message) {
+        match self
+            .inner
+            .handle
+            .direct_request_no_serialize(pid, message.into())
+        {
             Ok(()) => Ok(()),
             Err(e) => {
                 self.inner.metrics.num_failed_messages.add(1);
@@ -941,7 +1062,7 @@ impl<T: NodeType> ConnectedNetwork<T::SignatureKey> for Libp2pNetwork<T> {
     /// # Errors
     /// If there is a network-related failure.
     #[instrument(name = "Libp2pNetwork::recv_message", skip_all)]
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
         let result = self
             .inner
             .receiver
@@ -1035,6 +1156,32 @@ mod test {
             );
         }
 
+        /// Test derivation of a bracketed IPv6 address -> Multiaddr
+        #[test]
+        fn test_v6_bracketed() {
+            let addr = "[::]:8080".to_string();
+            let multiaddr =
+                derive_libp2p_multiaddr(&addr).expect("Failed to derive valid multiaddr, {}");
+
+            assert_eq!(multiaddr.to_string(), "/ip6/::/udp/8080/quic-v1");
+        }
+
+        /// Test derivation of a list of addresses -> Multiaddrs
+        #[test]
+        fn test_list() {
+            let multiaddrs = derive_libp2p_multiaddrs("0.0.0.0:8080, [::1]:8081")
+                .expect("Failed to derive valid multiaddrs");
+
+            assert_eq!(
+                multiaddrs
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                ["/ip4/0.0.0.0/udp/8080/quic-v1", "/ip6/::1/udp/8081/quic-v1"]
+            );
+            assert!(derive_libp2p_multiaddrs("0.0.0.0:8080,").is_err());
+        }
+
         /// Test that an invalid address fails to derive to a Multiaddr
         #[test]
         fn test_no_port() {
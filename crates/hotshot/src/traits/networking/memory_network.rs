@@ -20,6 +20,7 @@ use std::{
 
 use async_lock::{Mutex, RwLock};
 use async_trait::async_trait;
+use bytes::Bytes;
 use dashmap::DashMap;
 use hotshot_types::{
     boxed_sync,
@@ -70,9 +71,9 @@ impl<K: SignatureKey> MasterMap<K> {
 #[derive(Debug)]
 struct MemoryNetworkInner<K: SignatureKey> {
     /// Input for messages
-    input: RwLock<Option<Sender<Vec<u8>>>>,
+    input: RwLock<Option<Sender<Bytes>>>,
     /// Output for messages
-    output: Mutex<Receiver<Vec<u8>>>,
+    output: Mutex<Receiver<Bytes>>,
     /// The master map
     master_map: Arc<MasterMap<K>>,
 
@@ -123,7 +124,7 @@ impl<K: SignatureKey> MemoryNetwork<K> {
                 debug!("Starting background task");
                 trace!("Entering processing loop");
                 while let Some(vec) = task_recv.recv().await {
-                    trace!(?vec, "Incoming message");
+                    trace!(len = vec.len(), "Incoming message");
                     // Attempt to decode message
                     let ts = task_send.clone();
                     let res = ts.send(vec).await;
@@ -161,8 +162,8 @@ impl<K: SignatureKey> MemoryNetwork<K> {
         mn
     }
 
-    /// Send a [`Vec<u8>`] message to the inner `input`
-    async fn input(&self, message: Vec<u8>) -> Result<(), SendError<Vec<u8>>> {
+    /// Send a message to the inner `input`
+    async fn input(&self, message: Bytes) -> Result<(), SendError<Bytes>> {
         self.inner
             .in_flight_message_count
             .fetch_add(1, Ordering::Relaxed);
@@ -242,14 +243,14 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         boxed_sync(closure)
     }
 
-    #[instrument(name = "MemoryNetwork::broadcast_message")]
+    #[instrument(name = "MemoryNetwork::broadcast_message", skip(message))]
     async fn broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         topic: Topic,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
-        trace!(?message, "Broadcasting message");
+        trace!(len = message.len(), "Broadcasting message");
         for node in self
             .inner
             .master_map
@@ -266,7 +267,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                     let node2 = node.clone();
                     let fut = config.chaos_send_msg(
                         message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
+                        Arc::new(move |msg: Bytes| {
                             let node3 = (node2).clone();
                             boxed_sync(async move {
                                 let _res = node3.input(msg).await;
@@ -292,14 +293,14 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         Ok(())
     }
 
-    #[instrument(name = "MemoryNetwork::da_broadcast_message")]
+    #[instrument(name = "MemoryNetwork::da_broadcast_message", skip(message))]
     async fn da_broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipients: Vec<K>,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
-        trace!(?message, "Broadcasting message to DA");
+        trace!(len = message.len(), "Broadcasting message to DA");
         for node in self
             .inner
             .master_map
@@ -320,7 +321,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                     let node2 = node.clone();
                     let fut = config.chaos_send_msg(
                         message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
+                        Arc::new(move |msg: Bytes| {
                             let node3 = (node2).clone();
                             boxed_sync(async move {
                                 let _res = node3.input(msg).await;
@@ -346,8 +347,8 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
         Ok(())
     }
 
-    #[instrument(name = "MemoryNetwork::direct_message")]
-    async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+    #[instrument(name = "MemoryNetwork::direct_message", skip(message))]
+    async fn direct_message(&self, message: Bytes, recipient: K) -> Result<(), NetworkError> {
         // debug!(?message, ?recipient, "Sending direct message");
         // Bincode the message
         trace!("Message bincoded, finding recipient");
@@ -357,7 +358,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
                 {
                     let fut = config.chaos_send_msg(
                         message.clone(),
-                        Arc::new(move |msg: Vec<u8>| {
+                        Arc::new(move |msg: Bytes| {
                             let node2 = node.clone();
                             boxed_sync(async move {
                                 let _res = node2.input(msg).await;
@@ -393,7 +394,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for MemoryNetwork<K> {
     /// # Errors
     /// If the other side of the channel is closed
     #[instrument(name = "MemoryNetwork::recv_messages", skip_all)]
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
         let ret = self
             .inner
             .output
@@ -0,0 +1,512 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Networking implementation which end-to-end encrypts direct messages between consensus keys.
+//!
+//! Direct messages, such as votes and vote tokens bound for the next leader, may cross relays
+//! which see them in the clear, such as the brokers of the CDN. [`NoiseNetwork`] wraps another
+//! network and runs a [Noise handshake](hotshot_types::noise) with each peer it sends direct
+//! messages to, encrypting them under a key only the two of them know. Messages sent before the
+//! handshake is done wait for it.
+//!
+//! Broadcasts are meant for everyone, and are sent as they are. Every message a [`NoiseNetwork`]
+//! sends is framed, so all nodes of a network must wrap it alike.
+
+use std::{
+    collections::{hash_map::Entry, HashMap, VecDeque},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use bytes::{BufMut, Bytes, BytesMut};
+use hotshot_types::{
+    data::ViewNumber,
+    gossip_fanout::MessageClass,
+    noise::{Initiator, RecvSession, Responder, SendSession},
+    traits::{
+        network::{BroadcastDelay, ConnectedNetwork, PeerStatus, Topic},
+        node_implementation::NodeType,
+        signature_key::SignatureKey,
+    },
+    BoxSyncFuture,
+};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc::error::TrySendError;
+use tracing::{debug, warn};
+
+use super::NetworkError;
+
+/// Tag of a frame holding a message as it was sent
+const FRAME_PLAIN: u8 = 0;
+
+/// Tag of a frame holding a [`NoiseFrame`]
+const FRAME_NOISE: u8 = 1;
+
+/// How long a handshake may take before we start over
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest number of messages to a peer which wait for a handshake, or which arrive before the
+/// handshake they belong to is done. Older messages are dropped first.
+const MAX_PENDING: usize = 256;
+
+/// A message of the handshake, or of a session.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+enum NoiseFrame<K: SignatureKey> {
+    /// The first message of a handshake, from its initiator
+    Init {
+        /// The initiator, which the reply goes to
+        initiator: K,
+        /// The initiator's ephemeral key
+        ephemeral: Vec<u8>,
+    },
+    /// The reply of the responder
+    Reply {
+        /// The responder
+        responder: K,
+        /// The responder's handshake message, carrying its ephemeral key
+        ephemeral: Vec<u8>,
+        /// The responder's encrypted signature of the handshake
+        payload: Vec<u8>,
+    },
+    /// The last message of a handshake, from its initiator
+    Finish {
+        /// The initiator
+        initiator: K,
+        /// The initiator's encrypted signature of the handshake
+        payload: Vec<u8>,
+    },
+    /// An encrypted direct message
+    Data {
+        /// The sender, which initiated the session
+        initiator: K,
+        /// Nonce of the message in the session
+        nonce: u64,
+        /// The encrypted message
+        ciphertext: Vec<u8>,
+    },
+    /// Sent back for a message of a session the responder does not know, such as after it
+    /// restarted, so that the initiator starts a new one
+    Reset {
+        /// The responder
+        responder: K,
+    },
+}
+
+impl<K: SignatureKey> NoiseFrame<K> {
+    /// The frame holding this message.
+    fn encode(&self) -> Result<Bytes, NetworkError> {
+        let message =
+            bincode::serialize(self).map_err(|e| NetworkError::FailedToSerialize(e.to_string()))?;
+        let mut frame = BytesMut::with_capacity(1 + message.len());
+        frame.put_u8(FRAME_NOISE);
+        frame.put_slice(&message);
+        Ok(frame.freeze())
+    }
+}
+
+/// The frame holding `message` as it is.
+fn plain_frame(message: &[u8]) -> Bytes {
+    let mut frame = BytesMut::with_capacity(1 + message.len());
+    frame.put_u8(FRAME_PLAIN);
+    frame.put_slice(message);
+    frame.freeze()
+}
+
+/// Push `item` to `queue`, dropping the oldest item if it is full.
+fn push_bounded<T>(queue: &mut VecDeque<T>, item: T) {
+    if queue.len() >= MAX_PENDING {
+        queue.pop_front();
+    }
+    queue.push_back(item);
+}
+
+/// Our side of the session to a peer we send direct messages to.
+enum Outbound {
+    /// We started a handshake, and hold the messages to send once it is done
+    Handshaking {
+        /// Our side of the handshake
+        initiator: Initiator,
+        /// When we started it
+        started: Instant,
+        /// The messages waiting for it
+        pending: VecDeque<Bytes>,
+    },
+    /// The handshake is done
+    Established(SendSession),
+}
+
+/// A handshake a peer started with us, waiting for its last message.
+struct Inbound {
+    /// Our side of the handshake
+    responder: Responder,
+    /// Messages of the session which arrived before the handshake was done
+    early: VecDeque<(u64, Vec<u8>)>,
+}
+
+/// The sessions of a [`NoiseNetwork`].
+struct Sessions<K> {
+    /// Sessions to the peers we send direct messages to, by peer
+    outbound: HashMap<K, Outbound>,
+    /// Handshakes peers started with us, by peer
+    responding: HashMap<K, Inbound>,
+    /// Sessions from the peers which send us direct messages, by peer
+    inbound: HashMap<K, RecvSession>,
+}
+
+impl<K> Default for Sessions<K> {
+    fn default() -> Self {
+        Self {
+            outbound: HashMap::new(),
+            responding: HashMap::new(),
+            inbound: HashMap::new(),
+        }
+    }
+}
+
+/// A network which end-to-end encrypts the direct messages it sends over another network.
+#[derive(Clone)]
+pub struct NoiseNetwork<K: SignatureKey, N> {
+    /// The network the frames are sent over
+    network: N,
+
+    /// Our consensus key, which we prove to peers in handshakes
+    public_key: K,
+
+    /// The private key of our consensus key
+    private_key: K::PrivateKey,
+
+    /// The sessions to and from peers
+    sessions: Arc<Mutex<Sessions<K>>>,
+
+    /// Decrypted messages not yet returned by `recv_message`
+    received: Arc<Mutex<VecDeque<Bytes>>>,
+}
+
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> NoiseNetwork<K, N> {
+    /// Encrypt the direct messages sent over `network`, proving to peers that we hold the
+    /// consensus key `public_key`.
+    #[must_use]
+    pub fn new(network: N, public_key: K, private_key: K::PrivateKey) -> Self {
+        Self {
+            network,
+            public_key,
+            private_key,
+            sessions: Arc::default(),
+            received: Arc::default(),
+        }
+    }
+
+    /// Get a ref to the network the frames are sent over
+    #[must_use]
+    pub fn inner(&self) -> &N {
+        &self.network
+    }
+
+    /// Send `frame` to `recipient`.
+    async fn send(&self, frame: &NoiseFrame<K>, recipient: K) -> Result<(), NetworkError> {
+        self.network
+            .direct_message(frame.encode()?, recipient)
+            .await
+    }
+
+    /// Handle a handshake or session message, returning the frames to send in response.
+    fn handle(&self, frame: NoiseFrame<K>) -> Vec<(NoiseFrame<K>, K)> {
+        let mut sessions = self.sessions.lock();
+        match frame {
+            NoiseFrame::Init {
+                initiator,
+                ephemeral,
+            } => match Responder::respond::<K>(&ephemeral, &self.private_key) {
+                Ok((responder, ephemeral, payload)) => {
+                    sessions.responding.insert(
+                        initiator.clone(),
+                        Inbound {
+                            responder,
+                            early: VecDeque::new(),
+                        },
+                    );
+                    let reply = NoiseFrame::Reply {
+                        responder: self.public_key.clone(),
+                        ephemeral,
+                        payload,
+                    };
+                    vec![(reply, initiator)]
+                }
+                Err(e) => {
+                    warn!("Failed to answer handshake: {e}");
+                    vec![]
+                }
+            },
+            NoiseFrame::Reply {
+                responder,
+                ephemeral,
+                payload,
+            } => {
+                let Entry::Occupied(entry) = sessions.outbound.entry(responder.clone()) else {
+                    return vec![];
+                };
+                let Outbound::Handshaking {
+                    initiator, pending, ..
+                } = entry.get()
+                else {
+                    return vec![];
+                };
+                let (payload, mut session) = match initiator.clone().finish(
+                    &ephemeral,
+                    &payload,
+                    &responder,
+                    &self.private_key,
+                ) {
+                    Ok(finished) => finished,
+                    Err(e) => {
+                        warn!("Failed to finish handshake: {e}");
+                        return vec![];
+                    }
+                };
+                let mut frames = vec![(
+                    NoiseFrame::Finish {
+                        initiator: self.public_key.clone(),
+                        payload,
+                    },
+                    responder.clone(),
+                )];
+                for message in pending {
+                    let (nonce, ciphertext) = session.encrypt(message);
+                    let data = NoiseFrame::Data {
+                        initiator: self.public_key.clone(),
+                        nonce,
+                        ciphertext,
+                    };
+                    frames.push((data, responder.clone()));
+                }
+                *entry.into_mut() = Outbound::Established(session);
+                frames
+            }
+            NoiseFrame::Finish { initiator, payload } => {
+                let Some(inbound) = sessions.responding.remove(&initiator) else {
+                    return vec![];
+                };
+                match inbound.responder.finish(&payload, &initiator) {
+                    Ok(mut session) => {
+                        let mut received = self.received.lock();
+                        for (nonce, ciphertext) in inbound.early {
+                            match session.decrypt(nonce, &ciphertext) {
+                                Ok(message) => received.push_back(message.into()),
+                                Err(e) => warn!("Dropping direct message: {e}"),
+                            }
+                        }
+                        sessions.inbound.insert(initiator, session);
+                    }
+                    Err(e) => warn!("Failed to finish handshake: {e}"),
+                }
+                vec![]
+            }
+            NoiseFrame::Data {
+                initiator,
+                nonce,
+                ciphertext,
+            } => match sessions
+                .inbound
+                .get_mut(&initiator)
+                .map(|session| session.decrypt(nonce, &ciphertext))
+            {
+                Some(Ok(message)) => {
+                    self.received.lock().push_back(message.into());
+                    vec![]
+                }
+                decrypted => {
+                    if let Some(inbound) = sessions.responding.get_mut(&initiator) {
+                        // The message may be of the session the peer is setting up
+                        push_bounded(&mut inbound.early, (nonce, ciphertext));
+                        vec![]
+                    } else if let Some(Err(e)) = decrypted {
+                        warn!("Dropping direct message: {e}");
+                        vec![]
+                    } else {
+                        debug!("Received a direct message of an unknown session");
+                        let reset = NoiseFrame::Reset {
+                            responder: self.public_key.clone(),
+                        };
+                        vec![(reset, initiator)]
+                    }
+                }
+            },
+            NoiseFrame::Reset { responder } => {
+                if let Some(Outbound::Established(_)) = sessions.outbound.get(&responder) {
+                    debug!("Peer lost our session, starting a new one with the next message");
+                    sessions.outbound.remove(&responder);
+                }
+                vec![]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static, N: ConnectedNetwork<K>> ConnectedNetwork<K> for NoiseNetwork<K, N> {
+    fn pause(&self) {
+        self.network.pause();
+    }
+
+    fn resume(&self) {
+        self.network.resume();
+    }
+
+    async fn wait_for_ready(&self) {
+        self.network.wait_for_ready().await;
+    }
+
+    fn shut_down<'a, 'b>(&'a self) -> BoxSyncFuture<'b, ()>
+    where
+        'a: 'b,
+        Self: 'b,
+    {
+        self.network.shut_down()
+    }
+
+    async fn broadcast_message(
+        &self,
+        message: Bytes,
+        topic: Topic,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        self.network
+            .broadcast_message(plain_frame(&message), topic, broadcast_delay)
+            .await
+    }
+
+    async fn da_broadcast_message(
+        &self,
+        message: Bytes,
+        recipients: Vec<K>,
+        broadcast_delay: BroadcastDelay,
+    ) -> Result<(), NetworkError> {
+        self.network
+            .da_broadcast_message(plain_frame(&message), recipients, broadcast_delay)
+            .await
+    }
+
+    async fn vid_broadcast_message(&self, messages: HashMap<K, Bytes>) -> Result<(), NetworkError> {
+        let messages = messages
+            .into_iter()
+            .map(|(recipient, message)| (recipient, plain_frame(&message)))
+            .collect();
+        self.network.vid_broadcast_message(messages).await
+    }
+
+    /// Encrypt a direct message to `recipient`, or hold it until the handshake with it is done.
+    ///
+    /// # Errors
+    /// If the message or the first message of a handshake fails to send.
+    async fn direct_message(&self, message: Bytes, recipient: K) -> Result<(), NetworkError> {
+        let frame = {
+            let mut sessions = self.sessions.lock();
+            match sessions.outbound.get_mut(&recipient) {
+                Some(Outbound::Established(session)) => {
+                    let (nonce, ciphertext) = session.encrypt(&message);
+                    NoiseFrame::Data {
+                        initiator: self.public_key.clone(),
+                        nonce,
+                        ciphertext,
+                    }
+                }
+                Some(Outbound::Handshaking {
+                    started, pending, ..
+                }) if started.elapsed() < HANDSHAKE_TIMEOUT => {
+                    push_bounded(pending, message);
+                    return Ok(());
+                }
+                outbound => {
+                    // Start a handshake, keeping the messages of one which timed out
+                    let mut pending = match outbound {
+                        Some(Outbound::Handshaking { pending, .. }) => std::mem::take(pending),
+                        _ => VecDeque::new(),
+                    };
+                    push_bounded(&mut pending, message);
+                    let (initiator, ephemeral) = Initiator::new();
+                    sessions.outbound.insert(
+                        recipient.clone(),
+                        Outbound::Handshaking {
+                            initiator,
+                            started: Instant::now(),
+                            pending,
+                        },
+                    );
+                    NoiseFrame::Init {
+                        initiator: self.public_key.clone(),
+                        ephemeral,
+                    }
+                }
+            }
+        };
+        self.send(&frame, recipient).await
+    }
+
+    /// Receive one message, running the handshakes and decrypting the direct messages the
+    /// underlying network receives.
+    ///
+    /// # Errors
+    /// If there is a network-related failure, or a malformed frame is received.
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
+        loop {
+            if let Some(message) = self.received.lock().pop_front() {
+                return Ok(message);
+            }
+            let frame = self.network.recv_message().await?;
+            let frame = match frame.first() {
+                Some(&FRAME_PLAIN) => return Ok(frame.slice(1..)),
+                Some(&FRAME_NOISE) => bincode::deserialize(&frame[1..])
+                    .map_err(|e| NetworkError::FailedToDeserialize(e.to_string()))?,
+                _ => {
+                    return Err(NetworkError::FailedToDeserialize(
+                        "Malformed frame".to_string(),
+                    ))
+                }
+            };
+            for (frame, recipient) in self.handle(frame) {
+                if let Err(e) = self.send(&frame, recipient).await {
+                    warn!("Failed to send handshake message: {e}");
+                }
+            }
+        }
+    }
+
+    fn queue_node_lookup(
+        &self,
+        view_number: ViewNumber,
+        pk: K,
+    ) -> Result<(), TrySendError<Option<(ViewNumber, K)>>> {
+        self.network.queue_node_lookup(view_number, pk)
+    }
+
+    async fn update_view<'a, TYPES>(&'a self, view: u64, epoch: u64, membership: &TYPES::Membership)
+    where
+        TYPES: NodeType<SignatureKey = K> + 'a,
+    {
+        self.network
+            .update_view::<TYPES>(view, epoch, membership)
+            .await;
+    }
+
+    fn is_primary_down(&self) -> bool {
+        self.network.is_primary_down()
+    }
+
+    async fn connected_peers(&self) -> Option<usize> {
+        self.network.connected_peers().await
+    }
+
+    async fn peer_status(&self) -> Option<Vec<PeerStatus<K>>> {
+        self.network.peer_status().await
+    }
+
+    fn observe_propagation(&self, class: MessageClass, latency: Duration) {
+        self.network.observe_propagation(class, latency);
+    }
+}
@@ -12,6 +12,7 @@ use std::{path::Path, time::Duration};
 
 use async_trait::async_trait;
 use bincode::config::Options;
+use bytes::Bytes;
 use cdn_broker::reexports::{
     connection::protocols::{Tcp, TcpTls},
     def::{hook::NoMessageHook, ConnectionDef, RunDef, Topic as TopicTrait},
@@ -483,7 +484,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     /// - If we fail to send the broadcast message.
     async fn broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         topic: HotShotTopic,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
@@ -492,7 +493,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         if self.is_paused.load(Ordering::Relaxed) {
             return Ok(());
         }
-        self.broadcast_message(message, topic.into())
+        self.broadcast_message(message.into(), topic.into())
             .await
             .inspect_err(|_e| {
                 self.metrics.num_failed_messages.add(1);
@@ -506,7 +507,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     /// - If we fail to send the broadcast message.
     async fn da_broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         _recipients: Vec<K>,
         _broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError> {
@@ -515,7 +516,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         if self.is_paused.load(Ordering::Relaxed) {
             return Ok(());
         }
-        self.broadcast_message(message, Topic::Da)
+        self.broadcast_message(message.into(), Topic::Da)
             .await
             .inspect_err(|_e| {
                 self.metrics.num_failed_messages.add(1);
@@ -526,7 +527,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     ///
     /// - If we fail to serialize the message
     /// - If we fail to send the direct message
-    async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError> {
+    async fn direct_message(&self, message: Bytes, recipient: K) -> Result<(), NetworkError> {
         // If we're paused, don't send the message
         #[cfg(feature = "hotshot-testing")]
         if self.is_paused.load(Ordering::Relaxed) {
@@ -536,7 +537,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         // Send the message
         if let Err(e) = self
             .client
-            .send_direct_message(&WrappedSignatureKey(recipient), message)
+            .send_direct_message(&WrappedSignatureKey(recipient), message.into())
             .await
         {
             self.metrics.num_failed_messages.add(1);
@@ -553,7 +554,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
     ///
     /// # Errors
     /// - If we fail to receive messages. Will trigger a retry automatically.
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError> {
+    async fn recv_message(&self) -> Result<Bytes, NetworkError> {
         // Receive a message
         let message = self.client.receive_message().await;
 
@@ -561,7 +562,7 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
         #[cfg(feature = "hotshot-testing")]
         if self.is_paused.load(Ordering::Relaxed) {
             sleep(Duration::from_millis(100)).await;
-            return Ok(vec![]);
+            return Ok(Bytes::new());
         }
 
         // If it was an error, wait a bit and retry
@@ -581,10 +582,10 @@ impl<K: SignatureKey + 'static> ConnectedNetwork<K> for PushCdnNetwork<K> {
             recipient: _,
         })) = message
         else {
-            return Ok(vec![]);
+            return Ok(Bytes::new());
         };
 
-        Ok(message)
+        Ok(message.into())
     }
 
     /// Do nothing here, as we don't need to look up nodes.
@@ -0,0 +1,322 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Lifecycle tracking of submitted transactions.
+//!
+//! A [`TransactionTracker`] follows the event stream of a node and indexes where each transaction
+//! is in its lifecycle: pending in the mempool, included in the DA proposal of a view, finalized in
+//! a decided block, or rejected by the application state. Clients can then ask for the status of a
+//! transaction instead of polling blocks until it shows up.
+//!
+//! The index only covers what this node saw, and forgets transactions
+//! [`TransactionTrackerConfig::retention`] views after their status last changed.
+//!
+//! With the `query-api` feature, [`define_api`] serves the status of a transaction over HTTP.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use async_lock::RwLock;
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_types::traits::{
+    block_contents::BlockHeader,
+    node_implementation::{ConsensusTime, NodeType},
+    BlockPayload,
+};
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+use crate::types::{Event, EventType};
+
+/// Settings of the transaction index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TransactionTrackerConfig {
+    /// Views a transaction is kept after its status last changed
+    pub retention: u64,
+    /// Maximum number of transactions indexed at once. Further transactions are not tracked
+    pub max_tracked: usize,
+}
+
+impl Default for TransactionTrackerConfig {
+    fn default() -> Self {
+        Self {
+            retention: 1000,
+            max_tracked: 100_000,
+        }
+    }
+}
+
+/// Where a transaction is in its lifecycle.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub enum TransactionStatus<TYPES: NodeType> {
+    /// The transaction is in the mempool, and was not proposed yet
+    Pending {
+        /// The view in which we first saw the transaction
+        since: TYPES::View,
+    },
+    /// The transaction was included in a proposal, which is not decided yet
+    Proposed {
+        /// The view of the latest proposal including the transaction
+        view: TYPES::View,
+    },
+    /// The transaction was decided
+    Finalized {
+        /// The view of the decided leaf
+        view: TYPES::View,
+        /// The height of the decided block
+        height: u64,
+    },
+    /// The transaction was decided, but rejected by the application state
+    Rejected {
+        /// The view of the decided leaf
+        view: TYPES::View,
+        /// Application-provided reason for the rejection
+        reason: String,
+    },
+}
+
+impl<TYPES: NodeType> TransactionStatus<TYPES> {
+    /// Whether the status is final, and will not change anymore.
+    #[must_use]
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::Finalized { .. } | Self::Rejected { .. })
+    }
+}
+
+/// The index of transaction statuses, maintained from the event stream.
+#[derive(Debug)]
+pub struct TransactionIndex<TYPES: NodeType> {
+    /// Settings
+    config: TransactionTrackerConfig,
+    /// Status of each transaction, with the view it last changed in
+    statuses: HashMap<Commitment<TYPES::Transaction>, (TransactionStatus<TYPES>, TYPES::View)>,
+    /// Transactions of the undecided DA proposals, for leaves decided without their payload
+    proposed: BTreeMap<TYPES::View, Vec<Commitment<TYPES::Transaction>>>,
+    /// Highest view seen in an event
+    cur_view: TYPES::View,
+}
+
+impl<TYPES: NodeType> TransactionIndex<TYPES> {
+    /// An empty index.
+    #[must_use]
+    pub fn new(config: TransactionTrackerConfig) -> Self {
+        Self {
+            config,
+            statuses: HashMap::new(),
+            proposed: BTreeMap::new(),
+            cur_view: TYPES::View::genesis(),
+        }
+    }
+
+    /// The status of the transaction with `commitment`, if it is indexed.
+    #[must_use]
+    pub fn status(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<TransactionStatus<TYPES>> {
+        self.statuses
+            .get(commitment)
+            .map(|(status, _)| status.clone())
+    }
+
+    /// Number of indexed transactions.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.statuses.len()
+    }
+
+    /// Whether no transaction is indexed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+    }
+
+    /// Update the index from `event`.
+    pub fn handle_event(&mut self, event: &Event<TYPES>) {
+        if event.view_number > self.cur_view {
+            self.cur_view = event.view_number;
+            self.prune();
+        }
+
+        match &event.event {
+            EventType::Transactions { transactions } => {
+                for transaction in transactions {
+                    let since = event.view_number;
+                    self.update(transaction.commit(), TransactionStatus::Pending { since });
+                }
+            }
+            EventType::DaProposal { proposal, .. } => {
+                let view = proposal.data.view_number;
+                let payload = <TYPES::BlockPayload as BlockPayload<TYPES>>::from_bytes(
+                    &proposal.data.encoded_transactions,
+                    &proposal.data.metadata,
+                );
+                let commitments: Vec<_> = payload
+                    .transactions(&proposal.data.metadata)
+                    .map(|transaction| transaction.commit())
+                    .collect();
+                for commitment in &commitments {
+                    self.update(*commitment, TransactionStatus::Proposed { view });
+                }
+                self.proposed.insert(view, commitments);
+            }
+            EventType::Decide { leaf_chain, .. } => {
+                // Oldest first, so that a transaction decided twice keeps its first block
+                for info in leaf_chain.iter().rev() {
+                    let leaf = &info.leaf;
+                    let view = leaf.view_number();
+                    let commitments: Vec<_> = match leaf.block_payload() {
+                        Some(payload) => payload
+                            .transactions(leaf.block_header().metadata())
+                            .map(|transaction| transaction.commit())
+                            .collect(),
+                        None => self.proposed.get(&view).cloned().unwrap_or_default(),
+                    };
+
+                    for commitment in commitments {
+                        let status = match info
+                            .rejected
+                            .iter()
+                            .find(|rejected| rejected.commitment == commitment)
+                        {
+                            Some(rejected) => TransactionStatus::Rejected {
+                                view,
+                                reason: rejected.reason.clone(),
+                            },
+                            None => TransactionStatus::Finalized {
+                                view,
+                                height: leaf.height(),
+                            },
+                        };
+                        self.update(commitment, status);
+                    }
+                    self.proposed = self.proposed.split_off(&(view + 1));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Move the transaction with `commitment` to `status`, unless it is already further along.
+    fn update(
+        &mut self,
+        commitment: Commitment<TYPES::Transaction>,
+        status: TransactionStatus<TYPES>,
+    ) {
+        let view = self.cur_view;
+        match self.statuses.get_mut(&commitment) {
+            Some((current, _)) if current.is_final() => {}
+            Some((TransactionStatus::Proposed { .. }, _))
+                if matches!(status, TransactionStatus::Pending { .. }) => {}
+            Some(entry) => *entry = (status, view),
+            None if self.statuses.len() >= self.config.max_tracked => {
+                tracing::debug!("Transaction index is full, not tracking {commitment}");
+            }
+            None => {
+                self.statuses.insert(commitment, (status, view));
+            }
+        }
+    }
+
+    /// Forget transactions whose status has not changed within the retention window.
+    fn prune(&mut self) {
+        let retention = self.config.retention;
+        let cur_view = *self.cur_view;
+        self.statuses
+            .retain(|_, (_, updated)| updated.saturating_add(retention) >= cur_view);
+        let oldest = TYPES::View::new(cur_view.saturating_sub(retention));
+        self.proposed = self.proposed.split_off(&oldest);
+    }
+}
+
+/// Follows the event stream of a node and indexes the status of transactions.
+///
+/// The tracker stops following events when it is dropped.
+pub struct TransactionTracker<TYPES: NodeType> {
+    /// The index
+    index: Arc<RwLock<TransactionIndex<TYPES>>>,
+    /// The task following events
+    task: JoinHandle<()>,
+}
+
+impl<TYPES: NodeType> TransactionTracker<TYPES> {
+    /// Start following `events`.
+    pub fn spawn(
+        config: TransactionTrackerConfig,
+        events: impl Stream<Item = Event<TYPES>> + Send + Unpin + 'static,
+    ) -> Self {
+        let index = Arc::new(RwLock::new(TransactionIndex::new(config)));
+        let task = tokio::spawn(track(Arc::clone(&index), events));
+
+        Self { index, task }
+    }
+
+    /// The status of the transaction with `commitment`, if this node saw it recently.
+    pub async fn status(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<TransactionStatus<TYPES>> {
+        self.index.read().await.status(commitment)
+    }
+}
+
+impl<TYPES: NodeType> Drop for TransactionTracker<TYPES> {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Update `index` from `events`.
+async fn track<TYPES: NodeType>(
+    index: Arc<RwLock<TransactionIndex<TYPES>>>,
+    mut events: impl Stream<Item = Event<TYPES>> + Send + Unpin,
+) {
+    while let Some(event) = events.next().await {
+        index.write().await.handle_event(&event);
+    }
+}
+
+/// Define the transaction status API, serving the index of the [`TransactionTracker`] in `State`.
+///
+/// # Errors
+/// Fails if the API specification is invalid.
+#[cfg(feature = "query-api")]
+pub fn define_api<State, TYPES>() -> Result<
+    tide_disco::Api<State, crate::query_api::Error, crate::query_api::Version>,
+    tide_disco::api::ApiError,
+>
+where
+    TYPES: NodeType,
+    State: 'static + Send + Sync + tide_disco::method::ReadState<State = TransactionTracker<TYPES>>,
+{
+    use futures::FutureExt;
+
+    let toml: toml::Value =
+        toml::from_str(include_str!("../api/transactions.toml")).map_err(|err| {
+            tide_disco::api::ApiError::CannotReadToml {
+                reason: err.to_string(),
+            }
+        })?;
+
+    let mut api = tide_disco::Api::new(toml)?;
+    api.with_version("0.1.0".parse().unwrap())
+        .get("status", |req, tracker| {
+            async move {
+                let hash: Commitment<TYPES::Transaction> = req.blob_param("hash")?;
+                tracker
+                    .status(&hash)
+                    .await
+                    .ok_or_else(|| crate::query_api::Error::NotFound(format!("Transaction {hash}")))
+            }
+            .boxed()
+        })?;
+
+    Ok(api)
+}
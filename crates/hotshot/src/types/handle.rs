@@ -6,11 +6,12 @@
 
 //! Provides an event-streaming handle for a [`SystemContext`] running in the background
 
-use std::sync::Arc;
+use std::{num::NonZeroU64, sync::Arc};
 
 use anyhow::{anyhow, Context, Ok, Result};
 use async_broadcast::{InactiveReceiver, Receiver, Sender};
 use async_lock::RwLock;
+use bytes::Bytes;
 use committable::{Commitment, Committable};
 use futures::Stream;
 use hotshot_task::{
@@ -19,23 +20,42 @@ use hotshot_task::{
 };
 use hotshot_task_impls::{events::HotShotEvent, helpers::broadcast_event};
 use hotshot_types::{
+    beacon::BeaconOutput,
+    checkpoint::CheckpointSink,
     consensus::Consensus,
     data::{Leaf2, QuorumProposal2},
     error::HotShotError,
+    event::RejectedTransaction,
     message::{Message, MessageKind, Proposal, RecipientList},
     request_response::ProposalRequestPayload,
+    safety::SafetyAlertHandler,
     traits::{
-        consensus_api::ConsensusApi,
+        application::Application,
+        archive::{verify_storage, ArchiveStorage, Corruption},
         election::Membership,
+        metrics::Metrics,
         network::{BroadcastDelay, ConnectedNetwork, Topic},
         node_implementation::NodeType,
-        signature_key::SignatureKey,
+        signer::{Signer, SignerError},
     },
+    validator_ops::{OperationTransaction, SignedOperation, ValidatorOperation},
     vote::HasViewNumber,
 };
 use tracing::instrument;
 
-use crate::{traits::NodeImplementation, types::Event, SystemContext, Versions};
+use crate::{
+    application::ApplicationDriver,
+    archive::Archiver,
+    audit_log::{AuditLog, AuditLogConfig},
+    checkpoint::Checkpointer,
+    health::{network_status, HealthMonitor, HealthThresholds, NetworkStatus},
+    qc_relay::{QcRelay, QcRelayConfig, QcRelayMetrics},
+    relay::{Relay, RelayConfig},
+    traits::NodeImplementation,
+    transaction_status::{TransactionTracker, TransactionTrackerConfig},
+    types::Event,
+    SystemContext, Versions,
+};
 
 /// Event streaming handle for a [`SystemContext`] instance running in the background
 ///
@@ -109,7 +129,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
             sender: self.public_key().clone(),
             kind: MessageKind::External(msg),
         };
-        let serialized_message = self.hotshot.upgrade_lock.serialize(&message).await?;
+        let serialized_message = Bytes::from(
+            self.hotshot
+                .upgrade_lock
+                .serialize_signed(&message, self.hotshot.signer.as_ref())
+                .await?,
+        );
 
         match recipients {
             RecipientList::Broadcast => {
@@ -136,13 +161,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
     /// proposal this will block forever
     ///
     /// # Errors
-    /// Errors if signing the request for proposal fails
+    /// The returned future errors if signing the request for proposal fails
     pub fn request_proposal(
         &self,
         view: TYPES::View,
         leaf_commitment: Commitment<Leaf2<TYPES>>,
-    ) -> Result<impl futures::Future<Output = Result<Proposal<TYPES, QuorumProposal2<TYPES>>>>>
-    {
+    ) -> impl futures::Future<Output = Result<Proposal<TYPES, QuorumProposal2<TYPES>>>> {
         // We need to be able to sign this request before submitting it to the network. Compute the
         // payload first.
         let signed_proposal_request = ProposalRequestPayload {
@@ -150,17 +174,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
             key: self.public_key().clone(),
         };
 
-        // Finally, compute the signature for the payload.
-        let signature = TYPES::SignatureKey::sign(
-            self.private_key(),
-            signed_proposal_request.commit().as_ref(),
-        )?;
-
+        let signer = Arc::clone(&self.hotshot.signer);
         let mem = (*self.memberships).clone();
         let receiver = self.internal_event_stream.1.activate_cloned();
         let sender = self.internal_event_stream.0.clone();
         let epoch_height = self.epoch_height;
-        Ok(async move {
+        async move {
+            // Finally, compute the signature for the payload.
+            let signature = signer
+                .sign(signed_proposal_request.commit().as_ref())
+                .await
+                .context("Failed to sign the request for proposal")?;
+
             // First, broadcast that we need a proposal
             broadcast_event(
                 HotShotEvent::QuorumProposalRequestSend(signed_proposal_request, signature).into(),
@@ -199,7 +224,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
                     tracing::warn!("Proposal received from request has different commitment than expected.\nExpected = {:?}\nReceived{:?}", leaf_commitment, commit);
                 }
             }
-        })
+        }
     }
 
     /// HACK so we can know the types when running tests...
@@ -280,6 +305,245 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions>
         self.hotshot.publish_transaction_async(tx).await
     }
 
+    /// Submit a transaction, after `app` admitted it with [`Application::check_tx`].
+    ///
+    /// # Errors
+    ///
+    /// Will return [`HotShotError::InvalidTransaction`] if `app` rejects the transaction, or
+    /// any error of [`Self::submit_transaction`].
+    pub async fn submit_checked_transaction<A: Application<TYPES>>(
+        &self,
+        app: &A,
+        tx: TYPES::Transaction,
+    ) -> Result<(), HotShotError<TYPES>> {
+        app.check_tx(&tx)
+            .await
+            .map_err(|e| HotShotError::InvalidTransaction(e.to_string()))?;
+        self.submit_transaction(tx).await
+    }
+
+    /// Start finalizing decided blocks with `app`.
+    ///
+    /// Finalization stops when the returned [`ApplicationDriver`] is dropped.
+    #[must_use]
+    pub fn spawn_application<A: Application<TYPES>>(&self, app: Arc<A>) -> ApplicationDriver {
+        ApplicationDriver::spawn(app, self.event_stream_known_impl())
+    }
+
+    /// Start archiving the decided chain to `archive` and serving proposal requests from it.
+    ///
+    /// This is meant for nodes running in the archival role. Archiving stops when the returned
+    /// [`Archiver`] is dropped.
+    #[must_use]
+    pub fn spawn_archiver<A: ArchiveStorage<TYPES>>(&self, archive: Arc<A>) -> Archiver {
+        Archiver::spawn(
+            archive,
+            Arc::clone(&self.memberships),
+            self.event_stream_known_impl(),
+            self.internal_event_stream_receiver_known_impl(),
+            self.internal_event_stream_sender(),
+        )
+    }
+
+    /// Check the integrity of everything in `archive` against this node's stake tables, returning
+    /// the corruptions found. See [`verify_storage`].
+    ///
+    /// # Errors
+    /// If the archive cannot be read.
+    pub async fn verify_storage<A: ArchiveStorage<TYPES>>(
+        &self,
+        archive: &A,
+    ) -> Result<Vec<Corruption>> {
+        verify_storage(archive, &*self.memberships, &self.hotshot.upgrade_lock).await
+    }
+
+    /// Check whether a submitted transaction was rejected from a recently decided block.
+    ///
+    /// Returns the rejection, including the application's reason, or [`None`] if the transaction
+    /// has not been rejected (or was rejected too long ago to still be tracked).
+    pub async fn rejected_transaction(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<RejectedTransaction<TYPES>> {
+        self.hotshot
+            .consensus()
+            .read()
+            .await
+            .rejected_transaction(commitment)
+            .cloned()
+    }
+
+    /// The randomness beacon output for `view`, derived from the signature on its QC.
+    ///
+    /// Returns [`None`] if we have not seen a QC for `view`, or it was too long ago to still be
+    /// tracked.
+    pub async fn randomness(&self, view: TYPES::View) -> Option<BeaconOutput> {
+        self.hotshot.consensus().read().await.beacon().output(view)
+    }
+
+    /// Start writing proposals received, messages signed and decides to an audit log.
+    ///
+    /// Logging stops when the returned [`AuditLog`] is dropped.
+    ///
+    /// # Errors
+    /// Fails if the log file cannot be opened.
+    pub fn spawn_audit_log(&self, config: AuditLogConfig) -> Result<AuditLog> {
+        AuditLog::spawn(
+            config,
+            self.internal_event_stream_receiver_known_impl(),
+            self.event_stream_known_impl(),
+        )
+    }
+
+    /// Start checkpointing the decided chain to `sink` every `interval` blocks.
+    ///
+    /// Checkpointing stops when the returned [`Checkpointer`] is dropped.
+    #[must_use]
+    pub fn spawn_checkpointer<S: CheckpointSink<TYPES>>(
+        &self,
+        sink: S,
+        interval: NonZeroU64,
+    ) -> Checkpointer<TYPES> {
+        Checkpointer::spawn(
+            sink,
+            interval,
+            Arc::clone(&self.memberships),
+            self.event_stream_known_impl(),
+        )
+    }
+
+    /// Start condensing the event stream into a feed for dashboards.
+    ///
+    /// The feed stops when the returned [`Feed`](crate::feed::Feed) is dropped.
+    #[cfg(feature = "query-api")]
+    #[must_use]
+    pub fn spawn_feed(&self) -> crate::feed::Feed<TYPES> {
+        crate::feed::Feed::spawn(
+            Arc::clone(&self.memberships),
+            self.event_stream_known_impl(),
+        )
+    }
+
+    /// Start monitoring the consensus health of this node.
+    ///
+    /// Monitoring stops when the returned [`HealthMonitor`] is dropped.
+    #[must_use]
+    pub fn spawn_health_monitor(
+        &self,
+        thresholds: HealthThresholds,
+    ) -> HealthMonitor<TYPES, I::Network> {
+        HealthMonitor::spawn(
+            thresholds,
+            self.hotshot.consensus(),
+            Arc::clone(&self.hotshot.network),
+            Arc::clone(&self.memberships),
+            self.public_key(),
+            self.event_stream_known_impl(),
+        )
+    }
+
+    /// Get the status of this node's connections to its peers: whether they are connected, their
+    /// stake, when we last heard from them, round-trip times and failure counts.
+    ///
+    /// Returns `None` if the network does not track its peers.
+    pub async fn network_status(&self) -> Option<NetworkStatus<TYPES::SignatureKey>> {
+        network_status::<TYPES, I::Network>(
+            &self.network,
+            &self.memberships,
+            &self.public_key(),
+            self.cur_epoch().await,
+        )
+        .await
+    }
+
+    /// Start relaying compact QCs of decided leaves to an L1 endpoint.
+    ///
+    /// The relay stops when the returned [`QcRelay`] is dropped.
+    #[must_use]
+    pub fn spawn_qc_relay(&self, config: QcRelayConfig, metrics: &dyn Metrics) -> QcRelay {
+        QcRelay::spawn(
+            config,
+            QcRelayMetrics::new(metrics),
+            self.event_stream_known_impl(),
+        )
+    }
+
+    /// Start relaying proposals, QCs and decides to observer clients connecting over TCP.
+    ///
+    /// The relay stops when the returned [`Relay`] is dropped.
+    ///
+    /// # Errors
+    /// Fails if we cannot listen on the configured address.
+    pub async fn spawn_relay(&self, config: RelayConfig) -> std::io::Result<Relay> {
+        Relay::spawn(config, self.event_stream_known_impl()).await
+    }
+
+    /// Start indexing the status of transactions: pending, proposed, finalized or rejected.
+    ///
+    /// Indexing stops when the returned [`TransactionTracker`] is dropped.
+    #[must_use]
+    pub fn spawn_transaction_tracker(
+        &self,
+        config: TransactionTrackerConfig,
+    ) -> TransactionTracker<TYPES> {
+        TransactionTracker::spawn(config, self.event_stream_known_impl())
+    }
+
+    /// Sign a validator operation for this node with its consensus key, and with `new_key_signer`
+    /// for a key rotation. `nonce` is the number of previous operations of this node, see
+    /// [`ValidatorRegistry::next_nonce`](crate::validator_ops::ValidatorRegistry::next_nonce).
+    ///
+    /// # Errors
+    /// If the operation is not for this node, or signing fails.
+    pub async fn sign_validator_operation(
+        &self,
+        operation: ValidatorOperation<TYPES::SignatureKey>,
+        nonce: u64,
+        new_key_signer: Option<&dyn Signer<TYPES::SignatureKey>>,
+    ) -> Result<SignedOperation<TYPES::SignatureKey>, SignerError> {
+        SignedOperation::sign(
+            operation,
+            self.hotshot.config.chain_id,
+            nonce,
+            self.hotshot.signer.as_ref(),
+            new_key_signer,
+        )
+        .await
+    }
+
+    /// Submit a signed validator operation in a transaction, returning the commitment of the
+    /// transaction to look up its outcome with.
+    ///
+    /// # Errors
+    /// Will return [`HotShotError::InvalidTransaction`] if the operation does not fit in a
+    /// transaction, or any error of [`Self::submit_transaction`].
+    pub async fn submit_validator_operation(
+        &self,
+        operation: &SignedOperation<TYPES::SignatureKey>,
+    ) -> Result<Commitment<TYPES::Transaction>, HotShotError<TYPES>>
+    where
+        TYPES::Transaction: OperationTransaction,
+    {
+        let transaction: TYPES::Transaction = operation.to_transaction().ok_or_else(|| {
+            HotShotError::InvalidTransaction(
+                "The validator operation does not fit in a transaction".to_string(),
+            )
+        })?;
+        let commitment = transaction.commit();
+        self.submit_transaction(transaction)
+            .await
+            .map(|()| commitment)
+    }
+
+    /// Report safety violations detected by this node to `handler` instead of only logging them.
+    pub async fn set_safety_alert_handler(&self, handler: Arc<dyn SafetyAlertHandler<TYPES>>) {
+        self.hotshot
+            .consensus()
+            .write()
+            .await
+            .set_safety_alert_handler(handler);
+    }
+
     /// Get the underlying consensus state for this [`SystemContext`]
     #[must_use]
     pub fn consensus(&self) -> Arc<RwLock<Consensus<TYPES>>> {
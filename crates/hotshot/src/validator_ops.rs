@@ -0,0 +1,271 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Stake tables driven by validator operations.
+//!
+//! A [`ValidatorRegistry`] applies the [`SignedOperation`]s of decided blocks to a
+//! [`ValidatorSet`], and provides the resulting stake table of each epoch to an election such as
+//! [`ProvidedCommittee`](crate::traits::election::provided_committee::ProvidedCommittee). It also
+//! records the outcome of each operation, so an operator can tell when its registration, stake
+//! update, key rotation or exit takes effect.
+//!
+//! Every node must apply the same operations in the same order, so the registry follows decided
+//! leaves, typically read back from the archive, rather than the event stream, which can skip
+//! leaves or lack their payloads:
+//!
+//! ```ignore
+//! let registry = ValidatorRegistry::new(chain_id, epoch_height, stake_table, da_stake_table);
+//! let membership = ProvidedCommittee::with_provider(Arc::new(registry.clone()));
+//! // Start the node with `membership` and archive its chain, then
+//! let _follower = registry.follow(finalized.subscribe(LeafCursor::Genesis));
+//!
+//! let nonce = registry.next_nonce(handle.public_key());
+//! let exit = ValidatorOperation::Exit { key: handle.public_key().clone() };
+//! let exit = handle.sign_validator_operation(exit, nonce, None).await?;
+//! let commitment = handle.submit_validator_operation(&exit).await?;
+//! // Once decided
+//! let status = registry.status(&commitment);
+//! ```
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
+use anyhow::{bail, Context, Result};
+use committable::{Commitment, Committable};
+use futures::{Stream, StreamExt};
+use hotshot_types::{
+    data::Leaf2,
+    traits::{
+        block_contents::BlockHeader,
+        election::StakeTableProvider,
+        node_implementation::{ConsensusTime, NodeType},
+        BlockPayload,
+    },
+    utils::epoch_from_block_number,
+    validator_ops::{
+        effective_epoch, OperationError, OperationTransaction, SignedOperation, ValidatorSet,
+        ACTIVATION_DELAY_EPOCHS,
+    },
+    PeerConfig,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use tokio::task::JoinHandle;
+
+/// What became of a decided validator operation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub enum OperationStatus<TYPES: NodeType> {
+    /// The operation was applied
+    Applied {
+        /// Height of the block it was decided in
+        height: u64,
+        /// The first epoch whose stake table reflects it
+        effective_epoch: TYPES::Epoch,
+    },
+    /// The operation was decided but is invalid, and changed nothing
+    Invalid {
+        /// Height of the block it was decided in
+        height: u64,
+        /// Why it is invalid
+        reason: OperationError,
+    },
+}
+
+/// The state of a registry, shared between its clones.
+#[derive(Debug)]
+struct RegistryState<TYPES: NodeType> {
+    /// The validators after the latest applied leaf
+    validators: ValidatorSet<TYPES::SignatureKey>,
+    /// The stake table taking effect in each epoch in which operations changed it
+    stake_tables: BTreeMap<u64, Vec<PeerConfig<TYPES::SignatureKey>>>,
+    /// Height of the latest applied leaf
+    height: Option<u64>,
+    /// The outcome of each decided operation, by the commitment of its transaction
+    statuses: HashMap<Commitment<TYPES::Transaction>, OperationStatus<TYPES>>,
+}
+
+/// Applies decided validator operations, and provides the stake tables they lead to.
+///
+/// The DA stake table is not affected by operations, and stays the one the registry starts with.
+#[derive(Clone, Debug)]
+pub struct ValidatorRegistry<TYPES: NodeType> {
+    /// Number of blocks in an epoch
+    epoch_height: u64,
+    /// The DA stake table of every epoch
+    da_stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+    /// The validators and the outcome of operations
+    state: Arc<RwLock<RegistryState<TYPES>>>,
+}
+
+impl<TYPES: NodeType> ValidatorRegistry<TYPES>
+where
+    TYPES::Transaction: OperationTransaction,
+{
+    /// A registry of the chain `chain_id`, starting from `stake_table` and `da_stake_table`.
+    #[must_use]
+    pub fn new(
+        chain_id: u64,
+        epoch_height: u64,
+        stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+        da_stake_table: Vec<PeerConfig<TYPES::SignatureKey>>,
+    ) -> Self {
+        let state = RegistryState {
+            validators: ValidatorSet::new(chain_id, stake_table.clone()),
+            stake_tables: BTreeMap::from([(0, stake_table)]),
+            height: None,
+            statuses: HashMap::new(),
+        };
+        Self {
+            epoch_height,
+            da_stake_table,
+            state: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    /// The outcome of the operation carried by the transaction with `commitment`, once decided.
+    #[must_use]
+    pub fn status(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<OperationStatus<TYPES>> {
+        self.state.read().statuses.get(commitment).cloned()
+    }
+
+    /// The stake-table entry of the validator with `key` after the latest applied leaf.
+    #[must_use]
+    pub fn validator(&self, key: &TYPES::SignatureKey) -> Option<PeerConfig<TYPES::SignatureKey>> {
+        self.state.read().validators.validator(key).cloned()
+    }
+
+    /// The nonce the next operation signed by `key` must have.
+    #[must_use]
+    pub fn next_nonce(&self, key: &TYPES::SignatureKey) -> u64 {
+        self.state.read().validators.next_nonce(key)
+    }
+
+    /// Apply the operations in `leaf`, which must follow the previously applied leaf.
+    ///
+    /// A leaf which was already applied is ignored.
+    ///
+    /// # Errors
+    /// If the leaf does not follow the previous one or lacks its payload. The registry is then
+    /// unchanged, since applying it could make it disagree with other nodes.
+    pub fn apply_leaf(&self, leaf: &Leaf2<TYPES>) -> Result<()> {
+        let height = leaf.height();
+        let mut state = self.state.write();
+        match state.height {
+            Some(applied) if height <= applied => return Ok(()),
+            Some(applied) if height != applied + 1 => {
+                bail!("Leaf at height {height} does not follow the leaf at height {applied}")
+            }
+            _ => {}
+        }
+        let payload = leaf
+            .block_payload()
+            .with_context(|| format!("Leaf at height {height} has no payload"))?;
+
+        let epoch = effective_epoch(height, self.epoch_height);
+        let mut changed = false;
+        for transaction in payload.transactions(leaf.block_header().metadata()) {
+            let Some(operation) =
+                SignedOperation::<TYPES::SignatureKey>::from_transaction(&transaction)
+            else {
+                continue;
+            };
+            let status = match state.validators.apply(&operation, epoch) {
+                Ok(()) => {
+                    changed = true;
+                    OperationStatus::Applied {
+                        height,
+                        effective_epoch: TYPES::Epoch::new(epoch),
+                    }
+                }
+                Err(reason) => {
+                    tracing::info!("Ignoring invalid validator operation: {reason}");
+                    OperationStatus::Invalid { height, reason }
+                }
+            };
+            state.statuses.insert(transaction.commit(), status);
+        }
+        if changed {
+            let stake_table = state.validators.stake_table();
+            state.stake_tables.insert(epoch, stake_table);
+        }
+        state.height = Some(height);
+
+        Ok(())
+    }
+
+    /// Start applying the operations of `leaves`.
+    ///
+    /// The registry stops at the first error of the stream or leaf it cannot apply, leaving the
+    /// stake tables of later epochs unknown. It also stops when the returned
+    /// [`RegistryFollower`] is dropped.
+    #[must_use]
+    pub fn follow(
+        &self,
+        leaves: impl Stream<Item = Result<Leaf2<TYPES>>> + Send + Unpin + 'static,
+    ) -> RegistryFollower {
+        RegistryFollower {
+            task: tokio::spawn(follow(self.clone(), leaves)),
+        }
+    }
+}
+
+impl<TYPES: NodeType> StakeTableProvider<TYPES> for ValidatorRegistry<TYPES> {
+    fn stake_tables(
+        &self,
+        epoch: TYPES::Epoch,
+    ) -> Option<(
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+    )> {
+        let state = self.state.read();
+        // Operations decided later in the current epoch may still change the stake table taking
+        // effect `ACTIVATION_DELAY_EPOCHS` after it
+        let current = state.height.map_or(0, |height| {
+            epoch_from_block_number(height, self.epoch_height)
+        });
+        if *epoch >= current + ACTIVATION_DELAY_EPOCHS {
+            return None;
+        }
+        let (_, stake_table) = state.stake_tables.range(..=*epoch).next_back()?;
+
+        Some((stake_table.clone(), self.da_stake_table.clone()))
+    }
+}
+
+/// Applies decided leaves to a [`ValidatorRegistry`].
+///
+/// The registry stops following leaves when the follower is dropped.
+pub struct RegistryFollower {
+    /// The task applying leaves
+    task: JoinHandle<()>,
+}
+
+impl Drop for RegistryFollower {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Apply `leaves` to `registry` until the stream ends or fails.
+async fn follow<TYPES: NodeType>(
+    registry: ValidatorRegistry<TYPES>,
+    mut leaves: impl Stream<Item = Result<Leaf2<TYPES>>> + Send + Unpin,
+) where
+    TYPES::Transaction: OperationTransaction,
+{
+    while let Some(leaf) = leaves.next().await {
+        if let Err(e) = leaf.and_then(|leaf| registry.apply_leaf(&leaf)) {
+            tracing::error!("Validator registry stopped, later stake tables stay unknown: {e:#}");
+            return;
+        }
+    }
+}
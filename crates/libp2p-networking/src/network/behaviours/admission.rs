@@ -0,0 +1,394 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    net::IpAddr,
+    task::{Context, Poll},
+};
+
+use libp2p::{
+    core::{multiaddr::Protocol, transport::PortUse, Endpoint},
+    swarm::{
+        dummy, CloseConnection, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm,
+        ListenFailure, NetworkBehaviour, THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr,
+};
+use libp2p_identity::PeerId;
+use tracing::warn;
+
+use crate::network::transport::{PeerRole, PeerRoles};
+
+/// Limits on the connections remote peers open to us
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AdmissionConfig {
+    /// The maximum number of established inbound connections
+    pub max_inbound: usize,
+    /// The maximum number of inbound connections, established or not, from a single IP address.
+    /// Loopback addresses are exempt, so that local clusters can run on one host
+    pub max_inbound_per_ip: usize,
+    /// The maximum number of inbound connections still in their handshake
+    pub max_pending_handshakes: usize,
+    /// The number of inbound connections only validators are admitted to. Observers are turned
+    /// away once the others are taken
+    pub reserved_validator_slots: usize,
+}
+
+impl Default for AdmissionConfig {
+    fn default() -> Self {
+        Self {
+            max_inbound: 512,
+            max_inbound_per_ip: 16,
+            max_pending_handshakes: 128,
+            reserved_validator_slots: 256,
+        }
+    }
+}
+
+/// An established inbound connection, and what its peer proved in the handshake
+#[derive(Clone, Copy, Debug)]
+struct Admitted {
+    /// The remote peer
+    peer: PeerId,
+    /// The role the peer proved
+    role: PeerRole,
+    /// The stake of the peer, zero for observers
+    stake: u64,
+}
+
+impl Admitted {
+    /// How much we want to keep this connection: validators before observers, then by stake
+    fn rank(&self) -> (bool, u64) {
+        (self.role == PeerRole::Validator, self.stake)
+    }
+}
+
+/// Events of the [`AdmissionControl`] behaviour
+#[derive(Debug)]
+pub enum AdmissionEvent {
+    /// We closed the connection of a peer to make room for a peer outranking it
+    Evicted {
+        /// The peer whose connection we closed
+        peer: PeerId,
+        /// The peer we made room for
+        for_peer: PeerId,
+    },
+}
+
+/// Admission control of inbound connections, so that floods of connections cannot exhaust our
+/// file descriptors.
+///
+/// Handshakes in progress and connections per IP address are limited, before we know who the
+/// remote peer is. Once it authenticated, it is admitted if there is room for its role. When we are
+/// at capacity, a peer outranking the lowest ranked inbound connection takes its place: validators
+/// outrank observers, and validators with more stake outrank those with less.
+#[derive(Debug)]
+pub struct AdmissionControl {
+    /// Our limits
+    config: AdmissionConfig,
+    /// The roles peers proved in their handshakes, which the transport records
+    roles: PeerRoles,
+    /// Inbound connections still in their handshake
+    pending: HashSet<ConnectionId>,
+    /// The IP address of each inbound connection we count against the per-IP limit
+    connection_ips: HashMap<ConnectionId, IpAddr>,
+    /// Number of inbound connections from each IP address
+    per_ip: HashMap<IpAddr, usize>,
+    /// Established inbound connections
+    established: HashMap<ConnectionId, Admitted>,
+    /// Connections to close, with the peer we close them for
+    evictions: VecDeque<(ConnectionId, Admitted, PeerId)>,
+    /// Events to report
+    events: VecDeque<AdmissionEvent>,
+}
+
+impl AdmissionControl {
+    /// Admission control within `config`, reading roles from `roles`
+    #[must_use]
+    pub fn new(config: AdmissionConfig, roles: PeerRoles) -> Self {
+        Self {
+            config,
+            roles,
+            pending: HashSet::new(),
+            connection_ips: HashMap::new(),
+            per_ip: HashMap::new(),
+            established: HashMap::new(),
+            evictions: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    /// Admit a connection from `remote_addr` to its handshake, if we have room for it.
+    fn admit_pending(
+        &mut self,
+        connection_id: ConnectionId,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), String> {
+        if self.pending.len() >= self.config.max_pending_handshakes {
+            return Err(format!(
+                "Already {} handshakes in progress",
+                self.pending.len()
+            ));
+        }
+
+        if let Some(ip) = ip_of(remote_addr).filter(|ip| !ip.is_loopback()) {
+            let count = self.per_ip.entry(ip).or_default();
+            if *count >= self.config.max_inbound_per_ip {
+                return Err(format!("Already {count} connections from {ip}"));
+            }
+            *count += 1;
+            self.connection_ips.insert(connection_id, ip);
+        }
+
+        self.pending.insert(connection_id);
+        Ok(())
+    }
+
+    /// Admit the authenticated connection of `peer`, if there is room for its role, or if it
+    /// outranks a connection we can close for it.
+    fn admit_established(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        role: PeerRole,
+        stake: u64,
+    ) -> Result<(), String> {
+        self.pending.remove(&connection_id);
+        let admitted = Admitted { peer, role, stake };
+
+        let unreserved = self
+            .config
+            .max_inbound
+            .saturating_sub(self.config.reserved_validator_slots);
+        let limit = match role {
+            PeerRole::Validator => self.config.max_inbound,
+            PeerRole::Observer => unreserved,
+        };
+        if self.established.len() < limit {
+            self.established.insert(connection_id, admitted);
+            return Ok(());
+        }
+
+        // We are at capacity for this role: take the place of the lowest ranked connection, if we
+        // outrank it
+        let lowest = self
+            .established
+            .iter()
+            .filter(|(id, _)| !self.evictions.iter().any(|(evicted, _, _)| evicted == *id))
+            .min_by_key(|(_, other)| other.rank())
+            .map(|(id, other)| (*id, *other));
+        match lowest {
+            Some((id, other)) if other.rank() < admitted.rank() => {
+                self.evictions.push_back((id, other, peer));
+                self.established.insert(connection_id, admitted);
+                Ok(())
+            }
+            _ => Err(format!(
+                "Already {} inbound connections, none ranked below {role:?} {peer}",
+                self.established.len()
+            )),
+        }
+    }
+
+    /// Forget an inbound connection which failed or closed.
+    fn forget(&mut self, connection_id: ConnectionId) {
+        self.pending.remove(&connection_id);
+        self.established.remove(&connection_id);
+        if let Some(ip) = self.connection_ips.remove(&connection_id) {
+            if let Some(count) = self.per_ip.get_mut(&ip) {
+                *count -= 1;
+                if *count == 0 {
+                    self.per_ip.remove(&ip);
+                }
+            }
+        }
+    }
+}
+
+/// The IP address of `address`, if it has one
+fn ip_of(address: &Multiaddr) -> Option<IpAddr> {
+    address.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+impl NetworkBehaviour for AdmissionControl {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = AdmissionEvent;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.admit_pending(connection_id, remote_addr)
+            .map_err(|reason| {
+                warn!("Refusing connection from {remote_addr}: {reason}");
+                ConnectionDenied::new(reason)
+            })
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        // Peers the transport did not tag had nothing to prove, and are treated like observers
+        let (role, stake) = self.roles.get(&peer).unwrap_or((PeerRole::Observer, 0));
+        self.admit_established(connection_id, peer, role, stake)
+            .map_err(|reason| {
+                warn!("Refusing connection from {peer} at {remote_addr}: {reason}");
+                ConnectionDenied::new(reason)
+            })?;
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _peer: PeerId,
+        _addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        // We only limit the connections others open to us
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        match event {
+            FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. })
+            | FromSwarm::ListenFailure(ListenFailure { connection_id, .. }) => {
+                self.forget(connection_id);
+            }
+            _ => {}
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        // Close evicted connections first, then report them
+        if let Some((connection_id, evicted, for_peer)) = self.evictions.pop_front() {
+            self.established.remove(&connection_id);
+            self.events.push_back(AdmissionEvent::Evicted {
+                peer: evicted.peer,
+                for_peer,
+            });
+            return Poll::Ready(ToSwarm::CloseConnection {
+                peer_id: evicted.peer,
+                connection: CloseConnection::One(connection_id),
+            });
+        }
+        if let Some(event) = self.events.pop_front() {
+            return Poll::Ready(ToSwarm::GenerateEvent(event));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Admission control with room for `max_inbound` connections, half of them reserved
+    fn admission(max_inbound: usize) -> AdmissionControl {
+        AdmissionControl::new(
+            AdmissionConfig {
+                max_inbound,
+                max_inbound_per_ip: 2,
+                max_pending_handshakes: 4,
+                reserved_validator_slots: max_inbound / 2,
+            },
+            PeerRoles::default(),
+        )
+    }
+
+    #[test]
+    fn pending_and_per_ip_limits() {
+        let mut admission = admission(8);
+        let remote: Multiaddr = "/ip4/10.0.0.1/udp/1000/quic-v1".parse().unwrap();
+        let local: Multiaddr = "/ip4/127.0.0.1/udp/1000/quic-v1".parse().unwrap();
+
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(0), &remote)
+            .is_ok());
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(1), &remote)
+            .is_ok());
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(2), &remote)
+            .is_err());
+
+        // Closing a connection frees its address up
+        admission.forget(ConnectionId::new_unchecked(0));
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(3), &remote)
+            .is_ok());
+
+        // Loopback addresses are exempt from the per-IP limit, but not from the pending one
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(4), &local)
+            .is_ok());
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(5), &local)
+            .is_ok());
+        assert!(admission
+            .admit_pending(ConnectionId::new_unchecked(6), &local)
+            .is_err());
+    }
+
+    #[test]
+    fn validators_keep_their_slots() {
+        let mut admission = admission(4);
+        let mut next_id = 0;
+        let mut admit = |admission: &mut AdmissionControl, role, stake| {
+            next_id += 1;
+            admission.admit_established(
+                ConnectionId::new_unchecked(next_id),
+                PeerId::random(),
+                role,
+                stake,
+            )
+        };
+
+        // Observers only get the unreserved slots
+        assert!(admit(&mut admission, PeerRole::Observer, 0).is_ok());
+        assert!(admit(&mut admission, PeerRole::Observer, 0).is_ok());
+        assert!(admit(&mut admission, PeerRole::Observer, 0).is_err());
+
+        // Validators get the reserved ones
+        assert!(admit(&mut admission, PeerRole::Validator, 5).is_ok());
+        assert!(admit(&mut admission, PeerRole::Validator, 3).is_ok());
+        assert!(admission.evictions.is_empty());
+
+        // At capacity, a validator takes the place of an observer, then of the validator with the
+        // least stake, if it has more
+        assert!(admit(&mut admission, PeerRole::Validator, 1).is_ok());
+        assert!(admit(&mut admission, PeerRole::Validator, 1).is_ok());
+        assert!(admit(&mut admission, PeerRole::Validator, 2).is_ok());
+        assert_eq!(admission.evictions.len(), 3);
+        assert_eq!(admission.evictions[2].1.stake, 1);
+        assert!(admit(&mut admission, PeerRole::Validator, 1).is_err());
+        assert!(admit(&mut admission, PeerRole::Observer, 0).is_err());
+    }
+}
@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use libp2p::request_response::{Event, Message, OutboundRequestId, ResponseChannel};
 use libp2p_identity::PeerId;
@@ -12,7 +12,7 @@ use tokio::{spawn, sync::mpsc::UnboundedSender, time::sleep};
 use tracing::{debug, error, warn};
 
 use super::exponential_backoff::ExponentialBackoff;
-use crate::network::{ClientRequest, NetworkEvent};
+use crate::network::{peer_stats::PeerTracker, ClientRequest, NetworkEvent};
 
 /// Request to direct message a peert
 #[derive(Debug)]
@@ -25,6 +25,8 @@ pub struct DMRequest {
     pub backoff: ExponentialBackoff,
     /// the number of remaining retries before giving up
     pub(crate) retry_count: u8,
+    /// when the request was sent
+    pub(crate) sent_at: Instant,
 }
 
 /// Wrapper metadata around libp2p's request response
@@ -50,6 +52,7 @@ impl DMBehaviour {
         &mut self,
         event: Event<Vec<u8>, Vec<u8>>,
         retry_tx: Option<UnboundedSender<ClientRequest>>,
+        peer_tracker: &mut PeerTracker,
     ) -> Option<NetworkEvent> {
         match event {
             Event::InboundFailure {
@@ -58,6 +61,7 @@ impl DMBehaviour {
                 error,
             } => {
                 error!("Inbound message failure from {:?}: {:?}", peer, error);
+                peer_tracker.failed(peer);
                 None
             }
             Event::OutboundFailure {
@@ -66,6 +70,7 @@ impl DMBehaviour {
                 error,
             } => {
                 warn!("Outbound message failure to {:?}: {:?}", peer, error);
+                peer_tracker.failed(peer);
                 if let Some(mut req) = self.in_progress_rr.remove(&request_id) {
                     if req.retry_count == 0 {
                         return None;
@@ -91,6 +96,7 @@ impl DMBehaviour {
                     ..
                 } => {
                     debug!("Received direct request {:?}", msg);
+                    peer_tracker.seen(peer);
                     // receiver, not initiator.
                     // don't track. If we are disconnected, sender will reinitiate
                     Some(NetworkEvent::DirectRequest(msg, peer, channel))
@@ -102,6 +108,7 @@ impl DMBehaviour {
                     // success, finished.
                     if let Some(req) = self.in_progress_rr.remove(&request_id) {
                         debug!("Received direct response {:?}", msg);
+                        peer_tracker.answered(req.peer_id, req.sent_at.elapsed());
                         Some(NetworkEvent::DirectResponse(msg, req.peer_id))
                     } else {
                         warn!("Received response for unknown request id {:?}", request_id);
@@ -4,6 +4,9 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
+/// Admission control of inbound connections
+pub mod admission;
+
 /// Wrapper around `RequestResponse`
 pub mod direct_message;
 
@@ -0,0 +1,249 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compression of direct messages, negotiated with each peer when we connect to it.
+//!
+//! Compressing messages costs CPU time, which is only worth spending when the link to the peer is
+//! slow. During the connection handshake each side offers the algorithms it supports and the size
+//! below which it does not bother compressing, and the outcome is recorded for the peer. Every
+//! direct message then starts with a byte which says how the rest of it is compressed, so a node
+//! only ever has to decompress with an algorithm it offered.
+
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{bail, ensure, Context, Result as AnyhowResult};
+use libp2p_identity::PeerId;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+
+/// The `zstd` compression level we compress with
+const ZSTD_LEVEL: i32 = 3;
+
+/// The size above which messages to peers in a WAN deployment are compressed
+const WAN_THRESHOLD: usize = 1024;
+
+/// An algorithm a direct message can be compressed with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Compression {
+    /// The message is sent as is
+    #[default]
+    None,
+    /// The message is compressed with `zstd`
+    Zstd,
+}
+
+impl Compression {
+    /// The byte which marks a message compressed with this algorithm
+    fn tag(self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Zstd => 1,
+        }
+    }
+
+    /// The algorithm marked by `tag`, if we know it
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            0 => Some(Self::None),
+            1 => Some(Self::Zstd),
+            _ => None,
+        }
+    }
+}
+
+/// The compression we offer to peers during the connection handshake
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompressionConfig {
+    /// The algorithms we support, most preferred first. Uncompressed messages are always accepted.
+    pub algorithms: Vec<Compression>,
+    /// Messages smaller than this many bytes are not compressed
+    pub threshold: usize,
+}
+
+impl CompressionConfig {
+    /// No compression, for clusters on a local network where bandwidth is cheap
+    #[must_use]
+    pub fn lan() -> Self {
+        Self {
+            algorithms: vec![Compression::None],
+            threshold: 0,
+        }
+    }
+
+    /// `zstd` compression of all but small messages, for deployments spread over a WAN
+    #[must_use]
+    pub fn wan() -> Self {
+        Self {
+            algorithms: vec![Compression::Zstd, Compression::None],
+            threshold: WAN_THRESHOLD,
+        }
+    }
+
+    /// Whether a peer may send us messages compressed with `algorithm`
+    #[must_use]
+    pub fn accepts(&self, algorithm: Compression) -> bool {
+        algorithm == Compression::None || self.algorithms.contains(&algorithm)
+    }
+
+    /// How we compress messages to a peer which offered `remote`: with the first of our algorithms
+    /// the peer accepts, and only above both sides' thresholds.
+    #[must_use]
+    pub fn negotiate(&self, remote: &CompressionConfig) -> PeerCompression {
+        let algorithm = self
+            .algorithms
+            .iter()
+            .copied()
+            .find(|algorithm| remote.accepts(*algorithm))
+            .unwrap_or_default();
+
+        PeerCompression {
+            algorithm,
+            threshold: self.threshold.max(remote.threshold),
+        }
+    }
+
+    /// Decompress a direct message, which may only be compressed with an algorithm we offered.
+    /// The decompressed message may be at most `max_size` bytes long.
+    ///
+    /// # Errors
+    /// - If the message is empty or compressed with an algorithm we did not offer
+    /// - If the message fails to decompress or is too large once decompressed
+    pub fn decompress(&self, message: &[u8], max_size: usize) -> AnyhowResult<Vec<u8>> {
+        let (tag, payload) = message
+            .split_first()
+            .with_context(|| "Message is missing its compression tag")?;
+        let Some(algorithm) = Compression::from_tag(*tag) else {
+            bail!("Message is compressed with an unknown algorithm {tag}");
+        };
+        ensure!(
+            self.accepts(algorithm),
+            "Message is compressed with {algorithm:?}, which we did not offer"
+        );
+
+        match algorithm {
+            Compression::None => Ok(payload.to_vec()),
+            Compression::Zstd => zstd::bulk::decompress(payload, max_size)
+                .with_context(|| "Failed to decompress message"),
+        }
+    }
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self::lan()
+    }
+}
+
+/// How we compress direct messages to a peer, as negotiated when we connected to it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PeerCompression {
+    /// The algorithm we compress with
+    pub algorithm: Compression,
+    /// Messages smaller than this many bytes are sent uncompressed
+    pub threshold: usize,
+}
+
+impl PeerCompression {
+    /// Prefix `message` with its compression tag, compressing it first if it is large enough.
+    #[must_use]
+    pub fn compress(&self, message: &[u8]) -> Vec<u8> {
+        if self.algorithm == Compression::Zstd && message.len() >= self.threshold {
+            match zstd::bulk::compress(message, ZSTD_LEVEL) {
+                Ok(compressed) => return tagged(Compression::Zstd, &compressed),
+                Err(e) => tracing::warn!("Failed to compress message, sending it as is: {e}"),
+            }
+        }
+
+        tagged(Compression::None, message)
+    }
+}
+
+/// `payload` prefixed with the tag of `algorithm`
+fn tagged(algorithm: Compression, payload: &[u8]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(payload.len() + 1);
+    message.push(algorithm.tag());
+    message.extend_from_slice(payload);
+    message
+}
+
+/// The outcomes of connection handshakes, which the transport records for the node to pick up
+/// once the connection is established
+#[derive(Clone, Debug, Default)]
+pub struct NegotiatedCompression(Arc<Mutex<HashMap<PeerId, PeerCompression>>>);
+
+impl NegotiatedCompression {
+    /// Record the compression negotiated with `peer`
+    pub fn record(&self, peer: PeerId, compression: PeerCompression) {
+        self.0.lock().insert(peer, compression);
+    }
+
+    /// Take the compression negotiated with `peer`, if a handshake with it finished since we last
+    /// asked
+    #[must_use]
+    pub fn take(&self, peer: &PeerId) -> Option<PeerCompression> {
+        self.0.lock().remove(peer)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test that each side compresses with the best algorithm the other accepts
+    #[test]
+    fn negotiation() {
+        let lan = CompressionConfig::lan();
+        let wan = CompressionConfig::wan();
+
+        assert_eq!(
+            wan.negotiate(&wan),
+            PeerCompression {
+                algorithm: Compression::Zstd,
+                threshold: WAN_THRESHOLD,
+            }
+        );
+        assert_eq!(lan.negotiate(&lan).algorithm, Compression::None);
+
+        // A peer which does not offer `zstd` never receives `zstd`
+        assert_eq!(wan.negotiate(&lan).algorithm, Compression::None);
+        assert_eq!(lan.negotiate(&wan).algorithm, Compression::None);
+    }
+
+    /// Test that messages survive compression, and that small ones are left as they are
+    #[test]
+    fn round_trip() {
+        let wan = CompressionConfig::wan();
+        let peer = wan.negotiate(&wan);
+
+        let large = vec![7u8; 4 * WAN_THRESHOLD];
+        let compressed = peer.compress(&large);
+        assert!(compressed.len() < large.len());
+        assert_eq!(wan.decompress(&compressed, large.len()).unwrap(), large);
+
+        let small = vec![7u8; 16];
+        assert_eq!(peer.compress(&small)[1..], small[..]);
+        assert_eq!(wan.decompress(&peer.compress(&small), 16).unwrap(), small);
+    }
+
+    /// Test that we reject messages we cannot or did not agree to decompress
+    #[test]
+    fn rejects_unexpected_messages() {
+        let wan = CompressionConfig::wan();
+        let compressed = wan.negotiate(&wan).compress(&[7u8; 4 * WAN_THRESHOLD]);
+
+        // An algorithm we did not offer
+        assert!(CompressionConfig::lan()
+            .decompress(&compressed, 4 * WAN_THRESHOLD)
+            .is_err());
+
+        // A message larger than we allow
+        assert!(wan.decompress(&compressed, WAN_THRESHOLD).is_err());
+
+        // No tag, or an unknown one
+        assert!(wan.decompress(&[], 16).is_err());
+        assert!(wan.decompress(&[9, 1, 2, 3], 16).is_err());
+    }
+}
@@ -4,6 +4,8 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
+use std::collections::HashSet;
+
 use hotshot_types::traits::signature_key::SignatureKey;
 use libp2p::{
     autonat,
@@ -18,7 +20,10 @@ use libp2p_swarm_derive::NetworkBehaviour;
 use tracing::{debug, error};
 
 use super::{
-    behaviours::dht::store::{file_backed::FileBackedStore, validated::ValidatedStore},
+    behaviours::{
+        admission::{AdmissionControl, AdmissionEvent},
+        dht::store::{file_backed::FileBackedStore, validated::ValidatedStore},
+    },
     cbor, NetworkEventInternal,
 };
 
@@ -27,6 +32,7 @@ use super::{
 /// - direct messaging
 /// - p2p broadcast
 /// - connection management
+/// - admission control of inbound connections
 #[derive(NetworkBehaviour, derive_more::Debug)]
 #[behaviour(to_swarm = "NetworkEventInternal")]
 pub struct NetworkDef<K: SignatureKey + 'static> {
@@ -54,6 +60,9 @@ pub struct NetworkDef<K: SignatureKey + 'static> {
     /// by which address
     #[debug(skip)]
     pub autonat: libp2p::autonat::Behaviour,
+
+    /// purpose: limiting the connections remote peers open to us
+    pub admission: AdmissionControl,
 }
 
 impl<K: SignatureKey + 'static> NetworkDef<K> {
@@ -65,6 +74,7 @@ impl<K: SignatureKey + 'static> NetworkDef<K> {
         identify: IdentifyBehaviour,
         direct_message: super::cbor::Behaviour<Vec<u8>, Vec<u8>>,
         autonat: autonat::Behaviour,
+        admission: AdmissionControl,
     ) -> NetworkDef<K> {
         Self {
             gossipsub,
@@ -72,6 +82,7 @@ impl<K: SignatureKey + 'static> NetworkDef<K> {
             identify,
             direct_message,
             autonat,
+            admission,
         }
     }
 }
@@ -111,6 +122,21 @@ impl<K: SignatureKey + 'static> NetworkDef<K> {
             error!("Failed to unsubscribe from topic {:?}. Error: {:?}", t, e);
         }
     }
+
+    /// The peers in our gossip mesh, on any topic
+    pub fn gossip_mesh_peers(&self) -> HashSet<PeerId> {
+        self.gossipsub.all_mesh_peers().copied().collect()
+    }
+
+    /// Send all gossip to `peer`, whether or not it is in our mesh
+    pub fn add_fanout_peer(&mut self, peer: &PeerId) {
+        self.gossipsub.add_explicit_peer(peer);
+    }
+
+    /// Stop sending all gossip to `peer`, which was added with [`Self::add_fanout_peer`]
+    pub fn remove_fanout_peer(&mut self, peer: &PeerId) {
+        self.gossipsub.remove_explicit_peer(peer);
+    }
 }
 
 /// Request/response functions
@@ -154,3 +180,9 @@ impl From<libp2p::autonat::Event> for NetworkEventInternal {
         Self::AutonatEvent(event)
     }
 }
+
+impl From<AdmissionEvent> for NetworkEventInternal {
+    fn from(event: AdmissionEvent) -> Self {
+        Self::AdmissionEvent(event)
+    }
+}
@@ -6,17 +6,25 @@
 
 /// networking behaviours wrapping libp2p's behaviours
 pub mod behaviours;
+/// compression of direct messages, negotiated per peer
+pub mod compression;
 /// defines the swarm and network definition (internal)
 mod def;
 /// functionality of a libp2p network node
 mod node;
+/// per-peer connection statistics
+pub mod peer_stats;
 /// Alternative Libp2p transport implementations
 pub mod transport;
 
 /// Forked `cbor` codec with altered request/response sizes
 pub mod cbor;
 
-use std::{collections::HashSet, fmt::Debug};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    net::Ipv6Addr,
+};
 
 use futures::channel::oneshot::Sender;
 use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
@@ -32,9 +40,15 @@ use libp2p::{
     Multiaddr, Transport,
 };
 use libp2p_identity::PeerId;
+use peer_stats::PeerStats;
 use quic::tokio::Transport as QuicTransport;
 use tracing::instrument;
-use transport::StakeTableAuthentication;
+use transport::{AuthConfig, NetworkInfo, PeerRoles, StakeTableAuthentication};
+
+use self::{
+    behaviours::admission::AdmissionEvent,
+    compression::{CompressionConfig, NegotiatedCompression},
+};
 
 pub use self::{
     def::NetworkDef,
@@ -99,10 +113,14 @@ pub enum ClientRequest {
     GetConnectedPeerNum(Sender<usize>),
     /// Request the set of connected peers
     GetConnectedPeers(Sender<HashSet<PeerId>>),
+    /// Request the connection statistics of every peer we have dealt with
+    GetPeerStats(Sender<HashMap<PeerId, PeerStats>>),
     /// Print the routing  table to stderr, debugging only
     GetRoutingTable(Sender<()>),
     /// Get address of peer
     LookupPeer(PeerId, Sender<()>),
+    /// Send gossip to this many connected peers outside the mesh, on top of the mesh
+    SetExtraFanout(usize),
 }
 
 /// events generated by the swarm that we wish
@@ -136,6 +154,8 @@ pub enum NetworkEventInternal {
     DMEvent(libp2p::request_response::Event<Vec<u8>, Vec<u8>>),
     /// a autonat event
     AutonatEvent(libp2p::autonat::Event),
+    /// an admission control event
+    AdmissionEvent(AdmissionEvent),
 }
 
 /// Bind all interfaces on port `port`
@@ -145,22 +165,35 @@ pub fn gen_multiaddr(port: u16) -> Multiaddr {
     build_multiaddr!(Ip4([0, 0, 0, 0]), Udp(port), QuicV1)
 }
 
+/// Bind all IPv6 interfaces on port `port`, to listen on next to [`gen_multiaddr`] on dual-stack
+/// hosts
+#[must_use]
+pub fn gen_multiaddr_v6(port: u16) -> Multiaddr {
+    build_multiaddr!(Ip6(Ipv6Addr::UNSPECIFIED), Udp(port), QuicV1)
+}
+
 /// `BoxedTransport` is a type alias for a boxed tuple containing a `PeerId` and a `StreamMuxerBox`.
 ///
 /// This type is used to represent a transport in the libp2p network framework. The `PeerId` is a unique identifier for each peer in the network, and the `StreamMuxerBox` is a type of multiplexer that can handle multiple substreams over a single connection.
 type BoxedTransport = Boxed<(PeerId, StreamMuxerBox)>;
 
-/// Generates an authenticated transport checked against the stake table.
-/// If the stake table or authentication message is not provided, the transport will
-/// not participate in stake table authentication.
+/// Generates an authenticated transport checked against the stake table, as `auth` describes.
+/// If the stake table is not provided, the transport will not check remote peers against it.
+///
+/// Peers which are not part of the network described by `network_info` are rejected, the
+/// compression negotiated with each peer during the handshake is recorded in `negotiated`, and
+/// the role each peer proved in `roles`.
 ///
 /// # Errors
 /// If we could not create a DNS transport
 #[instrument(skip(identity))]
 pub async fn gen_transport<T: NodeType>(
     identity: Keypair,
-    stake_table: Option<T::Membership>,
-    auth_message: Option<Vec<u8>>,
+    auth: AuthConfig<T>,
+    compression: CompressionConfig,
+    negotiated: NegotiatedCompression,
+    roles: PeerRoles,
+    network_info: NetworkInfo,
 ) -> Result<BoxedTransport, NetworkError> {
     // Create the initial `Quic` transport
     let transport = {
@@ -170,8 +203,14 @@ pub async fn gen_transport<T: NodeType>(
     };
 
     // Require authentication against the stake table
-    let transport: StakeTableAuthentication<_, T, _> =
-        StakeTableAuthentication::new(transport, stake_table, auth_message);
+    let transport: StakeTableAuthentication<_, T, _> = StakeTableAuthentication::new(
+        transport,
+        auth,
+        compression,
+        negotiated,
+        roles,
+        network_info,
+    );
 
     // Support DNS resolution
     let transport = {
@@ -15,7 +15,7 @@ use std::{
     collections::{HashMap, HashSet},
     iter,
     num::{NonZeroU32, NonZeroUsize},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use futures::{channel::mpsc, SinkExt, StreamExt};
@@ -62,10 +62,14 @@ use super::{
         store::{file_backed::FileBackedStore, validated::ValidatedStore},
     },
     cbor::Cbor,
-    gen_transport, BoxedTransport, ClientRequest, NetworkDef, NetworkError, NetworkEvent,
-    NetworkEventInternal,
+    compression::{CompressionConfig, NegotiatedCompression, PeerCompression},
+    gen_transport,
+    peer_stats::PeerTracker,
+    transport::{AuthConfig, PeerRole, PeerRoles},
+    BoxedTransport, ClientRequest, NetworkDef, NetworkError, NetworkEvent, NetworkEventInternal,
 };
 use crate::network::behaviours::{
+    admission::{AdmissionControl, AdmissionEvent},
     dht::{DHTBehaviour, DHTProgress, KadPutQuery, NUM_REPLICATED_TO_TRUST},
     direct_message::{DMBehaviour, DMRequest},
     exponential_backoff::ExponentialBackoff,
@@ -88,14 +92,28 @@ pub struct NetworkNode<T: NodeType> {
     /// the swarm of networkbehaviours
     #[debug(skip)]
     swarm: Swarm<NetworkDef<T::SignatureKey>>,
-    /// the listener id we are listening on, if it exists
-    listener_id: Option<ListenerId>,
+    /// the ids of the listeners we are listening on
+    listener_ids: Vec<ListenerId>,
     /// Handler for direct messages
     direct_message_state: DMBehaviour,
     /// Handler for DHT Events
     dht_handler: DHTBehaviour<T::SignatureKey>,
     /// Channel to resend requests, set to Some when we call `spawn_listeners`
     resend_tx: Option<UnboundedSender<ClientRequest>>,
+    /// Connection statistics of the peers we have dealt with
+    peer_tracker: PeerTracker,
+    /// The compression we offered to peers, which they may compress their messages with
+    compression: CompressionConfig,
+    /// Compression negotiated by connection handshakes which we have not recorded yet
+    negotiated_compression: NegotiatedCompression,
+    /// Roles proved in connection handshakes which we have not recorded yet
+    peer_roles: PeerRoles,
+    /// The maximum number of observers we stay connected to
+    max_observers: usize,
+    /// Limits on the size of direct messages, which bound how large they may decompress to
+    request_response_config: RequestResponseConfig,
+    /// Peers outside the mesh we send all gossip to
+    extra_fanout_peers: HashSet<PeerId>,
 }
 
 impl<T: NodeType> NetworkNode<T> {
@@ -109,24 +127,58 @@ impl<T: NodeType> NetworkNode<T> {
         self.swarm.connected_peers().copied().collect()
     }
 
-    /// starts the swarm listening on `listen_addr`
-    /// and optionally dials into peer `known_peer`
-    /// returns the address the swarm is listening upon
+    /// starts the swarm listening on each of `listen_addrs`
+    /// returns the addresses the swarm is listening upon, at least one per listener
+    ///
+    /// # Errors
+    /// If we fail to listen on any of the addresses
     #[instrument(skip(self))]
     pub async fn start_listen(
         &mut self,
-        listen_addr: Multiaddr,
-    ) -> Result<Multiaddr, NetworkError> {
-        self.listener_id = Some(self.swarm.listen_on(listen_addr).map_err(|err| {
-            NetworkError::ListenError(format!("failed to listen for Libp2p: {err}"))
-        })?);
-        let addr = loop {
-            if let Some(SwarmEvent::NewListenAddr { address, .. }) = self.swarm.next().await {
-                break address;
+        listen_addrs: Vec<Multiaddr>,
+    ) -> Result<Vec<Multiaddr>, NetworkError> {
+        let mut starting = HashSet::new();
+        for listen_addr in listen_addrs {
+            let listener_id = self.swarm.listen_on(listen_addr.clone()).map_err(|err| {
+                NetworkError::ListenError(format!(
+                    "failed to listen for Libp2p on {listen_addr}: {err}"
+                ))
+            })?;
+            self.listener_ids.push(listener_id);
+            starting.insert(listener_id);
+        }
+
+        // Wait for every listener to report an address. Listeners on unspecified addresses report
+        // one per interface
+        let mut addrs = Vec::new();
+        while !starting.is_empty() {
+            match self.swarm.next().await {
+                Some(SwarmEvent::NewListenAddr {
+                    listener_id,
+                    address,
+                }) => {
+                    info!("Libp2p listening on {:?}", address);
+                    starting.remove(&listener_id);
+                    addrs.push(address);
+                }
+                Some(SwarmEvent::ListenerClosed {
+                    listener_id,
+                    reason,
+                    ..
+                }) if starting.contains(&listener_id) => {
+                    return Err(NetworkError::ListenError(format!(
+                        "Libp2p listener closed before listening: {reason:?}"
+                    )));
+                }
+                Some(_) => {}
+                None => {
+                    return Err(NetworkError::ListenError(
+                        "swarm stopped before listening".to_string(),
+                    ))
+                }
             }
-        };
-        info!("Libp2p listening on {:?}", addr);
-        Ok(addr)
+        }
+        Ok(addrs)
     }
 
     /// initialize the DHT with known peers
@@ -168,11 +220,23 @@ impl<T: NodeType> NetworkNode<T> {
         // Get the `PeerId` from the `KeyPair`
         let peer_id = PeerId::from(keypair.public());
 
-        // Generate the transport from the keypair, stake table, and auth message
+        // Generate the transport from the keypair, stake table, auth key, compression and
+        // network info
+        let negotiated_compression = NegotiatedCompression::default();
+        let peer_roles = PeerRoles::default();
+        let auth = AuthConfig {
+            stake_table: config.stake_table.clone(),
+            auth_key: config.auth_key.clone(),
+            peer_id,
+            accept_observers: config.max_observers > 0,
+        };
         let transport: BoxedTransport = gen_transport::<T>(
             keypair.clone(),
-            config.stake_table.clone(),
-            config.auth_message.clone(),
+            auth,
+            config.compression.clone(),
+            negotiated_compression.clone(),
+            peer_roles.clone(),
+            config.network_info.clone(),
         )
         .await?;
 
@@ -301,17 +365,24 @@ impl<T: NodeType> NetworkNode<T> {
                 identify,
                 direct_message,
                 autonat::Behaviour::new(peer_id, autonat_config),
+                AdmissionControl::new(config.admission, peer_roles.clone()),
             );
 
             // build swarm
             let swarm = SwarmBuilder::with_existing_identity(keypair.clone());
             let swarm = swarm.with_tokio();
 
+            // Race up to `dial_concurrency_factor` addresses of a peer when dialing it
+            let dial_concurrency_factor = config.dial_concurrency_factor;
             swarm
                 .with_other_transport(|_| transport)
                 .unwrap()
                 .with_behaviour(|_| network)
                 .unwrap()
+                .with_swarm_config(|swarm_config| match dial_concurrency_factor {
+                    Some(factor) => swarm_config.with_dial_concurrency_factor(factor),
+                    None => swarm_config,
+                })
                 .build()
         };
         for (peer, addr) in &config.to_connect_addrs {
@@ -323,7 +394,7 @@ impl<T: NodeType> NetworkNode<T> {
         Ok(Self {
             peer_id,
             swarm,
-            listener_id: None,
+            listener_ids: Vec::new(),
             direct_message_state: DMBehaviour::default(),
             dht_handler: DHTBehaviour::new(
                 peer_id,
@@ -332,9 +403,48 @@ impl<T: NodeType> NetworkNode<T> {
                     .unwrap_or(NonZeroUsize::new(4).unwrap()),
             ),
             resend_tx: None,
+            peer_tracker: PeerTracker::default(),
+            compression: config.compression.clone(),
+            negotiated_compression,
+            peer_roles,
+            max_observers: config.max_observers,
+            request_response_config: config.request_response_config.clone(),
+            extra_fanout_peers: HashSet::new(),
         })
     }
 
+    /// Send all gossip to `count` connected peers outside the mesh, keeping those we already send
+    /// it to where we can.
+    fn set_extra_fanout(&mut self, count: usize) {
+        let connected = self.connected_pids();
+        let behaviour = self.swarm.behaviour_mut();
+        let mesh = behaviour.gossip_mesh_peers();
+
+        // Drop the peers which left, or joined the mesh, and any beyond the count
+        let mut kept = HashSet::new();
+        for peer in self.extra_fanout_peers.drain() {
+            if kept.len() < count && connected.contains(&peer) && !mesh.contains(&peer) {
+                kept.insert(peer);
+            } else {
+                behaviour.remove_fanout_peer(&peer);
+            }
+        }
+        let mut candidates: Vec<_> = connected
+            .iter()
+            .filter(|peer| !mesh.contains(peer) && !kept.contains(peer))
+            .collect();
+        candidates.shuffle(&mut thread_rng());
+        for peer in candidates
+            .into_iter()
+            .take(count.saturating_sub(kept.len()))
+        {
+            behaviour.add_fanout_peer(peer);
+            kept.insert(*peer);
+        }
+        debug!("Sending gossip to {} peers outside the mesh", kept.len());
+        self.extra_fanout_peers = kept;
+    }
+
     /// Publish a key/value to the record store.
     ///
     /// # Panics
@@ -419,6 +529,11 @@ impl<T: NodeType> NetworkNode<T> {
                             error!("error sending peer set to client");
                         }
                     }
+                    ClientRequest::GetPeerStats(s) => {
+                        if s.send(self.peer_tracker.snapshot()).is_err() {
+                            error!("error sending peer stats to client");
+                        }
+                    }
                     ClientRequest::GetDHT {
                         key,
                         notify,
@@ -437,7 +552,7 @@ impl<T: NodeType> NetworkNode<T> {
                         // NOTE used by test with conductor only
                     }
                     ClientRequest::Shutdown => {
-                        if let Some(listener_id) = self.listener_id {
+                        for listener_id in self.listener_ids.drain(..) {
                             self.swarm.remove_listener(listener_id);
                         }
 
@@ -468,21 +583,30 @@ impl<T: NodeType> NetworkNode<T> {
                         retry_count,
                     } => {
                         debug!("Sending direct request to {:?}", pid);
-                        let id = behaviour.add_direct_request(pid, contents.clone());
+                        // Retries come back through here, so we keep the uncompressed contents
+                        let compressed = self.peer_tracker.compression(&pid).compress(&contents);
+                        let id = behaviour.add_direct_request(pid, compressed);
                         let req = DMRequest {
                             peer_id: pid,
                             data: contents,
                             backoff: ExponentialBackoff::default(),
                             retry_count,
+                            sent_at: Instant::now(),
                         };
                         self.direct_message_state.add_direct_request(req, id);
                     }
                     ClientRequest::DirectResponse(chan, msg) => {
+                        // We do not know which peer the channel leads to, so the response is
+                        // sent uncompressed, which every peer accepts
+                        let msg = PeerCompression::default().compress(&msg);
                         behaviour.add_direct_response(chan, msg);
                     }
                     ClientRequest::AddKnownPeers(peers) => {
                         self.add_known_peers(&peers);
                     }
+                    ClientRequest::SetExtraFanout(count) => {
+                        self.set_extra_fanout(count);
+                    }
                     ClientRequest::Prune(pid) => {
                         if self.swarm.disconnect_peer_id(pid).is_err() {
                             warn!("Could not disconnect from {:?}", pid);
@@ -497,6 +621,47 @@ impl<T: NodeType> NetworkNode<T> {
         Ok(false)
     }
 
+    /// Decompress the contents of a direct request or response, dropping it if it fails to
+    /// decompress. Other events are passed through.
+    fn decompress_direct_message(&mut self, event: NetworkEvent) -> Option<NetworkEvent> {
+        let limits = &self.request_response_config;
+        let (msg, peer_id, max_size) = match &event {
+            NetworkEvent::DirectRequest(msg, peer_id, _) => {
+                (msg, *peer_id, limits.request_size_maximum)
+            }
+            NetworkEvent::DirectResponse(msg, peer_id) => {
+                (msg, *peer_id, limits.response_size_maximum)
+            }
+            _ => return Some(event),
+        };
+
+        match self
+            .compression
+            .decompress(msg, usize::try_from(max_size).unwrap_or(usize::MAX))
+        {
+            Ok(msg) => Some(match event {
+                NetworkEvent::DirectRequest(_, peer_id, chan) => {
+                    NetworkEvent::DirectRequest(msg, peer_id, chan)
+                }
+                NetworkEvent::DirectResponse(_, peer_id) => {
+                    NetworkEvent::DirectResponse(msg, peer_id)
+                }
+                event => event,
+            }),
+            Err(e) => {
+                warn!("Dropping direct message from {:?}: {:?}", peer_id, e);
+                self.peer_tracker.failed(peer_id);
+                None
+            }
+        }
+    }
+
+    /// Whether we accept messages authored by `peer`. Observers only follow the network, so we
+    /// drop the messages they send us; we only know the roles of peers we connected to.
+    fn accepts_from(&self, peer: &PeerId) -> bool {
+        self.peer_tracker.role(peer) != Some(PeerRole::Observer)
+    }
+
     /// event handler for events emitted from the swarm
     #[allow(clippy::type_complexity)]
     #[instrument(skip(self))]
@@ -529,6 +694,26 @@ impl<T: NodeType> NetworkNode<T> {
                         peer_id, endpoint, concurrent_dial_errors
                     );
                 }
+                self.peer_tracker.set_connected(peer_id, true);
+                if let Some(compression) = self.negotiated_compression.take(&peer_id) {
+                    debug!("Negotiated {:?} with {:?}", compression, peer_id);
+                    self.peer_tracker.set_compression(peer_id, compression);
+                }
+                if let Some((role, _)) = self.peer_roles.take(&peer_id) {
+                    debug!("{:?} connected as {:?}", peer_id, role);
+                    self.peer_tracker.set_role(peer_id, role);
+                }
+
+                // Observers only follow the network, so we only spend so much on them
+                if self.peer_tracker.role(&peer_id) == Some(PeerRole::Observer)
+                    && self.peer_tracker.connected_observers() > self.max_observers
+                {
+                    warn!(
+                        "Disconnecting observer {:?}: already connected to {} observers",
+                        peer_id, self.max_observers
+                    );
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                }
 
                 // Send the number of connected peers to the client
                 send_to_client
@@ -553,6 +738,9 @@ impl<T: NodeType> NetworkNode<T> {
                         peer_id, endpoint, cause
                     );
                 }
+                if num_established == 0 {
+                    self.peer_tracker.set_connected(peer_id, false);
+                }
 
                 // Send the number of connected peers to the client
                 send_to_client
@@ -565,15 +753,18 @@ impl<T: NodeType> NetworkNode<T> {
             } => {
                 debug!("Attempting to dial {:?}", peer_id);
             }
+            SwarmEvent::NewListenAddr {
+                listener_id: _,
+                address,
+            } => {
+                // Identify advertises every address we listen on to peers
+                info!("Libp2p listening on {:?}", address);
+            }
             SwarmEvent::ListenerClosed {
                 listener_id: _,
                 addresses: _,
                 reason: _,
             }
-            | SwarmEvent::NewListenAddr {
-                listener_id: _,
-                address: _,
-            }
             | SwarmEvent::ExpiredListenAddr {
                 listener_id: _,
                 address: _,
@@ -617,10 +808,21 @@ impl<T: NodeType> NetworkNode<T> {
                     }
                     NetworkEventInternal::GossipEvent(e) => match *e {
                         GossipEvent::Message {
-                            propagation_source: _peer_id,
+                            propagation_source,
                             message_id: _id,
                             message,
-                        } => Some(NetworkEvent::GossipMsg(message.data)),
+                        } => {
+                            self.peer_tracker.seen(propagation_source);
+                            if message
+                                .source
+                                .is_some_and(|source| !self.accepts_from(&source))
+                            {
+                                debug!("Dropping gossip authored by an observer");
+                                None
+                            } else {
+                                Some(NetworkEvent::GossipMsg(message.data))
+                            }
+                        }
                         GossipEvent::Subscribed { peer_id, topic } => {
                             debug!("Peer {:?} subscribed to topic {:?}", peer_id, topic);
                             None
@@ -636,7 +838,14 @@ impl<T: NodeType> NetworkNode<T> {
                     },
                     NetworkEventInternal::DMEvent(e) => self
                         .direct_message_state
-                        .handle_dm_event(e, self.resend_tx.clone()),
+                        .handle_dm_event(e, self.resend_tx.clone(), &mut self.peer_tracker)
+                        .filter(|event| match event {
+                            NetworkEvent::DirectRequest(_, peer_id, _) => {
+                                self.accepts_from(peer_id)
+                            }
+                            _ => true,
+                        })
+                        .and_then(|event| self.decompress_direct_message(event)),
                     NetworkEventInternal::AutonatEvent(e) => {
                         match e {
                             autonat::Event::InboundProbe(_) => {}
@@ -660,6 +869,17 @@ impl<T: NodeType> NetworkNode<T> {
                         };
                         None
                     }
+                    NetworkEventInternal::AdmissionEvent(e) => {
+                        match e {
+                            AdmissionEvent::Evicted { peer, for_peer } => {
+                                info!(
+                                    "Closed the connection of {:?} to make room for {:?}",
+                                    peer, for_peer
+                                );
+                            }
+                        }
+                        None
+                    }
                 };
 
                 if let Some(event) = maybe_event {
@@ -675,6 +895,9 @@ impl<T: NodeType> NetworkNode<T> {
                 error,
             } => {
                 warn!("Outgoing connection error to {:?}: {:?}", peer_id, error);
+                if let Some(peer_id) = peer_id {
+                    self.peer_tracker.failed(peer_id);
+                }
             }
             SwarmEvent::IncomingConnectionError {
                 connection_id: _,
@@ -4,13 +4,22 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::HashSet, num::NonZeroUsize, time::Duration};
+use std::{
+    collections::HashSet,
+    num::{NonZeroU8, NonZeroUsize},
+    time::Duration,
+};
 
-use hotshot_types::traits::node_implementation::NodeType;
+use hotshot_types::{gossip_fanout::MeshParams, traits::node_implementation::NodeType};
 use libp2p::{identity::Keypair, Multiaddr};
 use libp2p_identity::PeerId;
 
 use super::MAX_GOSSIP_MSG_SIZE;
+use crate::network::{
+    behaviours::admission::AdmissionConfig,
+    compression::CompressionConfig,
+    transport::{AuthKey, NetworkInfo},
+};
 
 /// The default Kademlia replication factor
 pub const DEFAULT_REPLICATION_FACTOR: Option<NonZeroUsize> = NonZeroUsize::new(10);
@@ -25,6 +34,14 @@ pub struct NetworkNodeConfig<T: NodeType> {
     /// The address to bind to
     #[builder(default)]
     pub bind_address: Option<Multiaddr>,
+    /// Further addresses to bind to, such as an IPv6 address next to an IPv4 one, or other
+    /// interfaces and ports. We advertise all of them to peers
+    #[builder(default)]
+    pub extra_bind_addresses: Vec<Multiaddr>,
+    /// How many addresses of a peer we dial at once, keeping the first connection to succeed.
+    /// Defaults to libp2p's
+    #[builder(default)]
+    pub dial_concurrency_factor: Option<NonZeroU8>,
     /// Replication factor for entries in the DHT
     #[builder(setter(into, strip_option), default = "DEFAULT_REPLICATION_FACTOR")]
     pub replication_factor: Option<NonZeroUsize>,
@@ -55,14 +72,34 @@ pub struct NetworkNodeConfig<T: NodeType> {
     #[builder(default)]
     pub dht_file_path: Option<String>,
 
-    /// The signed authentication message sent to the remote peer
-    /// If not supplied we will not send an authentication message during the handshake
+    /// The stake table key we prove possession of to peers, to claim the validator role.
+    /// If not supplied, or not in the stake table, we connect to peers as an observer
+    #[builder(default)]
+    #[debug(skip)]
+    pub auth_key: Option<AuthKey<T::SignatureKey>>,
+
+    /// The maximum number of peers not in the stake table, which only follow the network, we
+    /// stay connected to. Defaults to none, in which case such peers are rejected
+    #[builder(default)]
+    pub max_observers: usize,
+
+    /// Limits on the connections remote peers open to us, and how many of them we keep for
+    /// validators
     #[builder(default)]
-    pub auth_message: Option<Vec<u8>>,
+    pub admission: AdmissionConfig,
 
     #[builder(default)]
     /// The timeout for DHT lookups.
     pub dht_timeout: Option<Duration>,
+
+    /// The compression we offer to peers when connecting to them. Defaults to none,
+    /// which suits clusters on a local network.
+    #[builder(default)]
+    pub compression: CompressionConfig,
+
+    /// The network we are part of, which we check peers are part of too when connecting
+    #[builder(default)]
+    pub network_info: NetworkInfo,
 }
 
 /// Configuration for Libp2p's Gossipsub
@@ -126,6 +163,21 @@ pub struct GossipConfig {
     pub gossip_lazy: usize,
 }
 
+impl GossipConfig {
+    /// This configuration, with the mesh parameters replaced by `mesh`
+    #[must_use]
+    pub fn with_mesh(self, mesh: MeshParams) -> Self {
+        Self {
+            mesh_n: mesh.mesh_n,
+            mesh_n_low: mesh.mesh_n_low,
+            mesh_n_high: mesh.mesh_n_high,
+            mesh_outbound_min: mesh.mesh_outbound_min,
+            gossip_lazy: mesh.gossip_lazy,
+            ..self
+        }
+    }
+}
+
 impl Default for GossipConfig {
     fn default() -> Self {
         Self {
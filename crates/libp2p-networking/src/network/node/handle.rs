@@ -4,7 +4,12 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{collections::HashSet, fmt::Debug, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Debug,
+    iter,
+    time::Duration,
+};
 
 use hotshot_types::traits::{network::NetworkError, node_implementation::NodeType};
 use libp2p::{request_response::ResponseChannel, Multiaddr};
@@ -17,7 +22,9 @@ use tracing::{debug, info, instrument};
 
 use crate::network::{
     behaviours::dht::record::{Namespace, RecordKey, RecordValue},
-    gen_multiaddr, ClientRequest, NetworkEvent, NetworkNode, NetworkNodeConfig,
+    gen_multiaddr,
+    peer_stats::PeerStats,
+    ClientRequest, NetworkEvent, NetworkNode, NetworkNodeConfig,
 };
 
 /// A handle containing:
@@ -31,8 +38,8 @@ pub struct NetworkNodeHandle<T: NodeType> {
     /// send an action to the networkbehaviour
     send_network: UnboundedSender<ClientRequest>,
 
-    /// the local address we're listening on
-    listen_addr: Multiaddr,
+    /// the local addresses we're listening on, the first one on our main bind address
+    listen_addrs: Vec<Multiaddr>,
 
     /// the peer id of the networkbehaviour
     peer_id: PeerId,
@@ -85,12 +92,16 @@ pub async fn spawn_network_node<T: NodeType>(
         .await
         .map_err(|e| NetworkError::ConfigError(format!("failed to create network node: {e}")))?;
     // randomly assigned port
-    let listen_addr = config
-        .bind_address
-        .clone()
-        .unwrap_or_else(|| gen_multiaddr(0));
+    let listen_addrs = iter::once(
+        config
+            .bind_address
+            .clone()
+            .unwrap_or_else(|| gen_multiaddr(0)),
+    )
+    .chain(config.extra_bind_addresses.iter().cloned())
+    .collect();
     let peer_id = network.peer_id();
-    let listen_addr = network.start_listen(listen_addr).await.map_err(|e| {
+    let listen_addrs = network.start_listen(listen_addrs).await.map_err(|e| {
         NetworkError::ListenError(format!("failed to start listening on Libp2p: {e}"))
     })?;
     // pin here to force the future onto the heap since it can be large
@@ -106,7 +117,7 @@ pub async fn spawn_network_node<T: NodeType>(
     let handle = NetworkNodeHandle::<T> {
         network_config: config,
         send_network: send_chan,
-        listen_addr,
+        listen_addrs,
         peer_id,
         id,
     };
@@ -134,7 +145,13 @@ impl<T: NodeType> NetworkNodeHandle<T> {
     /// Get a reference to the network node handle's listen addr.
     #[must_use]
     pub fn listen_addr(&self) -> Multiaddr {
-        self.listen_addr.clone()
+        self.listen_addrs[0].clone()
+    }
+
+    /// Every address the network node is listening on, as reported when it started listening
+    #[must_use]
+    pub fn listen_addrs(&self) -> &[Multiaddr] {
+        &self.listen_addrs
     }
 
     /// Print out the routing table used by kademlia
@@ -379,6 +396,14 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         self.send_request(req)
     }
 
+    /// Send all gossip to `count` connected peers outside the mesh, on top of the mesh
+    /// # Errors
+    /// If the channel is closed somehow
+    pub fn set_extra_fanout(&self, count: usize) -> Result<(), NetworkError> {
+        let req = ClientRequest::SetExtraFanout(count);
+        self.send_request(req)
+    }
+
     /// Gossip a message to peers
     /// # Errors
     /// - Will return [`NetworkError::ChannelSendError`] when underlying `NetworkNode` has been killed
@@ -446,6 +471,17 @@ impl<T: NodeType> NetworkNodeHandle<T> {
         Ok(r.await.unwrap())
     }
 
+    /// return the connection statistics of every peer this node has dealt with
+    /// # Errors
+    /// If the channel is closed somehow
+    pub async fn peer_stats(&self) -> Result<HashMap<PeerId, PeerStats>, NetworkError> {
+        let (s, r) = futures::channel::oneshot::channel();
+        let req = ClientRequest::GetPeerStats(s);
+        self.send_request(req)?;
+        r.await
+            .map_err(|err| NetworkError::ChannelReceiveError(err.to_string()))
+    }
+
     /// Get a reference to the network node handle's id.
     #[must_use]
     pub fn id(&self) -> usize {
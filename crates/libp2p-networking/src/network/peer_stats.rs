@@ -0,0 +1,106 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use libp2p_identity::PeerId;
+
+use super::{compression::PeerCompression, transport::PeerRole};
+
+/// What we know about the connection to a peer
+#[derive(Clone, Debug, Default)]
+pub struct PeerStats {
+    /// Whether we have at least one connection to the peer
+    pub connected: bool,
+    /// When we last received anything from the peer
+    pub last_seen: Option<Instant>,
+    /// Round-trip time of the latest direct request to the peer which was answered
+    pub rtt: Option<Duration>,
+    /// Number of failed connections and direct messages to or from the peer
+    pub failures: u64,
+    /// How we compress direct messages to the peer, as negotiated when we last connected to it
+    pub compression: PeerCompression,
+    /// The role the peer proved when we last connected to it
+    pub role: Option<PeerRole>,
+}
+
+/// Keeps the [`PeerStats`] of every peer we have dealt with
+#[derive(Debug, Default)]
+pub struct PeerTracker {
+    /// Stats, by peer
+    peers: HashMap<PeerId, PeerStats>,
+}
+
+impl PeerTracker {
+    /// Record that we connected to or lost our last connection to `peer`
+    pub fn set_connected(&mut self, peer: PeerId, connected: bool) {
+        let stats = self.peers.entry(peer).or_default();
+        stats.connected = connected;
+        if connected {
+            stats.last_seen = Some(Instant::now());
+        }
+    }
+
+    /// Record that we received something from `peer`
+    pub fn seen(&mut self, peer: PeerId) {
+        self.peers.entry(peer).or_default().last_seen = Some(Instant::now());
+    }
+
+    /// Record that `peer` answered a direct request after `rtt`
+    pub fn answered(&mut self, peer: PeerId, rtt: Duration) {
+        let stats = self.peers.entry(peer).or_default();
+        stats.last_seen = Some(Instant::now());
+        stats.rtt = Some(rtt);
+    }
+
+    /// Record the compression negotiated with `peer` when we connected to it
+    pub fn set_compression(&mut self, peer: PeerId, compression: PeerCompression) {
+        self.peers.entry(peer).or_default().compression = compression;
+    }
+
+    /// How we compress direct messages to `peer`
+    #[must_use]
+    pub fn compression(&self, peer: &PeerId) -> PeerCompression {
+        self.peers
+            .get(peer)
+            .map(|stats| stats.compression)
+            .unwrap_or_default()
+    }
+
+    /// Record the role `peer` proved when we connected to it
+    pub fn set_role(&mut self, peer: PeerId, role: PeerRole) {
+        self.peers.entry(peer).or_default().role = Some(role);
+    }
+
+    /// The role `peer` proved, if we ever connected to it
+    #[must_use]
+    pub fn role(&self, peer: &PeerId) -> Option<PeerRole> {
+        self.peers.get(peer).and_then(|stats| stats.role)
+    }
+
+    /// Number of observers we are connected to
+    #[must_use]
+    pub fn connected_observers(&self) -> usize {
+        self.peers
+            .values()
+            .filter(|stats| stats.connected && stats.role == Some(PeerRole::Observer))
+            .count()
+    }
+
+    /// Record a failed connection or message to or from `peer`
+    pub fn failed(&mut self, peer: PeerId) {
+        self.peers.entry(peer).or_default().failures += 1;
+    }
+
+    /// The stats of every peer
+    #[must_use]
+    pub fn snapshot(&self) -> HashMap<PeerId, PeerStats> {
+        self.peers.clone()
+    }
+}
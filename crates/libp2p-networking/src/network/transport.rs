@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     future::Future,
     io::{Error as IoError, ErrorKind as IoErrorKind},
     pin::Pin,
@@ -11,7 +12,7 @@ use futures::{future::poll_fn, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteEx
 use hotshot_types::traits::{
     election::Membership,
     node_implementation::{ConsensusTime, NodeType},
-    signature_key::SignatureKey,
+    signature_key::{SignatureKey, StakeTableEntryType},
 };
 use libp2p::{
     core::{
@@ -22,34 +23,61 @@ use libp2p::{
     identity::PeerId,
     Transport,
 };
+use parking_lot::Mutex;
 use pin_project::pin_project;
 use serde::{Deserialize, Serialize};
 use tokio::time::timeout;
 use tracing::warn;
 
+use super::compression::{CompressionConfig, NegotiatedCompression, PeerCompression};
+
 /// The maximum size of an authentication message. This is used to prevent
 /// DoS attacks by sending large messages.
 const MAX_AUTH_MESSAGE_SIZE: usize = 1024;
 
+/// The version of the protocol nodes speak to each other, which they exchange when they
+/// connect. Bump the major version on changes which older nodes cannot understand.
+pub const PROTOCOL_VERSION: ProtocolVersion = ProtocolVersion { major: 2, minor: 0 };
+
+/// The size of the challenge each side of a connection sends the other to sign
+const CHALLENGE_SIZE: usize = 32;
+
+/// Prefixed to everything a node signs to authenticate, so that the signature cannot be
+/// replayed anywhere else
+const AUTH_DOMAIN: &[u8] = b"HotShot peer authentication";
+
 /// The timeout for the authentication handshake. This is used to prevent
 /// attacks that keep connections open indefinitely by half-finishing the
 /// handshake.
 const AUTH_HANDSHAKE_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
 /// A wrapper for a `Transport` that bidirectionally authenticates connections
-/// by performing a handshake that checks if the remote peer is present in the
-/// stake table.
+/// by performing a handshake in which a remote peer claiming the validator role
+/// proves possession of a key in the stake table, by signing a fresh challenge.
+/// The handshake first checks that the remote peer is part of the same network
+/// and speaks a compatible protocol, and last negotiates how direct messages to
+/// the peer are compressed. The role each remote peer proved is recorded, so that
+/// validators and observers can be treated differently.
 #[pin_project]
 pub struct StakeTableAuthentication<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> {
     #[pin]
     /// The underlying transport we are wrapping
     pub inner: T,
 
-    /// The stake table we check against to authenticate connections
-    pub stake_table: Arc<Option<Types::Membership>>,
+    /// How we authenticate remote peers and prove ourselves to them
+    pub auth: Arc<AuthConfig<Types>>,
+
+    /// The compression we offer to remote peers
+    pub compression: Arc<CompressionConfig>,
+
+    /// Where we record the compression negotiated with each remote peer
+    pub negotiated: NegotiatedCompression,
+
+    /// Where we record the role each remote peer proved
+    pub roles: PeerRoles,
 
-    /// A pre-signed message that we send to the remote peer for authentication
-    pub auth_message: Arc<Option<Vec<u8>>>,
+    /// The network we are part of, which remote peers must be part of too
+    pub network_info: Arc<NetworkInfo>,
 
     /// Phantom data for the connection type
     pd: std::marker::PhantomData<C>,
@@ -61,87 +89,186 @@ type UpgradeFuture<T> =
 
 impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentication<T, Types, C> {
     /// Create a new `StakeTableAuthentication` transport that wraps the given transport
-    /// and authenticates connections against the stake table.
+    /// and authenticates connections as `auth` describes, recording the compression
+    /// negotiated with each peer in `negotiated` and the role it proved in `roles`. Peers
+    /// which are not part of the network described by `network_info` are rejected.
     pub fn new(
         inner: T,
-        stake_table: Option<Types::Membership>,
-        auth_message: Option<Vec<u8>>,
+        auth: AuthConfig<Types>,
+        compression: CompressionConfig,
+        negotiated: NegotiatedCompression,
+        roles: PeerRoles,
+        network_info: NetworkInfo,
     ) -> Self {
         Self {
             inner,
-            stake_table: Arc::from(stake_table),
-            auth_message: Arc::from(auth_message),
+            auth: Arc::new(auth),
+            compression: Arc::new(compression),
+            negotiated,
+            roles,
+            network_info: Arc::new(network_info),
             pd: std::marker::PhantomData,
         }
     }
 
-    /// Prove to the remote peer that we are in the stake table by sending
-    /// them our authentication message.
+    /// Send the remote peer a fresh challenge to sign and receive theirs. The side which
+    /// opened the connection goes first. Returns our challenge and theirs.
+    ///
+    /// # Errors
+    /// - If we fail to write our challenge or to read theirs
+    /// - If their challenge is not of the expected size
+    pub async fn exchange_challenges<S: AsyncRead + AsyncWrite + Unpin>(
+        stream: &mut S,
+        outgoing: bool,
+    ) -> AnyhowResult<([u8; CHALLENGE_SIZE], Vec<u8>)> {
+        let challenge: [u8; CHALLENGE_SIZE] = rand::random();
+        let remote_challenge = exchange(stream, outgoing, &challenge).await?;
+        ensure!(
+            remote_challenge.len() == CHALLENGE_SIZE,
+            "Invalid challenge size"
+        );
+
+        Ok((challenge, remote_challenge))
+    }
+
+    /// Prove our role to the remote peer. If we have a key in the stake table, we claim the
+    /// validator role by signing the remote peer's challenge along with the key and our peer
+    /// ID. Otherwise we claim the observer role.
     ///
     /// # Errors
+    /// - If we fail to sign the challenge
     /// - If we fail to write the message to the stream
     pub async fn authenticate_with_remote_peer<W: AsyncWrite + Unpin>(
         stream: &mut W,
-        auth_message: Arc<Option<Vec<u8>>>,
+        auth: &AuthConfig<Types>,
+        remote_challenge: &[u8],
     ) -> AnyhowResult<()> {
-        // If we have an auth message, send it to the remote peer, prefixed with
-        // the message length
-        if let Some(auth_message) = auth_message.as_ref() {
-            // Write the length-delimited message
-            write_length_delimited(stream, auth_message).await?;
-        }
+        let auth_message = match &auth.auth_key {
+            Some(key) if auth.is_validator() => construct_auth_message(
+                &key.public_key,
+                &auth.peer_id,
+                &key.private_key,
+                remote_challenge,
+            )?,
+            _ => construct_observer_auth_message::<Types::SignatureKey>(&auth.peer_id)?,
+        };
 
-        Ok(())
+        // Write the length-delimited message
+        write_length_delimited(stream, &auth_message).await
     }
 
-    /// Verify that the remote peer is:
-    /// - In the stake table
-    /// - Sending us a valid authentication message
-    /// - Sending us a valid signature
+    /// Verify the role the remote peer claims, returning it along with the peer's stake. A peer
+    /// claiming the validator role must be:
     /// - Matching the peer ID we expect
+    /// - Signing the challenge we sent it
+    /// - In the stake table
+    ///
+    /// A peer claiming the observer role is only accepted if we accept observers, and has no
+    /// stake. Without a stake table we do not authenticate peers, and treat all of them as
+    /// validators without stake.
     ///
     /// # Errors
     /// If the peer fails verification. This can happen if:
     /// - We fail to read the message from the stream
     /// - The message is too large
     /// - The message is invalid
-    /// - The peer is not in the stake table
     /// - The signature is invalid
+    /// - The peer is not in the stake table
+    /// - The peer is an observer, and we do not accept observers
     pub async fn verify_peer_authentication<R: AsyncReadExt + Unpin>(
         stream: &mut R,
-        stake_table: Arc<Option<Types::Membership>>,
+        auth: &AuthConfig<Types>,
+        challenge: &[u8],
         required_peer_id: &PeerId,
-    ) -> AnyhowResult<()> {
-        // If we have a stake table, check if the remote peer is in it
-        if let Some(stake_table) = stake_table.as_ref() {
-            // Read the length-delimited message from the remote peer
-            let message = read_length_delimited(stream, MAX_AUTH_MESSAGE_SIZE).await?;
-
-            // Deserialize the authentication message
-            let auth_message: AuthMessage<Types::SignatureKey> = bincode::deserialize(&message)
-                .with_context(|| "Failed to deserialize auth message")?;
-
-            // Verify the signature on the public keys
-            let public_key = auth_message
-                .validate()
-                .with_context(|| "Failed to verify authentication message")?;
-
-            // Deserialize the `PeerId`
-            let peer_id = PeerId::from_bytes(&auth_message.peer_id_bytes)
-                .with_context(|| "Failed to deserialize peer ID")?;
-
-            // Verify that the peer ID is the same as the remote peer
-            if peer_id != *required_peer_id {
-                return Err(anyhow::anyhow!("Peer ID mismatch"));
-            }
+    ) -> AnyhowResult<(PeerRole, u64)> {
+        // Read the length-delimited message from the remote peer
+        let message = read_length_delimited(stream, MAX_AUTH_MESSAGE_SIZE).await?;
 
-            // Check if the public key is in the stake table
-            if !stake_table.has_stake(&public_key, Types::Epoch::new(0)) {
-                return Err(anyhow::anyhow!("Peer not in stake table"));
-            }
+        // Without a stake table, there is nothing to check the remote peer against
+        let Some(stake_table) = auth.stake_table.as_ref() else {
+            return Ok((PeerRole::Validator, 0));
+        };
+
+        // Deserialize the authentication message
+        let auth_message: AuthMessage<Types::SignatureKey> =
+            bincode::deserialize(&message).with_context(|| "Failed to deserialize auth message")?;
+
+        // Deserialize the `PeerId`
+        let peer_id = PeerId::from_bytes(&auth_message.peer_id_bytes)
+            .with_context(|| "Failed to deserialize peer ID")?;
+
+        // Verify that the peer ID is the same as the remote peer
+        if peer_id != *required_peer_id {
+            return Err(anyhow::anyhow!("Peer ID mismatch"));
         }
 
-        Ok(())
+        let stake = match auth_message.role {
+            PeerRole::Validator => {
+                // Verify the signature on our challenge
+                let public_key = auth_message
+                    .validate(challenge)
+                    .with_context(|| "Failed to verify authentication message")?;
+
+                // Check if the public key is in the stake table
+                if !stake_table.has_stake(&public_key, Types::Epoch::new(0)) {
+                    return Err(anyhow::anyhow!("Peer not in stake table"));
+                }
+                stake_table
+                    .stake(&public_key, Types::Epoch::new(0))
+                    .map_or(0, |entry| u64::try_from(entry.stake()).unwrap_or(u64::MAX))
+            }
+            PeerRole::Observer => {
+                ensure!(
+                    auth.accept_observers,
+                    "Peer is an observer, which we do not accept"
+                );
+                0
+            }
+        };
+
+        Ok((auth_message.role, stake))
+    }
+
+    /// Exchange compression offers with the remote peer and agree on how we compress
+    /// direct messages to it. Like the rest of the handshake, the side which opened
+    /// the connection goes first.
+    ///
+    /// # Errors
+    /// - If we fail to write our offer or to read the remote peer's
+    /// - If the remote peer's offer is too large or invalid
+    pub async fn negotiate_compression<S: AsyncRead + AsyncWrite + Unpin>(
+        stream: &mut S,
+        outgoing: bool,
+        compression: &CompressionConfig,
+    ) -> AnyhowResult<PeerCompression> {
+        let offer = bincode::serialize(compression)
+            .with_context(|| "Failed to serialize compression offer")?;
+        let remote_offer = exchange(stream, outgoing, &offer).await?;
+        let remote_offer: CompressionConfig = bincode::deserialize(&remote_offer)
+            .with_context(|| "Failed to deserialize compression offer")?;
+
+        Ok(compression.negotiate(&remote_offer))
+    }
+
+    /// Tell the remote peer which network we are part of and which protocol version we
+    /// speak, and check that it is part of the same network and speaks a compatible
+    /// version. The side which opened the connection goes first.
+    ///
+    /// # Errors
+    /// - If we fail to write our information or to read the remote peer's
+    /// - If the remote peer is part of another network or speaks an incompatible version
+    pub async fn check_network_info<S: AsyncRead + AsyncWrite + Unpin>(
+        stream: &mut S,
+        outgoing: bool,
+        network_info: &NetworkInfo,
+    ) -> AnyhowResult<()> {
+        let ours =
+            bincode::serialize(network_info).with_context(|| "Failed to serialize network info")?;
+        let theirs = exchange(stream, outgoing, &ours).await?;
+        let theirs: NetworkInfo =
+            bincode::deserialize(&theirs).with_context(|| "Failed to deserialize network info")?;
+
+        network_info.check(&theirs)
     }
 
     /// Wrap the supplied future in an upgrade that performs the authentication handshake.
@@ -151,8 +278,11 @@ impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentica
     fn gen_handshake<F: Future<Output = Result<T::Output, T::Error>> + Send + 'static>(
         original_future: F,
         outgoing: bool,
-        stake_table: Arc<Option<Types::Membership>>,
-        auth_message: Arc<Option<Vec<u8>>>,
+        auth: Arc<AuthConfig<Types>>,
+        compression: Arc<CompressionConfig>,
+        negotiated: NegotiatedCompression,
+        roles: PeerRoles,
+        network_info: Arc<NetworkInfo>,
     ) -> UpgradeFuture<T>
     where
         T::Error: From<<C as StreamMuxer>::Error> + From<IoError>,
@@ -175,9 +305,26 @@ impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentica
                     poll_fn(|cx| stream.as_connection().poll_inbound_unpin(cx)).await?
                 };
 
-                if outgoing {
+                // Make sure we can understand each other before anything else
+                Self::check_network_info(&mut substream, outgoing, &network_info)
+                    .await
+                    .map_err(|e| {
+                        warn!("Rejecting remote peer {}: {:?}", stream.as_peer_id(), e);
+                        IoError::new(IoErrorKind::Other, e)
+                    })?;
+
+                // Each side signs a fresh challenge of the other, so that proofs cannot be replayed
+                let (challenge, remote_challenge) =
+                    Self::exchange_challenges(&mut substream, outgoing)
+                        .await
+                        .map_err(|e| {
+                            warn!("Failed to exchange challenges with remote peer: {:?}", e);
+                            IoError::new(IoErrorKind::Other, e)
+                        })?;
+
+                let (role, stake) = if outgoing {
                     // If the connection is outgoing, authenticate with the remote peer first
-                    Self::authenticate_with_remote_peer(&mut substream, auth_message)
+                    Self::authenticate_with_remote_peer(&mut substream, &auth, &remote_challenge)
                         .await
                         .map_err(|e| {
                             warn!("Failed to authenticate with remote peer: {:?}", e);
@@ -187,19 +334,21 @@ impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentica
                     // Verify the remote peer's authentication
                     Self::verify_peer_authentication(
                         &mut substream,
-                        stake_table,
+                        &auth,
+                        &challenge,
                         stream.as_peer_id(),
                     )
                     .await
                     .map_err(|e| {
                         warn!("Failed to verify remote peer: {:?}", e);
                         IoError::new(IoErrorKind::Other, e)
-                    })?;
+                    })?
                 } else {
                     // If it is incoming, verify the remote peer's authentication first
-                    Self::verify_peer_authentication(
+                    let verified = Self::verify_peer_authentication(
                         &mut substream,
-                        stake_table,
+                        &auth,
+                        &challenge,
                         stream.as_peer_id(),
                     )
                     .await
@@ -209,13 +358,26 @@ impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentica
                     })?;
 
                     // Authenticate with the remote peer
-                    Self::authenticate_with_remote_peer(&mut substream, auth_message)
+                    Self::authenticate_with_remote_peer(&mut substream, &auth, &remote_challenge)
                         .await
                         .map_err(|e| {
                             warn!("Failed to authenticate with remote peer: {:?}", e);
                             IoError::new(IoErrorKind::Other, e)
                         })?;
-                }
+
+                    verified
+                };
+                roles.record(*stream.as_peer_id(), role, stake);
+
+                // Agree on how we compress direct messages to the remote peer
+                let peer_compression =
+                    Self::negotiate_compression(&mut substream, outgoing, &compression)
+                        .await
+                        .map_err(|e| {
+                            warn!("Failed to negotiate compression with remote peer: {:?}", e);
+                            IoError::new(IoErrorKind::Other, e)
+                        })?;
+                negotiated.record(*stream.as_peer_id(), peer_compression);
 
                 Ok(stream)
             })
@@ -228,34 +390,182 @@ impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> StakeTableAuthentica
     }
 }
 
+/// The version of the protocol a node speaks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProtocolVersion {
+    /// Nodes only understand each other if their major versions are the same
+    pub major: u16,
+    /// Nodes whose minor versions differ still understand each other
+    pub minor: u16,
+}
+
+impl std::fmt::Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// What a node tells remote peers about itself when they connect, so that nodes of
+/// different networks or incompatible versions never get to exchange messages
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkInfo {
+    /// The version of the protocol the node speaks
+    pub protocol_version: ProtocolVersion,
+    /// The chain the node is part of
+    pub chain_id: u64,
+    /// Commitment to the genesis of the chain, such as to its genesis stake tables
+    pub genesis: [u8; 32],
+}
+
+impl Default for NetworkInfo {
+    fn default() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            chain_id: 0,
+            genesis: [0; 32],
+        }
+    }
+}
+
+impl NetworkInfo {
+    /// Check that a remote peer which sent us `remote` is part of our network and speaks a
+    /// compatible protocol version.
+    ///
+    /// # Errors
+    /// - If the remote peer speaks another major protocol version
+    /// - If the remote peer is part of another chain, or has another genesis
+    pub fn check(&self, remote: &NetworkInfo) -> AnyhowResult<()> {
+        ensure!(
+            self.protocol_version.major == remote.protocol_version.major,
+            "Peer speaks protocol version {}, which is incompatible with ours ({})",
+            remote.protocol_version,
+            self.protocol_version
+        );
+        ensure!(
+            self.chain_id == remote.chain_id,
+            "Peer is on chain {}, not on ours ({})",
+            remote.chain_id,
+            self.chain_id
+        );
+        ensure!(
+            self.genesis == remote.genesis,
+            "Peer has a different genesis on chain {}",
+            self.chain_id
+        );
+
+        Ok(())
+    }
+}
+
+/// The role a remote peer proved when it connected
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PeerRole {
+    /// The peer proved possession of a key in the stake table
+    Validator,
+    /// The peer has no key in the stake table, and only follows the network
+    Observer,
+}
+
+/// The roles and stake remote peers proved in connection handshakes, which the transport records
+/// for admission control and for the node to pick up once the connection is established
+#[derive(Clone, Debug, Default)]
+pub struct PeerRoles(Arc<Mutex<HashMap<PeerId, (PeerRole, u64)>>>);
+
+impl PeerRoles {
+    /// Record the role and stake `peer` proved
+    pub fn record(&self, peer: PeerId, role: PeerRole, stake: u64) {
+        self.0.lock().insert(peer, (role, stake));
+    }
+
+    /// The role and stake `peer` proved, if a handshake with it finished since they were taken
+    #[must_use]
+    pub fn get(&self, peer: &PeerId) -> Option<(PeerRole, u64)> {
+        self.0.lock().get(peer).copied()
+    }
+
+    /// Take the role and stake `peer` proved, if a handshake with it finished since we last asked
+    #[must_use]
+    pub fn take(&self, peer: &PeerId) -> Option<(PeerRole, u64)> {
+        self.0.lock().remove(peer)
+    }
+}
+
+/// A stake table key pair, possession of which a node proves to claim the validator role
+#[derive(Clone, derive_more::Debug)]
+pub struct AuthKey<S: SignatureKey> {
+    /// The public key, which is in the stake table
+    pub public_key: S,
+    /// The private key, with which the node signs challenges
+    #[debug(skip)]
+    pub private_key: S::PrivateKey,
+}
+
+/// How a node authenticates remote peers and proves its own role to them
+#[derive(Clone, derive_more::Debug)]
+pub struct AuthConfig<Types: NodeType> {
+    /// The stake table remote peers claiming the validator role are checked against. If not
+    /// supplied we will not check remote peers at all
+    pub stake_table: Option<Types::Membership>,
+    /// Our stake table key. If not supplied, or not in the stake table, we claim the observer
+    /// role
+    pub auth_key: Option<AuthKey<Types::SignatureKey>>,
+    /// Our peer ID, which our proof of possession of the key is bound to
+    pub peer_id: PeerId,
+    /// Whether we accept remote peers claiming the observer role
+    pub accept_observers: bool,
+}
+
+impl<Types: NodeType> AuthConfig<Types> {
+    /// Whether we can claim the validator role: we have a key, and it is in the stake table if
+    /// there is one.
+    #[must_use]
+    pub fn is_validator(&self) -> bool {
+        let Some(key) = &self.auth_key else {
+            return false;
+        };
+        match &self.stake_table {
+            Some(stake_table) => stake_table.has_stake(&key.public_key, Types::Epoch::new(0)),
+            None => true,
+        }
+    }
+}
+
 /// The deserialized form of an authentication message that is sent to the remote peer
 #[derive(Clone, Serialize, Deserialize)]
 struct AuthMessage<S: SignatureKey> {
-    /// The encoded (stake table) public key of the sender. This, along with the peer ID, is
-    /// signed. It is still encoded here to enable easy verification.
+    /// The role the sender claims
+    role: PeerRole,
+
+    /// The encoded (stake table) public key of the sender, empty for observers. This, along
+    /// with the challenge and the peer ID, is signed. It is still encoded here to enable easy
+    /// verification.
     public_key_bytes: Vec<u8>,
 
     /// The encoded peer ID of the sender. This is appended to the public key before signing.
     /// It is still encoded here to enable easy verification.
     peer_id_bytes: Vec<u8>,
 
-    /// The signature on the public key
-    signature: S::PureAssembledSignatureType,
+    /// The signature on the challenge, public key and peer ID, which observers do not send
+    signature: Option<S::PureAssembledSignatureType>,
 }
 
 impl<S: SignatureKey> AuthMessage<S> {
-    /// Validate the signature on the public key and return it if valid
-    pub fn validate(&self) -> AnyhowResult<S> {
+    /// Validate the signature on the `challenge` we sent and on the public key, and return the
+    /// public key if valid
+    pub fn validate(&self, challenge: &[u8]) -> AnyhowResult<S> {
         // Deserialize the stake table public key
         let public_key = S::from_bytes(&self.public_key_bytes)
             .with_context(|| "Failed to deserialize public key")?;
+        let signature = self
+            .signature
+            .as_ref()
+            .with_context(|| "Missing signature")?;
 
-        // Reconstruct the signed message from the public key and peer ID
-        let mut signed_message = public_key.to_bytes();
-        signed_message.extend(self.peer_id_bytes.clone());
+        // Reconstruct the signed message from the challenge, public key and peer ID
+        let signed_message = signed_message(challenge, &public_key.to_bytes(), &self.peer_id_bytes);
 
-        // Check if the signature is valid across both
-        if !public_key.validate(&self.signature, &signed_message) {
+        // Check if the signature is valid across all of them
+        if !public_key.validate(signature, &signed_message) {
             return Err(anyhow::anyhow!("Invalid signature"));
         }
 
@@ -263,38 +573,62 @@ impl<S: SignatureKey> AuthMessage<S> {
     }
 }
 
-/// Create an sign an authentication message to be sent to the remote peer
+/// The message a node claiming the validator role signs: the remote peer's `challenge`, its
+/// public key and its peer ID
+fn signed_message(challenge: &[u8], public_key_bytes: &[u8], peer_id_bytes: &[u8]) -> Vec<u8> {
+    [AUTH_DOMAIN, challenge, public_key_bytes, peer_id_bytes].concat()
+}
+
+/// Create and sign an authentication message claiming the validator role, to be sent to the
+/// remote peer which sent us `challenge`
 ///
 /// # Errors
-/// - If we fail to sign the public key
+/// - If we fail to sign the challenge
 /// - If we fail to serialize the authentication message
 pub fn construct_auth_message<S: SignatureKey + 'static>(
     public_key: &S,
     peer_id: &PeerId,
     private_key: &S::PrivateKey,
+    challenge: &[u8],
 ) -> AnyhowResult<Vec<u8>> {
-    // Serialize the stake table public key
-    let mut public_key_bytes = public_key.to_bytes();
-
-    // Serialize the peer ID and append it
+    // Serialize the stake table public key and the peer ID
+    let public_key_bytes = public_key.to_bytes();
     let peer_id_bytes = peer_id.to_bytes();
-    public_key_bytes.extend_from_slice(&peer_id_bytes);
 
-    // Sign our public key
-    let signature =
-        S::sign(private_key, &public_key_bytes).with_context(|| "Failed to sign public key")?;
+    // Sign the challenge along with both
+    let signature = S::sign(
+        private_key,
+        &signed_message(challenge, &public_key_bytes, &peer_id_bytes),
+    )
+    .with_context(|| "Failed to sign challenge")?;
 
     // Create the auth message
     let auth_message = AuthMessage::<S> {
+        role: PeerRole::Validator,
         public_key_bytes,
         peer_id_bytes,
-        signature,
+        signature: Some(signature),
     };
 
     // Serialize the auth message
     bincode::serialize(&auth_message).with_context(|| "Failed to serialize auth message")
 }
 
+/// Create an authentication message claiming the observer role, to be sent to the remote peer
+///
+/// # Errors
+/// - If we fail to serialize the authentication message
+pub fn construct_observer_auth_message<S: SignatureKey>(peer_id: &PeerId) -> AnyhowResult<Vec<u8>> {
+    let auth_message = AuthMessage::<S> {
+        role: PeerRole::Observer,
+        public_key_bytes: vec![],
+        peer_id_bytes: peer_id.to_bytes(),
+        signature: None,
+    };
+
+    bincode::serialize(&auth_message).with_context(|| "Failed to serialize auth message")
+}
+
 impl<T: Transport, Types: NodeType, C: StreamMuxer + Unpin> Transport
     for StakeTableAuthentication<T, Types, C>
 where
@@ -324,12 +658,23 @@ where
         let res = self.inner.dial(addr, opts);
 
         // Clone the necessary fields
-        let auth_message = Arc::clone(&self.auth_message);
-        let stake_table = Arc::clone(&self.stake_table);
+        let auth = Arc::clone(&self.auth);
+        let compression = Arc::clone(&self.compression);
+        let negotiated = self.negotiated.clone();
+        let roles = self.roles.clone();
+        let network_info = Arc::clone(&self.network_info);
 
         // If the dial was successful, perform the authentication handshake on top
         match res {
-            Ok(dial) => Ok(Self::gen_handshake(dial, true, stake_table, auth_message)),
+            Ok(dial) => Ok(Self::gen_handshake(
+                dial,
+                true,
+                auth,
+                compression,
+                negotiated,
+                roles,
+                network_info,
+            )),
             Err(err) => Err(err),
         }
     }
@@ -352,12 +697,22 @@ where
                     send_back_addr,
                 } => {
                     // Clone the necessary fields
-                    let auth_message = Arc::clone(&self.auth_message);
-                    let stake_table = Arc::clone(&self.stake_table);
+                    let auth = Arc::clone(&self.auth);
+                    let compression = Arc::clone(&self.compression);
+                    let negotiated = self.negotiated.clone();
+                    let roles = self.roles.clone();
+                    let network_info = Arc::clone(&self.network_info);
 
                     // Generate the handshake upgrade future (inbound)
-                    let auth_upgrade =
-                        Self::gen_handshake(upgrade, false, stake_table, auth_message);
+                    let auth_upgrade = Self::gen_handshake(
+                        upgrade,
+                        false,
+                        auth,
+                        compression,
+                        negotiated,
+                        roles,
+                        network_info,
+                    );
 
                     // Return the new event
                     TransportEvent::Incoming {
@@ -437,6 +792,28 @@ impl<C: StreamMuxer + Unpin> AsOutput<C> for (PeerId, C) {
     }
 }
 
+/// Send `message` to the remote peer and receive theirs, the side which opened the
+/// connection going first.
+///
+/// # Errors
+/// - If we fail to write our message or to read theirs
+/// - If their message is too large
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    outgoing: bool,
+    message: &[u8],
+) -> AnyhowResult<Vec<u8>> {
+    if outgoing {
+        write_length_delimited(stream, message).await?;
+    }
+    let remote_message = read_length_delimited(stream, MAX_AUTH_MESSAGE_SIZE).await?;
+    if !outgoing {
+        write_length_delimited(stream, message).await?;
+    }
+
+    Ok(remote_message)
+}
+
 /// A helper function to read a length-delimited message from a stream. Takes into
 /// account the maximum message size.
 ///
@@ -495,22 +872,48 @@ pub async fn write_length_delimited<S: AsyncWrite + Unpin>(
 
 #[cfg(test)]
 mod test {
-    use std::sync::Arc;
-
     use hotshot_example_types::node_types::TestTypes;
     use hotshot_types::{
-        light_client::StateVerKey, signature_key::BLSPubKey, traits::signature_key::SignatureKey,
-        PeerConfig,
+        light_client::StateVerKey, signature_key::BLSPubKey, stake_table::NodeMetadata,
+        traits::signature_key::SignatureKey, PeerConfig,
     };
     use libp2p::{core::transport::dummy::DummyTransport, quic::Connection};
     use rand::Rng;
 
     use super::*;
+    use crate::network::compression::Compression;
 
     /// A mock type to help with readability
     type MockStakeTableAuth = StakeTableAuthentication<DummyTransport, TestTypes, Connection>;
 
-    // Helper macro for generating a new identity and authentication message
+    /// The challenge we sent the remote peer
+    const CHALLENGE: [u8; CHALLENGE_SIZE] = [7; CHALLENGE_SIZE];
+
+    /// How we authenticate remote peers, against `stake_table`
+    fn auth_config(
+        stake_table: <TestTypes as NodeType>::Membership,
+        accept_observers: bool,
+    ) -> AuthConfig<TestTypes> {
+        AuthConfig {
+            stake_table: Some(stake_table),
+            auth_key: None,
+            peer_id: PeerId::random(),
+            accept_observers,
+        }
+    }
+
+    /// A stake table with only `key` in it
+    fn stake_table_with(key: &BLSPubKey) -> <TestTypes as NodeType>::Membership {
+        let peer_config = PeerConfig {
+            stake_table_entry: key.stake_table_entry(1),
+            state_ver_key: StateVerKey::default(),
+            bonded_since: 0,
+            metadata: NodeMetadata::default(),
+        };
+        <TestTypes as NodeType>::Membership::new(vec![peer_config.clone()], vec![peer_config])
+    }
+
+    // Helper macro for generating a new identity and authentication message, signing `CHALLENGE`
     macro_rules! new_identity {
         () => {{
             // Gen a new seed
@@ -526,7 +929,8 @@ mod test {
 
             // Construct an authentication message
             let auth_message =
-                super::construct_auth_message(&keypair.0, &peer_id, &keypair.1).unwrap();
+                super::construct_auth_message(&keypair.0, &peer_id, &keypair.1, &CHALLENGE)
+                    .unwrap();
 
             (keypair, peer_id, auth_message)
         }};
@@ -553,6 +957,7 @@ mod test {
         // Verify the authentication message
         let public_key = super::AuthMessage::<BLSPubKey>::validate(
             &bincode::deserialize(&auth_message).unwrap(),
+            &CHALLENGE,
         );
         assert!(public_key.is_ok());
     }
@@ -577,6 +982,7 @@ mod test {
         // Verify the authentication message
         let public_key = super::AuthMessage::<BLSPubKey>::validate(
             &bincode::deserialize(&auth_message).unwrap(),
+            &CHALLENGE,
         );
         assert!(public_key.is_err());
     }
@@ -601,6 +1007,22 @@ mod test {
         // Verify the authentication message
         let public_key = super::AuthMessage::<BLSPubKey>::validate(
             &bincode::deserialize(&auth_message).unwrap(),
+            &CHALLENGE,
+        );
+        assert!(public_key.is_err());
+    }
+
+    /// Test that an authentication message cannot be replayed to a peer which sent another
+    /// challenge
+    #[test]
+    fn signature_verify_other_challenge() {
+        // Create a new identity
+        let (_, _, auth_message) = new_identity!();
+
+        // Verify the authentication message against another challenge
+        let public_key = super::AuthMessage::<BLSPubKey>::validate(
+            &bincode::deserialize(&auth_message).unwrap(),
+            &[8; CHALLENGE_SIZE],
         );
         assert!(public_key.is_err());
     }
@@ -614,24 +1036,20 @@ mod test {
         let mut stream = cursor_from!(auth_message);
 
         // Create a stake table with the key
-        let peer_config = PeerConfig {
-            stake_table_entry: keypair.0.stake_table_entry(1),
-            state_ver_key: StateVerKey::default(),
-        };
-        let stake_table =
-            <TestTypes as NodeType>::Membership::new(vec![peer_config.clone()], vec![peer_config]);
+        let auth = auth_config(stake_table_with(&keypair.0), false);
 
         // Verify the authentication message
         let result = MockStakeTableAuth::verify_peer_authentication(
             &mut stream,
-            Arc::new(Some(stake_table)),
+            &auth,
+            &CHALLENGE,
             &peer_id,
         )
         .await;
 
-        assert!(
-            result.is_ok(),
-            "Should have passed authentication but did not"
+        assert_eq!(
+            result.expect("Should have passed authentication but did not"),
+            (PeerRole::Validator, 1)
         );
     }
 
@@ -644,12 +1062,16 @@ mod test {
         let mut stream = cursor_from!(auth_message);
 
         // Create an empty stake table
-        let stake_table = <TestTypes as NodeType>::Membership::new(vec![], vec![]);
+        let auth = auth_config(
+            <TestTypes as NodeType>::Membership::new(vec![], vec![]),
+            true,
+        );
 
         // Verify the authentication message
         let result = MockStakeTableAuth::verify_peer_authentication(
             &mut stream,
-            Arc::new(Some(stake_table)),
+            &auth,
+            &CHALLENGE,
             &peer_id,
         )
         .await;
@@ -676,17 +1098,13 @@ mod test {
         let mut stream = cursor_from!(auth_message);
 
         // Create a stake table with the key
-        let peer_config = PeerConfig {
-            stake_table_entry: keypair.0.stake_table_entry(1),
-            state_ver_key: StateVerKey::default(),
-        };
-        let stake_table =
-            <TestTypes as NodeType>::Membership::new(vec![peer_config.clone()], vec![peer_config]);
+        let auth = auth_config(stake_table_with(&keypair.0), false);
 
         // Check against the malicious peer ID
         let result = MockStakeTableAuth::verify_peer_authentication(
             &mut stream,
-            Arc::new(Some(stake_table)),
+            &auth,
+            &CHALLENGE,
             &malicious_peer_id,
         )
         .await;
@@ -701,6 +1119,45 @@ mod test {
         );
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn observer_authentication() {
+        // Create a new identity, which does not prove possession of its key
+        let (keypair, peer_id, _) = new_identity!();
+        let auth_message = construct_observer_auth_message::<BLSPubKey>(&peer_id).unwrap();
+
+        // Observers are only accepted if we accept them, and are tagged as such
+        let mut stream = cursor_from!(auth_message);
+        let auth = auth_config(stake_table_with(&keypair.0), true);
+        let result = MockStakeTableAuth::verify_peer_authentication(
+            &mut stream,
+            &auth,
+            &CHALLENGE,
+            &peer_id,
+        )
+        .await;
+        assert_eq!(
+            result.expect("Should have accepted the observer but did not"),
+            (PeerRole::Observer, 0)
+        );
+
+        let mut stream = cursor_from!(auth_message);
+        let auth = auth_config(stake_table_with(&keypair.0), false);
+        let result = MockStakeTableAuth::verify_peer_authentication(
+            &mut stream,
+            &auth,
+            &CHALLENGE,
+            &peer_id,
+        )
+        .await;
+        assert!(
+            result
+                .expect_err("Should have rejected the observer but did not")
+                .to_string()
+                .contains("observer"),
+            "Did not fail with the correct error"
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn read_and_write_length_delimited() {
         // Create a message
@@ -718,4 +1175,90 @@ mod test {
         // Check if the messages are the same
         assert_eq!(message, read_message.as_slice());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn compression_negotiation() {
+        // The remote peer opened the connection and offered `zstd`
+        let remote_offer = bincode::serialize(&CompressionConfig::wan()).unwrap();
+        let mut stream = cursor_from!(remote_offer);
+
+        // We only offer to send messages as they are
+        let negotiated = MockStakeTableAuth::negotiate_compression(
+            &mut stream,
+            false,
+            &CompressionConfig::lan(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(negotiated.algorithm, Compression::None);
+
+        // Our offer follows the remote peer's on the stream
+        let mut written = &stream.into_inner()[remote_offer.len() + 4..];
+        let our_offer: CompressionConfig =
+            bincode::deserialize(&read_length_delimited(&mut written, 1024).await.unwrap())
+                .unwrap();
+        assert_eq!(our_offer, CompressionConfig::lan());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn network_info_mismatch() {
+        let ours = NetworkInfo {
+            chain_id: 1,
+            ..NetworkInfo::default()
+        };
+
+        // A peer of the same network, on a newer minor version
+        let compatible = NetworkInfo {
+            protocol_version: ProtocolVersion {
+                minor: PROTOCOL_VERSION.minor + 1,
+                ..PROTOCOL_VERSION
+            },
+            ..ours.clone()
+        };
+        let mut stream = cursor_from!(bincode::serialize(&compatible).unwrap());
+        assert!(
+            MockStakeTableAuth::check_network_info(&mut stream, false, &ours)
+                .await
+                .is_ok(),
+            "Should have accepted a compatible peer but did not"
+        );
+
+        // Peers of another chain, another genesis, or an incompatible version
+        for (theirs, reason) in [
+            (
+                NetworkInfo {
+                    chain_id: 2,
+                    ..ours.clone()
+                },
+                "on chain 2",
+            ),
+            (
+                NetworkInfo {
+                    genesis: [1; 32],
+                    ..ours.clone()
+                },
+                "different genesis",
+            ),
+            (
+                NetworkInfo {
+                    protocol_version: ProtocolVersion {
+                        major: PROTOCOL_VERSION.major + 1,
+                        minor: 0,
+                    },
+                    ..ours.clone()
+                },
+                "incompatible",
+            ),
+        ] {
+            let mut stream = cursor_from!(bincode::serialize(&theirs).unwrap());
+            let result = MockStakeTableAuth::check_network_info(&mut stream, false, &ours).await;
+            assert!(
+                result
+                    .expect_err("Should have rejected the peer but did not")
+                    .to_string()
+                    .contains(reason),
+                "Did not fail with the correct error"
+            );
+        }
+    }
 }
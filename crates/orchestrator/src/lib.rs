@@ -855,6 +855,8 @@ where
         .map(|keys| PeerConfig {
             stake_table_entry: keys.stake_table_key.stake_table_entry(keys.stake),
             state_ver_key: keys.state_ver_key.clone(),
+            bonded_since: keys.bonded_since,
+            metadata: keys.metadata.clone(),
         })
         .collect();
 
@@ -865,6 +867,8 @@ where
         .map(|keys| PeerConfig {
             stake_table_entry: keys.stake_table_key.stake_table_entry(keys.stake),
             state_ver_key: keys.state_ver_key.clone(),
+            bonded_since: keys.bonded_since,
+            metadata: keys.metadata.clone(),
         })
         .collect();
 
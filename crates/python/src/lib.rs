@@ -0,0 +1,190 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Python bindings for scripting multi-node HotShot experiments.
+//!
+//! ```python
+//! from hotshot_py import Experiment
+//!
+//! exp = Experiment(num_nodes=10, da_nodes=5, num_successful_views=20)
+//! exp.crash(view=5, node=3)
+//! exp.restart(view=8, node=4, down_for=4)
+//! decides = exp.run()  # [(view, num_transactions), ...]
+//! ```
+//!
+//! Experiments are run by the test harness, so a failed run (e.g. not enough successful views)
+//! raises a `RuntimeError`.
+
+use std::{
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{Arc, Mutex},
+};
+
+use hotshot::traits::{NodeImplementation, TestableNodeImplementation};
+use hotshot_example_types::{
+    auction_results_provider_types::TestAuctionResultsProvider,
+    node_types::{Libp2pImpl, MemoryImpl, PushCdnImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    overall_safety_task::OverallSafetyPropertiesDescription,
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+};
+use tokio::runtime::Runtime;
+
+/// A scripted test network: its size, the faults to inject and the success criteria.
+#[pyclass]
+#[derive(Clone, Debug)]
+pub struct Experiment {
+    /// Number of staked nodes
+    #[pyo3(get, set)]
+    num_nodes: usize,
+    /// Size of the DA committee
+    #[pyo3(get, set)]
+    da_nodes: usize,
+    /// Number of views which must succeed
+    #[pyo3(get, set)]
+    num_successful_views: usize,
+    /// Number of views which may fail
+    #[pyo3(get, set)]
+    num_failed_views: usize,
+    /// Network to run on: "memory", "libp2p" or "cdn"
+    #[pyo3(get, set)]
+    network: String,
+    /// Faults to inject, keyed by view
+    faults: Vec<(u64, Vec<ChangeNode>)>,
+}
+
+impl Experiment {
+    /// Schedule `action` for `node` at `view`.
+    fn schedule(&mut self, view: u64, node: usize, action: NodeAction) {
+        let change = ChangeNode {
+            idx: node,
+            updown: action,
+        };
+        match self.faults.iter_mut().find(|(at, _)| *at == view) {
+            Some((_, changes)) => changes.push(change),
+            None => self.faults.push((view, vec![change])),
+        }
+    }
+
+    /// Run the experiment on the implementation `I`, returning the decided blocks.
+    async fn run_with<I>(&self) -> Vec<(u64, u64)>
+    where
+        I: TestableNodeImplementation<TestTypes>
+            + NodeImplementation<
+                TestTypes,
+                Storage = TestStorage<TestTypes>,
+                AuctionResultsProvider = TestAuctionResultsProvider<TestTypes>,
+            >,
+    {
+        let decides = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&decides);
+
+        let description = TestDescription::<TestTypes, I, TestVersions> {
+            num_nodes_with_stake: self.num_nodes,
+            start_nodes: self.num_nodes,
+            da_staked_committee_size: self.da_nodes,
+            overall_safety_properties: OverallSafetyPropertiesDescription {
+                num_successful_views: self.num_successful_views,
+                num_failed_views: self.num_failed_views,
+                ..Default::default()
+            },
+            spinning_properties: SpinningTaskDescription {
+                node_changes: self.faults.clone(),
+            },
+            validate_transactions: Arc::new(move |blocks| {
+                recorder
+                    .lock()
+                    .expect("decide recorder poisoned")
+                    .clone_from(blocks);
+                Ok(())
+            }),
+            ..TestDescription::default()
+        };
+
+        description
+            .gen_launcher(0)
+            .launch::<I::Network>()
+            .run_test::<SimpleBuilderImplementation>()
+            .await;
+
+        let decided = decides.lock().expect("decide recorder poisoned").clone();
+        decided
+    }
+}
+
+#[pymethods]
+impl Experiment {
+    /// Create an experiment with no faults.
+    #[new]
+    #[pyo3(signature = (num_nodes = 10, da_nodes = 5, num_successful_views = 10, num_failed_views = 0, network = "memory".to_string()))]
+    fn new(
+        num_nodes: usize,
+        da_nodes: usize,
+        num_successful_views: usize,
+        num_failed_views: usize,
+        network: String,
+    ) -> Self {
+        Self {
+            num_nodes,
+            da_nodes,
+            num_successful_views,
+            num_failed_views,
+            network,
+            faults: Vec::new(),
+        }
+    }
+
+    /// Shut `node` down at `view`.
+    fn crash(&mut self, view: u64, node: usize) {
+        self.schedule(view, node, NodeAction::Down);
+    }
+
+    /// Shut `node` down at `view` and restart it from storage `down_for` views later.
+    fn restart(&mut self, view: u64, node: usize, down_for: u64) {
+        self.schedule(view, node, NodeAction::RestartDown(down_for));
+    }
+
+    /// Cut `node` off the network at `view`.
+    fn disconnect(&mut self, view: u64, node: usize) {
+        self.schedule(view, node, NodeAction::NetworkDown);
+    }
+
+    /// Reconnect `node` to the network at `view`.
+    fn reconnect(&mut self, view: u64, node: usize) {
+        self.schedule(view, node, NodeAction::NetworkUp);
+    }
+
+    /// Run the experiment to completion, returning `(view, num_transactions)` for every decided
+    /// block.
+    fn run(&self, py: Python<'_>) -> PyResult<Vec<(u64, u64)>> {
+        let runtime = Runtime::new().map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+
+        py.allow_threads(|| {
+            catch_unwind(AssertUnwindSafe(|| match self.network.as_str() {
+                "memory" => Ok(runtime.block_on(self.run_with::<MemoryImpl>())),
+                "libp2p" => Ok(runtime.block_on(self.run_with::<Libp2pImpl>())),
+                "cdn" => Ok(runtime.block_on(self.run_with::<PushCdnImpl>())),
+                other => Err(PyValueError::new_err(format!("unknown network {other:?}"))),
+            }))
+            .unwrap_or_else(|_| Err(PyRuntimeError::new_err("experiment failed, see the logs")))
+        })
+    }
+}
+
+/// The `hotshot_py` Python module.
+#[pymodule]
+fn hotshot_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Experiment>()?;
+    Ok(())
+}
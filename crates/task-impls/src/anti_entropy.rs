@@ -0,0 +1,228 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeSet, sync::Arc};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    anti_entropy::{AntiEntropyConfig, AntiEntropyItem, AntiEntropyStore, DigestEntry, ItemKind},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+};
+use rand::{seq::IteratorRandom, thread_rng};
+use utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Holds the proposals and votes of recent views, and periodically reconciles them with a few
+/// peers so that a message which gossip lost is fetched before the view times out.
+pub struct AntiEntropyTaskState<TYPES: NodeType> {
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Membership for the quorum, whose members we reconcile with
+    pub membership: Arc<TYPES::Membership>,
+
+    /// Anti-entropy settings
+    pub config: AntiEntropyConfig,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// The messages of recent views we hold
+    pub store: AntiEntropyStore<TYPES>,
+
+    /// Entries we asked peers for and have not received yet
+    pub requested: BTreeSet<DigestEntry>,
+
+    /// This node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType> AntiEntropyTaskState<TYPES> {
+    /// The oldest view whose messages are reconciled.
+    fn oldest_view(&self) -> u64 {
+        self.cur_view.u64().saturating_sub(self.config.window)
+    }
+
+    /// Hold `item` if it is recent enough.
+    fn record(&mut self, item: AntiEntropyItem<TYPES>) {
+        if *item.view() < self.oldest_view() {
+            return;
+        }
+        self.requested.remove(&item.entry());
+        self.store.insert(item);
+    }
+
+    /// Whether we want the message `entry` identifies: every proposal, but only the votes we
+    /// collect as the leader of the next view.
+    fn wants(&self, entry: &DigestEntry) -> bool {
+        if entry.view < self.oldest_view() || self.store.contains(entry) {
+            return false;
+        }
+        match entry.kind {
+            ItemKind::Proposal => true,
+            ItemKind::QuorumVote | ItemKind::TimeoutVote => self
+                .membership
+                .leader(
+                    TYPES::View::new(entry.view.saturating_add(1)),
+                    self.cur_epoch,
+                )
+                .is_ok_and(|leader| leader == self.public_key),
+        }
+    }
+
+    /// Handles an event.
+    pub async fn handle(
+        &mut self,
+        event: Arc<HotShotEvent<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        match event.as_ref() {
+            HotShotEvent::QuorumProposalRecv(proposal, _)
+            | HotShotEvent::QuorumProposalSend(proposal, _) => {
+                self.record(AntiEntropyItem::Proposal(proposal.clone()));
+            }
+            HotShotEvent::QuorumVoteRecv(vote) | HotShotEvent::QuorumVoteSend(vote) => {
+                self.record(AntiEntropyItem::QuorumVote(vote.clone()));
+            }
+            HotShotEvent::TimeoutVoteRecv(vote) | HotShotEvent::TimeoutVoteSend(vote) => {
+                self.record(AntiEntropyItem::TimeoutVote(vote.clone()));
+            }
+            HotShotEvent::ViewChange(view, epoch) => {
+                if *epoch > self.cur_epoch {
+                    self.cur_epoch = *epoch;
+                }
+                if *view <= self.cur_view {
+                    return;
+                }
+                self.cur_view = *view;
+                let oldest = self.oldest_view();
+                self.store.prune(oldest);
+                self.requested.retain(|entry| entry.view >= oldest);
+            }
+            HotShotEvent::AntiEntropyTick => {
+                if self.store.is_empty() {
+                    return;
+                }
+                let mut peers = self
+                    .membership
+                    .committee_members(self.cur_view, self.cur_epoch)
+                    .into_iter()
+                    .filter(|peer| *peer != self.public_key)
+                    .choose_multiple(&mut thread_rng(), self.config.fanout);
+                // The next leader collects the votes of this view, so it always gets our digest
+                if let Ok(leader) = self.membership.leader(self.cur_view + 1, self.cur_epoch) {
+                    if leader != self.public_key && !peers.contains(&leader) {
+                        peers.push(leader);
+                    }
+                }
+                let digest = self.store.digest();
+                for peer in peers {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::AntiEntropyDigestSend(
+                            digest.clone(),
+                            self.public_key.clone(),
+                            peer,
+                        )),
+                        event_stream,
+                    )
+                    .await;
+                }
+            }
+            HotShotEvent::AntiEntropyDigestRecv(digest, peer) => {
+                let missing: Vec<_> = digest
+                    .iter()
+                    .filter(|entry| self.wants(entry))
+                    .copied()
+                    .collect();
+                if missing.is_empty() {
+                    return;
+                }
+                tracing::debug!(
+                    "Requesting {} missing messages from a digest",
+                    missing.len()
+                );
+                self.requested.extend(missing.iter().copied());
+                broadcast_event(
+                    Arc::new(HotShotEvent::AntiEntropyRequestSend(
+                        missing,
+                        self.public_key.clone(),
+                        peer.clone(),
+                    )),
+                    event_stream,
+                )
+                .await;
+            }
+            HotShotEvent::AntiEntropyRequestRecv(request, peer) => {
+                let items = self.store.get(request);
+                if items.is_empty() {
+                    return;
+                }
+                broadcast_event(
+                    Arc::new(HotShotEvent::AntiEntropyResponseSend(
+                        items,
+                        self.public_key.clone(),
+                        peer.clone(),
+                    )),
+                    event_stream,
+                )
+                .await;
+            }
+            HotShotEvent::AntiEntropyResponseRecv(items, peer) => {
+                for item in items {
+                    // Only take the messages we asked for, which are then validated as usual
+                    if !self.requested.remove(&item.entry()) {
+                        continue;
+                    }
+                    tracing::debug!(
+                        "Recovered a missed {:?} of view {:?}",
+                        item.kind(),
+                        item.view()
+                    );
+                    self.store.insert(item.clone());
+                    let event = match item {
+                        AntiEntropyItem::Proposal(proposal) => {
+                            HotShotEvent::QuorumProposalRecv(proposal.clone(), peer.clone())
+                        }
+                        AntiEntropyItem::QuorumVote(vote) => {
+                            HotShotEvent::QuorumVoteRecv(vote.clone())
+                        }
+                        AntiEntropyItem::TimeoutVote(vote) => {
+                            HotShotEvent::TimeoutVoteRecv(vote.clone())
+                        }
+                    };
+                    broadcast_event(Arc::new(event), event_stream).await;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TaskState for AntiEntropyTaskState<TYPES> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender).await;
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -8,24 +8,28 @@ use std::{sync::Arc, time::Duration};
 
 use async_broadcast::Sender;
 use chrono::Utc;
+use hotshot_task::spawn::spawn_named;
 use hotshot_types::{
     event::{Event, EventType},
-    simple_vote::{QuorumVote2, TimeoutData2, TimeoutVote2},
+    simple_vote::{HighQcData, HighQcVote, QuorumVote2, TimeoutData2, TimeoutVote2},
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
     },
     vote::HasViewNumber,
+    vote_aggregation::PartialQuorumCertificate,
 };
-use tokio::{spawn, time::sleep};
+use tokio::time::sleep;
 use tracing::instrument;
 use utils::anytrace::*;
 use vbs::version::StaticVersionType;
 
 use super::ConsensusTaskState;
 use crate::{
-    consensus::Versions, events::HotShotEvent, helpers::broadcast_event,
-    vote_collection::handle_vote,
+    consensus::Versions,
+    events::HotShotEvent,
+    helpers::broadcast_event,
+    vote_collection::{handle_vote, handle_vote_event_for_view},
 };
 
 /// Handle a `QuorumVoteRecv` event.
@@ -56,6 +60,8 @@ pub(crate) async fn handle_quorum_vote_recv<
         )
     );
 
+    task_state.consensus.write().await.check_vote(vote);
+
     handle_vote(
         &mut task_state.vote_collectors,
         vote,
@@ -66,6 +72,53 @@ pub(crate) async fn handle_quorum_vote_recv<
         &event,
         sender,
         &task_state.upgrade_lock,
+        &task_state.verification_workers,
+        !in_transition,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Handle a `PartialCertificateRecv` event.
+pub(crate) async fn handle_partial_certificate_recv<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+>(
+    partial: &PartialQuorumCertificate<TYPES>,
+    event: Arc<HotShotEvent<TYPES>>,
+    sender: &Sender<Arc<HotShotEvent<TYPES>>>,
+    task_state: &mut ConsensusTaskState<TYPES, I, V>,
+) -> Result<()> {
+    let in_transition = task_state
+        .consensus
+        .read()
+        .await
+        .is_high_qc_for_last_block();
+    let we_are_leader = task_state
+        .membership
+        .leader(partial.view_number() + 1, task_state.cur_epoch)?
+        == task_state.public_key;
+    ensure!(
+        in_transition || we_are_leader,
+        info!(
+            "We are not the leader for view {:?} and we are not in the epoch transition",
+            partial.view_number() + 1
+        )
+    );
+
+    handle_vote_event_for_view::<_, QuorumVote2<TYPES>, _, _>(
+        &mut task_state.vote_collectors,
+        partial.view_number(),
+        task_state.public_key.clone(),
+        &task_state.membership,
+        task_state.cur_epoch,
+        task_state.id,
+        &event,
+        sender,
+        &task_state.upgrade_lock,
+        &task_state.verification_workers,
         !in_transition,
     )
     .await?;
@@ -106,6 +159,7 @@ pub(crate) async fn handle_timeout_vote_recv<
         &event,
         sender,
         &task_state.upgrade_lock,
+        &task_state.verification_workers,
         true,
     )
     .await?;
@@ -113,12 +167,13 @@ pub(crate) async fn handle_timeout_vote_recv<
     Ok(())
 }
 
-/// Send an event to the next leader containing the highest QC we have
+/// Send an event to the next leader containing the highest QC we have, with a signed report of
+/// its view which the leader can use to prove it extends the highest QC known to a quorum.
 /// This is a necessary part of HotStuff 2 but not the original HotStuff
 ///
 /// #Errors
-/// Returns and error if we can't get the version or the version doesn't
-/// yet support HS 2
+/// Returns and error if we can't get the version or we don't follow the
+/// two-chain rule in this view
 pub async fn send_high_qc<TYPES: NodeType, V: Versions, I: NodeImplementation<TYPES>>(
     new_view_number: TYPES::View,
     sender: &Sender<Arc<HotShotEvent<TYPES>>>,
@@ -126,16 +181,31 @@ pub async fn send_high_qc<TYPES: NodeType, V: Versions, I: NodeImplementation<TY
 ) -> Result<()> {
     let version = task_state.upgrade_lock.version(new_view_number).await?;
     ensure!(
-        version >= V::Epochs::VERSION,
-        debug!("HotStuff 2 upgrade not yet in effect")
+        task_state
+            .commit_rule
+            .two_chain(version >= V::Epochs::VERSION),
+        debug!("Two-chain commit rule not in effect")
     );
     let high_qc = task_state.consensus.read().await.high_qc().clone();
     let leader = task_state
         .membership
         .leader(new_view_number, TYPES::Epoch::new(0))?;
+    let report = HighQcVote::create_signed_vote_with_signer(
+        HighQcData {
+            high_qc_view: high_qc.view_number(),
+            epoch: task_state.cur_epoch,
+        },
+        new_view_number,
+        task_state.signer.as_ref(),
+        &task_state.upgrade_lock,
+    )
+    .await
+    .wrap()
+    .context(error!("Failed to sign the high QC report"))?;
     broadcast_event(
         Arc::new(HotShotEvent::HighQcSend(
             high_qc,
+            report,
             leader,
             task_state.public_key.clone(),
         )),
@@ -213,7 +283,7 @@ pub(crate) async fn handle_view_change<
 
     // Spawn a timeout task if we did actually update view
     let timeout = task_state.timeout;
-    let new_timeout_task = spawn({
+    let new_timeout_task = spawn_named("view timeout", {
         let stream = sender.clone();
         let view_number = new_view_number;
         async move {
@@ -232,9 +302,8 @@ pub(crate) async fn handle_view_change<
     // Cancel the old timeout task
     std::mem::replace(&mut task_state.timeout_task, new_timeout_task).abort();
 
-    let consensus_reader = task_state.consensus.read().await;
-    consensus_reader
-        .metrics
+    task_state
+        .consensus_metrics
         .current_view
         .set(usize::try_from(task_state.cur_view.u64()).unwrap());
     let cur_view_time = Utc::now().timestamp();
@@ -244,8 +313,8 @@ pub(crate) async fn handle_view_change<
         == task_state.public_key
     {
         #[allow(clippy::cast_precision_loss)]
-        consensus_reader
-            .metrics
+        task_state
+            .consensus_metrics
             .view_duration_as_leader
             .add_point((cur_view_time - task_state.cur_view_time) as f64);
     }
@@ -253,15 +322,16 @@ pub(crate) async fn handle_view_change<
 
     // Do the comparison before the subtraction to avoid potential overflow, since
     // `last_decided_view` may be greater than `cur_view` if the node is catching up.
+    let last_decided_view = task_state.consensus.read().await.last_decided_view();
     if usize::try_from(task_state.cur_view.u64()).unwrap()
-        > usize::try_from(consensus_reader.last_decided_view().u64()).unwrap()
+        > usize::try_from(last_decided_view.u64()).unwrap()
     {
-        consensus_reader
-            .metrics
+        task_state
+            .consensus_metrics
             .number_of_views_since_last_decide
             .set(
                 usize::try_from(task_state.cur_view.u64()).unwrap()
-                    - usize::try_from(consensus_reader.last_decided_view().u64()).unwrap(),
+                    - usize::try_from(last_decided_view.u64()).unwrap(),
             );
     }
 
@@ -291,24 +361,42 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
         "Timeout event is for an old view"
     );
 
+    let has_stake = task_state
+        .membership
+        .has_stake(&task_state.public_key, epoch);
+    let diagnostics = task_state.consensus.read().await.timeout_diagnostics(
+        view_number,
+        &task_state.public_key,
+        task_state.membership.leader(view_number, epoch).ok(),
+        has_stake,
+    );
+    broadcast_event(
+        Event {
+            view_number,
+            event: EventType::ViewTimeout {
+                view_number,
+                diagnostics,
+            },
+        },
+        &task_state.output_event_stream,
+    )
+    .await;
+
     ensure!(
-        task_state
-            .membership
-            .has_stake(&task_state.public_key, epoch),
+        has_stake,
         debug!(
             "We were not chosen for the consensus committee for view {:?}",
             view_number
         )
     );
 
-    let vote = TimeoutVote2::create_signed_vote(
+    let vote = TimeoutVote2::create_signed_vote_with_signer(
         TimeoutData2::<TYPES> {
             view: view_number,
             epoch,
         },
         view_number,
-        &task_state.public_key,
-        &task_state.private_key,
+        task_state.signer.as_ref(),
         &task_state.upgrade_lock,
     )
     .await
@@ -316,14 +404,6 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
     .context(error!("Failed to sign TimeoutData"))?;
 
     broadcast_event(Arc::new(HotShotEvent::TimeoutVoteSend(vote)), sender).await;
-    broadcast_event(
-        Event {
-            view_number,
-            event: EventType::ViewTimeout { view_number },
-        },
-        &task_state.output_event_stream,
-    )
-    .await;
 
     tracing::error!(
         "We did not receive evidence for view {} in time, sending timeout vote for that view!",
@@ -339,23 +419,14 @@ pub(crate) async fn handle_timeout<TYPES: NodeType, I: NodeImplementation<TYPES>
     )
     .await;
 
-    task_state
-        .consensus
-        .read()
-        .await
-        .metrics
-        .number_of_timeouts
-        .add(1);
+    task_state.consensus_metrics.number_of_timeouts.add(1);
     if task_state
         .membership
         .leader(view_number, task_state.cur_epoch)?
         == task_state.public_key
     {
         task_state
-            .consensus
-            .read()
-            .await
-            .metrics
+            .consensus_metrics
             .number_of_timeouts_as_leader
             .add(1);
     }
@@ -11,16 +11,17 @@ use async_trait::async_trait;
 use either::Either;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
-    consensus::OuterConsensus,
+    consensus::{CommitRule, ConsensusMetricsValue, OuterConsensus},
     event::Event,
     message::UpgradeLock,
     simple_certificate::{QuorumCertificate2, TimeoutCertificate2},
     simple_vote::{QuorumVote2, TimeoutVote2},
     traits::{
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
     },
     utils::epoch_from_block_number,
+    view_timing::ViewPhase,
     vote::HasViewNumber,
 };
 use tokio::task::JoinHandle;
@@ -28,9 +29,13 @@ use tracing::instrument;
 use utils::anytrace::*;
 
 use self::handlers::{
-    handle_quorum_vote_recv, handle_timeout, handle_timeout_vote_recv, handle_view_change,
+    handle_partial_certificate_recv, handle_quorum_vote_recv, handle_timeout,
+    handle_timeout_vote_recv, handle_view_change,
+};
+use crate::{
+    events::HotShotEvent, helpers::broadcast_event, vote_collection::VoteCollectorsMap,
+    workers::WorkerPool,
 };
-use crate::{events::HotShotEvent, helpers::broadcast_event, vote_collection::VoteCollectorsMap};
 
 /// Event handlers for use in the `handle` method.
 mod handlers;
@@ -40,8 +45,8 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Immutable instance state
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -80,6 +85,12 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// A reference to the metrics trait.
     pub consensus: OuterConsensus<TYPES>,
 
+    /// Consensus metrics, kept here so that updating them doesn't need the consensus lock
+    pub consensus_metrics: Arc<ConsensusMetricsValue>,
+
+    /// Threads to check vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
+
     /// The node's id
     pub id: u64,
 
@@ -88,6 +99,9 @@ pub struct ConsensusTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// The rule by which leaves are decided, under which a new leader may need our high QC
+    pub commit_rule: CommitRule,
 }
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskState<TYPES, I, V> {
     /// Handles a consensus event received on the event stream
@@ -105,6 +119,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
                     tracing::debug!("Failed to handle QuorumVoteRecv event; error = {e}");
                 }
             }
+            HotShotEvent::PartialCertificateRecv(ref partial) => {
+                if let Err(e) =
+                    handle_partial_certificate_recv(partial, Arc::clone(&event), &sender, self)
+                        .await
+                {
+                    tracing::debug!("Failed to handle PartialCertificateRecv event; error = {e}");
+                }
+            }
             HotShotEvent::TimeoutVoteRecv(ref vote) => {
                 if let Err(e) =
                     handle_timeout_vote_recv(vote, Arc::clone(&event), &sender, self).await
@@ -125,6 +147,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> ConsensusTaskSt
                 }
             }
             HotShotEvent::Qc2Formed(Either::Left(quorum_cert)) => {
+                self.consensus
+                    .write()
+                    .await
+                    .record_view_phase(quorum_cert.view_number(), ViewPhase::QcFormed);
                 if !self
                     .consensus
                     .read()
@@ -4,25 +4,27 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{marker::PhantomData, sync::Arc};
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
 
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
-    consensus::{Consensus, OuterConsensus},
+    block_limits::BlockLimits,
+    consensus::{Consensus, OuterConsensus, SavedPayloads},
     data::{DaProposal2, PackedBundle},
     event::{Event, EventType},
     message::{Proposal, UpgradeLock},
+    payload_stream::{DaProposalHeader, PayloadAssembler, PayloadChunk, PayloadStreamConfig},
     simple_certificate::DaCertificate2,
     simple_vote::{DaData2, DaVote2},
     traits::{
-        block_contents::vid_commitment,
+        block_contents::{vid_commitment, BlockPayload},
         election::Membership,
         network::ConnectedNetwork,
         node_implementation::{NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
         storage::Storage,
     },
     vote::HasViewNumber,
@@ -36,8 +38,29 @@ use crate::{
     events::HotShotEvent,
     helpers::broadcast_event,
     vote_collection::{handle_vote, VoteCollectorsMap},
+    workers::WorkerPool,
 };
 
+/// A DA proposal whose payload is being streamed to us.
+pub struct PayloadStream<TYPES: NodeType> {
+    /// The validated header, and the chunks collected against it so far
+    receiving: Option<(DaProposalHeader<TYPES>, PayloadAssembler)>,
+    /// Chunks which arrived before the header, to be checked once it does
+    early_chunks: Vec<PayloadChunk<TYPES>>,
+    /// Total size of `early_chunks`, in bytes
+    early_bytes: u64,
+}
+
+impl<TYPES: NodeType> Default for PayloadStream<TYPES> {
+    fn default() -> Self {
+        Self {
+            receiving: None,
+            early_chunks: Vec::new(),
+            early_bytes: 0,
+        }
+    }
+}
+
 /// Tracks state of a DA task
 pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// Output events to application
@@ -52,6 +75,10 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     /// Reference to consensus. Leader will require a read lock on this.
     pub consensus: OuterConsensus<TYPES>,
 
+    /// Payloads we have promised to make available, shared with consensus but separately locked
+    /// so that saving one doesn't need the consensus write lock.
+    pub saved_payloads: SavedPayloads<TYPES>,
+
     /// Membership for the DA committee and quorum committee.
     /// We need the latter only for calculating the proper VID scheme
     /// from the number of nodes in the quorum.
@@ -66,8 +93,8 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes and proposals
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// This state's ID
     pub id: u64,
@@ -77,6 +104,18 @@ pub struct DaTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Version
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Resource limits a proposed block must respect before we vote for it
+    pub block_limits: BlockLimits,
+
+    /// Threads to check proposal and vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
+
+    /// Whether and how we stream the payloads of our DA proposals
+    pub payload_streaming: PayloadStreamConfig,
+
+    /// DA proposals whose payloads are being streamed to us, by view
+    pub payload_streams: BTreeMap<TYPES::View, PayloadStream<TYPES>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYPES, I, V> {
@@ -89,57 +128,123 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
     ) -> Result<()> {
         match event.as_ref() {
             HotShotEvent::DaProposalRecv(proposal, sender) => {
-                let sender = sender.clone();
                 tracing::debug!(
                     "DA proposal received for view: {:?}",
                     proposal.data.view_number()
                 );
-                // ED NOTE: Assuming that the next view leader is the one who sends DA proposal for this view
+                let payload_hash = Sha256::digest(&proposal.data.encoded_transactions).into();
+                self.validate_proposal(proposal, sender.clone(), payload_hash, &event_stream)
+                    .await?;
+            }
+            HotShotEvent::DaProposalHeaderRecv(proposal, sender) => {
                 let view = proposal.data.view_number();
+                tracing::debug!("DA proposal header received for view: {:?}", view);
 
-                // Allow a DA proposal that is one view older, in case we have voted on a quorum
-                // proposal and updated the view.
-                //
-                // Anything older is discarded because it is no longer relevant.
+                // Same freshness rules as for a whole DA proposal
                 ensure!(
                     self.cur_view <= view + 1,
-                    "Throwing away DA proposal that is more than one view older"
+                    "Throwing away DA proposal header that is more than one view older"
                 );
-
                 ensure!(
-                    !self
-                      .consensus
-                      .read()
-                      .await
-                      .saved_payloads()
-                      .contains_key(&view),
+                    !self.saved_payloads.contains_key(&view),
                     info!(
-                      "Received DA proposal for view {:?} but we already have a payload for that view.  Throwing it away",
-                      view
+                        "Received DA proposal header for view {:?} but we already have a payload for that view.  Throwing it away",
+                        view
                     )
                 );
+                ensure!(
+                    !self
+                        .payload_streams
+                        .get(&view)
+                        .is_some_and(|stream| stream.receiving.is_some()),
+                    debug!("Already receiving the payload for view {:?}", view)
+                );
 
-                let encoded_transactions_hash = Sha256::digest(&proposal.data.encoded_transactions);
                 let view_leader_key = self.membership.leader(view, self.cur_epoch)?;
                 ensure!(
-                    view_leader_key == sender,
+                    view_leader_key == *sender,
                     warn!(
-                      "DA proposal doesn't have expected leader key for view {} \n DA proposal is: {:?}",
-                      *view,
-                      proposal.data.clone()
+                        "DA proposal header doesn't have expected leader key for view {}",
+                        *view
                     )
                 );
+                ensure!(
+                    self.verification_workers
+                        .verify(
+                            view_leader_key,
+                            proposal.signature.clone(),
+                            proposal.data.digest()
+                        )
+                        .await,
+                    warn!("Could not verify DA proposal header.")
+                );
+
+                // Reject an oversized block before any of it is downloaded
+                self.block_limits
+                    .check_size(proposal.data.payload_size)
+                    .wrap()
+                    .context(warn!(
+                        "DA proposal for view {} exceeds the block limits",
+                        *view
+                    ))?;
+                let mut assembler = proposal
+                    .data
+                    .assembler()
+                    .wrap()
+                    .context(warn!("Invalid DA proposal header for view {}", *view))?;
+
+                let stream = self.payload_streams.entry(view).or_default();
+                for chunk in std::mem::take(&mut stream.early_chunks) {
+                    if let Err(e) = assembler.insert(chunk.index, chunk.bytes) {
+                        tracing::warn!("Invalid payload chunk for view {:?}: {e}", view);
+                    }
+                }
+                stream.early_bytes = 0;
+                stream.receiving = Some((proposal.data.clone(), assembler));
+
+                self.finish_payload_stream(view, sender, &event_stream)
+                    .await?;
+            }
+            HotShotEvent::DaPayloadChunkRecv(chunk, sender) => {
+                let view = chunk.view_number();
 
                 ensure!(
-                    view_leader_key.validate(&proposal.signature, &encoded_transactions_hash),
-                    warn!("Could not verify proposal.")
+                    self.cur_view <= view + 1,
+                    "Throwing away payload chunk that is more than one view older"
+                );
+                ensure!(
+                    !self.saved_payloads.contains_key(&view),
+                    debug!("Already have the payload for view {:?}", view)
+                );
+                ensure!(
+                    self.membership.leader(view, self.cur_epoch)? == *sender,
+                    warn!("Payload chunk for view {} wasn't sent by the leader", *view)
                 );
 
-                broadcast_event(
-                    Arc::new(HotShotEvent::DaProposalValidated(proposal.clone(), sender)),
-                    &event_stream,
-                )
-                .await;
+                let max_bytes = self.block_limits.max_bytes.unwrap_or(u64::MAX);
+                let stream = self.payload_streams.entry(view).or_default();
+                if let Some((_, assembler)) = &mut stream.receiving {
+                    assembler
+                        .insert(chunk.index, chunk.bytes.clone())
+                        .wrap()
+                        .context(warn!("Invalid payload chunk for view {}", *view))?;
+                } else {
+                    // Chunks can overtake their header, so keep them until it arrives
+                    let early_bytes = stream.early_bytes + chunk.bytes.len() as u64;
+                    ensure!(
+                        early_bytes <= max_bytes,
+                        warn!(
+                            "Too many payload chunks for view {} without a header",
+                            *view
+                        )
+                    );
+                    stream.early_bytes = early_bytes;
+                    stream.early_chunks.push(chunk.clone());
+                    return Ok(());
+                }
+
+                self.finish_payload_stream(view, sender, &event_stream)
+                    .await?;
             }
             HotShotEvent::DaProposalValidated(proposal, sender) => {
                 let cur_view = self.consensus.read().await.cur_view();
@@ -161,7 +266,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                         view_number,
                         event: EventType::DaProposal {
                             proposal: proposal.clone(),
-                            sender: sender.clone(),
+                            sender: sender.clone().into(),
                         },
                     },
                     &self.output_event_stream,
@@ -190,14 +295,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     .wrap()
                     .context(error!("Failed to append DA proposal to storage"))?;
                 // Generate and send vote
-                let vote = DaVote2::create_signed_vote(
+                let vote = DaVote2::create_signed_vote_with_signer(
                     DaData2 {
                         payload_commit: payload_commitment,
                         epoch: epoch_number,
                     },
                     view_number,
-                    &self.public_key,
-                    &self.private_key,
+                    self.signer.as_ref(),
                     &self.upgrade_lock,
                 )
                 .await?;
@@ -205,20 +309,20 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 tracing::debug!("Sending vote to the DA leader {:?}", vote.view_number());
 
                 broadcast_event(Arc::new(HotShotEvent::DaVoteSend(vote)), &event_stream).await;
-                let mut consensus_writer = self.consensus.write().await;
 
-                // Ensure this view is in the view map for garbage collection.
-
-                if let Err(e) =
-                    consensus_writer.update_da_view(view_number, epoch_number, payload_commitment)
+                // Record the payload we have promised to make available.
+                if let Err(e) = self
+                    .saved_payloads
+                    .insert(view_number, Arc::clone(&proposal.data.encoded_transactions))
                 {
                     tracing::trace!("{e:?}");
                 }
 
-                // Record the payload we have promised to make available.
-                if let Err(e) = consensus_writer.update_saved_payloads(
+                // Ensure this view is in the view map for garbage collection.
+                if let Err(e) = self.consensus.write().await.update_da_view(
                     view_number,
-                    Arc::clone(&proposal.data.encoded_transactions),
+                    epoch_number,
+                    payload_commitment,
                 ) {
                     tracing::trace!("{e:?}");
                 }
@@ -227,7 +331,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     let consensus =
                         OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
                     let membership = Arc::clone(&self.membership);
-                    let pk = self.private_key.clone();
+                    let signer = Arc::clone(&self.signer);
                     let public_key = self.public_key.clone();
                     let chan = event_stream.clone();
                     spawn(async move {
@@ -235,7 +339,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                             OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
                             view_number,
                             membership,
-                            &pk,
+                            signer.as_ref(),
                         )
                         .await;
                         if let Some(Some(vid_share)) = consensus
@@ -282,6 +386,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     &event,
                     &event_stream,
                     &self.upgrade_lock,
+                    &self.verification_workers,
                     true,
                 )
                 .await?;
@@ -301,6 +406,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     tracing::info!("View changed by more than 1 going to view {:?}", view);
                 }
                 self.cur_view = view;
+
+                // Drop the streams which can no longer make a fresh DA proposal
+                self.payload_streams
+                    .retain(|stream_view, _| *stream_view + 1 >= view);
             }
             HotShotEvent::BlockRecv(packed_bundle) => {
                 let PackedBundle::<TYPES> {
@@ -316,9 +425,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                 let encoded_transactions_hash = Sha256::digest(encoded_transactions);
 
                 // sign the encoded transactions as opposed to the VID commitment
-                let signature =
-                    TYPES::SignatureKey::sign(&self.private_key, &encoded_transactions_hash)
-                        .wrap()?;
+                let signature = self
+                    .signer
+                    .sign(&encoded_transactions_hash)
+                    .await
+                    .wrap()
+                    .context(warn!(
+                        "Failed to sign the DA proposal for view {}",
+                        *view_number
+                    ))?;
 
                 if self.membership.leader(view_number, *epoch_number)? != self.public_key {
                     tracing::debug!(
@@ -340,6 +455,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
                     _pd: PhantomData,
                 };
 
+                if self
+                    .payload_streaming
+                    .should_stream(encoded_transactions.len())
+                {
+                    return self.stream_proposal(&message, &event_stream).await;
+                }
+
                 broadcast_event(
                     Arc::new(HotShotEvent::DaProposalSend(
                         message.clone(),
@@ -353,6 +475,149 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> DaTaskState<TYP
         }
         Ok(())
     }
+
+    /// Send a DA proposal as a signed header followed by the chunks of its payload.
+    async fn stream_proposal(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let (header, chunks) = DaProposalHeader::split(proposal, self.payload_streaming.chunk_size);
+        let signature = self
+            .signer
+            .sign(&header.digest())
+            .await
+            .wrap()
+            .context(warn!("Failed to sign the DA proposal header"))?;
+
+        broadcast_event(
+            Arc::new(HotShotEvent::DaProposalHeaderSend(
+                Proposal {
+                    data: header,
+                    signature,
+                    _pd: PhantomData,
+                },
+                self.public_key.clone(),
+            )),
+            event_stream,
+        )
+        .await;
+        for chunk in chunks {
+            broadcast_event(
+                Arc::new(HotShotEvent::DaPayloadChunkSend(
+                    chunk,
+                    self.public_key.clone(),
+                )),
+                event_stream,
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Check a DA proposal, given the SHA-256 hash of its payload, and pass it on once it is valid.
+    async fn validate_proposal(
+        &self,
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        sender: TYPES::SignatureKey,
+        payload_hash: [u8; 32],
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        // ED NOTE: Assuming that the next view leader is the one who sends DA proposal for this view
+        let view = proposal.data.view_number();
+
+        // Allow a DA proposal that is one view older, in case we have voted on a quorum
+        // proposal and updated the view.
+        //
+        // Anything older is discarded because it is no longer relevant.
+        ensure!(
+            self.cur_view <= view + 1,
+            "Throwing away DA proposal that is more than one view older"
+        );
+
+        ensure!(
+            !self.saved_payloads.contains_key(&view),
+            info!(
+              "Received DA proposal for view {:?} but we already have a payload for that view.  Throwing it away",
+              view
+            )
+        );
+
+        let view_leader_key = self.membership.leader(view, self.cur_epoch)?;
+        ensure!(
+            view_leader_key == sender,
+            warn!(
+                "DA proposal doesn't have expected leader key for view {} \n DA proposal is: {:?}",
+                *view,
+                proposal.data.clone()
+            )
+        );
+
+        ensure!(
+            self.verification_workers
+                .verify(view_leader_key, proposal.signature.clone(), payload_hash)
+                .await,
+            warn!("Could not verify proposal.")
+        );
+
+        let payload = <TYPES::BlockPayload as BlockPayload<TYPES>>::from_bytes(
+            &proposal.data.encoded_transactions,
+            &proposal.data.metadata,
+        );
+        self.block_limits
+            .check::<TYPES>(&payload, &proposal.data.metadata)
+            .wrap()
+            .context(warn!(
+                "DA proposal for view {} exceeds the block limits",
+                *view
+            ))?;
+
+        broadcast_event(
+            Arc::new(HotShotEvent::DaProposalValidated(proposal.clone(), sender)),
+            event_stream,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Once the whole payload of the streamed DA proposal for `view` has arrived, validate the
+    /// rebuilt proposal, whose payload was hashed as it arrived.
+    async fn finish_payload_stream(
+        &mut self,
+        view: TYPES::View,
+        sender: &TYPES::SignatureKey,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let complete = self
+            .payload_streams
+            .get(&view)
+            .and_then(|stream| stream.receiving.as_ref())
+            .is_some_and(|(_, assembler)| assembler.is_complete());
+        if !complete {
+            return Ok(());
+        }
+        let Some((header, assembler)) = self
+            .payload_streams
+            .remove(&view)
+            .and_then(|stream| stream.receiving)
+        else {
+            return Ok(());
+        };
+
+        let (payload, payload_hash) = assembler
+            .finish_with_hash()
+            .wrap()
+            .context(warn!("Failed to rebuild the payload for view {}", *view))?;
+        self.validate_proposal(
+            &header.into_proposal(payload),
+            sender.clone(),
+            payload_hash,
+            event_stream,
+        )
+        .await
+    }
 }
 
 #[async_trait]
@@ -0,0 +1,628 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use committable::Commitment;
+use either::Either;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    consensus::OuterConsensus,
+    dag_mempool::{BatchProposal, BatchStore, DagMempoolConfig, TransactionBatch},
+    data::{DaProposal2, VidDisperse, VidDisperseShare2},
+    message::{Proposal, UpgradeLock},
+    simple_certificate::BatchCertificate,
+    simple_vote::{BatchData, BatchVote},
+    traits::{
+        block_contents::{BuilderFee, EncodeBytes},
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::BuilderSignatureKey,
+        signer::Signer,
+        BlockPayload,
+    },
+    vid::VidCommitment,
+    vote::{Certificate, HasViewNumber, VoteAccumulator},
+};
+use sha2::{Digest, Sha256};
+use utils::anytrace::*;
+use vbs::version::StaticVersionType;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Number of views a leader waits for its proposal to be validated before the batches it claimed
+/// are returned to the pool.
+const CLAIM_GRACE_VIEWS: u64 = 2;
+
+/// Accumulator for the availability votes of one of our batches
+type BatchAccumulator<TYPES, V> =
+    VoteAccumulator<TYPES, BatchVote<TYPES>, BatchCertificate<TYPES>, V>;
+
+/// Collects the transactions submitted to this node into batches, certifies that they are
+/// available, proposes this node's blocks by the certificates of their batches when it leads, and
+/// rebuilds the blocks other leaders propose that way.
+pub struct DagMempoolTaskState<TYPES: NodeType, V: Versions> {
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Signs our votes
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
+
+    /// Membership for the quorum, whose stake certifies batches
+    pub membership: Arc<TYPES::Membership>,
+
+    /// Reference to consensus, for the state blocks are built on
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// The instance state blocks are built with
+    pub instance_state: Arc<TYPES::InstanceState>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// DAG mempool settings
+    pub config: DagMempoolConfig,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// Transactions submitted to us which are not in a batch yet
+    pub pending: Vec<TYPES::Transaction>,
+
+    /// The batches we hold
+    pub store: BatchStore<TYPES>,
+
+    /// Votes for each of our batches which is not certified yet, with the view it was sent in
+    pub accumulators:
+        HashMap<Commitment<TransactionBatch<TYPES>>, (TYPES::View, BatchAccumulator<TYPES, V>)>,
+
+    /// The view and epoch of the last block we built before entering its view
+    pub prefetched: Option<(TYPES::View, TYPES::Epoch)>,
+
+    /// Checked proposals whose batches we don't all hold yet, by view, with their leader
+    pub waiting: BTreeMap<TYPES::View, (BatchProposal<TYPES>, TYPES::SignatureKey)>,
+
+    /// This node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> DagMempoolTaskState<TYPES, V> {
+    /// Vote that we hold the batch with commitment `batch_commit`, sent in `view`.
+    async fn vote(
+        &self,
+        batch_commit: Commitment<TransactionBatch<TYPES>>,
+        view: TYPES::View,
+    ) -> Result<BatchVote<TYPES>> {
+        BatchVote::create_signed_vote_with_signer(
+            BatchData {
+                batch_commit,
+                epoch: self.cur_epoch,
+            },
+            view,
+            self.signer.as_ref(),
+            &self.upgrade_lock,
+        )
+        .await
+    }
+
+    /// Count `vote` towards the certificate of our batch, broadcasting the certificate once the
+    /// batch is certified.
+    async fn accumulate(
+        &mut self,
+        vote: &BatchVote<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        let batch_commit = vote.data.batch_commit;
+        let Some((_, accumulator)) = self.accumulators.get_mut(&batch_commit) else {
+            return;
+        };
+        let Either::Right(cert) = accumulator
+            .accumulate(vote, &self.membership, vote.data.epoch)
+            .await
+        else {
+            return;
+        };
+
+        self.accumulators.remove(&batch_commit);
+        self.store.certify(cert.clone());
+        broadcast_event(
+            Arc::new(HotShotEvent::BatchCertificateSend(
+                cert,
+                self.public_key.clone(),
+            )),
+            event_stream,
+        )
+        .await;
+    }
+
+    /// Put the transactions submitted to us since the last view into a batch and send it out.
+    async fn send_batch(&mut self, event_stream: &Sender<Arc<HotShotEvent<TYPES>>>) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let rest = self
+            .pending
+            .split_off(self.pending.len().min(self.config.max_batch_size));
+        let batch = TransactionBatch {
+            author: self.public_key.clone(),
+            view: self.cur_view,
+            transactions: std::mem::replace(&mut self.pending, rest),
+        };
+        let batch_commit = self.store.insert(batch.clone());
+
+        self.accumulators.insert(
+            batch_commit,
+            (
+                self.cur_view,
+                VoteAccumulator::new(self.upgrade_lock.clone()),
+            ),
+        );
+        broadcast_event(
+            Arc::new(HotShotEvent::BatchSend(batch, self.public_key.clone())),
+            event_stream,
+        )
+        .await;
+
+        // We hold our own batch, so our vote counts towards its certificate
+        if self.membership.has_stake(&self.public_key, self.cur_epoch) {
+            let vote = self.vote(batch_commit, self.cur_view).await?;
+            self.accumulate(&vote, event_stream).await;
+        }
+
+        Ok(())
+    }
+
+    /// Build the payload of the block made of the batches in `batches`, which we all hold.
+    async fn build_payload(
+        &self,
+        batches: &[Commitment<TransactionBatch<TYPES>>],
+    ) -> Result<(
+        TYPES::BlockPayload,
+        <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    )> {
+        let transactions = self.store.transactions(batches);
+        let validated_state = self.consensus.read().await.decided_state();
+
+        TYPES::BlockPayload::from_transactions(transactions, &validated_state, &self.instance_state)
+            .await
+            .wrap()
+            .context(error!("Failed to build a block from certified batches"))
+    }
+
+    /// Propose our block for `view` by the certificates of certified batches no other leader has
+    /// claimed, and take the block through DA and VID ourselves, as every other node does once it
+    /// has the batches.
+    async fn propose_block(
+        &mut self,
+        view: TYPES::View,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let version = self.upgrade_lock.version(view).await?;
+        let epoch = self.cur_epoch;
+        let certificates = self.store.next_block(self.config.max_batches_per_block);
+        let batches: Vec<_> = certificates
+            .iter()
+            .map(|cert| cert.data.batch_commit)
+            .collect();
+        let (payload, metadata) = self.build_payload(&batches).await?;
+        tracing::debug!(
+            "Proposing {} certified batches for view {view:?}",
+            batches.len()
+        );
+
+        let encoded_transactions = payload.encode();
+        let da_signature = self
+            .signer
+            .sign(&Sha256::digest(&encoded_transactions))
+            .await
+            .wrap()
+            .context(warn!("Failed to sign the DA proposal for view {}", *view))?;
+        let vid_disperse = VidDisperse::calculate_vid_disperse(
+            Arc::clone(&encoded_transactions),
+            &self.membership,
+            view,
+            epoch,
+            None,
+        )
+        .await;
+        let payload_commitment = vid_disperse.payload_commitment;
+        let vid_signature = self
+            .signer
+            .sign(payload_commitment.as_ref())
+            .await
+            .wrap()
+            .context(warn!("Failed to sign the VID dispersal for view {}", *view))?;
+
+        let mut consensus_writer = self.consensus.write().await;
+        for share in VidDisperseShare2::from_vid_disperse(vid_disperse.clone()) {
+            consensus_writer.update_vid_shares(
+                view,
+                Proposal {
+                    data: share,
+                    signature: vid_signature.clone(),
+                    _pd: PhantomData,
+                },
+            );
+        }
+        drop(consensus_writer);
+
+        let fee = block_fee::<TYPES, V>(&metadata, &payload_commitment, version, view)
+            .context(error!("Failed to sign the fee of a DAG mempool block"))?;
+        let auction_result =
+            (version >= V::Marketplace::VERSION).then(TYPES::AuctionResult::default);
+
+        let proposal = BatchProposal {
+            view_number: view,
+            epoch,
+            certificates,
+            metadata: metadata.clone(),
+            da_signature,
+            vid_signature: vid_signature.clone(),
+        };
+        self.store.claim(view, batches);
+        broadcast_event(
+            Arc::new(HotShotEvent::BatchProposalSend(
+                proposal.clone(),
+                self.public_key.clone(),
+            )),
+            event_stream,
+        )
+        .await;
+        broadcast_event(
+            Arc::new(HotShotEvent::SendPayloadCommitmentAndMetadata(
+                payload_commitment,
+                payload.builder_commitment(&metadata),
+                metadata,
+                view,
+                vec1::vec1![fee],
+                auction_result,
+            )),
+            event_stream,
+        )
+        .await;
+        broadcast_event(
+            Arc::new(HotShotEvent::VidDisperseComputed(Proposal {
+                data: vid_disperse.clone(),
+                signature: vid_signature,
+                _pd: PhantomData,
+            })),
+            event_stream,
+        )
+        .await;
+
+        let leader = self.public_key.clone();
+        self.deliver(
+            &proposal,
+            &leader,
+            encoded_transactions,
+            vid_disperse,
+            event_stream,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Check the proposal `proposal` received from `sender`, and rebuild its block once we hold
+    /// all of its batches.
+    async fn receive_proposal(
+        &mut self,
+        proposal: &BatchProposal<TYPES>,
+        sender: &TYPES::SignatureKey,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let view = proposal.view_number;
+        ensure!(
+            view + 1 >= self.cur_view,
+            debug!("Received a batch proposal more than one view old")
+        );
+        ensure!(
+            self.membership.leader(view, self.cur_epoch)? == *sender,
+            warn!("Received a batch proposal from a node which does not lead view {view:?}")
+        );
+        ensure!(
+            proposal.certificates.len() <= self.config.max_batches_per_block,
+            warn!("Received a batch proposal with more than the maximum number of batches")
+        );
+        for cert in &proposal.certificates {
+            self.check_certificate(cert).await?;
+        }
+        for cert in &proposal.certificates {
+            self.store.certify(cert.clone());
+        }
+
+        if !self.store.holds_all(&proposal.batches()) {
+            tracing::debug!("Waiting for the batches of the proposal for view {view:?}");
+            self.waiting
+                .insert(view, (proposal.clone(), sender.clone()));
+            return Ok(());
+        }
+
+        self.rebuild(proposal, sender, event_stream).await
+    }
+
+    /// Rebuild the block of `proposal`, whose batches we all hold, and take it through DA and VID
+    /// under the signatures of its leader `sender`.
+    async fn rebuild(
+        &mut self,
+        proposal: &BatchProposal<TYPES>,
+        sender: &TYPES::SignatureKey,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let view = proposal.view_number;
+        let batches = proposal.batches();
+        let (payload, metadata) = self.build_payload(&batches).await?;
+        ensure!(
+            metadata == proposal.metadata,
+            warn!("Rebuilt a block other than the one proposed for view {view:?}")
+        );
+
+        let encoded_transactions = payload.encode();
+        let vid_disperse = VidDisperse::calculate_vid_disperse(
+            Arc::clone(&encoded_transactions),
+            &self.membership,
+            view,
+            proposal.epoch,
+            None,
+        )
+        .await;
+
+        self.store.claim(view, batches);
+        self.deliver(
+            proposal,
+            sender,
+            encoded_transactions,
+            vid_disperse,
+            event_stream,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Hand the block of `proposal`, with payload `encoded_transactions` and VID dispersal
+    /// `vid_disperse`, to the DA and quorum vote tasks as the DA proposal and VID share its leader
+    /// `sender` signed.
+    ///
+    /// The signatures are checked there, so a block other than the one the leader signed is not
+    /// voted for.
+    async fn deliver(
+        &self,
+        proposal: &BatchProposal<TYPES>,
+        sender: &TYPES::SignatureKey,
+        encoded_transactions: Arc<[u8]>,
+        vid_disperse: VidDisperse<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        broadcast_event(
+            Arc::new(HotShotEvent::DaProposalRecv(
+                Proposal {
+                    data: DaProposal2 {
+                        encoded_transactions,
+                        metadata: proposal.metadata.clone(),
+                        view_number: proposal.view_number,
+                        epoch: proposal.epoch,
+                    },
+                    signature: proposal.da_signature.clone(),
+                    _pd: PhantomData,
+                },
+                sender.clone(),
+            )),
+            event_stream,
+        )
+        .await;
+
+        let Some(share) = VidDisperseShare2::from_vid_disperse(vid_disperse)
+            .into_iter()
+            .find(|share| share.recipient_key == self.public_key)
+        else {
+            return;
+        };
+        broadcast_event(
+            Arc::new(HotShotEvent::VidShareRecv(
+                sender.clone(),
+                Proposal {
+                    data: share,
+                    signature: proposal.vid_signature.clone(),
+                    _pd: PhantomData,
+                },
+            )),
+            event_stream,
+        )
+        .await;
+    }
+
+    /// Check the availability certificate `cert` of a batch.
+    async fn check_certificate(&self, cert: &BatchCertificate<TYPES>) -> Result<()> {
+        let epoch = cert.data.epoch;
+        ensure!(
+            cert.is_valid_cert(
+                &self.membership.stake_table(epoch),
+                self.membership.failure_threshold(epoch),
+                &self.upgrade_lock
+            )
+            .await,
+            warn!("Received an invalid batch certificate")
+        );
+
+        Ok(())
+    }
+
+    /// Handles an event.
+    ///
+    /// # Errors
+    /// If the event is invalid or we fail to act on it.
+    pub async fn handle(
+        &mut self,
+        event: Arc<HotShotEvent<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::TransactionsRecv(transactions) => {
+                self.pending.extend(transactions.iter().cloned());
+            }
+            HotShotEvent::ViewChange(view, epoch) => {
+                let view = TYPES::View::new(std::cmp::max(1, **view));
+                ensure!(view > self.cur_view, debug!("View change to an older view"));
+                self.cur_view = view;
+                self.cur_epoch = *epoch;
+
+                self.store.release_before(TYPES::View::new(
+                    view.u64().saturating_sub(CLAIM_GRACE_VIEWS),
+                ));
+                let expired = TYPES::View::new(view.u64().saturating_sub(self.config.batch_ttl));
+                self.store.prune_before(expired);
+                self.accumulators.retain(|_, (sent, _)| *sent >= expired);
+                self.waiting
+                    .retain(|proposed, _| *proposed + 1 >= self.cur_view);
+
+                if self.prefetched != Some((view, *epoch))
+                    && self.membership.leader(view, *epoch)? == self.public_key
+                {
+                    self.propose_block(view, event_stream).await?;
+                }
+
+                self.send_batch(event_stream).await?;
+            }
+            HotShotEvent::BatchRecv(batch, sender) => {
+                ensure!(
+                    batch.author == *sender,
+                    warn!("Received a batch relayed by a node other than its author")
+                );
+                ensure!(
+                    self.membership.has_stake(sender, self.cur_epoch),
+                    debug!("Received a batch from a node without stake")
+                );
+                ensure!(
+                    batch.transactions.len() <= self.config.max_batch_size,
+                    warn!("Received a batch larger than the maximum batch size")
+                );
+                ensure!(
+                    batch.view.u64().saturating_add(self.config.batch_ttl) >= self.cur_view.u64(),
+                    debug!("Received an expired batch")
+                );
+
+                let author = batch.author.clone();
+                let view = batch.view;
+                let batch_commit = self.store.insert(batch.clone());
+                if self.membership.has_stake(&self.public_key, self.cur_epoch) {
+                    let vote = self.vote(batch_commit, view).await?;
+                    broadcast_event(
+                        Arc::new(HotShotEvent::BatchVoteSend(vote, author)),
+                        event_stream,
+                    )
+                    .await;
+                }
+
+                // Rebuild the proposed blocks this batch was the last one missing from
+                let ready: Vec<_> = self
+                    .waiting
+                    .iter()
+                    .filter(|(_, (proposal, _))| self.store.holds_all(&proposal.batches()))
+                    .map(|(proposed, _)| *proposed)
+                    .collect();
+                for proposed in ready {
+                    let Some((proposal, leader)) = self.waiting.remove(&proposed) else {
+                        continue;
+                    };
+                    if let Err(e) = self.rebuild(&proposal, &leader, event_stream).await {
+                        tracing::warn!("Failed to rebuild the block for view {proposed:?}: {e:?}");
+                    }
+                }
+            }
+            HotShotEvent::BatchVoteRecv(vote) => {
+                self.accumulate(vote, event_stream).await;
+            }
+            HotShotEvent::BatchCertificateRecv(cert) => {
+                self.check_certificate(cert).await?;
+                self.store.certify(cert.clone());
+            }
+            HotShotEvent::BatchProposalRecv(proposal, sender) => {
+                self.receive_proposal(proposal, sender, event_stream)
+                    .await?;
+            }
+            HotShotEvent::QuorumProposalValidated(proposal, _) => {
+                self.store.confirm(proposal.data.view_number());
+            }
+            HotShotEvent::QuorumProposalPreliminarilyValidated(proposal) => {
+                // Drain the pool for the next view while this one is still voting
+                let view = proposal.data.view_number() + 1;
+                ensure!(
+                    view > self.cur_view
+                        && self
+                            .prefetched
+                            .map_or(true, |(prefetched, _)| prefetched < view),
+                    debug!("Already built a block for view {view:?}")
+                );
+
+                if self.membership.leader(view, self.cur_epoch)? == self.public_key {
+                    self.propose_block(view, event_stream).await?;
+                    self.prefetched = Some((view, self.cur_epoch));
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// The fee of a block built from certified batches.
+///
+/// No builder is involved, so like a null block the block pays nothing, and the fee is signed by
+/// the same fixed key.
+fn block_fee<TYPES: NodeType, V: Versions>(
+    metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    payload_commitment: &VidCommitment,
+    version: vbs::version::Version,
+    view: TYPES::View,
+) -> Option<BuilderFee<TYPES>> {
+    /// The fee of the block
+    const FEE_AMOUNT: u64 = 0;
+
+    let (fee_account, private_key) =
+        <TYPES::BuilderSignatureKey as BuilderSignatureKey>::generated_from_seed_indexed(
+            [0_u8; 32], 0,
+        );
+    let fee_signature = if version >= V::Marketplace::VERSION {
+        TYPES::BuilderSignatureKey::sign_sequencing_fee_marketplace(&private_key, FEE_AMOUNT, *view)
+            .ok()?
+    } else {
+        TYPES::BuilderSignatureKey::sign_fee(&private_key, FEE_AMOUNT, metadata, payload_commitment)
+            .ok()?
+    };
+
+    Some(BuilderFee {
+        fee_amount: FEE_AMOUNT,
+        fee_account,
+        fee_signature,
+    })
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for DagMempoolTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender).await
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -7,23 +7,27 @@
 use std::fmt::Display;
 
 use async_broadcast::Sender;
+use committable::Commitment;
 use either::Either;
 use hotshot_task::task::TaskEvent;
 use hotshot_types::{
+    anti_entropy::{AntiEntropyItem, DigestEntry},
+    dag_mempool::{BatchProposal, TransactionBatch},
     data::{
         DaProposal2, Leaf2, PackedBundle, QuorumProposal2, UpgradeProposal, VidDisperse,
         VidDisperseShare2,
     },
     message::Proposal,
+    payload_stream::{DaProposalHeader, PayloadChunk},
     request_response::ProposalRequestPayload,
     simple_certificate::{
-        DaCertificate2, QuorumCertificate, QuorumCertificate2, TimeoutCertificate,
-        TimeoutCertificate2, UpgradeCertificate, ViewSyncCommitCertificate2,
-        ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
+        BatchCertificate, DaCertificate2, FallbackCertificate, QuorumCertificate,
+        QuorumCertificate2, TimeoutCertificate, TimeoutCertificate2, UpgradeCertificate,
+        ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
-        DaVote2, QuorumVote2, TimeoutVote2, UpgradeVote, ViewSyncCommitVote2,
-        ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
+        BatchVote, DaVote2, FallbackVote, HighQcVote, QuorumVote2, TimeoutVote2, UpgradeVote,
+        ViewSyncCommitVote2, ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
     },
     traits::{
         block_contents::BuilderFee, network::DataRequest, node_implementation::NodeType,
@@ -32,6 +36,7 @@ use hotshot_types::{
     utils::BuilderCommitment,
     vid::VidCommitment,
     vote::HasViewNumber,
+    vote_aggregation::PartialQuorumCertificate,
 };
 use vec1::Vec1;
 
@@ -81,6 +86,13 @@ pub enum HotShotEvent<TYPES: NodeType> {
     TimeoutVoteSend(TimeoutVote2<TYPES>),
     /// A DA proposal has been received from the network; handled by the DA task
     DaProposalRecv(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
+    /// The header of a streamed DA proposal has been received from the network; handled by the DA task
+    DaProposalHeaderRecv(
+        Proposal<TYPES, DaProposalHeader<TYPES>>,
+        TYPES::SignatureKey,
+    ),
+    /// Part of the payload of a streamed DA proposal has been received from the network; handled by the DA task
+    DaPayloadChunkRecv(PayloadChunk<TYPES>, TYPES::SignatureKey),
     /// A DA proposal has been validated; handled by the DA task and VID task
     DaProposalValidated(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
     /// A DA vote has been received by the network; handled by the DA task
@@ -101,7 +113,7 @@ pub enum HotShotEvent<TYPES: NodeType> {
     /// 2. The proposal has been correctly signed by the leader of the current view
     /// 3. The justify QC is valid
     /// 4. The proposal passes either liveness or safety check.
-    QuorumProposalValidated(Proposal<TYPES, QuorumProposal2<TYPES>>, Leaf2<TYPES>),
+    QuorumProposalValidated(Proposal<TYPES, QuorumProposal2<TYPES>>, Arc<Leaf2<TYPES>>),
     /// A quorum proposal is missing for a view that we need.
     QuorumProposalRequestSend(
         ProposalRequestPayload<TYPES>,
@@ -118,6 +130,13 @@ pub enum HotShotEvent<TYPES: NodeType> {
     QuorumProposalResponseRecv(Proposal<TYPES, QuorumProposal2<TYPES>>),
     /// Send a DA proposal to the DA committee; emitted by the DA leader (which is the same node as the leader of view v + 1) in the DA task
     DaProposalSend(Proposal<TYPES, DaProposal2<TYPES>>, TYPES::SignatureKey),
+    /// Send the header of a streamed DA proposal to the DA committee; emitted by the DA leader in the DA task instead of [`HotShotEvent::DaProposalSend`] for large payloads
+    DaProposalHeaderSend(
+        Proposal<TYPES, DaProposalHeader<TYPES>>,
+        TYPES::SignatureKey,
+    ),
+    /// Send part of the payload of a streamed DA proposal to the DA committee; emitted by the DA leader in the DA task after the header
+    DaPayloadChunkSend(PayloadChunk<TYPES>, TYPES::SignatureKey),
     /// Send a DA vote to the DA leader; emitted by DA committee members in the DA task after seeing a valid DA proposal
     DaVoteSend(DaVote2<TYPES>),
     /// The next leader has collected enough votes to form a QC; emitted by the next leader in the consensus task; an internal event only
@@ -182,6 +201,9 @@ pub enum HotShotEvent<TYPES: NodeType> {
     ///
     /// Like [`HotShotEvent::DaProposalSend`].
     VidDisperseSend(Proposal<TYPES, VidDisperse<TYPES>>, TYPES::SignatureKey),
+    /// The VID dispersal of our block as the leader, which is not sent since every node computes
+    /// its own share of a block made of DAG mempool batches; emitted by the DAG mempool task
+    VidDisperseComputed(Proposal<TYPES, VidDisperse<TYPES>>),
     /// Vid disperse share has been received from the network; handled by the consensus task
     ///
     /// Like [`HotShotEvent::DaProposalRecv`].
@@ -238,15 +260,148 @@ pub enum HotShotEvent<TYPES: NodeType> {
         Proposal<TYPES, VidDisperseShare2<TYPES>>,
     ),
 
-    /// A replica send us a High QC
-    HighQcRecv(QuorumCertificate2<TYPES>, TYPES::SignatureKey),
+    /// A replica send us a High QC, with its signed report of the QC's view
+    HighQcRecv(
+        QuorumCertificate2<TYPES>,
+        HighQcVote<TYPES>,
+        TYPES::SignatureKey,
+    ),
 
-    /// Send our HighQc to the next leader, should go to the same leader as our vote
+    /// Send our HighQc and its signed report to the next leader, should go to the same leader as
+    /// our vote
     HighQcSend(
         QuorumCertificate2<TYPES>,
+        HighQcVote<TYPES>,
+        TYPES::SignatureKey,
+        TYPES::SignatureKey,
+    ),
+
+    /// Relay transactions we haven't seen before to all nodes; emitted by the mempool task.
+    MempoolGossipSend(Vec<TYPES::Transaction>, TYPES::SignatureKey),
+
+    /// Broadcast a digest of our pending transactions; emitted by the mempool task.
+    MempoolDigestSend(Vec<Commitment<TYPES::Transaction>>, TYPES::SignatureKey),
+
+    /// A peer advertised the transactions it has pending.
+    MempoolDigestRecv(Vec<Commitment<TYPES::Transaction>>, TYPES::SignatureKey),
+
+    /// Ask a peer for transactions from its digest which we are missing.
+    MempoolRequestSend(
+        Vec<Commitment<TYPES::Transaction>>,
+        /// Sender key
         TYPES::SignatureKey,
+        /// Recipient key
         TYPES::SignatureKey,
     ),
+
+    /// A peer asked us for transactions from our digest.
+    MempoolRequestRecv(Vec<Commitment<TYPES::Transaction>>, TYPES::SignatureKey),
+
+    /// Send the transactions a peer asked for.
+    MempoolResponseSend(
+        Vec<TYPES::Transaction>,
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+
+    /// Time to send a digest of our recent messages to a few peers; emitted periodically when
+    /// anti-entropy is enabled.
+    AntiEntropyTick,
+
+    /// Send a digest of our recent messages to a peer; emitted by the anti-entropy task.
+    AntiEntropyDigestSend(
+        Vec<DigestEntry>,
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+
+    /// A peer sent us a digest of its recent messages.
+    AntiEntropyDigestRecv(Vec<DigestEntry>, TYPES::SignatureKey),
+
+    /// Ask a peer for messages from its digest which we are missing.
+    AntiEntropyRequestSend(
+        Vec<DigestEntry>,
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+
+    /// A peer asked us for messages from our digest.
+    AntiEntropyRequestRecv(Vec<DigestEntry>, TYPES::SignatureKey),
+
+    /// Send the messages a peer asked for.
+    AntiEntropyResponseSend(
+        Vec<AntiEntropyItem<TYPES>>,
+        /// Sender key
+        TYPES::SignatureKey,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+
+    /// A peer sent us the messages we asked it for.
+    AntiEntropyResponseRecv(Vec<AntiEntropyItem<TYPES>>, TYPES::SignatureKey),
+
+    /// Broadcast a batch of the DAG mempool we collected; emitted by the DAG mempool task.
+    BatchSend(TransactionBatch<TYPES>, TYPES::SignatureKey),
+
+    /// A peer sent us a batch of the DAG mempool.
+    BatchRecv(TransactionBatch<TYPES>, TYPES::SignatureKey),
+
+    /// Vote that we hold a batch, to be sent to its author.
+    BatchVoteSend(
+        BatchVote<TYPES>,
+        /// Recipient key
+        TYPES::SignatureKey,
+    ),
+
+    /// A peer voted that it holds one of our batches.
+    BatchVoteRecv(BatchVote<TYPES>),
+
+    /// Broadcast the availability certificate of one of our batches.
+    BatchCertificateSend(BatchCertificate<TYPES>, TYPES::SignatureKey),
+
+    /// A peer sent us the availability certificate of its batch.
+    BatchCertificateRecv(BatchCertificate<TYPES>),
+
+    /// Broadcast our block for the view as the certified batches it is made of, as its leader.
+    BatchProposalSend(BatchProposal<TYPES>, TYPES::SignatureKey),
+
+    /// A peer proposed a block for the view as the certified batches it is made of.
+    BatchProposalRecv(BatchProposal<TYPES>, TYPES::SignatureKey),
+
+    /// Broadcast our vote in a round of the coin fallback; emitted by the fallback task.
+    FallbackVoteSend(FallbackVote<TYPES>),
+
+    /// A peer voted in a round of the coin fallback.
+    FallbackVoteRecv(FallbackVote<TYPES>),
+
+    /// Broadcast the certificate of a round of the coin fallback, which elects the leader
+    /// of the next view.
+    FallbackCertificateSend(FallbackCertificate<TYPES>, TYPES::SignatureKey),
+
+    /// A peer sent us the certificate of a round of the coin fallback.
+    FallbackCertificateRecv(FallbackCertificate<TYPES>),
+
+    /// Forward the quorum votes we checked to the leader of the next view; emitted by the vote
+    /// aggregator task.
+    PartialCertificateSend(
+        PartialQuorumCertificate<TYPES>,
+        /// Leader key
+        TYPES::SignatureKey,
+        /// Sender key
+        TYPES::SignatureKey,
+    ),
+
+    /// An aggregator forwarded us quorum votes.
+    PartialCertificateRecv(PartialQuorumCertificate<TYPES>),
+
+    /// We have waited long enough for the rest of the votes we aggregate for the view.
+    AggregationTimeout(TYPES::View),
 }
 
 impl<TYPES: NodeType> HotShotEvent<TYPES> {
@@ -272,6 +427,10 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::DaProposalRecv(proposal, _)
             | HotShotEvent::DaProposalValidated(proposal, _)
             | HotShotEvent::DaProposalSend(proposal, _) => Some(proposal.data.view_number()),
+            HotShotEvent::DaProposalHeaderRecv(header, _)
+            | HotShotEvent::DaProposalHeaderSend(header, _) => Some(header.data.view_number()),
+            HotShotEvent::DaPayloadChunkRecv(chunk, _)
+            | HotShotEvent::DaPayloadChunkSend(chunk, _) => Some(chunk.view_number()),
             HotShotEvent::DaVoteRecv(vote) | HotShotEvent::DaVoteSend(vote) => {
                 Some(vote.view_number())
             }
@@ -301,8 +460,40 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             HotShotEvent::BlockRecv(packed_bundle) => Some(packed_bundle.view_number),
             HotShotEvent::Shutdown
             | HotShotEvent::TransactionSend(_, _)
-            | HotShotEvent::TransactionsRecv(_) => None,
-            HotShotEvent::VidDisperseSend(proposal, _) => Some(proposal.data.view_number()),
+            | HotShotEvent::TransactionsRecv(_)
+            | HotShotEvent::MempoolGossipSend(..)
+            | HotShotEvent::MempoolDigestSend(..)
+            | HotShotEvent::MempoolDigestRecv(..)
+            | HotShotEvent::MempoolRequestSend(..)
+            | HotShotEvent::MempoolRequestRecv(..)
+            | HotShotEvent::MempoolResponseSend(..)
+            | HotShotEvent::AntiEntropyTick
+            | HotShotEvent::AntiEntropyDigestSend(..)
+            | HotShotEvent::AntiEntropyDigestRecv(..)
+            | HotShotEvent::AntiEntropyRequestSend(..)
+            | HotShotEvent::AntiEntropyRequestRecv(..)
+            | HotShotEvent::AntiEntropyResponseSend(..)
+            | HotShotEvent::AntiEntropyResponseRecv(..) => None,
+            HotShotEvent::BatchSend(batch, _) | HotShotEvent::BatchRecv(batch, _) => {
+                Some(batch.view)
+            }
+            HotShotEvent::BatchVoteSend(vote, _) | HotShotEvent::BatchVoteRecv(vote) => {
+                Some(vote.view_number())
+            }
+            HotShotEvent::BatchCertificateSend(cert, _)
+            | HotShotEvent::BatchCertificateRecv(cert) => Some(cert.view_number()),
+            HotShotEvent::BatchProposalSend(proposal, _)
+            | HotShotEvent::BatchProposalRecv(proposal, _) => Some(proposal.view_number),
+            HotShotEvent::FallbackVoteSend(vote) | HotShotEvent::FallbackVoteRecv(vote) => {
+                Some(vote.view_number())
+            }
+            HotShotEvent::FallbackCertificateSend(cert, _)
+            | HotShotEvent::FallbackCertificateRecv(cert) => Some(cert.view_number()),
+            HotShotEvent::PartialCertificateSend(partial, ..)
+            | HotShotEvent::PartialCertificateRecv(partial) => Some(partial.view_number()),
+            HotShotEvent::AggregationTimeout(view) => Some(*view),
+            HotShotEvent::VidDisperseSend(proposal, _)
+            | HotShotEvent::VidDisperseComputed(proposal) => Some(proposal.data.view_number()),
             HotShotEvent::VidShareRecv(_, proposal) | HotShotEvent::VidShareValidated(proposal) => {
                 Some(proposal.data.view_number())
             }
@@ -326,7 +517,7 @@ impl<TYPES: NodeType> HotShotEvent<TYPES> {
             | HotShotEvent::VidRequestRecv(request, _) => Some(request.view),
             HotShotEvent::VidResponseSend(_, _, proposal)
             | HotShotEvent::VidResponseRecv(_, proposal) => Some(proposal.data.view_number),
-            HotShotEvent::HighQcRecv(qc, _) | HotShotEvent::HighQcSend(qc, ..) => {
+            HotShotEvent::HighQcRecv(qc, ..) | HotShotEvent::HighQcSend(qc, ..) => {
                 Some(qc.view_number())
             }
         }
@@ -369,6 +560,17 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "DaProposalValidated(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::DaProposalHeaderRecv(header, _) => write!(
+                f,
+                "DaProposalHeaderRecv(view_number={:?})",
+                header.data.view_number()
+            ),
+            HotShotEvent::DaPayloadChunkRecv(chunk, _) => write!(
+                f,
+                "DaPayloadChunkRecv(view_number={:?}, index={})",
+                chunk.view_number(),
+                chunk.index
+            ),
             HotShotEvent::DaVoteRecv(vote) => {
                 write!(f, "DaVoteRecv(view_number={:?})", vote.view_number())
             }
@@ -398,6 +600,17 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "DaProposalSend(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::DaProposalHeaderSend(header, _) => write!(
+                f,
+                "DaProposalHeaderSend(view_number={:?})",
+                header.data.view_number()
+            ),
+            HotShotEvent::DaPayloadChunkSend(chunk, _) => write!(
+                f,
+                "DaPayloadChunkSend(view_number={:?}, index={})",
+                chunk.view_number(),
+                chunk.index
+            ),
             HotShotEvent::DaVoteSend(vote) => {
                 write!(f, "DaVoteSend(view_number={:?})", vote.view_number())
             }
@@ -515,6 +728,11 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                 "VidDisperseSend(view_number={:?})",
                 proposal.data.view_number()
             ),
+            HotShotEvent::VidDisperseComputed(proposal) => write!(
+                f,
+                "VidDisperseComputed(view_number={:?})",
+                proposal.data.view_number()
+            ),
             HotShotEvent::VidShareRecv(_, proposal) => write!(
                 f,
                 "VIDShareRecv(view_number={:?})",
@@ -593,12 +811,130 @@ impl<TYPES: NodeType> Display for HotShotEvent<TYPES> {
                     proposal.data.view_number
                 )
             }
-            HotShotEvent::HighQcRecv(qc, _) => {
+            HotShotEvent::HighQcRecv(qc, ..) => {
                 write!(f, "HighQcRecv(view_number={:?}", qc.view_number())
             }
             HotShotEvent::HighQcSend(qc, ..) => {
                 write!(f, "HighQcSend(view_number={:?}", qc.view_number())
             }
+            HotShotEvent::MempoolGossipSend(transactions, _) => {
+                write!(f, "MempoolGossipSend(count={})", transactions.len())
+            }
+            HotShotEvent::MempoolDigestSend(digest, _) => {
+                write!(f, "MempoolDigestSend(count={})", digest.len())
+            }
+            HotShotEvent::MempoolDigestRecv(digest, _) => {
+                write!(f, "MempoolDigestRecv(count={})", digest.len())
+            }
+            HotShotEvent::MempoolRequestSend(request, ..) => {
+                write!(f, "MempoolRequestSend(count={})", request.len())
+            }
+            HotShotEvent::MempoolRequestRecv(request, _) => {
+                write!(f, "MempoolRequestRecv(count={})", request.len())
+            }
+            HotShotEvent::MempoolResponseSend(transactions, ..) => {
+                write!(f, "MempoolResponseSend(count={})", transactions.len())
+            }
+            HotShotEvent::AntiEntropyTick => write!(f, "AntiEntropyTick"),
+            HotShotEvent::AntiEntropyDigestSend(digest, ..) => {
+                write!(f, "AntiEntropyDigestSend(count={})", digest.len())
+            }
+            HotShotEvent::AntiEntropyDigestRecv(digest, _) => {
+                write!(f, "AntiEntropyDigestRecv(count={})", digest.len())
+            }
+            HotShotEvent::AntiEntropyRequestSend(request, ..) => {
+                write!(f, "AntiEntropyRequestSend(count={})", request.len())
+            }
+            HotShotEvent::AntiEntropyRequestRecv(request, _) => {
+                write!(f, "AntiEntropyRequestRecv(count={})", request.len())
+            }
+            HotShotEvent::AntiEntropyResponseSend(items, ..) => {
+                write!(f, "AntiEntropyResponseSend(count={})", items.len())
+            }
+            HotShotEvent::AntiEntropyResponseRecv(items, _) => {
+                write!(f, "AntiEntropyResponseRecv(count={})", items.len())
+            }
+            HotShotEvent::BatchSend(batch, _) => {
+                write!(f, "BatchSend(view_number={:?}", batch.view)
+            }
+            HotShotEvent::BatchRecv(batch, _) => {
+                write!(f, "BatchRecv(view_number={:?}", batch.view)
+            }
+            HotShotEvent::BatchVoteSend(vote, _) => {
+                write!(f, "BatchVoteSend(view_number={:?}", vote.view_number())
+            }
+            HotShotEvent::BatchVoteRecv(vote) => {
+                write!(f, "BatchVoteRecv(view_number={:?}", vote.view_number())
+            }
+            HotShotEvent::BatchCertificateSend(cert, _) => {
+                write!(
+                    f,
+                    "BatchCertificateSend(view_number={:?}",
+                    cert.view_number()
+                )
+            }
+            HotShotEvent::BatchCertificateRecv(cert) => {
+                write!(
+                    f,
+                    "BatchCertificateRecv(view_number={:?}",
+                    cert.view_number()
+                )
+            }
+            HotShotEvent::BatchProposalSend(proposal, _) => {
+                write!(
+                    f,
+                    "BatchProposalSend(view_number={:?}, count={})",
+                    proposal.view_number,
+                    proposal.certificates.len()
+                )
+            }
+            HotShotEvent::BatchProposalRecv(proposal, _) => {
+                write!(
+                    f,
+                    "BatchProposalRecv(view_number={:?}, count={})",
+                    proposal.view_number,
+                    proposal.certificates.len()
+                )
+            }
+            HotShotEvent::FallbackVoteSend(vote) => {
+                write!(f, "FallbackVoteSend(view_number={:?}", vote.view_number())
+            }
+            HotShotEvent::FallbackVoteRecv(vote) => {
+                write!(f, "FallbackVoteRecv(view_number={:?}", vote.view_number())
+            }
+            HotShotEvent::FallbackCertificateSend(cert, _) => {
+                write!(
+                    f,
+                    "FallbackCertificateSend(view_number={:?}",
+                    cert.view_number()
+                )
+            }
+            HotShotEvent::FallbackCertificateRecv(cert) => {
+                write!(
+                    f,
+                    "FallbackCertificateRecv(view_number={:?}",
+                    cert.view_number()
+                )
+            }
+            HotShotEvent::PartialCertificateSend(partial, ..) => {
+                write!(
+                    f,
+                    "PartialCertificateSend(view_number={:?}, count={})",
+                    partial.view_number(),
+                    partial.signatures.len()
+                )
+            }
+            HotShotEvent::PartialCertificateRecv(partial) => {
+                write!(
+                    f,
+                    "PartialCertificateRecv(view_number={:?}, count={})",
+                    partial.view_number(),
+                    partial.signatures.len()
+                )
+            }
+            HotShotEvent::AggregationTimeout(view) => {
+                write!(f, "AggregationTimeout(view_number={view:?})")
+            }
         }
     }
 }
@@ -0,0 +1,251 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use either::Either;
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    fallback::{elect_leader, FallbackConfig},
+    message::UpgradeLock,
+    simple_certificate::FallbackCertificate,
+    simple_vote::{FallbackData, FallbackVote},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signer::Signer,
+    },
+    vote::{Certificate, HasViewNumber, VoteAccumulator},
+};
+use utils::anytrace::*;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Accumulator for the votes of a fallback round
+type FallbackAccumulator<TYPES, V> =
+    VoteAccumulator<TYPES, FallbackVote<TYPES>, FallbackCertificate<TYPES>, V>;
+
+/// Counts the views which fail in a row, and once there are too many, runs a fallback round in
+/// every view to elect the leader of the next one by coin.
+pub struct FallbackTaskState<TYPES: NodeType, V: Versions> {
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Signs our votes
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
+
+    /// Membership for the quorum, which votes in the fallback and whose leaders it elects
+    pub membership: Arc<TYPES::Membership>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// When the fallback takes over
+    pub config: FallbackConfig,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// The latest view whose proposal we validated
+    pub last_progress: TYPES::View,
+
+    /// Votes of each recent round which has not elected a leader yet
+    pub accumulators: BTreeMap<TYPES::View, FallbackAccumulator<TYPES, V>>,
+
+    /// The leader elected by each recent round
+    pub elected: BTreeMap<TYPES::View, TYPES::SignatureKey>,
+
+    /// This node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> FallbackTaskState<TYPES, V> {
+    /// Number of views in a row which failed before `view`.
+    fn failed_views(&self, view: TYPES::View) -> u64 {
+        view.u64()
+            .saturating_sub(self.last_progress.u64().saturating_add(1))
+    }
+
+    /// Whether `round` is recent enough for us to take part in.
+    ///
+    /// Nodes change views at slightly different times, so the rounds next to ours are accepted.
+    fn is_current(&self, round: TYPES::View) -> bool {
+        round.u64().saturating_add(1) >= self.cur_view.u64()
+            && round.u64() <= self.cur_view.u64().saturating_add(1)
+    }
+
+    /// Count `vote` towards the certificate of its round.
+    async fn accumulate(
+        &mut self,
+        vote: &FallbackVote<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let round = vote.view_number();
+        if self.elected.contains_key(&round) {
+            return Ok(());
+        }
+
+        let upgrade_lock = self.upgrade_lock.clone();
+        let accumulator = self
+            .accumulators
+            .entry(round)
+            .or_insert_with(|| VoteAccumulator::new(upgrade_lock));
+        if let Either::Right(cert) = accumulator
+            .accumulate(vote, &self.membership, vote.data.epoch)
+            .await
+        {
+            self.elect(cert, event_stream).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Adopt the leader `cert` elects for the view after its round, unless the round already
+    /// elected one, and pass the certificate on so that the other nodes adopt the same leader.
+    async fn elect(
+        &mut self,
+        cert: FallbackCertificate<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let round = cert.view_number();
+        if self.elected.contains_key(&round) {
+            return Ok(());
+        }
+        self.accumulators.remove(&round);
+
+        let leader = elect_leader(&cert, &self.membership).context(warn!(
+            "The fallback certificate of round {round:?} elects no leader"
+        ))?;
+        tracing::info!("Fallback round {round:?} elected {leader:?} to lead the next view");
+        self.membership
+            .set_fallback_leader(round + 1, leader.clone());
+        self.elected.insert(round, leader);
+
+        broadcast_event(
+            Arc::new(HotShotEvent::FallbackCertificateSend(
+                cert,
+                self.public_key.clone(),
+            )),
+            event_stream,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    /// Handles an event.
+    ///
+    /// # Errors
+    /// If the event is invalid or we fail to act on it.
+    pub async fn handle(
+        &mut self,
+        event: Arc<HotShotEvent<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::QuorumProposalValidated(proposal, _) => {
+                self.last_progress = self.last_progress.max(proposal.data.view_number());
+            }
+            HotShotEvent::ViewChange(view, epoch) => {
+                ensure!(
+                    *view > self.cur_view,
+                    debug!("View change to an older view")
+                );
+                self.cur_view = *view;
+                self.cur_epoch = *epoch;
+
+                let oldest = TYPES::View::new(view.u64().saturating_sub(1));
+                self.accumulators = self.accumulators.split_off(&oldest);
+                self.elected = self.elected.split_off(&oldest);
+
+                let failed_views = self.failed_views(*view);
+                if !self.config.triggered(failed_views)
+                    || !self.membership.has_stake(&self.public_key, *epoch)
+                {
+                    return Ok(());
+                }
+
+                tracing::warn!(
+                    "{failed_views} views failed in a row, electing the leader of the next view \
+                     by coin"
+                );
+                let vote = FallbackVote::create_signed_vote_with_signer(
+                    FallbackData {
+                        round: *view,
+                        epoch: *epoch,
+                    },
+                    *view,
+                    self.signer.as_ref(),
+                    &self.upgrade_lock,
+                )
+                .await?;
+                broadcast_event(
+                    Arc::new(HotShotEvent::FallbackVoteSend(vote.clone())),
+                    event_stream,
+                )
+                .await;
+                self.accumulate(&vote, event_stream).await?;
+            }
+            HotShotEvent::FallbackVoteRecv(vote) => {
+                ensure!(
+                    vote.data.round == vote.view_number(),
+                    warn!("Received a fallback vote for another round than its view")
+                );
+                ensure!(
+                    self.is_current(vote.view_number()),
+                    debug!("Received a fallback vote for a stale or future round")
+                );
+                self.accumulate(vote, event_stream).await?;
+            }
+            HotShotEvent::FallbackCertificateRecv(cert) => {
+                let round = cert.view_number();
+                ensure!(
+                    !self.elected.contains_key(&round),
+                    debug!("Fallback round {round:?} already elected a leader")
+                );
+                ensure!(
+                    cert.data.round == round && self.is_current(round),
+                    debug!("Received a fallback certificate for a stale or future round")
+                );
+                let epoch = cert.data.epoch;
+                ensure!(
+                    cert.is_valid_cert(
+                        &self.membership.stake_table(epoch),
+                        self.membership.success_threshold(epoch),
+                        &self.upgrade_lock
+                    )
+                    .await,
+                    warn!("Received an invalid fallback certificate")
+                );
+                self.elect(cert.clone(), event_stream).await?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for FallbackTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender).await
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -14,8 +14,10 @@ use async_lock::RwLock;
 use committable::{Commitment, Committable};
 use hotshot_task::dependency::{Dependency, EventDependency};
 use hotshot_types::{
+    checkpoint::EpochCheckpoint,
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal2, ViewChangeEvidence},
+    error::{ConsensusError, HotShotError, Severity},
     event::{Event, EventType, LeafInfo},
     message::{Proposal, UpgradeLock},
     request_response::ProposalRequestPayload,
@@ -25,7 +27,7 @@ use hotshot_types::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
         BlockPayload, ValidatedState,
     },
     utils::{epoch_from_block_number, Terminator, View, ViewInner},
@@ -34,6 +36,7 @@ use hotshot_types::{
 use tokio::time::timeout;
 use tracing::instrument;
 use utils::anytrace::*;
+use vbs::version::StaticVersionType;
 
 use crate::{events::HotShotEvent, quorum_proposal_recv::ValidationInfo, request::REQUEST_TIMEOUT};
 
@@ -46,25 +49,23 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
     consensus: OuterConsensus<TYPES>,
-    sender_public_key: TYPES::SignatureKey,
-    sender_private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
     epoch_height: u64,
-) -> Result<(Leaf2<TYPES>, View<TYPES>)> {
+) -> Result<(Arc<Leaf2<TYPES>>, View<TYPES>)> {
     // We need to be able to sign this request before submitting it to the network. Compute the
     // payload first.
     let signed_proposal_request = ProposalRequestPayload {
         view_number,
-        key: sender_public_key,
+        key: signer.public_key().clone(),
     };
 
     // Finally, compute the signature for the payload.
-    let signature = TYPES::SignatureKey::sign(
-        &sender_private_key,
-        signed_proposal_request.commit().as_ref(),
-    )
-    .wrap()
-    .context(error!("Failed to sign proposal. This should never happen."))?;
+    let signature = signer
+        .sign(signed_proposal_request.commit().as_ref())
+        .await
+        .wrap()
+        .context(warn!("Failed to sign proposal request"))?;
 
     // First, broadcast that we need a proposal to the current leader
     broadcast_event(
@@ -128,7 +129,7 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
     let justify_qc_epoch = justify_qc.data.epoch();
     if !justify_qc
         .is_valid_cert(
-            quorum_membership.stake_table(justify_qc_epoch),
+            &quorum_membership.stake_table(justify_qc_epoch),
             quorum_membership.success_threshold(justify_qc_epoch),
             upgrade_lock,
         )
@@ -137,12 +138,12 @@ pub(crate) async fn fetch_proposal<TYPES: NodeType, V: Versions>(
         bail!("Invalid justify_qc in proposal for view {}", *view_number);
     }
     let mut consensus_writer = consensus.write().await;
-    let leaf = Leaf2::from_quorum_proposal(&proposal.data);
+    let leaf = Arc::new(Leaf2::from_quorum_proposal(&proposal.data));
     let state = Arc::new(
         <TYPES::ValidatedState as ValidatedState<TYPES>>::from_header(&proposal.data.block_header),
     );
 
-    if let Err(e) = consensus_writer.update_leaf(leaf.clone(), Arc::clone(&state), None) {
+    if let Err(e) = consensus_writer.update_leaf(Arc::clone(&leaf), Arc::clone(&state), None) {
         tracing::trace!("{e:?}");
     }
     let view = View {
@@ -257,7 +258,7 @@ pub async fn decide_from_proposal_2<TYPES: NodeType>(
             .get(&info.leaf.view_number())
         {
             let payload =
-                BlockPayload::from_bytes(encoded_txns, info.leaf.block_header().metadata());
+                BlockPayload::from_bytes(&encoded_txns, info.leaf.block_header().metadata());
 
             info.leaf.fill_block_payload_unchecked(payload);
         }
@@ -384,7 +385,7 @@ pub async fn decide_from_proposal<TYPES: NodeType>(
                     consensus_reader.saved_payloads().get(&leaf.view_number())
                 {
                     let payload =
-                        BlockPayload::from_bytes(encoded_txns, leaf.block_header().metadata());
+                        BlockPayload::from_bytes(&encoded_txns, leaf.block_header().metadata());
 
                     leaf.fill_block_payload_unchecked(payload);
                 }
@@ -431,13 +432,12 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
     event_sender: &Sender<Arc<HotShotEvent<TYPES>>>,
     event_receiver: &Receiver<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
-    public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     consensus: OuterConsensus<TYPES>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
     parent_view_number: TYPES::View,
     epoch_height: u64,
-) -> Result<(Leaf2<TYPES>, Arc<<TYPES as NodeType>::ValidatedState>)> {
+) -> Result<(Arc<Leaf2<TYPES>>, Arc<<TYPES as NodeType>::ValidatedState>)> {
     let consensus_reader = consensus.read().await;
     let vsm_contains_parent_view = consensus_reader
         .validated_state_map()
@@ -451,8 +451,7 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
             event_receiver.clone(),
             quorum_membership,
             consensus.clone(),
-            public_key.clone(),
-            private_key.clone(),
+            signer,
             upgrade_lock,
             epoch_height,
         )
@@ -484,7 +483,7 @@ pub(crate) async fn parent_leaf_and_state<TYPES: NodeType, V: Versions>(
         .get(&leaf_commitment)
         .context(info!("Failed to find high QC of parent"))?;
 
-    Ok((leaf.clone(), Arc::clone(state)))
+    Ok((Arc::clone(leaf), Arc::clone(state)))
 }
 
 /// Validate the state and safety and liveness of a proposal then emit
@@ -501,18 +500,26 @@ pub async fn validate_proposal_safety_and_liveness<
     V: Versions,
 >(
     proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
-    parent_leaf: Leaf2<TYPES>,
+    parent_leaf: Arc<Leaf2<TYPES>>,
     validation_info: &ValidationInfo<TYPES, I, V>,
     event_stream: Sender<Arc<HotShotEvent<TYPES>>>,
     sender: TYPES::SignatureKey,
 ) -> Result<()> {
     let view_number = proposal.data.view_number();
 
-    let proposed_leaf = Leaf2::from_quorum_proposal(&proposal.data);
+    let proposed_leaf = Arc::new(Leaf2::from_quorum_proposal(&proposal.data));
     ensure!(
         proposed_leaf.parent_commitment() == parent_leaf.commit(),
         "Proposed leaf does not extend the parent leaf."
     );
+    validation_info
+        .timestamp_rules
+        .validate(parent_leaf.timestamp(), proposed_leaf.timestamp())
+        .wrap()
+        .context(warn!(
+            "Proposal for view {} has an invalid timestamp",
+            *view_number
+        ))?;
     let proposal_epoch =
         epoch_from_block_number(proposed_leaf.height(), validation_info.epoch_height);
 
@@ -522,7 +529,7 @@ pub async fn validate_proposal_safety_and_liveness<
 
     {
         let mut consensus_writer = validation_info.consensus.write().await;
-        if let Err(e) = consensus_writer.update_leaf(proposed_leaf.clone(), state, None) {
+        if let Err(e) = consensus_writer.update_leaf(Arc::clone(&proposed_leaf), state, None) {
             tracing::trace!("{e:?}");
         }
 
@@ -549,6 +556,59 @@ pub async fn validate_proposal_safety_and_liveness<
         )
         .await?;
 
+    // The last block of an epoch must commit to the committees of the next one, so that the
+    // outgoing committee votes for the committees it hands over to. Proposals from before the
+    // epochs upgrade are sent in a format without the checkpoint, so they must not carry one.
+    let expected_checkpoint = if validation_info
+        .upgrade_lock
+        .version_infallible(view_number)
+        .await
+        >= V::Epochs::VERSION
+    {
+        EpochCheckpoint::for_block(
+            proposed_leaf.height(),
+            validation_info.epoch_height,
+            &validation_info.quorum_membership,
+        )
+    } else {
+        None
+    };
+    ensure!(
+        proposal.data.epoch_checkpoint == expected_checkpoint,
+        warn!(
+            "Proposal for view {} does not carry the expected checkpoint of the next epoch",
+            *view_number
+        )
+    );
+
+    // The first block of an epoch is only accepted if its committee is the one the outgoing
+    // committee handed over to
+    let parent_epoch = epoch_from_block_number(parent_leaf.height(), validation_info.epoch_height);
+    if let Some(checkpoint) = parent_leaf
+        .epoch_checkpoint()
+        .filter(|_| proposal_epoch > parent_epoch)
+    {
+        let epoch = TYPES::Epoch::new(proposal_epoch);
+        ensure!(
+            checkpoint.epoch == epoch,
+            warn!(
+                "The last block of epoch {parent_epoch} hands over to another epoch than {epoch}"
+            )
+        );
+        checkpoint
+            .verify_quorum(
+                validation_info
+                    .quorum_membership
+                    .stake_table(epoch)
+                    .into_owned(),
+                validation_info.quorum_membership.success_threshold(epoch),
+            )
+            .wrap()
+            .context(error!(
+                "Our committee of epoch {epoch} is not the one the previous epoch handed over to"
+            ))?;
+    }
+
     let justify_qc = proposal.data.justify_qc.clone();
     // Create a positive vote if either liveness or safety check
     // passes.
@@ -612,7 +672,7 @@ pub async fn validate_proposal_safety_and_liveness<
             view_number,
             event: EventType::QuorumProposal {
                 proposal: proposal.clone(),
-                sender,
+                sender: sender.into(),
             },
         },
         &validation_info.output_event_stream,
@@ -647,17 +707,48 @@ pub(crate) async fn validate_proposal_view_and_certs<
     validation_info: &ValidationInfo<TYPES, I, V>,
 ) -> Result<()> {
     let view_number = proposal.data.view_number();
-    ensure!(
-        view_number >= validation_info.consensus.read().await.cur_view(),
-        "Proposal is from an older view {:?}",
-        proposal.data.clone()
-    );
+    if view_number < validation_info.consensus.read().await.cur_view() {
+        return report_consensus_error(
+            Err(ConsensusError::StaleMessage(view_number)),
+            view_number,
+            &validation_info.consensus,
+            &validation_info.output_event_stream,
+        )
+        .await;
+    }
 
     // Validate the proposal's signature. This should also catch if the leaf_commitment does not equal our calculated parent commitment
-    proposal.validate_signature(
-        &validation_info.quorum_membership,
+    let proposal_epoch = TYPES::Epoch::new(epoch_from_block_number(
+        proposal.data.block_header.block_number(),
         validation_info.epoch_height,
-    )?;
+    ));
+    let view_leader_key = report_consensus_error(
+        validation_info
+            .quorum_membership
+            .try_leader(view_number, proposal_epoch),
+        view_number,
+        &validation_info.consensus,
+        &validation_info.output_event_stream,
+    )
+    .await?;
+    let proposed_leaf_commit = Leaf2::from_quorum_proposal(&proposal.data).commit();
+    if !validation_info
+        .verification_workers
+        .verify(
+            view_leader_key,
+            proposal.signature.clone(),
+            proposed_leaf_commit,
+        )
+        .await
+    {
+        return report_consensus_error(
+            Err(ConsensusError::InvalidSignature(view_number)),
+            view_number,
+            &validation_info.consensus,
+            &validation_info.output_event_stream,
+        )
+        .await;
+    }
 
     // Verify a timeout certificate OR a view sync certificate exists and is valid.
     if proposal.data.justify_qc.view_number() != view_number - 1 {
@@ -666,6 +757,7 @@ pub(crate) async fn validate_proposal_view_and_certs<
                 "Quorum proposal for view {} needed a timeout or view sync certificate, but did not have one",
                 *view_number
         ))?;
+        let evidence_epoch = received_proposal_cert.epoch();
 
         match received_proposal_cert {
             ViewChangeEvidence::Timeout(timeout_cert) => {
@@ -678,7 +770,7 @@ pub(crate) async fn validate_proposal_view_and_certs<
                 ensure!(
                     timeout_cert
                         .is_valid_cert(
-                            validation_info
+                            &validation_info
                                 .quorum_membership
                                 .stake_table(timeout_cert_epoch),
                             validation_info
@@ -704,7 +796,7 @@ pub(crate) async fn validate_proposal_view_and_certs<
                 ensure!(
                     view_sync_cert
                         .is_valid_cert(
-                            validation_info
+                            &validation_info
                                 .quorum_membership
                                 .stake_table(view_sync_cert_epoch),
                             validation_info
@@ -717,6 +809,30 @@ pub(crate) async fn validate_proposal_view_and_certs<
                 );
             }
         }
+
+        // Under the two-chain rule, the leader must also prove that no quorum knows of a QC newer
+        // than the one it extends, or it could build on a stale branch.
+        let epochs_active = validation_info
+            .upgrade_lock
+            .version_infallible(view_number)
+            .await
+            >= V::Epochs::VERSION;
+        if validation_info.commit_rule.two_chain(epochs_active) {
+            let high_qc_proof = proposal.data.high_qc_proof.as_ref().context(warn!(
+                "Quorum proposal for view {} after a view change did not prove it extends the \
+                 highest QC",
+                *view_number
+            ))?;
+            high_qc_proof
+                .validate(
+                    view_number,
+                    proposal.data.justify_qc.view_number(),
+                    evidence_epoch,
+                    &validation_info.quorum_membership,
+                    &validation_info.upgrade_lock,
+                )
+                .await?;
+        }
     }
 
     // Validate the upgrade certificate -- this is just a signature validation.
@@ -738,6 +854,40 @@ pub(crate) async fn validate_proposal_view_and_certs<
     Ok(())
 }
 
+/// Count the error in `result`, if there is one, and report it to the application unless it only
+/// cost us a single message.
+///
+/// # Errors
+/// Propagates the error in `result`, logged at a level matching its severity.
+pub async fn report_consensus_error<TYPES: NodeType, T>(
+    result: std::result::Result<T, ConsensusError<TYPES>>,
+    view_number: TYPES::View,
+    consensus: &OuterConsensus<TYPES>,
+    output_event_stream: &Sender<Event<TYPES>>,
+) -> Result<T> {
+    let error = match result {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+
+    let severity = error.severity();
+    consensus.read().await.metrics.record_error(severity);
+    if severity != Severity::Recoverable {
+        broadcast_event(
+            Event {
+                view_number,
+                event: EventType::Error {
+                    error: Arc::new(HotShotError::Consensus(error.clone())),
+                },
+            },
+            output_event_stream,
+        )
+        .await;
+    }
+
+    Err(error.into())
+}
+
 /// Helper function to send events and log errors
 pub async fn broadcast_event<E: Clone + std::fmt::Debug>(event: E, sender: &Sender<E>) {
     match sender.broadcast_direct(event).await {
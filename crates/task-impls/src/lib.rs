@@ -19,6 +19,15 @@ pub mod da;
 /// The task which implements all transaction handling
 pub mod transactions;
 
+/// The task which gossips transactions between nodes
+pub mod mempool;
+
+/// The task which disseminates and certifies batches of the DAG mempool
+pub mod dag_mempool;
+
+/// The task which reconciles recent proposals and votes with peers
+pub mod anti_entropy;
+
 /// Defines the events passed between tasks
 pub mod events;
 
@@ -31,12 +40,18 @@ pub mod harness;
 /// The task which implements view synchronization
 pub mod view_sync;
 
+/// The task which elects leaders by coin after a run of failed views
+pub mod fallback;
+
 /// The task which implements verifiable information dispersal
 pub mod vid;
 
 /// Generic task for collecting votes
 pub mod vote_collection;
 
+/// The task which aggregates quorum votes on behalf of the leader
+pub mod vote_aggregator;
+
 /// Task for handling upgrades
 pub mod upgrade;
 
@@ -61,3 +76,6 @@ pub mod quorum_proposal_recv;
 
 /// Task for storing and replaying all received tasks by a node
 pub mod rewind;
+
+/// Dedicated threads for heavyweight work, such as applying blocks to the state
+pub mod workers;
@@ -0,0 +1,244 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, hash::Hash, num::NonZeroUsize, sync::Arc, time::Instant};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use hotshot_task::task::TaskState;
+use hotshot_types::{
+    mempool::MempoolGossipConfig,
+    traits::node_implementation::{ConsensusTime, NodeType},
+};
+use lru::LruCache;
+use utils::anytrace::Result;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event};
+
+/// Number of peers whose rate limits are tracked at once
+const TRACKED_PEERS: usize = 10_000;
+
+/// Token bucket for a single peer
+#[derive(Clone, Debug)]
+struct TokenBucket {
+    /// Transactions the peer may still send
+    tokens: f64,
+    /// When `tokens` was last refilled
+    refilled: Instant,
+}
+
+/// Limits the number of transactions each peer may relay to us.
+#[derive(Clone, Debug)]
+pub struct PeerRateLimiter<K: Hash + Eq> {
+    /// Transactions added to each bucket per second
+    rate: f64,
+    /// Size of each bucket
+    burst: f64,
+    /// Buckets of the most recently seen peers
+    buckets: LruCache<K, TokenBucket>,
+}
+
+impl<K: Hash + Eq> PeerRateLimiter<K> {
+    /// Create a rate limiter from the gossip config.
+    #[must_use]
+    pub fn new(config: &MempoolGossipConfig) -> Self {
+        Self {
+            rate: f64::from(config.peer_rate_limit),
+            burst: f64::from(config.peer_burst),
+            buckets: LruCache::new(NonZeroUsize::new(TRACKED_PEERS).unwrap()),
+        }
+    }
+
+    /// Take up to `count` tokens from `peer`'s bucket, returning the number of transactions which
+    /// may be accepted.
+    pub fn admit(&mut self, peer: K, count: usize) -> usize {
+        let now = Instant::now();
+        let burst = self.burst;
+        let bucket = self.buckets.get_or_insert_mut(peer, || TokenBucket {
+            tokens: burst,
+            refilled: now,
+        });
+
+        let elapsed = now.duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.refilled = now;
+
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let admitted = count.min(bucket.tokens as usize);
+        #[allow(clippy::cast_precision_loss)]
+        let taken = admitted as f64;
+        bucket.tokens -= taken;
+
+        admitted
+    }
+}
+
+/// Tracks the transactions this node has seen, relays new ones to all nodes and reconciles with
+/// peers so that every node ends up with the union of all pending transactions.
+pub struct MempoolTaskState<TYPES: NodeType> {
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Gossip settings
+    pub config: MempoolGossipConfig,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// View of our last digest broadcast
+    pub last_digest_view: TYPES::View,
+
+    /// Pending transactions, with the view we first saw them in
+    pub pending: HashMap<Commitment<TYPES::Transaction>, (TYPES::Transaction, TYPES::View)>,
+}
+
+impl<TYPES: NodeType> MempoolTaskState<TYPES> {
+    /// Create the task state for a node which has seen no transactions yet.
+    #[must_use]
+    pub fn new(public_key: TYPES::SignatureKey, config: MempoolGossipConfig) -> Self {
+        Self {
+            public_key,
+            config,
+            cur_view: TYPES::View::genesis(),
+            last_digest_view: TYPES::View::genesis(),
+            pending: HashMap::new(),
+        }
+    }
+
+    /// Record `transactions`, returning the ones we had not seen before.
+    fn record(&mut self, transactions: &[TYPES::Transaction]) -> Vec<TYPES::Transaction> {
+        let mut new = Vec::new();
+        for transaction in transactions {
+            if self.pending.len() >= self.config.max_pending {
+                tracing::debug!("Mempool is full, not keeping transaction for reconciliation");
+                break;
+            }
+            let commitment = transaction.commit();
+            if self.pending.contains_key(&commitment) {
+                continue;
+            }
+            self.pending
+                .insert(commitment, (transaction.clone(), self.cur_view));
+            new.push(transaction.clone());
+        }
+
+        new
+    }
+
+    /// Forget transactions which have been pending for longer than the TTL.
+    fn prune(&mut self) {
+        let ttl = self.config.pending_ttl;
+        let cur_view = *self.cur_view;
+        self.pending
+            .retain(|_, (_, seen)| seen.saturating_add(ttl) >= cur_view);
+    }
+
+    /// Handles an event.
+    pub async fn handle(
+        &mut self,
+        event: Arc<HotShotEvent<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) {
+        match event.as_ref() {
+            HotShotEvent::TransactionsRecv(transactions) => {
+                let new = self.record(transactions);
+                if !new.is_empty() {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::MempoolGossipSend(
+                            new,
+                            self.public_key.clone(),
+                        )),
+                        event_stream,
+                    )
+                    .await;
+                }
+            }
+            HotShotEvent::ViewChange(view, _) => {
+                if *view <= self.cur_view {
+                    return;
+                }
+                self.cur_view = *view;
+                self.prune();
+
+                if self.pending.is_empty()
+                    || *self.cur_view
+                        < self
+                            .last_digest_view
+                            .saturating_add(self.config.reconcile_interval)
+                {
+                    return;
+                }
+                self.last_digest_view = self.cur_view;
+                broadcast_event(
+                    Arc::new(HotShotEvent::MempoolDigestSend(
+                        self.pending.keys().copied().collect(),
+                        self.public_key.clone(),
+                    )),
+                    event_stream,
+                )
+                .await;
+            }
+            HotShotEvent::MempoolDigestRecv(digest, peer) => {
+                let missing: Vec<_> = digest
+                    .iter()
+                    .filter(|commitment| !self.pending.contains_key(commitment))
+                    .copied()
+                    .collect();
+                if missing.is_empty() {
+                    return;
+                }
+                tracing::debug!("Requesting {} missing transactions", missing.len());
+                broadcast_event(
+                    Arc::new(HotShotEvent::MempoolRequestSend(
+                        missing,
+                        self.public_key.clone(),
+                        peer.clone(),
+                    )),
+                    event_stream,
+                )
+                .await;
+            }
+            HotShotEvent::MempoolRequestRecv(request, peer) => {
+                let transactions: Vec<_> = request
+                    .iter()
+                    .filter_map(|commitment| self.pending.get(commitment))
+                    .map(|(transaction, _)| transaction.clone())
+                    .collect();
+                if transactions.is_empty() {
+                    return;
+                }
+                broadcast_event(
+                    Arc::new(HotShotEvent::MempoolResponseSend(
+                        transactions,
+                        self.public_key.clone(),
+                        peer.clone(),
+                    )),
+                    event_stream,
+                )
+                .await;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> TaskState for MempoolTaskState<TYPES> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender).await;
+        Ok(())
+    }
+
+    fn cancel_subtasks(&mut self) {}
+}
@@ -8,20 +8,25 @@ use std::{
     collections::{BTreeMap, HashMap},
     hash::{DefaultHasher, Hash, Hasher},
     sync::Arc,
+    time::{Duration, Instant},
 };
 
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use bytes::Bytes;
+use hotshot_task::{spawn::spawn_named, task::TaskState};
 use hotshot_types::{
+    channels::{ChannelConfig, ViewChannels},
     consensus::OuterConsensus,
     data::{VidDisperse, VidDisperseShare},
-    event::{Event, EventType, HotShotAction},
+    event::{Event, EventType, HotShotAction, SendFailure},
+    gossip_fanout::MessageClass,
     message::{
         convert_proposal, DaConsensusMessage, DataMessage, GeneralConsensusMessage, Message,
         MessageKind, Proposal, SequencingMessage, UpgradeLock,
     },
+    simple_vote::{CompactQuorumVote, QuorumVote2},
     traits::{
         election::Membership,
         network::{
@@ -29,11 +34,13 @@ use hotshot_types::{
             ViewMessage,
         },
         node_implementation::{ConsensusTime, NodeType, Versions},
+        signer::Signer,
         storage::Storage,
     },
     vote::{HasViewNumber, Vote},
+    vote_aggregation::AggregationConfig,
 };
-use tokio::{spawn, task::JoinHandle};
+use tokio::{spawn, task::JoinHandle, time::sleep};
 use tracing::instrument;
 use utils::anytrace::*;
 use vbs::version::StaticVersionType;
@@ -41,8 +48,12 @@ use vbs::version::StaticVersionType;
 use crate::{
     events::{HotShotEvent, HotShotTaskCompleted},
     helpers::broadcast_event,
+    mempool::PeerRateLimiter,
 };
 
+/// How often a message waiting for room in the internal event channel checks again
+const BACKPRESSURE_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
 /// the network message task state
 #[derive(Clone)]
 pub struct NetworkMessageTaskState<TYPES: NodeType> {
@@ -57,15 +68,123 @@ pub struct NetworkMessageTaskState<TYPES: NodeType> {
 
     /// Transaction Cache to ignore previously seen transactions
     pub transactions_cache: lru::LruCache<u64, ()>,
+
+    /// Per-peer limit on relayed transactions, if mempool gossip is enabled
+    pub transaction_limiter: Option<PeerRateLimiter<TYPES::SignatureKey>>,
+
+    /// Overflow policy of the internal event channel
+    pub channels: ChannelConfig,
+
+    /// Reference to consensus, for the current view
+    pub consensus: OuterConsensus<TYPES>,
+
+    /// Membership for the quorum, whose stake table compact votes refer to their signers in
+    pub membership: Arc<TYPES::Membership>,
 }
 
 impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
+    /// Decide whether to forward a consensus message for `view`, given how full the internal event
+    /// channel is.
+    ///
+    /// Once the channel holds `stale_drop_depth` events, messages for past views are dropped, and
+    /// messages for the current or later views wait up to `backpressure_timeout` for room. If there
+    /// is still no room by then the message is sent anyway, evicting the oldest event.
+    async fn admit(&self, view: TYPES::View) -> bool {
+        if self.internal_event_stream.len() < self.channels.stale_drop_depth {
+            return true;
+        }
+
+        let consensus = self.consensus.read().await;
+        if view < consensus.cur_view() {
+            consensus.metrics.dropped_stale_messages.add(1);
+            return false;
+        }
+        drop(consensus);
+
+        let deadline = Instant::now() + self.channels.backpressure_timeout;
+        while self.internal_event_stream.is_full() && Instant::now() < deadline {
+            sleep(BACKPRESSURE_POLL_INTERVAL).await;
+        }
+        true
+    }
+
+    /// Queue a received message in the channel of its view, or with the messages not tied to one.
+    pub fn queue_message(
+        channels: &mut ViewChannels<TYPES::View, Message<TYPES>>,
+        message: Message<TYPES>,
+    ) {
+        if matches!(message.kind, MessageKind::Consensus(_)) {
+            channels.push_view(message.kind.view_number(), message);
+        } else {
+            channels.push_other(message);
+        }
+    }
+
+    /// The first view whose messages are still worth handling.
+    ///
+    /// This is the view before the current one, since its leader keeps collecting votes and timeout
+    /// votes for it after entering the current view.
+    pub async fn channel_floor(&self) -> TYPES::View {
+        let cur_view = self.consensus.read().await.cur_view();
+        TYPES::View::new(cur_view.u64().saturating_sub(1))
+    }
+
+    /// Whether the sender of `message` may send it: a member of the quorum or the DA committee of
+    /// the message's epoch, for the kinds of messages only members send. A valid signature only
+    /// shows the message is the sender's, not that they may send it.
+    #[must_use]
+    pub fn may_send(&self, message: &Message<TYPES>) -> bool {
+        let Some(epoch) = message.kind.sender_epoch() else {
+            return true;
+        };
+
+        self.membership.has_stake(&message.sender, epoch)
+            || self.membership.has_da_stake(&message.sender, epoch)
+    }
+
+    /// Count messages dropped with the channels of views consensus left.
+    pub async fn record_stale_messages(&self, dropped: usize) {
+        tracing::debug!("Dropped {dropped} messages for views consensus left");
+        self.consensus
+            .read()
+            .await
+            .metrics
+            .dropped_stale_messages
+            .add(dropped);
+    }
+
+    /// Record how long after we entered its view a consensus message reached us.
+    ///
+    /// Returns the message's class and latency, or `None` for other messages and for views we
+    /// have not entered or already pruned.
+    pub async fn record_propagation(
+        &self,
+        message: &Message<TYPES>,
+    ) -> Option<(MessageClass, Duration)> {
+        let MessageKind::Consensus(consensus_message) = &message.kind else {
+            return None;
+        };
+        let class = consensus_message.class();
+        let consensus = self.consensus.read().await;
+        let latency = consensus.time_in_view(message.kind.view_number())?;
+        consensus.metrics.record_propagation(class, latency);
+        Some((class, latency))
+    }
+
     #[instrument(skip_all, name = "Network message task", level = "trace")]
     /// Handles a (deserialized) message from the network
     pub async fn handle_message(&mut self, message: Message<TYPES>) {
         tracing::trace!("Received message from network:\n\n{message:?}");
 
         // Match the message kind and send the appropriate event to the internal event stream
+        if matches!(message.kind, MessageKind::Consensus(_)) {
+            let view = message.kind.view_number();
+            if !self.admit(view).await {
+                tracing::debug!("Event channel is full, dropping message for view {view:?}");
+                return;
+            }
+        }
+
         let sender = message.sender;
         match message.kind {
             // Handle consensus messages
@@ -144,7 +263,34 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                             tracing::error!("Received upgrade vote!");
                             HotShotEvent::UpgradeVoteRecv(message)
                         }
-                        GeneralConsensusMessage::HighQc(qc) => HotShotEvent::HighQcRecv(qc, sender),
+                        GeneralConsensusMessage::HighQc(qc, report) => {
+                            HotShotEvent::HighQcRecv(qc, report, sender)
+                        }
+                        GeneralConsensusMessage::FallbackVote(vote) => {
+                            HotShotEvent::FallbackVoteRecv(vote)
+                        }
+                        GeneralConsensusMessage::FallbackCertificate(cert) => {
+                            HotShotEvent::FallbackCertificateRecv(cert)
+                        }
+                        GeneralConsensusMessage::PartialQuorumCertificate(partial) => {
+                            HotShotEvent::PartialCertificateRecv(partial)
+                        }
+                        GeneralConsensusMessage::CompactVote(vote) => {
+                            let stake_table = self.membership.stake_table(vote.epoch);
+                            match vote.expand(&stake_table) {
+                                Ok(vote) if vote.signing_key() == sender => {
+                                    HotShotEvent::QuorumVoteRecv(vote)
+                                }
+                                Ok(_) => {
+                                    tracing::warn!("Received a compact vote of another node");
+                                    return;
+                                }
+                                Err(e) => {
+                                    tracing::warn!("Failed to expand compact vote: {e}");
+                                    return;
+                                }
+                            }
+                        }
                     },
                     SequencingMessage::Da(da_message) => match da_message {
                         DaConsensusMessage::DaProposal(proposal) => {
@@ -169,6 +315,12 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
                         DaConsensusMessage::DaCertificate2(cert) => {
                             HotShotEvent::DaCertificateRecv(cert)
                         }
+                        DaConsensusMessage::DaProposalHeader(header) => {
+                            HotShotEvent::DaProposalHeaderRecv(header, sender)
+                        }
+                        DaConsensusMessage::DaPayloadChunk(chunk) => {
+                            HotShotEvent::DaPayloadChunkRecv(chunk, sender)
+                        }
                     },
                 };
                 broadcast_event(Arc::new(event), &self.internal_event_stream).await;
@@ -177,13 +329,86 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
             // Handle data messages
             MessageKind::Data(message) => match message {
                 DataMessage::SubmitTransaction(transaction, _) => {
-                    let mut hasher = DefaultHasher::new();
-                    transaction.hash(&mut hasher);
-                    if self.transactions_cache.put(hasher.finish(), ()).is_some() {
+                    self.handle_transactions(vec![transaction], sender).await;
+                }
+                DataMessage::GossipTransactions(transactions, _) => {
+                    if sender == self.public_key {
+                        return;
+                    }
+                    self.handle_transactions(transactions, sender).await;
+                }
+                DataMessage::MempoolDigest(digest, _) => {
+                    if sender == self.public_key {
+                        return;
+                    }
+                    broadcast_event(
+                        Arc::new(HotShotEvent::MempoolDigestRecv(digest, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::MempoolRequest(request, _) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::MempoolRequestRecv(request, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::Batch(batch) => {
+                    if sender == self.public_key {
                         return;
                     }
                     broadcast_event(
-                        Arc::new(HotShotEvent::TransactionsRecv(vec![transaction])),
+                        Arc::new(HotShotEvent::BatchRecv(batch, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::BatchVote(vote) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::BatchVoteRecv(vote)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::BatchCertificate(cert) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::BatchCertificateRecv(cert)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::BatchProposal(proposal) => {
+                    // The leader handles its own block as it builds it
+                    if sender == self.public_key {
+                        return;
+                    }
+                    broadcast_event(
+                        Arc::new(HotShotEvent::BatchProposalRecv(proposal, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::AntiEntropyDigest(digest, _) => {
+                    if sender == self.public_key {
+                        return;
+                    }
+                    broadcast_event(
+                        Arc::new(HotShotEvent::AntiEntropyDigestRecv(digest, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::AntiEntropyRequest(request, _) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::AntiEntropyRequestRecv(request, sender)),
+                        &self.internal_event_stream,
+                    )
+                    .await;
+                }
+                DataMessage::AntiEntropyResponse(items, _) => {
+                    broadcast_event(
+                        Arc::new(HotShotEvent::AntiEntropyResponseRecv(items, sender)),
                         &self.internal_event_stream,
                     )
                     .await;
@@ -243,6 +468,40 @@ impl<TYPES: NodeType> NetworkMessageTaskState<TYPES> {
             }
         }
     }
+
+    /// Rate limits and deduplicates transactions received from `sender`, and passes the new ones
+    /// on to the other tasks.
+    async fn handle_transactions(
+        &mut self,
+        mut transactions: Vec<TYPES::Transaction>,
+        sender: TYPES::SignatureKey,
+    ) {
+        if let Some(limiter) = &mut self.transaction_limiter {
+            let admitted = limiter.admit(sender.clone(), transactions.len());
+            if admitted < transactions.len() {
+                tracing::debug!(
+                    "Peer {sender} exceeded its transaction rate limit, dropping {} transactions",
+                    transactions.len() - admitted
+                );
+                transactions.truncate(admitted);
+            }
+        }
+
+        transactions.retain(|transaction| {
+            let mut hasher = DefaultHasher::new();
+            transaction.hash(&mut hasher);
+            self.transactions_cache.put(hasher.finish(), ()).is_none()
+        });
+        if transactions.is_empty() {
+            return;
+        }
+
+        broadcast_event(
+            Arc::new(HotShotEvent::TransactionsRecv(transactions)),
+            &self.internal_event_stream,
+        )
+        .await;
+    }
 }
 
 /// network event task state
@@ -266,8 +525,14 @@ pub struct NetworkEventTaskState<
     pub consensus: OuterConsensus<TYPES>,
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+    /// Signs every message we send with our consensus key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// map view number to transmit tasks
     pub transmit_tasks: BTreeMap<TYPES::View, Vec<JoinHandle<()>>>,
+    /// Archival nodes, which receive DA proposals alongside the DA committee
+    pub archival_nodes: Vec<TYPES::SignatureKey>,
+    /// Aggregation of quorum votes, which decides where we send ours
+    pub aggregation: AggregationConfig,
 }
 
 #[async_trait]
@@ -314,6 +579,53 @@ impl<
         };
     }
 
+    /// The leader of `view_number` in `epoch`. If it cannot be calculated, the error is logged
+    /// and counted instead.
+    async fn leader(
+        &self,
+        view_number: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<TYPES::SignatureKey> {
+        match self.membership.try_leader(view_number, epoch) {
+            Ok(leader) => Some(leader),
+            Err(e) => {
+                self.consensus
+                    .read()
+                    .await
+                    .metrics
+                    .record_error(e.severity());
+                Err::<(), Error>(e.into())
+                    .context(info!("Failed to calculate leader for view {view_number}"))
+                    .log();
+                None
+            }
+        }
+    }
+
+    /// The message carrying our quorum `vote`, which is compactly encoded from the epochs
+    /// version on.
+    async fn quorum_vote_message(&self, vote: &QuorumVote2<TYPES>) -> MessageKind<TYPES> {
+        let message = if self
+            .upgrade_lock
+            .version_infallible(vote.view_number())
+            .await
+            < V::Epochs::VERSION
+        {
+            GeneralConsensusMessage::Vote(vote.clone().to_vote())
+        } else {
+            let stake_table = self.membership.stake_table(vote.data.epoch);
+            match CompactQuorumVote::compact(vote, &stake_table) {
+                Ok(compact) => GeneralConsensusMessage::CompactVote(compact),
+                Err(e) => {
+                    tracing::warn!("Sending our vote in full: {e}");
+                    GeneralConsensusMessage::Vote2(vote.clone())
+                }
+            }
+        };
+
+        MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(message))
+    }
+
     /// handle `VidDisperseSend`
     async fn handle_vid_disperse_proposal(
         &self,
@@ -332,8 +644,12 @@ impl<
                     DaConsensusMessage::VidDisperseMsg(proposal),
                 )),
             };
-            let serialized_message = match self.upgrade_lock.serialize(&message).await {
-                Ok(serialized) => serialized,
+            let serialized_message = match self
+                .upgrade_lock
+                .serialize_signed(&message, self.signer.as_ref())
+                .await
+            {
+                Ok(serialized) => Bytes::from(serialized),
                 Err(e) => {
                     tracing::error!("Failed to serialize message: {}", e);
                     continue;
@@ -346,7 +662,7 @@ impl<
         let net = Arc::clone(&self.network);
         let storage = Arc::clone(&self.storage);
         let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
-        spawn(async move {
+        spawn_named("vid disperse transmit", async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 Some(HotShotAction::VidDisperse),
                 storage,
@@ -384,7 +700,13 @@ impl<
             if matches!(action, HotShotAction::ViewSyncVote) {
                 action = HotShotAction::Vote;
             }
-            match storage.write().await.record_action(view, action).await {
+            let start = Instant::now();
+            let result = storage.write().await.record_action(view, action).await;
+            consensus
+                .write()
+                .await
+                .record_storage_write(start.elapsed());
+            match result {
                 Ok(()) => Ok(()),
                 Err(e) => {
                     tracing::warn!("Not Sending {:?} because of storage error: {:?}", action, e);
@@ -449,51 +771,26 @@ impl<
             HotShotEvent::QuorumVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::Vote);
                 let view_number = vote.view_number() + 1;
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
-
-                let message = if self
-                    .upgrade_lock
-                    .version_infallible(vote.view_number())
-                    .await
-                    >= V::Epochs::VERSION
-                {
-                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
-                        GeneralConsensusMessage::Vote2(vote.clone()),
-                    ))
-                } else {
-                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
-                        GeneralConsensusMessage::Vote(vote.clone().to_vote()),
-                    ))
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
+
+                let message = self.quorum_vote_message(&vote).await;
+
+                // With aggregation, the vote goes to the leader through our aggregator
+                let recipient = self
+                    .aggregation
+                    .aggregator_for::<TYPES>(
+                        &vote.signing_key(),
+                        &self.membership,
+                        vote.view_number(),
+                        self.epoch,
+                    )
+                    .unwrap_or(leader);
 
-                Some((vote.signing_key(), message, TransmitType::Direct(leader)))
+                Some((vote.signing_key(), message, TransmitType::Direct(recipient)))
             }
             HotShotEvent::ExtendedQuorumVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::Vote);
-                let message = if self
-                    .upgrade_lock
-                    .version_infallible(vote.view_number())
-                    .await
-                    >= V::Epochs::VERSION
-                {
-                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
-                        GeneralConsensusMessage::Vote2(vote.clone()),
-                    ))
-                } else {
-                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
-                        GeneralConsensusMessage::Vote(vote.clone().to_vote()),
-                    ))
-                };
+                let message = self.quorum_vote_message(&vote).await;
 
                 Some((vote.signing_key(), message, TransmitType::Broadcast))
             }
@@ -550,21 +847,29 @@ impl<
 
                 Some((sender, message, TransmitType::DaCommitteeBroadcast))
             }
+            HotShotEvent::DaProposalHeaderSend(header, sender) => {
+                *maybe_action = Some(HotShotAction::DaPropose);
+
+                Some((
+                    sender,
+                    MessageKind::<TYPES>::from_consensus_message(SequencingMessage::Da(
+                        DaConsensusMessage::DaProposalHeader(header),
+                    )),
+                    TransmitType::DaCommitteeBroadcast,
+                ))
+            }
+            HotShotEvent::DaPayloadChunkSend(chunk, sender) => Some((
+                sender,
+                MessageKind::<TYPES>::from_consensus_message(SequencingMessage::Da(
+                    DaConsensusMessage::DaPayloadChunk(chunk),
+                )),
+                TransmitType::DaCommitteeBroadcast,
+            )),
             HotShotEvent::DaVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::DaVote);
                 let view_number = vote.view_number();
                 let epoch = vote.data.epoch;
-                let leader = match self.membership.leader(view_number, epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, epoch).await?;
 
                 let message = if self.upgrade_lock.version_infallible(view_number).await
                     >= V::Epochs::VERSION
@@ -601,17 +906,7 @@ impl<
             }
             HotShotEvent::ViewSyncPreCommitVoteSend(vote) => {
                 let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
                 let message = if self
                     .upgrade_lock
                     .version_infallible(vote.view_number())
@@ -632,17 +927,7 @@ impl<
             HotShotEvent::ViewSyncCommitVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::ViewSyncVote);
                 let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
                 let message = if self
                     .upgrade_lock
                     .version_infallible(vote.view_number())
@@ -663,17 +948,7 @@ impl<
             HotShotEvent::ViewSyncFinalizeVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::ViewSyncVote);
                 let view_number = vote.view_number() + vote.date().relay;
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
                 let message = if self
                     .upgrade_lock
                     .version_infallible(vote.view_number())
@@ -742,17 +1017,7 @@ impl<
             HotShotEvent::TimeoutVoteSend(vote) => {
                 *maybe_action = Some(HotShotAction::Vote);
                 let view_number = vote.view_number() + 1;
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
                 let message = if self
                     .upgrade_lock
                     .version_infallible(vote.view_number())
@@ -780,17 +1045,7 @@ impl<
             HotShotEvent::UpgradeVoteSend(vote) => {
                 tracing::error!("Sending upgrade vote!");
                 let view_number = vote.view_number();
-                let leader = match self.membership.leader(view_number, self.epoch) {
-                    Ok(l) => l,
-                    Err(e) => {
-                        tracing::warn!(
-                            "Failed to calculate leader for view number {:?}. Error: {:?}",
-                            view_number,
-                            e
-                        );
-                        return None;
-                    }
-                };
+                let leader = self.leader(view_number, self.epoch).await?;
                 Some((
                     vote.signing_key(),
                     MessageKind::<TYPES>::from_consensus_message(SequencingMessage::General(
@@ -838,13 +1093,89 @@ impl<
                 };
                 Some((sender, message, TransmitType::Direct(to)))
             }
-            HotShotEvent::HighQcSend(quorum_cert, leader, sender) => Some((
+            HotShotEvent::HighQcSend(quorum_cert, report, leader, sender) => Some((
                 sender,
                 MessageKind::Consensus(SequencingMessage::General(
-                    GeneralConsensusMessage::HighQc(quorum_cert),
+                    GeneralConsensusMessage::HighQc(quorum_cert, report),
                 )),
                 TransmitType::Direct(leader),
             )),
+            HotShotEvent::FallbackVoteSend(vote) => Some((
+                vote.signing_key(),
+                MessageKind::Consensus(SequencingMessage::General(
+                    GeneralConsensusMessage::FallbackVote(vote),
+                )),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::FallbackCertificateSend(cert, sender) => Some((
+                sender,
+                MessageKind::Consensus(SequencingMessage::General(
+                    GeneralConsensusMessage::FallbackCertificate(cert),
+                )),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::PartialCertificateSend(partial, leader, sender) => Some((
+                sender,
+                MessageKind::Consensus(SequencingMessage::General(
+                    GeneralConsensusMessage::PartialQuorumCertificate(partial),
+                )),
+                TransmitType::Direct(leader),
+            )),
+            HotShotEvent::MempoolGossipSend(transactions, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::GossipTransactions(transactions, self.view)),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::MempoolDigestSend(digest, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::MempoolDigest(digest, self.view)),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::MempoolRequestSend(request, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::MempoolRequest(request, self.view)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::MempoolResponseSend(transactions, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::GossipTransactions(transactions, self.view)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::AntiEntropyDigestSend(digest, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::AntiEntropyDigest(digest, self.view)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::AntiEntropyRequestSend(request, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::AntiEntropyRequest(request, self.view)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::AntiEntropyResponseSend(items, sender, to) => Some((
+                sender,
+                MessageKind::Data(DataMessage::AntiEntropyResponse(items, self.view)),
+                TransmitType::Direct(to),
+            )),
+            HotShotEvent::BatchSend(batch, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::Batch(batch)),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::BatchVoteSend(vote, author) => Some((
+                vote.signing_key(),
+                MessageKind::Data(DataMessage::BatchVote(vote)),
+                TransmitType::Direct(author),
+            )),
+            HotShotEvent::BatchCertificateSend(cert, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::BatchCertificate(cert)),
+                TransmitType::Broadcast,
+            )),
+            HotShotEvent::BatchProposalSend(proposal, sender) => Some((
+                sender,
+                MessageKind::Data(DataMessage::BatchProposal(proposal)),
+                TransmitType::Broadcast,
+            )),
             _ => None,
         }
     }
@@ -870,18 +1201,20 @@ impl<
         };
         let view_number = message.kind.view_number();
         let committee_topic = Topic::Global;
-        let da_committee = self
+        let mut da_committee = self
             .membership
             .da_committee_members(view_number, self.epoch);
+        da_committee.extend(self.archival_nodes.iter().cloned());
         let network = Arc::clone(&self.network);
         let storage = Arc::clone(&self.storage);
         let consensus = OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus));
         let upgrade_lock = self.upgrade_lock.clone();
-        let handle = spawn(async move {
+        let signer = Arc::clone(&self.signer);
+        let handle = spawn_named("network transmit", async move {
             if NetworkEventTaskState::<TYPES, V, NET, S>::maybe_record_action(
                 maybe_action,
                 Arc::clone(&storage),
-                consensus,
+                consensus.clone(),
                 view_number,
             )
             .await
@@ -904,14 +1237,21 @@ impl<
                 }
             }
 
-            let serialized_message = match upgrade_lock.serialize(&message).await {
-                Ok(serialized) => serialized,
+            let serialized_message = match upgrade_lock
+                .serialize_signed(&message, signer.as_ref())
+                .await
+            {
+                Ok(serialized) => Bytes::from(serialized),
                 Err(e) => {
                     tracing::error!("Failed to serialize message: {}", e);
                     return;
                 }
             };
 
+            let recipient = match &transmit {
+                TransmitType::Direct(recipient) => Some(recipient.clone()),
+                TransmitType::Broadcast | TransmitType::DaCommitteeBroadcast => None,
+            };
             let transmit_result = match transmit {
                 TransmitType::Direct(recipient) => {
                     network.direct_message(serialized_message, recipient).await
@@ -932,9 +1272,16 @@ impl<
                 }
             };
 
-            match transmit_result {
-                Ok(()) => {}
-                Err(e) => tracing::warn!("Failed to send message task: {:?}", e),
+            if let Err(e) = transmit_result {
+                tracing::warn!("Failed to send message task: {:?}", e);
+                consensus.write().await.record_send_failure(
+                    view_number,
+                    SendFailure {
+                        action: maybe_action,
+                        recipient,
+                        error: e.to_string(),
+                    },
+                );
             }
         });
         self.transmit_tasks
@@ -1053,4 +1400,108 @@ pub mod test {
             &mut self.network_event_task_state
         }
     }
+
+    /// A dynamic type alias for a function that picks who a message parsed by
+    /// `NetworkEventTaskState::parse_event` is sent to. `None` sends it as usual.
+    pub type RecipientsClosure<TYPES> = dyn Fn(
+            &MessageKind<TYPES>,
+            &<TYPES as NodeType>::Membership,
+        ) -> Option<Vec<<TYPES as NodeType>::SignatureKey>>
+        + Send
+        + Sync;
+
+    /// A helper wrapper around `NetworkEventTaskState` that can send messages to a chosen set
+    /// of nodes instead of their usual recipients, for tests
+    pub struct NetworkEventTaskStateRecipients<
+        TYPES: NodeType,
+        V: Versions,
+        NET: ConnectedNetwork<TYPES::SignatureKey>,
+        S: Storage<TYPES>,
+    > {
+        /// The real `NetworkEventTaskState`
+        pub network_event_task_state: NetworkEventTaskState<TYPES, V, NET, S>,
+        /// A function that picks the recipients of a message
+        pub recipients: Arc<RecipientsClosure<TYPES>>,
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES> + 'static,
+        > NetworkEventTaskStateRecipients<TYPES, V, NET, S>
+    {
+        /// Handles the received event, sending it directly to each of the chosen recipients.
+        pub async fn handle(&mut self, event: Arc<HotShotEvent<TYPES>>) {
+            let mut maybe_action = None;
+            let Some((sender, message_kind, transmit)) =
+                self.parse_event(event, &mut maybe_action).await
+            else {
+                return;
+            };
+            let Some(recipients) = (self.recipients)(&message_kind, &self.membership) else {
+                self.spawn_transmit_task(message_kind, maybe_action, transmit, sender);
+                return;
+            };
+            for recipient in recipients {
+                // The action is recorded once, by the first transmission
+                self.spawn_transmit_task(
+                    message_kind.clone(),
+                    maybe_action.take(),
+                    TransmitType::Direct(recipient),
+                    sender.clone(),
+                );
+            }
+        }
+    }
+
+    #[async_trait]
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES> + 'static,
+        > TaskState for NetworkEventTaskStateRecipients<TYPES, V, NET, S>
+    {
+        type Event = HotShotEvent<TYPES>;
+
+        async fn handle_event(
+            &mut self,
+            event: Arc<Self::Event>,
+            _sender: &Sender<Arc<Self::Event>>,
+            _receiver: &Receiver<Arc<Self::Event>>,
+        ) -> Result<()> {
+            self.handle(event).await;
+
+            Ok(())
+        }
+
+        fn cancel_subtasks(&mut self) {}
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES>,
+        > Deref for NetworkEventTaskStateRecipients<TYPES, V, NET, S>
+    {
+        type Target = NetworkEventTaskState<TYPES, V, NET, S>;
+
+        fn deref(&self) -> &Self::Target {
+            &self.network_event_task_state
+        }
+    }
+
+    impl<
+            TYPES: NodeType,
+            V: Versions,
+            NET: ConnectedNetwork<TYPES::SignatureKey>,
+            S: Storage<TYPES>,
+        > DerefMut for NetworkEventTaskStateRecipients<TYPES, V, NET, S>
+    {
+        fn deref_mut(&mut self) -> &mut Self::Target {
+            &mut self.network_event_task_state
+        }
+    }
 }
@@ -19,20 +19,24 @@ use async_lock::RwLock;
 use committable::Committable;
 use hotshot_task::dependency_task::HandleDepOutput;
 use hotshot_types::{
-    consensus::{CommitmentAndMetadata, OuterConsensus},
-    data::{Leaf2, QuorumProposal2, VidDisperse, ViewChangeEvidence},
+    checkpoint::EpochCheckpoint,
+    consensus::{CommitRule, CommitmentAndMetadata, OuterConsensus},
+    data::{HighQcProof, Leaf2, QuorumProposal2, VidDisperse, ViewChangeEvidence},
     drb::{INITIAL_DRB_RESULT, INITIAL_DRB_SEED_INPUT},
     message::Proposal,
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
+    simple_vote::{HighQcData, HighQcVote},
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signer::Signer,
     },
     utils::epoch_from_block_number,
-    vote::{Certificate, HasViewNumber},
+    view_timing::Pacing,
+    vote::{Certificate, HasViewNumber, Vote},
 };
+use tokio::time::sleep;
 use tracing::instrument;
 use utils::anytrace::*;
 use vbs::version::StaticVersionType;
@@ -63,6 +67,9 @@ pub(crate) enum ProposalDependency {
 
     /// For the `VidShareValidated` event.
     VidShare,
+
+    /// For the `DaCertificateValidated` event of the parent view.
+    Dac,
 }
 
 /// Handler for the proposal dependency
@@ -88,8 +95,8 @@ pub struct ProposalDependencyHandle<TYPES: NodeType, V: Versions> {
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our proposals, high QC reports and requests to the network
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Shared consensus task state
     pub consensus: OuterConsensus<TYPES>,
@@ -118,57 +125,113 @@ pub struct ProposalDependencyHandle<TYPES: NodeType, V: Versions> {
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// The rule by which leaves are decided. Under the two-chain rule we propose from the
+    /// highest QC the replicas send us, since they may be locked on a newer QC than our own.
+    pub commit_rule: CommitRule,
+
+    /// When we send our proposal
+    pub pacing: Pacing,
+
+    /// `HighQcRecv` events for our view which arrived before this task started listening
+    pub received_high_qc_reports: Vec<Arc<HotShotEvent<TYPES>>>,
 }
 
 impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
-    /// Return the next HighQc we get from the event stream
+    /// The QC and report of a `HighQcRecv` event, if the report is for the view we propose in,
+    /// signed by its sender, and names the view of a valid QC.
+    async fn high_qc_report(
+        &self,
+        event: &HotShotEvent<TYPES>,
+    ) -> Option<(QuorumCertificate2<TYPES>, HighQcVote<TYPES>)> {
+        let HotShotEvent::HighQcRecv(qc, report, sender) = event else {
+            return None;
+        };
+        if report.view_number() == self.view_number
+            && report.signing_key() == *sender
+            && report.data.high_qc_view == qc.view_number()
+            && HighQcProof::is_signed(report, &self.upgrade_lock).await
+            && qc
+                .is_valid_cert(
+                    // TODO take epoch from `qc`
+                    // https://github.com/EspressoSystems/HotShot/issues/3917
+                    &self.quorum_membership.stake_table(qc.data.epoch),
+                    self.quorum_membership.success_threshold(qc.data.epoch),
+                    &self.upgrade_lock,
+                )
+                .await
+        {
+            return Some((qc.clone(), report.clone()));
+        }
+        None
+    }
+    /// Return the next HighQc we get from the event stream, with its sender's report of the
+    /// QC's view
     async fn wait_for_qc_event(
         &self,
         rx: &mut Receiver<Arc<HotShotEvent<TYPES>>>,
-    ) -> Option<QuorumCertificate2<TYPES>> {
+    ) -> Option<(QuorumCertificate2<TYPES>, HighQcVote<TYPES>)> {
         while let Ok(event) = rx.recv_direct().await {
-            if let HotShotEvent::HighQcRecv(qc, _sender) = event.as_ref() {
-                if qc
-                    .is_valid_cert(
-                        // TODO take epoch from `qc`
-                        // https://github.com/EspressoSystems/HotShot/issues/3917
-                        self.quorum_membership.stake_table(qc.data.epoch),
-                        self.quorum_membership.success_threshold(qc.data.epoch),
-                        &self.upgrade_lock,
-                    )
-                    .await
-                {
-                    return Some(qc.clone());
-                }
+            if let Some(report) = self.high_qc_report(&event).await {
+                return Some(report);
             }
         }
         None
     }
-    /// Waits for the ocnfigured timeout for nodes to send HighQc messages to us.  We'll
-    /// then propose with the highest QC from among these proposals.
-    async fn wait_for_highest_qc(&mut self) {
+    /// Waits for the ocnfigured timeout for nodes to send HighQc messages to us, or until a
+    /// quorum has.  We'll then propose with the highest QC from among these proposals, and prove
+    /// it is the highest with the reports of the nodes which sent them in `epoch`.
+    async fn wait_for_highest_qc(&mut self, epoch: TYPES::Epoch) -> HighQcProof<TYPES> {
         tracing::error!("waiting for QC");
-        // If we haven't upgraded to Hotstuff 2 just return the high qc right away
+        // If we don't follow the two-chain rule just return the high qc right away
         if self
             .upgrade_lock
             .version(self.view_number)
             .await
-            .is_ok_and(|version| version < V::Epochs::VERSION)
+            .is_ok_and(|version| !self.commit_rule.two_chain(version >= V::Epochs::VERSION))
         {
-            return;
+            return HighQcProof { reports: vec![] };
         }
+        let mut proof = HighQcProof { reports: vec![] };
+
+        // Our own high QC is reported like everyone else's
+        match HighQcVote::create_signed_vote_with_signer(
+            HighQcData {
+                high_qc_view: self.highest_qc.view_number(),
+                epoch,
+            },
+            self.view_number,
+            self.signer.as_ref(),
+            &self.upgrade_lock,
+        )
+        .await
+        {
+            Ok(report) => Self::add_report(&mut proof, report, epoch),
+            Err(e) => tracing::warn!("Failed to sign our own high QC report: {e}"),
+        }
+        for event in std::mem::take(&mut self.received_high_qc_reports) {
+            if let Some((qc, report)) = self.high_qc_report(&event).await {
+                if qc.view_number() > self.highest_qc.view_number() {
+                    self.highest_qc = qc;
+                }
+                Self::add_report(&mut proof, report, epoch);
+            }
+        }
+
         let wait_duration = Duration::from_millis(self.timeout / 2);
 
         // TODO configure timeout
-        while self.view_start_time.elapsed() < wait_duration {
+        while self.view_start_time.elapsed() < wait_duration
+            && !proof.is_from_quorum(&self.quorum_membership, epoch)
+        {
             let Some(time_spent) = Instant::now().checked_duration_since(self.view_start_time)
             else {
                 // Shouldn't be possible, now must be after the start
-                return;
+                break;
             };
             let Some(time_left) = wait_duration.checked_sub(time_spent) else {
                 // No time left
-                return;
+                break;
             };
             let Ok(maybe_qc) = tokio::time::timeout(
                 time_left,
@@ -177,25 +240,41 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             .await
             else {
                 // we timeout out, don't wait any longer
-                return;
+                break;
             };
-            let Some(qc) = maybe_qc else {
+            let Some((qc, report)) = maybe_qc else {
                 continue;
             };
             if qc.view_number() > self.highest_qc.view_number() {
                 self.highest_qc = qc;
             }
+            Self::add_report(&mut proof, report, epoch);
+        }
+
+        proof
+    }
+    /// Adds `report` to `proof` if it is for `epoch` and the first one from its node.
+    fn add_report(proof: &mut HighQcProof<TYPES>, report: HighQcVote<TYPES>, epoch: TYPES::Epoch) {
+        let key = report.signing_key();
+        if report.data.epoch == epoch
+            && !proof
+                .reports
+                .iter()
+                .any(|reported| reported.signing_key() == key)
+        {
+            proof.reports.push(report);
         }
     }
     /// Publishes a proposal given the [`CommitmentAndMetadata`], [`VidDisperse`]
     /// and high qc [`hotshot_types::simple_certificate::QuorumCertificate`],
-    /// with optional [`ViewChangeEvidence`].
+    /// with optional [`ViewChangeEvidence`] and [`HighQcProof`].
     #[instrument(skip_all, fields(id = self.id, view_number = *self.view_number, latest_proposed_view = *self.latest_proposed_view))]
     async fn publish_proposal(
         &self,
         commitment_and_metadata: CommitmentAndMetadata<TYPES>,
         vid_share: Proposal<TYPES, VidDisperse<TYPES>>,
         view_change_evidence: Option<ViewChangeEvidence<TYPES>>,
+        high_qc_proof: Option<HighQcProof<TYPES>>,
         formed_upgrade_certificate: Option<UpgradeCertificate<TYPES>>,
         decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
         parent_qc: QuorumCertificate2<TYPES>,
@@ -204,8 +283,7 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             &self.sender,
             &self.receiver,
             Arc::clone(&self.quorum_membership),
-            self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
             &self.upgrade_lock,
             parent_qc.view_number(),
@@ -213,6 +291,17 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
         )
         .await?;
 
+        // Replicas refuse to extend a leaf whose payload is not certified as available, however we
+        // came by its QC
+        ensure!(
+            self.consensus
+                .read()
+                .await
+                .is_payload_certified(&parent_leaf),
+            "Cannot propose because the DAC of our parent in view {} has not formed.",
+            *parent_leaf.view_number()
+        );
+
         // In order of priority, we should try to attach:
         //   - the parent certificate if it exists, or
         //   - our own certificate that we formed.
@@ -253,6 +342,16 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
 
         let version = self.upgrade_lock.version(self.view_number).await?;
 
+        // Under the two-chain rule, replicas only accept a proposal after a view change if a
+        // quorum reported no QC newer than the one it extends
+        let high_qc_proof = proposal_certificate.as_ref().and(high_qc_proof);
+        if let (Some(evidence), Some(proof)) = (&proposal_certificate, &high_qc_proof) {
+            ensure!(
+                proof.is_from_quorum(&self.quorum_membership, evidence.epoch()),
+                "Cannot propose because too few nodes reported their high QC to us."
+            );
+        }
+
         let builder_commitment = commitment_and_metadata.builder_commitment.clone();
         let metadata = commitment_and_metadata.metadata.clone();
 
@@ -312,6 +411,16 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             );
             return Ok(());
         }
+        // The last block of an epoch hands over to the committees of the next one
+        let epoch_checkpoint = if version >= V::Epochs::VERSION {
+            EpochCheckpoint::for_block(
+                block_header.block_number(),
+                self.epoch_height,
+                &self.quorum_membership,
+            )
+        } else {
+            None
+        };
         let proposal = QuorumProposal2 {
             block_header,
             view_number: self.view_number,
@@ -320,6 +429,8 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             view_change_evidence: proposal_certificate,
             drb_seed: INITIAL_DRB_SEED_INPUT,
             drb_result: INITIAL_DRB_RESULT,
+            high_qc_proof,
+            epoch_checkpoint,
         };
 
         let proposed_leaf = Leaf2::from_quorum_proposal(&proposal);
@@ -328,10 +439,12 @@ impl<TYPES: NodeType, V: Versions> ProposalDependencyHandle<TYPES, V> {
             "Proposed leaf parent does not equal high qc"
         );
 
-        let signature =
-            TYPES::SignatureKey::sign(&self.private_key, proposed_leaf.commit().as_ref())
-                .wrap()
-                .context(error!("Failed to compute proposed_leaf.commit()"))?;
+        let signature = self
+            .signer
+            .sign(proposed_leaf.commit().as_ref())
+            .await
+            .wrap()
+            .context(error!("Failed to sign the proposed leaf"))?;
 
         let message = Proposal {
             data: proposal,
@@ -396,7 +509,8 @@ impl<TYPES: NodeType, V: Versions> HandleDepOutput for ProposalDependencyHandle<
                 HotShotEvent::ViewSyncFinalizeCertificateRecv(cert) => {
                     view_sync_finalize_cert = Some(cert.clone());
                 }
-                HotShotEvent::VidDisperseSend(share, _) => {
+                HotShotEvent::VidDisperseSend(share, _)
+                | HotShotEvent::VidDisperseComputed(share) => {
                     vid_share = Some(share.clone());
                 }
                 _ => {}
@@ -410,12 +524,26 @@ impl<TYPES: NodeType, V: Versions> HandleDepOutput for ProposalDependencyHandle<
             );
             return;
         };
+        let proposal_cert = if let Some(view_sync_cert) = view_sync_finalize_cert {
+            Some(ViewChangeEvidence::ViewSync(view_sync_cert))
+        } else {
+            timeout_certificate.map(ViewChangeEvidence::Timeout)
+        };
+
+        let mut high_qc_proof = None;
         let parent_qc = if let Some(qc) = parent_qc {
             qc
-        } else if version < V::Epochs::VERSION {
+        } else if !self.commit_rule.two_chain(version >= V::Epochs::VERSION) {
             self.consensus.read().await.high_qc().clone()
         } else {
-            self.wait_for_highest_qc().await;
+            let Some(evidence) = &proposal_cert else {
+                tracing::error!(
+                    "Somehow completed the proposal dependency task without a QC or view change \
+                     evidence"
+                );
+                return;
+            };
+            high_qc_proof = Some(self.wait_for_highest_qc(evidence.epoch()).await);
             self.highest_qc.clone()
         };
 
@@ -431,17 +559,22 @@ impl<TYPES: NodeType, V: Versions> HandleDepOutput for ProposalDependencyHandle<
             return;
         }
 
-        let proposal_cert = if let Some(view_sync_cert) = view_sync_finalize_cert {
-            Some(ViewChangeEvidence::ViewSync(view_sync_cert))
-        } else {
-            timeout_certificate.map(ViewChangeEvidence::Timeout)
-        };
+        // Under fixed-interval pacing, hold the proposal until the interval is over
+        let delay = self.pacing.proposal_delay(self.view_start_time.elapsed());
+        if !delay.is_zero() {
+            tracing::debug!(
+                "Holding the proposal for view {:?} for {delay:?}",
+                self.view_number
+            );
+            sleep(delay).await;
+        }
 
         if let Err(e) = self
             .publish_proposal(
                 commit_and_metadata.unwrap(),
                 vid_share.unwrap(),
                 proposal_cert,
+                high_qc_proof,
                 self.formed_upgrade_certificate.clone(),
                 Arc::clone(&self.upgrade_lock.decided_upgrade_certificate),
                 parent_qc,
@@ -16,16 +16,17 @@ use hotshot_task::{
     task::TaskState,
 };
 use hotshot_types::{
-    consensus::OuterConsensus,
+    consensus::{CommitRule, OuterConsensus},
     message::UpgradeLock,
     simple_certificate::{QuorumCertificate2, UpgradeCertificate},
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
         storage::Storage,
     },
     utils::EpochTransitionIndicator,
+    view_timing::Pacing,
     vote::{Certificate, HasViewNumber},
 };
 use tokio::task::JoinHandle;
@@ -57,8 +58,8 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our proposals, high QC reports and requests to the network
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// View timeout from config.
     pub timeout: u64,
@@ -88,6 +89,16 @@ pub struct QuorumProposalTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>
 
     /// The highest_qc we've seen at the start of this task
     pub highest_qc: QuorumCertificate2<TYPES>,
+
+    /// The rule by which leaves are decided
+    pub commit_rule: CommitRule,
+
+    /// When we send our proposals
+    pub pacing: Pacing,
+
+    /// `HighQcRecv` events for each view we lead, kept until we propose in case they arrive
+    /// before the proposal dependency task for the view starts
+    pub high_qc_reports: BTreeMap<TYPES::View, Vec<Arc<HotShotEvent<TYPES>>>>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
@@ -153,12 +164,21 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                         }
                     }
                     ProposalDependency::VidShare => {
-                        if let HotShotEvent::VidDisperseSend(vid_share, _) = event {
+                        if let HotShotEvent::VidDisperseSend(vid_share, _)
+                        | HotShotEvent::VidDisperseComputed(vid_share) = event
+                        {
                             vid_share.data.view_number()
                         } else {
                             return false;
                         }
                     }
+                    ProposalDependency::Dac => {
+                        if let HotShotEvent::DaCertificateValidated(cert) = event {
+                            cert.view_number() + 1
+                        } else {
+                            return false;
+                        }
+                    }
                 };
                 let valid = event_view == view_number;
                 if valid {
@@ -214,6 +234,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
             event_receiver.clone(),
         );
 
+        let mut dac_dependency = self.create_event_dependency(
+            ProposalDependency::Dac,
+            view_number,
+            event_receiver.clone(),
+        );
+
         match event.as_ref() {
             HotShotEvent::SendPayloadCommitmentAndMetadata(..) => {
                 payload_commitment_dependency.mark_as_completed(Arc::clone(&event));
@@ -232,9 +258,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
             HotShotEvent::ViewSyncFinalizeCertificateRecv(_) => {
                 view_sync_dependency.mark_as_completed(event);
             }
-            HotShotEvent::VidDisperseSend(_, _) => {
+            HotShotEvent::VidDisperseSend(_, _) | HotShotEvent::VidDisperseComputed(_) => {
                 vid_share_dependency.mark_as_completed(event);
             }
+            HotShotEvent::DaCertificateValidated(_) => {
+                dac_dependency.mark_as_completed(event);
+            }
             _ => {}
         };
 
@@ -246,10 +275,14 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
             AndDependency::from_deps(vec![view_sync_dependency]),
         ];
         // 3. A `Qc2Formed`` event (and `QuorumProposalRecv` event)
+        //
+        // DA and quorum votes for the parent view are collected concurrently, so when extending
+        // a QC we also wait for the parent's DAC to make sure the payload we build on is available.
         if *view_number > 1 {
             secondary_deps.push(AndDependency::from_deps(vec![
                 qc_dependency,
                 proposal_dependency,
+                dac_dependency,
             ]));
         } else {
             secondary_deps.push(AndDependency::from_deps(vec![qc_dependency]));
@@ -324,7 +357,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 receiver: event_receiver,
                 quorum_membership: Arc::clone(&self.quorum_membership),
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: Arc::clone(&self.signer),
                 instance_state: Arc::clone(&self.instance_state),
                 consensus: OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
                 timeout: self.timeout,
@@ -334,6 +367,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 view_start_time: Instant::now(),
                 highest_qc: self.highest_qc.clone(),
                 epoch_height: self.epoch_height,
+                commit_rule: self.commit_rule,
+                pacing: self.pacing,
+                received_high_qc_reports: self
+                    .high_qc_reports
+                    .get(&view_number)
+                    .cloned()
+                    .unwrap_or_default(),
             },
         );
         self.proposal_dependencies
@@ -472,7 +512,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 ensure!(
                     certificate
                         .is_valid_cert(
-                            self.quorum_membership.stake_table(epoch_number),
+                            &self.quorum_membership.stake_table(epoch_number),
                             self.quorum_membership.success_threshold(epoch_number),
                             &self.upgrade_lock
                         )
@@ -518,7 +558,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                     "Failed to update latest proposed view"
                 );
             }
-            HotShotEvent::VidDisperseSend(vid_share, _) => {
+            HotShotEvent::VidDisperseSend(vid_share, _)
+            | HotShotEvent::VidDisperseComputed(vid_share) => {
                 let view_number = vid_share.data.view_number();
                 self.create_dependency_task_if_new(
                     view_number,
@@ -529,6 +570,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                     EpochTransitionIndicator::NotInTransition,
                 )?;
             }
+            HotShotEvent::DaCertificateValidated(cert) => {
+                let view_number = cert.view_number() + 1;
+                self.create_dependency_task_if_new(
+                    view_number,
+                    epoch_number,
+                    event_receiver,
+                    event_sender,
+                    Arc::clone(&event),
+                    epoch_transition_indicator,
+                )?;
+            }
             HotShotEvent::ViewChange(view, epoch) => {
                 if epoch > &self.cur_epoch {
                     self.cur_epoch = *epoch;
@@ -545,7 +597,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 let cert_epoch_number = qc.data.epoch;
                 ensure!(
                     qc.is_valid_cert(
-                        self.quorum_membership.stake_table(cert_epoch_number),
+                        &self.quorum_membership.stake_table(cert_epoch_number),
                         self.quorum_membership.success_threshold(cert_epoch_number),
                         &self.upgrade_lock
                     )
@@ -554,6 +606,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 );
                 self.highest_qc = qc.clone();
             }
+            HotShotEvent::HighQcRecv(_, report, _) => {
+                let view = report.view_number();
+                ensure!(
+                    view > self.latest_proposed_view
+                        && self.quorum_membership.leader(view, epoch_number)? == self.public_key,
+                    debug!("Received a high QC report for a view we do not lead")
+                );
+                let reports = self.high_qc_reports.entry(view).or_default();
+                if reports.len() < self.quorum_membership.total_nodes(epoch_number) {
+                    reports.push(Arc::clone(&event));
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -566,6 +630,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
             task.abort();
         }
         self.proposal_dependencies = keep;
+        self.high_qc_reports = self.high_qc_reports.split_off(&view);
     }
 }
 
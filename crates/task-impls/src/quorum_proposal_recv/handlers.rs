@@ -20,11 +20,12 @@ use hotshot_types::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signer::Signer,
         storage::Storage,
         ValidatedState,
     },
     utils::{epoch_from_block_number, View, ViewInner},
+    view_timing::ViewPhase,
     vote::{Certificate, HasViewNumber},
 };
 use tokio::spawn;
@@ -49,13 +50,13 @@ async fn validate_proposal_liveness<TYPES: NodeType, I: NodeImplementation<TYPES
 ) -> Result<()> {
     let mut consensus_writer = validation_info.consensus.write().await;
 
-    let leaf = Leaf2::from_quorum_proposal(&proposal.data);
+    let leaf = Arc::new(Leaf2::from_quorum_proposal(&proposal.data));
 
     let state = Arc::new(
         <TYPES::ValidatedState as ValidatedState<TYPES>>::from_header(&proposal.data.block_header),
     );
 
-    if let Err(e) = consensus_writer.update_leaf(leaf.clone(), state, None) {
+    if let Err(e) = consensus_writer.update_leaf(Arc::clone(&leaf), state, None) {
         tracing::trace!("{e:?}");
     }
 
@@ -74,13 +75,18 @@ async fn validate_proposal_liveness<TYPES: NodeType, I: NodeImplementation<TYPES
 
     let liveness_check =
         proposal.data.justify_qc.clone().view_number() > consensus_writer.locked_view();
-    // if we are using HS2 we update our locked view for any QC from a leader greater than our current lock
+    // if we are using the two-chain rule we update our locked view for any QC from a leader
+    // greater than our current lock
     if liveness_check
         && validation_info
             .upgrade_lock
             .version(leaf.view_number())
             .await
-            .is_ok_and(|v| v >= V::Epochs::VERSION)
+            .is_ok_and(|v| {
+                validation_info
+                    .commit_rule
+                    .two_chain(v >= V::Epochs::VERSION)
+            })
     {
         consensus_writer.update_locked_view(proposal.data.justify_qc.clone().view_number())?;
     }
@@ -102,8 +108,7 @@ fn spawn_fetch_proposal<TYPES: NodeType, V: Versions>(
     event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
     membership: Arc<TYPES::Membership>,
     consensus: OuterConsensus<TYPES>,
-    sender_public_key: TYPES::SignatureKey,
-    sender_private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     upgrade_lock: UpgradeLock<TYPES, V>,
     epoch_height: u64,
 ) {
@@ -116,8 +121,7 @@ fn spawn_fetch_proposal<TYPES: NodeType, V: Versions>(
             event_receiver,
             membership,
             consensus,
-            sender_public_key,
-            sender_private_key,
+            signer,
             &lock,
             epoch_height,
         )
@@ -161,7 +165,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
 
     if !justify_qc
         .is_valid_cert(
-            validation_info
+            &validation_info
                 .quorum_membership
                 .stake_table(justify_qc.data.epoch),
             validation_info
@@ -203,8 +207,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
             // Note that we explicitly use the node key here instead of the provided key in the signature.
             // This is because the key that we receive is for the prior leader, so the payload would be routed
             // incorrectly.
-            validation_info.public_key.clone(),
-            validation_info.private_key.clone(),
+            Arc::clone(&validation_info.signer),
             validation_info.upgrade_lock.clone(),
             validation_info.epoch_height,
         );
@@ -239,6 +242,7 @@ pub(crate) async fn handle_quorum_proposal_recv<
     if let Err(e) = consensus_writer.update_high_qc(justify_qc.clone()) {
         tracing::trace!("{e:?}");
     }
+    consensus_writer.record_view_phase(view_number, ViewPhase::ProposalReceived);
     drop(consensus_writer);
 
     let Some((parent_leaf, _parent_state)) = parent else {
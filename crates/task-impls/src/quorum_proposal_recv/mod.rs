@@ -15,14 +15,15 @@ use either::Either;
 use futures::future::{err, join_all};
 use hotshot_task::task::{Task, TaskState};
 use hotshot_types::{
-    consensus::{Consensus, OuterConsensus},
+    consensus::{CommitRule, Consensus, OuterConsensus},
     data::{EpochNumber, Leaf, ViewChangeEvidence},
     event::Event,
     message::UpgradeLock,
     simple_certificate::UpgradeCertificate,
+    timestamp_rules::TimestampRules,
     traits::{
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
     },
     vote::{Certificate, HasViewNumber},
 };
@@ -35,6 +36,7 @@ use self::handlers::handle_quorum_proposal_recv;
 use crate::{
     events::{HotShotEvent, ProposalMissing},
     helpers::{broadcast_event, fetch_proposal, parent_leaf_and_state},
+    workers::WorkerPool,
 };
 /// Event handlers for this task.
 mod handlers;
@@ -45,8 +47,8 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
     /// Our public key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our requests for proposals we are missing
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -81,6 +83,15 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Rules the timestamp of a proposed block must satisfy
+    pub timestamp_rules: TimestampRules,
+
+    /// Threads to check proposal signatures on
+    pub verification_workers: Arc<WorkerPool>,
+
+    /// The rule by which leaves are decided, which decides what we lock on
+    pub commit_rule: CommitRule,
 }
 
 /// all the info we need to validate a proposal.  This makes it easy to spawn an effemeral task to
@@ -88,10 +99,8 @@ pub struct QuorumProposalRecvTaskState<TYPES: NodeType, I: NodeImplementation<TY
 pub(crate) struct ValidationInfo<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// The node's id
     pub id: u64,
-    /// Our public key
-    pub(crate) public_key: TYPES::SignatureKey,
-    /// Our Private Key
-    pub(crate) private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our requests for proposals we are missing
+    pub(crate) signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// Reference to consensus. The replica will require a write lock on this.
     pub(crate) consensus: OuterConsensus<TYPES>,
     /// Membership for Quorum Certs/votes
@@ -104,6 +113,12 @@ pub(crate) struct ValidationInfo<TYPES: NodeType, I: NodeImplementation<TYPES>,
     pub(crate) upgrade_lock: UpgradeLock<TYPES, V>,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// Rules the timestamp of a proposed block must satisfy
+    pub timestamp_rules: TimestampRules,
+    /// Threads to check proposal signatures on
+    pub verification_workers: Arc<WorkerPool>,
+    /// The rule by which leaves are decided
+    pub commit_rule: CommitRule,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
@@ -139,14 +154,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>
                 }
                 let validation_info = ValidationInfo::<TYPES, I, V> {
                     id: self.id,
-                    public_key: self.public_key.clone(),
-                    private_key: self.private_key.clone(),
+                    signer: Arc::clone(&self.signer),
                     consensus: self.consensus.clone(),
                     quorum_membership: Arc::clone(&self.quorum_membership),
                     output_event_stream: self.output_event_stream.clone(),
                     storage: Arc::clone(&self.storage),
                     upgrade_lock: self.upgrade_lock.clone(),
                     epoch_height: self.epoch_height,
+                    timestamp_rules: self.timestamp_rules,
+                    verification_workers: Arc::clone(&self.verification_workers),
+                    commit_rule: self.commit_rule,
                 };
                 match handle_quorum_proposal_recv(
                     proposal,
@@ -1,10 +1,11 @@
 use std::collections::{btree_map, BTreeMap};
 
+use hotshot_task::spawn::spawn_named;
 use hotshot_types::{
     drb::{compute_drb_result, DrbResult, DrbSeedInput},
     traits::node_implementation::{ConsensusTime, NodeType},
 };
-use tokio::{spawn, task::JoinHandle};
+use tokio::task::JoinHandle;
 
 /// Number of previous results and seeds to keep
 pub const KEEP_PREVIOUS_RESULT_COUNT: u64 = 8;
@@ -86,7 +87,9 @@ impl<TYPES: NodeType> DrbComputations<TYPES> {
 
         if let btree_map::Entry::Occupied(entry) = self.seeds.entry(epoch) {
             let drb_seed_input = *entry.get();
-            let new_drb_task = spawn(async move { compute_drb_result::<TYPES>(drb_seed_input) });
+            let new_drb_task = spawn_named("drb computation", async move {
+                compute_drb_result::<TYPES>(drb_seed_input)
+            });
             self.task = Some((epoch, new_drb_task));
             entry.remove();
         }
@@ -11,6 +11,7 @@ use async_lock::RwLock;
 use chrono::Utc;
 use committable::Committable;
 use hotshot_types::{
+    beacon::beacon_output,
     consensus::OuterConsensus,
     data::{Leaf2, QuorumProposal2, VidDisperseShare2},
     event::{Event, EventType, LeafInfo},
@@ -20,8 +21,8 @@ use hotshot_types::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
-        signature_key::SignatureKey,
-        storage::Storage,
+        signer::Signer,
+        storage::{Storage, ViewWrites},
         ValidatedState,
     },
     utils::epoch_from_block_number,
@@ -39,6 +40,7 @@ use crate::{
         LeafChainTraversalOutcome,
     },
     quorum_vote::Versions,
+    workers::WorkerPool,
 };
 
 /// Handles starting the DRB calculation. Uses the seed previously stored in
@@ -56,22 +58,25 @@ async fn handle_quorum_proposal_validated_drb_calculation_start<
         task_state.epoch_height,
     ));
 
-    // Start the new task if we're in the committee for this epoch
-    if task_state
-        .membership
-        .has_stake(&task_state.public_key, current_epoch_number)
-    {
+    // Every node needs the result to look up leaders, so run the task whether or not we are in
+    // the committee
+    let next_epoch_number = current_epoch_number + 1;
+    task_state
+        .drb_computations
+        .start_task_if_not_running(next_epoch_number)
+        .await;
+    if let Some(drb_result) = task_state.drb_computations.get_result(next_epoch_number) {
         task_state
-            .drb_computations
-            .start_task_if_not_running(current_epoch_number + 1)
-            .await;
+            .membership
+            .add_drb_result(next_epoch_number, drb_result);
     }
 }
 
 /// Handles storing the seed for an upcoming DRB calculation.
 ///
 /// We store the DRB computation seed 2 epochs in advance, if the decided block is the last but
-/// third block in the current epoch and we are in the quorum committee of the next epoch.
+/// third block in the current epoch. Every node stores it, since leaders are picked with the
+/// result.
 ///
 /// Special cases:
 /// * Epoch 0: No DRB computation since we'll transition to epoch 1 immediately.
@@ -109,28 +114,41 @@ fn handle_quorum_proposal_validated_drb_calculation_seed<
             .drb_computations
             .garbage_collect(current_epoch_number);
 
-        // Skip if we are not in the committee of the next epoch.
-        if task_state
-            .membership
-            .has_stake(&task_state.public_key, current_epoch_number + 1)
-        {
-            let new_epoch_number = current_epoch_number + 2;
-            let Ok(drb_seed_input_vec) = bincode::serialize(&proposal.justify_qc.signatures) else {
-                bail!("Failed to serialize the QC signature.");
-            };
-            let Ok(drb_seed_input) = drb_seed_input_vec.try_into() else {
-                bail!("Failed to convert the serialized QC signature into a DRB seed input.");
-            };
-
-            // Store the drb seed input for the next calculation
-            task_state
-                .drb_computations
-                .store_seed(new_epoch_number, drb_seed_input);
-        }
+        let new_epoch_number = current_epoch_number + 2;
+        let Some(drb_seed_input) = beacon_output(&proposal.justify_qc) else {
+            bail!("The QC for the DRB seed has no signature.");
+        };
+
+        // Store the drb seed input for the next calculation
+        task_state
+            .drb_computations
+            .store_seed(new_epoch_number, drb_seed_input);
     }
     Ok(())
 }
 
+/// Records who signed the QCs of newly decided leaves, and jails the validators which were
+/// offline in an epoch once its last block is decided.
+///
+/// `leaf_views` is newest first, so it is walked backwards to record the leaves in chain order.
+fn handle_quorum_proposal_validated_participation<
+    TYPES: NodeType,
+    I: NodeImplementation<TYPES>,
+    V: Versions,
+>(
+    task_state: &mut QuorumVoteTaskState<TYPES, I, V>,
+    leaf_views: &[LeafInfo<TYPES>],
+) {
+    for leaf_info in leaf_views.iter().rev() {
+        task_state.participation.record_decided(
+            &leaf_info.leaf,
+            &task_state.membership,
+            task_state.jail_config,
+            task_state.epoch_height,
+        );
+    }
+}
+
 /// Handles the `QuorumProposalValidated` event.
 #[instrument(skip_all, fields(id = task_state.id, view = *proposal.view_number))]
 pub(crate) async fn handle_quorum_proposal_validated<
@@ -157,7 +175,10 @@ pub(crate) async fn handle_quorum_proposal_validated<
         leaf_views,
         included_txns,
         decided_upgrade_cert,
-    } = if version >= V::Epochs::VERSION {
+    } = if task_state
+        .commit_rule
+        .two_chain(version >= V::Epochs::VERSION)
+    {
         decide_from_proposal_2(
             proposal,
             OuterConsensus::new(Arc::clone(&task_state.consensus.inner_consensus)),
@@ -201,6 +222,14 @@ pub(crate) async fn handle_quorum_proposal_validated<
     if let Some(decided_view_number) = new_decided_view_number {
         // Bring in the cleanup crew. When a new decide is indeed valid, we need to clear out old memory.
 
+        consensus_writer.update_rejected_transactions(
+            leaf_views
+                .iter()
+                .flat_map(|leaf_info| leaf_info.rejected.iter().cloned()),
+        );
+
+        consensus_writer.check_decided_chain(leaf_views.iter().map(|leaf_info| &leaf_info.leaf));
+
         let old_decided_view = consensus_writer.last_decided_view();
         consensus_writer.collect_garbage(old_decided_view, decided_view_number);
 
@@ -248,6 +277,7 @@ pub(crate) async fn handle_quorum_proposal_validated<
         tracing::debug!("Successfully sent decide event");
 
         if version >= V::Epochs::VERSION {
+            handle_quorum_proposal_validated_participation(task_state, &leaf_views);
             handle_quorum_proposal_validated_drb_calculation_seed(
                 proposal,
                 task_state,
@@ -262,21 +292,17 @@ pub(crate) async fn handle_quorum_proposal_validated<
 /// Updates the shared consensus state with the new voting data.
 #[instrument(skip_all, target = "VoteDependencyHandle", fields(view = *view_number))]
 #[allow(clippy::too_many_arguments)]
-pub(crate) async fn update_shared_state<
-    TYPES: NodeType,
-    I: NodeImplementation<TYPES>,
-    V: Versions,
->(
+pub(crate) async fn update_shared_state<TYPES: NodeType, V: Versions>(
     consensus: OuterConsensus<TYPES>,
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
     receiver: InactiveReceiver<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
-    public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     upgrade_lock: UpgradeLock<TYPES, V>,
     view_number: TYPES::View,
     instance_state: Arc<TYPES::InstanceState>,
-    storage: Arc<RwLock<I::Storage>>,
+    state_workers: Arc<WorkerPool>,
+    writes: &mut ViewWrites<TYPES>,
     proposed_leaf: &Leaf2<TYPES>,
     vid_share: &Proposal<TYPES, VidDisperseShare2<TYPES>>,
     parent_view_number: Option<TYPES::View>,
@@ -311,8 +337,7 @@ pub(crate) async fn update_shared_state<
                 receiver.activate_cloned(),
                 Arc::clone(&quorum_membership),
                 OuterConsensus::new(Arc::clone(&consensus.inner_consensus)),
-                public_key.clone(),
-                private_key.clone(),
+                Arc::clone(&signer),
                 &upgrade_lock,
                 epoch_height,
             )
@@ -347,17 +372,29 @@ pub(crate) async fn update_shared_state<
 
     let version = upgrade_lock.version(view_number).await?;
 
-    let (validated_state, state_delta) = parent_state
-        .validate_and_apply_header(
-            &instance_state,
-            &parent,
-            &proposed_leaf.block_header().clone(),
-            vid_share.data.common.clone(),
-            version,
-            *view_number,
-        )
+    // Applications may take a long time to validate and apply a block, so do it on the state
+    // workers rather than on the runtime threads which drive our tasks.
+    let proposed_header = proposed_leaf.block_header().clone();
+    let vid_common = vid_share.data.common.clone();
+    let validation = state_workers.spawn(async move {
+        parent_state
+            .validate_and_apply_header(
+                &instance_state,
+                &parent,
+                &proposed_header,
+                vid_common,
+                version,
+                *view_number,
+            )
+            .await
+    });
+    let (validated_state, state_delta) = validation
         .await
         .wrap()
+        .context(warn!(
+            "State workers shut down before the block header was validated"
+        ))?
+        .wrap()
         .context(warn!("Block header doesn't extend the proposal!"))?;
 
     let state = Arc::new(validated_state);
@@ -379,14 +416,8 @@ pub(crate) async fn update_shared_state<
     let new_state = consensus_writer.validated_state_map().clone();
     drop(consensus_writer);
 
-    // Send the new state up to the sequencer.
-    storage
-        .write()
-        .await
-        .update_undecided_state2(new_leaves, new_state)
-        .await
-        .wrap()
-        .context(error!("Failed to update undecided state"))?;
+    // Send the new state up to the sequencer, along with the rest of the view's writes.
+    writes.undecided_state = Some((new_leaves, new_state));
 
     Ok(())
 }
@@ -398,11 +429,12 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
     quorum_membership: Arc<TYPES::Membership>,
     public_key: TYPES::SignatureKey,
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     upgrade_lock: UpgradeLock<TYPES, V>,
     view_number: TYPES::View,
     epoch_height: u64,
     storage: Arc<RwLock<I::Storage>>,
+    mut writes: ViewWrites<TYPES>,
     leaf: Leaf2<TYPES>,
     vid_share: Proposal<TYPES, VidDisperseShare2<TYPES>>,
     extended_vote: bool,
@@ -411,9 +443,23 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
         leaf.block_header().block_number(),
         epoch_height,
     ));
+    let has_stake = quorum_membership.has_stake(&public_key, epoch_number);
+
+    // Persist everything for this view in one go, before any vote for it can leave this node. We
+    // only keep our VID share if we are going to vote.
+    if has_stake {
+        writes.vid_share = Some(vid_share);
+    }
+    storage
+        .write()
+        .await
+        .write_view(writes)
+        .await
+        .wrap()
+        .context(error!("Failed to store view"))?;
 
     ensure!(
-        quorum_membership.has_stake(&public_key, epoch_number),
+        has_stake,
         info!(
             "We were not chosen for quorum committee on {:?}",
             view_number
@@ -421,27 +467,18 @@ pub(crate) async fn submit_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     );
 
     // Create and send the vote.
-    let vote = QuorumVote2::<TYPES>::create_signed_vote(
+    let vote = QuorumVote2::<TYPES>::create_signed_vote_with_signer(
         QuorumData2 {
             leaf_commit: leaf.commit(),
             epoch: epoch_number,
         },
         view_number,
-        &public_key,
-        &private_key,
+        signer.as_ref(),
         &upgrade_lock,
     )
     .await
     .wrap()
     .context(error!("Failed to sign vote. This should never happen."))?;
-    // Add to the storage.
-    storage
-        .write()
-        .await
-        .append_vid2(&vid_share)
-        .await
-        .wrap()
-        .context(error!("Failed to store VID share"))?;
 
     if extended_vote {
         tracing::debug!("sending extended vote to everybody",);
@@ -17,16 +17,17 @@ use hotshot_task::{
     task::TaskState,
 };
 use hotshot_types::{
-    consensus::{ConsensusMetricsValue, OuterConsensus},
+    consensus::{CommitRule, ConsensusMetricsValue, OuterConsensus},
     data::{Leaf2, QuorumProposal2},
     event::Event,
     message::{Proposal, UpgradeLock},
+    participation::{JailConfig, ParticipationTracker},
     traits::{
         block_contents::BlockHeader,
         election::Membership,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
-        signature_key::SignatureKey,
-        storage::Storage,
+        signer::Signer,
+        storage::ViewWrites,
     },
     utils::epoch_from_block_number,
     vid::vid_scheme,
@@ -42,6 +43,7 @@ use crate::{
     events::HotShotEvent,
     helpers::broadcast_event,
     quorum_vote::handlers::{handle_quorum_proposal_validated, submit_vote, update_shared_state},
+    workers::WorkerPool,
 };
 
 /// Helper for DRB Computations
@@ -55,18 +57,26 @@ mod handlers;
 enum VoteDependency {
     /// For the `QuorumProposalValidated` event after validating `QuorumProposalRecv`.
     QuorumProposal,
-    /// For the `DaCertificateRecv` event.
-    Dac,
+    /// For the `DaCertificateValidated` event of the previous view, whose leaf we extend.
+    ParentDac,
     /// For the `VidShareRecv` event.
     Vid,
 }
 
+/// Whether we wait for the DAC of the parent of `proposal` before voting for it, which we do when
+/// it extends the previous view. Otherwise the proposal follows a view change, long after the DAC
+/// of its parent could form, and we rely on the DAC we saved, which is checked before voting.
+fn waits_for_parent_dac<TYPES: NodeType>(proposal: &QuorumProposal2<TYPES>) -> bool {
+    let parent_view = proposal.justify_qc.view_number;
+    parent_view != TYPES::View::genesis() && parent_view + 1 == proposal.view_number
+}
+
 /// Handler for the vote dependency.
 pub struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     /// Public key.
     pub public_key: TYPES::SignatureKey,
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our vote and our requests to the network.
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
     /// Immutable instance state
@@ -85,6 +95,8 @@ pub struct VoteDependencyHandle<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     pub upgrade_lock: UpgradeLock<TYPES, V>,
     /// The consensus metrics
     pub consensus_metrics: Arc<ConsensusMetricsValue>,
+    /// Threads to validate the proposed block header and apply it to the state on
+    pub state_workers: Arc<WorkerPool>,
     /// The node's id
     pub id: u64,
     /// Number of blocks in an epoch, zero means there are no epochs
@@ -103,6 +115,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
         let mut leaf = None;
         let mut vid_share = None;
         let mut parent_view_number = None;
+        let mut parent = None;
+        let mut writes = ViewWrites::default();
         for event in res {
             match event.as_ref() {
                 #[allow(unused_assignments)]
@@ -140,25 +154,11 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
                         tracing::warn!("Proposed leaf parent commitment does not match parent leaf payload commitment. Aborting vote.");
                         return;
                     }
-                    // Update our persistent storage of the proposal. If we cannot store the proposal return
-                    // and error so we don't vote
-                    if let Err(e) = self.storage.write().await.append_proposal2(proposal).await {
-                        tracing::error!("failed to store proposal, not voting.  error = {e:#}");
-                        return;
-                    }
+                    // Stored with the rest of the view's writes just before we vote
+                    writes.proposal = Some(proposal.clone());
                     leaf = Some(proposed_leaf);
                     parent_view_number = Some(parent_leaf.view_number());
-                }
-                HotShotEvent::DaCertificateValidated(cert) => {
-                    let cert_payload_comm = &cert.data().payload_commit;
-                    if let Some(ref comm) = payload_commitment {
-                        if cert_payload_comm != comm {
-                            tracing::error!("DAC has inconsistent payload commitment with quorum proposal or VID.");
-                            return;
-                        }
-                    } else {
-                        payload_commitment = Some(*cert_payload_comm);
-                    }
+                    parent = Some(Arc::clone(parent_leaf));
                 }
                 HotShotEvent::VidShareValidated(share) => {
                     let vid_payload_commitment = &share.data.payload_commitment;
@@ -192,18 +192,49 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
             return;
         };
 
+        // The DA vote for this view runs concurrently with ours, so the DAC is not a dependency.
+        // If it has already arrived, it must still agree with the proposal and VID share.
+        if let Some(cert) = self
+            .consensus
+            .read()
+            .await
+            .saved_da_certs()
+            .get(&self.view_number)
+        {
+            if Some(cert.data().payload_commit) != payload_commitment {
+                tracing::error!(
+                    "DAC has inconsistent payload commitment with quorum proposal or VID."
+                );
+                return;
+            }
+        }
+
+        // A QC does not show that the payload of its leaf is available, since DA and quorum votes
+        // are collected concurrently. Only extend a leaf whose DAC we have validated ourselves.
+        let parent_certified = match &parent {
+            Some(parent) => self.consensus.read().await.is_payload_certified(parent),
+            None => false,
+        };
+        if !parent_certified {
+            tracing::warn!(
+                "The leaf the proposal for view {:?} extends has no DA certificate. Not voting!",
+                self.view_number
+            );
+            return;
+        }
+
         // Update internal state
-        if let Err(e) = update_shared_state::<TYPES, I, V>(
+        if let Err(e) = update_shared_state::<TYPES, V>(
             OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
             self.sender.clone(),
             self.receiver.clone(),
             Arc::clone(&self.quorum_membership),
-            self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             self.upgrade_lock.clone(),
             self.view_number,
             Arc::clone(&self.instance_state),
-            Arc::clone(&self.storage),
+            Arc::clone(&self.state_workers),
+            &mut writes,
             &leaf,
             &vid_share,
             parent_view_number,
@@ -235,11 +266,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + 'static, V: Versions> Handl
             self.sender.clone(),
             Arc::clone(&self.quorum_membership),
             self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             self.upgrade_lock.clone(),
             self.view_number,
             self.epoch_height,
             Arc::clone(&self.storage),
+            writes,
             leaf,
             vid_share,
             false,
@@ -258,8 +290,8 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// Public key.
     pub public_key: TYPES::SignatureKey,
 
-    /// Private Key.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes and our requests to the network.
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Reference to consensus. The replica will require a write lock on this.
     pub consensus: OuterConsensus<TYPES>,
@@ -292,6 +324,12 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
     /// The consensus metrics
     pub consensus_metrics: Arc<ConsensusMetricsValue>,
 
+    /// Threads which validate proposed block headers and apply them to the state
+    pub state_workers: Arc<WorkerPool>,
+
+    /// Threads to check VID share signatures on
+    pub verification_workers: Arc<WorkerPool>,
+
     /// Reference to the storage.
     pub storage: Arc<RwLock<I::Storage>>,
 
@@ -300,6 +338,15 @@ pub struct QuorumVoteTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V:
 
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+
+    /// Decided certificates signed by each validator, by which offline validators are jailed
+    pub participation: ParticipationTracker<TYPES>,
+
+    /// When offline validators are jailed
+    pub jail_config: JailConfig,
+
+    /// The rule by which leaves are decided
+    pub commit_rule: CommitRule,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskState<TYPES, I, V> {
@@ -324,13 +371,15 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                             return false;
                         }
                     }
-                    VoteDependency::Dac => {
-                        if let HotShotEvent::DaCertificateValidated(cert) = event {
-                            cert.view_number
-                        } else {
-                            return false;
+                    VoteDependency::ParentDac => match event {
+                        HotShotEvent::DaCertificateValidated(cert) => cert.view_number + 1,
+                        HotShotEvent::QuorumProposalValidated(proposal, _)
+                            if !waits_for_parent_dac(&proposal.data) =>
+                        {
+                            proposal.data.view_number
                         }
-                    }
+                        _ => return false,
+                    },
                     VoteDependency::Vid => {
                         if let HotShotEvent::VidShareValidated(disperse) = event {
                             disperse.data.view_number
@@ -356,7 +405,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
     /// Create and store an [`AndDependency`] combining [`EventDependency`]s associated with the
     /// given view number if it doesn't exist.
     #[instrument(skip_all, fields(id = self.id, latest_voted_view = *self.latest_voted_view), name = "Quorum vote crete dependency task if new", level = "error")]
-    fn create_dependency_task_if_new(
+    async fn create_dependency_task_if_new(
         &mut self,
         view_number: TYPES::View,
         event_receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
@@ -372,18 +421,40 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             view_number,
             event_receiver.clone(),
         );
-        let dac_dependency =
-            self.create_event_dependency(VoteDependency::Dac, view_number, event_receiver.clone());
+        let mut parent_dac_dependency = self.create_event_dependency(
+            VoteDependency::ParentDac,
+            view_number,
+            event_receiver.clone(),
+        );
         let vid_dependency =
             self.create_event_dependency(VoteDependency::Vid, view_number, event_receiver.clone());
         // If we have an event provided to us
         if let Some(event) = event {
-            if let HotShotEvent::QuorumProposalValidated(..) = event.as_ref() {
+            if let HotShotEvent::QuorumProposalValidated(proposal, _) = event.as_ref() {
+                if !waits_for_parent_dac(&proposal.data) {
+                    parent_dac_dependency.mark_as_completed(Arc::clone(&event));
+                }
                 quorum_proposal_dependency.mark_as_completed(event);
             }
         }
+        // The DAC of the previous view may have been validated before this task started
+        let parent_view = TYPES::View::new(view_number.saturating_sub(1));
+        if let Some(cert) = self
+            .consensus
+            .read()
+            .await
+            .saved_da_certs()
+            .get(&parent_view)
+        {
+            parent_dac_dependency
+                .mark_as_completed(Arc::new(HotShotEvent::DaCertificateValidated(cert.clone())));
+        }
 
-        let deps = vec![quorum_proposal_dependency, dac_dependency, vid_dependency];
+        let deps = vec![
+            quorum_proposal_dependency,
+            parent_dac_dependency,
+            vid_dependency,
+        ];
 
         let dependency_chain = AndDependency::from_deps(deps);
 
@@ -391,7 +462,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             dependency_chain,
             VoteDependencyHandle::<TYPES, I, V> {
                 public_key: self.public_key.clone(),
-                private_key: self.private_key.clone(),
+                signer: Arc::clone(&self.signer),
                 consensus: OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
                 instance_state: Arc::clone(&self.instance_state),
                 quorum_membership: Arc::clone(&self.membership),
@@ -403,6 +474,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 id: self.id,
                 epoch_height: self.epoch_height,
                 consensus_metrics: Arc::clone(&self.consensus_metrics),
+                state_workers: Arc::clone(&self.state_workers),
             },
         );
         self.vote_dependencies
@@ -490,16 +562,18 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                         event_receiver,
                         &event_sender,
                         Some(Arc::clone(&event)),
-                    );
+                    )
+                    .await;
                 }
             }
             HotShotEvent::DaCertificateRecv(cert) => {
                 let view = cert.view_number;
 
                 tracing::trace!("Received DAC for view {}", *view);
-                // Do nothing if the DAC is old
+                // Do nothing if the DAC is old. The DAC for the view we last voted in may still
+                // arrive after our vote, since DA and quorum voting proceed in parallel.
                 ensure!(
-                    view > self.latest_voted_view,
+                    view >= self.latest_voted_view,
                     "Received DAC for an older view."
                 );
 
@@ -507,7 +581,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                 // Validate the DAC.
                 ensure!(
                     cert.is_valid_cert(
-                        self.membership.da_stake_table(cert_epoch),
+                        &self.membership.da_stake_table(cert_epoch),
                         self.membership.da_success_threshold(cert_epoch),
                         &self.upgrade_lock
                     )
@@ -526,7 +600,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                     &event_sender.clone(),
                 )
                 .await;
-                self.create_dependency_task_if_new(view, event_receiver, &event_sender, None);
             }
             HotShotEvent::VidShareRecv(sender, disperse) => {
                 let view = disperse.data.view_number();
@@ -543,7 +616,13 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
 
                 // Check that the signature is valid
                 ensure!(
-                    sender.validate(&disperse.signature, payload_commitment.as_ref()),
+                    self.verification_workers
+                        .verify(
+                            sender.clone(),
+                            disperse.signature.clone(),
+                            *payload_commitment
+                        )
+                        .await,
                     "VID share signature is invalid"
                 );
 
@@ -584,7 +663,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
                     &event_sender.clone(),
                 )
                 .await;
-                self.create_dependency_task_if_new(view, event_receiver, &event_sender, None);
+                self.create_dependency_task_if_new(view, event_receiver, &event_sender, None)
+                    .await;
             }
             HotShotEvent::Timeout(view, ..) => {
                 let view = TYPES::View::new(view.saturating_sub(1));
@@ -660,25 +740,24 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             tracing::warn!("Proposed leaf parent commitment does not match parent leaf payload commitment. Aborting vote.");
             return;
         }
-        // Update our persistent storage of the proposal. If we cannot store the proposal return
-        // and error so we don't vote
-        if let Err(e) = self.storage.write().await.append_proposal2(proposal).await {
-            tracing::error!("failed to store proposal, not voting.  error = {e:#}");
-            return;
-        }
+        // Stored with the rest of the view's writes just before we vote
+        let mut writes = ViewWrites {
+            proposal: Some(proposal.clone()),
+            ..ViewWrites::default()
+        };
 
         // Update internal state
-        if let Err(e) = update_shared_state::<TYPES, I, V>(
+        if let Err(e) = update_shared_state::<TYPES, V>(
             OuterConsensus::new(Arc::clone(&self.consensus.inner_consensus)),
             event_sender.clone(),
             event_receiver.clone().deactivate(),
             Arc::clone(&self.membership),
-            self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             self.upgrade_lock.clone(),
             proposal.data.view_number(),
             Arc::clone(&self.instance_state),
-            Arc::clone(&self.storage),
+            Arc::clone(&self.state_workers),
+            &mut writes,
             &proposed_leaf,
             &updated_vid,
             Some(parent_leaf.view_number()),
@@ -718,11 +797,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> QuorumVoteTaskS
             event_sender.clone(),
             Arc::clone(&self.membership),
             self.public_key.clone(),
-            self.private_key.clone(),
+            Arc::clone(&self.signer),
             self.upgrade_lock.clone(),
             proposal.data.view_number(),
             self.epoch_height,
             Arc::clone(&self.storage),
+            writes,
             proposed_leaf,
             updated_vid,
             is_vote_leaf_extended,
@@ -17,6 +17,7 @@ use async_broadcast::{Receiver, Sender};
 use async_trait::async_trait;
 use hotshot_task::{
     dependency::{Dependency, EventDependency},
+    spawn::spawn_named,
     task::TaskState,
 };
 use hotshot_types::{
@@ -27,6 +28,7 @@ use hotshot_types::{
         network::{ConnectedNetwork, DataRequest, RequestKind},
         node_implementation::{ConsensusTime, NodeImplementation, NodeType},
         signature_key::SignatureKey,
+        signer::Signer,
     },
     utils::epoch_from_block_number,
     vote::HasViewNumber,
@@ -34,7 +36,6 @@ use hotshot_types::{
 use rand::{seq::SliceRandom, thread_rng};
 use sha2::{Digest, Sha256};
 use tokio::{
-    spawn,
     task::JoinHandle,
     time::{sleep, timeout},
 };
@@ -65,8 +66,8 @@ pub struct NetworkRequestState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     pub membership: TYPES::Membership,
     /// This nodes public key
     pub public_key: TYPES::SignatureKey,
-    /// This nodes private/signing key, used to sign requests.
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs requests with this node's consensus key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// The node's id
     pub id: u64,
     /// A flag indicating that `HotShotEvent::Shutdown` has been received
@@ -113,7 +114,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
                         .vid_shares()
                         .contains_key(&prop_view)
                 {
-                    self.spawn_requests(prop_view, prop_epoch, sender, receiver);
+                    self.spawn_requests(prop_view, prop_epoch, sender, receiver)
+                        .await;
                 }
                 Ok(())
             }
@@ -145,7 +147,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> TaskState for NetworkRequest
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I> {
     /// Creates and signs the payload, then will create a request task
-    fn spawn_requests(
+    async fn spawn_requests(
         &mut self,
         view: TYPES::View,
         epoch: TYPES::Epoch,
@@ -155,7 +157,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
         let request = RequestKind::Vid(view, self.public_key.clone());
 
         // First sign the request for the VID shares.
-        if let Some(signature) = self.serialize_and_sign(&request) {
+        if let Some(signature) = self.serialize_and_sign(&request).await {
             self.create_vid_request_task(
                 request,
                 signature,
@@ -206,7 +208,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
             view,
             signature,
         };
-        let handle: JoinHandle<()> = spawn(async move {
+        let handle: JoinHandle<()> = spawn_named("data request", async move {
             // Do the delay only if primary is up and then start sending
             if !network.is_primary_down() {
                 sleep(delay).await;
@@ -370,16 +372,17 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> NetworkRequestState<TYPES, I
     }
 
     /// Sign the serialized version of the request
-    fn serialize_and_sign(&self, request: &RequestKind<TYPES>) -> Option<Signature<TYPES>> {
+    async fn serialize_and_sign(&self, request: &RequestKind<TYPES>) -> Option<Signature<TYPES>> {
         let Ok(data) = bincode::serialize(&request) else {
             tracing::error!("Failed to serialize request!");
             return None;
         };
-        let Ok(signature) = TYPES::SignatureKey::sign(&self.private_key, &Sha256::digest(data))
-        else {
-            tracing::error!("Failed to sign Data Request");
-            return None;
-        };
-        Some(signature)
+        match self.signer.sign(&Sha256::digest(data)).await {
+            Ok(signature) => Some(signature),
+            Err(err) => {
+                tracing::error!("Failed to sign Data Request: {err}");
+                None
+            }
+        }
     }
 }
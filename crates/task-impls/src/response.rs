@@ -8,17 +8,18 @@ use std::{sync::Arc, time::Duration};
 
 use async_broadcast::{Receiver, Sender};
 use committable::Committable;
+use hotshot_task::spawn::spawn_named;
 use hotshot_types::{
     consensus::{Consensus, LockedConsensusState, OuterConsensus},
     data::VidDisperseShare2,
     message::Proposal,
     traits::{
         election::Membership, network::DataRequest, node_implementation::NodeType,
-        signature_key::SignatureKey,
+        signature_key::SignatureKey, signer::Signer,
     },
 };
 use sha2::{Digest, Sha256};
-use tokio::{spawn, task::JoinHandle, time::sleep};
+use tokio::{task::JoinHandle, time::sleep};
 use tracing::instrument;
 
 use crate::{events::HotShotEvent, helpers::broadcast_event};
@@ -35,8 +36,8 @@ pub struct NetworkResponseState<TYPES: NodeType> {
     quorum: Arc<TYPES::Membership>,
     /// This replicas public key
     pub_key: TYPES::SignatureKey,
-    /// This replicas private key
-    private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs the VID shares we calculate
+    signer: Arc<dyn Signer<TYPES::SignatureKey>>,
     /// The node's id
     id: u64,
 }
@@ -47,14 +48,14 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
         consensus: LockedConsensusState<TYPES>,
         quorum: Arc<TYPES::Membership>,
         pub_key: TYPES::SignatureKey,
-        private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: Arc<dyn Signer<TYPES::SignatureKey>>,
         id: u64,
     ) -> Self {
         Self {
             consensus,
             quorum,
             pub_key,
-            private_key,
+            signer,
             id,
         }
     }
@@ -152,7 +153,7 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
             OuterConsensus::new(Arc::clone(&self.consensus)),
             view,
             Arc::clone(&self.quorum),
-            &self.private_key,
+            self.signer.as_ref(),
         )
         .await
         .is_none()
@@ -163,7 +164,7 @@ impl<TYPES: NodeType> NetworkResponseState<TYPES> {
                 OuterConsensus::new(Arc::clone(&self.consensus)),
                 view,
                 Arc::clone(&self.quorum),
-                &self.private_key,
+                self.signer.as_ref(),
             )
             .await?;
         }
@@ -202,5 +203,8 @@ pub fn run_response_task<TYPES: NodeType>(
     event_stream: Receiver<Arc<HotShotEvent<TYPES>>>,
     sender: Sender<Arc<HotShotEvent<TYPES>>>,
 ) -> JoinHandle<()> {
-    spawn(task_state.run_response_loop(event_stream, sender))
+    spawn_named(
+        "response",
+        task_state.run_response_loop(event_stream, sender),
+    )
 }
@@ -15,7 +15,9 @@ use futures::{future::join_all, stream::FuturesUnordered, StreamExt};
 use hotshot_builder_api::v0_1::block_info::AvailableBlockInfo;
 use hotshot_task::task::TaskState;
 use hotshot_types::{
+    block_limits::BlockLimits,
     consensus::OuterConsensus,
+    dag_mempool::DagMempoolConfig,
     data::{null_block, PackedBundle},
     event::{Event, EventType},
     message::UpgradeLock,
@@ -25,6 +27,7 @@ use hotshot_types::{
         election::Membership,
         node_implementation::{ConsensusTime, HasUrls, NodeImplementation, NodeType, Versions},
         signature_key::{BuilderSignatureKey, SignatureKey},
+        signer::Signer,
         BlockPayload,
     },
     utils::ViewInner,
@@ -102,8 +105,8 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our requests to builders with our consensus key
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// InstanceState
     pub instance_state: Arc<TYPES::InstanceState>,
@@ -119,6 +122,15 @@ pub struct TransactionTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>, V
 
     /// fallback builder url
     pub fallback_builder_url: Url,
+
+    /// Resource limits the blocks we propose must respect
+    pub block_limits: BlockLimits,
+
+    /// DAG mempool settings; when it is enabled, the DAG mempool task builds our blocks instead
+    pub dag_mempool: DagMempoolConfig,
+
+    /// The view and epoch of the last block we started building before entering its view
+    pub prefetched: Option<(TYPES::View, TYPES::Epoch)>,
 }
 
 impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTaskState<TYPES, I, V> {
@@ -478,6 +490,27 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 )
                 .await;
             }
+            HotShotEvent::QuorumProposalPreliminarilyValidated(proposal) => {
+                // Build the block for the next view while this one is still voting, so that only
+                // the justify QC is left once the quorum certificate forms.
+                let view = proposal.data.view_number() + 1;
+                ensure!(
+                    view > self.cur_view
+                        && self
+                            .prefetched
+                            .map_or(true, |(prefetched, _)| prefetched < view),
+                    debug!("Already building a block for view {view:?}")
+                );
+
+                if !self.dag_mempool.enabled
+                    && self.membership.leader(view, self.cur_epoch)? == self.public_key
+                {
+                    tracing::debug!("Building our block for view {view:?} ahead of time");
+                    self.prefetched = Some((view, self.cur_epoch));
+                    self.handle_view_change(&event_stream, view, self.cur_epoch)
+                        .await;
+                }
+            }
             HotShotEvent::ViewChange(view, epoch) => {
                 let view = TYPES::View::new(std::cmp::max(1, **view));
 
@@ -492,7 +525,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 self.cur_view = view;
                 self.cur_epoch = *epoch;
 
-                if self.membership.leader(view, *epoch)? == self.public_key {
+                if !self.dag_mempool.enabled
+                    && self.prefetched != Some((view, *epoch))
+                    && self.membership.leader(view, *epoch)? == self.public_key
+                {
                     self.handle_view_change(&event_stream, view, *epoch).await;
                     return Ok(());
                 }
@@ -579,10 +615,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
             }
         };
 
-        let parent_comm_sig = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-            &self.private_key,
-            parent_comm.as_ref(),
-        ) {
+        let parent_comm_sig = match self.signer.sign(parent_comm.as_ref()).await {
             Ok(sig) => sig,
             Err(err) => {
                 tracing::error!(%err, "Failed to sign block hash");
@@ -728,6 +761,12 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
         };
 
         for (block_info, builder_idx) in available_blocks {
+            // Don't bother claiming a block which is advertised as too large.
+            if let Err(err) = self.block_limits.check_size(block_info.block_size) {
+                tracing::warn!(%err, "Skipping available block");
+                continue;
+            }
+
             // Verify signature over chosen block.
             if !block_info.sender.validate_block_info_signature(
                 &block_info.signature,
@@ -739,10 +778,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                 continue;
             }
 
-            let request_signature = match <<TYPES as NodeType>::SignatureKey as SignatureKey>::sign(
-                &self.private_key,
-                block_info.block_hash.as_ref(),
-            ) {
+            let request_signature = match self.signer.sign(block_info.block_hash.as_ref()).await {
                 Ok(request_signature) => request_signature,
                 Err(err) => {
                     tracing::error!(%err, "Failed to sign block hash");
@@ -799,6 +835,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> TransactionTask
                     continue;
                 }
 
+                // The builder may have under-reported the block size, so check the claimed
+                // payload itself before proposing it.
+                if let Err(err) = self
+                    .block_limits
+                    .check::<TYPES>(&block_data.block_payload, &block_data.metadata)
+                {
+                    tracing::warn!(%err, "Claimed block violates the block limits");
+                    continue;
+                }
+
                 let fee = BuilderFee {
                     fee_amount: block_info.offered_fee,
                     fee_account: header_input.sender,
@@ -23,7 +23,7 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
     },
     vote::HasViewNumber,
 };
@@ -35,6 +35,7 @@ use crate::{
     events::HotShotEvent,
     helpers::broadcast_event,
     vote_collection::{handle_vote, VoteCollectorsMap},
+    workers::WorkerPool,
 };
 
 /// Tracks state of an upgrade task
@@ -57,8 +58,8 @@ pub struct UpgradeTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes public key
     pub public_key: TYPES::SignatureKey,
 
-    /// This Nodes private key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes and proposals
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// This state's ID
     pub id: u64,
@@ -89,6 +90,9 @@ pub struct UpgradeTaskState<TYPES: NodeType, V: Versions> {
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Threads to check vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
 }
 
 impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
@@ -196,7 +200,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                         view_number: self.cur_view,
                         event: EventType::UpgradeProposal {
                             proposal: proposal.clone(),
-                            sender: sender.clone(),
+                            sender: sender.clone().into(),
                         },
                     },
                     &self.output_event_stream,
@@ -204,11 +208,10 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                 .await;
 
                 // If everything is fine up to here, we generate and send a vote on the proposal.
-                let vote = UpgradeVote::create_signed_vote(
+                let vote = UpgradeVote::create_signed_vote_with_signer(
                     proposal.data.upgrade_proposal.clone(),
                     view,
-                    &self.public_key,
-                    &self.private_key,
+                    self.signer.as_ref(),
                     &self.upgrade_lock,
                 )
                 .await?;
@@ -243,6 +246,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                     &event,
                     &tx,
                     &self.upgrade_lock,
+                    &self.verification_workers,
                     true,
                 )
                 .await?;
@@ -289,11 +293,12 @@ impl<TYPES: NodeType, V: Versions> UpgradeTaskState<TYPES, V> {
                         view_number: TYPES::View::new(view + UPGRADE_PROPOSE_OFFSET),
                     };
 
-                    let signature = TYPES::SignatureKey::sign(
-                        &self.private_key,
-                        upgrade_proposal_data.commit().as_ref(),
-                    )
-                    .expect("Failed to sign upgrade proposal commitment!");
+                    let signature = self
+                        .signer
+                        .sign(upgrade_proposal_data.commit().as_ref())
+                        .await
+                        .wrap()
+                        .context(warn!("Failed to sign upgrade proposal commitment"))?;
 
                     tracing::warn!("Sending upgrade proposal:\n\n {:?}", upgrade_proposal);
 
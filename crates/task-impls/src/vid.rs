@@ -16,7 +16,7 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{NodeImplementation, NodeType},
-        signature_key::SignatureKey,
+        signer::Signer,
         BlockPayload,
     },
 };
@@ -48,8 +48,8 @@ pub struct VidTaskState<TYPES: NodeType, I: NodeImplementation<TYPES>> {
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our VID dispersals
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// This state's ID
     pub id: u64,
@@ -93,12 +93,25 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 )
                 .await;
                 let payload_commitment = vid_disperse.payload_commitment;
+                // The dispersal and each of its shares are signed over the payload commitment
+                let signature = match self.signer.sign(payload_commitment.as_ref()).await {
+                    Ok(signature) => signature,
+                    Err(err) => {
+                        error!("VID: failed to sign dispersal payload: {err}");
+                        return None;
+                    }
+                };
                 let shares = VidDisperseShare2::from_vid_disperse(vid_disperse.clone());
                 let mut consensus_writer = self.consensus.write().await;
                 for share in shares {
-                    if let Some(disperse) = share.to_proposal(&self.private_key) {
-                        consensus_writer.update_vid_shares(*view_number, disperse);
-                    }
+                    consensus_writer.update_vid_shares(
+                        *view_number,
+                        Proposal {
+                            signature: signature.clone(),
+                            data: share,
+                            _pd: PhantomData,
+                        },
+                    );
                 }
                 drop(consensus_writer);
 
@@ -117,13 +130,6 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>> VidTaskState<TYPES, I> {
                 .await;
 
                 let view_number = *view_number;
-                let Ok(signature) = TYPES::SignatureKey::sign(
-                    &self.private_key,
-                    vid_disperse.payload_commitment.as_ref(),
-                ) else {
-                    error!("VID: failed to sign dispersal payload");
-                    return None;
-                };
                 debug!("publishing VID disperse for view {}", *view_number);
                 broadcast_event(
                     Arc::new(HotShotEvent::VidDisperseSend(
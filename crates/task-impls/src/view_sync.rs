@@ -14,7 +14,7 @@ use std::{
 use async_broadcast::{Receiver, Sender};
 use async_lock::RwLock;
 use async_trait::async_trait;
-use hotshot_task::task::TaskState;
+use hotshot_task::{spawn::spawn_named, task::TaskState};
 use hotshot_types::{
     message::UpgradeLock,
     simple_certificate::{
@@ -27,11 +27,11 @@ use hotshot_types::{
     traits::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType, Versions},
-        signature_key::SignatureKey,
+        signer::Signer,
     },
     vote::{Certificate, HasViewNumber, Vote},
 };
-use tokio::{spawn, task::JoinHandle, time::sleep};
+use tokio::{task::JoinHandle, time::sleep};
 use tracing::instrument;
 use utils::anytrace::*;
 
@@ -41,6 +41,7 @@ use crate::{
     vote_collection::{
         create_vote_accumulator, AccumulatorInfo, HandleVoteEvent, VoteCollectionTaskState,
     },
+    workers::WorkerPool,
 };
 #[derive(PartialEq, PartialOrd, Clone, Debug, Eq, Hash)]
 /// Phases of view sync
@@ -78,8 +79,8 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Our node id; for logging
     pub id: u64,
@@ -112,6 +113,9 @@ pub struct ViewSyncTaskState<TYPES: NodeType, V: Versions> {
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Threads to check vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
 }
 
 #[async_trait]
@@ -165,8 +169,8 @@ pub struct ViewSyncReplicaTaskState<TYPES: NodeType, V: Versions> {
     /// This Nodes Public Key
     pub public_key: TYPES::SignatureKey,
 
-    /// Our Private Key
-    pub private_key: <TYPES::SignatureKey as SignatureKey>::PrivateKey,
+    /// Signs our votes
+    pub signer: Arc<dyn Signer<TYPES::SignatureKey>>,
 
     /// Lock for a decided upgrade
     pub upgrade_lock: UpgradeLock<TYPES, V>,
@@ -236,7 +240,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
             timeout_task: None,
             membership: Arc::clone(&self.membership),
             public_key: self.public_key.clone(),
-            private_key: self.private_key.clone(),
+            signer: Arc::clone(&self.signer),
             view_sync_timeout: self.view_sync_timeout,
             id: self.id,
             upgrade_lock: self.upgrade_lock.clone(),
@@ -320,6 +324,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     view: vote_view,
                     epoch: self.cur_epoch,
                     id: self.id,
+                    verification_workers: Arc::clone(&self.verification_workers),
                 };
                 let vote_collector = create_vote_accumulator(
                     &info,
@@ -365,6 +370,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     view: vote_view,
                     epoch: self.cur_epoch,
                     id: self.id,
+                    verification_workers: Arc::clone(&self.verification_workers),
                 };
 
                 let vote_collector = create_vote_accumulator(
@@ -410,6 +416,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncTaskState<TYPES, V> {
                     view: vote_view,
                     epoch: self.cur_epoch,
                     id: self.id,
+                    verification_workers: Arc::clone(&self.verification_workers),
                 };
                 let vote_collector = create_vote_accumulator(
                     &info,
@@ -534,7 +541,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 // If certificate is not valid, return current state
                 if !certificate
                     .is_valid_cert(
-                        self.membership.stake_table(self.cur_epoch),
+                        &self.membership.stake_table(self.cur_epoch),
                         self.membership.failure_threshold(self.cur_epoch),
                         &self.upgrade_lock,
                     )
@@ -555,15 +562,14 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     self.relay = certificate.data().relay;
                 }
 
-                let Ok(vote) = ViewSyncCommitVote2::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncCommitVote2::<TYPES>::create_signed_vote_with_signer(
                     ViewSyncCommitData2 {
                         relay: certificate.data().relay,
                         round: self.next_view,
                         epoch: certificate.data().epoch,
                     },
                     self.next_view,
-                    &self.public_key,
-                    &self.private_key,
+                    self.signer.as_ref(),
                     &self.upgrade_lock,
                 )
                 .await
@@ -582,7 +588,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     timeout_task.abort();
                 }
 
-                self.timeout_task = Some(spawn({
+                self.timeout_task = Some(spawn_named("view sync timeout", {
                     let stream = event_stream.clone();
                     let phase = last_seen_certificate;
                     let relay = self.relay;
@@ -618,7 +624,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 // If certificate is not valid, return current state
                 if !certificate
                     .is_valid_cert(
-                        self.membership.stake_table(self.cur_epoch),
+                        &self.membership.stake_table(self.cur_epoch),
                         self.membership.success_threshold(self.cur_epoch),
                         &self.upgrade_lock,
                     )
@@ -639,15 +645,14 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     self.relay = certificate.data().relay;
                 }
 
-                let Ok(vote) = ViewSyncFinalizeVote2::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncFinalizeVote2::<TYPES>::create_signed_vote_with_signer(
                     ViewSyncFinalizeData2 {
                         relay: certificate.data().relay,
                         round: self.next_view,
                         epoch: certificate.data().epoch,
                     },
                     self.next_view,
-                    &self.public_key,
-                    &self.private_key,
+                    self.signer.as_ref(),
                     &self.upgrade_lock,
                 )
                 .await
@@ -677,7 +682,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 if let Some(timeout_task) = self.timeout_task.take() {
                     timeout_task.abort();
                 }
-                self.timeout_task = Some(spawn({
+                self.timeout_task = Some(spawn_named("view sync timeout", {
                     let stream = event_stream.clone();
                     let phase = last_seen_certificate;
                     let relay = self.relay;
@@ -713,7 +718,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 // If certificate is not valid, return current state
                 if !certificate
                     .is_valid_cert(
-                        self.membership.stake_table(self.cur_epoch),
+                        &self.membership.stake_table(self.cur_epoch),
                         self.membership.success_threshold(self.cur_epoch),
                         &self.upgrade_lock,
                     )
@@ -755,15 +760,14 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 }
 
                 let epoch = self.cur_epoch;
-                let Ok(vote) = ViewSyncPreCommitVote2::<TYPES>::create_signed_vote(
+                let Ok(vote) = ViewSyncPreCommitVote2::<TYPES>::create_signed_vote_with_signer(
                     ViewSyncPreCommitData2 {
                         relay: 0,
                         round: view_number,
                         epoch,
                     },
                     view_number,
-                    &self.public_key,
-                    &self.private_key,
+                    self.signer.as_ref(),
                     &self.upgrade_lock,
                 )
                 .await
@@ -778,7 +782,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                 )
                 .await;
 
-                self.timeout_task = Some(spawn({
+                self.timeout_task = Some(spawn_named("view sync timeout", {
                     let stream = event_stream.clone();
                     let relay = self.relay;
                     let next_view = self.next_view;
@@ -811,18 +815,18 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                     self.relay += 1;
                     match last_seen_certificate {
                         ViewSyncPhase::None | ViewSyncPhase::PreCommit | ViewSyncPhase::Commit => {
-                            let Ok(vote) = ViewSyncPreCommitVote2::<TYPES>::create_signed_vote(
-                                ViewSyncPreCommitData2 {
-                                    relay: self.relay,
-                                    round: self.next_view,
-                                    epoch: self.cur_epoch,
-                                },
-                                self.next_view,
-                                &self.public_key,
-                                &self.private_key,
-                                &self.upgrade_lock,
-                            )
-                            .await
+                            let Ok(vote) =
+                                ViewSyncPreCommitVote2::<TYPES>::create_signed_vote_with_signer(
+                                    ViewSyncPreCommitData2 {
+                                        relay: self.relay,
+                                        round: self.next_view,
+                                        epoch: self.cur_epoch,
+                                    },
+                                    self.next_view,
+                                    self.signer.as_ref(),
+                                    &self.upgrade_lock,
+                                )
+                                .await
                             else {
                                 tracing::error!("Failed to sign ViewSyncPreCommitData!");
                                 return None;
@@ -840,7 +844,7 @@ impl<TYPES: NodeType, V: Versions> ViewSyncReplicaTaskState<TYPES, V> {
                         }
                     }
 
-                    self.timeout_task = Some(spawn({
+                    self.timeout_task = Some(spawn_named("view sync timeout", {
                         let stream = event_stream.clone();
                         let relay = self.relay;
                         let next_view = self.next_view;
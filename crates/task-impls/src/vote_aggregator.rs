@@ -0,0 +1,267 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use async_broadcast::{Receiver, Sender};
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use hotshot_task::{spawn::spawn_named, task::TaskState};
+use hotshot_types::{
+    message::UpgradeLock,
+    simple_vote::{QuorumData2, QuorumVote2, VersionedVoteData},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::{HasViewNumber, Vote},
+    vote_aggregation::{AggregationConfig, PartialCertificate},
+};
+use tokio::{task::JoinHandle, time::sleep};
+use utils::anytrace::*;
+
+use crate::{events::HotShotEvent, helpers::broadcast_event, workers::WorkerPool};
+
+/// Signatures of the votes for the same data, keyed by the position of their signers in the
+/// stake table
+type VoteBatch<TYPES> = (
+    QuorumData2<TYPES>,
+    BTreeMap<
+        usize,
+        <<TYPES as NodeType>::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    >,
+);
+
+/// The quorum votes of a view which we aggregate.
+pub struct PendingVotes<TYPES: NodeType, V: Versions> {
+    /// Positions in the stake table of the voters whose votes we checked, forwarded or not
+    pub voters: HashSet<usize>,
+
+    /// Votes we have not forwarded yet, by the commitment of the data they sign
+    pub batches:
+        HashMap<Commitment<VersionedVoteData<TYPES, QuorumData2<TYPES>, V>>, VoteBatch<TYPES>>,
+
+    /// Task which forwards the votes we have once we have waited long enough for the rest
+    pub flush_task: Option<JoinHandle<()>>,
+}
+
+impl<TYPES: NodeType, V: Versions> Default for PendingVotes<TYPES, V> {
+    fn default() -> Self {
+        Self {
+            voters: HashSet::new(),
+            batches: HashMap::new(),
+            flush_task: None,
+        }
+    }
+}
+
+/// Checks the quorum votes of the voters assigned to this node, and forwards them to the leader of
+/// the next view in partial certificates.
+pub struct VoteAggregatorTaskState<TYPES: NodeType, V: Versions> {
+    /// This node's public key
+    pub public_key: TYPES::SignatureKey,
+
+    /// Membership for the quorum, whose votes we aggregate
+    pub membership: Arc<TYPES::Membership>,
+
+    /// Lock for a decided upgrade
+    pub upgrade_lock: UpgradeLock<TYPES, V>,
+
+    /// Which votes we aggregate, and how long we wait for them
+    pub config: AggregationConfig,
+
+    /// The current view
+    pub cur_view: TYPES::View,
+
+    /// The current epoch
+    pub cur_epoch: TYPES::Epoch,
+
+    /// Votes of each recent view
+    pub pending: BTreeMap<TYPES::View, PendingVotes<TYPES, V>>,
+
+    /// Pool of workers which check vote signatures
+    pub verification_workers: Arc<WorkerPool>,
+
+    /// This node's id
+    pub id: u64,
+}
+
+impl<TYPES: NodeType, V: Versions> VoteAggregatorTaskState<TYPES, V> {
+    /// Check `vote` and hold on to it until the rest of our voters' votes arrive, or until the
+    /// flush delay passes.
+    async fn aggregate(
+        &mut self,
+        vote: &QuorumVote2<TYPES>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let view = vote.view_number();
+        let epoch = self.cur_epoch;
+        ensure!(
+            view.u64().saturating_add(1) >= self.cur_view.u64()
+                && view.u64() <= self.cur_view.u64().saturating_add(1),
+            debug!("Received a quorum vote for a stale or future view")
+        );
+        ensure!(
+            self.config
+                .aggregator_for::<TYPES>(&vote.signing_key(), &self.membership, view, epoch)
+                .is_some_and(|aggregator| aggregator == self.public_key),
+            debug!("Received a quorum vote we do not aggregate")
+        );
+        // The leader counts the votes it receives itself, so it never forwards them
+        ensure!(
+            self.membership.leader(view + 1, epoch)? != self.public_key,
+            debug!(
+                "We lead view {:?}, so we do not aggregate its votes",
+                view + 1
+            )
+        );
+
+        let stake_table = self.membership.stake_table(epoch);
+        let index = stake_table
+            .iter()
+            .position(|entry| TYPES::SignatureKey::public_key(entry) == vote.signing_key())
+            .context(warn!("Received a quorum vote from a node without stake"))?;
+        ensure!(
+            !self
+                .pending
+                .get(&view)
+                .is_some_and(|pending| pending.voters.contains(&index)),
+            debug!("Received a duplicate quorum vote for view {view:?}")
+        );
+
+        let vote_commitment = VersionedVoteData::new(vote.date().clone(), view, &self.upgrade_lock)
+            .await?
+            .commit();
+        ensure!(
+            self.verification_workers
+                .verify(vote.signing_key(), vote.signature(), vote_commitment)
+                .await,
+            warn!("Received an invalid quorum vote for view {view:?}")
+        );
+
+        let pending = self.pending.entry(view).or_default();
+        pending.voters.insert(index);
+        pending
+            .batches
+            .entry(vote_commitment)
+            .or_insert_with(|| (vote.date().clone(), BTreeMap::new()))
+            .1
+            .insert(index, vote.signature());
+
+        let expected =
+            self.config
+                .voters_of::<TYPES>(&self.public_key, &self.membership, view, epoch);
+        if pending.voters.len() >= expected {
+            return self.flush(view, event_stream).await;
+        }
+
+        if pending.flush_task.is_none() {
+            let delay = self.config.flush_delay;
+            let stream = event_stream.clone();
+            pending.flush_task = Some(spawn_named("aggregation flush", async move {
+                sleep(delay).await;
+                broadcast_event(Arc::new(HotShotEvent::AggregationTimeout(view)), &stream).await;
+            }));
+        }
+
+        Ok(())
+    }
+
+    /// Forward the votes of `view` we have not forwarded yet to the leader of the next view.
+    async fn flush(
+        &mut self,
+        view: TYPES::View,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        let Some(pending) = self.pending.get_mut(&view) else {
+            return Ok(());
+        };
+        if let Some(flush_task) = pending.flush_task.take() {
+            flush_task.abort();
+        }
+
+        let leader = self.membership.leader(view + 1, self.cur_epoch)?;
+        let total_nodes = self.membership.total_nodes(self.cur_epoch);
+        for (_, (data, votes)) in pending.batches.drain() {
+            tracing::debug!(
+                "Forwarding {} quorum votes for view {view:?} to {leader:?}",
+                votes.len()
+            );
+            broadcast_event(
+                Arc::new(HotShotEvent::PartialCertificateSend(
+                    PartialCertificate::new(data, view, total_nodes, &votes),
+                    leader.clone(),
+                    self.public_key.clone(),
+                )),
+                event_stream,
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
+    /// Handles an event.
+    ///
+    /// # Errors
+    /// If the event is invalid or we fail to act on it.
+    pub async fn handle(
+        &mut self,
+        event: Arc<HotShotEvent<TYPES>>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<()> {
+        match event.as_ref() {
+            HotShotEvent::QuorumVoteRecv(vote) => self.aggregate(vote, event_stream).await?,
+            HotShotEvent::AggregationTimeout(view) => self.flush(*view, event_stream).await?,
+            HotShotEvent::ViewChange(view, epoch) => {
+                ensure!(
+                    *view > self.cur_view,
+                    debug!("View change to an older view")
+                );
+                self.cur_view = *view;
+                self.cur_epoch = *epoch;
+
+                // Votes of older views arrive too late to form a certificate
+                let oldest = TYPES::View::new(view.u64().saturating_sub(1));
+                let current = self.pending.split_off(&oldest);
+                for (_, pending) in std::mem::replace(&mut self.pending, current) {
+                    if let Some(flush_task) = pending.flush_task {
+                        flush_task.abort();
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, V: Versions> TaskState for VoteAggregatorTaskState<TYPES, V> {
+    type Event = HotShotEvent<TYPES>;
+
+    async fn handle_event(
+        &mut self,
+        event: Arc<Self::Event>,
+        sender: &Sender<Arc<Self::Event>>,
+        _receiver: &Receiver<Arc<Self::Event>>,
+    ) -> Result<()> {
+        self.handle(event, sender).await
+    }
+
+    fn cancel_subtasks(&mut self) {
+        for (_, pending) in std::mem::take(&mut self.pending) {
+            if let Some(flush_task) = pending.flush_task {
+                flush_task.abort();
+            }
+        }
+    }
+}
@@ -5,14 +5,14 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 use std::{
-    collections::{btree_map::Entry, BTreeMap, HashMap},
+    collections::{btree_map::Entry, BTreeMap},
     fmt::Debug,
-    marker::PhantomData,
     sync::Arc,
 };
 
 use async_broadcast::Sender;
 use async_trait::async_trait;
+use committable::Committable;
 use either::Either::{self, Left, Right};
 use hotshot_types::{
     message::UpgradeLock,
@@ -22,18 +22,19 @@ use hotshot_types::{
         ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
-        DaVote2, QuorumVote, QuorumVote2, TimeoutVote2, UpgradeVote, ViewSyncCommitVote2,
-        ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
+        DaVote2, QuorumVote, QuorumVote2, TimeoutVote2, UpgradeVote, VersionedVoteData,
+        ViewSyncCommitVote2, ViewSyncFinalizeVote2, ViewSyncPreCommitVote2,
     },
     traits::{
         election::Membership,
         node_implementation::{NodeType, Versions},
     },
     vote::{Certificate, HasViewNumber, Vote, VoteAccumulator},
+    vote_aggregation::{signatures_are_valid, PartialCertificate},
 };
 use utils::anytrace::*;
 
-use crate::{events::HotShotEvent, helpers::broadcast_event};
+use crate::{events::HotShotEvent, helpers::broadcast_event, workers::WorkerPool};
 
 /// Alias for a map of Vote Collectors
 pub type VoteCollectorsMap<TYPES, VOTE, CERT, V> =
@@ -66,6 +67,9 @@ pub struct VoteCollectionTaskState<
 
     /// Whether we should check if we are the leader when handling a vote
     pub check_if_leader: bool,
+
+    /// Threads to check vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
 }
 
 /// Describes the functions a vote must implement for it to be aggregatable by the generic vote collection task
@@ -126,25 +130,103 @@ impl<
             "No accumulator to handle vote with. This shouldn't happen."
         ))?;
 
-        match accumulator
-            .accumulate(vote, &self.membership, self.epoch)
-            .await
-        {
+        let vote_commitment = VersionedVoteData::new(
+            vote.date().clone(),
+            vote.view_number(),
+            &accumulator.upgrade_lock,
+        )
+        .await?
+        .commit();
+
+        // Check the signature on the verification workers, where it can run alongside the checks
+        // for other tasks instead of holding up a runtime thread.
+        ensure!(
+            self.verification_workers
+                .verify(vote.signing_key(), vote.signature(), vote_commitment)
+                .await,
+            error!("Invalid vote! Vote Data {:?}", vote.date())
+        );
+
+        match accumulator.accumulate_verified(vote, vote_commitment, &self.membership, self.epoch) {
             Either::Left(()) => Ok(None),
-            Either::Right(cert) => {
-                tracing::debug!("Certificate Formed! {:?}", cert);
+            Either::Right(cert) => Ok(Some(self.certificate_formed(cert, event_stream).await)),
+        }
+    }
 
-                broadcast_event(
-                    Arc::new(VOTE::make_cert_event(cert.clone(), &self.public_key)),
-                    event_stream,
-                )
-                .await;
-                self.accumulator = None;
+    /// Take the votes an aggregator forwarded and accumulate them. Returns the cert if they
+    /// complete it.
+    ///
+    /// # Errors
+    /// If the partial certificate is for another view or its signatures are invalid
+    pub async fn accumulate_partial(
+        &mut self,
+        partial: &PartialCertificate<TYPES, VOTE::Commitment>,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> Result<Option<CERT>> {
+        ensure!(
+            partial.view_number() == self.view,
+            error!(
+                "Partial certificate view {} does not match the view {} we collect votes for",
+                *partial.view_number(),
+                *self.view
+            )
+        );
 
-                Ok(Some(cert))
-            }
+        let accumulator = self.accumulator.as_mut().context(warn!(
+            "No accumulator to handle partial certificate with. This shouldn't happen."
+        ))?;
+
+        let vote_commitment = VersionedVoteData::new(
+            partial.data.clone(),
+            partial.view_number(),
+            &accumulator.upgrade_lock,
+        )
+        .await?
+        .commit();
+
+        // All the signatures are checked at once, on the verification workers
+        let stake_table = CERT::stake_table(&self.membership, self.epoch).into_owned();
+        let signers = partial.signers.clone();
+        let signatures = partial.signatures.clone();
+        ensure!(
+            self.verification_workers
+                .spawn_blocking(move || {
+                    signatures_are_valid::<TYPES>(
+                        &stake_table,
+                        &signers,
+                        &signatures,
+                        vote_commitment.as_ref(),
+                    )
+                })
+                .await
+                .unwrap_or(false),
+            warn!("Invalid partial certificate for view {:?}", self.view)
+        );
+
+        match accumulator.accumulate_partial(partial, vote_commitment, &self.membership, self.epoch)
+        {
+            Either::Left(()) => Ok(None),
+            Either::Right(cert) => Ok(Some(self.certificate_formed(cert, event_stream).await)),
         }
     }
+
+    /// Announce `cert` and stop accumulating votes.
+    async fn certificate_formed(
+        &mut self,
+        cert: CERT,
+        event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    ) -> CERT {
+        tracing::debug!("Certificate Formed! {:?}", cert);
+
+        broadcast_event(
+            Arc::new(VOTE::make_cert_event(cert.clone(), &self.public_key)),
+            event_stream,
+        )
+        .await;
+        self.accumulator = None;
+
+        cert
+    }
 }
 
 /// Trait for types which will handle a vote event.
@@ -181,6 +263,8 @@ pub struct AccumulatorInfo<TYPES: NodeType> {
     pub epoch: TYPES::Epoch,
     /// This nodes id
     pub id: u64,
+    /// Threads to check vote signatures on
+    pub verification_workers: Arc<WorkerPool>,
 }
 
 /// Generic function for spawning a vote task.  Returns the event stream id of the spawned task if created
@@ -212,12 +296,7 @@ where
     V: Versions,
     VoteCollectionTaskState<TYPES, VOTE, CERT, V>: HandleVoteEvent<TYPES, VOTE, CERT>,
 {
-    let new_accumulator = VoteAccumulator {
-        vote_outcomes: HashMap::new(),
-        signers: HashMap::new(),
-        phantom: PhantomData,
-        upgrade_lock,
-    };
+    let new_accumulator = VoteAccumulator::new(upgrade_lock);
 
     let mut state = VoteCollectionTaskState::<TYPES, VOTE, CERT, V> {
         membership: Arc::clone(&info.membership),
@@ -227,6 +306,7 @@ where
         epoch: info.epoch,
         id: info.id,
         check_if_leader,
+        verification_workers: Arc::clone(&info.verification_workers),
     };
 
     state.handle_vote_event(Arc::clone(&event), sender).await?;
@@ -258,20 +338,69 @@ pub async fn handle_vote<
     event: &Arc<HotShotEvent<TYPES>>,
     event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
     upgrade_lock: &UpgradeLock<TYPES, V>,
+    verification_workers: &Arc<WorkerPool>,
+    check_if_leader: bool,
+) -> Result<()>
+where
+    VoteCollectionTaskState<TYPES, VOTE, CERT, V>: HandleVoteEvent<TYPES, VOTE, CERT>,
+{
+    handle_vote_event_for_view(
+        collectors,
+        vote.view_number(),
+        public_key,
+        membership,
+        epoch,
+        id,
+        event,
+        event_stream,
+        upgrade_lock,
+        verification_workers,
+        check_if_leader,
+    )
+    .await
+}
+
+/// Hands an event with votes for `view` to the collector of the view, starting one if it is the
+/// first event for the view.
+///
+/// # Errors
+/// If we fail to handle the event
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_vote_event_for_view<
+    TYPES: NodeType,
+    VOTE: Vote<TYPES> + AggregatableVote<TYPES, VOTE, CERT> + Send + Sync + 'static,
+    CERT: Certificate<TYPES, VOTE::Commitment, Voteable = VOTE::Commitment>
+        + Debug
+        + Send
+        + Sync
+        + 'static,
+    V: Versions,
+>(
+    collectors: &mut VoteCollectorsMap<TYPES, VOTE, CERT, V>,
+    view: TYPES::View,
+    public_key: TYPES::SignatureKey,
+    membership: &Arc<TYPES::Membership>,
+    epoch: TYPES::Epoch,
+    id: u64,
+    event: &Arc<HotShotEvent<TYPES>>,
+    event_stream: &Sender<Arc<HotShotEvent<TYPES>>>,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+    verification_workers: &Arc<WorkerPool>,
     check_if_leader: bool,
 ) -> Result<()>
 where
     VoteCollectionTaskState<TYPES, VOTE, CERT, V>: HandleVoteEvent<TYPES, VOTE, CERT>,
 {
-    match collectors.entry(vote.view_number()) {
+    match collectors.entry(view) {
         Entry::Vacant(entry) => {
-            tracing::debug!("Starting vote handle for view {:?}", vote.view_number());
+            tracing::debug!("Starting vote handle for view {:?}", view);
             let info = AccumulatorInfo {
                 public_key,
                 membership: Arc::clone(membership),
-                view: vote.view_number(),
+                view,
                 epoch,
                 id,
+                verification_workers: Arc::clone(verification_workers),
             };
             let collector = create_vote_accumulator(
                 &info,
@@ -295,7 +424,7 @@ where
                 .is_some()
             {
                 entry.remove();
-                *collectors = collectors.split_off(&vote.view_number());
+                *collectors = collectors.split_off(&view);
             }
 
             Ok(())
@@ -497,11 +626,17 @@ impl<TYPES: NodeType, V: Versions>
     ) -> Result<Option<QuorumCertificate2<TYPES>>> {
         match event.as_ref() {
             HotShotEvent::QuorumVoteRecv(vote) => self.accumulate_vote(vote, sender).await,
+            HotShotEvent::PartialCertificateRecv(partial) => {
+                self.accumulate_partial(partial, sender).await
+            }
             _ => Ok(None),
         }
     }
     fn filter(event: Arc<HotShotEvent<TYPES>>) -> bool {
-        matches!(event.as_ref(), HotShotEvent::QuorumVoteRecv(_))
+        matches!(
+            event.as_ref(),
+            HotShotEvent::QuorumVoteRecv(_) | HotShotEvent::PartialCertificateRecv(_)
+        )
     }
 }
 
@@ -0,0 +1,101 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Dedicated threads for work that must not hold up the consensus tasks.
+//!
+//! Tasks hand work to a [`WorkerPool`] and await its result on a channel, so however long the work
+//! takes, the runtime threads that drive the tasks stay free to handle other events.
+
+use std::{future::Future, io};
+
+use hotshot_types::traits::signature_key::SignatureKey;
+use tokio::{
+    runtime::{Builder, Runtime},
+    sync::oneshot,
+};
+
+/// A pool of threads, with their own runtime, to run heavyweight work on.
+#[derive(Debug)]
+pub struct WorkerPool {
+    /// The runtime whose threads run the work. Only `None` while the pool is being dropped.
+    runtime: Option<Runtime>,
+}
+
+impl WorkerPool {
+    /// Start a pool of `threads` threads (at least one), named after `name`.
+    ///
+    /// # Errors
+    /// If the threads cannot be started.
+    pub fn new(name: &str, threads: usize) -> io::Result<Self> {
+        let runtime = Builder::new_multi_thread()
+            .worker_threads(threads.max(1))
+            .thread_name(format!("hotshot-{name}"))
+            .enable_all()
+            .build()?;
+
+        Ok(Self {
+            runtime: Some(runtime),
+        })
+    }
+
+    /// Run `work` on the pool.
+    ///
+    /// The returned channel receives the output of `work` once it completes. It is closed without
+    /// a value if the pool shuts down first.
+    pub fn spawn<F>(&self, work: F) -> oneshot::Receiver<F::Output>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (sender, receiver) = oneshot::channel();
+        if let Some(runtime) = &self.runtime {
+            runtime.spawn(async move {
+                // The caller may have stopped waiting, which is fine
+                let _ = sender.send(work.await);
+            });
+        }
+
+        receiver
+    }
+
+    /// Run the blocking function `work` on the pool.
+    ///
+    /// The returned channel receives the output of `work`, as for [`WorkerPool::spawn`].
+    pub fn spawn_blocking<F, R>(&self, work: F) -> oneshot::Receiver<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.spawn(async move { work() })
+    }
+
+    /// Check on the pool that `signature` is `key`'s signature of `data`.
+    ///
+    /// Returns `false` if the signature is invalid, or if the pool shut down before checking it.
+    pub async fn verify<K, D>(
+        &self,
+        key: K,
+        signature: K::PureAssembledSignatureType,
+        data: D,
+    ) -> bool
+    where
+        K: SignatureKey + 'static,
+        D: AsRef<[u8]> + Send + 'static,
+    {
+        self.spawn_blocking(move || key.validate(&signature, data.as_ref()))
+            .await
+            .unwrap_or(false)
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        // A runtime waits for its threads when dropped, which would panic inside a task
+        if let Some(runtime) = self.runtime.take() {
+            runtime.shutdown_background();
+        }
+    }
+}
@@ -10,5 +10,7 @@
 pub mod dependency;
 /// Task which can uses dependencies
 pub mod dependency_task;
+/// Spawning of named tasks, for runtime instrumentation
+pub mod spawn;
 /// Basic task types
 pub mod task;
@@ -0,0 +1,68 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Spawning of named tasks.
+//!
+//! When built with `RUSTFLAGS="--cfg tokio_unstable"`, tasks are registered with tokio under their
+//! name, so that tools like `tokio-console` can tell which consensus task is stuck or starved.
+//! Otherwise, the name is ignored.
+
+use std::future::Future;
+
+use tokio::task::JoinHandle;
+
+/// Spawn `future` as a task called `name`.
+///
+/// # Panics
+/// Panics if tokio fails to spawn the task, which it only does outside of a runtime, like
+/// [`tokio::spawn`].
+#[allow(clippy::needless_pass_by_value)]
+pub fn spawn_named<F>(name: impl AsRef<str>, future: F) -> JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(tokio_unstable)]
+    {
+        tokio::task::Builder::new()
+            .name(name.as_ref())
+            .spawn(future)
+            .expect("failed to spawn task")
+    }
+    #[cfg(not(tokio_unstable))]
+    {
+        let _ = name;
+        tokio::spawn(future)
+    }
+}
+
+/// A short name for type `T`, without module paths or generic parameters.
+#[must_use]
+pub fn type_task_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// A type with a generic parameter, to name
+    struct Named<T>(T);
+
+    #[test]
+    fn test_type_task_name() {
+        assert_eq!(type_task_name::<Named<Vec<u8>>>(), "Named");
+        assert_eq!(type_task_name::<u64>(), "u64");
+    }
+
+    #[tokio::test]
+    async fn test_spawn_named() {
+        let handle = spawn_named("answer", async { 42 });
+        assert_eq!(handle.await.unwrap(), 42);
+    }
+}
@@ -9,9 +9,11 @@ use std::sync::Arc;
 use async_broadcast::{Receiver, RecvError, Sender};
 use async_trait::async_trait;
 use futures::future::try_join_all;
-use tokio::task::{spawn, JoinHandle};
+use tokio::task::JoinHandle;
 use utils::anytrace::Result;
 
+use crate::spawn::{spawn_named, type_task_name};
+
 /// Trait for events that long-running tasks handle
 pub trait TaskEvent: PartialEq {
     /// The shutdown signal for this event type
@@ -72,7 +74,7 @@ impl<S: TaskState + Send + 'static> Task<S> {
     /// Spawn the task loop, consuming self.  Will continue until
     /// the task reaches some shutdown condition
     pub fn run(mut self) -> JoinHandle<Box<dyn TaskState<Event = S::Event>>> {
-        spawn(async move {
+        spawn_named(type_task_name::<S>(), async move {
             loop {
                 match self.receiver.recv_direct().await {
                     Ok(input) => {
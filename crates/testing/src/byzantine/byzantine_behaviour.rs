@@ -1,6 +1,6 @@
 use std::{
     collections::{BTreeMap, HashMap, HashSet},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use anyhow::Context;
@@ -13,16 +13,22 @@ use hotshot::{
 use hotshot_task_impls::{
     events::HotShotEvent,
     network::{
-        test::{ModifierClosure, NetworkEventTaskStateModifier},
+        test::{ModifierClosure, NetworkEventTaskStateModifier, NetworkEventTaskStateRecipients},
         NetworkEventTaskState,
     },
 };
 use hotshot_types::{
     consensus::{Consensus, OuterConsensus},
     data::QuorumProposal2,
-    message::{Proposal, UpgradeLock},
+    message::{
+        GeneralConsensusMessage, MessageKind, Proposal, SequencingMessage, UpgradeLock, ViewMessage,
+    },
     simple_vote::QuorumVote2,
-    traits::node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+    traits::{
+        consensus_api::ConsensusApi,
+        node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+        signer::Signer,
+    },
 };
 
 #[derive(Debug)]
@@ -45,8 +51,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> EventTransforme
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -88,8 +93,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> EventTransforme
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -170,8 +174,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -214,8 +217,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -284,8 +286,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        _public_key: &TYPES::SignatureKey,
-        _private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
         _upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -312,18 +313,16 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
         if let HotShotEvent::QuorumVoteSend(vote) = event {
             let new_view = vote.view_number + self.view_increment;
-            let spoofed_vote = QuorumVote2::<TYPES>::create_signed_vote(
+            let spoofed_vote = QuorumVote2::<TYPES>::create_signed_vote_with_signer(
                 vote.data.clone(),
                 new_view,
-                public_key,
-                private_key,
+                signer,
                 upgrade_lock,
             )
             .await
@@ -341,24 +340,35 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         network: Arc<<I as NodeImplementation<TYPES>>::Network>,
         membership: TYPES::Membership,
     ) {
-        let network_state: NetworkEventTaskState<_, V, _, _> = NetworkEventTaskState {
-            network,
-            view: TYPES::View::genesis(),
-            epoch: TYPES::Epoch::genesis(),
-            membership,
-            storage: Arc::clone(&handle.storage()),
-            consensus: OuterConsensus::new(handle.consensus()),
-            upgrade_lock: handle.hotshot.upgrade_lock.clone(),
-            transmit_tasks: BTreeMap::new(),
-        };
         let modified_network_state = NetworkEventTaskStateModifier {
-            network_event_task_state: network_state,
+            network_event_task_state: network_event_task_state(handle, network, membership),
             modifier: Arc::clone(&self.modifier),
         };
         handle.add_task(modified_network_state);
     }
 }
 
+/// The network task state a node would run, for byzantine behaviours to wrap.
+fn network_event_task_state<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions>(
+    handle: &SystemContextHandle<TYPES, I, V>,
+    network: Arc<<I as NodeImplementation<TYPES>>::Network>,
+    membership: TYPES::Membership,
+) -> NetworkEventTaskState<TYPES, V, I::Network, I::Storage> {
+    NetworkEventTaskState {
+        network,
+        view: TYPES::View::genesis(),
+        epoch: TYPES::Epoch::genesis(),
+        membership,
+        storage: Arc::clone(&handle.storage()),
+        consensus: OuterConsensus::new(handle.consensus()),
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+        signer: handle.signer(),
+        transmit_tasks: BTreeMap::new(),
+        archival_nodes: handle.hotshot.config.archival_nodes.clone(),
+        aggregation: handle.hotshot.config.aggregation,
+    }
+}
+
 impl<TYPES: NodeType> std::fmt::Debug for DishonestVoting<TYPES> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("DishonestVoting")
@@ -387,8 +397,7 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
     async fn send_handler(
         &mut self,
         event: &HotShotEvent<TYPES>,
-        public_key: &TYPES::SignatureKey,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
         upgrade_lock: &UpgradeLock<TYPES, V>,
         _consensus: Arc<RwLock<Consensus<TYPES>>>,
     ) -> Vec<HotShotEvent<TYPES>> {
@@ -400,11 +409,10 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
                     // Create a vote using data from most recent vote and the current event number
                     // We wont update internal consensus state for this Byzantine replica but we are at least
                     // Going to send a vote to the next honest leader
-                    let vote = QuorumVote2::<TYPES>::create_signed_vote(
+                    let vote = QuorumVote2::<TYPES>::create_signed_vote_with_signer(
                         self.votes_sent.last().unwrap().data.clone(),
                         event.view_number().unwrap(),
-                        public_key,
-                        private_key,
+                        signer,
                         upgrade_lock,
                     )
                     .await
@@ -430,3 +438,144 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Version
         vec![event.clone()]
     }
 }
+
+/// How a [`WithholdingLeader`] misbehaves with a proposal.
+#[derive(Clone, Debug)]
+pub enum Withholding {
+    /// Never send the proposal
+    Withhold,
+    /// Send the proposal only to the nodes with these ids
+    SendTo(Vec<u64>),
+    /// Send the proposal only once the view it is for has timed out for the leader
+    DelayPastTimeout,
+}
+
+/// An `EventHandlerState` for a leader which withholds some of its proposals, sends them to only
+/// some of the nodes, or sends them too late, so that the view times out
+pub struct WithholdingLeader<TYPES: NodeType> {
+    /// How the leader misbehaves
+    withholding: Withholding,
+    /// Which proposals to misbehave with
+    withhold_at_proposal_numbers: HashSet<u64>,
+    /// How many times current node has been elected leader and sent proposal
+    total_proposals_from_node: u64,
+    /// Proposals held back until their view times out
+    delayed_proposals: BTreeMap<TYPES::View, HotShotEvent<TYPES>>,
+    /// Views of the proposals which only reach some nodes, shared with the network task
+    partial_views: Arc<Mutex<HashSet<u64>>>,
+}
+
+impl<TYPES: NodeType> WithholdingLeader<TYPES> {
+    /// A leader misbehaving as `withholding` with the proposals numbered in
+    /// `withhold_at_proposal_numbers`, counting from 1.
+    #[must_use]
+    pub fn new(withholding: Withholding, withhold_at_proposal_numbers: HashSet<u64>) -> Self {
+        Self {
+            withholding,
+            withhold_at_proposal_numbers,
+            total_proposals_from_node: 0,
+            delayed_proposals: BTreeMap::new(),
+            partial_views: Arc::default(),
+        }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType, I: NodeImplementation<TYPES> + std::fmt::Debug, V: Versions>
+    EventTransformerState<TYPES, I, V> for WithholdingLeader<TYPES>
+{
+    async fn recv_handler(&mut self, event: &HotShotEvent<TYPES>) -> Vec<HotShotEvent<TYPES>> {
+        vec![event.clone()]
+    }
+
+    async fn send_handler(
+        &mut self,
+        event: &HotShotEvent<TYPES>,
+        _signer: &dyn Signer<TYPES::SignatureKey>,
+        _upgrade_lock: &UpgradeLock<TYPES, V>,
+        _consensus: Arc<RwLock<Consensus<TYPES>>>,
+    ) -> Vec<HotShotEvent<TYPES>> {
+        match event {
+            HotShotEvent::QuorumProposalSend(proposal, _) => {
+                self.total_proposals_from_node += 1;
+                if !self
+                    .withhold_at_proposal_numbers
+                    .contains(&self.total_proposals_from_node)
+                {
+                    return vec![event.clone()];
+                }
+                let view = proposal.data.view_number;
+                tracing::debug!(
+                    "Misbehaving as {:?} with proposal for view {view:?}",
+                    self.withholding
+                );
+                match self.withholding {
+                    Withholding::Withhold => vec![],
+                    Withholding::SendTo(_) => {
+                        self.partial_views.lock().unwrap().insert(*view);
+                        vec![event.clone()]
+                    }
+                    Withholding::DelayPastTimeout => {
+                        self.delayed_proposals.insert(view, event.clone());
+                        vec![]
+                    }
+                }
+            }
+            HotShotEvent::TimeoutVoteSend(vote) => {
+                // Release the proposal right after our timeout vote, and drop any older ones
+                let mut due = std::mem::take(&mut self.delayed_proposals);
+                self.delayed_proposals = due.split_off(&(vote.view_number + 1));
+                let mut result = vec![event.clone()];
+                result.extend(due.remove(&vote.view_number));
+                result
+            }
+            _ => vec![event.clone()],
+        }
+    }
+
+    fn add_network_event_task(
+        &self,
+        handle: &mut SystemContextHandle<TYPES, I, V>,
+        network: Arc<<I as NodeImplementation<TYPES>>::Network>,
+        membership: TYPES::Membership,
+    ) {
+        let recipients: Vec<_> = match &self.withholding {
+            Withholding::SendTo(node_ids) => node_ids
+                .iter()
+                .map(|id| TYPES::SignatureKey::generated_from_seed_indexed([0u8; 32], *id).0)
+                .collect(),
+            Withholding::Withhold | Withholding::DelayPastTimeout => vec![],
+        };
+        let partial_views = Arc::clone(&self.partial_views);
+        let recipients_network_state = NetworkEventTaskStateRecipients {
+            network_event_task_state: network_event_task_state(handle, network, membership),
+            recipients: Arc::new(move |message_kind, _membership| {
+                let MessageKind::Consensus(SequencingMessage::General(
+                    GeneralConsensusMessage::Proposal(_) | GeneralConsensusMessage::Proposal2(_),
+                )) = message_kind
+                else {
+                    return None;
+                };
+                partial_views
+                    .lock()
+                    .unwrap()
+                    .contains(&*message_kind.view_number())
+                    .then(|| recipients.clone())
+            }),
+        };
+        handle.add_task(recipients_network_state);
+    }
+}
+
+impl<TYPES: NodeType> std::fmt::Debug for WithholdingLeader<TYPES> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WithholdingLeader")
+            .field("withholding", &self.withholding)
+            .field(
+                "withhold_at_proposal_numbers",
+                &self.withhold_at_proposal_numbers,
+            )
+            .field("total_proposals_from_node", &self.total_proposals_from_node)
+            .finish_non_exhaustive()
+    }
+}
@@ -34,6 +34,7 @@ use hotshot_types::{
         consensus_api::ConsensusApi,
         election::Membership,
         node_implementation::{ConsensusTime, NodeType, Versions},
+        signer::LocalSigner,
     },
     utils::{epoch_from_block_number, View, ViewInner},
     vid::{vid_scheme, VidCommitment, VidProposal, VidSchemeType},
@@ -106,17 +107,16 @@ pub async fn build_system_handle_from_launcher<
     // We assign node's public key and stake value rather than read from config file since it's a test
     let validator_config: ValidatorConfig<TYPES::SignatureKey> =
         ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da);
-    let private_key = validator_config.private_key.clone();
-    let public_key = validator_config.public_key.clone();
+    let signer = Arc::new(LocalSigner::new(
+        validator_config.public_key.clone(),
+        validator_config.private_key.clone(),
+    ));
 
-    let memberships = TYPES::Membership::new(
-        config.known_nodes_with_stake.clone(),
-        config.known_da_nodes.clone(),
-    );
+    let (known_nodes_with_stake, known_da_nodes) = config.stake_tables(*TYPES::Epoch::genesis());
+    let memberships = TYPES::Membership::new(known_nodes_with_stake, known_da_nodes);
 
     SystemContext::init(
-        public_key,
-        private_key,
+        signer,
         node_id,
         config,
         memberships,
@@ -215,7 +215,7 @@ pub async fn build_assembled_sig<
     let stake_table = CERT::stake_table(membership, epoch);
     let real_qc_pp: <TYPES::SignatureKey as SignatureKey>::QcParams =
         <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table.clone(),
+            stake_table.to_vec(),
             U256::from(CERT::threshold(membership, epoch)),
         );
     let total_nodes = stake_table.len();
@@ -395,14 +395,13 @@ pub async fn build_vote<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versio
     let view = proposal.view_number;
 
     let leaf: Leaf2<_> = Leaf2::from_quorum_proposal(&proposal);
-    let vote = QuorumVote2::<TYPES>::create_signed_vote(
+    let vote = QuorumVote2::<TYPES>::create_signed_vote_with_signer(
         QuorumData2 {
             leaf_commit: leaf.commit(),
             epoch: leaf.epoch(),
         },
         view,
-        &handle.public_key(),
-        handle.private_key(),
+        handle.signer().as_ref(),
         &handle.hotshot.upgrade_lock,
     )
     .await
@@ -39,6 +39,9 @@ pub mod test_task;
 /// task for checking if view sync got activated
 pub mod view_sync_task;
 
+/// assertions on the metrics nodes record during a test
+pub mod metrics_assertions;
+
 /// Test implementation of block builder
 pub mod block_builder;
 
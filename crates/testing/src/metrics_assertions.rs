@@ -0,0 +1,99 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Assertions on the metrics nodes record during a test.
+//!
+//! Every node of a test records its consensus metrics into a shared [`RecordingMetrics`], in the
+//! subgroup `node-<id>`. Once the nodes are shut down, the runner checks the
+//! [`MetricsAssertion`]s of the test description against the metrics of every node which ran.
+
+use anyhow::{ensure, Result};
+use hotshot_types::{
+    consensus::ConsensusMetricsValue,
+    traits::metrics::{Metrics, RecordingMetrics},
+};
+
+/// Consensus metrics of node `node_id`, recorded into `recorder`.
+#[must_use]
+pub fn node_metrics(recorder: &RecordingMetrics, node_id: u64) -> ConsensusMetricsValue {
+    ConsensusMetricsValue::new(&*recorder.subgroup(format!("node-{node_id}")))
+}
+
+/// A bound on a metric which every node must meet at the end of a test.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MetricsAssertion {
+    /// The counter `name` is at most `max`
+    CounterAtMost {
+        /// Name of the counter, as in [`ConsensusMetricsValue::new`]
+        name: String,
+        /// Highest allowed value
+        max: usize,
+    },
+    /// The gauge `name` is at least `min`
+    GaugeAtLeast {
+        /// Name of the gauge, as in [`ConsensusMetricsValue::new`]
+        name: String,
+        /// Lowest allowed value
+        min: usize,
+    },
+}
+
+impl MetricsAssertion {
+    /// The counter `name` is at most `max`.
+    #[must_use]
+    pub fn counter_at_most(name: &str, max: usize) -> Self {
+        Self::CounterAtMost {
+            name: name.to_string(),
+            max,
+        }
+    }
+
+    /// The gauge `name` is at least `min`.
+    #[must_use]
+    pub fn gauge_at_least(name: &str, min: usize) -> Self {
+        Self::GaugeAtLeast {
+            name: name.to_string(),
+            min,
+        }
+    }
+
+    /// What a healthy network meets: no node hits an error which costs it a view or more, and
+    /// no node times out in more than `max_timeouts` views.
+    #[must_use]
+    pub fn healthy(max_timeouts: usize) -> Vec<Self> {
+        vec![
+            Self::counter_at_most("view_fatal_errors", 0),
+            Self::counter_at_most("node_fatal_errors", 0),
+            Self::counter_at_most("number_of_timeouts", max_timeouts),
+        ]
+    }
+
+    /// Check the assertion against the metrics of node `node_id`.
+    ///
+    /// # Errors
+    /// If the node's metric is out of bounds.
+    pub fn check(&self, recorder: &RecordingMetrics, node_id: u64) -> Result<()> {
+        match self {
+            Self::CounterAtMost { name, max } => {
+                let value = recorder.counter(&format!("node-{node_id}-{name}"));
+                ensure!(
+                    value <= *max,
+                    "Node {node_id} counted {value} {name}, more than {max}"
+                );
+            }
+            Self::GaugeAtLeast { name, min } => {
+                let value = recorder
+                    .gauge(&format!("node-{node_id}-{name}"))
+                    .unwrap_or_default();
+                ensure!(
+                    value >= *min,
+                    "Node {node_id} ended with {name} at {value}, less than {min}"
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -10,7 +10,7 @@ use std::{
 };
 
 use anyhow::Result;
-use async_broadcast::broadcast;
+use async_broadcast::{broadcast, Receiver, RecvError};
 use async_lock::RwLock;
 use async_trait::async_trait;
 use futures::future::join_all;
@@ -24,20 +24,24 @@ use hotshot_example_types::{
     storage_types::TestStorage,
     testable_delay::DelayConfig,
 };
+use hotshot_task_impls::events::HotShotEvent;
 use hotshot_types::{
     constants::EVENT_CHANNEL_SIZE,
     data::Leaf2,
     event::Event,
     simple_certificate::QuorumCertificate2,
     traits::{
+        metrics::RecordingMetrics,
         network::{AsyncGenerator, ConnectedNetwork},
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
     },
     vote::HasViewNumber,
     ValidatorConfig,
 };
+use tokio::spawn;
 
 use crate::{
+    metrics_assertions::node_metrics,
     test_launcher::Network,
     test_runner::{LateNodeContext, LateNodeContextParameters, LateStartNode, Node, TestRunner},
     test_task::{TestResult, TestTaskState},
@@ -71,6 +75,115 @@ pub struct SpinningTask<
     pub(crate) restart_contexts: HashMap<usize, RestartContext<TYPES, N, I, V>>,
     /// Generate network channel for restart nodes
     pub(crate) channel_generator: AsyncGenerator<Network<TYPES, I>>,
+    /// Metrics recorded by all the nodes
+    pub(crate) metrics: RecordingMetrics,
+    /// Number of views after starting within which nodes started late must vote, if checked
+    pub(crate) late_join_max_views: Option<u64>,
+    /// Nodes started late, by id
+    pub(crate) late_joins: BTreeMap<u64, LateJoin<TYPES>>,
+    /// What went wrong with nodes recovered from their storage
+    pub(crate) recovery_errors: Arc<RwLock<Vec<String>>>,
+}
+
+/// A node which started late, and when it first voted
+pub(crate) struct LateJoin<TYPES: NodeType> {
+    /// View in which the node started
+    started_at: TYPES::View,
+    /// First view the node sent a quorum vote in, once it has
+    first_vote: Arc<RwLock<Option<TYPES::View>>>,
+}
+
+/// Record in `first_vote` the first view in which the node emitting the internal events of
+/// `receiver` sends a quorum vote.
+fn watch_first_vote<TYPES: NodeType>(
+    mut receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
+    first_vote: Arc<RwLock<Option<TYPES::View>>>,
+) {
+    spawn(async move {
+        loop {
+            match receiver.recv_direct().await {
+                Ok(event) => {
+                    if let HotShotEvent::QuorumVoteSend(vote) = event.as_ref() {
+                        *first_vote.write().await = Some(vote.view_number());
+                        return;
+                    }
+                }
+                Err(RecvError::Overflowed(_)) => {}
+                Err(RecvError::Closed) => return,
+            }
+        }
+    });
+}
+
+/// Record in `errors` every vote or proposal node `node_id`, emitting the internal events of
+/// `receiver`, sends for a view up to `actioned_view`, which it acted in before it restarted.
+fn watch_equivocations<TYPES: NodeType>(
+    node_id: u64,
+    mut receiver: Receiver<Arc<HotShotEvent<TYPES>>>,
+    actioned_view: TYPES::View,
+    errors: Arc<RwLock<Vec<String>>>,
+) {
+    if actioned_view == TYPES::View::genesis() {
+        return;
+    }
+    spawn(async move {
+        loop {
+            let event = match receiver.recv_direct().await {
+                Ok(event) => event,
+                Err(RecvError::Overflowed(_)) => continue,
+                Err(RecvError::Closed) => return,
+            };
+            let (action, view) = match event.as_ref() {
+                HotShotEvent::QuorumVoteSend(vote) => ("vote", vote.view_number()),
+                HotShotEvent::QuorumProposalSend(proposal, _) => {
+                    ("proposal", proposal.data.view_number())
+                }
+                _ => continue,
+            };
+            if view <= actioned_view {
+                errors.write().await.push(format!(
+                    "Node {node_id} sent a {action} for view {:?} after recovering, but had \
+                     already acted in view {:?}",
+                    *view, *actioned_view
+                ));
+            }
+        }
+    });
+}
+
+/// The initializer of a node rebuilt from its persisted `storage` alone.
+async fn initializer_from_storage<TYPES, V>(
+    storage: &TestStorage<TYPES>,
+    instance_state: TestInstanceState,
+) -> HotShotInitializer<TYPES>
+where
+    TYPES: NodeType<InstanceState = TestInstanceState, ValidatedState = TestValidatedState>,
+    V: Versions,
+{
+    let anchor_leaf = match storage.decided_leaf().await {
+        Some(leaf) => leaf,
+        None => Leaf2::genesis(&TestValidatedState::default(), &instance_state).await,
+    };
+    let high_qc = match storage.high_qc_cloned().await {
+        Some(high_qc) => high_qc,
+        None => {
+            QuorumCertificate2::genesis::<V>(&TestValidatedState::default(), &instance_state).await
+        }
+    };
+    let actioned_view = storage.last_actioned_view().await;
+    HotShotInitializer::<TYPES>::from_reload(
+        anchor_leaf,
+        instance_state,
+        None,
+        actioned_view,
+        storage.last_actioned_epoch().await,
+        actioned_view,
+        storage.proposals_cloned().await,
+        high_qc,
+        storage.decided_upgrade_certificate().await,
+        Vec::new(),
+        BTreeMap::new(),
+    )
 }
 
 #[async_trait]
@@ -95,7 +208,7 @@ where
 {
     type Event = Event<TYPES>;
 
-    async fn handle_event(&mut self, (message, _id): (Self::Event, usize)) -> Result<()> {
+    async fn handle_event(&mut self, (message, id): (Self::Event, usize)) -> Result<()> {
         let Event { view_number, event } = message;
 
         if let EventType::Decide {
@@ -105,6 +218,11 @@ where
         } = event
         {
             let leaf = leaf_chain.first().unwrap().leaf.clone();
+            // Persist the decided leaf to the node's storage, as an application would
+            if let Some(node) = self.handles.read().await.get(id) {
+                let storage = node.handle.storage();
+                storage.read().await.record_decided_leaf(leaf.clone()).await;
+            }
             if leaf.view_number() > self.last_decided_leaf.view_number() {
                 self.last_decided_leaf = leaf;
             }
@@ -183,6 +301,7 @@ where
                                             validator_config,
                                             storage,
                                             marketplace_config,
+                                            node_metrics(&self.metrics, node_id),
                                         )
                                         .await
                                     }
@@ -193,6 +312,21 @@ where
 
                                 let handle = context.run_tasks().await;
 
+                                if self.late_join_max_views.is_some() {
+                                    let first_vote = Arc::default();
+                                    watch_first_vote(
+                                        handle.internal_event_stream_receiver_known_impl(),
+                                        Arc::clone(&first_vote),
+                                    );
+                                    self.late_joins.insert(
+                                        node_id,
+                                        LateJoin {
+                                            started_at: view_number,
+                                            first_vote,
+                                        },
+                                    );
+                                }
+
                                 // Create the node and add it to the state, so we can shut them
                                 // down properly later to avoid the overflow error in the overall
                                 // safety task.
@@ -272,6 +406,82 @@ where
                                         validator_config,
                                         (*read_storage).clone(),
                                         marketplace_config.clone(),
+                                        node_metrics(&self.metrics, node_id),
+                                        internal_chan,
+                                        (
+                                            node.handle.external_channel_sender(),
+                                            node.handle.event_stream_known_impl().new_receiver(),
+                                        ),
+                                    )
+                                    .await;
+                                if delay_views == 0 {
+                                    new_nodes.push((context, idx));
+                                    new_networks.push(generated_network.clone());
+                                } else {
+                                    let up_view = view_number + delay_views;
+                                    let change = ChangeNode {
+                                        idx,
+                                        updown: NodeAction::RestartUp,
+                                    };
+                                    self.changes.entry(up_view).or_default().push(change);
+                                    let new_ctx = RestartContext {
+                                        context,
+                                        network: generated_network.clone(),
+                                    };
+                                    self.restart_contexts.insert(idx, new_ctx);
+                                }
+                            }
+                        }
+                        NodeAction::RecoverDown(delay_views) => {
+                            let node_id = idx.try_into().unwrap();
+                            if let Some(node) = self.handles.write().await.get_mut(idx) {
+                                tracing::error!("Node {} shutting down to recover", idx);
+                                node.handle.shut_down().await;
+                                let generated_network = (self.channel_generator)(node_id).await;
+
+                                let Some(LateStartNode {
+                                    network: _,
+                                    context: LateNodeContext::Restart,
+                                }) = self.late_start.get(&node_id)
+                                else {
+                                    panic!("Recovered Nodes must have an uninitialized context");
+                                };
+
+                                // Nothing but the persisted storage survives the restart
+                                let storage = node.handle.storage().read().await.reopen().await;
+                                let actioned_view = storage.last_actioned_view().await;
+                                let high_qc = storage.high_qc_cloned().await;
+                                let initializer = initializer_from_storage::<TYPES, V>(
+                                    &storage,
+                                    TestInstanceState::new(self.async_delay_config.clone()),
+                                )
+                                .await;
+                                let config = node.handle.hotshot.config.clone();
+                                let validator_config = ValidatorConfig::generated_from_seed_indexed(
+                                    [0u8; 32],
+                                    node_id,
+                                    1,
+                                    // For tests, make the node DA based on its index
+                                    node_id < config.da_staked_committee_size as u64,
+                                );
+                                let internal_chan = broadcast(EVENT_CHANNEL_SIZE);
+                                watch_equivocations(
+                                    node_id,
+                                    internal_chan.1.clone(),
+                                    actioned_view,
+                                    Arc::clone(&self.recovery_errors),
+                                );
+                                let context =
+                                    TestRunner::<TYPES, I, V, N>::add_node_with_config_and_channels(
+                                        node_id,
+                                        generated_network.clone(),
+                                        (*node.handle.memberships).clone(),
+                                        initializer,
+                                        config,
+                                        validator_config,
+                                        storage,
+                                        node.handle.hotshot.marketplace_config.clone(),
+                                        node_metrics(&self.metrics, node_id),
                                         internal_chan,
                                         (
                                             node.handle.external_channel_sender(),
@@ -279,6 +489,31 @@ where
                                         ),
                                     )
                                     .await;
+
+                                // The node resumes where it left off, with the QC it persisted
+                                let consensus = context.consensus();
+                                let consensus = consensus.read().await;
+                                let mut recovery_errors = self.recovery_errors.write().await;
+                                if consensus.cur_view() < actioned_view {
+                                    recovery_errors.push(format!(
+                                        "Node {node_id} recovered in view {:?}, before view {:?} \
+                                         it had acted in",
+                                        *consensus.cur_view(),
+                                        *actioned_view
+                                    ));
+                                }
+                                if high_qc.is_some_and(|high_qc| {
+                                    consensus.high_qc().view_number() < high_qc.view_number()
+                                }) {
+                                    recovery_errors.push(format!(
+                                        "Node {node_id} recovered with a high QC for view {:?}, \
+                                         older than the one it persisted",
+                                        *consensus.high_qc().view_number()
+                                    ));
+                                }
+                                drop(recovery_errors);
+                                drop(consensus);
+
                                 if delay_views == 0 {
                                     new_nodes.push((context, idx));
                                     new_networks.push(generated_network.clone());
@@ -361,6 +596,31 @@ where
     }
 
     async fn check(&self) -> TestResult {
+        let recovery_errors = self.recovery_errors.read().await;
+        if !recovery_errors.is_empty() {
+            return TestResult::Fail(Box::new(recovery_errors.clone()));
+        }
+        let (Some(max_views), Some(latest_view)) = (self.late_join_max_views, self.latest_view)
+        else {
+            return TestResult::Pass;
+        };
+        for (node_id, late_join) in &self.late_joins {
+            let deadline = late_join.started_at + max_views;
+            // The network did not run long enough to tell
+            if latest_view < deadline {
+                continue;
+            }
+            let first_vote = *late_join.first_vote.read().await;
+            if !first_vote.is_some_and(|view| view <= deadline) {
+                return TestResult::Fail(Box::new(format!(
+                    "Node {node_id} started in view {:?} but did not vote by view {:?}, \
+                     first voting in view {:?}",
+                    *late_join.started_at,
+                    *deadline,
+                    first_vote.map(|view| *view)
+                )));
+            }
+        }
         TestResult::Pass
     }
 }
@@ -389,8 +649,11 @@ pub enum NodeAction {
     NetworkDown,
     /// Take a node down to be restarted after a number of views
     RestartDown(u64),
+    /// Like `RestartDown`, but rebuild the node from its persisted storage alone, and check that
+    /// it resumes where it left off and never acts twice in a view
+    RecoverDown(u64),
     /// Start a node up again after it's been shutdown for restart.  This
-    /// should only be created following a `RestartDown`
+    /// should only be created following a `RestartDown` or `RecoverDown`
     RestartUp,
 }
 
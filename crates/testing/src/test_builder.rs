@@ -18,15 +18,33 @@ use hotshot_example_types::{
     storage_types::TestStorage, testable_delay::DelayConfig,
 };
 use hotshot_types::{
-    consensus::ConsensusMetricsValue,
-    traits::node_implementation::{NodeType, Versions},
-    HotShotConfig, ValidatorConfig,
+    anti_entropy::AntiEntropyConfig,
+    block_limits::BlockLimits,
+    channels::ChannelConfig,
+    consensus::{CommitRule, ConsensusMetricsValue},
+    dag_mempool::DagMempoolConfig,
+    fallback::FallbackConfig,
+    gossip_fanout::GossipFanoutConfig,
+    mempool::MempoolGossipConfig,
+    participation::JailConfig,
+    payload_stream::PayloadStreamConfig,
+    stake_table::{BondingCurve, LeaderConstraint},
+    timestamp_rules::TimestampRules,
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signer::LocalSigner,
+    },
+    view_timing::Pacing,
+    vote_aggregation::AggregationConfig,
+    workers::WorkerConfig,
+    HotShotConfig, NodeRole, ValidatorConfig,
 };
 use tide_disco::Url;
 use vec1::Vec1;
 
 use super::{
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    metrics_assertions::MetricsAssertion,
     overall_safety_task::OverallSafetyPropertiesDescription,
     txn_task::TxnTaskDescription,
 };
@@ -100,6 +118,11 @@ pub struct TestDescription<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Ver
     pub validate_transactions: TransactionValidator,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// bounds every node's metrics must meet at the end of the test
+    pub metrics_assertions: Vec<MetricsAssertion>,
+    /// Number of views after starting within which every node started late must catch up and
+    /// vote, or `None` not to check
+    pub late_join_max_views: Option<u64>,
 }
 
 pub fn nonempty_block_threshold(threshold: (u64, u64)) -> TransactionValidator {
@@ -167,6 +190,7 @@ pub enum Behaviour<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> {
     Standard,
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn create_test_handle<
     TYPES: NodeType<InstanceState = TestInstanceState>,
     I: NodeImplementation<TYPES>,
@@ -179,6 +203,7 @@ pub async fn create_test_handle<
     config: HotShotConfig<TYPES::SignatureKey>,
     storage: I::Storage,
     marketplace_config: MarketplaceConfig<TYPES, I>,
+    metrics: ConsensusMetricsValue,
 ) -> SystemContextHandle<TYPES, I, V> {
     let initializer = HotShotInitializer::<TYPES>::from_genesis::<V>(TestInstanceState::new(
         metadata.async_delay_config,
@@ -193,8 +218,10 @@ pub async fn create_test_handle<
         ValidatorConfig::generated_from_seed_indexed([0u8; 32], node_id, 1, is_da);
 
     // Get key pair for certificate aggregation
-    let private_key = validator_config.private_key.clone();
-    let public_key = validator_config.public_key.clone();
+    let signer = Arc::new(LocalSigner::new(
+        validator_config.public_key,
+        validator_config.private_key,
+    ));
 
     let behaviour = (metadata.behaviour)(node_id);
     match behaviour {
@@ -202,14 +229,13 @@ pub async fn create_test_handle<
             let state = Box::leak(state);
             let (left_handle, _right_handle) = state
                 .spawn_twin_handles(
-                    public_key,
-                    private_key,
+                    signer,
                     node_id,
                     config,
                     memberships,
                     network,
                     initializer,
-                    ConsensusMetricsValue::default(),
+                    metrics,
                     storage,
                     marketplace_config,
                 )
@@ -221,14 +247,13 @@ pub async fn create_test_handle<
             let state = Box::leak(state);
             state
                 .spawn_handle(
-                    public_key,
-                    private_key,
+                    signer,
                     node_id,
                     config,
                     memberships,
                     network,
                     initializer,
-                    ConsensusMetricsValue::default(),
+                    metrics,
                     storage,
                     marketplace_config,
                 )
@@ -236,14 +261,13 @@ pub async fn create_test_handle<
         }
         Behaviour::Standard => {
             let hotshot = SystemContext::<TYPES, I, V>::new(
-                public_key,
-                private_key,
+                signer,
                 node_id,
                 config,
                 memberships,
                 network,
                 initializer,
-                ConsensusMetricsValue::default(),
+                metrics,
                 storage,
                 marketplace_config,
             )
@@ -418,6 +442,8 @@ impl<TYPES: NodeType, I: NodeImplementation<TYPES>, V: Versions> Default
             start_solver: true,
             validate_transactions: Arc::new(|_| Ok(())),
             epoch_height: 0,
+            metrics_assertions: vec![],
+            late_join_max_views: None,
         }
     }
 }
@@ -513,6 +539,28 @@ where
             start_voting_time: u64::MAX,
             stop_voting_time: 0,
             epoch_height,
+            block_limits: BlockLimits::default(),
+            // Test nodes share a clock, but tests may build headers by hand.
+            timestamp_rules: TimestampRules::lenient(),
+            mempool_gossip: MempoolGossipConfig::default(),
+            gossip_fanout: GossipFanoutConfig::default(),
+            anti_entropy: AntiEntropyConfig::default(),
+            role: NodeRole::default(),
+            archival_nodes: Vec::new(),
+            channels: ChannelConfig::default(),
+            workers: WorkerConfig::default(),
+            payload_streaming: PayloadStreamConfig::default(),
+            bonding_curve: BondingCurve::default(),
+            leader_constraint: LeaderConstraint::default(),
+            min_stake: 0,
+            chain_id: 0,
+            jail: JailConfig::default(),
+            commit_rule: CommitRule::default(),
+            pacing: Pacing::default(),
+            dag_mempool: DagMempoolConfig::default(),
+            fallback: FallbackConfig::default(),
+            aggregation: AggregationConfig::default(),
+            remote_signer: None,
         };
         let TimingData {
             next_view_timeout,
@@ -13,6 +13,7 @@ use hotshot::{
 use hotshot_example_types::storage_types::TestStorage;
 use hotshot_types::{
     traits::{
+        metrics::RecordingMetrics,
         network::{AsyncGenerator, ConnectedNetwork},
         node_implementation::{NodeType, Versions},
     },
@@ -62,6 +63,7 @@ impl<TYPES: NodeType, I: TestableNodeImplementation<TYPES>, V: Versions> TestLau
             solver_server: None,
             late_start: HashMap::new(),
             next_node_id: 0,
+            metrics: RecordingMetrics::default(),
             _pd: PhantomData,
         }
     }
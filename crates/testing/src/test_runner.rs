@@ -34,8 +34,10 @@ use hotshot_types::{
     simple_certificate::QuorumCertificate2,
     traits::{
         election::Membership,
+        metrics::RecordingMetrics,
         network::ConnectedNetwork,
         node_implementation::{ConsensusTime, NodeImplementation, NodeType, Versions},
+        signer::LocalSigner,
     },
     HotShotConfig, ValidatorConfig,
 };
@@ -53,6 +55,7 @@ use super::{
 use crate::{
     block_builder::{BuilderTask, TestBuilderImplementation},
     completion_task::CompletionTaskDescription,
+    metrics_assertions::node_metrics,
     spinning_task::{ChangeNode, NodeAction, SpinningTask},
     test_builder::create_test_handle,
     test_launcher::{Network, TestLauncher},
@@ -104,7 +107,10 @@ where
                 if matches!(change.updown, NodeAction::Up) {
                     late_start_nodes.insert(change.idx.try_into().unwrap());
                 }
-                if matches!(change.updown, NodeAction::RestartDown(_)) {
+                if matches!(
+                    change.updown,
+                    NodeAction::RestartDown(_) | NodeAction::RecoverDown(_)
+                ) {
                     restart_nodes.insert(change.idx.try_into().unwrap());
                 }
             }
@@ -134,6 +140,7 @@ where
             solver_server,
             late_start,
             next_node_id: _,
+            metrics,
             _pd: _,
         } = self;
 
@@ -192,6 +199,10 @@ where
             async_delay_config: launcher.metadata.async_delay_config,
             restart_contexts: HashMap::new(),
             channel_generator: launcher.resource_generator.channel_generator,
+            metrics: metrics.clone(),
+            late_join_max_views: launcher.metadata.late_join_max_views,
+            late_joins: BTreeMap::new(),
+            recovery_errors: Arc::default(),
         };
         let spinning_task = TestTask::<SpinningTask<TYPES, N, I, V>>::new(
             spinning_task_state,
@@ -308,6 +319,14 @@ where
         }
         tracing::info!("Nodes shutdown");
 
+        for node in &*nodes {
+            for assertion in &meta.metrics_assertions {
+                if let Err(e) = assertion.check(&metrics, node.node_id) {
+                    error_list.push(Box::new(e));
+                }
+            }
+        }
+
         completion_handle.abort();
 
         assert!(
@@ -417,10 +436,10 @@ where
             self.next_node_id += 1;
             tracing::debug!("launch node {}", i);
 
-            let memberships = <TYPES as NodeType>::Membership::new(
-                config.known_nodes_with_stake.clone(),
-                config.known_da_nodes.clone(),
-            );
+            let (known_nodes_with_stake, known_da_nodes) =
+                config.stake_tables(*TYPES::Epoch::genesis());
+            let memberships =
+                <TYPES as NodeType>::Membership::new(known_nodes_with_stake, known_da_nodes);
             config.builder_urls = builder_urls
                 .clone()
                 .try_into()
@@ -487,6 +506,7 @@ where
                         validator_config,
                         storage,
                         marketplace_config,
+                        node_metrics(&self.metrics, node_id),
                     )
                     .await;
                     self.late_start.insert(
@@ -540,6 +560,7 @@ where
                 config.clone(),
                 storage,
                 marketplace_config,
+                node_metrics(&self.metrics, node_id),
             )
             .await;
 
@@ -581,20 +602,22 @@ where
         validator_config: ValidatorConfig<TYPES::SignatureKey>,
         storage: I::Storage,
         marketplace_config: MarketplaceConfig<TYPES, I>,
+        metrics: ConsensusMetricsValue,
     ) -> Arc<SystemContext<TYPES, I, V>> {
         // Get key pair for certificate aggregation
-        let private_key = validator_config.private_key.clone();
-        let public_key = validator_config.public_key.clone();
+        let signer = Arc::new(LocalSigner::new(
+            validator_config.public_key,
+            validator_config.private_key,
+        ));
 
         SystemContext::new(
-            public_key,
-            private_key,
+            signer,
             node_id,
             config,
             memberships,
             network,
             initializer,
-            ConsensusMetricsValue::default(),
+            metrics,
             storage,
             marketplace_config,
         )
@@ -614,6 +637,7 @@ where
         validator_config: ValidatorConfig<TYPES::SignatureKey>,
         storage: I::Storage,
         marketplace_config: MarketplaceConfig<TYPES, I>,
+        metrics: ConsensusMetricsValue,
         internal_channel: (
             Sender<Arc<HotShotEvent<TYPES>>>,
             Receiver<Arc<HotShotEvent<TYPES>>>,
@@ -621,18 +645,19 @@ where
         external_channel: (Sender<Event<TYPES>>, Receiver<Event<TYPES>>),
     ) -> Arc<SystemContext<TYPES, I, V>> {
         // Get key pair for certificate aggregation
-        let private_key = validator_config.private_key.clone();
-        let public_key = validator_config.public_key.clone();
+        let signer = Arc::new(LocalSigner::new(
+            validator_config.public_key,
+            validator_config.private_key,
+        ));
 
         SystemContext::new_from_channels(
-            public_key,
-            private_key,
+            signer,
             node_id,
             config,
             memberships,
             network,
             initializer,
-            ConsensusMetricsValue::default(),
+            metrics,
             storage,
             marketplace_config,
             internal_channel,
@@ -708,6 +733,8 @@ pub struct TestRunner<
     pub(crate) late_start: HashMap<u64, LateStartNode<TYPES, I, V>>,
     /// the next node unique identifier
     pub(crate) next_node_id: u64,
+    /// metrics recorded by all the nodes, each in its own subgroup
+    pub(crate) metrics: RecordingMetrics,
     /// Phantom for N
     pub(crate) _pd: PhantomData<N>,
 }
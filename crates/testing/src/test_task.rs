@@ -17,7 +17,10 @@ use hotshot::{
 };
 use hotshot_task_impls::{events::HotShotEvent, network::NetworkMessageTaskState};
 use hotshot_types::{
+    channels::ChannelConfig,
+    consensus::OuterConsensus,
     message::UpgradeLock,
+    replay::ReplayGuard,
     traits::{
         network::ConnectedNetwork,
         node_implementation::{NodeType, Versions},
@@ -162,6 +165,8 @@ pub async fn add_network_message_test_task<
     upgrade_lock: UpgradeLock<TYPES, V>,
     channel: Arc<NET>,
     public_key: TYPES::SignatureKey,
+    consensus: OuterConsensus<TYPES>,
+    membership: Arc<TYPES::Membership>,
 ) -> JoinHandle<()> {
     let net = Arc::clone(&channel);
     let network_state: NetworkMessageTaskState<_> = NetworkMessageTaskState {
@@ -169,12 +174,17 @@ pub async fn add_network_message_test_task<
         external_event_stream: external_event_stream.clone(),
         public_key,
         transactions_cache: lru::LruCache::new(NonZeroUsize::new(100_000).unwrap()),
+        transaction_limiter: None,
+        channels: ChannelConfig::default(),
+        consensus,
+        membership,
     };
 
     let network = Arc::clone(&net);
     let mut state = network_state.clone();
 
     spawn(async move {
+        let mut replay = ReplayGuard::default();
         loop {
             // Get the next message from the network
             let message = match network.recv_message().await {
@@ -187,7 +197,7 @@ pub async fn add_network_message_test_task<
 
             // Deserialize the message
             let deserialized_message: Message<TYPES> =
-                match upgrade_lock.deserialize(&message).await {
+                match upgrade_lock.deserialize_signed(&message, &mut replay).await {
                     Ok(message) => message,
                     Err(e) => {
                         tracing::error!("Failed to deserialize message: {:?}", e);
@@ -26,6 +26,7 @@ use hotshot_types::{
         ViewChangeEvidence, ViewNumber,
     },
     drb::{INITIAL_DRB_RESULT, INITIAL_DRB_SEED_INPUT},
+    hlc::HybridTimestamp,
     message::{Proposal, UpgradeLock},
     simple_certificate::{
         DaCertificate2, QuorumCertificate2, TimeoutCertificate2, UpgradeCertificate,
@@ -142,6 +143,8 @@ impl TestView {
             view_change_evidence: None,
             drb_result: INITIAL_DRB_RESULT,
             drb_seed: INITIAL_DRB_SEED_INPUT,
+            high_qc_proof: None,
+            epoch_checkpoint: None,
         };
 
         let encoded_transactions = Arc::from(TestTransaction::encode(&transactions));
@@ -357,7 +360,7 @@ impl TestView {
 
         let block_header = TestBlockHeader {
             block_number: *next_view,
-            timestamp: *next_view,
+            timestamp: HybridTimestamp::new(*next_view, 0),
             payload_commitment,
             builder_commitment,
             metadata,
@@ -372,6 +375,8 @@ impl TestView {
             view_change_evidence,
             drb_result: INITIAL_DRB_RESULT,
             drb_seed: INITIAL_DRB_SEED_INPUT,
+            high_qc_proof: None,
+            epoch_checkpoint: None,
         };
 
         let mut leaf = Leaf2::from_quorum_proposal(&proposal);
@@ -441,14 +446,13 @@ impl TestView {
         &self,
         handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
     ) -> QuorumVote2<TestTypes> {
-        QuorumVote2::<TestTypes>::create_signed_vote(
+        QuorumVote2::<TestTypes>::create_signed_vote_with_signer(
             QuorumData2 {
                 leaf_commit: self.leaf.commit(),
                 epoch: self.epoch_number,
             },
             self.view_number,
-            &handle.public_key(),
-            handle.private_key(),
+            handle.signer().as_ref(),
             &handle.hotshot.upgrade_lock,
         )
         .await
@@ -460,11 +464,10 @@ impl TestView {
         data: UpgradeProposalData<TestTypes>,
         handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
     ) -> UpgradeVote<TestTypes> {
-        UpgradeVote::<TestTypes>::create_signed_vote(
+        UpgradeVote::<TestTypes>::create_signed_vote_with_signer(
             data,
             self.view_number,
-            &handle.public_key(),
-            handle.private_key(),
+            handle.signer().as_ref(),
             &handle.hotshot.upgrade_lock,
         )
         .await
@@ -476,11 +479,10 @@ impl TestView {
         data: DaData2<TestTypes>,
         handle: &SystemContextHandle<TestTypes, MemoryImpl, TestVersions>,
     ) -> DaVote2<TestTypes> {
-        DaVote2::create_signed_vote(
+        DaVote2::create_signed_vote_with_signer(
             data,
             self.view_number,
-            &handle.public_key(),
-            handle.private_key(),
+            handle.signer().as_ref(),
             &handle.hotshot.upgrade_lock,
         )
         .await
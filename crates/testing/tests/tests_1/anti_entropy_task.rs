@@ -0,0 +1,92 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::{
+    anti_entropy::AntiEntropyTaskState, events::HotShotEvent, harness::run_harness,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    anti_entropy::{AntiEntropyItem, AntiEntropyStore},
+    data::ViewNumber,
+    signature_key::BLSPubKey,
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a node serves the proposals it holds, asks a peer only for those of its digest it
+// misses, and handles the ones it gets back as if gossip had delivered them.
+async fn test_anti_entropy_task_reconciles_proposals() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let public_key = handle.public_key().clone();
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut proposals = vec![];
+    let mut leaders = vec![];
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.quorum_proposal.clone());
+        leaders.push(view.leader_public_key);
+    }
+    let held = AntiEntropyItem::Proposal(proposals[0].clone());
+    let missed = AntiEntropyItem::Proposal(proposals[1].clone());
+    let digest = vec![held.entry(), missed.entry()];
+
+    let input = vec![
+        HotShotEvent::QuorumProposalRecv(proposals[0].clone(), leaders[0]),
+        HotShotEvent::AntiEntropyRequestRecv(digest.clone(), peer),
+        HotShotEvent::AntiEntropyDigestRecv(digest, peer),
+        // Sent twice, but only taken once
+        HotShotEvent::AntiEntropyResponseRecv(vec![missed.clone()], peer),
+        HotShotEvent::AntiEntropyResponseRecv(vec![missed.clone()], peer),
+        HotShotEvent::Shutdown,
+    ];
+    let output = vec![
+        HotShotEvent::AntiEntropyResponseSend(vec![held], public_key, peer),
+        HotShotEvent::AntiEntropyRequestSend(vec![missed.entry()], public_key, peer),
+        HotShotEvent::QuorumProposalRecv(proposals[1].clone(), peer),
+    ];
+
+    let mut state = AntiEntropyTaskState::<TestTypes>::create_from(&handle).await;
+    state.config.enabled = true;
+    run_harness(input, output, state, false).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the store forgets the messages of the views it prunes.
+async fn test_anti_entropy_store_prunes_old_views() {
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+
+    let mut store = AntiEntropyStore::<TestTypes>::default();
+    let mut entries = vec![];
+    for view in (&mut generator).take(3).collect::<Vec<_>>().await {
+        let item = AntiEntropyItem::Proposal(view.quorum_proposal.clone());
+        entries.push(item.entry());
+        assert!(store.insert(item.clone()));
+        assert!(!store.insert(item));
+
+        let vote = view.create_quorum_vote(&handle).await;
+        assert!(store.insert(AntiEntropyItem::QuorumVote(vote)));
+    }
+    assert_eq!(store.digest().len(), 6);
+    assert_eq!(store.get(&entries).len(), 3);
+
+    store.prune(*ViewNumber::new(3));
+    assert_eq!(store.digest().len(), 2);
+    assert!(!store.contains(&entries[1]));
+    assert!(store.contains(&entries[2]));
+}
@@ -0,0 +1,68 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::fmt::Debug;
+
+use hotshot_example_types::node_types::OrderingTypes;
+use hotshot_types::{
+    arbitrary::stake_table,
+    data::{Leaf2, QuorumProposal2},
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::QuorumVote2,
+};
+use proptest::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Check that `value` survives a round trip through bincode.
+fn round_trips<T: Serialize + DeserializeOwned + PartialEq + Debug>(
+    value: &T,
+) -> Result<(), TestCaseError> {
+    let bytes = bincode::serialize(value).unwrap();
+    prop_assert_eq!(&bincode::deserialize::<T>(&bytes).unwrap(), value);
+    Ok(())
+}
+
+proptest! {
+    // Drawing keys and signatures is slow, so draw fewer cases than usual
+    #![proptest_config(ProptestConfig::with_cases(16))]
+
+    #[test]
+    fn test_arbitrary_leaves_round_trip(leaf in any::<Leaf2<OrderingTypes>>()) {
+        round_trips(&leaf)?;
+    }
+
+    #[test]
+    fn test_arbitrary_proposals_round_trip(proposal in any::<QuorumProposal2<OrderingTypes>>()) {
+        round_trips(&proposal)?;
+
+        // The leaf of a proposal extends the leaf its QC is for
+        let leaf = Leaf2::from_quorum_proposal(&proposal);
+        prop_assert_eq!(leaf.parent_commitment(), proposal.justify_qc.data.leaf_commit);
+        prop_assert_eq!(leaf.view_number(), proposal.view_number);
+    }
+
+    #[test]
+    fn test_arbitrary_votes_round_trip(vote in any::<QuorumVote2<OrderingTypes>>()) {
+        round_trips(&vote)?;
+    }
+
+    #[test]
+    fn test_arbitrary_certificates_round_trip(qc in any::<QuorumCertificate2<OrderingTypes>>()) {
+        round_trips(&qc)?;
+    }
+
+    #[test]
+    fn test_arbitrary_stake_tables_have_distinct_keys(
+        table in stake_table::<BLSPubKey>(1..8)
+    ) {
+        round_trips(&table)?;
+        let keys: Vec<_> = table.iter().map(|peer| &peer.stake_table_entry.stake_key).collect();
+        for (i, key) in keys.iter().enumerate() {
+            prop_assert!(!keys[..i].contains(key));
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use futures::{StreamExt, TryStreamExt};
+use hotshot::archive::MemoryArchive;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{data::Leaf2, traits::archive::ArchiveStorage};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the ancestors of a leaf are walked newest first, and that the walk stops at a gap in
+// the archive.
+async fn test_archive_ancestors() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut leaves = Vec::new();
+    for _ in 0..6 {
+        leaves.push(generator.next().await.unwrap().leaf);
+    }
+
+    let archive = MemoryArchive::<TestTypes>::default();
+    for leaf in &leaves {
+        archive.append_leaf(leaf.clone()).await.unwrap();
+    }
+    let heights =
+        |leaves: Vec<Leaf2<TestTypes>>| -> Vec<u64> { leaves.iter().map(Leaf2::height).collect() };
+
+    let tip = leaves[5].commit();
+    let ancestors: Vec<_> = archive.ancestors_of(tip).try_collect().await.unwrap();
+    assert_eq!(heights(ancestors), vec![5, 4, 3, 2, 1]);
+    assert_eq!(heights(archive.leaves(2..4).await.unwrap()), vec![2, 3]);
+
+    // The walk does not go past a leaf the archive is missing
+    let gapped = MemoryArchive::<TestTypes>::default();
+    for leaf in leaves.iter().filter(|leaf| leaf.height() != 3) {
+        gapped.append_leaf(leaf.clone()).await.unwrap();
+    }
+    let ancestors: Vec<_> = gapped.ancestors_of(tip).try_collect().await.unwrap();
+    assert_eq!(heights(ancestors), vec![5, 4]);
+
+    // Nor does it start from a leaf the archive does not have
+    let unknown = archive.ancestors_of(leaves[0].parent_commitment());
+    assert_eq!(unknown.count().await, 0);
+}
@@ -0,0 +1,74 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot::{archive::MemoryArchive, traits::election::static_committee::StaticCommittee};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    signature_key::BLSPubKey,
+    traits::{
+        archive::{verify_storage, ArchiveStorage, Corruption},
+        election::Membership,
+    },
+    ValidatorConfig,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that an intact archive verifies, that QCs are checked against the stake tables we are
+// given, and that a leaf which does not extend the one before it is reported.
+async fn test_verify_storage() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = &*handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    let mut views = Vec::new();
+    for _ in 0..4 {
+        views.push(generator.next().await.unwrap());
+    }
+    let archive = MemoryArchive::<TestTypes>::default();
+    for view in &views {
+        archive
+            .append_proposal(view.quorum_proposal.clone())
+            .await
+            .unwrap();
+        archive.append_leaf(view.leaf.clone()).await.unwrap();
+    }
+    assert_eq!(handle.verify_storage(&archive).await.unwrap(), vec![]);
+
+    // Nobody in another committee signed these QCs, except the genesis QC which needs no signatures
+    let strangers: Vec<_> = (0..4)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([1u8; 32], i, 1, true)
+                .public_config()
+        })
+        .collect();
+    let strangers = StaticCommittee::<TestTypes>::new(strangers.clone(), strangers);
+    let corruptions = verify_storage(&archive, &strangers, upgrade_lock)
+        .await
+        .unwrap();
+    assert_eq!(corruptions.len(), views.len() - 1);
+    assert!(corruptions
+        .iter()
+        .all(|corruption| matches!(corruption, Corruption::InvalidQc { .. })));
+
+    // A leaf from another branch, stored right after the tip of this one
+    generator.next_from_ancestor_view(views[0].clone()).await;
+    let fork = generator.current_view.clone().unwrap();
+    archive.append_leaf(fork.leaf.clone()).await.unwrap();
+    assert_eq!(
+        handle.verify_storage(&archive).await.unwrap(),
+        vec![Corruption::BrokenChain {
+            height: fork.leaf.height()
+        }]
+    );
+}
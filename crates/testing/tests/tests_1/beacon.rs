@@ -0,0 +1,49 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::beacon::{beacon_output, RandomnessBeacon};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_beacon_outputs_from_qcs() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut qcs = Vec::new();
+    for _ in 0..4 {
+        let view = generator.next().await.unwrap();
+        qcs.push(view.quorum_proposal.data.justify_qc.clone());
+    }
+
+    // The genesis QC has no signature, so it has no output
+    assert!(beacon_output(&qcs[0]).is_none());
+
+    let mut beacon = RandomnessBeacon::<TestTypes>::default();
+    for qc in &qcs {
+        beacon.record(qc);
+    }
+    let outputs: Vec<_> = qcs[1..]
+        .iter()
+        .map(|qc| beacon.output(qc.view_number).unwrap())
+        .collect();
+    assert_ne!(outputs[0], outputs[1]);
+    assert_ne!(outputs[1], outputs[2]);
+
+    // The seed for a view is the output of the latest view before it
+    let last_view = qcs[3].view_number;
+    assert_eq!(beacon.latest(), Some((last_view, outputs[2])));
+    assert_eq!(beacon.seed_for(last_view), Some(outputs[1]));
+    assert_eq!(beacon.seed_for(last_view + 1), Some(outputs[2]));
+
+    beacon.prune_before(last_view);
+    assert!(beacon.output(qcs[2].view_number).is_none());
+    assert!(beacon.seed_for(last_view).is_none());
+}
@@ -0,0 +1,93 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::num::NonZeroUsize;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::{archive::MemoryArchive, cached_storage::CachedStorage};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::traits::{
+    archive::ArchiveStorage,
+    metrics::NoMetrics,
+    storage::{Storage, StorageKey, StoredValue},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that values are served from the cache once read or written through it, that the least
+// recently used ones are evicted, and that writes through the cache keep it up to date.
+async fn test_cached_storage() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut views = Vec::new();
+    for _ in 0..3 {
+        views.push(generator.next().await.unwrap());
+    }
+    let proposal_key = |i: usize| StorageKey::Proposal(views[i].view_number);
+    let is_cached = |value: Option<StoredValue<TestTypes>>| value.is_some();
+
+    let storage = CachedStorage::new(
+        TestStorage::<TestTypes>::default(),
+        NonZeroUsize::new(2).unwrap(),
+        &*NoMetrics::boxed(),
+        "test",
+    );
+    for view in &views {
+        storage
+            .inner()
+            .append_proposal2(&view.quorum_proposal)
+            .await
+            .unwrap();
+    }
+    for i in 0..3 {
+        assert!(is_cached(storage.load(&proposal_key(i)).await.unwrap()));
+    }
+
+    // Once the backend drops them, only the two most recently read are still served
+    storage
+        .inner()
+        .prune(views[2].view_number + 1)
+        .await
+        .unwrap();
+    assert!(is_cached(storage.load(&proposal_key(2)).await.unwrap()));
+    assert!(is_cached(storage.load(&proposal_key(1)).await.unwrap()));
+    assert!(!is_cached(storage.load(&proposal_key(0)).await.unwrap()));
+
+    // Pruning through the cache drops what it pruned
+    storage.prune(views[2].view_number).await.unwrap();
+    assert!(!is_cached(storage.load(&proposal_key(1)).await.unwrap()));
+    assert!(is_cached(storage.load(&proposal_key(2)).await.unwrap()));
+
+    // Leaves written to an archive through the cache are served from it
+    let archive = CachedStorage::new(
+        MemoryArchive::<TestTypes>::default(),
+        NonZeroUsize::new(16).unwrap(),
+        &*NoMetrics::boxed(),
+        "archive",
+    );
+    for view in &views {
+        archive.append_leaf(view.leaf.clone()).await.unwrap();
+    }
+    let leaf = &views[1].leaf;
+    assert_eq!(
+        archive.leaf_by_commitment(leaf.commit()).await.unwrap(),
+        Some(leaf.clone())
+    );
+    assert_eq!(
+        archive.leaf_by_view(leaf.view_number()).await.unwrap(),
+        Some(leaf.clone())
+    );
+    assert_eq!(archive.leaves(1..4).await.unwrap().len(), 3);
+}
@@ -0,0 +1,105 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    checkpoint::{Checkpoint, CheckpointError},
+    message::UpgradeLock,
+    traits::election::Membership,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a checkpoint accepts a chain extending the anchored leaf which is certified by a QC
+// of the checkpointed stake table, and rejects broken chains, QCs on other leaves and other stake
+// tables.
+async fn test_checkpoint_verifies_certified_extension() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    let mut views = Vec::new();
+    for _ in 0..4 {
+        views.push(generator.next().await.unwrap());
+    }
+    // The QC on the leaf of each view is carried by the proposal of the next one
+    let qc = |i: usize| views[i + 1].quorum_proposal.data.justify_qc.clone();
+
+    let epoch = qc(0).data.epoch;
+    let stake_table = membership.stake_table(epoch).into_owned();
+    let threshold = membership.success_threshold(epoch);
+
+    let checkpoint = Checkpoint::new(&views[0].leaf, qc(0), stake_table.clone()).unwrap();
+    assert_eq!(
+        Checkpoint::new(&views[1].leaf, qc(0), stake_table.clone()),
+        Err(CheckpointError::QcMismatch { view: 1 })
+    );
+
+    let extension = [views[1].leaf.clone(), views[2].leaf.clone()];
+    assert_eq!(
+        checkpoint
+            .verify_certified_extension(
+                &extension,
+                &qc(2),
+                stake_table.clone(),
+                threshold,
+                &upgrade_lock
+            )
+            .await,
+        Ok(())
+    );
+
+    // A chain skipping a leaf
+    assert_eq!(
+        checkpoint
+            .verify_certified_extension(
+                &extension[1..],
+                &qc(2),
+                stake_table.clone(),
+                threshold,
+                &upgrade_lock
+            )
+            .await,
+        Err(CheckpointError::BrokenChain {
+            height: views[2].leaf.height()
+        })
+    );
+
+    // A QC on a leaf other than the tip
+    assert_eq!(
+        checkpoint
+            .verify_certified_extension(
+                &extension,
+                &qc(1),
+                stake_table.clone(),
+                threshold,
+                &upgrade_lock
+            )
+            .await,
+        Err(CheckpointError::QcMismatch { view: 2 })
+    );
+
+    // A stake table other than the checkpointed one
+    assert_eq!(
+        checkpoint
+            .verify_certified_extension(
+                &extension,
+                &qc(2),
+                stake_table[1..].to_vec(),
+                threshold,
+                &upgrade_lock
+            )
+            .await,
+        Err(CheckpointError::StakeTableMismatch)
+    );
+}
@@ -0,0 +1,98 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{EpochsTestVersions, MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    helpers::build_system_handle_from_launcher,
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+};
+use hotshot_types::consensus::CommitRule;
+
+#[test]
+// Checks that the two-chain rule applies exactly when the configured rule asks for it.
+fn commit_rule_selects_the_decide_rule() {
+    assert!(!CommitRule::ByVersion.two_chain(false));
+    assert!(CommitRule::ByVersion.two_chain(true));
+    assert!(!CommitRule::ThreeChain.two_chain(false));
+    assert!(!CommitRule::ThreeChain.two_chain(true));
+    assert!(CommitRule::TwoChain.two_chain(false));
+    assert!(CommitRule::TwoChain.two_chain(true));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the two-chain rule decides within three views, which the three-chain rule cannot
+// do.
+async fn two_chain_rule_decides_in_three_views() {
+    hotshot::helpers::initialize_logging();
+
+    let mut metadata: TestDescription<TestTypes, MemoryImpl, EpochsTestVersions> =
+        TestDescription {
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                TimeBasedCompletionTaskDescription {
+                    duration: Duration::from_secs(100),
+                },
+            ),
+            ..TestDescription::default()
+        };
+    // The leader after the first three is down, so only a decide within three views succeeds
+    metadata.spinning_properties = SpinningTaskDescription {
+        node_changes: vec![(
+            1,
+            vec![ChangeNode {
+                idx: 4,
+                updown: NodeAction::Down,
+            }],
+        )],
+    };
+    metadata.overall_safety_properties.num_successful_views = 1;
+    metadata.overall_safety_properties.num_failed_views = 0;
+
+    metadata
+        .gen_launcher(0)
+        .modify_default_config(|config| config.commit_rule = CommitRule::TwoChain)
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the three-chain rule keeps deciding safely after the epochs upgrade.
+async fn three_chain_rule_holds_after_the_epochs_upgrade() {
+    hotshot::helpers::initialize_logging();
+
+    let metadata: TestDescription<TestTypes, MemoryImpl, EpochsTestVersions> = TestDescription {
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestDescription::default()
+    };
+
+    metadata
+        .gen_launcher(0)
+        .modify_default_config(|config| config.commit_rule = CommitRule::ThreeChain)
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+#[should_panic(expected = "two-chain commit rule needs the epochs upgrade")]
+// Checks that a node refuses the two-chain rule when it would start before the epochs upgrade,
+// as its proposals could not prove that they extend the highest QC.
+async fn two_chain_rule_is_rejected_before_the_epochs_upgrade() {
+    let launcher = TestDescription::<TestTypes, MemoryImpl, TestVersions>::default()
+        .gen_launcher(0)
+        .modify_default_config(|config| config.commit_rule = CommitRule::TwoChain);
+
+    build_system_handle_from_launcher(0, &launcher).await;
+}
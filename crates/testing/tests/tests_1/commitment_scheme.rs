@@ -0,0 +1,54 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::Leaf2,
+    traits::{
+        commitment::{CommitmentScheme, KeccakCommitments},
+        node_implementation::NodeType,
+    },
+};
+
+/// A scheme which commits to block headers differently from the default
+#[derive(Clone, Copy, Debug, Default)]
+struct OtherHeaderCommitments;
+
+impl CommitmentScheme<TestTypes> for OtherHeaderCommitments {
+    fn block_header(
+        header: &<TestTypes as NodeType>::BlockHeader,
+    ) -> Commitment<<TestTypes as NodeType>::BlockHeader> {
+        RawCommitmentBuilder::new("other header commitment")
+            .field("header", header.commit())
+            .finalize()
+    }
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that leaves are committed to with the scheme of their node type, and that swapping how
+// block headers are committed to changes the leaf commitment too.
+async fn test_leaf_commitment_follows_the_scheme() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+
+    let mut generator = TestViewGenerator::generate(membership);
+    let view = generator.next().await.unwrap();
+    let leaf = Leaf2::from_quorum_proposal(&view.quorum_proposal.data);
+
+    assert_eq!(
+        leaf.commit(),
+        <KeccakCommitments as CommitmentScheme<TestTypes>>::leaf(&leaf)
+    );
+    assert_ne!(leaf.commit(), OtherHeaderCommitments::leaf(&leaf));
+}
@@ -0,0 +1,50 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot::traits::election::static_committee::StaticCommittee;
+use hotshot_example_types::node_types::{TestTypes, TestTypesRandomizedLeader};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    error::{ConsensusError, Severity},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    ValidatorConfig,
+};
+
+type RandomizedMembership = <TestTypesRandomizedLeader as NodeType>::Membership;
+
+#[test]
+// Checks that a leader missing for want of a DRB result only costs us the view, while a committee
+// nobody may lead stops the node.
+fn leader_errors_are_typed_by_severity() {
+    let peers: Vec<_> = (0..4)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                .public_config()
+        })
+        .collect();
+    let view = ViewNumber::new(100);
+    let epoch = EpochNumber::new(3);
+
+    let randomized = RandomizedMembership::new(peers.clone(), peers);
+    let error = randomized.try_leader(view, epoch).unwrap_err();
+    assert_eq!(error, ConsensusError::MissingDrbResult(epoch));
+    assert_eq!(error.severity(), Severity::ViewFatal);
+
+    let empty = StaticCommittee::<TestTypes>::new(vec![], vec![]);
+    let error = empty.try_leader(view, epoch).unwrap_err();
+    assert_eq!(error, ConsensusError::NoEligibleLeaders(epoch));
+    assert_eq!(error.severity(), Severity::NodeFatal);
+    assert!(empty.leader(view, epoch).is_err());
+
+    assert_eq!(
+        ConsensusError::<TestTypes>::StaleMessage(view).severity(),
+        Severity::Recoverable
+    );
+}
@@ -4,7 +4,7 @@
 // You should have received a copy of the MIT License
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
-use std::{sync::Arc, time::Duration};
+use std::{marker::PhantomData, sync::Arc, time::Duration};
 
 use futures::StreamExt;
 use hotshot::tasks::task_state::CreateTaskState;
@@ -15,7 +15,7 @@ use hotshot_example_types::{
 use hotshot_macros::{run_test, test_scripts};
 use hotshot_task_impls::{da::DaTaskState, events::HotShotEvent::*};
 use hotshot_testing::{
-    helpers::build_system_handle,
+    helpers::{build_system_handle, key_pair_for_id},
     predicates::event::exact,
     script::{Expectations, InputOrder, TaskScript},
     serial,
@@ -23,11 +23,14 @@ use hotshot_testing::{
 };
 use hotshot_types::{
     data::{null_block, EpochNumber, PackedBundle, ViewNumber},
+    message::Proposal,
+    payload_stream::DaProposalHeader,
     simple_vote::DaData2,
     traits::{
         block_contents::precompute_vid_commitment,
         election::Membership,
-        node_implementation::{ConsensusTime, Versions},
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::SignatureKey,
     },
 };
 use vbs::version::StaticVersionType;
@@ -248,3 +251,87 @@ async fn test_da_task_storage_failure() {
 
     run_test![inputs, da_script].await;
 }
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_da_task_streamed_proposal() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+
+    let membership = (*handle.hotshot.memberships).clone();
+
+    let transactions = vec![TestTransaction::new(vec![0])];
+    let encoded_transactions = Arc::from(TestTransaction::encode(&transactions));
+    let (payload_commit, _) = precompute_vid_commitment(
+        &encoded_transactions,
+        handle.hotshot.memberships.total_nodes(EpochNumber::new(0)),
+    );
+
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    generator.add_transactions(transactions);
+
+    let mut proposals = Vec::new();
+    let mut leaders = Vec::new();
+    let mut votes = Vec::new();
+
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        proposals.push(view.da_proposal.clone());
+        leaders.push(view.leader_public_key);
+        votes.push(
+            view.create_da_vote(
+                DaData2 {
+                    payload_commit,
+                    epoch: view.da_proposal.data.epoch,
+                },
+                &handle,
+            )
+            .await,
+        );
+    }
+
+    // Stream the proposal for view 2 in chunks of 2 bytes, signed by its leader
+    let (header, chunks) = DaProposalHeader::split(&proposals[1], 2);
+    assert!(chunks.len() > 1);
+    let (private_key, _) = key_pair_for_id::<TestTypes>(2);
+    let header = Proposal {
+        signature: <TestTypes as NodeType>::SignatureKey::sign(&private_key, &header.digest())
+            .unwrap(),
+        data: header,
+        _pd: PhantomData,
+    };
+
+    let inputs = vec![
+        serial![
+            ViewChange(ViewNumber::new(1), EpochNumber::new(0)),
+            ViewChange(ViewNumber::new(2), EpochNumber::new(0)),
+            // Chunks may overtake their header
+            DaPayloadChunkRecv(chunks[0].clone(), leaders[1]),
+            DaProposalHeaderRecv(header, leaders[1]),
+        ],
+        InputOrder::Serial(
+            chunks[1..]
+                .iter()
+                .map(|chunk| DaPayloadChunkRecv(chunk.clone(), leaders[1]))
+                .collect(),
+        ),
+    ];
+
+    let da_state = DaTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    let mut da_script = TaskScript {
+        timeout: Duration::from_millis(35),
+        state: da_state,
+        expectations: vec![
+            Expectations::from_outputs(vec![]),
+            // The last chunk completes the proposal, which is validated without another pass
+            // over its payload
+            Expectations::from_outputs(vec![
+                exact(DaProposalValidated(proposals[1].clone(), leaders[1])),
+                exact(DaVoteSend(votes[1].clone())),
+            ]),
+        ],
+    };
+
+    run_test![inputs, da_script].await;
+}
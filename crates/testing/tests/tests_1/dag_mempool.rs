@@ -0,0 +1,219 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::marker::PhantomData;
+
+use committable::{Commitment, Committable};
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::{
+    block_types::{TestMetadata, TestTransaction},
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_task_impls::{
+    dag_mempool::DagMempoolTaskState, events::HotShotEvent, harness::run_harness,
+};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    dag_mempool::{BatchProposal, BatchStore, TransactionBatch},
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    simple_certificate::BatchCertificate,
+    simple_vote::{BatchData, BatchVote},
+    traits::{
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
+        signature_key::SignatureKey,
+    },
+};
+
+/// A batch of `transactions` sent by node `author` in `view`.
+fn batch(author: u64, view: u64, transactions: &[u8]) -> TransactionBatch<TestTypes> {
+    TransactionBatch {
+        author: BLSPubKey::generated_from_seed_indexed([0u8; 32], author).0,
+        view: ViewNumber::new(view),
+        transactions: transactions
+            .iter()
+            .map(|tx| TestTransaction::new(vec![*tx]))
+            .collect(),
+    }
+}
+
+/// An unsigned certificate for the batch with commitment `batch_commit`, sent in `view`.
+fn certificate(
+    batch_commit: Commitment<TransactionBatch<TestTypes>>,
+    view: u64,
+) -> BatchCertificate<TestTypes> {
+    let data = BatchData {
+        batch_commit,
+        epoch: EpochNumber::genesis(),
+    };
+    let vote_commitment = data.commit();
+
+    BatchCertificate::new(
+        data,
+        vote_commitment,
+        ViewNumber::new(view),
+        None,
+        PhantomData,
+    )
+}
+
+/// The batch commitments certified by `certificates`, in order.
+fn batches(
+    certificates: &[BatchCertificate<TestTypes>],
+) -> Vec<Commitment<TransactionBatch<TestTypes>>> {
+    certificates
+        .iter()
+        .map(|cert| cert.data.batch_commit)
+        .collect()
+}
+
+#[test]
+// Checks that leaders only propose certified batches which no other leader has claimed, and that
+// a claim whose proposal is never validated is released.
+fn batch_store_offers_each_certified_batch_once() {
+    let mut store = BatchStore::<TestTypes>::default();
+    let a = store.insert(batch(0, 1, &[1, 2]));
+    let b = store.insert(batch(1, 2, &[3]));
+    let c = store.insert(batch(2, 2, &[4]));
+
+    // Nothing is certified yet
+    assert!(store.next_block(10).is_empty());
+
+    store.certify(certificate(b, 2));
+    store.certify(certificate(a, 1));
+    // A certificate received twice is only offered once
+    store.certify(certificate(a, 1));
+    assert_eq!(batches(&store.next_block(10)), vec![a, b]);
+    assert_eq!(batches(&store.next_block(1)), vec![a]);
+    assert!(store.holds_all(&[a, b]));
+    assert_eq!(
+        store.transactions(&[a, b]),
+        vec![
+            TestTransaction::new(vec![1]),
+            TestTransaction::new(vec![2]),
+            TestTransaction::new(vec![3])
+        ]
+    );
+
+    // The leader of view 3 claims `a`, and its proposal is validated
+    store.claim(ViewNumber::new(3), vec![a]);
+    assert_eq!(batches(&store.next_block(10)), vec![b]);
+    store.confirm(ViewNumber::new(3));
+    assert!(store.get(&a).is_none());
+
+    // The leader of view 4 claims `b`, but its proposal never shows up
+    store.certify(certificate(c, 2));
+    store.claim(ViewNumber::new(4), vec![b]);
+    assert_eq!(batches(&store.next_block(10)), vec![c]);
+    store.release_before(ViewNumber::new(5));
+    assert_eq!(batches(&store.next_block(10)), vec![b, c]);
+
+    // A claim arriving after its proposal was validated drops the batches at once
+    store.confirm(ViewNumber::new(6));
+    store.claim(ViewNumber::new(6), vec![c]);
+    assert!(store.get(&c).is_none());
+    assert!(!store.holds_all(&[b, c]));
+
+    store.prune_before(ViewNumber::new(3));
+    assert!(store.is_empty());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a node with stake votes for a batch it receives from its author, and ignores one
+// relayed by anyone else.
+async fn test_dag_mempool_task_votes_for_batches() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+
+    let author = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let relay = BLSPubKey::generated_from_seed_indexed([0u8; 32], 3).0;
+    let batch = batch(1, 1, &[1, 2, 3]);
+    let vote = BatchVote::create_signed_vote(
+        BatchData {
+            batch_commit: batch.commit(),
+            epoch: handle.cur_epoch().await,
+        },
+        ViewNumber::new(1),
+        ConsensusApi::public_key(&handle),
+        ConsensusApi::private_key(&handle),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .expect("Failed to create a batch vote");
+
+    let input = vec![
+        HotShotEvent::BatchRecv(batch.clone(), relay),
+        HotShotEvent::BatchRecv(batch, author),
+        HotShotEvent::Shutdown,
+    ];
+    let output = vec![HotShotEvent::BatchVoteSend(vote, author)];
+
+    let dag_mempool_state =
+        DagMempoolTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    run_harness(input, output, dag_mempool_state, false).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a node does not take the block of a batch proposal through DA and VID when the
+// proposal was not sent by the leader of its view, even if it holds every batch of the block.
+async fn test_dag_mempool_task_ignores_proposals_from_non_leaders() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let epoch = handle.cur_epoch().await;
+    let view = ViewNumber::new(2);
+
+    let leader = handle.hotshot.memberships.leader(view, epoch).unwrap();
+    let (impostor, impostor_key) = (0..)
+        .map(|index| BLSPubKey::generated_from_seed_indexed([0u8; 32], index))
+        .find(|(key, _)| *key != leader)
+        .unwrap();
+
+    let author = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let batch = batch(1, 1, &[1, 2, 3]);
+    let vote = BatchVote::create_signed_vote(
+        BatchData {
+            batch_commit: batch.commit(),
+            epoch,
+        },
+        ViewNumber::new(1),
+        ConsensusApi::public_key(&handle),
+        ConsensusApi::private_key(&handle),
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .expect("Failed to create a batch vote");
+
+    let signature = BLSPubKey::sign(&impostor_key, &[]).unwrap();
+    let proposal = BatchProposal {
+        view_number: view,
+        epoch,
+        certificates: vec![certificate(batch.commit(), 1)],
+        metadata: TestMetadata {
+            num_transactions: 3,
+        },
+        da_signature: signature.clone(),
+        vid_signature: signature,
+    };
+
+    let input = vec![
+        HotShotEvent::BatchRecv(batch, author),
+        HotShotEvent::BatchProposalRecv(proposal, impostor),
+        HotShotEvent::Shutdown,
+    ];
+    let output = vec![HotShotEvent::BatchVoteSend(vote, author)];
+
+    let dag_mempool_state =
+        DagMempoolTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    run_harness(input, output, dag_mempool_state, false).await;
+}
@@ -0,0 +1,65 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{data::ViewNumber, traits::node_implementation::ConsensusTime};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that discarding a leaf drops it and every saved leaf extending it, marks their views as
+// failed and returns those views, while leaves on another fork are kept.
+async fn test_discard_leaf_removes_descendants() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+
+    // Views 1 to 3 form a chain, and view 4 forks off view 1.
+    let mut generator = TestViewGenerator::generate(membership);
+    let mut views = (&mut generator).take(3).collect::<Vec<_>>().await;
+    generator.next_from_ancestor_view(views[0].clone()).await;
+    views.push(generator.current_view.clone().unwrap());
+    let leaves: Vec<_> = views.iter().map(|view| view.leaf.clone()).collect();
+
+    let consensus = handle.hotshot.consensus();
+    let mut consensus_writer = consensus.write().await;
+    for leaf in &leaves {
+        consensus_writer
+            .update_leaf(leaf.clone(), Arc::new(TestValidatedState::default()), None)
+            .unwrap();
+    }
+
+    let discarded = consensus_writer.discard_leaf(leaves[1].commit()).unwrap();
+    assert_eq!(discarded, vec![ViewNumber::new(2), ViewNumber::new(3)]);
+
+    for leaf in &leaves[1..3] {
+        assert!(!consensus_writer.saved_leaves().contains_key(&leaf.commit()));
+        assert_eq!(
+            consensus_writer.validated_state_map()[&leaf.view_number()].leaf_commitment(),
+            None
+        );
+    }
+    for leaf in [&leaves[0], &leaves[3]] {
+        assert!(consensus_writer.saved_leaves().contains_key(&leaf.commit()));
+        assert_eq!(
+            consensus_writer.validated_state_map()[&leaf.view_number()].leaf_commitment(),
+            Some(leaf.commit())
+        );
+    }
+
+    // The leaf is gone, so it can't be discarded twice.
+    assert!(consensus_writer.discard_leaf(leaves[1].commit()).is_err());
+}
@@ -0,0 +1,19 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot::traits::election::test_vectors::ElectionVectors;
+use hotshot_example_types::node_types::TestTypes;
+
+#[test]
+// Checks that elections still pick the leaders and quorums of the committed test vectors. If this
+// fails, nodes running this version would fork from nodes running the previous one.
+fn test_election_matches_committed_vectors() {
+    let committed = ElectionVectors::committed();
+    committed.verify::<TestTypes>().unwrap();
+
+    // The committed vectors cover every input, so none is left unchecked
+    assert_eq!(ElectionVectors::generate::<TestTypes>(), committed);
+}
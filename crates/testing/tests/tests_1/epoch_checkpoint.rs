@@ -0,0 +1,89 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::num::NonZeroU64;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    checkpoint::{CheckpointError, EpochCheckpoint},
+    data::{EpochNumber, Leaf2},
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that only the last block of an epoch carries a checkpoint, that the checkpoint is of the
+// next epoch's committees, and that a committee other than the checkpointed one is rejected.
+async fn test_epoch_checkpoint_anchors_the_next_committee() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = &*handle.hotshot.memberships;
+
+    assert!(EpochCheckpoint::<TestTypes>::for_block(0, 10, membership).is_none());
+    assert!(EpochCheckpoint::<TestTypes>::for_block(19, 10, membership).is_none());
+    assert!(EpochCheckpoint::<TestTypes>::for_block(20, 0, membership).is_none());
+
+    let checkpoint = EpochCheckpoint::<TestTypes>::for_block(20, 10, membership)
+        .expect("The last block of an epoch should carry a checkpoint");
+    let epoch = EpochNumber::new(3);
+    assert_eq!(checkpoint, EpochCheckpoint::new(membership, epoch));
+
+    let stake_table = membership.stake_table(epoch).into_owned();
+    let threshold = membership.success_threshold(epoch);
+    assert_eq!(
+        checkpoint.verify_quorum(stake_table.clone(), threshold),
+        Ok(())
+    );
+
+    // A committee missing a member
+    assert_eq!(
+        checkpoint.verify_quorum(stake_table[1..].to_vec(), threshold),
+        Err(CheckpointError::StakeTableMismatch)
+    );
+
+    // A committee which needs less stake to certify
+    let lower = NonZeroU64::new(threshold.get() - 1).unwrap();
+    assert_eq!(
+        checkpoint.verify_quorum(stake_table, lower),
+        Err(CheckpointError::ThresholdMismatch {
+            expected: threshold.get(),
+            actual: lower.get()
+        })
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the checkpoint a proposal carries is part of its leaf, and so signed by the leader
+// and certified by the QC on the leaf.
+async fn test_epoch_checkpoint_is_committed_by_the_leaf() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    let view = generator.next().await.unwrap();
+    let mut proposal = view.quorum_proposal.data.clone();
+    let plain = Leaf2::from_quorum_proposal(&proposal);
+    assert!(plain.epoch_checkpoint().is_none());
+
+    proposal.epoch_checkpoint = Some(EpochCheckpoint::new(&membership, EpochNumber::new(2)));
+    let anchored = Leaf2::from_quorum_proposal(&proposal);
+    assert_eq!(
+        anchored.epoch_checkpoint(),
+        proposal.epoch_checkpoint.as_ref()
+    );
+    assert_ne!(plain.commit(), anchored.commit());
+}
@@ -0,0 +1,164 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{collections::HashMap, marker::PhantomData};
+
+use either::Either;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_task_impls::{events::HotShotEvent, fallback::FallbackTaskState, harness::run_harness};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    helpers::build_system_handle,
+    spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+    test_builder::TestDescription,
+};
+use hotshot_types::{
+    data::ViewNumber,
+    fallback::{elect_leader, FallbackConfig},
+    signature_key::BLSPubKey,
+    simple_vote::{FallbackData, FallbackVote},
+    traits::{
+        election::Membership, node_implementation::ConsensusTime, signature_key::SignatureKey,
+    },
+    vote::VoteAccumulator,
+};
+
+#[test]
+// Checks that the fallback starts once enough views have failed in a row, and never when disabled.
+fn fallback_starts_after_the_configured_failed_views() {
+    let config = FallbackConfig {
+        enabled: true,
+        failed_views: 2,
+    };
+    assert!(!config.triggered(1));
+    assert!(config.triggered(2));
+    assert!(config.triggered(5));
+    assert!(!FallbackConfig::default().triggered(100));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that after a run of failed views a node votes in the fallback round, forms the round's
+// certificate from a quorum of votes, and hands the next view to the leader the coin elects.
+async fn test_fallback_task_elects_the_next_leader() {
+    hotshot::helpers::initialize_logging();
+
+    let node_id = 2;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let epoch = handle.cur_epoch().await;
+    let round = ViewNumber::new(4);
+
+    // Our own vote comes first, then those of the other nodes
+    let total_nodes = membership.total_nodes(epoch) as u64;
+    let mut votes = vec![];
+    for id in std::iter::once(node_id).chain((0..total_nodes).filter(|id| *id != node_id)) {
+        let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], id);
+        votes.push(
+            FallbackVote::create_signed_vote(
+                FallbackData { round, epoch },
+                round,
+                &public_key,
+                &private_key,
+                &handle.hotshot.upgrade_lock,
+            )
+            .await
+            .expect("Failed to create a fallback vote"),
+        );
+    }
+
+    // Gather the certificate the task should form from the same votes
+    let mut accumulator = VoteAccumulator {
+        vote_outcomes: HashMap::new(),
+        signers: HashMap::new(),
+        phantom: PhantomData,
+        upgrade_lock: handle.hotshot.upgrade_lock.clone(),
+    };
+    let mut cert = None;
+    let mut needed = 0;
+    for vote in &votes {
+        needed += 1;
+        if let Either::Right(formed) = accumulator.accumulate(vote, membership, epoch).await {
+            cert = Some(formed);
+            break;
+        }
+    }
+    let cert = cert.expect("A quorum of votes should form a certificate");
+    let leader = elect_leader(&cert, membership).expect("The certificate should elect a leader");
+
+    // Views 1 through 3 fail, so view 4 is a fallback round
+    let mut input = vec![
+        HotShotEvent::ViewChange(ViewNumber::new(1), epoch),
+        HotShotEvent::ViewChange(round, epoch),
+    ];
+    input.extend(
+        votes[1..needed]
+            .iter()
+            .cloned()
+            .map(HotShotEvent::FallbackVoteRecv),
+    );
+    input.push(HotShotEvent::Shutdown);
+    let output = vec![
+        HotShotEvent::FallbackVoteSend(votes[0].clone()),
+        HotShotEvent::FallbackCertificateSend(cert, handle.public_key()),
+    ];
+
+    let mut fallback_state =
+        FallbackTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    fallback_state.config = FallbackConfig {
+        enabled: true,
+        failed_views: 2,
+    };
+    run_harness(input, output, fallback_state, false).await;
+
+    assert_eq!(membership.leader(round + 1, epoch).unwrap(), leader);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that consensus stays live and safe when the fallback elects the leaders after two
+// consecutive leaders go down.
+async fn fallback_recovers_from_consecutive_failed_leaders() {
+    hotshot::helpers::initialize_logging();
+
+    let mut metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default_more_nodes();
+    metadata.num_bootstrap_nodes = 10;
+    metadata.num_nodes_with_stake = 12;
+    metadata.da_staked_committee_size = 12;
+    metadata.start_nodes = 12;
+    metadata.spinning_properties = SpinningTaskDescription {
+        node_changes: vec![(
+            5,
+            vec![
+                ChangeNode {
+                    idx: 10,
+                    updown: NodeAction::Down,
+                },
+                ChangeNode {
+                    idx: 11,
+                    updown: NodeAction::Down,
+                },
+            ],
+        )],
+    };
+    metadata.overall_safety_properties.num_failed_views = 2;
+    metadata.overall_safety_properties.num_successful_views = 13;
+
+    metadata
+        .gen_launcher(0)
+        .modify_default_config(|config| {
+            config.fallback = FallbackConfig {
+                enabled: true,
+                failed_views: 1,
+            };
+        })
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{sync::Arc, time::Duration};
+
+use futures::StreamExt;
+use hotshot::{
+    archive::MemoryArchive,
+    finalized_leaves::{FinalizedLeaves, LeafCursor},
+};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::traits::archive::ArchiveStorage;
+use tokio::{sync::watch, time::timeout};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a subscription resumes after its cursor, catches up from storage, waits for new
+// leaves and ends when the archiver stops.
+async fn test_finalized_leaf_subscription() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut leaves = Vec::new();
+    for _ in 0..5 {
+        leaves.push(generator.next().await.unwrap().leaf);
+    }
+
+    let archive = Arc::new(MemoryArchive::<TestTypes>::default());
+    for leaf in &leaves[..3] {
+        archive.append_leaf(leaf.clone()).await.unwrap();
+    }
+    let (archived, archived_height) = watch::channel(Some(2));
+    let finalized = FinalizedLeaves::new(Arc::clone(&archive), archived_height);
+
+    // Resuming after the first leaf delivers the backlog from storage
+    let mut subscription = finalized.subscribe(LeafCursor::After(leaves[0].view_number()));
+    for leaf in &leaves[1..3] {
+        assert_eq!(&subscription.next().await.unwrap().unwrap(), leaf);
+    }
+    let mut latest = finalized.subscribe(LeafCursor::<TestTypes>::Latest);
+
+    // Then waits for the next leaf to be archived
+    assert!(timeout(Duration::from_millis(100), subscription.next())
+        .await
+        .is_err());
+    assert!(timeout(Duration::from_millis(100), latest.next())
+        .await
+        .is_err());
+    for leaf in &leaves[3..] {
+        archive.append_leaf(leaf.clone()).await.unwrap();
+        archived.send_replace(Some(leaf.height()));
+    }
+    for leaf in &leaves[3..] {
+        assert_eq!(&subscription.next().await.unwrap().unwrap(), leaf);
+        assert_eq!(&latest.next().await.unwrap().unwrap(), leaf);
+    }
+
+    // A cursor which is not a decided view is an error
+    let mut unknown = finalized.subscribe(LeafCursor::After(leaves[4].view_number() + 1));
+    assert!(unknown.next().await.unwrap().is_err());
+
+    drop(archived);
+    assert!(subscription.next().await.is_none());
+}
@@ -0,0 +1,51 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::BTreeSet;
+
+use hotshot::traits::election::static_committee::StaticCommittee;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership, node_implementation::ConsensusTime,
+        signature_key::StakeTableEntryType,
+    },
+    ValidatorConfig,
+};
+
+#[test]
+// Checks that in the fixed-weight committee every member votes in every view with its full
+// stake, so the quorum size is known in advance.
+fn every_member_votes_every_view_with_its_stake() {
+    let peers: Vec<_> = (0..4)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, i + 1, true)
+                .public_config()
+        })
+        .collect();
+    let keys: BTreeSet<_> = peers
+        .iter()
+        .map(|peer| peer.stake_table_entry.public_key())
+        .collect();
+    let membership = StaticCommittee::<TestTypes>::new(peers.clone(), peers.clone());
+    let other = StaticCommittee::<TestTypes>::new(peers.clone(), peers);
+
+    for epoch in (0..3).map(EpochNumber::new) {
+        for view in (0..8).map(ViewNumber::new) {
+            assert_eq!(membership.committee_members(view, epoch), keys);
+            assert_eq!(
+                membership.leader(view, epoch).unwrap(),
+                other.leader(view, epoch).unwrap()
+            );
+        }
+
+        // Four members, of which more than two thirds make a quorum
+        assert_eq!(membership.success_threshold(epoch).get(), 3);
+        assert_eq!(membership.failure_threshold(epoch).get(), 2);
+    }
+}
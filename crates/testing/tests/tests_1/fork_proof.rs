@@ -0,0 +1,116 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    helpers::{build_cert, build_system_handle, key_pair_for_id},
+    view_generator::TestViewGenerator,
+};
+use hotshot_types::{
+    consensus::LeafMap,
+    data::ViewNumber,
+    message::UpgradeLock,
+    safety::{ForkProof, ForkProofError},
+    simple_certificate::QuorumCertificate2,
+    simple_vote::{QuorumData2, QuorumVote2},
+    traits::{election::Membership, node_implementation::ConsensusTime},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that QCs for different leaves in one view, or for leaves on different branches, are
+// found to conflict and that the proofs verify, while QCs along one chain do not conflict.
+async fn test_fork_proofs() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+
+    // Views 1 to 4 form a chain, and view 5 forks off view 1
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    let mut views = Vec::new();
+    for _ in 0..4 {
+        views.push(generator.next().await.unwrap());
+    }
+    generator.next_from_ancestor_view(views[0].clone()).await;
+    views.push(generator.current_view.clone().unwrap());
+    views.push(generator.next().await.unwrap());
+
+    let leaves: LeafMap<TestTypes> = views
+        .iter()
+        .map(|view| (view.leaf.commit(), Arc::new(view.leaf.clone())))
+        .collect();
+    // The QC of each view is carried by the proposal of the view after it
+    let qc_2 = views[2].quorum_proposal.data.justify_qc.clone();
+    let qc_3 = views[3].quorum_proposal.data.justify_qc.clone();
+    let qc_5 = views[5].quorum_proposal.data.justify_qc.clone();
+
+    let epoch = views[0].epoch_number;
+    let stake_table = membership.stake_table(epoch);
+    let threshold = membership.success_threshold(epoch);
+
+    // Along one chain
+    assert!(ForkProof::find(&qc_2, &qc_3, &leaves).is_none());
+
+    // On different branches
+    let proof = ForkProof::find(&qc_5, &qc_3, &leaves).unwrap();
+    let ForkProof::Divergence { ref branch, .. } = proof else {
+        panic!("QCs on different branches should diverge");
+    };
+    assert_eq!(branch.len(), 2);
+    assert_eq!(
+        proof.verify(&stake_table, threshold, &upgrade_lock).await,
+        Ok(())
+    );
+
+    // Different leaves in the same view
+    let (private_key, public_key) = key_pair_for_id::<TestTypes>(3);
+    let equivocating = build_cert::<
+        TestTypes,
+        TestVersions,
+        QuorumData2<TestTypes>,
+        QuorumVote2<TestTypes>,
+        QuorumCertificate2<TestTypes>,
+    >(
+        QuorumData2 {
+            leaf_commit: views[4].leaf.commit(),
+            epoch,
+        },
+        &membership,
+        ViewNumber::new(3),
+        epoch,
+        &public_key,
+        &private_key,
+        &upgrade_lock,
+    )
+    .await;
+    let proof = ForkProof::find(&qc_3, &equivocating, &leaves).unwrap();
+    assert!(matches!(proof, ForkProof::Equivocation { .. }));
+    assert_eq!(
+        proof.verify(&stake_table, threshold, &upgrade_lock).await,
+        Ok(())
+    );
+
+    // A proof which does not show a conflict is rejected
+    let bogus = ForkProof::Equivocation {
+        first: qc_3.clone(),
+        second: qc_3.clone(),
+    };
+    assert_eq!(
+        bogus.verify(&stake_table, threshold, &upgrade_lock).await,
+        Err(ForkProofError::NoConflict(
+            ViewNumber::new(3),
+            ViewNumber::new(3)
+        ))
+    );
+}
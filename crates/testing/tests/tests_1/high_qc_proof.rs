@@ -0,0 +1,111 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    data::{EpochNumber, HighQcProof, ViewNumber},
+    signature_key::BLSPubKey,
+    simple_vote::{HighQcData, HighQcVote},
+    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a proposal after a view change is only accepted with the reports of a quorum, each
+// signed for the proposal's view, none of which names a QC newer than the one it extends.
+async fn test_high_qc_proof_validation() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let epoch = EpochNumber::new(0);
+    let view = ViewNumber::new(6);
+
+    let report = |id: u64, view: ViewNumber, high_qc_view: u64| async move {
+        let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], id);
+        HighQcVote::create_signed_vote(
+            HighQcData {
+                high_qc_view: ViewNumber::new(high_qc_view),
+                epoch,
+            },
+            view,
+            &public_key,
+            &private_key,
+            upgrade_lock,
+        )
+        .await
+        .expect("Failed to create a high QC report")
+    };
+
+    // Gather reports until they come from a quorum, the last one naming the QC of view 4
+    let mut proof = HighQcProof { reports: vec![] };
+    let mut id = 0;
+    while !proof.is_from_quorum(membership, epoch) {
+        proof.reports.push(report(id, view, 3).await);
+        id += 1;
+    }
+    proof.reports.pop();
+    proof.reports.push(report(id - 1, view, 4).await);
+
+    assert!(proof
+        .validate(view, ViewNumber::new(4), epoch, membership, upgrade_lock)
+        .await
+        .is_ok());
+    assert!(proof
+        .validate(view, ViewNumber::new(5), epoch, membership, upgrade_lock)
+        .await
+        .is_ok());
+
+    // The leader extends a QC older than one a replica holds
+    assert!(proof
+        .validate(view, ViewNumber::new(3), epoch, membership, upgrade_lock)
+        .await
+        .is_err());
+
+    // The reports were made for another view
+    assert!(proof
+        .validate(
+            view + 1,
+            ViewNumber::new(4),
+            epoch,
+            membership,
+            upgrade_lock
+        )
+        .await
+        .is_err());
+
+    // A replica reports twice to make up for a missing one
+    let mut duplicated = proof.clone();
+    duplicated.reports.pop();
+    duplicated.reports.push(report(0, view, 3).await);
+    assert!(duplicated
+        .validate(view, ViewNumber::new(4), epoch, membership, upgrade_lock)
+        .await
+        .is_err());
+
+    // Too few replicas report
+    let mut short = proof.clone();
+    short.reports.pop();
+    assert!(short
+        .validate(view, ViewNumber::new(4), epoch, membership, upgrade_lock)
+        .await
+        .is_err());
+
+    // A report is signed for another view than it claims
+    let mut forged = proof.clone();
+    let mut stale = report(id - 1, view - 1, 4).await;
+    stale.view_number = view;
+    forged.reports.pop();
+    forged.reports.push(stale);
+    assert!(forged
+        .validate(view, ViewNumber::new(4), epoch, membership, upgrade_lock)
+        .await
+        .is_err());
+}
@@ -0,0 +1,196 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::BTreeSet;
+
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{
+        MemoryImpl, TestConsecutiveLeaderTypes, TestTwoStakeTablesTypes, TestTypes, TestVersions,
+    },
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    participation::{qc_signers, JailConfig, ParticipationTracker},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+        storage::Storage,
+    },
+    ValidatorConfig,
+};
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the signers of a QC are read back from its signature.
+async fn test_qc_signers() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    generator.next().await.unwrap();
+    let view = generator.next().await.unwrap();
+
+    let qc = view.quorum_proposal.data.justify_qc;
+    let stake_table = membership.stake_table(qc.data.epoch);
+    let signers: BTreeSet<_> = qc_signers(&qc, &stake_table).into_iter().collect();
+    let members: BTreeSet<_> = stake_table
+        .iter()
+        .map(<TestTypes as NodeType>::SignatureKey::public_key)
+        .collect();
+    assert_eq!(signers, members);
+}
+
+#[test]
+// Checks that a validator which misses most certificates of an epoch does not lead during the
+// penalty, and leads again once it is over.
+fn offline_validators_are_jailed_for_the_penalty() {
+    let peers: Vec<_> = (0..4)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                .public_config()
+        })
+        .collect();
+    let keys: Vec<_> = peers
+        .iter()
+        .map(|peer| peer.stake_table_entry.public_key())
+        .collect();
+    let membership = <TestTypes as NodeType>::Membership::new(peers.clone(), peers);
+    let config = JailConfig {
+        liveness_threshold_percent: 50,
+        penalty_epochs: 1,
+    };
+
+    // The last validator signs two certificates out of ten
+    let mut participation = ParticipationTracker::<TestTypes>::default();
+    for certificate in 0..10 {
+        let signers = if certificate < 2 {
+            &keys[..]
+        } else {
+            &keys[..3]
+        };
+        participation.record(EpochNumber::new(1), signers.to_vec());
+    }
+
+    let jailed_epoch = EpochNumber::new(3);
+    let jailed = participation.jailed(jailed_epoch, &membership, config);
+    assert_eq!(jailed.iter().collect::<Vec<_>>(), [&keys[3]]);
+    assert!(participation
+        .jailed(EpochNumber::new(4), &membership, config)
+        .is_empty());
+
+    membership.set_jailed(jailed_epoch, jailed);
+    let leaders = |epoch| {
+        (0..12)
+            .map(|view| membership.leader(ViewNumber::new(view), epoch).unwrap())
+            .collect::<BTreeSet<_>>()
+    };
+    assert!(!leaders(jailed_epoch).contains(&keys[3]));
+    assert!(leaders(EpochNumber::new(4)).contains(&keys[3]));
+}
+
+/// The leaders of the first views of `epoch`.
+fn leaders<TYPES: NodeType<View = ViewNumber>>(
+    membership: &TYPES::Membership,
+    epoch: TYPES::Epoch,
+) -> BTreeSet<TYPES::SignatureKey> {
+    (0..16)
+        .map(|view| membership.leader(ViewNumber::new(view), epoch).unwrap())
+        .collect()
+}
+
+/// Checks that `membership` passes over jailed validators and follows the fallback's leaders.
+fn check_eligibility<TYPES: NodeType<View = ViewNumber>>(
+    membership: &TYPES::Membership,
+    epoch: TYPES::Epoch,
+) {
+    let jailed = leaders::<TYPES>(membership, epoch)
+        .into_iter()
+        .next()
+        .unwrap();
+    membership.set_jailed(epoch, BTreeSet::from([jailed.clone()]));
+    assert!(!leaders::<TYPES>(membership, epoch).contains(&jailed));
+
+    membership.set_fallback_leader(ViewNumber::new(3), jailed.clone());
+    assert_eq!(
+        membership.leader(ViewNumber::new(3), epoch).unwrap(),
+        jailed
+    );
+}
+
+#[test]
+// Checks that the memberships with other leader rotations honour the jail and the fallback too.
+fn every_membership_honours_the_jail_and_fallback() {
+    let peers: Vec<_> = (0..4)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                .public_config()
+        })
+        .collect();
+
+    check_eligibility::<TestConsecutiveLeaderTypes>(
+        &<TestConsecutiveLeaderTypes as NodeType>::Membership::new(peers.clone(), peers.clone()),
+        EpochNumber::new(1),
+    );
+    check_eligibility::<TestTwoStakeTablesTypes>(
+        &<TestTwoStakeTablesTypes as NodeType>::Membership::new(peers.clone(), peers),
+        EpochNumber::new(2),
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that participation depends on the decided chain alone: recording it in other batches,
+// recording a leaf twice, or rebuilding it from storage after a restart gives the same result.
+async fn test_participation_follows_the_decided_chain() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let mut generator = TestViewGenerator::generate(membership.clone());
+    let mut views = Vec::new();
+    for _ in 0..6 {
+        views.push(generator.next().await.unwrap());
+    }
+    let config = JailConfig {
+        liveness_threshold_percent: 50,
+        penalty_epochs: 1,
+    };
+    let epoch_height = 2;
+
+    let mut one_by_one = ParticipationTracker::<TestTypes>::default();
+    for view in &views {
+        one_by_one.record_decided(&view.leaf, &membership, config, epoch_height);
+    }
+
+    // Two decides, the second of which repeats a leaf of the first
+    let mut batched = ParticipationTracker::<TestTypes>::default();
+    for view in views[..4].iter().chain(&views[3..]) {
+        batched.record_decided(&view.leaf, &membership, config, epoch_height);
+    }
+    assert_eq!(format!("{batched:?}"), format!("{one_by_one:?}"));
+
+    let storage = TestStorage::<TestTypes>::default();
+    for view in &views {
+        storage
+            .append_proposal2(&view.quorum_proposal)
+            .await
+            .unwrap();
+    }
+    let restored =
+        ParticipationTracker::restore(&storage, &views[5].leaf, &membership, config, epoch_height)
+            .await
+            .unwrap();
+    assert_eq!(format!("{restored:?}"), format!("{one_by_one:?}"));
+}
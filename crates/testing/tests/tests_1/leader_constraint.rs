@@ -0,0 +1,49 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    stake_table::{LeaderConstraint, NodeMetadata},
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    PeerConfig, ValidatorConfig,
+};
+
+#[test]
+// Checks that a round-robin election following an arranged stake table never has one operator
+// lead two views in a row.
+fn consecutive_leaders_have_different_operators() {
+    // Operators run blocks of nodes, so the configured order alone would have them lead in turns
+    let operators = ["a", "a", "a", "b", "b", "c", "c", "c"];
+    let peers: Vec<PeerConfig<BLSPubKey>> = (0..)
+        .zip(operators)
+        .map(|(i, operator)| PeerConfig {
+            metadata: NodeMetadata {
+                region: None,
+                operator: Some(operator.to_string()),
+            },
+            ..ValidatorConfig::generated_from_seed_indexed([0u8; 32], i, 1, true).public_config()
+        })
+        .collect();
+    let arranged = LeaderConstraint::DistinctOperator.arrange(peers.clone());
+    let membership = <TestTypes as NodeType>::Membership::new(arranged, peers.clone());
+    let epoch = EpochNumber::new(0);
+
+    let operator_of = |view| {
+        let leader = membership.leader(ViewNumber::new(view), epoch).unwrap();
+        peers
+            .iter()
+            .find(|peer| peer.stake_table_entry.stake_key == leader)
+            .and_then(|peer| peer.metadata.operator.clone())
+    };
+    for view in 0..24 {
+        assert_ne!(operator_of(view), operator_of(view + 1));
+    }
+}
@@ -0,0 +1,69 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_example_types::node_types::TestTypesRandomizedLeader;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+    },
+    ValidatorConfig,
+};
+
+type RandomizedMembership = <TestTypesRandomizedLeader as NodeType>::Membership;
+
+/// A randomized membership of ten nodes.
+fn membership() -> RandomizedMembership {
+    let peers: Vec<_> = (0..10)
+        .map(|i| {
+            ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                .public_config()
+        })
+        .collect();
+    RandomizedMembership::new(peers.clone(), peers)
+}
+
+/// The leaders of views 100 to 119 in `epoch`.
+fn leaders(membership: &RandomizedMembership, epoch: EpochNumber) -> Vec<BLSPubKey> {
+    (100..120)
+        .map(|view| membership.leader(ViewNumber::new(view), epoch).unwrap())
+        .collect()
+}
+
+#[test]
+// Checks that the leaders of an epoch cannot be worked out before its DRB result is known.
+fn leaders_are_unknown_before_the_drb_result() {
+    let membership = membership();
+    let epoch = EpochNumber::new(3);
+
+    assert!(membership.leader(ViewNumber::new(100), epoch).is_err());
+
+    // Clones share the result, since tasks hold the same membership
+    membership.clone().add_drb_result(epoch, [1; 32]);
+    assert!(membership.leader(ViewNumber::new(100), epoch).is_ok());
+}
+
+#[test]
+// Checks that leaders depend on the DRB result, not only on the view number.
+fn leaders_depend_on_the_drb_result() {
+    let epoch = EpochNumber::new(3);
+    let first = membership();
+    first.add_drb_result(epoch, [1; 32]);
+    let second = membership();
+    second.add_drb_result(epoch, [2; 32]);
+
+    assert_eq!(leaders(&first, epoch), leaders(&first, epoch));
+    assert_ne!(leaders(&first, epoch), leaders(&second, epoch));
+
+    // The first epochs start before any result is computed, so every node agrees on their leaders
+    let genesis_epoch = EpochNumber::new(1);
+    assert_eq!(
+        leaders(&first, genesis_epoch),
+        leaders(&second, genesis_epoch)
+    );
+}
@@ -0,0 +1,66 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot_example_types::{block_types::TestTransaction, node_types::TestTypes};
+use hotshot_task_impls::{
+    events::HotShotEvent,
+    harness::run_harness,
+    mempool::{MempoolTaskState, PeerRateLimiter},
+};
+use hotshot_types::{
+    mempool::MempoolGossipConfig, signature_key::BLSPubKey, traits::signature_key::SignatureKey,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_mempool_task_relays_and_reconciles() {
+    hotshot::helpers::initialize_logging();
+
+    let public_key = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0).0;
+    let peer = BLSPubKey::generated_from_seed_indexed([0u8; 32], 1).0;
+    let a = TestTransaction::new(vec![1]);
+    let b = TestTransaction::new(vec![2]);
+    let c = TestTransaction::new(vec![3]);
+
+    let input = vec![
+        HotShotEvent::TransactionsRecv(vec![a.clone(), b.clone()]),
+        // Already seen, so not relayed again
+        HotShotEvent::TransactionsRecv(vec![a.clone()]),
+        HotShotEvent::MempoolDigestRecv(vec![a.commit(), c.commit()], peer),
+        HotShotEvent::MempoolRequestRecv(vec![b.commit()], peer),
+        HotShotEvent::Shutdown,
+    ];
+    let output = vec![
+        HotShotEvent::MempoolGossipSend(vec![a, b.clone()], public_key),
+        HotShotEvent::MempoolRequestSend(vec![c.commit()], public_key, peer),
+        HotShotEvent::MempoolResponseSend(vec![b], public_key, peer),
+    ];
+
+    let mempool_state = MempoolTaskState::<TestTypes>::new(
+        public_key,
+        MempoolGossipConfig {
+            enabled: true,
+            ..MempoolGossipConfig::default()
+        },
+    );
+    run_harness(input, output, mempool_state, false).await;
+}
+
+#[test]
+fn test_peer_rate_limiter() {
+    let mut limiter = PeerRateLimiter::new(&MempoolGossipConfig {
+        peer_rate_limit: 0,
+        peer_burst: 10,
+        ..MempoolGossipConfig::default()
+    });
+
+    assert_eq!(limiter.admit(1u64, 8), 8);
+    assert_eq!(limiter.admit(1u64, 8), 2);
+    assert_eq!(limiter.admit(1u64, 1), 0);
+    // Every peer has its own bucket
+    assert_eq!(limiter.admit(2u64, 10), 10);
+}
@@ -14,13 +14,21 @@ use hotshot_types::{
     signature_key::BLSPubKey,
     simple_certificate::SimpleCertificate,
     simple_vote::ViewSyncCommitData2,
-    traits::{node_implementation::ConsensusTime, signature_key::SignatureKey},
+    traits::{
+        node_implementation::ConsensusTime, signature_key::SignatureKey, signer::LocalSigner,
+    },
 };
 use vbs::{
     version::{StaticVersion, Version},
     BinarySerializer, Serializer,
 };
 
+/// The key of the node with `index`, and a signer holding its private key.
+fn key_and_signer(index: u64) -> (BLSPubKey, LocalSigner<BLSPubKey>) {
+    let (key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], index);
+    (key, LocalSigner::new(key, private_key))
+}
+
 #[test]
 // Checks that the current program protocol version
 // correctly appears at the start of a serialized messaged.
@@ -105,7 +113,7 @@ async fn test_certificate2_validity() {
 
     assert!(
         qc.is_valid_cert(
-            membership.stake_table(EpochNumber::new(0)),
+            &membership.stake_table(EpochNumber::new(0)),
             membership.success_threshold(EpochNumber::new(0)),
             &handle.hotshot.upgrade_lock
         )
@@ -114,7 +122,7 @@ async fn test_certificate2_validity() {
 
     assert!(
         qc2.is_valid_cert(
-            membership.stake_table(EpochNumber::new(0)),
+            &membership.stake_table(EpochNumber::new(0)),
             membership.success_threshold(EpochNumber::new(0)),
             &handle.hotshot.upgrade_lock
         )
@@ -132,3 +140,250 @@ async fn test_certificate2_validity() {
 
     assert!(leaf2.parent_commitment() == parent_leaf2.commit());
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a message only comes out of its envelope if its sender signed it.
+async fn test_signed_envelope_is_bound_to_the_sender() {
+    use hotshot_example_types::node_types::EpochsTestVersions;
+    use hotshot_types::{
+        message::{SignedEnvelope, UpgradeLock},
+        replay::ReplayGuard,
+    };
+
+    let upgrade_lock = UpgradeLock::<TestTypes, EpochsTestVersions>::new();
+    let mut replay = ReplayGuard::default();
+    let (sender, sender_key) = key_and_signer(0);
+    let (other, other_key) = key_and_signer(1);
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::External(vec![1, 2, 3]),
+    };
+
+    let envelope = upgrade_lock
+        .serialize_signed(&message, &sender_key)
+        .await
+        .unwrap();
+    assert_eq!(
+        upgrade_lock
+            .deserialize_signed(&envelope, &mut replay)
+            .await
+            .unwrap(),
+        message
+    );
+
+    // Signed by someone other than the sender
+    let forged = upgrade_lock
+        .serialize_signed(&message, &other_key)
+        .await
+        .unwrap();
+    assert!(upgrade_lock
+        .deserialize_signed(&forged, &mut replay)
+        .await
+        .is_err());
+
+    // The sender's signature on another sender's message
+    let (version, sealed) = Version::deserialize(&envelope).unwrap();
+    let mut sealed: SignedEnvelope<TestTypes> = bincode::deserialize(sealed).unwrap();
+    sealed.message = upgrade_lock
+        .serialize(&Message::<TestTypes> {
+            sender: other,
+            ..message.clone()
+        })
+        .await
+        .unwrap();
+    let swapped = [version.serialize(), bincode::serialize(&sealed).unwrap()].concat();
+    assert!(upgrade_lock
+        .deserialize_signed(&swapped, &mut replay)
+        .await
+        .is_err());
+
+    // A message which was never sealed
+    let unsealed = upgrade_lock.serialize(&message).await.unwrap();
+    assert!(upgrade_lock
+        .deserialize_signed(&unsealed, &mut replay)
+        .await
+        .is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that messages of versions before envelopes were introduced travel bare.
+async fn test_signed_envelope_is_gated_on_the_version() {
+    use hotshot_example_types::node_types::TestVersions;
+    use hotshot_types::{message::UpgradeLock, replay::ReplayGuard};
+
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let mut replay = ReplayGuard::default();
+    let (sender, sender_key) = key_and_signer(0);
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::External(vec![1, 2, 3]),
+    };
+
+    let bare = upgrade_lock.serialize(&message).await.unwrap();
+    assert_eq!(
+        upgrade_lock
+            .serialize_signed(&message, &sender_key)
+            .await
+            .unwrap(),
+        bare
+    );
+    assert_eq!(
+        upgrade_lock
+            .deserialize_signed(&bare, &mut replay)
+            .await
+            .unwrap(),
+        message
+    );
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a signed envelope is only accepted once, and that a forgery under the nonce of a
+// genuine envelope does not get the genuine one rejected.
+async fn test_signed_envelope_is_not_replayed() {
+    use hotshot_example_types::node_types::EpochsTestVersions;
+    use hotshot_types::{
+        message::{SignedEnvelope, UpgradeLock},
+        replay::ReplayGuard,
+    };
+
+    let upgrade_lock = UpgradeLock::<TestTypes, EpochsTestVersions>::new();
+    let mut replay = ReplayGuard::default();
+    let (sender, sender_key) = key_and_signer(0);
+    let (_, other_key) = key_and_signer(1);
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::External(vec![1, 2, 3]),
+    };
+
+    let envelope = upgrade_lock
+        .serialize_signed(&message, &sender_key)
+        .await
+        .unwrap();
+
+    // A forgery carrying the genuine envelope's nonce is rejected without recording it
+    let open = |envelope: &[u8]| {
+        let (version, sealed) = Version::deserialize(envelope).unwrap();
+        (
+            version,
+            bincode::deserialize::<SignedEnvelope<TestTypes>>(sealed).unwrap(),
+        )
+    };
+    let (version, mut forged) = open(
+        &upgrade_lock
+            .serialize_signed(&message, &other_key)
+            .await
+            .unwrap(),
+    );
+    forged.nonce = open(&envelope).1.nonce;
+    let forged = [version.serialize(), bincode::serialize(&forged).unwrap()].concat();
+    assert!(upgrade_lock
+        .deserialize_signed(&forged, &mut replay)
+        .await
+        .is_err());
+
+    assert_eq!(
+        upgrade_lock
+            .deserialize_signed(&envelope, &mut replay)
+            .await
+            .unwrap(),
+        message
+    );
+    assert!(upgrade_lock
+        .deserialize_signed(&envelope, &mut replay)
+        .await
+        .is_err());
+
+    // The same message sealed again is a new envelope
+    let resealed = upgrade_lock
+        .serialize_signed(&message, &sender_key)
+        .await
+        .unwrap();
+    assert!(upgrade_lock
+        .deserialize_signed(&resealed, &mut replay)
+        .await
+        .is_ok());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that of two copies of an envelope whose signatures are verified at the same time, only
+// the first one accepted gets through.
+async fn test_signed_envelope_verified_concurrently_is_accepted_once() {
+    use hotshot_example_types::node_types::EpochsTestVersions;
+    use hotshot_types::{message::UpgradeLock, replay::ReplayGuard};
+
+    let upgrade_lock = UpgradeLock::<TestTypes, EpochsTestVersions>::new();
+    let mut replay = ReplayGuard::default();
+    let (sender, sender_key) = key_and_signer(0);
+    let message = Message::<TestTypes> {
+        sender,
+        kind: MessageKind::External(vec![1, 2, 3]),
+    };
+    let envelope = upgrade_lock
+        .serialize_signed(&message, &sender_key)
+        .await
+        .unwrap();
+
+    let first = upgrade_lock.open_signed(&envelope).await.unwrap();
+    let second = upgrade_lock.open_signed(&envelope).await.unwrap();
+    assert!(first.check_replay(&replay).is_ok());
+    assert!(second.check_replay(&replay).is_ok());
+    assert!(first.verify() && second.verify());
+
+    assert_eq!(first.accept(&mut replay).unwrap(), message);
+    assert!(second.accept(&mut replay).is_err());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a compact quorum vote expands back into the vote it was made from, and is smaller on
+// the wire.
+async fn test_compact_vote_round_trip() {
+    use hotshot_example_types::node_types::{MemoryImpl, TestVersions};
+    use hotshot_testing::helpers::build_system_handle;
+    use hotshot_types::{
+        data::{EpochNumber, Leaf2, ViewNumber},
+        simple_vote::{CompactQuorumVote, QuorumData2, QuorumVote2},
+        traits::election::Membership,
+    };
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let epoch = EpochNumber::new(1);
+    let stake_table = handle.hotshot.memberships.stake_table(epoch);
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 2);
+    let leaf = Leaf2::<TestTypes>::genesis(&Default::default(), &Default::default()).await;
+    let vote = QuorumVote2::<TestTypes>::create_signed_vote(
+        QuorumData2 {
+            leaf_commit: leaf.commit(),
+            epoch,
+        },
+        ViewNumber::new(3),
+        &public_key,
+        &private_key,
+        &handle.hotshot.upgrade_lock,
+    )
+    .await
+    .unwrap();
+
+    let compact = CompactQuorumVote::compact(&vote, &stake_table).unwrap();
+    let index = stake_table
+        .iter()
+        .position(|entry| BLSPubKey::public_key(entry) == public_key)
+        .unwrap();
+    assert_eq!(compact.signer as usize, index);
+    assert!(bincode::serialize(&compact).unwrap().len() < bincode::serialize(&vote).unwrap().len());
+    assert_eq!(compact.clone().expand(&stake_table).unwrap(), vote);
+
+    // A signer outside the stake table
+    let unknown = CompactQuorumVote {
+        signer: u32::try_from(stake_table.len()).unwrap(),
+        ..compact
+    };
+    assert!(unknown.expand(&stake_table).is_err());
+    assert!(CompactQuorumVote::compact(&vote, &stake_table[index + 1..]).is_err());
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use futures::StreamExt;
+use hotshot::metered_storage::{MeteredStorage, StorageQuota};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        metrics::NoMetrics,
+        node_implementation::ConsensusTime,
+        storage::{Storage, ViewWrites},
+    },
+    utils::{View, ViewInner},
+};
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_storage_quota_prunes_decided_views() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let view = generator.next().await.unwrap();
+    let vid_commit = view.vid_proposal.0[0].data.payload_commitment;
+
+    let storage = MeteredStorage::new(
+        TestStorage::<TestTypes>::default(),
+        StorageQuota { max_bytes: Some(1) },
+        &*NoMetrics::boxed(),
+        "test",
+    );
+
+    // Proposals are needed for safety, so they are stored even over the quota
+    storage
+        .append_proposal2(&view.quorum_proposal)
+        .await
+        .unwrap();
+    assert_eq!(storage.inner().proposals_cloned().await.len(), 1);
+    // DA proposals are not, so they are refused while nothing can be pruned
+    assert!(storage
+        .append_da2(&view.da_proposal, vid_commit)
+        .await
+        .is_err());
+
+    // Once the view is decided, it is pruned to make room
+    let undecided = BTreeMap::from([(
+        ViewNumber::new(5),
+        View {
+            view_inner: ViewInner::Failed,
+        },
+    )]);
+    storage
+        .update_undecided_state2(HashMap::new(), undecided)
+        .await
+        .unwrap();
+    storage
+        .append_da2(&view.da_proposal, vid_commit)
+        .await
+        .unwrap();
+    assert!(storage.inner().proposals_cloned().await.is_empty());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_storage_quota_refuses_view_writes_with_vid_share() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let first_view = generator.next().await.unwrap();
+    let second_view = generator.next().await.unwrap();
+
+    let storage = MeteredStorage::new(
+        TestStorage::<TestTypes>::default(),
+        StorageQuota { max_bytes: Some(1) },
+        &*NoMetrics::boxed(),
+        "test",
+    );
+
+    // A view without a VID share only holds writes needed for safety, so it is stored
+    storage
+        .write_view(ViewWrites {
+            proposal: Some(first_view.quorum_proposal.clone()),
+            ..ViewWrites::default()
+        })
+        .await
+        .unwrap();
+    assert_eq!(storage.inner().proposals_cloned().await.len(), 1);
+
+    // With a VID share the whole view is refused, not just the share
+    assert!(storage
+        .write_view(ViewWrites {
+            proposal: Some(second_view.quorum_proposal.clone()),
+            vid_share: Some(second_view.vid_proposal.0[0].clone()),
+            ..ViewWrites::default()
+        })
+        .await
+        .is_err());
+    assert_eq!(storage.inner().proposals_cloned().await.len(), 1);
+}
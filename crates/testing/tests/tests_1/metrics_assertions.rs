@@ -0,0 +1,47 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use hotshot_testing::metrics_assertions::{node_metrics, MetricsAssertion};
+use hotshot_types::traits::metrics::RecordingMetrics;
+
+#[test]
+// Checks that assertions read the metrics of the node they are checked for.
+fn metrics_assertions_bound_each_node() {
+    let recorder = RecordingMetrics::default();
+    let node_0 = node_metrics(&recorder, 0);
+    let node_1 = node_metrics(&recorder, 1);
+    node_0.number_of_timeouts.add(3);
+    node_1.number_of_timeouts.add(1);
+    node_1.current_view.set(10);
+
+    assert_eq!(recorder.counter("node-0-number_of_timeouts"), 3);
+    assert_eq!(recorder.counter("node-1-view_fatal_errors"), 0);
+
+    let at_most_two = MetricsAssertion::counter_at_most("number_of_timeouts", 2);
+    assert!(at_most_two.check(&recorder, 0).is_err());
+    assert!(at_most_two.check(&recorder, 1).is_ok());
+
+    let reached_view = MetricsAssertion::gauge_at_least("current_view", 5);
+    assert!(reached_view.check(&recorder, 0).is_err());
+    assert!(reached_view.check(&recorder, 1).is_ok());
+}
+
+#[test]
+// Checks that the healthy bounds catch errors which cost a node its view.
+fn healthy_metrics_reject_fatal_errors() {
+    let recorder = RecordingMetrics::default();
+    let node = node_metrics(&recorder, 0);
+    node.recoverable_errors.add(5);
+    let healthy = MetricsAssertion::healthy(0);
+    assert!(healthy
+        .iter()
+        .all(|bound| bound.check(&recorder, 0).is_ok()));
+
+    node.view_fatal_errors.add(1);
+    assert!(healthy
+        .iter()
+        .any(|bound| bound.check(&recorder, 0).is_err()));
+}
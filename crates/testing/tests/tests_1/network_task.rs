@@ -24,6 +24,7 @@ use hotshot_types::{
         election::Membership,
         node_implementation::{ConsensusTime, NodeType},
     },
+    vote_aggregation::AggregationConfig,
 };
 use tokio::time::timeout;
 
@@ -66,9 +67,12 @@ async fn test_network_task() {
             epoch: EpochNumber::new(0),
             membership: membership.clone(),
             upgrade_lock: upgrade_lock.clone(),
+            private_key: validator_config.private_key.clone(),
             storage,
             consensus,
             transmit_tasks: BTreeMap::new(),
+            archival_nodes: vec![],
+            aggregation: AggregationConfig::default(),
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
@@ -87,6 +91,8 @@ async fn test_network_task() {
         upgrade_lock,
         network.clone(),
         public_key,
+        OuterConsensus::new(handle.hotshot.consensus()),
+        Arc::clone(&handle.hotshot.memberships),
     )
     .await;
 
@@ -235,9 +241,12 @@ async fn test_network_storage_fail() {
             epoch: EpochNumber::new(0),
             membership: membership.clone(),
             upgrade_lock: upgrade_lock.clone(),
+            private_key: validator_config.private_key.clone(),
             storage,
             consensus,
             transmit_tasks: BTreeMap::new(),
+            archival_nodes: vec![],
+            aggregation: AggregationConfig::default(),
         };
     let (tx, rx) = async_broadcast::broadcast(10);
     let mut task_reg = ConsensusTaskRegistry::new();
@@ -257,6 +266,8 @@ async fn test_network_storage_fail() {
         upgrade_lock,
         network.clone(),
         public_key,
+        OuterConsensus::new(handle.hotshot.consensus()),
+        Arc::clone(&handle.hotshot.memberships),
     )
     .await;
 
@@ -0,0 +1,43 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{
+    block_builder::SimpleBuilderImplementation,
+    completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    test_builder::TestDescription,
+};
+use hotshot_types::view_timing::Pacing;
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that leaders which hold their proposals for a fixed interval still make progress without
+// failed views.
+async fn fixed_interval_pacing_makes_progress() {
+    hotshot::helpers::initialize_logging();
+
+    let mut metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> = TestDescription {
+        completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(60),
+            },
+        ),
+        ..TestDescription::default()
+    };
+    metadata.overall_safety_properties.num_failed_views = 0;
+
+    metadata
+        .gen_launcher(0)
+        .modify_default_config(|config| {
+            config.pacing = Pacing::FixedInterval {
+                interval: Duration::from_millis(300),
+            };
+        })
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
@@ -31,10 +31,7 @@ use hotshot_types::{
     data::{EpochNumber, Leaf2, ViewNumber},
     request_response::ProposalRequestPayload,
     traits::{
-        consensus_api::ConsensusApi,
-        election::Membership,
-        node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        consensus_api::ConsensusApi, election::Membership, node_implementation::ConsensusTime,
         ValidatedState,
     },
 };
@@ -94,7 +91,7 @@ async fn test_quorum_proposal_recv_task() {
         exact(QuorumProposalPreliminarilyValidated(proposals[1].clone())),
         exact(QuorumProposalValidated(
             proposals[1].clone(),
-            leaves[0].clone(),
+            leaves[0].clone().into(),
         )),
         exact(ViewChange(ViewNumber::new(2), EpochNumber::new(0))),
     ])];
@@ -183,9 +180,7 @@ async fn test_quorum_proposal_recv_task_liveness_check() {
     };
 
     // make the signed commitment
-    let signature =
-        <TestTypes as NodeType>::SignatureKey::sign(handle.private_key(), req.commit().as_ref())
-            .unwrap();
+    let signature = handle.signer().sign(req.commit().as_ref()).await.unwrap();
 
     let expectations = vec![Expectations::from_outputs(all_predicates![
         exact(QuorumProposalPreliminarilyValidated(proposals[2].clone())),
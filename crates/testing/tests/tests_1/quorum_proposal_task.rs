@@ -84,6 +84,11 @@ async fn test_quorum_proposal_task_quorum_proposal_view_1() {
                 None,
             )
             .unwrap();
+        // Saved by the quorum vote task, which we don't run here
+        consensus_writer.update_saved_da_certs(
+            view.quorum_proposal.data.view_number,
+            view.da_certificate.clone(),
+        );
     }
 
     // We must send the genesis cert here to initialize hotshot successfully.
@@ -154,6 +159,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
     let mut leaves = Vec::new();
     let mut vids = Vec::new();
     let mut vid_dispersals = Vec::new();
+    let mut dacs = Vec::new();
     let consensus = handle.hotshot.consensus();
     let mut consensus_writer = consensus.write().await;
     for view in (&mut generator).take(5).collect::<Vec<_>>().await {
@@ -162,6 +168,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
         leaves.push(view.leaf.clone());
         vids.push(view.vid_proposal.clone());
         vid_dispersals.push(view.vid_disperse.clone());
+        dacs.push(view.da_certificate.clone());
 
         // We don't have a `QuorumProposalRecv` task handler, so we'll just manually insert the proposals
         // to make sure they show up during tests.
@@ -172,6 +179,11 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
                 None,
             )
             .unwrap();
+        // Saved by the quorum vote task, which we don't run here
+        consensus_writer.update_saved_da_certs(
+            view.quorum_proposal.data.view_number,
+            view.da_certificate.clone(),
+        );
     }
 
     // We need to handle the views where we aren't the leader to ensure that the states are
@@ -210,6 +222,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[0].clone()),
             Qc2Formed(either::Left(proposals[1].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[0].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -227,6 +240,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[1].clone()),
             Qc2Formed(either::Left(proposals[2].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[1].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -244,6 +258,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[2].clone()),
             Qc2Formed(either::Left(proposals[3].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[2].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -261,6 +276,7 @@ async fn test_quorum_proposal_task_quorum_proposal_view_gt_1() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[3].clone()),
             Qc2Formed(either::Left(proposals[4].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[3].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -495,6 +511,7 @@ async fn test_quorum_proposal_task_liveness_check() {
     let mut leaves = Vec::new();
     let mut vids = Vec::new();
     let mut vid_dispersals = Vec::new();
+    let mut dacs = Vec::new();
     let consensus = handle.hotshot.consensus();
     let mut consensus_writer = consensus.write().await;
     for view in (&mut generator).take(5).collect::<Vec<_>>().await {
@@ -503,6 +520,7 @@ async fn test_quorum_proposal_task_liveness_check() {
         leaves.push(view.leaf.clone());
         vids.push(view.vid_proposal.clone());
         vid_dispersals.push(view.vid_disperse.clone());
+        dacs.push(view.da_certificate.clone());
 
         // We don't have a `QuorumProposalRecv` task handler, so we'll just manually insert the proposals
         // to make sure they show up during tests.
@@ -513,6 +531,11 @@ async fn test_quorum_proposal_task_liveness_check() {
                 None,
             )
             .unwrap();
+        // Saved by the quorum vote task, which we don't run here
+        consensus_writer.update_saved_da_certs(
+            view.quorum_proposal.data.view_number,
+            view.da_certificate.clone(),
+        );
     }
     drop(consensus_writer);
 
@@ -550,6 +573,7 @@ async fn test_quorum_proposal_task_liveness_check() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[0].clone()),
             Qc2Formed(either::Left(proposals[1].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[0].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -567,6 +591,7 @@ async fn test_quorum_proposal_task_liveness_check() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[1].clone()),
             Qc2Formed(either::Left(proposals[2].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[1].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -584,6 +609,7 @@ async fn test_quorum_proposal_task_liveness_check() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[2].clone()),
             Qc2Formed(either::Left(proposals[3].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[2].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -601,6 +627,7 @@ async fn test_quorum_proposal_task_liveness_check() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[3].clone()),
             Qc2Formed(either::Left(proposals[4].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[3].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -72,15 +72,17 @@ async fn test_quorum_vote_task_success() {
     }
     drop(consensus_writer);
 
-    // Send the quorum proposal, DAC, VID share data, and validated state, in which case a dummy
-    // vote can be formed and the view number will be updated.
+    // Send the quorum proposal, the DACs of its view and its parent, VID share data, and validated
+    // state, in which case a dummy vote can be formed and the view number will be updated.
     let inputs = vec![random![
-        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
+        DaCertificateRecv(dacs[0].clone()),
         DaCertificateRecv(dacs[1].clone()),
         VidShareRecv(leaders[1], vids[1].0[0].clone()),
     ]];
 
     let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(DaCertificateValidated(dacs[0].clone())),
         exact(DaCertificateValidated(dacs[1].clone())),
         exact(VidShareValidated(vids[1].0[0].clone())),
         exact(ViewChange(ViewNumber::new(3), EpochNumber::new(0))),
@@ -142,18 +144,19 @@ async fn test_quorum_vote_task_miss_dependency() {
     }
     drop(consensus_writer);
 
-    // Send two of quorum proposal, DAC, VID share data, in which case there's no vote.
+    // Send two of quorum proposal, the DAC of its parent and VID share data, in which case there's
+    // no vote.
     let inputs = vec![
         random![
-            QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+            QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
             VidShareRecv(leaders[1], vid_share(&vids[1].0, handle.public_key())),
         ],
         random![
-            QuorumProposalValidated(proposals[2].clone(), leaves[1].clone()),
-            DaCertificateRecv(dacs[2].clone()),
+            QuorumProposalValidated(proposals[2].clone(), leaves[1].clone().into()),
+            DaCertificateRecv(dacs[1].clone()),
         ],
         random![
-            DaCertificateRecv(dacs[3].clone()),
+            DaCertificateRecv(dacs[2].clone()),
             VidShareRecv(leaders[3], vid_share(&vids[3].0, handle.public_key())),
         ],
     ];
@@ -163,10 +166,10 @@ async fn test_quorum_vote_task_miss_dependency() {
             vids[1].0[0].clone()
         ))]),
         Expectations::from_outputs(all_predicates![exact(DaCertificateValidated(
-            dacs[2].clone()
+            dacs[1].clone()
         ))]),
         Expectations::from_outputs(all_predicates![
-            exact(DaCertificateValidated(dacs[3].clone())),
+            exact(DaCertificateValidated(dacs[2].clone())),
             exact(VidShareValidated(vids[3].0[0].clone())),
         ]),
     ];
@@ -182,6 +185,75 @@ async fn test_quorum_vote_task_miss_dependency() {
     run_test![inputs, script].await;
 }
 
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_quorum_vote_task_votes_before_own_dac() {
+    use hotshot_task_impls::{events::HotShotEvent::*, quorum_vote::QuorumVoteTaskState};
+    use hotshot_testing::{
+        helpers::build_system_handle,
+        predicates::event::{exact, quorum_vote_send},
+        view_generator::TestViewGenerator,
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+
+    let membership = (*handle.hotshot.memberships).clone();
+
+    let mut generator = TestViewGenerator::generate(membership.clone());
+
+    let mut proposals = Vec::new();
+    let mut leaves = Vec::new();
+    let mut dacs = Vec::new();
+    let mut vids = Vec::new();
+    let mut leaders = Vec::new();
+    let consensus = handle.hotshot.consensus().clone();
+    let mut consensus_writer = consensus.write().await;
+    for view in (&mut generator).take(2).collect::<Vec<_>>().await {
+        leaders.push(view.leader_public_key);
+        proposals.push(view.quorum_proposal.clone());
+        leaves.push(view.leaf.clone());
+        dacs.push(view.da_certificate.clone());
+        vids.push(view.vid_proposal.clone());
+        consensus_writer
+            .update_leaf(
+                Leaf2::from_quorum_proposal(&view.quorum_proposal.data),
+                Arc::new(TestValidatedState::default()),
+                None,
+            )
+            .unwrap();
+    }
+    drop(consensus_writer);
+
+    // The DA votes of view 2 are collected concurrently with ours, so we vote once we have the
+    // proposal, our VID share and the DAC of the parent, without the DAC of view 2.
+    let inputs = vec![random![
+        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
+        DaCertificateRecv(dacs[0].clone()),
+        VidShareRecv(leaders[1], vids[1].0[0].clone()),
+    ]];
+
+    let expectations = vec![Expectations::from_outputs(all_predicates![
+        exact(DaCertificateValidated(dacs[0].clone())),
+        exact(VidShareValidated(vids[1].0[0].clone())),
+        exact(ViewChange(ViewNumber::new(3), EpochNumber::new(0))),
+        quorum_vote_send(),
+    ])];
+
+    let quorum_vote_state =
+        QuorumVoteTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+
+    let mut script = TaskScript {
+        timeout: TIMEOUT,
+        state: quorum_vote_state,
+        expectations,
+    };
+    run_test![inputs, script].await;
+}
+
 #[cfg(test)]
 #[tokio::test(flavor = "multi_thread")]
 async fn test_quorum_vote_task_incorrect_dependency() {
@@ -215,7 +287,7 @@ async fn test_quorum_vote_task_incorrect_dependency() {
 
     // Send the correct quorum proposal and DAC, and incorrect VID share data.
     let inputs = vec![random![
-        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
         DaCertificateRecv(dacs[1].clone()),
         VidShareRecv(leaders[0], vids[0].0[0].clone()),
     ]];
@@ -0,0 +1,74 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use futures::{channel::mpsc, SinkExt, StreamExt};
+use hotshot::{
+    relay::{read_message, read_version, Relay, RelayConfig, RelayMessage},
+    types::{Event, EventType},
+};
+use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::traits::signature_key::ProposerId;
+use tokio::net::TcpStream;
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a relay client receives proposals, each new justify QC once, in order.
+async fn test_relay_forwards_proposals_and_qcs() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let mut generator = TestViewGenerator::generate(membership);
+
+    let (mut events, stream) = mpsc::unbounded::<Event<TestTypes>>();
+    let relay = Relay::spawn(RelayConfig::new("127.0.0.1:0".parse().unwrap()), stream)
+        .await
+        .unwrap();
+    let mut client = TcpStream::connect(relay.local_addr()).await.unwrap();
+    // The client is subscribed once it got the version
+    read_version(&mut client).await.unwrap();
+
+    let mut views = Vec::new();
+    for _ in 0..2 {
+        views.push(generator.next().await.unwrap());
+    }
+    // The second proposal is sent twice, but its QC is only relayed the first time
+    for view in views.iter().chain(views.last()) {
+        events
+            .send(Event {
+                view_number: view.view_number,
+                event: EventType::QuorumProposal {
+                    proposal: view.quorum_proposal.clone(),
+                    sender: ProposerId::new(view.leader_public_key),
+                },
+            })
+            .await
+            .unwrap();
+    }
+
+    // Each proposal is preceded by its justify QC, unless that was relayed already
+    let qc_views = views
+        .iter()
+        .map(|view| view.quorum_proposal.data.justify_qc.view_number);
+    let proposal_views = views.iter().map(|view| view.view_number);
+    let expected = qc_views
+        .zip(proposal_views.clone())
+        .flat_map(|(qc, proposal)| [(true, qc), (false, proposal)])
+        .chain(proposal_views.last().map(|proposal| (false, proposal)));
+
+    for (is_qc, view_number) in expected {
+        match read_message::<TestTypes, _>(&mut client).await.unwrap() {
+            RelayMessage::Qc(qc) if is_qc => assert_eq!(qc.view_number, view_number),
+            RelayMessage::QuorumProposal { proposal, .. } if !is_qc => {
+                assert_eq!(proposal.data.view_number, view_number);
+            }
+            message => panic!("unexpected relay message {message:?}"),
+        }
+    }
+}
@@ -0,0 +1,81 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use committable::Committable;
+use hotshot::remote_signer::RemoteSigner;
+use hotshot_example_types::node_types::{TestTypes, TestVersions};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    simple_vote::{TimeoutData2, TimeoutVote2, VersionedVoteData},
+    traits::{
+        node_implementation::ConsensusTime,
+        signature_key::SignatureKey,
+        signer::{LocalSigner, RemoteSignerConfig, Signer, SignerError},
+    },
+    vote::Vote,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a vote signed through a local signer validates against its public key.
+async fn test_local_signer_votes() {
+    hotshot::helpers::initialize_logging();
+
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let signer = LocalSigner::new(public_key, private_key);
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let view = ViewNumber::new(5);
+    let data = TimeoutData2 {
+        view,
+        epoch: EpochNumber::new(1),
+    };
+
+    let vote = TimeoutVote2::create_signed_vote_with_signer(data, view, &signer, &upgrade_lock)
+        .await
+        .unwrap();
+    let commit = VersionedVoteData::new(vote.date().clone(), view, &upgrade_lock)
+        .await
+        .unwrap()
+        .commit();
+
+    assert_eq!(&vote.signing_key(), signer.public_key());
+    assert!(signer
+        .public_key()
+        .validate(&vote.signature(), commit.as_ref()));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that an unreachable signing service fails the vote instead of hanging or panicking.
+async fn test_remote_signer_unavailable() {
+    hotshot::helpers::initialize_logging();
+
+    let (public_key, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let mut config = RemoteSignerConfig::new("http://127.0.0.1:1".parse().unwrap());
+    config.timeout = Duration::from_millis(100);
+    let signer = RemoteSigner::new(config, public_key);
+
+    assert!(matches!(
+        signer.sign(b"data").await,
+        Err(SignerError::Unavailable(_))
+    ));
+
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let view = ViewNumber::new(5);
+    let data = TimeoutData2 {
+        view,
+        epoch: EpochNumber::new(1),
+    };
+    assert!(
+        TimeoutVote2::create_signed_vote_with_signer(data, view, &signer, &upgrade_lock)
+            .await
+            .is_err()
+    );
+}
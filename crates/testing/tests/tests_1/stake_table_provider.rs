@@ -0,0 +1,112 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use hotshot::traits::election::provided_committee::ProvidedCommittee;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    signature_key::BLSPubKey,
+    traits::{
+        election::{Membership, StakeTableProvider},
+        node_implementation::ConsensusTime,
+    },
+    PeerConfig, ValidatorConfig,
+};
+
+/// A provider which knows the tables of the first five epochs, and hands a different half of the
+/// nodes the stake in odd and even epochs.
+#[derive(Debug, Default)]
+struct AlternatingProvider {
+    /// How often the provider has been queried
+    queries: AtomicUsize,
+}
+
+impl StakeTableProvider<TestTypes> for AlternatingProvider {
+    fn stake_tables(
+        &self,
+        epoch: EpochNumber,
+    ) -> Option<(Vec<PeerConfig<BLSPubKey>>, Vec<PeerConfig<BLSPubKey>>)> {
+        self.queries.fetch_add(1, Ordering::SeqCst);
+        if *epoch > 5 {
+            return None;
+        }
+
+        let peers: Vec<_> = (0..8)
+            .filter(|i| i % 2 == *epoch % 2)
+            .map(|i| {
+                ValidatorConfig::<BLSPubKey>::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                    .public_config()
+            })
+            .collect();
+        Some((peers.clone(), peers))
+    }
+}
+
+/// The keys of the nodes `indices`.
+fn keys(indices: impl Iterator<Item = u64>) -> BTreeSet<BLSPubKey> {
+    indices
+        .map(|i| BLSPubKey::generated_from_seed_indexed([0u8; 32], i).0)
+        .collect()
+}
+
+#[test]
+// Checks that the committee follows the provider from epoch to epoch.
+fn committee_follows_the_provider() {
+    let provider = Arc::new(AlternatingProvider::default());
+    let membership = ProvidedCommittee::<TestTypes>::with_provider(Arc::clone(&provider) as _);
+    let view = ViewNumber::new(0);
+
+    let even = EpochNumber::new(2);
+    let odd = EpochNumber::new(3);
+    assert_eq!(
+        membership.committee_members(view, even),
+        keys((0..8).step_by(2))
+    );
+    assert_eq!(
+        membership.committee_members(view, odd),
+        keys((1..8).step_by(2))
+    );
+    assert_eq!(membership.total_nodes(odd), 4);
+    assert_eq!(membership.success_threshold(odd).get(), 3);
+
+    // The provider is queried once per epoch, however many clones ask
+    let queries = provider.queries.load(Ordering::SeqCst);
+    assert_eq!(membership.clone().total_nodes(even), 4);
+    assert_eq!(provider.queries.load(Ordering::SeqCst), queries);
+}
+
+#[test]
+// Checks that nothing is staked in an epoch the provider has no tables for yet.
+fn unknown_epochs_have_no_stake() {
+    let membership =
+        ProvidedCommittee::<TestTypes>::with_provider(Arc::new(AlternatingProvider::default()));
+    let epoch = EpochNumber::new(6);
+    let (key, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+
+    assert_eq!(membership.total_nodes(epoch), 0);
+    assert!(!membership.has_stake(&key, epoch));
+    assert!(membership.leader(ViewNumber::new(0), epoch).is_err());
+}
+
+#[test]
+// Checks that the stake tables of a known epoch are borrowed from the cache rather than copied.
+fn stake_tables_are_borrowed() {
+    let membership =
+        ProvidedCommittee::<TestTypes>::with_provider(Arc::new(AlternatingProvider::default()));
+    let epoch = EpochNumber::new(2);
+
+    assert!(matches!(membership.stake_table(epoch), Cow::Borrowed(table) if table.len() == 4));
+    assert!(matches!(membership.da_stake_table(epoch), Cow::Borrowed(table) if table.len() == 4));
+}
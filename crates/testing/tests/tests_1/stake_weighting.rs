@@ -0,0 +1,120 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use hotshot::traits::election::provided_committee::ProvidedCommittee;
+use hotshot_example_types::node_types::TestTypes;
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    hotshot_config_file::HotShotConfigFile,
+    signature_key::BLSPubKey,
+    stake_table::BondingCurve,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::StakeTableEntryType,
+    },
+    HotShotConfig, PeerConfig, ValidatorConfig,
+};
+use primitive_types::U256;
+
+#[test]
+// Checks that long-bonded stake outweighs new stake in the stake table.
+fn bonded_stake_outweighs_new_stake() {
+    let curve = BondingCurve::Linear {
+        ramp_epochs: 100,
+        max_bonus_percent: 100,
+    };
+    let peers: Vec<PeerConfig<BLSPubKey>> = (0..4)
+        .map(|i| PeerConfig {
+            bonded_since: if i < 2 { 0 } else { 100 },
+            ..ValidatorConfig::generated_from_seed_indexed([0u8; 32], i, 1000, true).public_config()
+        })
+        .collect();
+    let weighted: Vec<_> = peers
+        .iter()
+        .map(|peer| peer.with_bonded_weight(curve, 100))
+        .collect();
+    let membership = <TestTypes as NodeType>::Membership::new(weighted.clone(), weighted);
+    let epoch = EpochNumber::new(0);
+
+    let stakes: Vec<U256> = peers
+        .iter()
+        .map(|peer| {
+            let key = peer.stake_table_entry.public_key();
+            membership.stake(&key, epoch).unwrap().stake()
+        })
+        .collect();
+    assert_eq!(stakes, [2000u64, 2000, 1000, 1000].map(U256::from).to_vec());
+}
+
+#[test]
+// Checks that nodes below the minimum stake neither vote nor lead, nor count towards thresholds.
+fn dust_stake_is_left_out_of_the_committees() {
+    let mut config: HotShotConfig<BLSPubKey> =
+        HotShotConfigFile::hotshot_config_5_nodes_10_da().into();
+    config.known_nodes_with_stake = (0..6)
+        .map(|i| {
+            let stake = if i < 4 { 100 } else { 1 };
+            ValidatorConfig::generated_from_seed_indexed([0u8; 32], i, stake, true).public_config()
+        })
+        .collect();
+    config.known_da_nodes = config.known_nodes_with_stake.clone();
+    config.min_stake = 10;
+
+    let (stake_table, da_stake_table) = config.stake_tables(0);
+    let membership = <TestTypes as NodeType>::Membership::new(stake_table, da_stake_table);
+    let epoch = EpochNumber::new(0);
+
+    for peer in &config.known_nodes_with_stake[4..] {
+        let key = peer.stake_table_entry.public_key();
+        assert!(!membership.has_stake(&key, epoch));
+        assert!(!membership.has_da_stake(&key, epoch));
+        for view in 0..12 {
+            assert_ne!(
+                membership.leader(ViewNumber::new(view), epoch).unwrap(),
+                key
+            );
+        }
+    }
+    assert_eq!(membership.total_nodes(epoch), 4);
+    assert_eq!(membership.success_threshold(epoch).get(), 3);
+}
+
+#[test]
+// Checks that an election taking its stake tables from the config weighs stake anew each epoch,
+// so newly bonded stake gains weight as the epochs go by.
+fn bonded_weights_grow_from_epoch_to_epoch() {
+    let mut config: HotShotConfig<BLSPubKey> =
+        HotShotConfigFile::hotshot_config_5_nodes_10_da().into();
+    config.known_nodes_with_stake = (0..4)
+        .map(|i| PeerConfig {
+            bonded_since: 10,
+            ..ValidatorConfig::generated_from_seed_indexed([0u8; 32], i, 1000, true).public_config()
+        })
+        .collect();
+    config.known_da_nodes = config.known_nodes_with_stake.clone();
+    config.bonding_curve = BondingCurve::Linear {
+        ramp_epochs: 10,
+        max_bonus_percent: 100,
+    };
+    let key = config.known_nodes_with_stake[0]
+        .stake_table_entry
+        .public_key();
+
+    let membership = ProvidedCommittee::<TestTypes>::with_provider(Arc::new(config));
+    let stake = |epoch| {
+        membership
+            .stake(&key, EpochNumber::new(epoch))
+            .unwrap()
+            .stake()
+    };
+    assert_eq!(stake(0), U256::from(1000));
+    assert_eq!(stake(10), U256::from(1000));
+    assert_eq!(stake(15), U256::from(1500));
+    assert_eq!(stake(20), U256::from(2000));
+}
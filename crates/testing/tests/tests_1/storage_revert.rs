@@ -0,0 +1,84 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        node_implementation::ConsensusTime,
+        storage::{Storage, StorageKey, ViewWrites},
+    },
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that reverting to a view undoes the writes of the views after it, newest first, and
+// that decided views can no longer be reverted once pruned.
+async fn test_revert_speculative_views() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut views = Vec::new();
+    for _ in 0..3 {
+        views.push(generator.next().await.unwrap());
+    }
+
+    let storage = TestStorage::<TestTypes>::default();
+    for view in &views {
+        storage
+            .write_view(ViewWrites {
+                proposal: Some(view.quorum_proposal.clone()),
+                vid_share: Some(view.vid_proposal.0[0].clone()),
+                high_qc: Some(view.quorum_proposal.data.justify_qc.clone()),
+                ..ViewWrites::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    let first_view = views[0].view_number;
+    let reverted = storage.revert_to(first_view).await.unwrap();
+    assert_eq!(
+        reverted.iter().map(|undo| undo.view).collect::<Vec<_>>(),
+        vec![views[2].view_number, views[1].view_number]
+    );
+    assert_eq!(reverted[1].parent, views[0].leaf.commit());
+    assert!(reverted[1]
+        .added
+        .contains(&StorageKey::Proposal(views[1].view_number)));
+    assert_eq!(reverted[1].removed.len(), 1);
+
+    // Only the first view's writes are left
+    assert_eq!(
+        storage
+            .proposals_cloned()
+            .await
+            .into_keys()
+            .collect::<Vec<_>>(),
+        vec![first_view]
+    );
+    assert_eq!(
+        storage.high_qc_cloned().await,
+        Some(views[0].quorum_proposal.data.justify_qc.clone())
+    );
+    assert!(storage.revert_to(first_view).await.unwrap().is_empty());
+
+    storage.prune(first_view + 1).await.unwrap();
+    assert!(storage
+        .revert_to(ViewNumber::genesis())
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -17,6 +17,7 @@ use hotshot_macros::cross_tests;
 use hotshot_testing::{
     block_builder::SimpleBuilderImplementation,
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+    metrics_assertions::MetricsAssertion,
     overall_safety_task::OverallSafetyPropertiesDescription,
     spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
     test_builder::TestDescription,
@@ -42,6 +43,27 @@ cross_tests!(
     },
 );
 
+cross_tests!(
+    TestName: test_success_with_healthy_metrics,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            // The first views may time out while the nodes connect
+            metrics_assertions: MetricsAssertion::healthy(2),
+            ..TestDescription::default()
+        }
+    },
+);
+
 // cross_tests!(
 //     TestName: test_epoch_success,
 //     Impls: [MemoryImpl, Libp2pImpl, PushCdnImpl],
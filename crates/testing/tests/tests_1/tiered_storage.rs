@@ -0,0 +1,120 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::collections::{BTreeMap, HashMap};
+
+use futures::StreamExt;
+use hotshot::tiered_storage::{StorageTiering, TieredStorage};
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    storage_types::TestStorage,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::ViewNumber,
+    traits::{
+        node_implementation::ConsensusTime,
+        storage::{Storage, StorageKey, StoredValue, ViewWrites},
+    },
+    utils::{View, ViewInner},
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that views decided for long enough move to the cold tier, that reads of them go through
+// to it, and that recent views stay in the hot tier.
+async fn test_tiered_storage_migrates_decided_views() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(2)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let mut views = Vec::new();
+    for _ in 0..4 {
+        views.push(generator.next().await.unwrap());
+    }
+
+    let storage = TieredStorage::new(
+        TestStorage::<TestTypes>::default(),
+        TestStorage::<TestTypes>::default(),
+        StorageTiering { hot_views: 1 },
+    );
+    for view in &views {
+        storage
+            .write_view(ViewWrites {
+                proposal: Some(view.quorum_proposal.clone()),
+                vid_share: Some(view.vid_proposal.0[0].clone()),
+                ..ViewWrites::default()
+            })
+            .await
+            .unwrap();
+    }
+    let proposal_views = |storage: &TestStorage<TestTypes>| {
+        let storage = storage.clone();
+        async move {
+            storage
+                .proposals_cloned()
+                .await
+                .into_keys()
+                .collect::<Vec<_>>()
+        }
+    };
+    assert!(proposal_views(storage.cold()).await.is_empty());
+
+    // Deciding the views before the last one keeps one decided view hot and migrates the rest
+    let undecided = BTreeMap::from([(
+        views[3].view_number,
+        View {
+            view_inner: ViewInner::Failed,
+        },
+    )]);
+    storage
+        .update_undecided_state2(HashMap::new(), undecided)
+        .await
+        .unwrap();
+    assert_eq!(storage.migrated_before().await, views[2].view_number);
+    assert_eq!(
+        proposal_views(storage.cold()).await,
+        vec![views[0].view_number, views[1].view_number]
+    );
+    assert_eq!(
+        proposal_views(storage.hot()).await,
+        vec![views[2].view_number, views[3].view_number]
+    );
+
+    // Migrated views are read from the cold tier
+    let share = &views[0].vid_proposal.0[0];
+    let key = StorageKey::VidShare(views[0].view_number, share.data.recipient_key.clone());
+    assert!(storage.hot().load(&key).await.unwrap().is_none());
+    assert!(matches!(
+        storage.load(&key).await.unwrap(),
+        Some(StoredValue::VidShare(loaded)) if loaded.data == share.data
+    ));
+    let loaded: Vec<_> = storage
+        .load_before(views[3].view_number)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter_map(|value| match value {
+            StoredValue::Proposal(proposal) => Some(proposal.data.view_number),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        loaded,
+        vec![
+            views[0].view_number,
+            views[1].view_number,
+            views[2].view_number
+        ]
+    );
+    assert!(storage
+        .load(&StorageKey::Proposal(ViewNumber::new(100)))
+        .await
+        .unwrap()
+        .is_none());
+}
@@ -0,0 +1,114 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::sync::Arc;
+
+use committable::Committable;
+use futures::StreamExt;
+use hotshot::{
+    transaction_status::{TransactionIndex, TransactionStatus, TransactionTrackerConfig},
+    types::{Event, EventType},
+};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::TestValidatedState,
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    event::{LeafInfo, RejectedTransaction},
+    traits::signature_key::ProposerId,
+};
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Follows two transactions from the mempool through a proposal to a decide, where one of them is
+// rejected by the application state.
+async fn test_transaction_lifecycle() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(1)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let mut generator = TestViewGenerator::generate(membership);
+
+    let accepted = TestTransaction::new(vec![1]);
+    let rejected = TestTransaction::new(vec![2]);
+    let unknown = TestTransaction::new(vec![3]);
+
+    let first = generator.next().await.unwrap();
+    generator.add_transactions(vec![accepted.clone(), rejected.clone()]);
+    let second = generator.next().await.unwrap();
+
+    let mut index = TransactionIndex::<TestTypes>::new(TransactionTrackerConfig::default());
+    let submitted = Event {
+        view_number: first.view_number,
+        event: EventType::Transactions {
+            transactions: vec![accepted.clone(), rejected.clone()],
+        },
+    };
+    index.handle_event(&submitted);
+    assert_eq!(
+        index.status(&accepted.commit()),
+        Some(TransactionStatus::Pending {
+            since: first.view_number
+        })
+    );
+    assert_eq!(index.status(&unknown.commit()), None);
+
+    index.handle_event(&Event {
+        view_number: second.view_number,
+        event: EventType::DaProposal {
+            proposal: second.da_proposal.clone(),
+            sender: ProposerId::new(second.leader_public_key),
+        },
+    });
+    let proposed = TransactionStatus::Proposed {
+        view: second.view_number,
+    };
+    assert_eq!(index.status(&accepted.commit()), Some(proposed.clone()));
+
+    // Seeing the transactions again does not move them back to the mempool
+    index.handle_event(&submitted);
+    assert_eq!(index.status(&rejected.commit()), Some(proposed));
+
+    let mut leaf_info = LeafInfo::new(
+        second.leaf.clone(),
+        Arc::new(TestValidatedState::default()),
+        None,
+        None,
+    );
+    leaf_info.rejected.push(RejectedTransaction {
+        commitment: rejected.commit(),
+        view_number: second.view_number,
+        reason: "insufficient balance".to_string(),
+    });
+    index.handle_event(&Event {
+        view_number: second.view_number,
+        event: EventType::Decide {
+            leaf_chain: Arc::new(vec![leaf_info]),
+            qc: Arc::new(second.quorum_proposal.data.justify_qc.clone()),
+            block_size: None,
+        },
+    });
+
+    assert_eq!(
+        index.status(&accepted.commit()),
+        Some(TransactionStatus::Finalized {
+            view: second.view_number,
+            height: second.leaf.height(),
+        })
+    );
+    assert_eq!(
+        index.status(&rejected.commit()),
+        Some(TransactionStatus::Rejected {
+            view: second.view_number,
+            reason: "insufficient balance".to_string(),
+        })
+    );
+    assert_eq!(index.len(), 2);
+}
@@ -1,12 +1,13 @@
+use futures::StreamExt;
 use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::{
     block_types::TestMetadata,
-    node_types::{MemoryImpl, TestConsecutiveLeaderTypes, TestVersions},
+    node_types::{MemoryImpl, TestConsecutiveLeaderTypes, TestTypes, TestVersions},
 };
 use hotshot_task_impls::{
     events::HotShotEvent, harness::run_harness, transactions::TransactionTaskState,
 };
-use hotshot_testing::helpers::build_system_handle;
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
 use hotshot_types::{
     data::{null_block, EpochNumber, PackedBundle, ViewNumber},
     traits::{
@@ -77,3 +78,60 @@ async fn test_transaction_task_leader_two_views_in_a_row() {
         .await;
     run_harness(input, output, transaction_state, false).await;
 }
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the leader of the next view builds its block as soon as the proposal of the current
+// view is validated, and does not build it again when it enters the view.
+async fn test_transaction_task_prefetches_the_next_block() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 3 leads view 3 and the views a full rotation later
+    let node_id = 3;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+    let membership = (*handle.hotshot.memberships).clone();
+    let epoch = EpochNumber::new(1);
+    let total_nodes = membership.total_nodes(epoch);
+    let next_turn = ViewNumber::new(3 + total_nodes as u64);
+
+    let mut generator = TestViewGenerator::generate(membership);
+    let mut proposal = generator.next().await.unwrap().quorum_proposal;
+    proposal.data.view_number = ViewNumber::new(2);
+    let mut later_proposal = proposal.clone();
+    later_proposal.data.view_number = next_turn - 1;
+
+    let input = vec![
+        HotShotEvent::ViewChange(ViewNumber::new(2), epoch),
+        HotShotEvent::QuorumProposalPreliminarilyValidated(proposal),
+        HotShotEvent::ViewChange(ViewNumber::new(3), epoch),
+        HotShotEvent::QuorumProposalPreliminarilyValidated(later_proposal),
+        HotShotEvent::Shutdown,
+    ];
+
+    let (_, precompute_data) = precompute_vid_commitment(&[], total_nodes);
+    let empty_block = |view: ViewNumber| {
+        HotShotEvent::BlockRecv(PackedBundle::new(
+            vec![].into(),
+            TestMetadata {
+                num_transactions: 0,
+            },
+            view,
+            epoch,
+            vec1::vec1![null_block::builder_fee::<TestTypes, TestVersions>(
+                total_nodes,
+                <TestVersions as Versions>::Base::VERSION,
+                *view,
+            )
+            .unwrap()],
+            Some(precompute_data.clone()),
+            None,
+        ))
+    };
+    let output = vec![empty_block(ViewNumber::new(3)), empty_block(next_turn)];
+
+    let transaction_state =
+        TransactionTaskState::<TestTypes, MemoryImpl, TestVersions>::create_from(&handle).await;
+    run_harness(input, output, transaction_state, false).await;
+}
@@ -101,6 +101,11 @@ async fn test_upgrade_task_with_proposal() {
                 None,
             )
             .unwrap();
+        // Saved by the quorum vote task, which we don't run here
+        consensus_writer.update_saved_da_certs(
+            view.quorum_proposal.data.view_number,
+            view.da_certificate.clone(),
+        );
     }
 
     generator.add_upgrade(upgrade_data.clone());
@@ -120,6 +125,11 @@ async fn test_upgrade_task_with_proposal() {
                 None,
             )
             .unwrap();
+        // Saved by the quorum vote task, which we don't run here
+        consensus_writer.update_saved_da_certs(
+            view.quorum_proposal.data.view_number,
+            view.da_certificate.clone(),
+        );
     }
     drop(consensus_writer);
 
@@ -170,6 +180,7 @@ async fn test_upgrade_task_with_proposal() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[0].clone()),
             Qc2Formed(either::Left(proposals[1].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[0].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -188,6 +199,7 @@ async fn test_upgrade_task_with_proposal() {
         random![
             QuorumProposalPreliminarilyValidated(proposals[1].clone()),
             Qc2Formed(either::Left(proposals[2].data.justify_qc.clone())),
+            DaCertificateValidated(dacs[1].clone()),
             SendPayloadCommitmentAndMetadata(
                 build_payload_commitment::<TestTypes>(
                     &membership,
@@ -103,33 +103,35 @@ async fn test_upgrade_task_with_vote() {
 
     let inputs = vec![
         random![
-            QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+            QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
+            DaCertificateRecv(dacs[0].clone()),
             DaCertificateRecv(dacs[1].clone()),
             VidShareRecv(leaders[1], vids[1].0[0].clone()),
         ],
         random![
-            QuorumProposalValidated(proposals[2].clone(), leaves[1].clone()),
+            QuorumProposalValidated(proposals[2].clone(), leaves[1].clone().into()),
             DaCertificateRecv(dacs[2].clone()),
             VidShareRecv(leaders[2], vids[2].0[0].clone()),
         ],
         random![
-            QuorumProposalValidated(proposals[3].clone(), leaves[2].clone()),
+            QuorumProposalValidated(proposals[3].clone(), leaves[2].clone().into()),
             DaCertificateRecv(dacs[3].clone()),
             VidShareRecv(leaders[3], vids[3].0[0].clone()),
         ],
         random![
-            QuorumProposalValidated(proposals[4].clone(), leaves[3].clone()),
+            QuorumProposalValidated(proposals[4].clone(), leaves[3].clone().into()),
             DaCertificateRecv(dacs[4].clone()),
             VidShareRecv(leaders[4], vids[4].0[0].clone()),
         ],
         random![QuorumProposalValidated(
             proposals[5].clone(),
-            leaves[5].clone()
+            leaves[5].clone().into()
         ),],
     ];
 
     let expectations = vec![
         Expectations::from_outputs(all_predicates![
+            exact(DaCertificateValidated(dacs[0].clone())),
             exact(DaCertificateValidated(dacs[1].clone())),
             exact(VidShareValidated(vids[1].0[0].clone())),
             exact(ViewChange(ViewNumber::new(3), EpochNumber::new(0))),
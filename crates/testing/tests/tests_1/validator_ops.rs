@@ -0,0 +1,231 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot::validator_ops::{OperationStatus, ValidatorRegistry};
+use hotshot_example_types::{
+    block_types::TestTransaction,
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+};
+use hotshot_testing::{helpers::build_system_handle, view_generator::TestViewGenerator};
+use hotshot_types::{
+    data::EpochNumber,
+    signature_key::BLSPubKey,
+    traits::{
+        election::StakeTableProvider,
+        node_implementation::ConsensusTime,
+        signature_key::{SignatureKey, StakeTableEntryType},
+        signer::{LocalSigner, Signer},
+    },
+    utils::epoch_from_block_number,
+    validator_ops::{
+        effective_epoch, OperationError, SignedOperation, ValidatorOperation, ValidatorSet,
+    },
+    ValidatorConfig,
+};
+
+/// The chain of the tests.
+const CHAIN_ID: u64 = 7;
+
+/// Blocks per epoch in the tests.
+const EPOCH_HEIGHT: u64 = 2;
+
+/// The validator with `index` and `stake`, and a signer holding its consensus key.
+fn validator(index: u64, stake: u64) -> (ValidatorConfig<BLSPubKey>, LocalSigner<BLSPubKey>) {
+    let config = ValidatorConfig::generated_from_seed_indexed([0u8; 32], index, stake, false);
+    let signer = LocalSigner::new(config.public_key, config.private_key.clone());
+    (config, signer)
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Takes a validator through registration, a stake update, a key rotation and its exit, and checks
+// that replayed, forged and conflicting operations change nothing.
+async fn test_validator_lifecycle() {
+    hotshot::helpers::initialize_logging();
+
+    let (initial, initial_signer) = validator(0, 10);
+    let (joining, signer) = validator(1, 5);
+    let (rotated, new_signer) = validator(2, 0);
+    let key = joining.public_key;
+    let new_key = rotated.public_key;
+    let mut validators = ValidatorSet::new(CHAIN_ID, vec![initial.public_config()]);
+
+    let register = ValidatorOperation::Register {
+        peer: joining.public_config(),
+    };
+    let register = SignedOperation::sign(register, CHAIN_ID, 0, &signer, None)
+        .await
+        .unwrap();
+    validators.apply(&register, 7).unwrap();
+    assert_eq!(validators.validator(&key).unwrap().bonded_since, 7);
+    assert_eq!(
+        validators.apply(&register, 8),
+        Err(OperationError::WrongNonce {
+            expected: 1,
+            got: 0
+        })
+    );
+
+    // Only the validator itself can sign its operations, and only for this chain
+    let update = ValidatorOperation::UpdateStake { key, stake: 20 };
+    let update = SignedOperation::sign(update, CHAIN_ID, 1, &signer, None)
+        .await
+        .unwrap();
+    let mut forged = update.clone();
+    forged.operation = ValidatorOperation::UpdateStake {
+        key: initial.public_key,
+        stake: 20,
+    };
+    assert_eq!(
+        validators.apply(&forged, 8),
+        Err(OperationError::InvalidSignature)
+    );
+    let exit = ValidatorOperation::Exit {
+        key: initial.public_key,
+    };
+    let other_chain = SignedOperation::sign(exit, CHAIN_ID + 1, 0, &initial_signer, None)
+        .await
+        .unwrap();
+    assert!(matches!(
+        validators.apply(&other_chain, 8),
+        Err(OperationError::WrongChain { .. })
+    ));
+
+    validators.apply(&update, 8).unwrap();
+    assert_eq!(
+        validators
+            .validator(&key)
+            .unwrap()
+            .stake_table_entry
+            .stake(),
+        key.stake_table_entry(20).stake()
+    );
+
+    // A rotation must be co-signed by the new key, which must be unused
+    let rotate = ValidatorOperation::RotateKey {
+        key,
+        new_key,
+        new_state_ver_key: rotated.state_key_pair.ver_key(),
+    };
+    assert!(
+        SignedOperation::sign(rotate.clone(), CHAIN_ID, 2, &signer, None)
+            .await
+            .is_err()
+    );
+    let rotate = SignedOperation::sign(rotate, CHAIN_ID, 2, &signer, Some(&new_signer))
+        .await
+        .unwrap();
+    validators.apply(&rotate, 9).unwrap();
+    assert!(validators.validator(&key).is_none());
+    let entry = validators.validator(&new_key).unwrap();
+    assert_eq!(
+        entry.stake_table_entry.stake(),
+        key.stake_table_entry(20).stake()
+    );
+    assert_eq!(entry.bonded_since, 7);
+    assert_eq!(validators.next_nonce(&new_key), 3);
+
+    let back = ValidatorOperation::RotateKey {
+        key: new_key,
+        new_key: key,
+        new_state_ver_key: joining.state_key_pair.ver_key(),
+    };
+    let back = SignedOperation::sign(back, CHAIN_ID, 3, &new_signer, Some(&signer))
+        .await
+        .unwrap();
+    assert_eq!(validators.apply(&back, 10), Err(OperationError::KeyInUse));
+
+    let exit = ValidatorOperation::Exit { key: new_key };
+    let exit = SignedOperation::sign(exit, CHAIN_ID, 3, &new_signer, None)
+        .await
+        .unwrap();
+    validators.apply(&exit, 10).unwrap();
+    assert_eq!(validators.stake_table(), vec![initial.public_config()]);
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the registry applies operations from decided leaves, reports their outcome, and
+// only changes the stake table of the epoch they take effect in.
+async fn test_validator_registry() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let mut generator = TestViewGenerator::generate((*handle.hotshot.memberships).clone());
+    let (initial, _) = validator(0, 10);
+    let (joining, signer) = validator(1, 5);
+    let registry = ValidatorRegistry::<TestTypes>::new(
+        CHAIN_ID,
+        EPOCH_HEIGHT,
+        vec![initial.public_config()],
+        vec![],
+    );
+
+    let register = ValidatorOperation::Register {
+        peer: joining.public_config(),
+    };
+    let register = SignedOperation::sign(register, CHAIN_ID, 0, &signer, None)
+        .await
+        .unwrap();
+    let wrong_chain = SignedOperation::sign(
+        ValidatorOperation::Exit {
+            key: *signer.public_key(),
+        },
+        CHAIN_ID + 1,
+        0,
+        &signer,
+        None,
+    )
+    .await
+    .unwrap();
+    let register = register.to_transaction::<TestTransaction>().unwrap();
+    let wrong_chain = wrong_chain.to_transaction::<TestTransaction>().unwrap();
+
+    let first = generator.next().await.unwrap().leaf;
+    registry.apply_leaf(&first).unwrap();
+    generator.add_transactions(vec![register.clone(), wrong_chain.clone()]);
+    let decided = generator.next().await.unwrap().leaf;
+    let skipped = generator.next().await.unwrap().leaf;
+    let mut leaf = generator.next().await.unwrap().leaf;
+    assert!(registry.apply_leaf(&leaf).is_err());
+    registry.apply_leaf(&decided).unwrap();
+
+    let height = decided.height();
+    let epoch = effective_epoch(height, EPOCH_HEIGHT);
+    assert_eq!(
+        registry.status(&register.commit()),
+        Some(OperationStatus::Applied {
+            height,
+            effective_epoch: EpochNumber::new(epoch),
+        })
+    );
+    assert!(matches!(
+        registry.status(&wrong_chain.commit()),
+        Some(OperationStatus::Invalid {
+            reason: OperationError::WrongChain { .. },
+            ..
+        })
+    ));
+    assert_eq!(registry.next_nonce(&joining.public_key), 1);
+
+    // The epoch before the operation takes effect keeps the old table, and the table of the epoch
+    // it takes effect in is only known once the epoch it was decided in is over
+    let (stake_table, _) = registry.stake_tables(EpochNumber::new(epoch - 1)).unwrap();
+    assert_eq!(stake_table, vec![initial.public_config()]);
+    assert!(registry.stake_tables(EpochNumber::new(epoch)).is_none());
+
+    registry.apply_leaf(&skipped).unwrap();
+    registry.apply_leaf(&leaf).unwrap();
+    while epoch_from_block_number(leaf.height(), EPOCH_HEIGHT) < epoch - 1 {
+        leaf = generator.next().await.unwrap().leaf;
+        registry.apply_leaf(&leaf).unwrap();
+    }
+    let (stake_table, _) = registry.stake_tables(EpochNumber::new(epoch)).unwrap();
+    assert_eq!(stake_table.len(), 2);
+}
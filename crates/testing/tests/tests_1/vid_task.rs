@@ -6,7 +6,7 @@
 
 use std::{marker::PhantomData, sync::Arc};
 
-use hotshot::{tasks::task_state::CreateTaskState, types::SignatureKey};
+use hotshot::tasks::task_state::CreateTaskState;
 use hotshot_example_types::{
     block_types::{TestBlockPayload, TestMetadata, TestTransaction},
     node_types::{MemoryImpl, TestTypes, TestVersions},
@@ -25,7 +25,7 @@ use hotshot_types::{
     traits::{
         consensus_api::ConsensusApi,
         election::Membership,
-        node_implementation::{ConsensusTime, NodeType, Versions},
+        node_implementation::{ConsensusTime, Versions},
         BlockPayload,
     },
 };
@@ -68,11 +68,11 @@ async fn test_vid_task() {
     let (_, vid_precompute) = vid.commit_only_precompute(&encoded_transactions).unwrap();
     let payload_commitment = vid_disperse.commit;
 
-    let signature = <TestTypes as NodeType>::SignatureKey::sign(
-        handle.private_key(),
-        payload_commitment.as_ref(),
-    )
-    .expect("Failed to sign block payload!");
+    let signature = handle
+        .signer()
+        .sign(payload_commitment.as_ref())
+        .await
+        .expect("Failed to sign block payload!");
     let proposal: DaProposal<TestTypes> = DaProposal {
         encoded_transactions: encoded_transactions.clone(),
         metadata: TestMetadata {
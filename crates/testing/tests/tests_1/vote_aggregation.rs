@@ -0,0 +1,367 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    marker::PhantomData,
+    num::NonZeroUsize,
+    time::Duration,
+};
+
+use committable::Committable;
+use either::Either;
+use hotshot::tasks::task_state::CreateTaskState;
+use hotshot_example_types::{
+    node_types::{MemoryImpl, TestTypes, TestVersions},
+    state_types::{TestInstanceState, TestValidatedState},
+};
+use hotshot_task_impls::{
+    events::HotShotEvent, harness::run_harness, vote_aggregator::VoteAggregatorTaskState,
+};
+use hotshot_testing::helpers::build_system_handle;
+use hotshot_types::{
+    data::{EpochNumber, Leaf2, ViewNumber},
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::{QuorumData2, QuorumVote2, VersionedVoteData},
+    traits::{
+        election::Membership, node_implementation::ConsensusTime, signature_key::SignatureKey,
+    },
+    vote::{Certificate, Vote, VoteAccumulator},
+    vote_aggregation::{AggregationConfig, PartialCertificate},
+};
+
+/// Aggregation with three aggregators per view, which never forward before their group is complete
+fn config() -> AggregationConfig {
+    AggregationConfig {
+        enabled: true,
+        aggregators: NonZeroUsize::new(3).unwrap(),
+        flush_delay: Duration::from_secs(60),
+    }
+}
+
+/// Quorum votes of every node for the genesis leaf in `view`.
+async fn votes(
+    view: ViewNumber,
+    epoch: EpochNumber,
+    total_nodes: u64,
+) -> Vec<(BLSPubKey, QuorumVote2<TestTypes>)> {
+    let upgrade_lock = UpgradeLock::<TestTypes, TestVersions>::new();
+    let leaf = Leaf2::<TestTypes>::genesis(
+        &TestValidatedState::default(),
+        &TestInstanceState::default(),
+    )
+    .await;
+
+    let mut votes = vec![];
+    for id in 0..total_nodes {
+        let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], id);
+        let vote = QuorumVote2::create_signed_vote(
+            QuorumData2 {
+                leaf_commit: leaf.commit(),
+                epoch,
+            },
+            view,
+            &public_key,
+            &private_key,
+            &upgrade_lock,
+        )
+        .await
+        .expect("Failed to create a quorum vote");
+        votes.push((public_key, vote));
+    }
+    votes
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that every voter sends its vote to exactly one of the configured number of aggregators,
+// and that the aggregators' groups cover the stake table.
+async fn test_every_voter_has_one_aggregator() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let epoch = EpochNumber::new(1);
+    let total_nodes = membership.total_nodes(epoch);
+    let config = config();
+
+    for view in (1..=total_nodes as u64 + 1).map(ViewNumber::new) {
+        let mut groups: HashMap<BLSPubKey, usize> = HashMap::new();
+        for id in 0..total_nodes as u64 {
+            let (voter, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], id);
+            let aggregator = config
+                .aggregator_for::<TestTypes>(&voter, membership, view, epoch)
+                .expect("Every voter with stake should have an aggregator");
+            *groups.entry(aggregator).or_default() += 1;
+        }
+
+        assert_eq!(groups.len(), config.aggregators.get());
+        for (aggregator, voters) in &groups {
+            assert_eq!(
+                config.voters_of::<TestTypes>(aggregator, membership, view, epoch),
+                *voters
+            );
+        }
+        assert_eq!(groups.values().sum::<usize>(), total_nodes);
+    }
+
+    // Votes go straight to the leader when aggregation is disabled
+    let (voter, _) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    assert!(AggregationConfig::default()
+        .aggregator_for::<TestTypes>(&voter, membership, ViewNumber::new(1), epoch)
+        .is_none());
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that an aggregator forwards the votes it has to the next leader when the flush delay
+// passes, and the rest of its group's votes as soon as they are all in.
+async fn test_vote_aggregator_task_forwards_partial_certificates() {
+    hotshot::helpers::initialize_logging();
+
+    // Node 2 aggregates the votes of view 2, whose next leader is node 3
+    let node_id = 2;
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(node_id)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let epoch = EpochNumber::new(1);
+    let view = ViewNumber::new(2);
+    let total_nodes = membership.total_nodes(epoch);
+    let leader = membership.leader(view + 1, epoch).unwrap();
+    assert_ne!(leader, handle.public_key());
+
+    let stake_table = membership.stake_table(epoch);
+    let ours: Vec<_> = votes(view, epoch, total_nodes as u64)
+        .await
+        .into_iter()
+        .filter(|(voter, _)| {
+            config().aggregator_for::<TestTypes>(voter, membership, view, epoch)
+                == Some(handle.public_key())
+        })
+        .map(|(voter, vote)| {
+            let index = stake_table
+                .iter()
+                .position(|entry| BLSPubKey::public_key(entry) == voter)
+                .unwrap();
+            (index, vote)
+        })
+        .collect();
+    assert!(ours.len() > 1);
+
+    let partial = |votes: &[(usize, QuorumVote2<TestTypes>)]| {
+        let signatures: BTreeMap<_, _> = votes
+            .iter()
+            .map(|(index, vote)| (*index, vote.signature()))
+            .collect();
+        HotShotEvent::PartialCertificateSend(
+            PartialCertificate::new(ours[0].1.data.clone(), view, total_nodes, &signatures),
+            leader.clone(),
+            handle.public_key(),
+        )
+    };
+    let (last, early) = ours.split_last().unwrap();
+
+    let mut input = vec![HotShotEvent::ViewChange(view + 1, epoch)];
+    input.extend(
+        early
+            .iter()
+            .map(|(_, vote)| HotShotEvent::QuorumVoteRecv(vote.clone())),
+    );
+    input.push(HotShotEvent::AggregationTimeout(view));
+    input.push(HotShotEvent::QuorumVoteRecv(last.1.clone()));
+    input.push(HotShotEvent::Shutdown);
+    let output = vec![partial(early), partial(std::slice::from_ref(last))];
+
+    let mut aggregator_state =
+        VoteAggregatorTaskState::<TestTypes, TestVersions>::create_from(&handle).await;
+    aggregator_state.config = config();
+    run_harness(input, output, aggregator_state, false).await;
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that the leader forms a QC from the partial certificates of the aggregators, never counts
+// a vote twice, and still counts the other votes of a partial certificate repeating some.
+async fn test_partial_certificates_form_a_quorum_certificate() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let epoch = EpochNumber::new(1);
+    let view = ViewNumber::new(2);
+    let total_nodes = membership.total_nodes(epoch);
+    let stake_table = membership.stake_table(epoch);
+
+    // Group the votes as the aggregators would
+    let mut groups: HashMap<BLSPubKey, BTreeMap<usize, _>> = HashMap::new();
+    let all_votes = votes(view, epoch, total_nodes as u64).await;
+    for (voter, vote) in &all_votes {
+        let aggregator = config()
+            .aggregator_for::<TestTypes>(voter, membership, view, epoch)
+            .unwrap();
+        let index = stake_table
+            .iter()
+            .position(|entry| BLSPubKey::public_key(entry) == *voter)
+            .unwrap();
+        groups
+            .entry(aggregator)
+            .or_default()
+            .insert(index, vote.signature());
+    }
+    let data = all_votes[0].1.data.clone();
+    let vote_commitment = VersionedVoteData::new(data.clone(), view, upgrade_lock)
+        .await
+        .unwrap()
+        .commit();
+    let partials: Vec<_> = groups
+        .values()
+        .map(|signatures| PartialCertificate::new(data.clone(), view, total_nodes, signatures))
+        .collect();
+    for partial in &partials {
+        assert!(partial.is_valid(&stake_table, vote_commitment));
+    }
+
+    let accumulator = || VoteAccumulator::<
+        TestTypes,
+        QuorumVote2<TestTypes>,
+        QuorumCertificate2<TestTypes>,
+        TestVersions,
+    > {
+        vote_outcomes: HashMap::new(),
+        signers: HashMap::new(),
+        phantom: PhantomData,
+        upgrade_lock: upgrade_lock.clone(),
+    };
+    let mut from_partials = accumulator();
+    assert!(from_partials
+        .accumulate_partial(&partials[0], vote_commitment, membership, epoch)
+        .is_left());
+    assert!(from_partials
+        .accumulate_partial(&partials[0], vote_commitment, membership, epoch)
+        .is_left());
+    let cert = partials[1..]
+        .iter()
+        .find_map(|partial| {
+            from_partials
+                .accumulate_partial(partial, vote_commitment, membership, epoch)
+                .right()
+        })
+        .expect("The partial certificates of every voter should form a QC");
+    assert!(
+        cert.is_valid_cert(
+            &stake_table,
+            membership.success_threshold(epoch),
+            upgrade_lock
+        )
+        .await
+    );
+
+    // Every vote at once, some of which were counted already, adds the others
+    let everyone = PartialCertificate::new(
+        data,
+        view,
+        total_nodes,
+        &groups
+            .values()
+            .flatten()
+            .map(|(i, s)| (*i, s.clone()))
+            .collect(),
+    );
+    let mut overlapping = accumulator();
+    assert!(overlapping
+        .accumulate_partial(&partials[0], vote_commitment, membership, epoch)
+        .is_left());
+
+    // But none are counted if one of those is invalid, even though the aggregate signature is valid
+    let fresh: Vec<_> = everyone
+        .signers
+        .iter_ones()
+        .enumerate()
+        .filter(|(_, index)| !partials[0].signers[*index])
+        .map(|(position, _)| position)
+        .collect();
+    let mut swapped = everyone.clone();
+    swapped.signatures.swap(fresh[0], fresh[1]);
+    assert!(swapped.is_valid(&stake_table, vote_commitment));
+    assert!(overlapping
+        .accumulate_partial(&swapped, vote_commitment, membership, epoch)
+        .is_left());
+    assert_eq!(
+        overlapping.signers.values().next().unwrap().0,
+        partials[0].signers
+    );
+
+    let cert = overlapping
+        .accumulate_partial(&everyone, vote_commitment, membership, epoch)
+        .right()
+        .expect("The votes not counted yet should complete the QC");
+    assert!(
+        cert.is_valid_cert(
+            &stake_table,
+            membership.success_threshold(epoch),
+            upgrade_lock
+        )
+        .await
+    );
+
+    // A signature of another aggregator's voter invalidates the whole partial certificate
+    let mut forged = partials[0].clone();
+    forged.signatures[0] = partials[1].signatures[0].clone();
+    assert!(!forged.is_valid(&stake_table, vote_commitment));
+}
+
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+// Checks that appending votes hands the accumulator back until a quorum has voted, and then a
+// valid certificate.
+async fn test_vote_accumulator_append() {
+    hotshot::helpers::initialize_logging();
+
+    let handle = build_system_handle::<TestTypes, MemoryImpl, TestVersions>(0)
+        .await
+        .0;
+    let membership = &handle.hotshot.memberships;
+    let upgrade_lock = &handle.hotshot.upgrade_lock;
+    let epoch = EpochNumber::new(1);
+    let view = ViewNumber::new(2);
+    let total_nodes = membership.total_nodes(epoch);
+
+    let mut accumulator = Either::Left(VoteAccumulator::<
+        TestTypes,
+        QuorumVote2<TestTypes>,
+        QuorumCertificate2<TestTypes>,
+        TestVersions,
+    >::new(upgrade_lock.clone()));
+    let mut appended = 0;
+    for (_, vote) in votes(view, epoch, total_nodes as u64).await {
+        let Either::Left(pending) = accumulator else {
+            break;
+        };
+        accumulator = pending.append(&vote, membership, epoch).await;
+        appended += 1;
+    }
+
+    let cert = accumulator
+        .right()
+        .expect("The votes of every node should form a QC");
+    assert!(appended < total_nodes);
+    assert!(
+        cert.is_valid_cert(
+            &membership.stake_table(epoch),
+            membership.success_threshold(epoch),
+            upgrade_lock
+        )
+        .await
+    );
+}
@@ -60,13 +60,15 @@ async fn test_vote_dependency_handle() {
             )
             .unwrap();
     }
+    // We only vote to extend a leaf whose DAC we have validated
+    consensus_writer.update_saved_da_certs(ViewNumber::new(1), dacs[0].clone());
     drop(consensus_writer);
 
     // We permute all possible orderings of inputs. Ordinarily we'd use `random!` for this, but
     // the dependency handles do not (yet) work with the existing test suite.
     let all_inputs = vec![
         DaCertificateValidated(dacs[1].clone()),
-        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone()),
+        QuorumProposalValidated(proposals[1].clone(), leaves[0].clone().into()),
         VidShareValidated(vids[1].0[0].clone()),
     ]
     .into_iter()
@@ -86,9 +88,10 @@ async fn test_vote_dependency_handle() {
         let vote_dependency_handle_state =
             VoteDependencyHandle::<TestTypes, MemoryImpl, TestVersions> {
                 public_key: handle.public_key(),
-                private_key: handle.private_key().clone(),
+                signer: handle.signer(),
                 consensus: OuterConsensus::new(consensus.clone()),
                 consensus_metrics: Arc::clone(&consensus.read().await.metrics),
+                state_workers: Arc::clone(&handle.hotshot.state_workers),
                 instance_state: handle.hotshot.instance_state(),
                 quorum_membership: (*handle.hotshot.memberships).clone().into(),
                 storage: Arc::clone(&handle.storage()),
@@ -0,0 +1,73 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use committable::Committable;
+use hotshot_example_types::node_types::{EpochsTestVersions, TestTypes, TestVersions};
+use hotshot_types::{
+    data::{EpochNumber, ViewNumber},
+    message::UpgradeLock,
+    signature_key::BLSPubKey,
+    simple_vote::{TimeoutData2, TimeoutVote2, VersionedVoteData},
+    traits::{
+        node_implementation::{ConsensusTime, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::Vote,
+};
+
+/// A timeout vote for `view`, signed on the chain of `upgrade_lock`.
+async fn timeout_vote<V: Versions>(
+    view: ViewNumber,
+    upgrade_lock: &UpgradeLock<TestTypes, V>,
+) -> TimeoutVote2<TestTypes> {
+    let (public_key, private_key) = BLSPubKey::generated_from_seed_indexed([0u8; 32], 0);
+    let data = TimeoutData2 {
+        view,
+        epoch: EpochNumber::new(1),
+    };
+    TimeoutVote2::create_signed_vote(data, view, &public_key, &private_key, upgrade_lock)
+        .await
+        .unwrap()
+}
+
+/// Whether `vote` validates as a vote for `view` on the chain of `upgrade_lock`.
+async fn validates<V: Versions>(
+    vote: &TimeoutVote2<TestTypes>,
+    view: ViewNumber,
+    upgrade_lock: &UpgradeLock<TestTypes, V>,
+) -> bool {
+    let commit = VersionedVoteData::new(vote.date().clone(), view, upgrade_lock)
+        .await
+        .unwrap()
+        .commit();
+    vote.signing_key()
+        .validate(&vote.signature(), commit.as_ref())
+}
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that a vote signed for one chain and view validates for no other.
+async fn votes_cannot_be_replayed_across_chains_or_views() {
+    let chain_a = UpgradeLock::<TestTypes, EpochsTestVersions>::new().with_chain_id(1);
+    let chain_b = UpgradeLock::<TestTypes, EpochsTestVersions>::new().with_chain_id(2);
+    let view = ViewNumber::new(5);
+    let vote = timeout_vote(view, &chain_a).await;
+
+    assert!(validates(&vote, view, &chain_a).await);
+    assert!(!validates(&vote, view, &chain_b).await);
+    assert!(!validates(&vote, view + 1, &chain_a).await);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+// Checks that votes from before the epochs version are unaffected by the chain, so that
+// certificates signed before the upgrade still verify.
+async fn votes_before_epochs_ignore_the_chain() {
+    let chain_a = UpgradeLock::<TestTypes, TestVersions>::new().with_chain_id(1);
+    let chain_b = UpgradeLock::<TestTypes, TestVersions>::new().with_chain_id(2);
+    let view = ViewNumber::new(5);
+    let vote = timeout_vote(view, &chain_a).await;
+
+    assert!(validates(&vote, view, &chain_b).await);
+}
@@ -0,0 +1,52 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+use std::time::Duration;
+
+use hotshot_task_impls::workers::WorkerPool;
+use hotshot_types::{signature_key::BLSPubKey, traits::signature_key::SignatureKey};
+use tokio::time::{sleep, timeout};
+
+#[tokio::test(flavor = "current_thread")]
+async fn test_worker_pool_does_not_block_the_caller() {
+    let pool = WorkerPool::new("test", 1).unwrap();
+
+    // Block the only worker thread outright
+    let blocked = pool.spawn(async {
+        std::thread::sleep(Duration::from_millis(500));
+        1
+    });
+
+    // Our own single-threaded runtime is still free to make progress in the meantime
+    timeout(Duration::from_millis(100), sleep(Duration::from_millis(10)))
+        .await
+        .expect("caller runtime was blocked by the worker pool");
+
+    assert_eq!(blocked.await.unwrap(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_worker_pool_closes_channel_on_shutdown() {
+    let pool = WorkerPool::new("test", 1).unwrap();
+
+    let pending = pool.spawn(async {
+        sleep(Duration::from_secs(60)).await;
+    });
+    drop(pool);
+
+    assert!(pending.await.is_err());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_worker_pool_verifies_signatures() {
+    let pool = WorkerPool::new("test", 2).unwrap();
+    let key = |index| BLSPubKey::generated_from_seed_indexed([0u8; 32], index);
+    let signature = BLSPubKey::sign(&key(0).1, &[1u8; 32]).unwrap();
+
+    assert!(pool.verify(key(0).0, signature.clone(), [1u8; 32]).await);
+    assert!(!pool.verify(key(0).0, signature.clone(), [2u8; 32]).await);
+    assert!(!pool.verify(key(1).0, signature, [1u8; 32]).await);
+}
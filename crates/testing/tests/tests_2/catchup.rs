@@ -307,6 +307,73 @@ async fn test_catchup_reload() {
         .await;
 }
 
+/// Test that nodes which join long after genesis, reloaded from the anchor leaf, catch up and
+/// vote within a bounded number of views
+#[cfg(test)]
+#[tokio::test(flavor = "multi_thread")]
+async fn test_late_join_votes() {
+    use std::time::Duration;
+
+    use hotshot_example_types::node_types::{MemoryImpl, TestTypes, TestVersions};
+    use hotshot_testing::{
+        block_builder::SimpleBuilderImplementation,
+        completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
+        overall_safety_task::OverallSafetyPropertiesDescription,
+        spinning_task::{ChangeNode, NodeAction, SpinningTaskDescription},
+        test_builder::{TestDescription, TimingData},
+    };
+
+    hotshot::helpers::initialize_logging();
+
+    let timing_data = TimingData {
+        next_view_timeout: 2000,
+        ..Default::default()
+    };
+    let mut metadata: TestDescription<TestTypes, MemoryImpl, TestVersions> =
+        TestDescription::default();
+    let late_nodes = vec![
+        ChangeNode {
+            idx: 18,
+            updown: NodeAction::Up,
+        },
+        ChangeNode {
+            idx: 19,
+            updown: NodeAction::Up,
+        },
+    ];
+
+    metadata.timing_data = timing_data;
+    metadata.start_nodes = 18;
+    metadata.skip_late = true;
+    metadata.num_nodes_with_stake = 20;
+    metadata.late_join_max_views = Some(10);
+
+    metadata.view_sync_properties =
+        hotshot_testing::view_sync_task::ViewSyncTaskDescription::Threshold(0, 20);
+
+    metadata.spinning_properties = SpinningTaskDescription {
+        node_changes: vec![(30, late_nodes)],
+    };
+
+    metadata.completion_task_description =
+        CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+            TimeBasedCompletionTaskDescription {
+                duration: Duration::from_secs(90),
+            },
+        );
+    metadata.overall_safety_properties = OverallSafetyPropertiesDescription {
+        // Make sure we keep committing rounds well past the bound after the nodes join.
+        num_successful_views: 45,
+        ..Default::default()
+    };
+
+    metadata
+        .gen_launcher(0)
+        .launch()
+        .run_test::<SimpleBuilderImplementation>()
+        .await;
+}
+
 cross_tests!(
     TestName: test_all_restart,
     Impls: [CombinedImpl, PushCdnImpl],
@@ -474,3 +541,53 @@ cross_tests!(
       metadata
     },
 );
+
+// Nodes rebuilt from nothing but their persisted storage must resume in the view they last acted
+// in, with the high QC they persisted, and never vote or propose again in a view they acted in.
+cross_tests!(
+    TestName: test_recover_from_storage,
+    Impls: [CombinedImpl, PushCdnImpl],
+    Types: [TestTypes, TestTypesRandomizedLeader],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+      let timing_data = TimingData {
+          next_view_timeout: 2000,
+          ..Default::default()
+      };
+      let mut metadata = TestDescription::default();
+      let mut recovering_nodes = vec![];
+
+      for i in 0..10 {
+          recovering_nodes.push(ChangeNode {
+              idx: i,
+              updown: NodeAction::RecoverDown(0),
+          })
+      }
+
+      metadata.timing_data = timing_data;
+      metadata.start_nodes = 10;
+      metadata.num_nodes_with_stake = 10;
+
+      metadata.spinning_properties = SpinningTaskDescription {
+          // Recover all the nodes in view 13
+          node_changes: vec![(13, recovering_nodes)],
+      };
+      metadata.view_sync_properties =
+          hotshot_testing::view_sync_task::ViewSyncTaskDescription::Threshold(0, 10);
+
+      metadata.completion_task_description =
+          CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+              TimeBasedCompletionTaskDescription {
+                  duration: Duration::from_secs(60),
+              },
+          );
+      metadata.overall_safety_properties = OverallSafetyPropertiesDescription {
+          num_successful_views: 22,
+          num_failed_views: 15,
+          ..Default::default()
+      };
+
+      metadata
+    },
+);
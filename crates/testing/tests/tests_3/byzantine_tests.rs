@@ -15,7 +15,7 @@ use hotshot_testing::{
     block_builder::SimpleBuilderImplementation,
     byzantine::byzantine_behaviour::{
         BadProposalViewDos, DishonestDa, DishonestLeader, DishonestVoter, DishonestVoting,
-        DoubleProposeVote,
+        DoubleProposeVote, Withholding, WithholdingLeader,
     },
     completion_task::{CompletionTaskDescription, TimeBasedCompletionTaskDescription},
     test_builder::{Behaviour, TestDescription},
@@ -128,6 +128,129 @@ cross_tests!(
     },
 );
 
+// Test where node 2, the leader of views 2, 7 and 12, never sends its second and third proposals,
+// so that views 7 and 12 time out
+cross_tests!(
+    TestName: withholding_leader,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let behaviour = Rc::new(|node_id| {
+                let withholding_leader = WithholdingLeader::new(
+                    Withholding::Withhold,
+                    HashSet::from([2, 3]),
+                );
+                match node_id {
+                    2 => Behaviour::Byzantine(Box::new(withholding_leader)),
+                    _ => Behaviour::Standard,
+                }
+            });
+
+        let mut metadata = TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            behaviour,
+            ..TestDescription::default()
+        };
+
+        metadata.overall_safety_properties.num_failed_views = 2;
+        metadata.num_nodes_with_stake = 5;
+        metadata.overall_safety_properties.expected_views_to_fail = HashMap::from([
+            (ViewNumber::new(7), false),
+            (ViewNumber::new(12), false)
+        ]);
+        metadata
+    },
+);
+
+// Test where node 2, the leader of views 2, 7 and 12, sends its second and third proposals to
+// nodes 0 and 1 only, too few to form a QC, so that views 7 and 12 time out
+cross_tests!(
+    TestName: selective_leader,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let behaviour = Rc::new(|node_id| {
+                let withholding_leader = WithholdingLeader::new(
+                    Withholding::SendTo(vec![0, 1]),
+                    HashSet::from([2, 3]),
+                );
+                match node_id {
+                    2 => Behaviour::Byzantine(Box::new(withholding_leader)),
+                    _ => Behaviour::Standard,
+                }
+            });
+
+        let mut metadata = TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            behaviour,
+            ..TestDescription::default()
+        };
+
+        metadata.overall_safety_properties.num_failed_views = 2;
+        metadata.num_nodes_with_stake = 5;
+        metadata.overall_safety_properties.expected_views_to_fail = HashMap::from([
+            (ViewNumber::new(7), false),
+            (ViewNumber::new(12), false)
+        ]);
+        metadata
+    },
+);
+
+// Test where node 2, the leader of views 2, 7 and 12, holds back its second and third proposals
+// until views 7 and 12 have timed out, and the late proposals must not revive them
+cross_tests!(
+    TestName: late_leader,
+    Impls: [MemoryImpl],
+    Types: [TestTypes],
+    Versions: [TestVersions],
+    Ignore: false,
+    Metadata: {
+        let behaviour = Rc::new(|node_id| {
+                let withholding_leader = WithholdingLeader::new(
+                    Withholding::DelayPastTimeout,
+                    HashSet::from([2, 3]),
+                );
+                match node_id {
+                    2 => Behaviour::Byzantine(Box::new(withholding_leader)),
+                    _ => Behaviour::Standard,
+                }
+            });
+
+        let mut metadata = TestDescription {
+            // allow more time to pass in CI
+            completion_task_description: CompletionTaskDescription::TimeBasedCompletionTaskBuilder(
+                                             TimeBasedCompletionTaskDescription {
+                                                 duration: Duration::from_secs(60),
+                                             },
+                                         ),
+            behaviour,
+            ..TestDescription::default()
+        };
+
+        metadata.overall_safety_properties.num_failed_views = 2;
+        metadata.num_nodes_with_stake = 5;
+        metadata.overall_safety_properties.expected_views_to_fail = HashMap::from([
+            (ViewNumber::new(7), false),
+            (ViewNumber::new(12), false)
+        ]);
+        metadata
+    },
+);
+
 cross_tests!(
     TestName: dishonest_da,
     Impls: [MemoryImpl, Libp2pImpl, PushCdnImpl],
@@ -7,10 +7,11 @@
 #![allow(clippy::panic)]
 use std::{sync::Arc, time::Duration};
 
+use bytes::Bytes;
 use hotshot::{
     traits::{
         election::static_committee::StaticCommittee,
-        implementations::{MasterMap, MemoryNetwork},
+        implementations::{unbatch, BatchingNetwork, MasterMap, MemoryNetwork, NoiseNetwork},
         NodeImplementation,
     },
     types::SignatureKey,
@@ -27,13 +28,14 @@ use hotshot_types::{
     message::{DataMessage, Message, MessageKind, UpgradeLock},
     signature_key::{BLSPubKey, BuilderKey},
     traits::{
+        commitment::KeccakCommitments,
         network::{BroadcastDelay, ConnectedNetwork, TestableNetworkingImplementation, Topic},
         node_implementation::{ConsensusTime, NodeType},
     },
 };
 use rand::{rngs::StdRng, RngCore, SeedableRng};
 use serde::{Deserialize, Serialize};
-use tokio::time::timeout;
+use tokio::{spawn, time::timeout};
 use tracing::{instrument, trace};
 
 #[derive(
@@ -65,6 +67,7 @@ impl NodeType for Test {
     type InstanceState = TestInstanceState;
     type Membership = StaticCommittee<Test>;
     type BuilderSignatureKey = BuilderKey;
+    type CommitmentScheme = KeccakCommitments;
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize, Hash, PartialEq, Eq)]
@@ -169,7 +172,7 @@ async fn memory_network_direct_queue() {
     // Test 1 -> 2
     // Send messages
     for sent_message in first_messages {
-        let serialized_message = upgrade_lock.serialize(&sent_message).await.unwrap();
+        let serialized_message = Bytes::from(upgrade_lock.serialize(&sent_message).await.unwrap());
         network1
             .direct_message(serialized_message.clone(), pub_key_2)
             .await
@@ -190,7 +193,7 @@ async fn memory_network_direct_queue() {
     // Test 2 -> 1
     // Send messages
     for sent_message in second_messages {
-        let serialized_message = upgrade_lock.serialize(&sent_message).await.unwrap();
+        let serialized_message = Bytes::from(upgrade_lock.serialize(&sent_message).await.unwrap());
         network2
             .direct_message(serialized_message.clone(), pub_key_1)
             .await
@@ -226,7 +229,7 @@ async fn memory_network_broadcast_queue() {
     // Test 1 -> 2
     // Send messages
     for sent_message in first_messages {
-        let serialized_message = upgrade_lock.serialize(&sent_message).await.unwrap();
+        let serialized_message = Bytes::from(upgrade_lock.serialize(&sent_message).await.unwrap());
         network1
             .broadcast_message(serialized_message.clone(), Topic::Da, BroadcastDelay::None)
             .await
@@ -247,7 +250,7 @@ async fn memory_network_broadcast_queue() {
     // Test 2 -> 1
     // Send messages
     for sent_message in second_messages {
-        let serialized_message = upgrade_lock.serialize(&sent_message).await.unwrap();
+        let serialized_message = Bytes::from(upgrade_lock.serialize(&sent_message).await.unwrap());
         network2
             .broadcast_message(
                 serialized_message.clone(),
@@ -268,6 +271,133 @@ async fn memory_network_broadcast_queue() {
     }
 }
 
+// Check that direct messages sent within the window travel as one frame, and come out of it in
+// order alongside unbatched broadcasts
+
+#[tokio::test(flavor = "multi_thread")]
+#[instrument]
+async fn memory_network_batching() {
+    hotshot::helpers::initialize_logging();
+
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let pub_key_1 = pubkey();
+    let network1 = BatchingNetwork::new(
+        MemoryNetwork::new(&pub_key_1, &group.clone(), &[Topic::Global], Option::None),
+        Some(Duration::from_millis(200)),
+        None,
+    );
+    let pub_key_2 = pubkey();
+    let network2 = MemoryNetwork::new(&pub_key_2, &group, &[Topic::Global], Option::None);
+    let batching_network2 = BatchingNetwork::new(network2.clone(), None, None);
+
+    let upgrade_lock = UpgradeLock::<Test, TestVersions>::new();
+    let mut serialized_messages = vec![];
+    for message in gen_messages(5, 100, pub_key_1) {
+        serialized_messages.push(Bytes::from(upgrade_lock.serialize(&message).await.unwrap()));
+    }
+
+    for message in &serialized_messages {
+        network1
+            .direct_message(message.clone(), pub_key_2)
+            .await
+            .expect("Failed to message node");
+    }
+    let frame = network2
+        .recv_message()
+        .await
+        .expect("Failed to receive message");
+    assert_eq!(unbatch(frame).unwrap(), serialized_messages);
+    assert!(timeout(Duration::from_secs(1), network2.recv_message())
+        .await
+        .is_err());
+
+    for message in &serialized_messages {
+        network1
+            .direct_message(message.clone(), pub_key_2)
+            .await
+            .expect("Failed to message node");
+    }
+    network1
+        .broadcast_message(
+            serialized_messages[0].clone(),
+            Topic::Global,
+            BroadcastDelay::None,
+        )
+        .await
+        .expect("Failed to broadcast message");
+    let mut received = vec![];
+    for _ in 0..=serialized_messages.len() {
+        let message = batching_network2
+            .recv_message()
+            .await
+            .expect("Failed to receive message");
+        upgrade_lock
+            .deserialize::<Message<Test>>(&message)
+            .await
+            .unwrap();
+        received.push(message);
+    }
+    // The broadcast is sent right away, ahead of the batch
+    assert_eq!(received[0], serialized_messages[0]);
+    assert_eq!(received[1..], serialized_messages);
+}
+
+// Check that direct messages wait for the handshake, come out of it in order, and then cross the
+// network encrypted
+
+#[tokio::test(flavor = "multi_thread")]
+#[instrument]
+async fn memory_network_noise() {
+    hotshot::helpers::initialize_logging();
+
+    let group: Arc<MasterMap<<Test as NodeType>::SignatureKey>> = MasterMap::new();
+    let (pub_key_1, priv_key_1) = BLSPubKey::generated_from_seed_indexed([1; 32], 0);
+    let network1 = NoiseNetwork::new(
+        MemoryNetwork::new(&pub_key_1, &group.clone(), &[Topic::Global], Option::None),
+        pub_key_1,
+        priv_key_1,
+    );
+    let (pub_key_2, priv_key_2) = BLSPubKey::generated_from_seed_indexed([2; 32], 0);
+    let network2 = MemoryNetwork::new(&pub_key_2, &group, &[Topic::Global], Option::None);
+    let noise_network2 = NoiseNetwork::new(network2.clone(), pub_key_2, priv_key_2);
+
+    // The sender finishes handshakes as it receives the replies
+    let receiver = network1.clone();
+    spawn(async move { while receiver.recv_message().await.is_ok() {} });
+
+    let upgrade_lock = UpgradeLock::<Test, TestVersions>::new();
+    let mut serialized_messages = vec![];
+    for message in gen_messages(5, 100, pub_key_1) {
+        serialized_messages.push(Bytes::from(upgrade_lock.serialize(&message).await.unwrap()));
+    }
+
+    for message in &serialized_messages {
+        network1
+            .direct_message(message.clone(), pub_key_2)
+            .await
+            .expect("Failed to message node");
+    }
+    for message in &serialized_messages {
+        let received = timeout(Duration::from_secs(5), noise_network2.recv_message())
+            .await
+            .expect("Timed out waiting for message")
+            .expect("Failed to receive message");
+        assert_eq!(&received, message);
+    }
+
+    network1
+        .direct_message(serialized_messages[0].clone(), pub_key_2)
+        .await
+        .expect("Failed to message node");
+    let frame = network2
+        .recv_message()
+        .await
+        .expect("Failed to receive message");
+    assert!(!frame
+        .windows(serialized_messages[0].len())
+        .any(|window| window == serialized_messages[0]));
+}
+
 #[tokio::test(flavor = "multi_thread")]
 #[instrument]
 #[allow(deprecated)]
@@ -296,7 +426,7 @@ async fn memory_network_test_in_flight_message_count() {
     let upgrade_lock = UpgradeLock::<Test, TestVersions>::new();
 
     for (count, message) in messages.iter().enumerate() {
-        let serialized_message = upgrade_lock.serialize(message).await.unwrap();
+        let serialized_message = Bytes::from(upgrade_lock.serialize(message).await.unwrap());
 
         network1
             .direct_message(serialized_message.clone(), pub_key_2)
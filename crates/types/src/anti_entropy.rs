@@ -0,0 +1,213 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Anti-entropy reconciliation of recent consensus messages.
+//!
+//! Gossip loses a message now and then, and a node which misses a proposal or a vote otherwise
+//! only recovers by timing out of the view. With anti-entropy, every node keeps the proposals and
+//! votes of the last few views in an [`AntiEntropyStore`], and periodically sends a few peers a
+//! digest of them: one compact [`DigestEntry`] per message. A peer which finds entries it misses
+//! in a digest asks for them, and handles the messages it gets back as if gossip had delivered
+//! them.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::QuorumProposal2,
+    message::Proposal,
+    simple_vote::{QuorumVote2, TimeoutVote2},
+    traits::node_implementation::NodeType,
+    vote::HasViewNumber,
+};
+
+/// Default time between two digest exchanges, in milliseconds.
+const DEFAULT_INTERVAL_MS: u64 = 500;
+
+/// Default number of views before the current one whose messages are reconciled.
+const DEFAULT_WINDOW: u64 = 2;
+
+/// Default number of peers each digest is sent to.
+const DEFAULT_FANOUT: usize = 2;
+
+/// Settings of the anti-entropy exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AntiEntropyConfig {
+    /// Whether nodes exchange digests of their recent messages
+    #[serde(default)]
+    pub enabled: bool,
+    /// Time between two digest exchanges, in milliseconds; it should be well below the view
+    /// timeout for the exchange to recover a view before it times out
+    #[serde(default = "default_interval_ms")]
+    pub interval_ms: u64,
+    /// Number of views before the current one whose messages are reconciled
+    #[serde(default = "default_window")]
+    pub window: u64,
+    /// Number of random peers each digest is sent to
+    #[serde(default = "default_fanout")]
+    pub fanout: usize,
+}
+
+/// Default value of [`AntiEntropyConfig::interval_ms`], for serde.
+fn default_interval_ms() -> u64 {
+    DEFAULT_INTERVAL_MS
+}
+
+/// Default value of [`AntiEntropyConfig::window`], for serde.
+fn default_window() -> u64 {
+    DEFAULT_WINDOW
+}
+
+/// Default value of [`AntiEntropyConfig::fanout`], for serde.
+fn default_fanout() -> usize {
+    DEFAULT_FANOUT
+}
+
+impl Default for AntiEntropyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_ms: DEFAULT_INTERVAL_MS,
+            window: DEFAULT_WINDOW,
+            fanout: DEFAULT_FANOUT,
+        }
+    }
+}
+
+/// Kinds of messages which are reconciled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// Quorum proposals
+    Proposal,
+    /// Quorum votes
+    QuorumVote,
+    /// Timeout votes
+    TimeoutVote,
+}
+
+/// Compact identifier of a message held for reconciliation.
+///
+/// Entries order by view first, so that the entries of old views are pruned together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DigestEntry {
+    /// View of the message
+    pub view: u64,
+    /// Kind of the message
+    pub kind: ItemKind,
+    /// First bytes of the hash of the serialized message
+    pub hash: u64,
+}
+
+/// A message held for reconciliation.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub enum AntiEntropyItem<TYPES: NodeType> {
+    /// A quorum proposal
+    Proposal(Proposal<TYPES, QuorumProposal2<TYPES>>),
+    /// A quorum vote
+    QuorumVote(QuorumVote2<TYPES>),
+    /// A timeout vote
+    TimeoutVote(TimeoutVote2<TYPES>),
+}
+
+impl<TYPES: NodeType> AntiEntropyItem<TYPES> {
+    /// Kind of the message.
+    #[must_use]
+    pub fn kind(&self) -> ItemKind {
+        match self {
+            Self::Proposal(_) => ItemKind::Proposal,
+            Self::QuorumVote(_) => ItemKind::QuorumVote,
+            Self::TimeoutVote(_) => ItemKind::TimeoutVote,
+        }
+    }
+
+    /// View of the message.
+    #[must_use]
+    pub fn view(&self) -> TYPES::View {
+        match self {
+            Self::Proposal(proposal) => proposal.data.view_number(),
+            Self::QuorumVote(vote) => vote.view_number(),
+            Self::TimeoutVote(vote) => vote.view_number(),
+        }
+    }
+
+    /// The entry identifying the message in digests.
+    ///
+    /// # Panics
+    /// If the message fails to serialize, which it can't.
+    #[must_use]
+    pub fn entry(&self) -> DigestEntry {
+        let bytes = bincode::serialize(self).expect("Consensus messages serialize");
+        let hash = blake3::hash(&bytes);
+        let mut prefix = [0; 8];
+        prefix.copy_from_slice(&hash.as_bytes()[..8]);
+        DigestEntry {
+            view: *self.view(),
+            kind: self.kind(),
+            hash: u64::from_le_bytes(prefix),
+        }
+    }
+}
+
+/// The messages of recent views a node holds for reconciliation.
+#[derive(Debug, Clone)]
+pub struct AntiEntropyStore<TYPES: NodeType> {
+    /// The messages, by their entry
+    items: BTreeMap<DigestEntry, AntiEntropyItem<TYPES>>,
+}
+
+impl<TYPES: NodeType> Default for AntiEntropyStore<TYPES> {
+    fn default() -> Self {
+        Self {
+            items: BTreeMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> AntiEntropyStore<TYPES> {
+    /// Hold `item`, returning whether we did not already.
+    pub fn insert(&mut self, item: AntiEntropyItem<TYPES>) -> bool {
+        self.items.insert(item.entry(), item).is_none()
+    }
+
+    /// Whether we hold the message `entry` identifies.
+    #[must_use]
+    pub fn contains(&self, entry: &DigestEntry) -> bool {
+        self.items.contains_key(entry)
+    }
+
+    /// Whether we hold no message.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// The entries of all messages we hold.
+    #[must_use]
+    pub fn digest(&self) -> Vec<DigestEntry> {
+        self.items.keys().copied().collect()
+    }
+
+    /// The messages among `entries` which we hold.
+    #[must_use]
+    pub fn get(&self, entries: &[DigestEntry]) -> Vec<AntiEntropyItem<TYPES>> {
+        let entries: BTreeSet<_> = entries.iter().collect();
+        entries
+            .into_iter()
+            .filter_map(|entry| self.items.get(entry).cloned())
+            .collect()
+    }
+
+    /// Forget the messages of the views before `view`.
+    pub fn prune(&mut self, view: u64) {
+        self.items = self.items.split_off(&DigestEntry {
+            view,
+            kind: ItemKind::Proposal,
+            hash: 0,
+        });
+    }
+}
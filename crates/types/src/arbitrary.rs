@@ -0,0 +1,355 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! [`proptest`] strategies for the core consensus types.
+//!
+//! With these, property tests can draw leaves, proposals, votes, certificates and stake tables
+//! with `any::<T>()` rather than building them by hand. The types generic over a [`NodeType`]
+//! get an [`Arbitrary`] implementation whenever the node type's block header does, as the
+//! ordering types of [`crate::ordering`] do.
+//!
+//! The values are well-formed but not necessarily valid: keys, signatures and aggregated
+//! signatures are real, but sign the plain commitment of the data rather than its versioned
+//! commitment, so votes and certificates do not verify against a membership. Leaves are those
+//! of nodes outside the DA committee, without their payload.
+
+use std::fmt::Debug;
+
+use bitvec::vec::BitVec;
+use committable::{Commitment, Committable};
+use primitive_types::U256;
+use proptest::{
+    collection::{vec, SizeRange},
+    option,
+    prelude::*,
+};
+
+use crate::{
+    data::{EpochNumber, Leaf, Leaf2, QuorumProposal2, ViewNumber},
+    drb::{DrbResult, DrbSeedInput},
+    hlc::HybridTimestamp,
+    ordering::{OpaqueTransaction, OrderedMetadata, OrderedPayload, OrderingHeader},
+    simple_certificate::{QuorumCertificate2, SimpleCertificate, Threshold},
+    simple_vote::{QuorumData, QuorumData2, SimpleVote, Voteable},
+    traits::{
+        block_contents::vid_commitment,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+        EncodeBytes,
+    },
+    utils::BuilderCommitment,
+    PeerConfig, ValidatorConfig,
+};
+
+/// Largest view or epoch drawn. Small enough that tests can step past it without overflowing.
+const MAX_TIME: u64 = 1 << 32;
+
+/// Largest number of validators in a drawn committee.
+const MAX_COMMITTEE: usize = 7;
+
+/// Largest stake of a drawn validator.
+const MAX_STAKE: u64 = 1_000;
+
+/// Number of storage nodes the VID commitments of drawn headers are computed for.
+const VID_STORAGE_NODES: usize = 4;
+
+/// Views of the node type.
+pub fn view<TYPES: NodeType>() -> impl Strategy<Value = TYPES::View> {
+    (0..MAX_TIME).prop_map(TYPES::View::new)
+}
+
+/// Epochs of the node type.
+pub fn epoch<TYPES: NodeType>() -> impl Strategy<Value = TYPES::Epoch> {
+    (0..MAX_TIME).prop_map(TYPES::Epoch::new)
+}
+
+/// Commitments to random bytes, which commit to no actual value.
+pub fn commitment<T: Committable + 'static>() -> impl Strategy<Value = Commitment<T>> {
+    any::<[u8; 32]>().prop_map(Commitment::from_raw)
+}
+
+/// Stake tables of `size` validators, with distinct keys and stakes of at least 1.
+pub fn stake_table<KEY: SignatureKey + 'static>(
+    size: impl Into<SizeRange>,
+) -> impl Strategy<Value = Vec<PeerConfig<KEY>>> {
+    (any::<[u8; 32]>(), vec(1..=MAX_STAKE, size)).prop_map(|(seed, stakes)| {
+        committee::<KEY>(seed, &stakes)
+            .iter()
+            .map(ValidatorConfig::public_config)
+            .collect()
+    })
+}
+
+/// The validators drawn from `seed`, with the given stakes.
+fn committee<KEY: SignatureKey>(seed: [u8; 32], stakes: &[u64]) -> Vec<ValidatorConfig<KEY>> {
+    (0..)
+        .zip(stakes)
+        .map(|(index, stake)| {
+            ValidatorConfig::generated_from_seed_indexed(seed, index, *stake, false)
+        })
+        .collect()
+}
+
+/// Sign `data` with `key`.
+fn sign<KEY: SignatureKey>(
+    key: &KEY::PrivateKey,
+    data: &impl Committable,
+) -> KEY::PureAssembledSignatureType {
+    KEY::sign(key, data.commit().as_ref()).expect("Generated keys can sign")
+}
+
+/// Signatures over `data` by the validators drawn from `seed` with the given stakes, aggregated
+/// over those selected by `signers`. At least one validator signs.
+fn aggregate_signature<KEY: SignatureKey>(
+    data: &impl Committable,
+    seed: [u8; 32],
+    stakes: &[u64],
+    mut signers: BitVec,
+) -> KEY::QcType {
+    if signers.not_any() {
+        signers.set(0, true);
+    }
+    let validators = committee::<KEY>(seed, stakes);
+    let signed_stake = validators
+        .iter()
+        .zip(signers.iter())
+        .filter(|(_, signed)| **signed)
+        .map(|(validator, _)| U256::from(validator.stake_value))
+        .fold(U256::zero(), |total, stake| total + stake);
+    let signatures: Vec<_> = validators
+        .iter()
+        .zip(signers.iter())
+        .filter(|(_, signed)| **signed)
+        .map(|(validator, _)| sign::<KEY>(&validator.private_key, data))
+        .collect();
+    let params = KEY::public_parameter(
+        validators
+            .iter()
+            .map(|validator| {
+                validator
+                    .public_key
+                    .stake_table_entry(validator.stake_value)
+            })
+            .collect(),
+        signed_stake,
+    );
+    KEY::assemble(&params, &signers, &signatures)
+}
+
+/// A committee drawn as a seed, stakes, and which of its members sign.
+fn signing_committee() -> impl Strategy<Value = ([u8; 32], Vec<u64>, BitVec)> {
+    (any::<[u8; 32]>(), vec(1..=MAX_STAKE, 1..=MAX_COMMITTEE)).prop_flat_map(|(seed, stakes)| {
+        let size = stakes.len();
+        (
+            Just(seed),
+            Just(stakes),
+            vec(any::<bool>(), size).prop_map(BitVec::from_iter),
+        )
+    })
+}
+
+impl Arbitrary for ViewNumber {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (0..MAX_TIME).prop_map(Self::new).boxed()
+    }
+}
+
+impl Arbitrary for EpochNumber {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (0..MAX_TIME).prop_map(Self::new).boxed()
+    }
+}
+
+impl<KEY: SignatureKey + 'static> Arbitrary for PeerConfig<KEY> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (any::<[u8; 32]>(), 1..=MAX_STAKE)
+            .prop_map(|(seed, stake)| {
+                ValidatorConfig::<KEY>::generated_from_seed_indexed(seed, 0, stake, false)
+                    .public_config()
+            })
+            .boxed()
+    }
+}
+
+impl<TYPES: NodeType> Arbitrary for QuorumData<TYPES> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        commitment::<Leaf<TYPES>>()
+            .prop_map(|leaf_commit| Self { leaf_commit })
+            .boxed()
+    }
+}
+
+impl<TYPES: NodeType> Arbitrary for QuorumData2<TYPES> {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (commitment::<Leaf2<TYPES>>(), epoch::<TYPES>())
+            .prop_map(|(leaf_commit, epoch)| Self { leaf_commit, epoch })
+            .boxed()
+    }
+}
+
+impl<TYPES, DATA> Arbitrary for SimpleVote<TYPES, DATA>
+where
+    TYPES: NodeType,
+    DATA: Voteable<TYPES> + Arbitrary + 'static,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (
+            any::<DATA>(),
+            view::<TYPES>(),
+            any::<[u8; 32]>(),
+            any::<u64>(),
+        )
+            .prop_map(|(data, view_number, seed, index)| {
+                let (public_key, private_key) =
+                    TYPES::SignatureKey::generated_from_seed_indexed(seed, index);
+                Self {
+                    signature: (public_key, sign::<TYPES::SignatureKey>(&private_key, &data)),
+                    data,
+                    view_number,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl<TYPES, VOTEABLE, THRESHOLD> Arbitrary for SimpleCertificate<TYPES, VOTEABLE, THRESHOLD>
+where
+    TYPES: NodeType,
+    VOTEABLE: Voteable<TYPES> + Arbitrary + 'static,
+    THRESHOLD: Threshold<TYPES> + Debug + 'static,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (
+            any::<VOTEABLE>(),
+            view::<TYPES>(),
+            option::of(signing_committee()),
+        )
+            .prop_map(|(data, view_number, committee)| {
+                let signatures = committee.map(|(seed, stakes, signers)| {
+                    aggregate_signature::<TYPES::SignatureKey>(&data, seed, &stakes, signers)
+                });
+                let vote_commitment = data.commit();
+                Self::new(data, vote_commitment, view_number, signatures)
+            })
+            .boxed()
+    }
+}
+
+impl<TYPES: NodeType> Arbitrary for QuorumProposal2<TYPES>
+where
+    TYPES::BlockHeader: Arbitrary,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (
+            any::<TYPES::BlockHeader>(),
+            view::<TYPES>(),
+            any::<QuorumCertificate2<TYPES>>(),
+            any::<DrbSeedInput>(),
+            any::<DrbResult>(),
+        )
+            .prop_map(
+                |(block_header, view_number, justify_qc, drb_seed, drb_result)| Self {
+                    block_header,
+                    view_number,
+                    justify_qc,
+                    upgrade_certificate: None,
+                    view_change_evidence: None,
+                    drb_seed,
+                    drb_result,
+                    high_qc_proof: None,
+                    epoch_checkpoint: None,
+                },
+            )
+            .boxed()
+    }
+}
+
+impl<TYPES: NodeType> Arbitrary for Leaf2<TYPES>
+where
+    TYPES::BlockHeader: Arbitrary,
+{
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        any::<QuorumProposal2<TYPES>>()
+            .prop_map(|proposal| Self::from_quorum_proposal(&proposal))
+            .boxed()
+    }
+}
+
+impl Arbitrary for OpaqueTransaction {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        vec(any::<u8>(), 0..64).prop_map(Self).boxed()
+    }
+}
+
+impl Arbitrary for OrderedPayload {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        vec(any::<OpaqueTransaction>(), 0..8)
+            .prop_map(|transactions| Self { transactions })
+            .boxed()
+    }
+}
+
+/// Headers commit to a drawn payload, so that they are consistent with some payload even though
+/// the leaves holding them are not filled.
+impl Arbitrary for OrderingHeader {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with((): ()) -> Self::Strategy {
+        (
+            any::<OrderedPayload>(),
+            0..MAX_TIME,
+            any::<u64>(),
+            any::<u32>(),
+        )
+            .prop_map(|(payload, block_number, physical, logical)| {
+                let encoded = payload.encode();
+                Self {
+                    block_number,
+                    payload_commitment: vid_commitment(&encoded, VID_STORAGE_NODES),
+                    builder_commitment: BuilderCommitment::from_bytes(&encoded),
+                    metadata: OrderedMetadata {
+                        num_transactions: payload.transactions.len() as u64,
+                    },
+                    timestamp: HybridTimestamp::new(physical, logical),
+                }
+            })
+            .boxed()
+    }
+}
@@ -0,0 +1,112 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A randomness beacon derived from quorum certificates.
+//!
+//! Every QC carries a signature aggregated from a quorum of the stake table. Hashing it gives a
+//! value for the QC's view which no minority of the committee can predict before the QC forms.
+//! The leader which assembles the QC chooses which votes go into it, so it can pick between a few
+//! outputs, but it cannot set one.
+
+use std::collections::BTreeMap;
+
+use bincode::Options;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    simple_certificate::QuorumCertificate2,
+    traits::node_implementation::{ConsensusTime, NodeType},
+    utils::bincode_opts,
+};
+
+/// Domain separator, so beacon outputs never collide with other hashes of QC signatures.
+const BEACON_DOMAIN: &[u8] = b"HotShot randomness beacon";
+
+/// The random value for a view.
+pub type BeaconOutput = [u8; 32];
+
+/// Hash a view's aggregated QC signature, serialized as `signature`, into its beacon output.
+fn hash_signature(view: u64, signature: &[u8]) -> BeaconOutput {
+    let mut hasher = Sha256::new();
+    hasher.update(BEACON_DOMAIN);
+    hasher.update(view.to_le_bytes());
+    hasher.update(signature);
+    hasher.finalize().into()
+}
+
+/// The beacon output for the view of `qc`, or `None` if `qc` carries no signature, as the
+/// genesis QC does.
+#[must_use]
+pub fn beacon_output<TYPES: NodeType>(qc: &QuorumCertificate2<TYPES>) -> Option<BeaconOutput> {
+    let signature = bincode_opts().serialize(qc.signatures.as_ref()?).ok()?;
+    Some(hash_signature(qc.view_number.u64(), &signature))
+}
+
+/// Beacon outputs for recent views.
+#[derive(Clone, Debug)]
+pub struct RandomnessBeacon<TYPES: NodeType> {
+    /// Output of each view we have seen a QC for
+    outputs: BTreeMap<TYPES::View, BeaconOutput>,
+}
+
+impl<TYPES: NodeType> Default for RandomnessBeacon<TYPES> {
+    fn default() -> Self {
+        Self {
+            outputs: BTreeMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> RandomnessBeacon<TYPES> {
+    /// Record the output for the view of `qc`. QCs without a signature are ignored.
+    pub fn record(&mut self, qc: &QuorumCertificate2<TYPES>) {
+        if let Some(output) = beacon_output(qc) {
+            self.outputs.insert(qc.view_number, output);
+        }
+    }
+
+    /// The output for `view`, if we have seen its QC.
+    #[must_use]
+    pub fn output(&self, view: TYPES::View) -> Option<BeaconOutput> {
+        self.outputs.get(&view).copied()
+    }
+
+    /// The latest output we have, and its view.
+    #[must_use]
+    pub fn latest(&self) -> Option<(TYPES::View, BeaconOutput)> {
+        self.outputs
+            .last_key_value()
+            .map(|(view, output)| (*view, *output))
+    }
+
+    /// The latest output from a view before `view`, suitable as a seed for `view`: it was fixed
+    /// before `view` started, and nobody knew it before its own QC formed.
+    #[must_use]
+    pub fn seed_for(&self, view: TYPES::View) -> Option<BeaconOutput> {
+        self.outputs
+            .range(..view)
+            .next_back()
+            .map(|(_, output)| *output)
+    }
+
+    /// Forget the outputs for views before `view`.
+    pub fn prune_before(&mut self, view: TYPES::View) {
+        self.outputs = self.outputs.split_off(&view);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn outputs_depend_on_view_and_signature() {
+        let output = hash_signature(1, b"signature");
+        assert_eq!(output, hash_signature(1, b"signature"));
+        assert_ne!(output, hash_signature(2, b"signature"));
+        assert_ne!(output, hash_signature(1, b"other signature"));
+    }
+}
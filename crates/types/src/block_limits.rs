@@ -0,0 +1,173 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Per-block resource limits.
+//!
+//! The same [`BlockLimits`] are applied by the leader when choosing a block from the builders and
+//! by DA members when validating a DA proposal, so an oversized block is never certified.
+
+use thiserror::Error;
+
+use crate::traits::{
+    block_contents::{BlockPayload, EncodeBytes, Transaction},
+    node_implementation::NodeType,
+};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+/// Holds the resource limits for a single block. `None` means the resource is unbounded.
+pub struct BlockLimits {
+    /// Maximum size of the encoded block payload, in bytes
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+    /// Maximum number of transactions in a block
+    #[serde(default)]
+    pub max_transactions: Option<u64>,
+    /// Maximum total gas of a block, as reported by [`Transaction::gas`]
+    #[serde(default)]
+    pub max_gas: Option<u64>,
+}
+
+/// Reasons a block can violate its [`BlockLimits`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum BlockLimitsError {
+    /// The encoded payload is larger than allowed
+    #[error("Block is {size} bytes, which exceeds the limit of {max} bytes")]
+    TooManyBytes {
+        /// Size of the encoded payload
+        size: u64,
+        /// Configured limit
+        max: u64,
+    },
+    /// The block holds more transactions than allowed
+    #[error("Block has {count} transactions, which exceeds the limit of {max}")]
+    TooManyTransactions {
+        /// Number of transactions in the block
+        count: u64,
+        /// Configured limit
+        max: u64,
+    },
+    /// The block uses more gas than allowed
+    #[error("Block uses {gas} gas, which exceeds the limit of {max}")]
+    TooMuchGas {
+        /// Total gas used by the block
+        gas: u64,
+        /// Configured limit
+        max: u64,
+    },
+}
+
+impl BlockLimits {
+    /// Limits that accept every block.
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            max_bytes: None,
+            max_transactions: None,
+            max_gas: None,
+        }
+    }
+
+    /// Check an encoded payload size against `max_bytes`.
+    ///
+    /// This is cheap enough to run on the size advertised by a builder, before claiming the block.
+    ///
+    /// # Errors
+    /// If `size` exceeds `max_bytes`.
+    pub fn check_size(&self, size: u64) -> Result<(), BlockLimitsError> {
+        match self.max_bytes {
+            Some(max) if size > max => Err(BlockLimitsError::TooManyBytes { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check already computed block resource usage against all limits.
+    ///
+    /// # Errors
+    /// On the first limit that is exceeded.
+    pub fn check_usage(&self, size: u64, count: u64, gas: u64) -> Result<(), BlockLimitsError> {
+        self.check_size(size)?;
+
+        if let Some(max) = self.max_transactions {
+            if count > max {
+                return Err(BlockLimitsError::TooManyTransactions { count, max });
+            }
+        }
+
+        if let Some(max) = self.max_gas {
+            if gas > max {
+                return Err(BlockLimitsError::TooMuchGas { gas, max });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check a block payload against all limits.
+    ///
+    /// # Errors
+    /// On the first limit that is exceeded.
+    pub fn check<TYPES: NodeType>(
+        &self,
+        payload: &TYPES::BlockPayload,
+        metadata: &<TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    ) -> Result<(), BlockLimitsError> {
+        let size = payload.encode().len() as u64;
+
+        // Skip decoding the transactions entirely if nothing but the size is bounded.
+        if self.max_transactions.is_none() && self.max_gas.is_none() {
+            return self.check_size(size);
+        }
+
+        let (count, gas) = payload
+            .transactions(metadata)
+            .fold((0u64, 0u64), |(count, gas), txn| {
+                (count + 1, gas.saturating_add(txn.gas()))
+            });
+
+        self.check_usage(size, count, gas)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn unlimited_accepts_everything() {
+        let limits = BlockLimits::unlimited();
+        assert_eq!(limits, BlockLimits::default());
+        assert!(limits.check_usage(u64::MAX, u64::MAX, u64::MAX).is_ok());
+    }
+
+    #[test]
+    fn limits_are_inclusive() {
+        let limits = BlockLimits {
+            max_bytes: Some(100),
+            max_transactions: Some(10),
+            max_gas: Some(1000),
+        };
+
+        assert!(limits.check_usage(100, 10, 1000).is_ok());
+        assert_eq!(
+            limits.check_usage(101, 10, 1000),
+            Err(BlockLimitsError::TooManyBytes {
+                size: 101,
+                max: 100
+            })
+        );
+        assert_eq!(
+            limits.check_usage(100, 11, 1000),
+            Err(BlockLimitsError::TooManyTransactions { count: 11, max: 10 })
+        );
+        assert_eq!(
+            limits.check_usage(100, 10, 1001),
+            Err(BlockLimitsError::TooMuchGas {
+                gas: 1001,
+                max: 1000
+            })
+        );
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Configuration of the capacity and overflow policy of internal channels.
+//!
+//! The internal event channel drops its oldest events when it is full, so that a slow task cannot
+//! stall consensus. A flood of network messages could then evict events consensus still needs. To
+//! prevent this, once the channel fills past [`ChannelConfig::stale_drop_depth`], consensus
+//! messages for views before the current one are dropped on arrival, and messages for the current
+//! or later views wait for room, up to [`ChannelConfig::backpressure_timeout`].
+//!
+//! Messages wait for room in [`ViewChannels`], one logical channel per view, rather than in the
+//! network. When the view advances, the channels of the views consensus left are dropped whole, so
+//! that messages for the current view never wait behind a backlog of stale ones.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{EVENT_CHANNEL_SIZE, EXTERNAL_EVENT_CHANNEL_SIZE};
+
+/// Default time a current-view message waits for room in the internal channel.
+const DEFAULT_BACKPRESSURE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Default number of messages each view channel holds.
+const DEFAULT_VIEW_CHANNEL_CAPACITY: usize = 1024;
+
+/// Largest number of views with a channel at once. Messages for views beyond are dropped.
+const MAX_VIEW_CHANNELS: usize = 64;
+
+/// Capacities and overflow policy of the channels of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelConfig {
+    /// Capacity of the channel between consensus tasks
+    #[serde(default = "default_internal_capacity")]
+    pub internal_capacity: usize,
+    /// Capacity of the channel of events for the application
+    #[serde(default = "default_external_capacity")]
+    pub external_capacity: usize,
+    /// Number of queued internal events from which messages for past views are dropped
+    #[serde(default = "default_stale_drop_depth")]
+    pub stale_drop_depth: usize,
+    /// How long a message for the current view waits for room in a full internal channel
+    #[serde(default = "default_backpressure_timeout")]
+    pub backpressure_timeout: Duration,
+    /// Number of received messages each view channel holds before dropping its oldest ones
+    #[serde(default = "default_view_channel_capacity")]
+    pub view_channel_capacity: usize,
+}
+
+/// Default value of [`ChannelConfig::internal_capacity`], for serde.
+fn default_internal_capacity() -> usize {
+    EVENT_CHANNEL_SIZE
+}
+
+/// Default value of [`ChannelConfig::external_capacity`], for serde.
+fn default_external_capacity() -> usize {
+    EXTERNAL_EVENT_CHANNEL_SIZE
+}
+
+/// Default value of [`ChannelConfig::stale_drop_depth`], for serde.
+fn default_stale_drop_depth() -> usize {
+    EVENT_CHANNEL_SIZE / 2
+}
+
+/// Default value of [`ChannelConfig::backpressure_timeout`], for serde.
+fn default_backpressure_timeout() -> Duration {
+    DEFAULT_BACKPRESSURE_TIMEOUT
+}
+
+/// Default value of [`ChannelConfig::view_channel_capacity`], for serde.
+fn default_view_channel_capacity() -> usize {
+    DEFAULT_VIEW_CHANNEL_CAPACITY
+}
+
+impl Default for ChannelConfig {
+    fn default() -> Self {
+        Self {
+            internal_capacity: default_internal_capacity(),
+            external_capacity: default_external_capacity(),
+            stale_drop_depth: default_stale_drop_depth(),
+            backpressure_timeout: default_backpressure_timeout(),
+            view_channel_capacity: default_view_channel_capacity(),
+        }
+    }
+}
+
+/// Received messages waiting to be handled, in one logical channel per view.
+///
+/// Consensus messages are queued by view, and those of the lowest view are handled first, so that
+/// messages for future views wait behind the current ones but never the reverse. Other messages,
+/// such as transactions and data responses, are not tied to a view and take turns with them.
+#[derive(Debug)]
+pub struct ViewChannels<V, T> {
+    /// Consensus messages, by view
+    views: BTreeMap<V, VecDeque<T>>,
+    /// Messages not tied to a view, in the order they arrived
+    other: VecDeque<T>,
+    /// Views before this one were left, and their messages are dropped
+    floor: Option<V>,
+    /// Number of messages each channel holds
+    capacity: usize,
+    /// Whether the next message is taken from `other` first
+    other_next: bool,
+}
+
+impl<V: Ord + Copy, T> ViewChannels<V, T> {
+    /// Channels holding up to `capacity` messages each.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            views: BTreeMap::new(),
+            other: VecDeque::new(),
+            floor: None,
+            capacity: capacity.max(1),
+            other_next: false,
+        }
+    }
+
+    /// Queue a consensus message for `view`, returning whether it was kept: messages of views
+    /// before the floor, or too far beyond the views already queued, are dropped.
+    pub fn push_view(&mut self, view: V, message: T) -> bool {
+        if self.floor.is_some_and(|floor| view < floor) {
+            return false;
+        }
+        if !self.views.contains_key(&view) && self.views.len() >= MAX_VIEW_CHANNELS {
+            // Make room for a lower view by dropping the highest one, which is needed last
+            match self.views.last_key_value() {
+                Some((highest, _)) if view < *highest => {
+                    self.views.pop_last();
+                }
+                _ => return false,
+            }
+        }
+        let channel = self.views.entry(view).or_default();
+        if channel.len() >= self.capacity {
+            channel.pop_front();
+        }
+        channel.push_back(message);
+        true
+    }
+
+    /// Queue a message not tied to a view.
+    pub fn push_other(&mut self, message: T) {
+        if self.other.len() >= self.capacity {
+            self.other.pop_front();
+        }
+        self.other.push_back(message);
+    }
+
+    /// Drop the channels of the views before `floor`, returning how many messages they held.
+    pub fn advance(&mut self, floor: V) -> usize {
+        if self.floor.is_some_and(|current| floor <= current) {
+            return 0;
+        }
+        self.floor = Some(floor);
+        let kept = self.views.split_off(&floor);
+        let dropped = std::mem::replace(&mut self.views, kept);
+        dropped.values().map(VecDeque::len).sum()
+    }
+
+    /// Take the next message to handle.
+    pub fn pop(&mut self) -> Option<T> {
+        self.other_next = !self.other_next;
+        if self.other_next {
+            if let Some(message) = self.other.pop_front() {
+                return Some(message);
+            }
+        }
+        while let Some(mut entry) = self.views.first_entry() {
+            if let Some(message) = entry.get_mut().pop_front() {
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+                return Some(message);
+            }
+            entry.remove();
+        }
+        self.other.pop_front()
+    }
+
+    /// Whether no message is waiting.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.other.is_empty() && self.views.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn current_view_is_not_stuck_behind_stale_views() {
+        let mut channels = ViewChannels::new(16);
+        for view in 1..=3u64 {
+            for i in 0..4 {
+                assert!(channels.push_view(view, (view, i)));
+            }
+        }
+        channels.push_other((0, 0));
+
+        // Views 1 and 2 were left with their messages still queued
+        assert_eq!(channels.advance(3), 8);
+        assert!(!channels.push_view(2, (2, 4)));
+
+        assert_eq!(channels.pop(), Some((0, 0)));
+        for i in 0..4 {
+            assert_eq!(channels.pop(), Some((3, i)));
+        }
+        assert!(channels.is_empty());
+    }
+
+    #[test]
+    fn channels_are_bounded() {
+        let mut channels = ViewChannels::new(2);
+        for i in 0..3u64 {
+            channels.push_view(1u64, i);
+        }
+        assert_eq!(channels.pop(), Some(1));
+        assert_eq!(channels.pop(), Some(2));
+
+        for view in 0..MAX_VIEW_CHANNELS as u64 {
+            assert!(channels.push_view(view + 10, view));
+        }
+        // A view beyond all the others is dropped, but a lower one makes room for itself
+        assert!(!channels.push_view(1000, 0));
+        assert!(channels.push_view(5, 5));
+        assert_eq!(channels.pop(), Some(5));
+    }
+}
@@ -0,0 +1,283 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Compact checkpoints of the decided chain, for anchoring HotShot to an external chain.
+//!
+//! A [`Checkpoint`] commits to a decided leaf, the QC which decided it and the stake table which
+//! signed that QC. It is handed to a [`CheckpointSink`], e.g. a contract submitter, and can later
+//! be used to check that a certified chain of leaves extends the anchored one.
+//!
+//! An [`EpochCheckpoint`] is carried by the last block of every epoch instead. It commits to the
+//! committees of the next epoch and the stake they need to certify, so the outgoing committee
+//! votes for the committees it hands over to, and the incoming committee, like any light client,
+//! can anchor the stake tables it uses on a block the outgoing committee decided.
+
+use std::num::NonZeroU64;
+
+use async_trait::async_trait;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    data::Leaf2,
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType, Versions},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    vote::{Certificate, HasViewNumber},
+};
+
+/// The stake table which signed the QC of a [`Checkpoint`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct StakeTableRoot<TYPES: NodeType>(
+    pub Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+);
+
+impl<TYPES: NodeType> Committable for StakeTableRoot<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        self.0
+            .iter()
+            .fold(
+                RawCommitmentBuilder::new("Stake table root").u64(self.0.len() as u64),
+                |builder, entry| {
+                    let mut stake = [0u8; 32];
+                    entry.stake().to_little_endian(&mut stake);
+                    builder
+                        .var_size_bytes(&entry.public_key().to_bytes())
+                        .fixed_size_bytes(&stake)
+                },
+            )
+            .finalize()
+    }
+}
+
+/// A compact commitment to the decided chain up to some leaf.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct Checkpoint<TYPES: NodeType> {
+    /// Commitment to the anchored leaf
+    pub leaf_commit: Commitment<Leaf2<TYPES>>,
+    /// View of the anchored leaf
+    pub view_number: TYPES::View,
+    /// Block height of the anchored leaf
+    pub block_height: u64,
+    /// The QC signing the anchored leaf
+    pub qc: QuorumCertificate2<TYPES>,
+    /// Commitment to the stake table which signed `qc`
+    pub stake_table_root: Commitment<StakeTableRoot<TYPES>>,
+}
+
+/// Reasons a [`Checkpoint`] can not be built, or a chain does not extend it.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum CheckpointError {
+    /// There is no checkpoint to verify against yet
+    #[error("No checkpoint has been taken yet")]
+    NoCheckpoint,
+    /// The QC does not sign the leaf it is paired with
+    #[error("QC for view {view} does not sign the expected leaf")]
+    QcMismatch {
+        /// View of the QC
+        view: u64,
+    },
+    /// The QC signatures are invalid for the given stake table
+    #[error("QC for view {view} is not valid for the checkpointed stake table")]
+    InvalidQc {
+        /// View of the QC
+        view: u64,
+    },
+    /// The stake table differs from the one which was checkpointed
+    #[error("Stake table does not match the checkpointed stake table root")]
+    StakeTableMismatch,
+    /// A leaf does not extend its predecessor
+    #[error("Leaf at height {height} does not extend the previous leaf")]
+    BrokenChain {
+        /// Height of the offending leaf
+        height: u64,
+    },
+    /// The threshold differs from the one which was checkpointed
+    #[error("Threshold {actual} does not match the checkpointed threshold {expected}")]
+    ThresholdMismatch {
+        /// Checkpointed threshold
+        expected: u64,
+        /// Threshold in use
+        actual: u64,
+    },
+}
+
+impl<TYPES: NodeType> Checkpoint<TYPES> {
+    /// Build a checkpoint anchoring `leaf`.
+    ///
+    /// # Errors
+    /// If `qc` does not sign `leaf`.
+    pub fn new(
+        leaf: &Leaf2<TYPES>,
+        qc: QuorumCertificate2<TYPES>,
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+    ) -> Result<Self, CheckpointError> {
+        let leaf_commit = leaf.commit();
+        if qc.data.leaf_commit != leaf_commit {
+            return Err(CheckpointError::QcMismatch {
+                view: qc.view_number().u64(),
+            });
+        }
+
+        Ok(Self {
+            leaf_commit,
+            view_number: leaf.view_number(),
+            block_height: leaf.height(),
+            qc,
+            stake_table_root: StakeTableRoot(stake_table).commit(),
+        })
+    }
+
+    /// Verify that `leaves` extend this checkpoint and that `qc` certifies the last of them.
+    ///
+    /// `leaves` are ordered from oldest to newest and must start with the child of the anchored
+    /// leaf. `stake_table` must be the checkpointed stake table, which is expected to have signed
+    /// `qc`.
+    ///
+    /// A QC only certifies its leaf, which is not decided until its child, proposed in the next
+    /// view, is certified as well. Callers who need finality should check that the last two of
+    /// `leaves` are of consecutive views and treat the second to last as the decided one.
+    ///
+    /// # Errors
+    /// If the chain is broken, the stake table is not the checkpointed one or `qc` is invalid.
+    pub async fn verify_certified_extension<V: Versions>(
+        &self,
+        leaves: &[Leaf2<TYPES>],
+        qc: &QuorumCertificate2<TYPES>,
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        threshold: NonZeroU64,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<(), CheckpointError> {
+        let stake_table = StakeTableRoot::<TYPES>(stake_table);
+        if stake_table.commit() != self.stake_table_root {
+            return Err(CheckpointError::StakeTableMismatch);
+        }
+
+        let tip = leaves.iter().try_fold(self.leaf_commit, |parent, leaf| {
+            if leaf.parent_commitment() == parent {
+                Ok(leaf.commit())
+            } else {
+                Err(CheckpointError::BrokenChain {
+                    height: leaf.height(),
+                })
+            }
+        })?;
+
+        let view = qc.view_number().u64();
+        if qc.data.leaf_commit != tip {
+            return Err(CheckpointError::QcMismatch { view });
+        }
+        if !qc
+            .is_valid_cert(&stake_table.0, threshold, upgrade_lock)
+            .await
+        {
+            return Err(CheckpointError::InvalidQc { view });
+        }
+
+        Ok(())
+    }
+}
+
+/// The committees of an epoch, as committed to by the last block of the epoch before it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct EpochCheckpoint<TYPES: NodeType> {
+    /// The epoch the committees serve in
+    pub epoch: TYPES::Epoch,
+    /// Commitment to the stake table of the quorum
+    pub stake_table_root: Commitment<StakeTableRoot<TYPES>>,
+    /// Stake needed for a quorum certificate
+    pub success_threshold: NonZeroU64,
+    /// Commitment to the stake table of the DA committee
+    pub da_stake_table_root: Commitment<StakeTableRoot<TYPES>>,
+    /// Stake needed for a DA certificate
+    pub da_success_threshold: NonZeroU64,
+}
+
+impl<TYPES: NodeType> EpochCheckpoint<TYPES> {
+    /// The checkpoint of the committees `membership` holds for `epoch`.
+    #[must_use]
+    pub fn new(membership: &TYPES::Membership, epoch: TYPES::Epoch) -> Self {
+        Self {
+            epoch,
+            stake_table_root: StakeTableRoot(membership.stake_table(epoch).into_owned()).commit(),
+            success_threshold: membership.success_threshold(epoch),
+            da_stake_table_root: StakeTableRoot(membership.da_stake_table(epoch).into_owned())
+                .commit(),
+            da_success_threshold: membership.da_success_threshold(epoch),
+        }
+    }
+
+    /// The checkpoint the block at `block_height` must carry: that of the next epoch if the block
+    /// is the last of its epoch, and none otherwise.
+    #[must_use]
+    pub fn for_block(
+        block_height: u64,
+        epoch_height: u64,
+        membership: &TYPES::Membership,
+    ) -> Option<Self> {
+        if epoch_height == 0 || block_height == 0 || block_height % epoch_height != 0 {
+            return None;
+        }
+
+        Some(Self::new(
+            membership,
+            TYPES::Epoch::new(block_height / epoch_height + 1),
+        ))
+    }
+
+    /// Check that the quorum of the checkpointed epoch has `stake_table` and needs `threshold`
+    /// stake to certify, e.g. before trusting a certificate it signed.
+    ///
+    /// # Errors
+    /// If the stake table or the threshold differ from the checkpointed ones.
+    pub fn verify_quorum(
+        &self,
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        threshold: NonZeroU64,
+    ) -> Result<(), CheckpointError> {
+        if StakeTableRoot::<TYPES>(stake_table).commit() != self.stake_table_root {
+            return Err(CheckpointError::StakeTableMismatch);
+        }
+        if threshold != self.success_threshold {
+            return Err(CheckpointError::ThresholdMismatch {
+                expected: self.success_threshold.get(),
+                actual: threshold.get(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<TYPES: NodeType> Committable for EpochCheckpoint<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Epoch checkpoint")
+            .u64_field("epoch", self.epoch.u64())
+            .field("stake table root", self.stake_table_root)
+            .u64_field("success threshold", self.success_threshold.get())
+            .field("DA stake table root", self.da_stake_table_root)
+            .u64_field("DA success threshold", self.da_success_threshold.get())
+            .finalize()
+    }
+}
+
+/// A destination for checkpoints, e.g. a contract on an external chain.
+#[async_trait]
+pub trait CheckpointSink<TYPES: NodeType>: Send + Sync + 'static {
+    /// Submit a checkpoint.
+    ///
+    /// # Errors
+    /// If the checkpoint could not be submitted. It will be retried on the next decide.
+    async fn submit(&self, checkpoint: &Checkpoint<TYPES>) -> anyhow::Result<()>;
+}
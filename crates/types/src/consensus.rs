@@ -6,52 +6,162 @@
 
 //! Provides the core consensus types
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 use std::{
     collections::{BTreeMap, HashMap},
+    marker::PhantomData,
     mem::ManuallyDrop,
     ops::{Deref, DerefMut},
     sync::Arc,
+    time::Duration,
 };
 
 use async_lock::{RwLock, RwLockReadGuard, RwLockUpgradableReadGuard, RwLockWriteGuard};
 use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
 use tracing::instrument;
 use utils::anytrace::*;
 use vec1::Vec1;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 pub use crate::utils::{View, ViewInner};
 use crate::{
+    beacon::RandomnessBeacon,
+    constants::{BEACON_RETENTION_VIEWS, REJECTED_TRANSACTION_RETENTION_VIEWS},
     data::{Leaf2, QuorumProposal2, VidDisperse, VidDisperseShare2},
-    error::HotShotError,
-    event::{HotShotAction, LeafInfo},
+    error::{HotShotError, Severity},
+    event::{HotShotAction, LeafInfo, RejectedTransaction, SendFailure, ViewTimeoutDiagnostics},
+    gossip_fanout::MessageClass,
     message::Proposal,
+    safety::{SafetyAlertHandler, SafetyMonitor},
     simple_certificate::{DaCertificate2, QuorumCertificate2},
+    simple_vote::QuorumVote2,
     traits::{
         block_contents::BuilderFee,
-        metrics::{Counter, Gauge, Histogram, Metrics, NoMetrics},
+        metrics::{Counter, Gauge, Histogram, Metrics, MetricsFamily, NoMetrics},
         node_implementation::{ConsensusTime, NodeType},
-        signature_key::SignatureKey,
+        signer::Signer,
         BlockPayload, ValidatedState,
     },
     utils::{
         epoch_from_block_number, BuilderCommitment, LeafCommitment, StateAndDelta, Terminator,
     },
     vid::VidCommitment,
+    view_timing::{ViewPhase, ViewTimeline},
     vote::{Certificate, HasViewNumber},
 };
 
 /// A type alias for `HashMap<Commitment<T>, T>`
 pub type CommitmentMap<T> = HashMap<Commitment<T>, T>;
 
+/// Shared leaves by commitment, so the map and its leaves can be handed around without copying
+/// block headers.
+pub type LeafMap<TYPES> = HashMap<LeafCommitment<TYPES>, Arc<Leaf2<TYPES>>>;
+
 /// A type alias for `BTreeMap<T::Time, HashMap<T::SignatureKey, Proposal<T, VidDisperseShare<T>>>>`
 pub type VidShares<TYPES> = BTreeMap<
     <TYPES as NodeType>::View,
     HashMap<<TYPES as NodeType>::SignatureKey, Proposal<TYPES, VidDisperseShare2<TYPES>>>,
 >;
 
+/// Payloads we have promised to make available, by view.
+///
+/// These sit behind their own lock rather than the consensus lock: the DA task records one for
+/// every view it votes on and the request, response and VID paths read them back, none of which
+/// has to wait on (or hold up) the rest of [`Consensus`]. Clones share the same payloads.
+#[derive(Debug)]
+pub struct SavedPayloads<TYPES: NodeType>(
+    Arc<parking_lot::RwLock<BTreeMap<TYPES::View, Arc<[u8]>>>>,
+);
+
+impl<TYPES: NodeType> Clone for SavedPayloads<TYPES> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<TYPES: NodeType> SavedPayloads<TYPES> {
+    /// Create a new set of saved payloads, starting from `payloads`.
+    pub fn new(payloads: BTreeMap<TYPES::View, Arc<[u8]>>) -> Self {
+        Self(Arc::new(parking_lot::RwLock::new(payloads)))
+    }
+
+    /// Get the payload saved for `view`, if any.
+    #[must_use]
+    pub fn get(&self, view: &TYPES::View) -> Option<Arc<[u8]>> {
+        self.0.read().get(view).map(Arc::clone)
+    }
+
+    /// Whether we have a payload saved for `view`.
+    #[must_use]
+    pub fn contains_key(&self, view: &TYPES::View) -> bool {
+        self.0.read().contains_key(view)
+    }
+
+    /// Save the payload for `view`.
+    ///
+    /// # Errors
+    /// Returns an error if we already have a payload for `view`.
+    pub fn insert(&self, view: TYPES::View, payload: Arc<[u8]>) -> Result<()> {
+        let mut payloads = self.0.write();
+        ensure!(
+            !payloads.contains_key(&view),
+            "Payload with the same view already exists."
+        );
+        payloads.insert(view, payload);
+        Ok(())
+    }
+
+    /// Drop every payload older than `view`.
+    pub fn prune_before(&self, view: TYPES::View) {
+        let mut payloads = self.0.write();
+        *payloads = payloads.split_off(&view);
+    }
+}
+
+/// The rule by which a replica decides leaves and picks the leaf it locks on.
+///
+/// Every node of a network must follow the same rule, or they disagree on which proposals are
+/// safe to vote for.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitRule {
+    /// The three-chain rule until the epochs upgrade, and the two-chain rule from it on.
+    #[default]
+    ByVersion,
+    /// A leaf is decided by a chain of three consecutive certified descendants, and the replica
+    /// locks on the parent of the newest certified leaf.
+    ThreeChain,
+    /// A leaf is decided by a chain of two consecutive certified leaves, as in Jolteon, and the
+    /// replica locks on the newest certified leaf. Views in which the chain breaks are left
+    /// through timeout certificates, which the next proposal carries.
+    ///
+    /// Only usable when the epochs upgrade is in effect from genesis, since proposals from
+    /// before it cannot carry the proof of the highest QC that the rule depends on.
+    TwoChain,
+}
+
+impl CommitRule {
+    /// Whether the two-chain rule applies to a proposal, given whether the epochs upgrade is in
+    /// effect for its view.
+    #[must_use]
+    pub fn two_chain(self, epochs_active: bool) -> bool {
+        match self {
+            Self::ByVersion => epochs_active,
+            Self::ThreeChain => false,
+            Self::TwoChain => true,
+        }
+    }
+}
+
 /// Type alias for consensus state wrapped in a lock.
 pub type LockedConsensusState<TYPES> = Arc<RwLock<Consensus<TYPES>>>;
 
+/// Number of send failures kept for each view, so that a dead network cannot fill memory
+const MAX_SEND_FAILURES_PER_VIEW: usize = 100;
+
 /// A thin wrapper around `LockedConsensusState` that helps debugging locks
 #[derive(Clone, Debug)]
 pub struct OuterConsensus<TYPES: NodeType> {
@@ -302,7 +412,7 @@ pub struct Consensus<TYPES: NodeType> {
     /// Map of leaf hash -> leaf
     /// - contains undecided leaves
     /// - includes the MOST RECENT decided leaf
-    saved_leaves: CommitmentMap<Leaf2<TYPES>>,
+    saved_leaves: LeafMap<TYPES>,
 
     /// Bundle of views which we performed the most recent action
     /// visibible to the network.  Actions are votes and proposals
@@ -312,11 +422,29 @@ pub struct Consensus<TYPES: NodeType> {
     /// Saved payloads.
     ///
     /// Encoded transactions for every view if we got a payload for that view.
-    saved_payloads: BTreeMap<TYPES::View, Arc<[u8]>>,
+    saved_payloads: SavedPayloads<TYPES>,
 
     /// the highqc per spec
     high_qc: QuorumCertificate2<TYPES>,
 
+    /// Transactions rejected from recently decided blocks, so submitters can look them up
+    rejected_transactions: HashMap<Commitment<TYPES::Transaction>, RejectedTransaction<TYPES>>,
+
+    /// Random values derived from the QCs of recent views
+    beacon: RandomnessBeacon<TYPES>,
+
+    /// How long our latest write to storage took, if we have written anything yet
+    storage_write_latency: Option<Duration>,
+
+    /// When the undecided views reached each phase
+    view_timeline: ViewTimeline<TYPES::View>,
+
+    /// Checks for safety violations in the QCs, votes and decides we see
+    safety: SafetyMonitor<TYPES>,
+
+    /// Messages we failed to send, by view, for the views which have not been decided yet
+    send_failures: BTreeMap<TYPES::View, Vec<SendFailure<TYPES>>>,
+
     /// A reference to the metrics trait
     pub metrics: Arc<ConsensusMetricsValue>,
 
@@ -360,6 +488,26 @@ pub struct ConsensusMetricsValue {
     pub number_of_empty_blocks_proposed: Box<dyn Counter>,
     /// Number of events in the hotshot event queue
     pub internal_event_queue_len: Box<dyn Gauge>,
+    /// Number of events in the queue for the application
+    pub external_event_queue_len: Box<dyn Gauge>,
+    /// Number of messages for past views dropped because the internal event queue was full
+    pub dropped_stale_messages: Box<dyn Counter>,
+    /// Seconds from the start of a view until we received its proposal
+    pub view_proposal_received_latency: Box<dyn Histogram>,
+    /// Seconds from the start of a view until we voted in it
+    pub view_vote_sent_latency: Box<dyn Histogram>,
+    /// Seconds from the start of a view until we formed a QC for it
+    pub view_qc_formed_latency: Box<dyn Histogram>,
+    /// Seconds from the start of a view until its proposal was decided
+    pub view_decided_latency: Box<dyn Histogram>,
+    /// Number of messages dropped because of a recoverable error
+    pub recoverable_errors: Box<dyn Counter>,
+    /// Number of views we could not take part in because of an error
+    pub view_fatal_errors: Box<dyn Counter>,
+    /// Number of errors which keep us from taking part in consensus at all
+    pub node_fatal_errors: Box<dyn Counter>,
+    /// Seconds from the start of a view until we received a message for it, by message class
+    pub message_propagation_latency: BTreeMap<MessageClass, Box<dyn Histogram>>,
 }
 
 impl ConsensusMetricsValue {
@@ -391,6 +539,47 @@ impl ConsensusMetricsValue {
                 .create_counter(String::from("number_of_empty_blocks_proposed"), None),
             internal_event_queue_len: metrics
                 .create_gauge(String::from("internal_event_queue_len"), None),
+            external_event_queue_len: metrics
+                .create_gauge(String::from("external_event_queue_len"), None),
+            dropped_stale_messages: metrics
+                .create_counter(String::from("dropped_stale_messages"), None),
+            view_proposal_received_latency: metrics
+                .create_histogram(String::from("view_proposal_received_latency"), None),
+            view_vote_sent_latency: metrics
+                .create_histogram(String::from("view_vote_sent_latency"), None),
+            view_qc_formed_latency: metrics
+                .create_histogram(String::from("view_qc_formed_latency"), None),
+            view_decided_latency: metrics
+                .create_histogram(String::from("view_decided_latency"), None),
+            recoverable_errors: metrics.create_counter(String::from("recoverable_errors"), None),
+            view_fatal_errors: metrics.create_counter(String::from("view_fatal_errors"), None),
+            node_fatal_errors: metrics.create_counter(String::from("node_fatal_errors"), None),
+            message_propagation_latency: {
+                let family = metrics.histogram_family(
+                    String::from("message_propagation_latency"),
+                    vec![String::from("class")],
+                );
+                MessageClass::ALL
+                    .into_iter()
+                    .map(|class| (class, family.create(vec![class.name().to_string()])))
+                    .collect()
+            },
+        }
+    }
+
+    /// Record that a message of `class` reached us `latency` after the start of its view
+    pub fn record_propagation(&self, class: MessageClass, latency: Duration) {
+        if let Some(histogram) = self.message_propagation_latency.get(&class) {
+            histogram.add_point(latency.as_secs_f64());
+        }
+    }
+
+    /// Count an error of the given `severity`
+    pub fn record_error(&self, severity: Severity) {
+        match severity {
+            Severity::Recoverable => self.recoverable_errors.add(1),
+            Severity::ViewFatal => self.view_fatal_errors.add(1),
+            Severity::NodeFatal => self.node_fatal_errors.add(1),
         }
     }
 }
@@ -412,8 +601,8 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         last_decided_view: TYPES::View,
         last_actioned_view: TYPES::View,
         last_proposals: BTreeMap<TYPES::View, Proposal<TYPES, QuorumProposal2<TYPES>>>,
-        saved_leaves: CommitmentMap<Leaf2<TYPES>>,
-        saved_payloads: BTreeMap<TYPES::View, Arc<[u8]>>,
+        saved_leaves: LeafMap<TYPES>,
+        saved_payloads: SavedPayloads<TYPES>,
         high_qc: QuorumCertificate2<TYPES>,
         metrics: Arc<ConsensusMetricsValue>,
         epoch_height: u64,
@@ -431,6 +620,12 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             saved_leaves,
             saved_payloads,
             high_qc,
+            rejected_transactions: HashMap::new(),
+            beacon: RandomnessBeacon::default(),
+            storage_write_latency: None,
+            view_timeline: ViewTimeline::default(),
+            safety: SafetyMonitor::default(),
+            send_failures: BTreeMap::new(),
             metrics,
             epoch_height,
             vote_tracker: VoteTracker::new(),
@@ -468,12 +663,14 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     }
 
     /// Get the saved leaves.
-    pub fn saved_leaves(&self) -> &CommitmentMap<Leaf2<TYPES>> {
+    pub fn saved_leaves(&self) -> &LeafMap<TYPES> {
         &self.saved_leaves
     }
 
     /// Get the saved payloads.
-    pub fn saved_payloads(&self) -> &BTreeMap<TYPES::View, Arc<[u8]>> {
+    ///
+    /// Clone the returned handle to read or record payloads without holding the consensus lock.
+    pub fn saved_payloads(&self) -> &SavedPayloads<TYPES> {
         &self.saved_payloads
     }
 
@@ -487,6 +684,21 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         &self.saved_da_certs
     }
 
+    /// Whether the payload of `leaf` is certified as available by a saved DA certificate.
+    ///
+    /// DA and quorum votes of a view are collected concurrently, so a QC alone does not show that
+    /// the payload of its leaf is available. Nobody may extend a leaf until its DAC has formed.
+    /// The genesis leaf needs no certificate.
+    pub fn is_payload_certified(&self, leaf: &Leaf2<TYPES>) -> bool {
+        let view = leaf.view_number();
+        let payload_commitment = leaf.payload_commitment();
+        view == TYPES::View::genesis()
+            || self
+                .saved_da_certs
+                .get(&view)
+                .is_some_and(|cert| cert.data().payload_commit == payload_commitment)
+    }
+
     /// Get the map of our recent proposals
     pub fn last_proposals(
         &self,
@@ -503,9 +715,80 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             debug!("New view isn't newer than the current view.")
         );
         self.cur_view = view_number;
+        self.record_view_phase(view_number, ViewPhase::Start);
         Ok(())
     }
 
+    /// Record that `view` reached `phase` now, and add its latency to the metrics.
+    pub fn record_view_phase(&mut self, view: TYPES::View, phase: ViewPhase) {
+        let Some(latency) = self.view_timeline.record(view, phase, Instant::now()) else {
+            return;
+        };
+        let histogram = match phase {
+            ViewPhase::Start => return,
+            ViewPhase::ProposalReceived => &self.metrics.view_proposal_received_latency,
+            ViewPhase::VoteSent => &self.metrics.view_vote_sent_latency,
+            ViewPhase::QcFormed => &self.metrics.view_qc_formed_latency,
+            ViewPhase::Decided => &self.metrics.view_decided_latency,
+        };
+        histogram.add_point(latency.as_secs_f64());
+    }
+
+    /// Time since we entered `view`, if we did and have not pruned it yet.
+    #[must_use]
+    pub fn time_in_view(&self, view: TYPES::View) -> Option<Duration> {
+        self.view_timeline
+            .time_of(view, ViewPhase::Start)
+            .map(|start| start.elapsed())
+    }
+
+    /// Record that we failed to send a message for `view`.
+    pub fn record_send_failure(&mut self, view: TYPES::View, failure: SendFailure<TYPES>) {
+        if view < self.last_decided_view {
+            return;
+        }
+        let failures = self.send_failures.entry(view).or_default();
+        if failures.len() < MAX_SEND_FAILURES_PER_VIEW {
+            failures.push(failure);
+        }
+    }
+
+    /// Collect what we know about `view`, which just timed out.
+    ///
+    /// `leader` and `had_stake` come from the membership, which consensus does not keep.
+    #[must_use]
+    pub fn timeout_diagnostics(
+        &self,
+        view: TYPES::View,
+        public_key: &TYPES::SignatureKey,
+        leader: Option<TYPES::SignatureKey>,
+        had_stake: bool,
+    ) -> ViewTimeoutDiagnostics<TYPES> {
+        let send_failures = self.send_failures.get(&view).cloned().unwrap_or_default();
+        let mut unreachable_peers = vec![];
+        for recipient in send_failures.iter().filter_map(|f| f.recipient.as_ref()) {
+            if !unreachable_peers.contains(recipient) {
+                unreachable_peers.push(recipient.clone());
+            }
+        }
+
+        ViewTimeoutDiagnostics {
+            leader,
+            had_stake,
+            proposal_received: self
+                .view_timeline
+                .reached(view, ViewPhase::ProposalReceived),
+            da_certificate_received: self.saved_da_certs.contains_key(&view),
+            vid_share_received: self
+                .vid_shares
+                .get(&view)
+                .is_some_and(|shares| shares.contains_key(public_key)),
+            voted: self.view_timeline.reached(view, ViewPhase::VoteSent),
+            send_failures,
+            unreachable_peers,
+        }
+    }
+
     /// Get the parent Leaf Info from a given leaf and our public key.
     /// Returns None if we don't have the data in out state
     pub fn parent_leaf_info(
@@ -528,12 +811,12 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             .cloned()
             .map(|prop| prop.data);
 
-        Some(LeafInfo {
-            leaf: parent_leaf.clone(),
+        Some(LeafInfo::new(
+            Leaf2::clone(parent_leaf),
             state,
             delta,
-            vid_share: parent_vid,
-        })
+            parent_vid,
+        ))
     }
 
     /// Update the current epoch.
@@ -557,7 +840,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             HotShotAction::DaVote => {
                 // Use vote tracker to prevent double voting
                 let voter_key = Arc::new(self.public_key().clone());
-                
+
                 if !self.vote_tracker.record_vote(view, voter_key) {
                     tracing::warn!("Prevented double voting attempt for view {}", view);
                     return false;
@@ -566,13 +849,16 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                 if view > self.last_actions.da_vote {
                     self.last_actions.da_vote = view;
                 }
-                
+
                 // Clean up old vote records periodically
                 self.vote_tracker.cleanup_old_views(view);
-                
+
                 true
             }
-            HotShotAction::Vote => &mut self.last_actions.voted,
+            HotShotAction::Vote => {
+                self.record_view_phase(view, ViewPhase::VoteSent);
+                &mut self.last_actions.voted
+            }
             HotShotAction::Propose => &mut self.last_actions.proposed,
             HotShotAction::DaPropose => &mut self.last_actions.da_proposed,
             _ => return true,
@@ -624,6 +910,10 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             debug!("New view isn't newer than the previously decided view.")
         );
         self.last_decided_view = view_number;
+        self.record_view_phase(view_number, ViewPhase::Decided);
+        self.view_timeline.prune(view_number);
+        self.safety.prune(view_number);
+        self.send_failures = self.send_failures.split_off(&view_number);
         Ok(())
     }
 
@@ -667,10 +957,11 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     /// with the same view number.
     pub fn update_leaf(
         &mut self,
-        leaf: Leaf2<TYPES>,
+        leaf: impl Into<Arc<Leaf2<TYPES>>>,
         state: Arc<TYPES::ValidatedState>,
         delta: Option<Arc<<TYPES::ValidatedState as ValidatedState<TYPES>>::Delta>>,
     ) -> Result<()> {
+        let leaf = leaf.into();
         let view_number = leaf.view_number();
         let epoch = TYPES::Epoch::new(epoch_from_block_number(leaf.height(), self.epoch_height));
         let view = View {
@@ -721,36 +1012,122 @@ impl<TYPES: NodeType> Consensus<TYPES> {
     }
 
     /// Update the saved leaves with a new leaf.
-    fn update_saved_leaves(&mut self, leaf: Leaf2<TYPES>) {
+    fn update_saved_leaves(&mut self, leaf: Arc<Leaf2<TYPES>>) {
         self.saved_leaves.insert(leaf.commit(), leaf);
     }
 
+    /// Discard the speculative state of a leaf which will never be decided, along with the state
+    /// of every saved leaf extending it.
+    ///
+    /// The views of the discarded leaves are marked as failed, so their states can no longer be
+    /// used as a parent state. Payloads and VID shares are kept, since they are still garbage
+    /// collected by view. Returns the discarded views in ascending order.
+    ///
+    /// # Errors
+    /// If the leaf is unknown, or if it is at or below the locked view, since that state may
+    /// already be (or become) decided.
+    pub fn discard_leaf(&mut self, leaf_commit: LeafCommitment<TYPES>) -> Result<Vec<TYPES::View>> {
+        let leaf_view = self
+            .saved_leaves
+            .get(&leaf_commit)
+            .context(debug!("Cannot discard a leaf we don't have"))?
+            .view_number();
+        ensure!(
+            leaf_view > self.locked_view,
+            warn!(
+                "Refusing to discard the state of view {:?}, which is not above the locked view {:?}",
+                leaf_view,
+                self.locked_view
+            )
+        );
+
+        // Walk the saved leaves in view order, so every parent is visited before its children.
+        let mut descendants: Vec<_> = self
+            .saved_leaves
+            .iter()
+            .filter(|(_, leaf)| leaf.view_number() > leaf_view)
+            .map(|(commit, leaf)| (leaf.view_number(), *commit, leaf.parent_commitment()))
+            .collect();
+        descendants.sort_unstable_by_key(|(view, ..)| *view);
+
+        let mut discarded = vec![(leaf_view, leaf_commit)];
+        for (view, commit, parent) in descendants {
+            if discarded.iter().any(|(_, discarded)| *discarded == parent) {
+                discarded.push((view, commit));
+            }
+        }
+
+        for (view, commit) in &discarded {
+            self.saved_leaves.remove(commit);
+            if self
+                .validated_state_map
+                .get(view)
+                .and_then(|view| view.leaf_commitment())
+                == Some(*commit)
+            {
+                self.validated_state_map.insert(
+                    *view,
+                    View {
+                        view_inner: ViewInner::Failed,
+                    },
+                );
+            }
+        }
+
+        Ok(discarded.into_iter().map(|(view, _)| view).collect())
+    }
+
     /// Update the saved payloads with a new encoded transaction.
     ///
+    /// The payloads have their own lock, so this only needs read access to `Consensus`.
+    ///
     /// # Errors
     /// Can return an error when there's an existing payload corresponding to the same view number.
     pub fn update_saved_payloads(
-        &mut self,
+        &self,
         view_number: TYPES::View,
         encoded_transaction: Arc<[u8]>,
     ) -> Result<()> {
-        ensure!(
-            !self.saved_payloads.contains_key(&view_number),
-            "Payload with the same view already exists."
-        );
-        self.saved_payloads.insert(view_number, encoded_transaction);
-        Ok(())
+        self.saved_payloads.insert(view_number, encoded_transaction)
+    }
+
+    /// Report safety violations to `handler` from now on.
+    pub fn set_safety_alert_handler(&mut self, handler: Arc<dyn SafetyAlertHandler<TYPES>>) {
+        self.safety.set_handler(handler);
+    }
+
+    /// Check a valid QC for conflicts with the other QCs of its view.
+    pub fn check_qc(&mut self, qc: &QuorumCertificate2<TYPES>) {
+        self.safety.check_qc(qc);
+    }
+
+    /// Check a vote for conflicts with the other votes of its sender in its view.
+    pub fn check_vote(&mut self, vote: &QuorumVote2<TYPES>) {
+        self.safety.check_vote(vote);
+    }
+
+    /// Check that newly decided leaves, newest first, extend the last decided leaf.
+    ///
+    /// Must be called before the last decided view is updated.
+    pub fn check_decided_chain<'a>(
+        &self,
+        leaves: impl DoubleEndedIterator<Item = &'a Leaf2<TYPES>>,
+    ) {
+        self.safety
+            .check_decided_chain(&self.decided_leaf(), leaves);
     }
 
     /// Update the high QC if given a newer one.
     /// # Errors
     /// Can return an error when the provided high_qc is not newer than the existing entry.
     pub fn update_high_qc(&mut self, high_qc: QuorumCertificate2<TYPES>) -> Result<()> {
+        self.safety.check_qc(&high_qc);
         ensure!(
             high_qc.view_number > self.high_qc.view_number || high_qc == self.high_qc,
             debug!("High QC with an equal or higher view exists.")
         );
         tracing::debug!("Updating high QC");
+        self.beacon.record(&high_qc);
         self.high_qc = high_qc;
 
         Ok(())
@@ -861,9 +1238,53 @@ impl<TYPES: NodeType> Consensus<TYPES> {
                 self.saved_leaves.remove(&leaf);
             });
         self.validated_state_map = self.validated_state_map.split_off(&gc_view);
-        self.saved_payloads = self.saved_payloads.split_off(&gc_view);
+        self.saved_payloads.prune_before(gc_view);
         self.vid_shares = self.vid_shares.split_off(&gc_view);
         self.last_proposals = self.last_proposals.split_off(&gc_view);
+        self.rejected_transactions.retain(|_, rejected| {
+            *rejected.view_number + REJECTED_TRANSACTION_RETENTION_VIEWS >= *new_anchor_view
+        });
+        self.beacon.prune_before(TYPES::View::new(
+            new_anchor_view.saturating_sub(BEACON_RETENTION_VIEWS),
+        ));
+    }
+
+    /// Look up why a transaction was rejected from a recently decided block.
+    #[must_use]
+    pub fn rejected_transaction(
+        &self,
+        commitment: &Commitment<TYPES::Transaction>,
+    ) -> Option<&RejectedTransaction<TYPES>> {
+        self.rejected_transactions.get(commitment)
+    }
+
+    /// The randomness beacon outputs of recent views.
+    #[must_use]
+    pub fn beacon(&self) -> &RandomnessBeacon<TYPES> {
+        &self.beacon
+    }
+
+    /// Record the transactions rejected from newly decided leaves.
+    pub fn update_rejected_transactions(
+        &mut self,
+        rejected: impl IntoIterator<Item = RejectedTransaction<TYPES>>,
+    ) {
+        self.rejected_transactions.extend(
+            rejected
+                .into_iter()
+                .map(|rejected| (rejected.commitment, rejected)),
+        );
+    }
+
+    /// How long our latest write to storage took.
+    #[must_use]
+    pub fn storage_write_latency(&self) -> Option<Duration> {
+        self.storage_write_latency
+    }
+
+    /// Record how long a write to storage took.
+    pub fn record_storage_write(&mut self, latency: Duration) {
+        self.storage_write_latency = Some(latency);
     }
 
     /// Gets the last decided leaf.
@@ -878,7 +1299,7 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         let leaf = view
             .leaf_commitment()
             .expect("Decided leaf not found! Consensus internally inconsistent");
-        self.saved_leaves.get(&leaf).unwrap().clone()
+        Leaf2::clone(&self.saved_leaves[&leaf])
     }
 
     /// Gets the validated state with the given view number, if in the state map.
@@ -922,9 +1343,9 @@ impl<TYPES: NodeType> Consensus<TYPES> {
         consensus: OuterConsensus<TYPES>,
         view: <TYPES as NodeType>::View,
         membership: Arc<TYPES::Membership>,
-        private_key: &<TYPES::SignatureKey as SignatureKey>::PrivateKey,
+        signer: &dyn Signer<TYPES::SignatureKey>,
     ) -> Option<()> {
-        let txns = Arc::clone(consensus.read().await.saved_payloads().get(&view)?);
+        let txns = consensus.read().await.saved_payloads().get(&view)?;
         let epoch = consensus
             .read()
             .await
@@ -933,12 +1354,25 @@ impl<TYPES: NodeType> Consensus<TYPES> {
             .view_inner
             .epoch()?;
         let vid = VidDisperse::calculate_vid_disperse(txns, &membership, view, epoch, None).await;
+        // All shares carry the same payload commitment, so one signature covers them
+        let signature = match signer.sign(vid.payload_commitment.as_ref()).await {
+            Ok(signature) => signature,
+            Err(err) => {
+                tracing::error!("VID: failed to sign dispersal share payload: {err}");
+                return None;
+            }
+        };
         let shares = VidDisperseShare2::from_vid_disperse(vid);
         let mut consensus_writer = consensus.write().await;
         for share in shares {
-            if let Some(prop) = share.to_proposal(private_key) {
-                consensus_writer.update_vid_shares(view, prop);
-            }
+            consensus_writer.update_vid_shares(
+                view,
+                Proposal {
+                    data: share,
+                    signature: signature.clone(),
+                    _pd: PhantomData,
+                },
+            );
         }
         Some(())
     }
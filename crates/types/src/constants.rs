@@ -32,6 +32,13 @@ pub const COMBINED_NETWORK_PRIMARY_CHECK_INTERVAL: u64 = 50;
 /// the default delay duration value in milliseconds of sending on the secondary in the combined networks
 pub const COMBINED_NETWORK_DELAY_DURATION: u64 = 5000;
 
+/// the default time in milliseconds the batching network holds direct messages to a peer for,
+/// waiting for more to send along with them
+pub const BATCHING_NETWORK_WINDOW: u64 = 5;
+
+/// the default largest size in bytes of a batch of messages sent by the batching network
+pub const BATCHING_NETWORK_MAX_BATCH_SIZE: usize = 64 * 1024;
+
 /// The default network data request delay in milliseconds
 pub const REQUEST_DATA_DELAY: u64 = 5000;
 
@@ -41,6 +48,12 @@ pub const EVENT_CHANNEL_SIZE: usize = 100_000;
 /// Default channel size for HotShot -> application communication
 pub const EXTERNAL_EVENT_CHANNEL_SIZE: usize = 100_000;
 
+/// The number of views past a decide for which rejected transactions can still be looked up
+pub const REJECTED_TRANSACTION_RETENTION_VIEWS: u64 = 1000;
+
+/// The number of views past a decide for which randomness beacon outputs can still be looked up
+pub const BEACON_RETENTION_VIEWS: u64 = 1000;
+
 /// The offset for how far in the future we will send out a `QuorumProposal` with an `UpgradeCertificate` we form. This is also how far in advance of sending a `QuorumProposal` we begin collecting votes on an `UpgradeProposal`.
 pub const UPGRADE_PROPOSE_OFFSET: u64 = 5;
 
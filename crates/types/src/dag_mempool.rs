@@ -0,0 +1,322 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A Narwhal-style mempool, which disseminates transactions in batches ahead of consensus.
+//!
+//! Every node collects the transactions submitted to it into a batch, which it broadcasts once a
+//! view. The nodes which receive a batch store it and vote for it, and once the votes of at least
+//! one honest node's worth of stake are gathered the author broadcasts an availability
+//! certificate for the batch.
+//!
+//! A leader then proposes a block made of certified batches by their certificates alone, in a
+//! [`BatchProposal`], rather than sending its payload through DA and VID. Every node checks the
+//! certificates, rebuilds the payload from the batches it holds, checks it against the leader's
+//! signatures and computes its own VID share, so a DA vote or a quorum vote for the block needs
+//! nothing but the batches a node already received. Dissemination thus runs at its own pace
+//! rather than in the critical path of each view.
+//!
+//! The payload is rebuilt with [`BlockPayload::from_transactions`] over the decided state, as the
+//! leader built it. A node which does not hold every batch of a proposal, or rebuilds another
+//! payload, does not vote for it.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simple_certificate::BatchCertificate,
+    traits::{
+        block_contents::BlockPayload, node_implementation::NodeType, signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+
+/// Default number of transactions in a batch.
+const DEFAULT_MAX_BATCH_SIZE: usize = 512;
+
+/// Default number of batches in a block.
+const DEFAULT_MAX_BATCHES_PER_BLOCK: usize = 32;
+
+/// Default number of views a batch is kept for.
+const DEFAULT_BATCH_TTL: u64 = 100;
+
+/// Settings of the DAG mempool.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DagMempoolConfig {
+    /// Whether leaders build blocks from certified batches, rather than asking the builders
+    #[serde(default)]
+    pub enabled: bool,
+    /// Most transactions in a batch; the rest wait for the next one
+    #[serde(default = "default_max_batch_size")]
+    pub max_batch_size: usize,
+    /// Most batches a leader puts in a block
+    #[serde(default = "default_max_batches_per_block")]
+    pub max_batches_per_block: usize,
+    /// Views a batch is kept for after it was sent, whether or not it was proposed
+    #[serde(default = "default_batch_ttl")]
+    pub batch_ttl: u64,
+}
+
+/// Default value of [`DagMempoolConfig::max_batch_size`], for serde.
+fn default_max_batch_size() -> usize {
+    DEFAULT_MAX_BATCH_SIZE
+}
+
+/// Default value of [`DagMempoolConfig::max_batches_per_block`], for serde.
+fn default_max_batches_per_block() -> usize {
+    DEFAULT_MAX_BATCHES_PER_BLOCK
+}
+
+/// Default value of [`DagMempoolConfig::batch_ttl`], for serde.
+fn default_batch_ttl() -> u64 {
+    DEFAULT_BATCH_TTL
+}
+
+impl Default for DagMempoolConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_batch_size: DEFAULT_MAX_BATCH_SIZE,
+            max_batches_per_block: DEFAULT_MAX_BATCHES_PER_BLOCK,
+            batch_ttl: DEFAULT_BATCH_TTL,
+        }
+    }
+}
+
+/// Transactions a node disseminated together.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct TransactionBatch<TYPES: NodeType> {
+    /// The node which collected the transactions
+    pub author: TYPES::SignatureKey,
+    /// The view the batch was sent in
+    pub view: TYPES::View,
+    /// The transactions, in the order they were received
+    pub transactions: Vec<TYPES::Transaction>,
+}
+
+impl<TYPES: NodeType> Committable for TransactionBatch<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        let transactions: Vec<_> = self.transactions.iter().map(Committable::commit).collect();
+
+        RawCommitmentBuilder::new("Transaction batch")
+            .var_size_field("author", &self.author.to_bytes())
+            .u64_field("view", *self.view)
+            .array_field("transactions", &transactions)
+            .finalize()
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for TransactionBatch<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view
+    }
+}
+
+/// A leader's proposal of a block made of certified batches, in place of its DA proposal and VID
+/// dispersal.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct BatchProposal<TYPES: NodeType> {
+    /// The view the block is proposed for
+    pub view_number: TYPES::View,
+    /// The epoch of the view
+    pub epoch: TYPES::Epoch,
+    /// The certificates of the batches the block is made of, in order
+    pub certificates: Vec<BatchCertificate<TYPES>>,
+    /// Metadata of the block payload
+    pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    /// The leader's signature of the DA proposal of the payload, over its hash
+    pub da_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    /// The leader's signature of the VID shares of the payload, over its VID commitment
+    pub vid_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> BatchProposal<TYPES> {
+    /// The commitments of the batches the block is made of, in order.
+    #[must_use]
+    pub fn batches(&self) -> Vec<Commitment<TransactionBatch<TYPES>>> {
+        self.certificates
+            .iter()
+            .map(|cert| cert.data.batch_commit)
+            .collect()
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for BatchProposal<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
+/// The batches this node holds, which of them are certified, and which have been claimed by a
+/// leader.
+#[derive(Clone, Debug)]
+pub struct BatchStore<TYPES: NodeType> {
+    /// The batches we hold
+    batches: HashMap<Commitment<TransactionBatch<TYPES>>, TransactionBatch<TYPES>>,
+    /// The certificates of the certified batches, by the view they were sent in
+    certified: BTreeMap<TYPES::View, Vec<BatchCertificate<TYPES>>>,
+    /// The batches claimed by the leader of each view, until its proposal is validated
+    claims: BTreeMap<TYPES::View, Vec<Commitment<TransactionBatch<TYPES>>>>,
+    /// Batches claimed by some leader
+    claimed: HashSet<Commitment<TransactionBatch<TYPES>>>,
+    /// Views whose proposal was validated
+    confirmed: BTreeSet<TYPES::View>,
+}
+
+impl<TYPES: NodeType> Default for BatchStore<TYPES> {
+    fn default() -> Self {
+        Self {
+            batches: HashMap::new(),
+            certified: BTreeMap::new(),
+            claims: BTreeMap::new(),
+            claimed: HashSet::new(),
+            confirmed: BTreeSet::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> BatchStore<TYPES> {
+    /// Keep `batch`, returning its commitment.
+    pub fn insert(
+        &mut self,
+        batch: TransactionBatch<TYPES>,
+    ) -> Commitment<TransactionBatch<TYPES>> {
+        let commitment = batch.commit();
+        self.batches.entry(commitment).or_insert(batch);
+
+        commitment
+    }
+
+    /// The batch with commitment `commitment`, if we hold it.
+    #[must_use]
+    pub fn get(
+        &self,
+        commitment: &Commitment<TransactionBatch<TYPES>>,
+    ) -> Option<&TransactionBatch<TYPES>> {
+        self.batches.get(commitment)
+    }
+
+    /// Record the certificate `cert` of a batch, which is checked.
+    pub fn certify(&mut self, cert: BatchCertificate<TYPES>) {
+        let certified = self.certified.entry(cert.view_number).or_default();
+        if !certified
+            .iter()
+            .any(|certified| certified.data.batch_commit == cert.data.batch_commit)
+        {
+            certified.push(cert);
+        }
+    }
+
+    /// The certificates of up to `max_batches` certified batches we hold which no leader has
+    /// claimed, oldest first.
+    #[must_use]
+    pub fn next_block(&self, max_batches: usize) -> Vec<BatchCertificate<TYPES>> {
+        self.certified
+            .values()
+            .flatten()
+            .filter(|cert| {
+                let commitment = &cert.data.batch_commit;
+                self.batches.contains_key(commitment) && !self.claimed.contains(commitment)
+            })
+            .take(max_batches)
+            .cloned()
+            .collect()
+    }
+
+    /// Whether we hold every batch in `commitments`.
+    #[must_use]
+    pub fn holds_all(&self, commitments: &[Commitment<TransactionBatch<TYPES>>]) -> bool {
+        commitments
+            .iter()
+            .all(|commitment| self.batches.contains_key(commitment))
+    }
+
+    /// The transactions of the batches in `commitments`, in order, skipping those we don't hold.
+    #[must_use]
+    pub fn transactions(
+        &self,
+        commitments: &[Commitment<TransactionBatch<TYPES>>],
+    ) -> Vec<TYPES::Transaction> {
+        commitments
+            .iter()
+            .filter_map(|commitment| self.batches.get(commitment))
+            .flat_map(|batch| batch.transactions.iter().cloned())
+            .collect()
+    }
+
+    /// Record that the leader of `view` put the batches in `commitments` in its block.
+    ///
+    /// If the proposal of `view` was already validated, the batches are done with and dropped.
+    pub fn claim(
+        &mut self,
+        view: TYPES::View,
+        commitments: Vec<Commitment<TransactionBatch<TYPES>>>,
+    ) {
+        if self.confirmed.contains(&view) {
+            self.drop_batches(&commitments);
+            return;
+        }
+        self.claimed.extend(commitments.iter().copied());
+        self.claims.entry(view).or_default().extend(commitments);
+    }
+
+    /// Record that the proposal of `view` was validated, so the batches its leader claimed are
+    /// done with.
+    pub fn confirm(&mut self, view: TYPES::View) {
+        self.confirmed.insert(view);
+        if let Some(commitments) = self.claims.remove(&view) {
+            self.drop_batches(&commitments);
+        }
+    }
+
+    /// Return the batches claimed for views before `view` whose proposals were never validated,
+    /// so that a later leader can propose them.
+    pub fn release_before(&mut self, view: TYPES::View) {
+        let pending = self.claims.split_off(&view);
+        for commitment in std::mem::replace(&mut self.claims, pending)
+            .into_values()
+            .flatten()
+        {
+            self.claimed.remove(&commitment);
+        }
+    }
+
+    /// Forget everything about batches sent before `view`.
+    pub fn prune_before(&mut self, view: TYPES::View) {
+        self.batches.retain(|_, batch| batch.view >= view);
+        self.certified = self.certified.split_off(&view);
+        self.confirmed = self.confirmed.split_off(&view);
+        self.claimed
+            .retain(|commitment| self.batches.contains_key(commitment));
+    }
+
+    /// Number of batches we hold.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.batches.len()
+    }
+
+    /// Whether we hold no batches.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Forget the batches in `commitments`.
+    fn drop_batches(&mut self, commitments: &[Commitment<TransactionBatch<TYPES>>]) {
+        for commitment in commitments {
+            if let Some(batch) = self.batches.remove(commitment) {
+                if let Some(certified) = self.certified.get_mut(&batch.view) {
+                    certified.retain(|cert| cert.data.batch_commit != *commitment);
+                }
+            }
+            self.claimed.remove(commitment);
+        }
+    }
+}
@@ -10,7 +10,7 @@
 //! `HotShot`'s version of a block, and proposals, messages upon which to reach the consensus.
 
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashSet},
     fmt::{Debug, Display},
     hash::Hash,
     marker::PhantomData,
@@ -21,37 +21,44 @@ use async_lock::RwLock;
 use bincode::Options;
 use committable::{Commitment, CommitmentBoundsArkless, Committable, RawCommitmentBuilder};
 use jf_vid::{precomputable::Precomputable, VidDisperse as JfVidDisperse, VidScheme};
+use primitive_types::U256;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
+#[cfg(not(target_arch = "wasm32"))]
 use tokio::task::spawn_blocking;
 use tracing::error;
 use utils::anytrace::*;
 use vec1::Vec1;
 
 use crate::{
+    checkpoint::EpochCheckpoint,
     drb::{DrbResult, DrbSeedInput, INITIAL_DRB_RESULT, INITIAL_DRB_SEED_INPUT},
+    hlc::HybridTimestamp,
     impl_has_epoch,
     message::{Proposal, UpgradeLock},
     simple_certificate::{
         QuorumCertificate, QuorumCertificate2, TimeoutCertificate2, UpgradeCertificate,
         ViewSyncFinalizeCertificate2,
     },
-    simple_vote::{HasEpoch, QuorumData, QuorumData2, UpgradeProposalData, VersionedVoteData},
+    simple_vote::{
+        HasEpoch, HighQcVote, QuorumData, QuorumData2, UpgradeProposalData, VersionedVoteData,
+    },
     traits::{
         block_contents::{
             vid_commitment, BlockHeader, BuilderFee, EncodeBytes, TestableBlock,
             GENESIS_VID_NUM_STORAGE_NODES,
         },
+        commitment::CommitmentScheme,
         election::Membership,
         node_implementation::{ConsensusTime, NodeType, Versions},
-        signature_key::SignatureKey,
+        signature_key::{SignatureKey, StakeTableEntryType},
         states::TestableState,
         BlockPayload,
     },
     utils::{bincode_opts, epoch_from_block_number},
     vid::{vid_scheme, VidCommitment, VidCommon, VidPrecomputeData, VidSchemeType, VidShare},
-    vote::{Certificate, HasViewNumber},
+    vote::{Certificate, HasViewNumber, Vote},
 };
 
 /// Implements `ConsensusTime`, `Display`, `Add`, `AddAssign`, `Deref` and `Sub`
@@ -255,16 +262,20 @@ impl<TYPES: NodeType> VidDisperse<TYPES> {
     ) -> Self {
         let num_nodes = membership.total_nodes(epoch);
 
-        let vid_disperse = spawn_blocking(move || {
+        let disperse = move || {
             precompute_data
                 .map_or_else(
                     || vid_scheme(num_nodes).disperse(Arc::clone(&txns)),
                     |data| vid_scheme(num_nodes).disperse_precompute(Arc::clone(&txns), &data)
                 )
                 .unwrap_or_else(|err| panic!("VID disperse failure:(num_storage nodes,payload_byte_len)=({num_nodes},{}) error: {err}", txns.len()))
-        }).await;
+        };
         // Unwrap here will just propagate any panic from the spawned task, it's not a new place we can panic.
-        let vid_disperse = vid_disperse.unwrap();
+        #[cfg(not(target_arch = "wasm32"))]
+        let vid_disperse = spawn_blocking(disperse).await.unwrap();
+        // There is no blocking thread pool on wasm, so we disperse inline.
+        #[cfg(target_arch = "wasm32")]
+        let vid_disperse = disperse();
 
         Self::from_membership(view, vid_disperse, membership.as_ref(), epoch)
     }
@@ -289,6 +300,120 @@ impl<TYPES: NodeType> ViewChangeEvidence<TYPES> {
             ViewChangeEvidence::ViewSync(view_sync_cert) => view_sync_cert.view_number == *view,
         }
     }
+
+    /// The epoch of the certificate.
+    pub fn epoch(&self) -> TYPES::Epoch {
+        match self {
+            ViewChangeEvidence::Timeout(timeout_cert) => timeout_cert.data().epoch(),
+            ViewChangeEvidence::ViewSync(view_sync_cert) => view_sync_cert.data().epoch(),
+        }
+    }
+}
+
+/// Proof that a proposal made after a view change extends the highest QC known to a quorum.
+///
+/// On entering a view, every replica sends the leader its highest QC along with a signed report
+/// of its view. A leader proposing without a QC from the preceding view attaches the reports of a
+/// quorum, and replicas only accept the proposal if its `justify_qc` is at least as high as every
+/// reported QC. Any quorum includes an honest replica locked on the newest QC which could have
+/// decided a leaf, so the leader cannot extend a stale branch.
+#[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct HighQcProof<TYPES: NodeType> {
+    /// The replicas' reports, one per replica
+    pub reports: Vec<HighQcVote<TYPES>>,
+}
+
+impl<TYPES: NodeType> HighQcProof<TYPES> {
+    /// Whether `report` is signed by the replica it names.
+    pub async fn is_signed<V: Versions>(
+        report: &HighQcVote<TYPES>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> bool {
+        let Ok(data) =
+            VersionedVoteData::new(report.data.clone(), report.view_number, upgrade_lock).await
+        else {
+            return false;
+        };
+
+        report
+            .signing_key()
+            .validate(&report.signature(), data.commit().as_ref())
+    }
+
+    /// Total stake in `epoch` of the replicas which sent the reports.
+    #[must_use]
+    pub fn stake(&self, membership: &TYPES::Membership, epoch: TYPES::Epoch) -> U256 {
+        self.reports
+            .iter()
+            .filter_map(|report| membership.stake(&report.signing_key(), epoch))
+            .fold(U256::zero(), |total, entry| {
+                total.saturating_add(entry.stake())
+            })
+    }
+
+    /// Whether the replicas which sent the reports hold the success threshold of stake in `epoch`.
+    #[must_use]
+    pub fn is_from_quorum(&self, membership: &TYPES::Membership, epoch: TYPES::Epoch) -> bool {
+        self.stake(membership, epoch) >= U256::from(membership.success_threshold(epoch).get())
+    }
+
+    /// The view of the highest QC any report names.
+    #[must_use]
+    pub fn highest_reported_view(&self) -> Option<TYPES::View> {
+        self.reports
+            .iter()
+            .map(|report| report.data.high_qc_view)
+            .max()
+    }
+
+    /// Validates the proof for a proposal in `view` of the given epoch whose `justify_qc` is for
+    /// `justify_qc_view`.
+    ///
+    /// # Errors
+    /// If a report is not for `view` and `epoch`, a replica reports more than once, a signature
+    /// is invalid, the replicas hold less than the success threshold of stake, or a replica
+    /// reports a QC newer than `justify_qc_view`.
+    pub async fn validate<V: Versions>(
+        &self,
+        view: TYPES::View,
+        justify_qc_view: TYPES::View,
+        epoch: TYPES::Epoch,
+        membership: &TYPES::Membership,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<()> {
+        let mut signers = HashSet::new();
+        for report in &self.reports {
+            ensure!(
+                report.view_number == view && report.data.epoch == epoch,
+                "High QC report of {:?} is not for view {view:?} in epoch {epoch:?}",
+                report.signing_key()
+            );
+            ensure!(
+                signers.insert(report.signing_key()),
+                "{:?} reported its high QC more than once",
+                report.signing_key()
+            );
+            ensure!(
+                Self::is_signed(report, upgrade_lock).await,
+                "High QC report of {:?} has an invalid signature",
+                report.signing_key()
+            );
+        }
+
+        ensure!(
+            self.is_from_quorum(membership, epoch),
+            "High QC reports for view {view:?} do not come from a quorum"
+        );
+        let highest = self.highest_reported_view().unwrap_or(justify_qc_view);
+        ensure!(
+            justify_qc_view >= highest,
+            "Proposal for view {view:?} extends the QC of view {justify_qc_view:?}, but a replica \
+             holds a QC of view {highest:?}"
+        );
+
+        Ok(())
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
@@ -591,6 +716,15 @@ pub struct QuorumProposal2<TYPES: NodeType> {
     /// The DRB computation with this result was started two epochs ago.
     #[serde(with = "serde_bytes")]
     pub drb_result: DrbResult,
+
+    /// Proof that the `justify_qc` is the highest QC known to a quorum. Under the two-chain rule
+    /// it must be attached whenever the view change evidence is.
+    pub high_qc_proof: Option<HighQcProof<TYPES>>,
+
+    /// The committees of the next epoch, which the last block of every epoch must commit to.
+    /// Proposals are only sent in this format from the epochs upgrade on, so it is never set
+    /// before then.
+    pub epoch_checkpoint: Option<EpochCheckpoint<TYPES>>,
 }
 
 impl<TYPES: NodeType> From<QuorumProposal<TYPES>> for QuorumProposal2<TYPES> {
@@ -603,6 +737,8 @@ impl<TYPES: NodeType> From<QuorumProposal<TYPES>> for QuorumProposal2<TYPES> {
             view_change_evidence: quorum_proposal.proposal_certificate,
             drb_seed: INITIAL_DRB_SEED_INPUT,
             drb_result: INITIAL_DRB_RESULT,
+            high_qc_proof: None,
+            epoch_checkpoint: None,
         }
     }
 }
@@ -634,6 +770,7 @@ impl<TYPES: NodeType> From<Leaf<TYPES>> for Leaf2<TYPES> {
             view_change_evidence: None,
             drb_seed: INITIAL_DRB_SEED_INPUT,
             drb_result: INITIAL_DRB_RESULT,
+            epoch_checkpoint: None,
         }
     }
 }
@@ -788,6 +925,9 @@ pub struct Leaf2<TYPES: NodeType> {
     /// The DRB computation with this result was started two epochs ago.
     #[serde(with = "serde_bytes")]
     pub drb_result: DrbResult,
+
+    /// The committees of the next epoch, if this is the last block of an epoch.
+    epoch_checkpoint: Option<EpochCheckpoint<TYPES>>,
 }
 
 impl<TYPES: NodeType> Leaf2<TYPES> {
@@ -842,6 +982,7 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
             view_change_evidence: None,
             drb_seed: [0; 32],
             drb_result: [0; 32],
+            epoch_checkpoint: None,
         }
     }
     /// Time when this leaf was created.
@@ -866,6 +1007,10 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
     pub fn upgrade_certificate(&self) -> Option<UpgradeCertificate<TYPES>> {
         self.upgrade_certificate.clone()
     }
+    /// The committees of the next epoch, if this is the last block of an epoch.
+    pub fn epoch_checkpoint(&self) -> Option<&EpochCheckpoint<TYPES>> {
+        self.epoch_checkpoint.as_ref()
+    }
     /// Commitment to this leaf's parent.
     pub fn parent_commitment(&self) -> Commitment<Self> {
         self.parent_commitment
@@ -874,6 +1019,11 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
     pub fn block_header(&self) -> &<TYPES as NodeType>::BlockHeader {
         &self.block_header
     }
+    /// The timestamp of this leaf's block, if its header carries one. Timestamps strictly increase
+    /// from a leaf to its children.
+    pub fn timestamp(&self) -> Option<HybridTimestamp> {
+        self.block_header.timestamp()
+    }
 
     /// Get a mutable reference to the block header contained in this leaf.
     pub fn block_header_mut(&mut self) -> &mut <TYPES as NodeType>::BlockHeader {
@@ -964,13 +1114,7 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
 
 impl<TYPES: NodeType> Committable for Leaf2<TYPES> {
     fn commit(&self) -> committable::Commitment<Self> {
-        RawCommitmentBuilder::new("leaf commitment")
-            .u64_field("view number", *self.view_number)
-            .field("parent leaf commitment", self.parent_commitment)
-            .field("block header", self.block_header.commit())
-            .field("justify qc", self.justify_qc.commit())
-            .optional("upgrade certificate", &self.upgrade_certificate)
-            .finalize()
+        TYPES::CommitmentScheme::leaf(self)
     }
 }
 
@@ -1008,6 +1152,7 @@ impl<TYPES: NodeType> PartialEq for Leaf2<TYPES> {
             view_change_evidence,
             drb_seed,
             drb_result,
+            epoch_checkpoint,
         } = self;
 
         *view_number == other.view_number
@@ -1019,6 +1164,7 @@ impl<TYPES: NodeType> PartialEq for Leaf2<TYPES> {
             && *view_change_evidence == other.view_change_evidence
             && *drb_seed == other.drb_seed
             && *drb_result == other.drb_result
+            && *epoch_checkpoint == other.epoch_checkpoint
     }
 }
 
@@ -1383,6 +1529,8 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
             view_change_evidence,
             drb_seed,
             drb_result,
+            high_qc_proof: _,
+            epoch_checkpoint,
         } = quorum_proposal;
 
         Self {
@@ -1399,6 +1547,7 @@ impl<TYPES: NodeType> Leaf2<TYPES> {
             view_change_evidence: view_change_evidence.clone(),
             drb_seed: *drb_seed,
             drb_result: *drb_result,
+            epoch_checkpoint: epoch_checkpoint.clone(),
         }
     }
 }
@@ -28,7 +28,7 @@ pub const INITIAL_DRB_SEED_INPUT: [u8; 32] = [0; 32];
 /// DRB result for epoch 1 and 2.
 pub const INITIAL_DRB_RESULT: [u8; 32] = [0; 32];
 
-/// Alias for DRB seed input for `compute_drb_result`, serialized from the QC signature.
+/// Alias for DRB seed input for `compute_drb_result`, the beacon output of a QC.
 pub type DrbSeedInput = [u8; 32];
 
 /// Alias for DRB result from `compute_drb_result`.
@@ -50,7 +50,7 @@ pub fn difficulty_level() -> u64 {
 /// This is to be started two epochs in advance and spawned in a non-blocking thread.
 ///
 /// # Arguments
-/// * `drb_seed_input` - Serialized QC signature.
+/// * `drb_seed_input` - Beacon output of the QC, see [`crate::beacon::beacon_output`].
 #[must_use]
 pub fn compute_drb_result<TYPES: NodeType>(drb_seed_input: DrbSeedInput) -> DrbResult {
     let mut hash = drb_seed_input.to_vec();
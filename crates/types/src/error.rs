@@ -7,7 +7,8 @@
 //! Error type for `HotShot`
 //!
 //! This module provides [`HotShotError`], which is an enum representing possible faults that can
-//! occur while interacting with this crate.
+//! occur while interacting with this crate, and [`ConsensusError`], the faults consensus itself
+//! runs into, each with the [`Severity`] that says how much of the protocol it stops.
 
 use committable::Commitment;
 use serde::{Deserialize, Serialize};
@@ -35,6 +36,10 @@ pub enum HotShotError<TYPES: NodeType> {
     #[error("Failed to deserialize: {0}")]
     FailedToDeserialize(String),
 
+    /// The application rejected a transaction
+    #[error("Transaction rejected: {0}")]
+    InvalidTransaction(String),
+
     /// The view timed out
     #[error("View {view_number} timed out: {state:?}")]
     ViewTimedOut {
@@ -43,6 +48,73 @@ pub enum HotShotError<TYPES: NodeType> {
         /// The state that the round was in when it timed out
         state: RoundTimedoutState,
     },
+
+    /// Consensus ran into a fault
+    #[error(transparent)]
+    Consensus(#[from] ConsensusError<TYPES>),
+}
+
+/// How much of the protocol an error stops
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Only the message being handled is dropped
+    Recoverable,
+    /// We cannot take part in the view, and have to wait for the next one
+    ViewFatal,
+    /// We cannot take part in consensus until the node is reconfigured
+    NodeFatal,
+}
+
+/// A fault consensus runs into while handling messages or electing leaders
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum ConsensusError<TYPES: NodeType> {
+    /// A message is for a view we have already left
+    #[error("Message for view {0} is stale")]
+    StaleMessage(TYPES::View),
+
+    /// A message is not signed by the node it claims to be from
+    #[error("Message for view {0} has an invalid signature")]
+    InvalidSignature(TYPES::View),
+
+    /// We do not have the stake table of an epoch yet
+    #[error("No stake table for epoch {0} yet")]
+    MissingStakeTable(TYPES::Epoch),
+
+    /// We do not have the DRB result which picks the leaders of an epoch yet
+    #[error("No DRB result for epoch {0} yet")]
+    MissingDrbResult(TYPES::Epoch),
+
+    /// No node in the stake table of an epoch may lead
+    #[error("No eligible leaders in epoch {0}")]
+    NoEligibleLeaders(TYPES::Epoch),
+}
+
+impl<TYPES: NodeType> ConsensusError<TYPES> {
+    /// How much of the protocol this error stops
+    #[must_use]
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::StaleMessage(_) | Self::InvalidSignature(_) => Severity::Recoverable,
+            Self::MissingStakeTable(_) | Self::MissingDrbResult(_) => Severity::ViewFatal,
+            Self::NoEligibleLeaders(_) => Severity::NodeFatal,
+        }
+    }
+}
+
+impl<TYPES: NodeType> From<ConsensusError<TYPES>> for utils::anytrace::Error {
+    fn from(error: ConsensusError<TYPES>) -> Self {
+        let level = match error.severity() {
+            Severity::Recoverable => utils::anytrace::Level::Info,
+            Severity::ViewFatal => utils::anytrace::Level::Warn,
+            Severity::NodeFatal => utils::anytrace::Level::Error,
+        };
+
+        Self {
+            level,
+            message: error.to_string(),
+        }
+    }
 }
 
 /// Contains information about what the state of the hotshot-consensus was when a round timed out
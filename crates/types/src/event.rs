@@ -8,6 +8,7 @@
 
 use std::sync::Arc;
 
+use committable::Commitment;
 use serde::{Deserialize, Serialize};
 
 use crate::{
@@ -15,7 +16,7 @@ use crate::{
     error::HotShotError,
     message::Proposal,
     simple_certificate::QuorumCertificate2,
-    traits::{node_implementation::NodeType, ValidatedState},
+    traits::{node_implementation::NodeType, signature_key::ProposerId, ValidatedState},
 };
 
 /// A status event emitted by a `HotShot` instance
@@ -43,6 +44,8 @@ pub struct LeafInfo<TYPES: NodeType> {
     pub delta: Option<Arc<<<TYPES as NodeType>::ValidatedState as ValidatedState<TYPES>>::Delta>>,
     /// Optional VID share data.
     pub vid_share: Option<VidDisperseShare2<TYPES>>,
+    /// Transactions of this leaf's block that were rejected when applying it to the state.
+    pub rejected: Vec<RejectedTransaction<TYPES>>,
 }
 
 impl<TYPES: NodeType> LeafInfo<TYPES> {
@@ -53,15 +56,33 @@ impl<TYPES: NodeType> LeafInfo<TYPES> {
         delta: Option<Arc<<<TYPES as NodeType>::ValidatedState as ValidatedState<TYPES>>::Delta>>,
         vid_share: Option<VidDisperseShare2<TYPES>>,
     ) -> Self {
+        let rejected = delta
+            .as_ref()
+            .map(|delta| state.rejected_transactions(leaf.view_number(), delta))
+            .unwrap_or_default();
+
         Self {
             leaf,
             state,
             delta,
             vid_share,
+            rejected,
         }
     }
 }
 
+/// A transaction which was included in a block, but rejected by the application state.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct RejectedTransaction<TYPES: NodeType> {
+    /// Commitment of the rejected transaction
+    pub commitment: Commitment<TYPES::Transaction>,
+    /// View of the block the transaction was rejected from
+    pub view_number: TYPES::View,
+    /// Application-provided reason for the rejection
+    pub reason: String,
+}
+
 /// The chain of decided leaves with its corresponding state and VID info.
 pub type LeafChain<TYPES> = Vec<LeafInfo<TYPES>>;
 
@@ -141,6 +162,8 @@ pub enum EventType<TYPES: NodeType> {
     ViewTimeout {
         /// The view that timed out
         view_number: TYPES::View,
+        /// What we know about why it timed out
+        diagnostics: ViewTimeoutDiagnostics<TYPES>,
     },
     /// New transactions were received from the network
     /// or submitted to the network by us
@@ -153,24 +176,24 @@ pub enum EventType<TYPES: NodeType> {
     DaProposal {
         /// Contents of the proposal
         proposal: Proposal<TYPES, DaProposal2<TYPES>>,
-        /// Public key of the leader submitting the proposal
-        sender: TYPES::SignatureKey,
+        /// The leader submitting the proposal
+        sender: ProposerId<TYPES::SignatureKey>,
     },
     /// Quorum proposal was received from the network
     /// or submitted to the network by us
     QuorumProposal {
         /// Contents of the proposal
         proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
-        /// Public key of the leader submitting the proposal
-        sender: TYPES::SignatureKey,
+        /// The leader submitting the proposal
+        sender: ProposerId<TYPES::SignatureKey>,
     },
     /// Upgrade proposal was received from the network
     /// or submitted to the network by us
     UpgradeProposal {
         /// Contents of the proposal
         proposal: Proposal<TYPES, UpgradeProposal<TYPES>>,
-        /// Public key of the leader submitting the proposal
-        sender: TYPES::SignatureKey,
+        /// The leader submitting the proposal
+        sender: ProposerId<TYPES::SignatureKey>,
     },
 
     /// A message destined for external listeners was received
@@ -181,6 +204,40 @@ pub enum EventType<TYPES: NodeType> {
         data: Vec<u8>,
     },
 }
+/// A message we failed to send.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct SendFailure<TYPES: NodeType> {
+    /// The action the message carried, if it is one we track
+    pub action: Option<HotShotAction>,
+    /// The recipient of the message, if it was sent directly
+    pub recipient: Option<TYPES::SignatureKey>,
+    /// Why sending failed
+    pub error: String,
+}
+
+/// What a node knew about a view when it timed out, to tell why it did.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct ViewTimeoutDiagnostics<TYPES: NodeType> {
+    /// Leader of the view, if we could tell
+    pub leader: Option<TYPES::SignatureKey>,
+    /// Whether we had stake, and so could vote, in the view
+    pub had_stake: bool,
+    /// Whether we received a valid proposal for the view
+    pub proposal_received: bool,
+    /// Whether we received a DA certificate for the view
+    pub da_certificate_received: bool,
+    /// Whether we received our VID share for the view
+    pub vid_share_received: bool,
+    /// Whether we voted in the view
+    pub voted: bool,
+    /// Messages for the view we failed to send
+    pub send_failures: Vec<SendFailure<TYPES>>,
+    /// Peers we failed to send messages for the view to
+    pub unreachable_peers: Vec<TYPES::SignatureKey>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 /// A list of actions that we track for nodes
 pub enum HotShotAction {
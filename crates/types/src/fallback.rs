@@ -0,0 +1,126 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A fallback which elects leaders by coin after a run of failed views.
+//!
+//! Rotating leaders are known in advance, so an adversary which can delay the messages of chosen
+//! nodes can keep each of them from being heard until its view times out. Once `failed_views`
+//! views in a row have passed without a validated proposal, every node instead votes in the view it
+//! enters, and the aggregated signature of a quorum of those votes is hashed into a coin. The coin
+//! elects the leader of the next view among the signers of the certificate, weighted by stake, so
+//! that the leader is not known before a quorum has voted, and is a node which took part in the
+//! round. Rounds repeat every view until a proposal is validated, when the usual rotation resumes.
+//!
+//! This is not an asynchronous protocol, and it does not make consensus live without synchrony:
+//!
+//! - Rounds move on by view timeouts, so a round only elects a leader if a quorum's votes arrive
+//!   before the view times out.
+//! - The coin can be biased. The aggregated signature depends on which quorum signed, so whoever
+//!   assembles a certificate can pick among the quorums it holds votes from until it likes the
+//!   coin. A coin nobody can grind needs a unique threshold signature, which this crate lacks.
+//!
+//! What the fallback buys is that leaders are unknown until a quorum has voted in their round, so
+//! they cannot be singled out in advance.
+//!
+//! The fallback only decides who leads a view; proposals and votes follow the usual rules. Nodes
+//! which adopt different certificates for a round may disagree on the leader, which costs the view
+//! but never safety.
+
+use bincode::Options;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    simple_certificate::FallbackCertificate,
+    traits::{
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::{SignatureKey, StakeTableEntryType},
+    },
+    utils::bincode_opts,
+};
+
+/// Domain separator, so coins never collide with other hashes of certificate signatures.
+const COIN_DOMAIN: &[u8] = b"HotShot fallback coin";
+
+/// When the coin fallback takes over from the rotating leaders.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FallbackConfig {
+    /// Whether leaders are elected by coin after a run of failed views
+    pub enabled: bool,
+    /// Number of views in a row without a validated proposal which start the fallback
+    pub failed_views: u64,
+}
+
+impl Default for FallbackConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            failed_views: 3,
+        }
+    }
+}
+
+impl FallbackConfig {
+    /// Whether a view entered after `failed_views` failed views in a row is a fallback round.
+    #[must_use]
+    pub fn triggered(self, failed_views: u64) -> bool {
+        self.enabled && failed_views >= self.failed_views
+    }
+}
+
+/// The coin of the round certified by `cert`, or `None` if `cert` carries no signature.
+#[must_use]
+pub fn fallback_coin<TYPES: NodeType>(cert: &FallbackCertificate<TYPES>) -> Option<[u8; 32]> {
+    let signature = bincode_opts().serialize(cert.signatures.as_ref()?).ok()?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(COIN_DOMAIN);
+    hasher.update(cert.view_number.u64().to_le_bytes());
+    hasher.update(signature);
+    Some(hasher.finalize().into())
+}
+
+/// The leader of the view after the round certified by `cert`: a signer of `cert` which is
+/// eligible to lead that view, picked by the round's coin with probability proportional to its
+/// stake.
+#[must_use]
+pub fn elect_leader<TYPES: NodeType>(
+    cert: &FallbackCertificate<TYPES>,
+    membership: &TYPES::Membership,
+) -> Option<TYPES::SignatureKey> {
+    let coin = fallback_coin(cert)?;
+    let epoch = cert.data.epoch;
+    let view = cert.view_number + 1;
+    let (_, signed) = TYPES::SignatureKey::sig_proof(cert.signatures.as_ref()?);
+    let leaders = membership.committee_leaders(view, epoch);
+
+    let candidates: Vec<_> = membership
+        .stake_table(epoch)
+        .iter()
+        .zip(signed.iter())
+        .filter(|(_, signed)| **signed)
+        .map(|(entry, _)| (TYPES::SignatureKey::public_key(entry), entry.stake()))
+        .filter(|(key, stake)| leaders.contains(key) && !stake.is_zero())
+        .collect();
+    let total = candidates.iter().fold(U256::zero(), |total, (_, stake)| {
+        total.saturating_add(*stake)
+    });
+    if total.is_zero() {
+        return None;
+    }
+
+    let mut ticket = U256::from_little_endian(&coin) % total;
+    for (key, stake) in candidates {
+        if ticket < stake {
+            return Some(key);
+        }
+        ticket -= stake;
+    }
+
+    None
+}
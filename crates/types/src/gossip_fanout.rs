@@ -0,0 +1,339 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Configuration and tuning of the gossip fanout.
+//!
+//! Gossip reaches every node through a mesh: each node relays the messages it sees to its mesh
+//! peers. A wider mesh propagates messages in fewer hops, but every message then costs more
+//! bandwidth. [`GossipFanoutConfig`] sets the mesh parameters, which [`GossipFanoutConfig::mesh`]
+//! fits to the size of the network, since a node can't have more mesh peers than there are other
+//! nodes.
+//!
+//! With adaptive tuning, a [`FanoutTuner`] watches how long after the start of a view its proposal
+//! reaches us. When proposals arrive late it widens the fanout, sending gossip to extra peers on
+//! top of the mesh, and narrows it again once they arrive well in time.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+/// Default target number of peers in the mesh.
+const DEFAULT_MESH_N: usize = 8;
+
+/// Default least number of peers in the mesh.
+const DEFAULT_MESH_N_LOW: usize = 6;
+
+/// Default largest number of peers in the mesh.
+const DEFAULT_MESH_N_HIGH: usize = 12;
+
+/// Default least number of outbound peers in the mesh.
+const DEFAULT_MESH_OUTBOUND_MIN: usize = 2;
+
+/// Default least number of peers to gossip message IDs to at each heartbeat.
+const DEFAULT_GOSSIP_LAZY: usize = 6;
+
+/// Default time after the start of a view by which its proposal should reach us, in
+/// milliseconds.
+const DEFAULT_TARGET_PROPOSAL_LATENCY_MS: u64 = 1_000;
+
+/// Default largest number of extra peers gossip is sent to on top of the mesh.
+const DEFAULT_MAX_EXTRA_FANOUT: usize = 4;
+
+/// Number of proposals the tuner waits for between two adjustments.
+const SAMPLES_PER_ADJUSTMENT: usize = 10;
+
+/// Settings of the gossip mesh, and of its adaptive tuning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GossipFanoutConfig {
+    /// Target number of peers in the mesh
+    #[serde(default = "default_mesh_n")]
+    pub mesh_n: usize,
+    /// Least number of peers in the mesh, below which we add peers to it
+    #[serde(default = "default_mesh_n_low")]
+    pub mesh_n_low: usize,
+    /// Largest number of peers in the mesh, above which we remove peers from it
+    #[serde(default = "default_mesh_n_high")]
+    pub mesh_n_high: usize,
+    /// Least number of peers in the mesh which we connected to, rather than they to us
+    #[serde(default = "default_mesh_outbound_min")]
+    pub mesh_outbound_min: usize,
+    /// Least number of peers outside the mesh to gossip message IDs to at each heartbeat
+    #[serde(default = "default_gossip_lazy")]
+    pub gossip_lazy: usize,
+    /// Whether to widen the fanout when proposals arrive late
+    #[serde(default)]
+    pub adaptive: bool,
+    /// Time after the start of a view by which its proposal should reach us, in milliseconds
+    #[serde(default = "default_target_proposal_latency_ms")]
+    pub target_proposal_latency_ms: u64,
+    /// Largest number of extra peers gossip is sent to on top of the mesh
+    #[serde(default = "default_max_extra_fanout")]
+    pub max_extra_fanout: usize,
+}
+
+/// Default value of [`GossipFanoutConfig::mesh_n`], for serde.
+fn default_mesh_n() -> usize {
+    DEFAULT_MESH_N
+}
+
+/// Default value of [`GossipFanoutConfig::mesh_n_low`], for serde.
+fn default_mesh_n_low() -> usize {
+    DEFAULT_MESH_N_LOW
+}
+
+/// Default value of [`GossipFanoutConfig::mesh_n_high`], for serde.
+fn default_mesh_n_high() -> usize {
+    DEFAULT_MESH_N_HIGH
+}
+
+/// Default value of [`GossipFanoutConfig::mesh_outbound_min`], for serde.
+fn default_mesh_outbound_min() -> usize {
+    DEFAULT_MESH_OUTBOUND_MIN
+}
+
+/// Default value of [`GossipFanoutConfig::gossip_lazy`], for serde.
+fn default_gossip_lazy() -> usize {
+    DEFAULT_GOSSIP_LAZY
+}
+
+/// Default value of [`GossipFanoutConfig::target_proposal_latency_ms`], for serde.
+fn default_target_proposal_latency_ms() -> u64 {
+    DEFAULT_TARGET_PROPOSAL_LATENCY_MS
+}
+
+/// Default value of [`GossipFanoutConfig::max_extra_fanout`], for serde.
+fn default_max_extra_fanout() -> usize {
+    DEFAULT_MAX_EXTRA_FANOUT
+}
+
+impl Default for GossipFanoutConfig {
+    fn default() -> Self {
+        Self {
+            mesh_n: DEFAULT_MESH_N,
+            mesh_n_low: DEFAULT_MESH_N_LOW,
+            mesh_n_high: DEFAULT_MESH_N_HIGH,
+            mesh_outbound_min: DEFAULT_MESH_OUTBOUND_MIN,
+            gossip_lazy: DEFAULT_GOSSIP_LAZY,
+            adaptive: false,
+            target_proposal_latency_ms: DEFAULT_TARGET_PROPOSAL_LATENCY_MS,
+            max_extra_fanout: DEFAULT_MAX_EXTRA_FANOUT,
+        }
+    }
+}
+
+/// Mesh parameters fitted to a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MeshParams {
+    /// Target number of peers in the mesh
+    pub mesh_n: usize,
+    /// Least number of peers in the mesh
+    pub mesh_n_low: usize,
+    /// Largest number of peers in the mesh
+    pub mesh_n_high: usize,
+    /// Least number of outbound peers in the mesh
+    pub mesh_outbound_min: usize,
+    /// Least number of peers to gossip message IDs to at each heartbeat
+    pub gossip_lazy: usize,
+}
+
+impl GossipFanoutConfig {
+    /// The mesh parameters for a network of `num_nodes` nodes, shrunk to the number of other
+    /// nodes on small networks. The result is always consistent, i.e.
+    /// `mesh_outbound_min <= mesh_n_low <= mesh_n <= mesh_n_high` with at most half of the target
+    /// mesh outbound.
+    #[must_use]
+    pub fn mesh(&self, num_nodes: usize) -> MeshParams {
+        let peers = num_nodes.saturating_sub(1).max(1);
+        let mesh_n = self.mesh_n.clamp(1, peers);
+        let mesh_n_low = self.mesh_n_low.clamp(1, mesh_n);
+        MeshParams {
+            mesh_n,
+            mesh_n_low,
+            mesh_n_high: self.mesh_n_high.clamp(mesh_n, peers),
+            mesh_outbound_min: self.mesh_outbound_min.min(mesh_n_low).min(mesh_n / 2),
+            gossip_lazy: self.gossip_lazy.min(peers),
+        }
+    }
+
+    /// Time after the start of a view by which its proposal should reach us.
+    #[must_use]
+    pub fn target_proposal_latency(&self) -> Duration {
+        Duration::from_millis(self.target_proposal_latency_ms)
+    }
+}
+
+/// Kinds of consensus messages, whose propagation is measured separately.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageClass {
+    /// Quorum proposals
+    Proposal,
+    /// Quorum votes and their aggregates
+    Vote,
+    /// Timeout votes
+    Timeout,
+    /// View sync votes and certificates
+    ViewSync,
+    /// DA proposals, votes and certificates
+    Da,
+    /// VID shares
+    Vid,
+    /// Every other consensus message
+    Other,
+}
+
+impl MessageClass {
+    /// All classes.
+    pub const ALL: [Self; 7] = [
+        Self::Proposal,
+        Self::Vote,
+        Self::Timeout,
+        Self::ViewSync,
+        Self::Da,
+        Self::Vid,
+        Self::Other,
+    ];
+
+    /// Name of the class, as a metrics label.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Proposal => "proposal",
+            Self::Vote => "vote",
+            Self::Timeout => "timeout",
+            Self::ViewSync => "view_sync",
+            Self::Da => "da",
+            Self::Vid => "vid",
+            Self::Other => "other",
+        }
+    }
+}
+
+/// Decides how many extra peers to gossip to, from how late proposals reach us.
+#[derive(Debug, Clone)]
+pub struct FanoutTuner {
+    /// Time after the start of a view by which its proposal should reach us
+    target: Duration,
+    /// Largest number of extra peers
+    max_extra: usize,
+    /// Latencies of the proposals since the last adjustment
+    samples: Vec<Duration>,
+    /// Current number of extra peers
+    extra: usize,
+}
+
+impl FanoutTuner {
+    /// A tuner for a network of `num_nodes` nodes, which never adds more extra peers than there
+    /// are nodes outside the mesh.
+    #[must_use]
+    pub fn new(config: &GossipFanoutConfig, num_nodes: usize) -> Self {
+        let outside_mesh = num_nodes
+            .saturating_sub(1)
+            .saturating_sub(config.mesh(num_nodes).mesh_n);
+        Self {
+            target: config.target_proposal_latency(),
+            max_extra: config.max_extra_fanout.min(outside_mesh),
+            samples: Vec::with_capacity(SAMPLES_PER_ADJUSTMENT),
+            extra: 0,
+        }
+    }
+
+    /// Current number of extra peers to gossip to.
+    #[must_use]
+    pub fn extra_fanout(&self) -> usize {
+        self.extra
+    }
+
+    /// Record that a proposal reached us `latency` after we entered its view.
+    ///
+    /// Every few proposals, the fanout grows by one peer if the median proposal was later than
+    /// the target, and shrinks by one if it arrived within half the target. Returns the new
+    /// number of extra peers when it changes.
+    pub fn observe_proposal(&mut self, latency: Duration) -> Option<usize> {
+        self.samples.push(latency);
+        if self.samples.len() < SAMPLES_PER_ADJUSTMENT {
+            return None;
+        }
+        self.samples.sort_unstable();
+        let median = self.samples[self.samples.len() / 2];
+        self.samples.clear();
+
+        let extra = if median > self.target {
+            (self.extra + 1).min(self.max_extra)
+        } else if median < self.target / 2 {
+            self.extra.saturating_sub(1)
+        } else {
+            self.extra
+        };
+        if extra == self.extra {
+            return None;
+        }
+        self.extra = extra;
+        Some(extra)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn mesh_shrinks_to_small_networks() {
+        let config = GossipFanoutConfig::default();
+        assert_eq!(
+            config.mesh(100),
+            MeshParams {
+                mesh_n: 8,
+                mesh_n_low: 6,
+                mesh_n_high: 12,
+                mesh_outbound_min: 2,
+                gossip_lazy: 6,
+            }
+        );
+        assert_eq!(
+            config.mesh(5),
+            MeshParams {
+                mesh_n: 4,
+                mesh_n_low: 4,
+                mesh_n_high: 4,
+                mesh_outbound_min: 2,
+                gossip_lazy: 4,
+            }
+        );
+        for num_nodes in 0..20 {
+            let mesh = config.mesh(num_nodes);
+            assert!(mesh.mesh_outbound_min <= mesh.mesh_n_low);
+            assert!(mesh.mesh_n_low <= mesh.mesh_n);
+            assert!(mesh.mesh_n <= mesh.mesh_n_high);
+            assert!(mesh.mesh_outbound_min * 2 <= mesh.mesh_n);
+        }
+    }
+
+    #[test]
+    fn tuner_follows_proposal_latency() {
+        let config = GossipFanoutConfig {
+            adaptive: true,
+            max_extra_fanout: 2,
+            ..GossipFanoutConfig::default()
+        };
+        let mut tuner = FanoutTuner::new(&config, 100);
+        let late = config.target_proposal_latency() * 2;
+        let early = config.target_proposal_latency() / 4;
+
+        let mut changes = vec![];
+        for latency in [late; 30].into_iter().chain([early; 30]) {
+            changes.extend(tuner.observe_proposal(latency));
+        }
+        // Grows up to the bound, then shrinks back once proposals arrive early
+        assert_eq!(changes, [1, 2, 1, 0]);
+        assert_eq!(tuner.extra_fanout(), 0);
+
+        // No room for extra peers outside the mesh of a small network
+        let mut tuner = FanoutTuner::new(&config, 5);
+        for _ in 0..30 {
+            assert_eq!(tuner.observe_proposal(late), None);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Hybrid logical clock timestamps for blocks.
+//!
+//! A hybrid timestamp pairs a physical time with a logical counter. A leader stamps its block with
+//! its own clock, unless that would not be later than the parent block, in which case it keeps the
+//! parent's physical time and bumps the counter. Timestamps therefore strictly increase along the
+//! chain whatever the leaders' clocks say, stay close to wall-clock time, and order the same way on
+//! every node.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fmt::Display, time::Duration};
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+/// A hybrid logical clock timestamp. Timestamps are ordered by physical time, then by counter.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub struct HybridTimestamp {
+    /// Physical time, in unix nanoseconds
+    physical: u64,
+    /// Counter which orders timestamps with the same physical time
+    logical: u32,
+}
+
+impl HybridTimestamp {
+    /// The timestamp with the given physical time, in unix nanoseconds, and counter
+    #[must_use]
+    pub const fn new(physical: u64, logical: u32) -> Self {
+        Self { physical, logical }
+    }
+
+    /// The current wall-clock time
+    #[must_use]
+    pub fn now() -> Self {
+        Self::new(wall_clock_nanos(), 0)
+    }
+
+    /// The timestamp of a block extending a block stamped `parent`, given our clock reads `now`
+    /// unix nanoseconds
+    #[must_use]
+    pub fn successor_at(parent: Self, now: u64) -> Self {
+        if now > parent.physical {
+            return Self::new(now, 0);
+        }
+
+        match parent.logical.checked_add(1) {
+            Some(logical) => Self::new(parent.physical, logical),
+            None => Self::new(parent.physical.saturating_add(1), 0),
+        }
+    }
+
+    /// The timestamp of a block extending a block stamped `parent`, using the local clock
+    #[must_use]
+    pub fn successor(parent: Self) -> Self {
+        Self::successor_at(parent, wall_clock_nanos())
+    }
+
+    /// Physical time, in unix nanoseconds
+    #[must_use]
+    pub fn physical(&self) -> u64 {
+        self.physical
+    }
+
+    /// Physical time, in whole unix seconds
+    #[must_use]
+    pub fn unix_seconds(&self) -> u64 {
+        self.physical / 1_000_000_000
+    }
+
+    /// Counter which orders timestamps with the same physical time
+    #[must_use]
+    pub fn logical(&self) -> u32 {
+        self.logical
+    }
+
+    /// How far the physical time of this timestamp is ahead of `now` unix nanoseconds
+    #[must_use]
+    pub fn ahead_of(&self, now: u64) -> Duration {
+        Duration::from_nanos(self.physical.saturating_sub(now))
+    }
+}
+
+impl Display for HybridTimestamp {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.physical, self.logical)
+    }
+}
+
+/// The local clock, in unix nanoseconds
+#[must_use]
+pub fn wall_clock_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| {
+            u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX)
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn successor_is_monotone() {
+        let parent = HybridTimestamp::new(100, 3);
+
+        // Our clock is ahead of the parent: use it
+        assert_eq!(
+            HybridTimestamp::successor_at(parent, 200),
+            HybridTimestamp::new(200, 0)
+        );
+
+        // Our clock is behind the parent: bump the counter instead
+        assert_eq!(
+            HybridTimestamp::successor_at(parent, 50),
+            HybridTimestamp::new(100, 4)
+        );
+        assert_eq!(
+            HybridTimestamp::successor_at(parent, 100),
+            HybridTimestamp::new(100, 4)
+        );
+
+        let full = HybridTimestamp::new(100, u32::MAX);
+        assert_eq!(
+            HybridTimestamp::successor_at(full, 100),
+            HybridTimestamp::new(101, 0)
+        );
+
+        for now in [0, 100, 101, 1000] {
+            assert!(HybridTimestamp::successor_at(parent, now) > parent);
+        }
+    }
+}
@@ -10,8 +10,25 @@ use url::Url;
 use vec1::Vec1;
 
 use crate::{
-    constants::REQUEST_DATA_DELAY, traits::signature_key::SignatureKey,
-    upgrade_config::UpgradeConfig, HotShotConfig, PeerConfig, ValidatorConfig,
+    anti_entropy::AntiEntropyConfig,
+    block_limits::BlockLimits,
+    channels::ChannelConfig,
+    consensus::CommitRule,
+    constants::REQUEST_DATA_DELAY,
+    dag_mempool::DagMempoolConfig,
+    fallback::FallbackConfig,
+    gossip_fanout::GossipFanoutConfig,
+    mempool::MempoolGossipConfig,
+    participation::JailConfig,
+    payload_stream::PayloadStreamConfig,
+    stake_table::{BondingCurve, LeaderConstraint},
+    timestamp_rules::TimestampRules,
+    traits::{signature_key::SignatureKey, signer::RemoteSignerConfig},
+    upgrade_config::UpgradeConfig,
+    view_timing::Pacing,
+    vote_aggregation::AggregationConfig,
+    workers::WorkerConfig,
+    HotShotConfig, NodeRole, PeerConfig, ValidatorConfig,
 };
 
 /// Default builder URL, used as placeholder
@@ -55,6 +72,69 @@ pub struct HotShotConfigFile<KEY: SignatureKey> {
     pub upgrade: UpgradeConfig,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// Per-block resource limits
+    #[serde(default)]
+    pub block_limits: BlockLimits,
+    /// Proposal timestamp validation rules
+    #[serde(default)]
+    pub timestamp_rules: TimestampRules,
+    /// Transaction gossip between nodes
+    #[serde(default)]
+    pub mempool_gossip: MempoolGossipConfig,
+    /// Size of the gossip mesh, and its adaptive tuning
+    #[serde(default)]
+    pub gossip_fanout: GossipFanoutConfig,
+    /// Periodic reconciliation of recent proposals and votes between peers
+    #[serde(default)]
+    pub anti_entropy: AntiEntropyConfig,
+    /// The part this node plays in the network
+    #[serde(default)]
+    pub role: NodeRole,
+    /// Public keys of the archival nodes
+    #[serde(default)]
+    pub archival_nodes: Vec<KEY>,
+    /// Capacities and overflow policy of internal channels
+    #[serde(default)]
+    pub channels: ChannelConfig,
+    /// Sizes of the worker pools
+    #[serde(default)]
+    pub workers: WorkerConfig,
+    /// Streaming of the payloads of the DA proposals we send
+    #[serde(default)]
+    pub payload_streaming: PayloadStreamConfig,
+    /// How stake is weighted by the time it has been bonded
+    #[serde(default)]
+    pub bonding_curve: BondingCurve,
+    /// Attribute which consecutive leaders must not share
+    #[serde(default)]
+    pub leader_constraint: LeaderConstraint,
+    /// Weighted stake below which a node is left out of the committees
+    #[serde(default)]
+    pub min_stake: u64,
+    /// Identifier of the chain, which votes are bound to
+    #[serde(default)]
+    pub chain_id: u64,
+    /// When validators which miss certificates are kept from leading
+    #[serde(default)]
+    pub jail: JailConfig,
+    /// The rule by which leaves are decided
+    #[serde(default)]
+    pub commit_rule: CommitRule,
+    /// When leaders send their proposals
+    #[serde(default)]
+    pub pacing: Pacing,
+    /// Dissemination of transactions in certified batches
+    #[serde(default)]
+    pub dag_mempool: DagMempoolConfig,
+    /// Election of leaders by a common coin after a run of failed views
+    #[serde(default)]
+    pub fallback: FallbackConfig,
+    /// Aggregation of quorum votes by a few nodes before they reach the leader
+    #[serde(default)]
+    pub aggregation: AggregationConfig,
+    /// Signing service holding our consensus key
+    #[serde(default)]
+    pub remote_signer: Option<RemoteSignerConfig>,
 }
 
 impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
@@ -83,6 +163,27 @@ impl<KEY: SignatureKey> From<HotShotConfigFile<KEY>> for HotShotConfig<KEY> {
             start_voting_time: val.upgrade.start_voting_time,
             stop_voting_time: val.upgrade.stop_voting_time,
             epoch_height: val.epoch_height,
+            block_limits: val.block_limits,
+            timestamp_rules: val.timestamp_rules,
+            mempool_gossip: val.mempool_gossip,
+            gossip_fanout: val.gossip_fanout,
+            anti_entropy: val.anti_entropy,
+            role: val.role,
+            archival_nodes: val.archival_nodes,
+            channels: val.channels,
+            workers: val.workers,
+            payload_streaming: val.payload_streaming,
+            bonding_curve: val.bonding_curve,
+            leader_constraint: val.leader_constraint,
+            min_stake: val.min_stake,
+            chain_id: val.chain_id,
+            jail: val.jail,
+            commit_rule: val.commit_rule,
+            pacing: val.pacing,
+            dag_mempool: val.dag_mempool,
+            fallback: val.fallback,
+            aggregation: val.aggregation,
+            remote_signer: val.remote_signer,
         }
     }
 }
@@ -128,6 +229,27 @@ impl<KEY: SignatureKey> HotShotConfigFile<KEY> {
             builder_urls: default_builder_urls(),
             upgrade: UpgradeConfig::default(),
             epoch_height: 0,
+            block_limits: BlockLimits::default(),
+            timestamp_rules: TimestampRules::default(),
+            mempool_gossip: MempoolGossipConfig::default(),
+            gossip_fanout: GossipFanoutConfig::default(),
+            anti_entropy: AntiEntropyConfig::default(),
+            role: NodeRole::default(),
+            archival_nodes: Vec::new(),
+            channels: ChannelConfig::default(),
+            workers: WorkerConfig::default(),
+            payload_streaming: PayloadStreamConfig::default(),
+            bonding_curve: BondingCurve::default(),
+            leader_constraint: LeaderConstraint::default(),
+            min_stake: 0,
+            chain_id: 0,
+            jail: JailConfig::default(),
+            commit_rule: CommitRule::default(),
+            pacing: Pacing::default(),
+            dag_mempool: DagMempoolConfig::default(),
+            fallback: FallbackConfig::default(),
+            aggregation: AggregationConfig::default(),
+            remote_signer: None,
         }
     }
 }
@@ -0,0 +1,461 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Generation of validator keys, and password-encrypted keystore files holding them.
+//!
+//! A [`Keystore`] holds a validator's consensus signing key and state signing key, encrypted
+//! with ChaCha20-Poly1305 under a key derived from a password with PBKDF2-HMAC-SHA256. Its
+//! `crypto` section is laid out as in an EIP-2335 keystore. The cipher authenticates the
+//! ciphertext, so a wrong password or a tampered file is detected before any key is parsed.
+//! Keystores derived with fewer than [`MIN_KDF_ITERATIONS`] iterations are neither written nor
+//! read. The public keys are kept in the clear, so that stake-table entries can be derived from a
+//! keystore without its password.
+//!
+//! Keys derived from a seed and a node index, as with
+//! [`ValidatorConfig::generated_from_seed_indexed`], are meant for tests, where every node can
+//! derive everyone's keys. Real deployments generate keys with [`ValidatorConfig::generate`],
+//! store them with [`Keystore::encrypt`] and load them at startup with
+//! [`ValidatorConfig::from_keystore`].
+
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use ark_serialize::{CanonicalDeserialize, CanonicalSerialize};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use pbkdf2::pbkdf2_hmac;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use thiserror::Error;
+
+use crate::{
+    light_client::{StateKeyPair, StateSignKey, StateVerKey},
+    stake_table::NodeMetadata,
+    traits::signature_key::{PrivateSignatureKey, SignatureKey},
+    PeerConfig, ValidatorConfig,
+};
+
+/// Version of the keystore format written by this module
+const KEYSTORE_VERSION: u32 = 2;
+
+/// The fewest PBKDF2 iterations a keystore is written or read with. Fewer would make guessing the
+/// password too cheap for anyone holding the file.
+pub const MIN_KDF_ITERATIONS: u32 = 100_000;
+
+/// Name of the key derivation function, as stored
+const KDF_FUNCTION: &str = "pbkdf2";
+
+/// Name of the pseudo-random function of the key derivation, as stored
+const KDF_PRF: &str = "hmac-sha256";
+
+/// Length of the derived key, which is the key of the cipher
+const KDF_KEY_LENGTH: u32 = 32;
+
+/// Name of the cipher, as stored
+const CIPHER_FUNCTION: &str = "chacha20-poly1305";
+
+/// Errors reading or decrypting a keystore.
+#[derive(Debug, Error)]
+pub enum KeystoreError {
+    /// The keystore file could not be read or written
+    #[error("Keystore I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    /// The keystore file is not valid JSON of a keystore
+    #[error("Malformed keystore: {0}")]
+    Format(#[from] serde_json::Error),
+    /// The keystore was written by an unknown version of this module
+    #[error("Unsupported keystore version {0}")]
+    UnsupportedVersion(u32),
+    /// The keystore uses a key derivation or cipher this module does not support
+    #[error("Unsupported keystore crypto: {0}")]
+    UnsupportedCrypto(String),
+    /// The key derivation has fewer than [`MIN_KDF_ITERATIONS`] iterations
+    #[error("Key derivation with {0} iterations is too weak")]
+    WeakKdf(u32),
+    /// The password is wrong, or the encrypted keys were tampered with
+    #[error("Wrong password, or the keystore has been tampered with")]
+    Authentication,
+    /// The decrypted keys are invalid, or do not match the public keys of the keystore
+    #[error("Corrupt keystore: {0}")]
+    Corrupt(String),
+}
+
+/// Parameters of the password-based key derivation, chosen when writing a keystore.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KdfParams {
+    /// Number of PBKDF2 iterations
+    pub iterations: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        Self {
+            iterations: 262_144,
+        }
+    }
+}
+
+/// The `crypto` section of a keystore, as in EIP-2335.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Crypto {
+    /// How the key of the cipher is derived from the password
+    kdf: Module<Pbkdf2Params>,
+    /// The encryption of the private keys
+    cipher: Module<CipherParams>,
+}
+
+/// A step of the `crypto` section: a function, its parameters and its output.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Module<P> {
+    /// Name of the function
+    function: String,
+    /// Parameters of the function
+    params: P,
+    /// Output of the function. Empty for the key derivation, whose output is secret
+    #[serde(with = "hex_bytes")]
+    message: Vec<u8>,
+}
+
+/// Parameters of PBKDF2, as stored.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct Pbkdf2Params {
+    /// Length of the derived key, in bytes
+    dklen: u32,
+    /// Number of iterations
+    c: u32,
+    /// The pseudo-random function
+    prf: String,
+    /// Salt of the derivation
+    #[serde(with = "hex_bytes")]
+    salt: Vec<u8>,
+}
+
+/// Parameters of the cipher, as stored.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct CipherParams {
+    /// Nonce of the encryption
+    #[serde(with = "hex_bytes")]
+    nonce: Vec<u8>,
+}
+
+/// A validator's keys, encrypted under a password.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct Keystore<KEY: SignatureKey> {
+    /// Version of the keystore format
+    version: u32,
+    /// The consensus public key
+    public_key: KEY,
+    /// The state verification key
+    state_ver_key: StateVerKey,
+    /// The encrypted private keys, and how to decrypt them
+    crypto: Crypto,
+}
+
+impl<KEY: SignatureKey> Keystore<KEY> {
+    /// Encrypt the keys of `config` under `password`. Its stake and DA membership are not stored.
+    ///
+    /// # Errors
+    /// If `params` has fewer than [`MIN_KDF_ITERATIONS`] iterations.
+    ///
+    /// # Panics
+    /// If the state signing key cannot be serialized, which it always can.
+    pub fn encrypt(
+        config: &ValidatorConfig<KEY>,
+        password: &str,
+        params: KdfParams,
+    ) -> Result<Self, KeystoreError> {
+        if params.iterations < MIN_KDF_ITERATIONS {
+            return Err(KeystoreError::WeakKdf(params.iterations));
+        }
+        let kdf = Pbkdf2Params {
+            dklen: KDF_KEY_LENGTH,
+            c: params.iterations,
+            prf: KDF_PRF.to_string(),
+            salt: rand::random::<[u8; 32]>().to_vec(),
+        };
+        let nonce: [u8; 12] = rand::random();
+
+        let private_key = config.private_key.to_bytes();
+        let mut plaintext = Vec::new();
+        plaintext.extend_from_slice(
+            &u32::try_from(private_key.len())
+                .expect("Private keys are short")
+                .to_be_bytes(),
+        );
+        plaintext.extend_from_slice(&private_key);
+        config
+            .state_key_pair
+            .sign_key_ref()
+            .serialize_compressed(&mut plaintext)
+            .expect("Serializing to a vector cannot fail");
+
+        let ciphertext = ChaCha20Poly1305::new(&derive_key(password, &kdf))
+            .encrypt(&Nonce::from(nonce), plaintext.as_slice())
+            .expect("Keys are far shorter than the cipher's limit");
+
+        Ok(Self {
+            version: KEYSTORE_VERSION,
+            public_key: config.public_key.clone(),
+            state_ver_key: config.state_key_pair.ver_key(),
+            crypto: Crypto {
+                kdf: Module {
+                    function: KDF_FUNCTION.to_string(),
+                    params: kdf,
+                    message: Vec::new(),
+                },
+                cipher: Module {
+                    function: CIPHER_FUNCTION.to_string(),
+                    params: CipherParams {
+                        nonce: nonce.to_vec(),
+                    },
+                    message: ciphertext,
+                },
+            },
+        })
+    }
+
+    /// Decrypt the consensus private key and state key pair with `password`.
+    ///
+    /// # Errors
+    /// If the password is wrong, or the keystore is unsupported, too weakly derived or corrupt.
+    pub fn decrypt(
+        &self,
+        password: &str,
+    ) -> Result<(KEY::PrivateKey, StateKeyPair), KeystoreError> {
+        if self.version != KEYSTORE_VERSION {
+            return Err(KeystoreError::UnsupportedVersion(self.version));
+        }
+
+        let Crypto { kdf, cipher } = &self.crypto;
+        if kdf.function != KDF_FUNCTION
+            || kdf.params.prf != KDF_PRF
+            || kdf.params.dklen != KDF_KEY_LENGTH
+        {
+            return Err(KeystoreError::UnsupportedCrypto(format!(
+                "{} with {} to {} bytes",
+                kdf.function, kdf.params.prf, kdf.params.dklen
+            )));
+        }
+        if kdf.params.c < MIN_KDF_ITERATIONS {
+            return Err(KeystoreError::WeakKdf(kdf.params.c));
+        }
+        if cipher.function != CIPHER_FUNCTION {
+            return Err(KeystoreError::UnsupportedCrypto(cipher.function.clone()));
+        }
+        let nonce = <[u8; 12]>::try_from(cipher.params.nonce.as_slice())
+            .map_err(|_| KeystoreError::Corrupt("the nonce is not 12 bytes".to_string()))?;
+
+        let plaintext = ChaCha20Poly1305::new(&derive_key(password, &kdf.params))
+            .decrypt(&Nonce::from(nonce), cipher.message.as_slice())
+            .map_err(|_| KeystoreError::Authentication)?;
+        let corrupt = |e: &dyn std::fmt::Display| KeystoreError::Corrupt(e.to_string());
+        let (length, rest) = plaintext
+            .split_first_chunk::<4>()
+            .ok_or_else(|| corrupt(&"truncated keys"))?;
+        let length = usize::try_from(u32::from_be_bytes(*length)).map_err(|e| corrupt(&e))?;
+        if rest.len() < length {
+            return Err(corrupt(&"truncated consensus key"));
+        }
+        let (private_key, state_sign_key) = rest.split_at(length);
+        let private_key = KEY::PrivateKey::from_bytes(private_key).map_err(|e| corrupt(&e))?;
+        let state_sign_key =
+            StateSignKey::deserialize_compressed(state_sign_key).map_err(|e| corrupt(&e))?;
+        let state_key_pair = StateKeyPair::from_sign_key(state_sign_key);
+
+        if KEY::from_private(&private_key) != self.public_key {
+            return Err(corrupt(&"consensus key does not match the public key"));
+        }
+        if state_key_pair.ver_key() != self.state_ver_key {
+            return Err(corrupt(&"state key does not match the verification key"));
+        }
+
+        Ok((private_key, state_key_pair))
+    }
+
+    /// The consensus public key, readable without the password.
+    #[must_use]
+    pub fn public_key(&self) -> &KEY {
+        &self.public_key
+    }
+
+    /// The stake-table entry of this validator with `stake_value`, derived without the password.
+    #[must_use]
+    pub fn peer_config(&self, stake_value: u64) -> PeerConfig<KEY> {
+        PeerConfig {
+            stake_table_entry: self.public_key.stake_table_entry(stake_value),
+            state_ver_key: self.state_ver_key.clone(),
+            bonded_since: 0,
+            metadata: NodeMetadata::default(),
+        }
+    }
+
+    /// Read a keystore from `path`.
+    ///
+    /// # Errors
+    /// If the file cannot be read or is not a keystore.
+    pub fn read(path: &Path) -> Result<Self, KeystoreError> {
+        Ok(serde_json::from_slice(&fs::read(path)?)?)
+    }
+
+    /// Write the keystore to a new file at `path`, readable only by its owner. An existing file
+    /// is never overwritten.
+    ///
+    /// # Errors
+    /// If the file exists or cannot be written.
+    pub fn write(&self, path: &Path) -> Result<(), KeystoreError> {
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+        let mut file = options.open(path)?;
+        file.write_all(&serde_json::to_vec_pretty(self)?)?;
+        file.sync_all()?;
+        Ok(())
+    }
+}
+
+impl<KEY: SignatureKey> ValidatorConfig<KEY> {
+    /// Generate a validator with fresh random keys.
+    #[must_use]
+    pub fn generate(stake_value: u64, is_da: bool) -> Self {
+        let (public_key, private_key) = KEY::generated_from_seed_indexed(rand::random(), 0);
+        Self {
+            public_key,
+            private_key,
+            stake_value,
+            state_key_pair: StateKeyPair::generate(),
+            is_da,
+        }
+    }
+
+    /// Load a validator's keys from the keystore at `path`.
+    ///
+    /// # Errors
+    /// If the keystore cannot be read or decrypted with `password`.
+    pub fn from_keystore(
+        path: &Path,
+        password: &str,
+        stake_value: u64,
+        is_da: bool,
+    ) -> Result<Self, KeystoreError> {
+        let keystore = Keystore::<KEY>::read(path)?;
+        let (private_key, state_key_pair) = keystore.decrypt(password)?;
+        Ok(Self {
+            public_key: keystore.public_key,
+            private_key,
+            stake_value,
+            state_key_pair,
+            is_da,
+        })
+    }
+}
+
+/// Derive the encryption key from `password` as `kdf` describes.
+fn derive_key(password: &str, kdf: &Pbkdf2Params) -> Key {
+    let mut key = Key::default();
+    pbkdf2_hmac::<Sha256>(password.as_bytes(), &kdf.salt, kdf.c, &mut key);
+    key
+}
+
+/// Serde of byte strings as lowercase hex.
+mod hex_bytes {
+    use std::fmt::Write;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    /// Serialize `bytes` as hex.
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hex = String::with_capacity(2 * bytes.len());
+        for byte in bytes {
+            let _ = write!(hex, "{byte:02x}");
+        }
+        serializer.serialize_str(&hex)
+    }
+
+    /// Deserialize bytes from hex.
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| {
+                hex.get(i..i + 2)
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                    .ok_or_else(|| D::Error::custom("invalid hex digit"))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    /// The cheapest key derivation allowed, for tests
+    const TEST_KDF: KdfParams = KdfParams {
+        iterations: MIN_KDF_ITERATIONS,
+    };
+
+    #[test]
+    fn keystore_round_trips() {
+        let config = ValidatorConfig::<BLSPubKey>::generate(1, true);
+        let keystore = Keystore::encrypt(&config, "hunter2", TEST_KDF).unwrap();
+
+        let json = serde_json::to_string(&keystore).unwrap();
+        let keystore: Keystore<BLSPubKey> = serde_json::from_str(&json).unwrap();
+        assert_eq!(keystore.peer_config(1), config.public_config());
+
+        let (private_key, state_key_pair) = keystore.decrypt("hunter2").unwrap();
+        assert_eq!(private_key, config.private_key);
+        assert_eq!(state_key_pair.ver_key(), config.state_key_pair.ver_key());
+    }
+
+    #[test]
+    fn keystore_rejects_wrong_password_and_tampering() {
+        let config = ValidatorConfig::<BLSPubKey>::generate(1, false);
+        let mut keystore = Keystore::encrypt(&config, "hunter2", TEST_KDF).unwrap();
+        assert!(matches!(
+            keystore.decrypt("hunter3"),
+            Err(KeystoreError::Authentication)
+        ));
+
+        keystore.crypto.cipher.message[0] ^= 1;
+        assert!(matches!(
+            keystore.decrypt("hunter2"),
+            Err(KeystoreError::Authentication)
+        ));
+    }
+
+    #[test]
+    fn keystore_rejects_weak_kdf() {
+        let config = ValidatorConfig::<BLSPubKey>::generate(1, false);
+        let weak = KdfParams {
+            iterations: MIN_KDF_ITERATIONS - 1,
+        };
+        assert!(matches!(
+            Keystore::encrypt(&config, "hunter2", weak),
+            Err(KeystoreError::WeakKdf(_))
+        ));
+
+        // A keystore whose file was edited to a cheaper derivation is refused before deriving
+        let mut keystore = Keystore::encrypt(&config, "hunter2", TEST_KDF).unwrap();
+        keystore.crypto.kdf.params.c = 1;
+        assert!(matches!(
+            keystore.decrypt("hunter2"),
+            Err(KeystoreError::WeakKdf(1))
+        ));
+    }
+}
@@ -10,33 +10,95 @@ use std::{fmt::Debug, future::Future, num::NonZeroUsize, pin::Pin, time::Duratio
 use bincode::Options;
 use displaydoc::Display;
 use light_client::StateVerKey;
+use primitive_types::U256;
 use tracing::error;
-use traits::signature_key::SignatureKey;
+use traits::signature_key::{SignatureKey, StakeTableEntryType};
 use url::Url;
 use vec1::Vec1;
 
-use crate::utils::bincode_opts;
+use crate::{
+    anti_entropy::AntiEntropyConfig,
+    block_limits::BlockLimits,
+    channels::ChannelConfig,
+    consensus::CommitRule,
+    dag_mempool::DagMempoolConfig,
+    fallback::FallbackConfig,
+    gossip_fanout::GossipFanoutConfig,
+    mempool::MempoolGossipConfig,
+    participation::JailConfig,
+    payload_stream::PayloadStreamConfig,
+    stake_table::{BondingCurve, LeaderConstraint, NodeMetadata},
+    timestamp_rules::TimestampRules,
+    traits::{
+        election::StakeTableProvider, node_implementation::NodeType, signer::RemoteSignerConfig,
+    },
+    utils::bincode_opts,
+    view_timing::Pacing,
+    vote_aggregation::AggregationConfig,
+    workers::WorkerConfig,
+};
+/// Holds the anti-entropy reconciliation of recent consensus messages.
+pub mod anti_entropy;
+/// Holds the `proptest` strategies for the core types.
+#[cfg(feature = "proptest")]
+pub mod arbitrary;
+/// Holds the randomness beacon derived from quorum certificates.
+pub mod beacon;
+/// Holds the per-block resource limits enforced by leaders and DA members.
+pub mod block_limits;
 pub mod bundle;
+/// Holds the capacities and overflow policy of internal channels.
+pub mod channels;
+/// Holds compact checkpoints of the decided chain and the sinks which receive them.
+pub mod checkpoint;
 pub mod consensus;
 pub mod constants;
+/// Holds the batches and availability certificates of the Narwhal-style DAG mempool.
+pub mod dag_mempool;
 pub mod data;
 /// Holds the types and functions for DRB computation.
 pub mod drb;
 pub mod error;
 pub mod event;
+/// Holds the coin which elects leaders after a run of failed views.
+pub mod fallback;
+/// Holds the configuration and adaptive tuning of the gossip fanout.
+pub mod gossip_fanout;
+/// Holds the hybrid logical clock timestamps of blocks.
+pub mod hlc;
 /// Holds the configuration file specification for a HotShot node.
 pub mod hotshot_config_file;
+/// Holds the generation of validator keys and the encrypted keystore files holding them.
+pub mod keystore;
 pub mod light_client;
+/// Holds the configuration of transaction gossip between nodes.
+pub mod mempool;
+/// Holds a key-value application state committed to by a sparse Merkle trie.
+pub mod merkle_state;
 pub mod message;
 
 /// Holds the network configuration specification for HotShot nodes.
 pub mod network;
+/// Holds the Noise handshakes which end-to-end encrypt direct messages between consensus keys.
+pub mod noise;
+/// Holds the block, header and state types of an ordering-only node.
+pub mod ordering;
+/// Holds the tracking of validator participation in certificates, and jailing of offline ones.
+pub mod participation;
+/// Holds the streaming of large block payloads to the DA committee.
+pub mod payload_stream;
 pub mod qc;
+/// Holds the replay protection of signed network messages.
+pub mod replay;
 pub mod request_response;
+/// Holds the detection of safety violations and the handlers they are reported to.
+pub mod safety;
 pub mod signature_key;
 pub mod simple_certificate;
 pub mod simple_vote;
 pub mod stake_table;
+/// Holds the rules for validating proposal timestamps.
+pub mod timestamp_rules;
 pub mod traits;
 
 /// Holds the upgrade configuration specification for HotShot nodes.
@@ -44,8 +106,16 @@ pub mod upgrade_config;
 pub mod utils;
 /// Holds the validator configuration specification for HotShot nodes.
 pub mod validator_config;
+/// Holds the operations validators submit to join, change or leave the stake table.
+pub mod validator_ops;
 pub mod vid;
+/// Holds the timestamps of the phases of each view, and the pacing of proposals.
+pub mod view_timing;
 pub mod vote;
+/// Holds the aggregation of quorum votes by a few nodes on behalf of the leader.
+pub mod vote_aggregation;
+/// Holds the sizes of the worker pools which run heavyweight work off the consensus tasks.
+pub mod workers;
 
 /// Pinned future that is Send and Sync
 pub type BoxSyncFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + Sync + 'a>>;
@@ -105,6 +175,8 @@ impl<KEY: SignatureKey> ValidatorConfig<KEY> {
         PeerConfig {
             stake_table_entry: self.public_key.stake_table_entry(self.stake_value),
             state_ver_key: self.state_key_pair.0.ver_key(),
+            bonded_since: 0,
+            metadata: NodeMetadata::default(),
         }
     }
 }
@@ -123,9 +195,31 @@ pub struct PeerConfig<KEY: SignatureKey> {
     pub stake_table_entry: KEY::StakeTableEntry,
     /// the peer's state public key
     pub state_ver_key: StateVerKey,
+    /// The epoch since which the peer's stake has been bonded
+    #[serde(default)]
+    pub bonded_since: u64,
+    /// Where and by whom the peer is run
+    #[serde(default)]
+    pub metadata: NodeMetadata,
 }
 
 impl<KEY: SignatureKey> PeerConfig<KEY> {
+    /// This peer's config with its stake replaced by its effective weight in `epoch`, as given by
+    /// `curve` for the epochs the stake has been bonded.
+    #[must_use]
+    pub fn with_bonded_weight(&self, curve: BondingCurve, epoch: u64) -> Self {
+        let weight = curve.effective_weight(
+            self.stake_table_entry.stake(),
+            epoch.saturating_sub(self.bonded_since),
+        );
+        let stake_table_entry = KEY::public_key(&self.stake_table_entry)
+            .stake_table_entry(weight.min(U256::from(u64::MAX)).low_u64());
+        Self {
+            stake_table_entry,
+            ..self.clone()
+        }
+    }
+
     /// Serialize a peer's config to bytes
     pub fn to_bytes(config: &Self) -> Vec<u8> {
         let x = bincode_opts().serialize(config);
@@ -160,6 +254,18 @@ impl<KEY: SignatureKey> Default for PeerConfig<KEY> {
     }
 }
 
+/// The part a node plays in the network
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum NodeRole {
+    /// Takes part in consensus, if it has stake
+    #[default]
+    Validator,
+    /// Follows the chain without voting, and keeps all of its history to serve to others
+    Archival,
+}
+
 /// Holds configuration for a `HotShot`
 #[derive(Clone, derive_more::Debug, serde::Serialize, serde::Deserialize)]
 #[serde(bound(deserialize = ""))]
@@ -208,6 +314,70 @@ pub struct HotShotConfig<KEY: SignatureKey> {
     pub stop_voting_time: u64,
     /// Number of blocks in an epoch, zero means there are no epochs
     pub epoch_height: u64,
+    /// Resource limits every block must respect
+    #[serde(default)]
+    pub block_limits: BlockLimits,
+    /// Rules for validating the timestamps of proposed blocks
+    #[serde(default)]
+    pub timestamp_rules: TimestampRules,
+    /// Transaction gossip between nodes
+    #[serde(default)]
+    pub mempool_gossip: MempoolGossipConfig,
+    /// Size of the gossip mesh, and its adaptive tuning
+    #[serde(default)]
+    pub gossip_fanout: GossipFanoutConfig,
+    /// Periodic reconciliation of recent proposals and votes between peers
+    #[serde(default)]
+    pub anti_entropy: AntiEntropyConfig,
+    /// The part this node plays in the network
+    #[serde(default)]
+    pub role: NodeRole,
+    /// Public keys of the archival nodes, which receive DA proposals alongside the DA committee
+    #[serde(default)]
+    pub archival_nodes: Vec<KEY>,
+    /// Capacities and overflow policy of internal channels
+    #[serde(default)]
+    pub channels: ChannelConfig,
+    /// Sizes of the worker pools
+    #[serde(default)]
+    pub workers: WorkerConfig,
+    /// Streaming of the payloads of the DA proposals we send
+    #[serde(default)]
+    pub payload_streaming: PayloadStreamConfig,
+    /// How stake is weighted by the time it has been bonded
+    #[serde(default)]
+    pub bonding_curve: BondingCurve,
+    /// Attribute which consecutive leaders must not share
+    #[serde(default)]
+    pub leader_constraint: LeaderConstraint,
+    /// Weighted stake below which a node is left out of the committees, so that it can neither
+    /// vote nor lead
+    #[serde(default)]
+    pub min_stake: u64,
+    /// Identifier of the chain, which votes are bound to so they cannot be replayed on another
+    #[serde(default)]
+    pub chain_id: u64,
+    /// When validators which miss certificates are kept from leading
+    #[serde(default)]
+    pub jail: JailConfig,
+    /// The rule by which leaves are decided, which must be the same on every node
+    #[serde(default)]
+    pub commit_rule: CommitRule,
+    /// When leaders send their proposals
+    #[serde(default)]
+    pub pacing: Pacing,
+    /// Dissemination of transactions in certified batches, from which leaders build blocks
+    #[serde(default)]
+    pub dag_mempool: DagMempoolConfig,
+    /// Election of leaders by a common coin after a run of failed views
+    #[serde(default)]
+    pub fallback: FallbackConfig,
+    /// Aggregation of quorum votes by a few nodes before they reach the leader
+    #[serde(default)]
+    pub aggregation: AggregationConfig,
+    /// Signing service holding our consensus key, instead of signing with the key in memory
+    #[serde(default)]
+    pub remote_signer: Option<RemoteSignerConfig>,
 }
 
 impl<KEY: SignatureKey> HotShotConfig<KEY> {
@@ -222,4 +392,46 @@ impl<KEY: SignatureKey> HotShotConfig<KEY> {
         self.start_voting_time = 0;
         self.stop_voting_time = u64::MAX;
     }
+
+    /// The stake table and DA stake table of `epoch`, with every stake weighted by the
+    /// `bonding_curve` for the epochs it has been bonded, and the stake table ordered to satisfy
+    /// the `leader_constraint`.
+    ///
+    /// Nodes whose weighted stake is below `min_stake` are left out of both tables. They keep
+    /// their stake, but every election built from these tables denies them votes and leadership
+    /// alike, so dust accounts add nothing to a Sybil.
+    ///
+    /// Memberships should be built from these rather than from the configured tables, so that
+    /// elections and certificates agree on each node's weight. A membership built once for the
+    /// whole run must take the tables of the genesis epoch, which every node agrees on however
+    /// late it starts. For the weights to grow with bonding time, the config itself can be the
+    /// [`StakeTableProvider`] of an election, which then weighs stake anew each epoch.
+    #[must_use]
+    pub fn stake_tables(&self, epoch: u64) -> (Vec<PeerConfig<KEY>>, Vec<PeerConfig<KEY>>) {
+        let min_stake = U256::from(self.min_stake);
+        let weigh = |peers: &[PeerConfig<KEY>]| {
+            peers
+                .iter()
+                .map(|peer| peer.with_bonded_weight(self.bonding_curve, epoch))
+                .filter(|peer| peer.stake_table_entry.stake() >= min_stake)
+                .collect()
+        };
+        (
+            self.leader_constraint
+                .arrange(weigh(&self.known_nodes_with_stake)),
+            weigh(&self.known_da_nodes),
+        )
+    }
+}
+
+impl<TYPES: NodeType> StakeTableProvider<TYPES> for HotShotConfig<TYPES::SignatureKey> {
+    fn stake_tables(
+        &self,
+        epoch: TYPES::Epoch,
+    ) -> Option<(
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+    )> {
+        Some(HotShotConfig::stake_tables(self, *epoch))
+    }
 }
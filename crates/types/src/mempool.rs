@@ -0,0 +1,90 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Configuration of transaction gossip.
+//!
+//! Without gossip, a submitted transaction is only sent to the DA committee. With gossip, every
+//! node relays the transactions it hasn't seen before to all nodes, and nodes periodically
+//! exchange digests of their pending transactions to fetch the ones they missed. Any node can then
+//! accept submissions, and every node sees the union of all pending transactions.
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of transactions a peer may relay to us per second.
+const DEFAULT_PEER_RATE_LIMIT: u32 = 1_000;
+
+/// Default number of transactions a peer may relay to us in a burst.
+const DEFAULT_PEER_BURST: u32 = 5_000;
+
+/// Default number of views between two digest exchanges.
+const DEFAULT_RECONCILE_INTERVAL: u64 = 10;
+
+/// Default number of views a transaction is kept for reconciliation.
+const DEFAULT_PENDING_TTL: u64 = 100;
+
+/// Default maximum number of transactions kept for reconciliation.
+const DEFAULT_MAX_PENDING: usize = 10_000;
+
+/// Settings for transaction gossip between nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MempoolGossipConfig {
+    /// Whether transactions are gossiped to all nodes, rather than only sent to the DA committee
+    #[serde(default)]
+    pub enabled: bool,
+    /// Transactions per second each peer may relay to us; excess transactions are dropped
+    #[serde(default = "default_peer_rate_limit")]
+    pub peer_rate_limit: u32,
+    /// Transactions each peer may relay to us at once, before the rate limit applies
+    #[serde(default = "default_peer_burst")]
+    pub peer_burst: u32,
+    /// Views between two broadcasts of our pending transaction digest
+    #[serde(default = "default_reconcile_interval")]
+    pub reconcile_interval: u64,
+    /// Views a transaction is kept for reconciliation after we first saw it
+    #[serde(default = "default_pending_ttl")]
+    pub pending_ttl: u64,
+    /// Maximum number of transactions kept for reconciliation
+    #[serde(default = "default_max_pending")]
+    pub max_pending: usize,
+}
+
+/// Default value of [`MempoolGossipConfig::peer_rate_limit`], for serde.
+fn default_peer_rate_limit() -> u32 {
+    DEFAULT_PEER_RATE_LIMIT
+}
+
+/// Default value of [`MempoolGossipConfig::peer_burst`], for serde.
+fn default_peer_burst() -> u32 {
+    DEFAULT_PEER_BURST
+}
+
+/// Default value of [`MempoolGossipConfig::reconcile_interval`], for serde.
+fn default_reconcile_interval() -> u64 {
+    DEFAULT_RECONCILE_INTERVAL
+}
+
+/// Default value of [`MempoolGossipConfig::pending_ttl`], for serde.
+fn default_pending_ttl() -> u64 {
+    DEFAULT_PENDING_TTL
+}
+
+/// Default value of [`MempoolGossipConfig::max_pending`], for serde.
+fn default_max_pending() -> usize {
+    DEFAULT_MAX_PENDING
+}
+
+impl Default for MempoolGossipConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            peer_rate_limit: DEFAULT_PEER_RATE_LIMIT,
+            peer_burst: DEFAULT_PEER_BURST,
+            reconcile_interval: DEFAULT_RECONCILE_INTERVAL,
+            pending_ttl: DEFAULT_PENDING_TTL,
+            max_pending: DEFAULT_MAX_PENDING,
+        }
+    }
+}
@@ -0,0 +1,615 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! A key-value application state committed to by a sparse Merkle trie.
+//!
+//! [`MerkleState`] maps keys to values and commits to them by the root of a binary trie over the
+//! SHA-256 hashes of the keys. A subtree holding a single entry collapses into that entry, so
+//! proofs are as long as needed to tell the entry apart from its neighbours rather than 256
+//! levels. [`MerkleState::prove`] proves that a key holds a value, or that it holds none, against
+//! the root, which is what the state commits to and so what every leaf carrying the state commits
+//! to.
+//!
+//! Like any state which only sees block headers while voting, [`MerkleState`] advances by header
+//! during consensus. The application writes the entries of decided blocks with
+//! [`MerkleState::apply`], whose delta carries a proof for every write, so the root can be carried
+//! forward from the delta alone with [`IncrementalCommitment::append`].
+
+use std::{
+    collections::BTreeMap,
+    fmt::{Debug, Display},
+};
+
+use bincode::Options;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use vbs::version::Version;
+
+use crate::{
+    data::{BlockError, Leaf2},
+    traits::{
+        block_contents::BlockHeader,
+        node_implementation::NodeType,
+        states::{IncrementalCommitment, StateDelta, ValidatedState},
+    },
+    utils::bincode_opts,
+    vid::VidCommon,
+};
+
+/// A SHA-256 digest, and the path of a key through the trie
+type Digest256 = [u8; 32];
+
+/// Digest of an empty subtree
+const EMPTY_DIGEST: Digest256 = [0; 32];
+
+/// The most levels a proof can have, one per bit of a key path
+const MAX_DEPTH: usize = 256;
+
+/// Root of a [`MerkleState`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct MerkleRoot(pub [u8; 32]);
+
+impl Display for MerkleRoot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for byte in self.0 {
+            write!(f, "{byte:02x}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Errors verifying a [`MerkleProof`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Error)]
+pub enum MerkleProofError {
+    /// The proof is not shaped like a proof for the key
+    #[error("Proof is malformed for the key")]
+    Malformed,
+    /// The proof is for a different root
+    #[error("Proof does not match the root")]
+    RootMismatch,
+}
+
+/// A subtree of the trie, as seen from a proof
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+enum Subtree {
+    /// A subtree with no entries
+    Empty,
+    /// A subtree holding a single entry, with its digest
+    Leaf(Digest256),
+    /// A subtree holding several entries, with its digest
+    Inner(Digest256),
+}
+
+impl Subtree {
+    /// The subtree at `depth` holding the ordered `leaves`
+    fn of(leaves: &[(Digest256, Digest256)], depth: usize) -> Self {
+        match leaves {
+            [] => Self::Empty,
+            [(path, value)] => Self::Leaf(leaf_digest(path, value)),
+            _ => Self::Inner(subtree_digest(leaves, depth)),
+        }
+    }
+
+    /// Digest of the subtree
+    fn digest(self) -> Digest256 {
+        match self {
+            Self::Empty => EMPTY_DIGEST,
+            Self::Leaf(digest) | Self::Inner(digest) => digest,
+        }
+    }
+}
+
+/// Proof of the value a key holds, or that it holds none, in a [`MerkleState`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleProof<V> {
+    /// The value of the key, if it has one
+    value: Option<V>,
+    /// The entry the key's path ends at when the key has no value but shares its subtree with
+    /// another key: the other key's path and value digest
+    other: Option<(Digest256, Digest256)>,
+    /// The siblings along the key's path, from the root down
+    siblings: Vec<Subtree>,
+}
+
+impl<V: Serialize> MerkleProof<V> {
+    /// Check this proof for `key` against `root`, returning the value it proves the key holds.
+    ///
+    /// # Errors
+    /// If the proof does not prove anything about `key` under `root`.
+    pub fn verify<K: Serialize>(
+        &self,
+        root: &MerkleRoot,
+        key: &K,
+    ) -> Result<Option<&V>, MerkleProofError> {
+        self.check(root, &key_path(key))?;
+        Ok(self.value.as_ref())
+    }
+
+    /// Check that this proof is for the key at `path` and leads to `root`
+    fn check(&self, root: &MerkleRoot, path: &Digest256) -> Result<(), MerkleProofError> {
+        if self.siblings.len() > MAX_DEPTH {
+            return Err(MerkleProofError::Malformed);
+        }
+
+        let mut digest = match (&self.value, &self.other) {
+            (Some(value), None) => leaf_digest(path, &value_digest(value)),
+            (None, Some((other, value))) => {
+                // The other key must live in the subtree the key's path leads to
+                let depth = self.siblings.len();
+                if other == path || (0..depth).any(|d| bit(other, d) != bit(path, d)) {
+                    return Err(MerkleProofError::Malformed);
+                }
+                leaf_digest(other, value)
+            }
+            (None, None) => EMPTY_DIGEST,
+            (Some(_), Some(_)) => return Err(MerkleProofError::Malformed),
+        };
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            digest = if bit(path, depth) {
+                node_digest(&sibling.digest(), &digest)
+            } else {
+                node_digest(&digest, &sibling.digest())
+            };
+        }
+
+        if digest == root.0 {
+            Ok(())
+        } else {
+            Err(MerkleProofError::RootMismatch)
+        }
+    }
+
+    /// The root after setting the key at `path` to `value`, given this proof was checked for it.
+    ///
+    /// Only the subtree the path ends in changes, after which subtrees left holding a single
+    /// entry collapse into it on the way up.
+    fn root_after(&self, path: &Digest256, value: Option<&V>) -> MerkleRoot {
+        let mut leaves: Vec<_> = self
+            .other
+            .into_iter()
+            .chain(value.map(|value| (*path, value_digest(value))))
+            .collect();
+        leaves.sort_unstable();
+
+        let mut subtree = Subtree::of(&leaves, self.siblings.len());
+        for (depth, sibling) in self.siblings.iter().enumerate().rev() {
+            subtree = match (subtree, *sibling) {
+                (Subtree::Empty, sibling @ (Subtree::Empty | Subtree::Leaf(_))) => sibling,
+                (leaf @ Subtree::Leaf(_), Subtree::Empty) => leaf,
+                (subtree, sibling) => Subtree::Inner(if bit(path, depth) {
+                    node_digest(&sibling.digest(), &subtree.digest())
+                } else {
+                    node_digest(&subtree.digest(), &sibling.digest())
+                }),
+            };
+        }
+
+        MerkleRoot(subtree.digest())
+    }
+}
+
+/// The writes of a block to a [`MerkleState`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleDelta<K, V> {
+    /// Keys written, in order, with their new value or `None` if they were removed
+    pub writes: Vec<(K, Option<V>)>,
+    /// Proof of each written key against the root just before it was written
+    pub witnesses: Vec<MerkleProof<V>>,
+}
+
+impl<K, V> Default for MerkleDelta<K, V> {
+    fn default() -> Self {
+        Self {
+            writes: Vec::new(),
+            witnesses: Vec::new(),
+        }
+    }
+}
+
+impl<K: Serialize, V: Serialize> MerkleDelta<K, V> {
+    /// The root after applying these writes to the state with the given `root`, or `None` if
+    /// the witnesses are not for that state.
+    ///
+    /// This hashes along the path of each written key only, rather than the whole state.
+    #[must_use]
+    pub fn apply_to(&self, root: &MerkleRoot) -> Option<MerkleRoot> {
+        if self.writes.len() != self.witnesses.len() {
+            return None;
+        }
+
+        self.writes
+            .iter()
+            .zip(&self.witnesses)
+            .try_fold(*root, |root, ((key, value), witness)| {
+                let path = key_path(key);
+                witness.check(&root, &path).ok()?;
+                Some(witness.root_after(&path, value.as_ref()))
+            })
+    }
+}
+
+impl<K, V> StateDelta for MerkleDelta<K, V>
+where
+    K: Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+    V: Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+{
+}
+
+/// Key-value state committed to by a sparse Merkle trie.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MerkleState<K, V> {
+    /// Height of the last applied block
+    block_height: u64,
+    /// Entries by the path of their key
+    entries: BTreeMap<Digest256, (K, V)>,
+}
+
+impl<K, V> Default for MerkleState<K, V> {
+    fn default() -> Self {
+        Self {
+            block_height: 0,
+            entries: BTreeMap::new(),
+        }
+    }
+}
+
+impl<K: Serialize, V: Serialize + Clone> MerkleState<K, V> {
+    /// Height of the last applied block
+    #[must_use]
+    pub fn block_height(&self) -> u64 {
+        self.block_height
+    }
+
+    /// Number of keys with a value
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether no key has a value
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The value of `key`
+    #[must_use]
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.entries.get(&key_path(key)).map(|(_, value)| value)
+    }
+
+    /// Set the value of `key`, returning its previous value
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.entries
+            .insert(key_path(&key), (key, value))
+            .map(|(_, value)| value)
+    }
+
+    /// Remove the value of `key`, returning it
+    pub fn remove(&mut self, key: &K) -> Option<V> {
+        self.entries.remove(&key_path(key)).map(|(_, value)| value)
+    }
+
+    /// Apply the `writes` of a block in order, where `None` removes a key.
+    ///
+    /// The delta proves each write against the root before it, which means hashing the whole
+    /// state once per write here so that nobody else has to.
+    pub fn apply(&mut self, writes: impl IntoIterator<Item = (K, Option<V>)>) -> MerkleDelta<K, V>
+    where
+        K: Clone,
+    {
+        let writes: Vec<_> = writes.into_iter().collect();
+        let mut witnesses = Vec::with_capacity(writes.len());
+        for (key, value) in &writes {
+            witnesses.push(self.prove(key));
+            match value {
+                Some(value) => self.insert(key.clone(), value.clone()),
+                None => self.remove(key),
+            };
+        }
+
+        MerkleDelta { writes, witnesses }
+    }
+
+    /// Root of the trie, which the state commits to
+    #[must_use]
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot(subtree_digest(&self.leaves(), 0))
+    }
+
+    /// Prove the value of `key`, or that it has none, against [`MerkleState::root`].
+    ///
+    /// Digests are not cached, so this hashes the whole state.
+    #[must_use]
+    pub fn prove(&self, key: &K) -> MerkleProof<V> {
+        let path = key_path(key);
+        let leaves = self.leaves();
+        let mut subtree = &leaves[..];
+        let mut siblings = Vec::new();
+        while subtree.len() > 1 {
+            let depth = siblings.len();
+            let (left, right) = split(subtree, depth);
+            if bit(&path, depth) {
+                siblings.push(Subtree::of(left, depth + 1));
+                subtree = right;
+            } else {
+                siblings.push(Subtree::of(right, depth + 1));
+                subtree = left;
+            }
+        }
+
+        let other = match subtree {
+            [(other, value)] if *other != path => Some((*other, *value)),
+            _ => None,
+        };
+        MerkleProof {
+            value: self.get(key).cloned(),
+            other,
+            siblings,
+        }
+    }
+
+    /// Paths and value digests of all entries, ordered by path
+    fn leaves(&self) -> Vec<(Digest256, Digest256)> {
+        self.entries
+            .iter()
+            .map(|(path, (_, value))| (*path, value_digest(value)))
+            .collect()
+    }
+}
+
+impl<K: Serialize, V: Serialize + Clone> Committable for MerkleState<K, V> {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Merkle State Comm")
+            .u64_field("block_height", self.block_height)
+            .fixed_size_field("root", &self.root().0)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "MERKLE_STATE".to_string()
+    }
+}
+
+impl<TYPES, K, V> ValidatedState<TYPES> for MerkleState<K, V>
+where
+    TYPES: NodeType,
+    K: Clone + Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+    V: Clone + Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+{
+    type Error = BlockError;
+    type Instance = TYPES::InstanceState;
+    type Delta = MerkleDelta<K, V>;
+    type Time = TYPES::View;
+
+    async fn validate_and_apply_header(
+        &self,
+        _instance: &Self::Instance,
+        parent_leaf: &Leaf2<TYPES>,
+        proposed_header: &TYPES::BlockHeader,
+        _vid_common: VidCommon,
+        _version: Version,
+        _view_number: u64,
+    ) -> Result<(Self, Self::Delta), Self::Error> {
+        // The entries are written when the block is decided, see `MerkleState::apply`.
+        let expected = parent_leaf.height() + 1;
+        if proposed_header.block_number() != expected {
+            return Err(BlockError::InvalidBlockHeader(format!(
+                "block number {} does not extend parent at height {}",
+                proposed_header.block_number(),
+                parent_leaf.height()
+            )));
+        }
+
+        Ok((
+            Self {
+                block_height: expected,
+                entries: self.entries.clone(),
+            },
+            MerkleDelta::default(),
+        ))
+    }
+
+    fn from_header(block_header: &TYPES::BlockHeader) -> Self {
+        Self {
+            block_height: block_header.block_number(),
+            ..Self::default()
+        }
+    }
+
+    fn genesis(_instance: &Self::Instance) -> (Self, Self::Delta) {
+        (Self::default(), MerkleDelta::default())
+    }
+
+    fn on_commit(&self) {}
+}
+
+impl<TYPES, K, V> IncrementalCommitment<TYPES> for MerkleState<K, V>
+where
+    TYPES: NodeType,
+    K: Clone + Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+    V: Clone + Debug + Eq + Send + Sync + Serialize + DeserializeOwned,
+{
+    type Commitment = MerkleRoot;
+
+    fn commitment(&self) -> MerkleRoot {
+        self.root()
+    }
+
+    fn append(parent: &MerkleRoot, delta: &Self::Delta) -> Option<MerkleRoot> {
+        delta.apply_to(parent)
+    }
+}
+
+/// Path of `key` through the trie
+fn key_path<K: Serialize>(key: &K) -> Digest256 {
+    let bytes = bincode_opts()
+        .serialize(key)
+        .expect("Merkle state keys are serializable");
+    Sha256::digest(bytes).into()
+}
+
+/// Digest of `value`
+fn value_digest<V: Serialize>(value: &V) -> Digest256 {
+    let bytes = bincode_opts()
+        .serialize(value)
+        .expect("Merkle state values are serializable");
+    Sha256::digest(bytes).into()
+}
+
+/// Digest of a subtree holding only the entry at `path`
+fn leaf_digest(path: &Digest256, value: &Digest256) -> Digest256 {
+    let mut digest = Sha256::new();
+    digest.update([0]);
+    digest.update(path);
+    digest.update(value);
+    digest.finalize().into()
+}
+
+/// Digest of an inner node
+fn node_digest(left: &Digest256, right: &Digest256) -> Digest256 {
+    let mut digest = Sha256::new();
+    digest.update([1]);
+    digest.update(left);
+    digest.update(right);
+    digest.finalize().into()
+}
+
+/// Bit `depth` of `path`, which picks the right child at that depth when set
+fn bit(path: &Digest256, depth: usize) -> bool {
+    path[depth / 8] & (0x80 >> (depth % 8)) != 0
+}
+
+/// Split the ordered entries of a subtree at `depth` into its left and right children
+fn split(leaves: &[(Digest256, Digest256)], depth: usize) -> SplitLeaves<'_> {
+    leaves.split_at(leaves.partition_point(|(path, _)| !bit(path, depth)))
+}
+
+/// The entries of the two children of a subtree
+type SplitLeaves<'a> = (&'a [(Digest256, Digest256)], &'a [(Digest256, Digest256)]);
+
+/// Digest of the subtree at `depth` holding the ordered `leaves`
+fn subtree_digest(leaves: &[(Digest256, Digest256)], depth: usize) -> Digest256 {
+    match leaves {
+        [] => EMPTY_DIGEST,
+        [(path, value)] => leaf_digest(path, value),
+        _ => {
+            let (left, right) = split(leaves, depth);
+            node_digest(
+                &subtree_digest(left, depth + 1),
+                &subtree_digest(right, depth + 1),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn proofs_verify_against_the_root() {
+        let mut state = MerkleState::<String, u64>::default();
+        let empty = state.root();
+        assert_eq!(empty, MerkleRoot(EMPTY_DIGEST));
+        let alice = "alice".to_string();
+        assert_eq!(state.prove(&alice).verify(&empty, &alice), Ok(None));
+
+        let accounts: Vec<_> = (0..50).map(|i| (format!("account {i}"), i)).collect();
+        let delta = state.apply(
+            accounts
+                .iter()
+                .cloned()
+                .map(|(key, value)| (key, Some(value))),
+        );
+        assert_eq!(delta.writes.len(), 50);
+        let root = state.root();
+
+        // Inclusion
+        for (key, value) in &accounts {
+            assert_eq!(state.prove(key).verify(&root, key), Ok(Some(value)));
+        }
+
+        // Exclusion, whether the path ends in an empty subtree or at another key
+        for i in 50..100 {
+            let key = format!("account {i}");
+            assert_eq!(state.prove(&key).verify(&root, &key), Ok(None));
+        }
+
+        // A proof is only good for its key and root
+        let key = "account 7".to_string();
+        let proof = state.prove(&key);
+        assert_eq!(
+            proof.verify(&root, &"account 8".to_string()),
+            Err(MerkleProofError::RootMismatch)
+        );
+        assert_eq!(
+            proof.verify(&empty, &key),
+            Err(MerkleProofError::RootMismatch)
+        );
+        let mut forged = proof.clone();
+        forged.value = Some(1000);
+        assert_eq!(
+            forged.verify(&root, &key),
+            Err(MerkleProofError::RootMismatch)
+        );
+
+        // The root depends on the entries only
+        state.insert(key.clone(), 1000);
+        assert_ne!(state.root(), root);
+        state.insert(key.clone(), 7);
+        assert_eq!(state.root(), root);
+
+        let mut reversed = MerkleState::default();
+        for (key, value) in accounts.into_iter().rev() {
+            reversed.insert(key, value);
+        }
+        assert_eq!(reversed.root(), root);
+
+        reversed.apply(
+            reversed
+                .clone()
+                .entries
+                .into_values()
+                .map(|(key, _)| (key, None)),
+        );
+        assert!(reversed.is_empty());
+        assert_eq!(reversed.root(), empty);
+    }
+
+    #[test]
+    fn deltas_carry_the_root_forward() {
+        let mut state = MerkleState::<u64, u64>::default();
+        let mut root = state.root();
+
+        let blocks: Vec<Vec<_>> = vec![
+            // Insert into an empty state, and next to single entries
+            vec![(1, Some(10))],
+            (0..40).map(|key| (key, Some(key))).collect(),
+            // Update and remove, down to subtrees with a single entry
+            (0..40).step_by(3).map(|key| (key, Some(key * 2))).collect(),
+            (0..39).map(|key| (key, None)).collect(),
+            // Remove keys which have no value, then everything
+            vec![(100, None), (39, None), (39, None)],
+            vec![(7, Some(7))],
+        ];
+        for writes in blocks {
+            let delta = state.apply(writes);
+            root = delta.apply_to(&root).unwrap();
+            assert_eq!(root, state.root());
+        }
+
+        // The witnesses are only good for the state they were taken from
+        let delta = state.apply([(8, Some(8))]);
+        assert_eq!(
+            delta.apply_to(&MerkleState::<u64, u64>::default().root()),
+            None
+        );
+        assert_eq!(delta.apply_to(&root), Some(state.root()));
+    }
+}
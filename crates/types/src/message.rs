@@ -16,7 +16,7 @@ use std::{
 };
 
 use async_lock::RwLock;
-use committable::Committable;
+use committable::{Commitment, Committable};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use utils::anytrace::*;
 use vbs::{
@@ -25,20 +25,28 @@ use vbs::{
 };
 
 use crate::{
+    anti_entropy::{AntiEntropyItem, DigestEntry},
+    dag_mempool::{BatchProposal, TransactionBatch},
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, UpgradeProposal,
         VidDisperseShare, VidDisperseShare2,
     },
+    gossip_fanout::MessageClass,
+    hlc::HybridTimestamp,
+    payload_stream::{DaProposalHeader, PayloadChunk},
+    replay::{next_nonce, ReplayGuard},
     request_response::ProposalRequestPayload,
     simple_certificate::{
-        DaCertificate, DaCertificate2, QuorumCertificate2, UpgradeCertificate,
-        ViewSyncCommitCertificate, ViewSyncCommitCertificate2, ViewSyncFinalizeCertificate,
-        ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate, ViewSyncPreCommitCertificate2,
+        BatchCertificate, DaCertificate, DaCertificate2, FallbackCertificate, QuorumCertificate2,
+        UpgradeCertificate, ViewSyncCommitCertificate, ViewSyncCommitCertificate2,
+        ViewSyncFinalizeCertificate, ViewSyncFinalizeCertificate2, ViewSyncPreCommitCertificate,
+        ViewSyncPreCommitCertificate2,
     },
     simple_vote::{
-        DaVote, DaVote2, QuorumVote, QuorumVote2, TimeoutVote, TimeoutVote2, UpgradeVote,
-        ViewSyncCommitVote, ViewSyncCommitVote2, ViewSyncFinalizeVote, ViewSyncFinalizeVote2,
-        ViewSyncPreCommitVote, ViewSyncPreCommitVote2,
+        BatchVote, CompactQuorumVote, DaVote, DaVote2, FallbackVote, HighQcVote, QuorumVote,
+        QuorumVote2, TimeoutVote, TimeoutVote2, UpgradeVote, ViewSyncCommitVote,
+        ViewSyncCommitVote2, ViewSyncFinalizeVote, ViewSyncFinalizeVote2, ViewSyncPreCommitVote,
+        ViewSyncPreCommitVote2,
     },
     traits::{
         block_contents::BlockHeader,
@@ -46,9 +54,11 @@ use crate::{
         network::{DataRequest, ResponseMessage, ViewMessage},
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::SignatureKey,
+        signer::Signer,
     },
     utils::{epoch_from_block_number, mnemonic},
     vote::HasViewNumber,
+    vote_aggregation::PartialQuorumCertificate,
 };
 
 /// Incoming message
@@ -62,6 +72,34 @@ pub struct Message<TYPES: NodeType> {
     pub kind: MessageKind<TYPES>,
 }
 
+/// A serialized [`Message`] signed by its sender, which is how every message travels over the
+/// network. Messages are only handed to consensus once the signature checks out, and the nonce was
+/// not seen before.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound(deserialize = ""))]
+pub struct SignedEnvelope<TYPES: NodeType> {
+    /// The versioned serialization of the message
+    pub message: Vec<u8>,
+
+    /// Nonce distinguishing this envelope from any other the sender sealed
+    pub nonce: HybridTimestamp,
+
+    /// The signature of `nonce` and `message` by the message's sender
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> SignedEnvelope<TYPES> {
+    /// The bytes the sender signs: the nonce, followed by the message.
+    #[must_use]
+    pub fn signed_bytes(nonce: HybridTimestamp, message: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(12 + message.len());
+        bytes.extend_from_slice(&nonce.physical().to_le_bytes());
+        bytes.extend_from_slice(&nonce.logical().to_le_bytes());
+        bytes.extend_from_slice(message);
+        bytes
+    }
+}
+
 impl<TYPES: NodeType> fmt::Debug for Message<TYPES> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("Message")
@@ -144,6 +182,16 @@ impl<TYPES: NodeType> MessageKind<TYPES> {
     pub fn from_consensus_message(m: SequencingMessage<TYPES>) -> Self {
         Self::Consensus(m)
     }
+
+    /// The epoch whose committees the sender must be in, for the messages only members send;
+    /// see [`SequencingMessage::sender_epoch`]. Data and external messages may come from anyone.
+    #[must_use]
+    pub fn sender_epoch(&self) -> Option<TYPES::Epoch> {
+        match self {
+            MessageKind::Consensus(message) => message.sender_epoch(),
+            MessageKind::Data(_) | MessageKind::External(_) => None,
+        }
+    }
 }
 
 impl<TYPES: NodeType> From<DataMessage<TYPES>> for MessageKind<TYPES> {
@@ -156,7 +204,19 @@ impl<TYPES: NodeType> ViewMessage<TYPES> for MessageKind<TYPES> {
     fn view_number(&self) -> TYPES::View {
         match &self {
             MessageKind::Consensus(message) => message.view_number(),
-            MessageKind::Data(DataMessage::SubmitTransaction(_, v)) => *v,
+            MessageKind::Data(
+                DataMessage::SubmitTransaction(_, v)
+                | DataMessage::GossipTransactions(_, v)
+                | DataMessage::MempoolDigest(_, v)
+                | DataMessage::MempoolRequest(_, v)
+                | DataMessage::AntiEntropyDigest(_, v)
+                | DataMessage::AntiEntropyRequest(_, v)
+                | DataMessage::AntiEntropyResponse(_, v),
+            ) => *v,
+            MessageKind::Data(DataMessage::Batch(batch)) => batch.view,
+            MessageKind::Data(DataMessage::BatchVote(vote)) => vote.view_number(),
+            MessageKind::Data(DataMessage::BatchCertificate(cert)) => cert.view_number(),
+            MessageKind::Data(DataMessage::BatchProposal(proposal)) => proposal.view_number,
             MessageKind::Data(DataMessage::RequestData(msg)) => msg.view,
             MessageKind::Data(DataMessage::DataResponse(msg)) => match msg {
                 ResponseMessage::Found(m) => m.view_number(),
@@ -222,8 +282,8 @@ pub enum GeneralConsensusMessage<TYPES: NodeType> {
     /// A replica has responded with a valid proposal.
     ProposalResponse2(Proposal<TYPES, QuorumProposal2<TYPES>>),
 
-    /// Message for the next leader containing our highest QC
-    HighQc(QuorumCertificate2<TYPES>),
+    /// Message for the next leader containing our highest QC and our signed report of its view
+    HighQc(QuorumCertificate2<TYPES>, HighQcVote<TYPES>),
 
     /// Message with a view sync pre-commit vote
     ViewSyncPreCommitVote2(ViewSyncPreCommitVote2<TYPES>),
@@ -245,6 +305,18 @@ pub enum GeneralConsensusMessage<TYPES: NodeType> {
 
     /// Message with a Timeout vote
     TimeoutVote2(TimeoutVote2<TYPES>),
+
+    /// Message with a vote in a round of the coin fallback
+    FallbackVote(FallbackVote<TYPES>),
+
+    /// Message with the certificate of a round of the coin fallback
+    FallbackCertificate(FallbackCertificate<TYPES>),
+
+    /// Message with quorum votes an aggregator forwards to the leader
+    PartialQuorumCertificate(PartialQuorumCertificate<TYPES>),
+
+    /// Message for Consensus with a compactly encoded quorum vote
+    CompactVote(CompactQuorumVote<TYPES>),
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -278,6 +350,12 @@ pub enum DaConsensusMessage<TYPES: NodeType> {
     ///
     /// Like [`DaProposal`]. Use `Msg` suffix to distinguish from `VidDisperse`.
     VidDisperseMsg2(Proposal<TYPES, VidDisperseShare2<TYPES>>),
+
+    /// Header of a DA proposal whose payload follows in [`DaConsensusMessage::DaPayloadChunk`]s
+    DaProposalHeader(Proposal<TYPES, DaProposalHeader<TYPES>>),
+
+    /// Part of the payload of a streamed DA proposal
+    DaPayloadChunk(PayloadChunk<TYPES>),
 }
 
 /// Messages for sequencing consensus.
@@ -350,7 +428,13 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     }
                     GeneralConsensusMessage::UpgradeProposal(message) => message.data.view_number(),
                     GeneralConsensusMessage::UpgradeVote(message) => message.view_number(),
-                    GeneralConsensusMessage::HighQc(qc) => qc.view_number(),
+                    GeneralConsensusMessage::HighQc(qc, _) => qc.view_number(),
+                    GeneralConsensusMessage::FallbackVote(vote) => vote.view_number(),
+                    GeneralConsensusMessage::FallbackCertificate(cert) => cert.view_number(),
+                    GeneralConsensusMessage::PartialQuorumCertificate(partial) => {
+                        partial.view_number()
+                    }
+                    GeneralConsensusMessage::CompactVote(vote) => vote.view_number(),
                 }
             }
             SequencingMessage::Da(da_message) => {
@@ -371,10 +455,121 @@ impl<TYPES: NodeType> SequencingMessage<TYPES> {
                     }
                     DaConsensusMessage::DaVote2(vote_message) => vote_message.view_number(),
                     DaConsensusMessage::DaCertificate2(cert) => cert.view_number,
+                    DaConsensusMessage::DaProposalHeader(p) => p.data.view_number(),
+                    DaConsensusMessage::DaPayloadChunk(chunk) => chunk.view_number(),
                 }
             }
         }
     }
+
+    /// The class of the message, whose propagation is measured separately.
+    #[must_use]
+    pub fn class(&self) -> MessageClass {
+        match self {
+            SequencingMessage::General(general_message) => match general_message {
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::Proposal2(_)
+                | GeneralConsensusMessage::ProposalResponse(_)
+                | GeneralConsensusMessage::ProposalResponse2(_) => MessageClass::Proposal,
+                GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::Vote2(_)
+                | GeneralConsensusMessage::CompactVote(_)
+                | GeneralConsensusMessage::PartialQuorumCertificate(_) => MessageClass::Vote,
+                GeneralConsensusMessage::TimeoutVote(_)
+                | GeneralConsensusMessage::TimeoutVote2(_) => MessageClass::Timeout,
+                GeneralConsensusMessage::ViewSyncPreCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote2(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate2(_) => {
+                    MessageClass::ViewSync
+                }
+                GeneralConsensusMessage::ProposalRequested(..)
+                | GeneralConsensusMessage::UpgradeProposal(_)
+                | GeneralConsensusMessage::UpgradeVote(_)
+                | GeneralConsensusMessage::HighQc(..)
+                | GeneralConsensusMessage::FallbackVote(_)
+                | GeneralConsensusMessage::FallbackCertificate(_) => MessageClass::Other,
+            },
+            SequencingMessage::Da(da_message) => match da_message {
+                DaConsensusMessage::VidDisperseMsg(_) | DaConsensusMessage::VidDisperseMsg2(_) => {
+                    MessageClass::Vid
+                }
+                DaConsensusMessage::DaProposal(_)
+                | DaConsensusMessage::DaVote(_)
+                | DaConsensusMessage::DaCertificate(_)
+                | DaConsensusMessage::DaProposal2(_)
+                | DaConsensusMessage::DaVote2(_)
+                | DaConsensusMessage::DaCertificate2(_)
+                | DaConsensusMessage::DaProposalHeader(_)
+                | DaConsensusMessage::DaPayloadChunk(_) => MessageClass::Da,
+            },
+        }
+    }
+
+    /// The epoch whose quorum or DA committee the sender must be in, for the messages only
+    /// members send: votes, and the DA proposals and VID shares of leaders.
+    ///
+    /// Anyone may relay certificates, which carry their own signatures, or ask for and return
+    /// proposals when catching up, so those have none. Nor do the messages of versions before
+    /// epochs, which carry no epoch, and quorum proposals, which are checked against the leader
+    /// of their view when they are received.
+    #[must_use]
+    pub fn sender_epoch(&self) -> Option<TYPES::Epoch> {
+        match self {
+            SequencingMessage::General(general_message) => match general_message {
+                GeneralConsensusMessage::Vote2(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::TimeoutVote2(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::ViewSyncPreCommitVote2(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::ViewSyncCommitVote2(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::ViewSyncFinalizeVote2(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::FallbackVote(vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::HighQc(_, vote) => Some(vote.data.epoch),
+                GeneralConsensusMessage::CompactVote(vote) => Some(vote.epoch),
+                GeneralConsensusMessage::PartialQuorumCertificate(partial) => {
+                    Some(partial.data.epoch)
+                }
+                GeneralConsensusMessage::Proposal(_)
+                | GeneralConsensusMessage::Proposal2(_)
+                | GeneralConsensusMessage::ProposalRequested(..)
+                | GeneralConsensusMessage::ProposalResponse(_)
+                | GeneralConsensusMessage::ProposalResponse2(_)
+                | GeneralConsensusMessage::Vote(_)
+                | GeneralConsensusMessage::TimeoutVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncCommitVote(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeVote(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate(_)
+                | GeneralConsensusMessage::ViewSyncPreCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncCommitCertificate2(_)
+                | GeneralConsensusMessage::ViewSyncFinalizeCertificate2(_)
+                | GeneralConsensusMessage::UpgradeProposal(_)
+                | GeneralConsensusMessage::UpgradeVote(_)
+                | GeneralConsensusMessage::FallbackCertificate(_) => None,
+            },
+            SequencingMessage::Da(da_message) => match da_message {
+                DaConsensusMessage::DaProposal2(proposal) => Some(proposal.data.epoch),
+                DaConsensusMessage::DaProposalHeader(proposal) => Some(proposal.data.epoch),
+                DaConsensusMessage::VidDisperseMsg2(disperse) => Some(disperse.data.epoch),
+                DaConsensusMessage::DaVote2(vote) => Some(vote.data.epoch),
+                DaConsensusMessage::DaProposal(_)
+                | DaConsensusMessage::DaVote(_)
+                | DaConsensusMessage::DaCertificate(_)
+                | DaConsensusMessage::VidDisperseMsg(_)
+                | DaConsensusMessage::DaCertificate2(_)
+                | DaConsensusMessage::DaPayloadChunk(_) => None,
+            },
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -391,6 +586,26 @@ pub enum DataMessage<TYPES: NodeType> {
     RequestData(DataRequest<TYPES>),
     /// A response to a data request
     DataResponse(ResponseMessage<TYPES>),
+    /// Transactions relayed by mempool gossip, or sent in reply to a [`DataMessage::MempoolRequest`]
+    GossipTransactions(Vec<TYPES::Transaction>, TYPES::View),
+    /// Commitments to the transactions pending at the sender, for reconciliation
+    MempoolDigest(Vec<Commitment<TYPES::Transaction>>, TYPES::View),
+    /// A request for the transactions with these commitments, which the recipient advertised
+    MempoolRequest(Vec<Commitment<TYPES::Transaction>>, TYPES::View),
+    /// A batch of the DAG mempool, sent by its author
+    Batch(TransactionBatch<TYPES>),
+    /// A vote that the sender holds a batch, sent to its author
+    BatchVote(BatchVote<TYPES>),
+    /// A certificate that a batch is available, sent by its author
+    BatchCertificate(BatchCertificate<TYPES>),
+    /// The block of the view as the certified batches it is made of, sent by the leader
+    BatchProposal(BatchProposal<TYPES>),
+    /// Entries of the recent consensus messages held by the sender, for anti-entropy
+    AntiEntropyDigest(Vec<DigestEntry>, TYPES::View),
+    /// A request for the messages with these entries, which the recipient advertised
+    AntiEntropyRequest(Vec<DigestEntry>, TYPES::View),
+    /// Messages sent in reply to a [`DataMessage::AntiEntropyRequest`]
+    AntiEntropyResponse(Vec<AntiEntropyItem<TYPES>>, TYPES::View),
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
@@ -491,6 +706,9 @@ pub struct UpgradeLock<TYPES: NodeType, V: Versions> {
     /// a shared lock to an upgrade certificate decided by consensus
     pub decided_upgrade_certificate: Arc<RwLock<Option<UpgradeCertificate<TYPES>>>>,
 
+    /// the chain this node runs, which votes are bound to from the epochs version on
+    pub chain_id: u64,
+
     /// phantom data for the `Versions` trait
     pub _pd: PhantomData<V>,
 }
@@ -501,6 +719,7 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
     pub fn new() -> Self {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(None)),
+            chain_id: 0,
             _pd: PhantomData::<V>,
         }
     }
@@ -510,10 +729,18 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
     pub fn from_certificate(certificate: &Option<UpgradeCertificate<TYPES>>) -> Self {
         Self {
             decided_upgrade_certificate: Arc::new(RwLock::new(certificate.clone())),
+            chain_id: 0,
             _pd: PhantomData::<V>,
         }
     }
 
+    /// Bind the votes signed and verified under this lock to `chain_id`.
+    #[must_use]
+    pub fn with_chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = chain_id;
+        self
+    }
+
     /// Calculate the version applied in a view, based on the provided upgrade lock.
     ///
     /// # Errors
@@ -619,4 +846,193 @@ impl<TYPES: NodeType, V: Versions> UpgradeLock<TYPES, V> {
 
         Ok(deserialized_message)
     }
+
+    /// Serialize `message` and seal it in a [`SignedEnvelope`] under a fresh nonce, signed by
+    /// `signer`, which must sign for the message's sender.
+    ///
+    /// Envelopes are only sent from the epochs version on. Messages of earlier versions are sent
+    /// bare, as nodes running those versions expect them.
+    ///
+    /// # Errors
+    ///
+    /// Errors if serialization or signing fails.
+    pub async fn serialize_signed(
+        &self,
+        message: &Message<TYPES>,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+    ) -> Result<Vec<u8>> {
+        let version = self.version(message.view_number()).await?;
+        let message = self.serialize(message).await?;
+        if version < V::Epochs::VERSION {
+            return Ok(message);
+        }
+
+        let nonce = next_nonce();
+        let signature = signer
+            .sign(&SignedEnvelope::<TYPES>::signed_bytes(nonce, &message))
+            .await
+            .wrap()
+            .context(error!("Failed to sign message!"))?;
+
+        // The envelope is prefixed with the version of its message, so that receivers can tell it
+        // from a bare message
+        let mut envelope = version.serialize();
+        bincode::serialize_into(
+            &mut envelope,
+            &SignedEnvelope::<TYPES> {
+                message,
+                nonce,
+                signature,
+            },
+        )
+        .wrap()
+        .context(info!("Failed to serialize message envelope!"))?;
+
+        Ok(envelope)
+    }
+
+    /// Take the message out of a [`SignedEnvelope`] received from the network, leaving its seal to
+    /// be checked with [`UnsealedMessage::check_replay`], [`UnsealedMessage::verify`] and
+    /// [`UnsealedMessage::accept`].
+    ///
+    /// Messages of versions before the epochs version are not sealed, and are deserialized as
+    /// they are.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deserialization fails, or if the envelope's version is not its message's.
+    pub async fn open_signed(&self, envelope: &[u8]) -> Result<UnsealedMessage<TYPES>> {
+        let (version, sealed) = Version::deserialize(envelope)
+            .wrap()
+            .context(info!("Failed to read message version!"))?;
+        if version < V::Epochs::VERSION {
+            return Ok(UnsealedMessage {
+                message: self.deserialize(envelope).await?,
+                seal: None,
+            });
+        }
+
+        let envelope: SignedEnvelope<TYPES> = bincode::deserialize(sealed)
+            .wrap()
+            .context(info!("Failed to deserialize message envelope!"))?;
+        let message: Message<TYPES> = self.deserialize(&envelope.message).await?;
+        ensure!(
+            self.version(message.view_number()).await? == version,
+            "Message envelope has version {version}, which is not the version of its message"
+        );
+
+        Ok(UnsealedMessage {
+            message,
+            seal: Some((
+                envelope.nonce,
+                SignedEnvelope::<TYPES>::signed_bytes(envelope.nonce, &envelope.message),
+                envelope.signature,
+            )),
+        })
+    }
+
+    /// Open a [`SignedEnvelope`] received from the network, checking that the message in it is
+    /// signed by its sender, and that `replay` did not see its nonce before.
+    ///
+    /// Replays are rejected before the signature is verified, but a nonce is only recorded once the
+    /// signature checks out, so that forgeries cannot block the sender's genuine envelopes.
+    ///
+    /// Messages of versions before the epochs version are not sealed, and are deserialized as
+    /// they are. Whether the message came from a node which may send it is up to the caller.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deserialization fails, if the nonce is replayed or outside the replay window, if
+    /// the signature is not the sender's, or if a message of the epochs version on is not sealed.
+    pub async fn deserialize_signed(
+        &self,
+        envelope: &[u8],
+        replay: &mut ReplayGuard<TYPES::SignatureKey>,
+    ) -> Result<Message<TYPES>> {
+        let unsealed = self.open_signed(envelope).await?;
+        unsealed.check_replay(replay)?;
+        ensure!(
+            unsealed.verify(),
+            warn!(
+                "Message is not signed by its sender {}",
+                mnemonic(&unsealed.message.sender)
+            )
+        );
+
+        unsealed.accept(replay)
+    }
+}
+
+/// A message taken out of its [`SignedEnvelope`] by [`UpgradeLock::open_signed`], whose seal is
+/// not checked yet.
+pub struct UnsealedMessage<TYPES: NodeType> {
+    /// The message
+    message: Message<TYPES>,
+    /// The nonce of the envelope, the bytes signed and the signature, or `None` for a message
+    /// sent bare
+    seal: Option<(
+        HybridTimestamp,
+        Vec<u8>,
+        <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    )>,
+}
+
+impl<TYPES: NodeType> UnsealedMessage<TYPES> {
+    /// The sender the message claims.
+    #[must_use]
+    pub fn sender(&self) -> &TYPES::SignatureKey {
+        &self.message.sender
+    }
+
+    /// Check that `replay` did not see the envelope's nonce before, which is cheap enough to do
+    /// before the signature.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the nonce is replayed or outside the replay window.
+    pub fn check_replay(&self, replay: &ReplayGuard<TYPES::SignatureKey>) -> Result<()> {
+        let Some((nonce, ..)) = &self.seal else {
+            return Ok(());
+        };
+
+        replay
+            .check(&self.message.sender, *nonce)
+            .wrap()
+            .context(info!(
+                "Rejected message envelope from {}",
+                mnemonic(&self.message.sender)
+            ))
+    }
+
+    /// Whether the envelope is signed by the message's sender. A bare message has nothing to
+    /// check.
+    ///
+    /// This is the costly part of opening an envelope, so it may be run away from the task
+    /// receiving messages.
+    #[must_use]
+    pub fn verify(&self) -> bool {
+        self.seal
+            .as_ref()
+            .map_or(true, |(_, signed_bytes, signature)| {
+                self.message.sender.validate(signature, signed_bytes)
+            })
+    }
+
+    /// Record the envelope's nonce in `replay` and return the message, once [`Self::verify`]
+    /// passed.
+    ///
+    /// The nonce is checked again, since another copy of the envelope may have been accepted
+    /// while this one was verified.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the nonce is replayed or outside the replay window.
+    pub fn accept(self, replay: &mut ReplayGuard<TYPES::SignatureKey>) -> Result<Message<TYPES>> {
+        self.check_replay(replay)?;
+        if let Some((nonce, ..)) = &self.seal {
+            replay.record(&self.message.sender, *nonce);
+        }
+
+        Ok(self.message)
+    }
 }
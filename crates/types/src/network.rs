@@ -20,6 +20,7 @@ use crate::{
     },
     hotshot_config_file::HotShotConfigFile,
     light_client::StateVerKey,
+    stake_table::NodeMetadata,
     traits::signature_key::SignatureKey,
     HotShotConfig, ValidatorConfig,
 };
@@ -83,6 +84,12 @@ pub struct PeerConfigKeys<KEY: SignatureKey> {
     pub stake: u64,
     /// whether the node is a DA node
     pub da: bool,
+    /// the epoch since which the peer's stake has been bonded
+    #[serde(default)]
+    pub bonded_since: u64,
+    /// where and by whom the peer is run
+    #[serde(default)]
+    pub metadata: NodeMetadata,
 }
 
 /// Options controlling how the random builder generates blocks
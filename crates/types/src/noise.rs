@@ -0,0 +1,406 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Noise handshakes which end-to-end encrypt direct messages between consensus keys.
+//!
+//! Direct messages such as votes may cross relays, such as the brokers of the CDN, which see
+//! them in the clear even when the links to them are encrypted. Two nodes which run the
+//! handshake here agree on a key no relay knows, and send each other messages encrypted and
+//! authenticated under it.
+//!
+//! The handshake is `Noise_NN_25519_ChaChaPoly_SHA256` of the
+//! [Noise framework](https://noiseprotocol.org/), as implemented by `snow`: each side sends a
+//! fresh ephemeral key, and both derive their keys from the Diffie-Hellman of the two. Since
+//! consensus keys cannot do Diffie-Hellman, each side then proves its consensus identity by
+//! signing the handshake hash, which commits to both ephemeral keys, with its consensus key. A
+//! relay can therefore neither read the messages nor pose as either side.
+//!
+//! The handshake takes three messages:
+//! 1. The initiator sends its ephemeral key.
+//! 2. The responder sends its ephemeral key, and its signature of the handshake encrypted under
+//!    the key from the responder to the initiator.
+//! 3. The initiator sends its signature of the handshake encrypted under the key from the
+//!    initiator to the responder, with nonce 0.
+//!
+//! Sessions are one-way, from the initiator to the responder. Messages carry their nonce, so
+//! that they may arrive out of order over networks which do not keep it, and replays are
+//! rejected within a window of recent nonces. Noise transport messages are limited to 64 KiB,
+//! which VID shares exceed, so messages of a session are encrypted with ChaCha20-Poly1305 under
+//! the keys the handshake splits into, with the nonce encoded as in Noise.
+
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use snow::{params::NoiseParams, Builder, HandshakeState};
+use thiserror::Error;
+
+use crate::traits::signature_key::SignatureKey;
+
+/// Name of the protocol of the handshake
+const PROTOCOL_NAME: &str = "Noise_NN_25519_ChaChaPoly_SHA256";
+
+/// Largest message of a Noise handshake
+const MAX_HANDSHAKE_MESSAGE: usize = 65535;
+
+/// Signed by the responder along with the handshake hash, so its signature can't be reflected
+const RESPONDER_LABEL: &[u8] = b"noise responder";
+
+/// Signed by the initiator along with the handshake hash
+const INITIATOR_LABEL: &[u8] = b"noise initiator";
+
+/// Number of nonces below the highest one received which may still arrive out of order
+const REPLAY_WINDOW: u64 = 64;
+
+/// Errors of handshakes and encrypted messages.
+#[derive(Debug, Error)]
+pub enum NoiseError {
+    /// A ciphertext was tampered with, or encrypted under another key
+    #[error("Failed to authenticate ciphertext")]
+    Authentication,
+    /// The remote side did not sign the handshake with the consensus key we expected
+    #[error("Invalid handshake signature")]
+    Signature,
+    /// A message was received before, or is too old to tell
+    #[error("Replayed message")]
+    Replay,
+    /// A handshake message could not be encoded or decoded
+    #[error("Malformed handshake message: {0}")]
+    Malformed(String),
+}
+
+impl From<snow::Error> for NoiseError {
+    fn from(error: snow::Error) -> Self {
+        match error {
+            snow::Error::Decrypt => Self::Authentication,
+            error => Self::Malformed(error.to_string()),
+        }
+    }
+}
+
+/// A handshake of our protocol, on the side `initiator` says.
+fn build_handshake(initiator: bool) -> HandshakeState {
+    let params: NoiseParams = PROTOCOL_NAME.parse().expect("The protocol name is valid");
+    let builder = Builder::new(params);
+    let handshake = if initiator {
+        builder.build_initiator()
+    } else {
+        builder.build_responder()
+    };
+    handshake.expect("The protocol needs no static keys")
+}
+
+/// What a finished handshake results in.
+struct Split {
+    /// The handshake hash, which each side signs
+    hash: Vec<u8>,
+    /// The cipher of messages from the initiator to the responder
+    initiator: ChaCha20Poly1305,
+    /// The cipher of messages from the responder to the initiator
+    responder: ChaCha20Poly1305,
+}
+
+impl Split {
+    /// Split the keys of the finished `handshake`.
+    fn new(mut handshake: HandshakeState) -> Self {
+        let hash = handshake.get_handshake_hash().to_vec();
+        let (initiator, responder) = handshake.dangerously_get_raw_split();
+        Self {
+            hash,
+            initiator: ChaCha20Poly1305::new(&Key::from(initiator)),
+            responder: ChaCha20Poly1305::new(&Key::from(responder)),
+        }
+    }
+}
+
+/// The ChaChaPoly nonce of Noise for `counter`: four zero bytes, then the counter in
+/// little-endian.
+fn nonce(counter: u64) -> Nonce {
+    let mut nonce = Nonce::default();
+    nonce[4..].copy_from_slice(&counter.to_le_bytes());
+    nonce
+}
+
+/// Encrypt `plaintext` with `cipher` under the nonce `counter`.
+fn encrypt(cipher: &ChaCha20Poly1305, counter: u64, plaintext: &[u8]) -> Vec<u8> {
+    cipher
+        .encrypt(&nonce(counter), plaintext)
+        .expect("Messages are far shorter than the cipher's limit")
+}
+
+/// Decrypt `ciphertext` with `cipher` under the nonce `counter`.
+fn decrypt(
+    cipher: &ChaCha20Poly1305,
+    counter: u64,
+    ciphertext: &[u8],
+) -> Result<Vec<u8>, NoiseError> {
+    cipher
+        .decrypt(&nonce(counter), ciphertext)
+        .map_err(|_| NoiseError::Authentication)
+}
+
+/// Sign the handshake hash `h` as the side `label` names.
+fn sign_handshake<K: SignatureKey>(
+    private_key: &K::PrivateKey,
+    label: &[u8],
+    h: &[u8],
+) -> Result<Vec<u8>, NoiseError> {
+    let signature = K::sign(private_key, &[label, h].concat())
+        .map_err(|e| NoiseError::Malformed(e.to_string()))?;
+    bincode::serialize(&signature).map_err(|e| NoiseError::Malformed(e.to_string()))
+}
+
+/// Check that `signature` is `key`'s signature of the handshake hash `h` as the side `label`
+/// names.
+fn verify_handshake<K: SignatureKey>(
+    key: &K,
+    label: &[u8],
+    h: &[u8],
+    signature: &[u8],
+) -> Result<(), NoiseError> {
+    let signature: K::PureAssembledSignatureType =
+        bincode::deserialize(signature).map_err(|_| NoiseError::Signature)?;
+    if !key.validate(&signature, &[label, h].concat()) {
+        return Err(NoiseError::Signature);
+    }
+    Ok(())
+}
+
+/// The side of a handshake which starts it, and then sends messages over the session.
+pub struct Initiator {
+    /// The handshake so far
+    handshake: HandshakeState,
+}
+
+impl Initiator {
+    /// Start a handshake, returning the first message: our ephemeral key.
+    ///
+    /// # Panics
+    /// Never, since the first message of the handshake has no payload.
+    #[must_use]
+    pub fn new() -> (Self, Vec<u8>) {
+        let mut handshake = build_handshake(true);
+        let mut message = vec![0; MAX_HANDSHAKE_MESSAGE];
+        let length = handshake
+            .write_message(&[], &mut message)
+            .expect("The first message fits");
+        message.truncate(length);
+        (Self { handshake }, message)
+    }
+
+    /// Read the responder's reply, checking that `responder` signed it, and return the last
+    /// message of the handshake along with the session to the responder.
+    ///
+    /// # Errors
+    /// If the reply is invalid, or was not signed by `responder`.
+    pub fn finish<K: SignatureKey>(
+        mut self,
+        reply: &[u8],
+        payload: &[u8],
+        responder: &K,
+        private_key: &K::PrivateKey,
+    ) -> Result<(Vec<u8>, SendSession), NoiseError> {
+        self.handshake.read_message(reply, &mut [])?;
+        let split = Split::new(self.handshake);
+
+        let signature = decrypt(&split.responder, 0, payload)?;
+        verify_handshake(responder, RESPONDER_LABEL, &split.hash, &signature)?;
+
+        let signature = sign_handshake::<K>(private_key, INITIATOR_LABEL, &split.hash)?;
+        let message = encrypt(&split.initiator, 0, &signature);
+        // Nonce 0 carried our signature
+        Ok((
+            message,
+            SendSession {
+                cipher: split.initiator,
+                nonce: 1,
+            },
+        ))
+    }
+}
+
+/// The side of a handshake which answers it, and then receives messages over the session.
+pub struct Responder {
+    /// The keys of the handshake
+    split: Split,
+}
+
+impl Responder {
+    /// Answer the first message of a handshake, returning our reply and the encrypted signature
+    /// of the handshake to send the initiator.
+    ///
+    /// # Errors
+    /// If the initiator's message is invalid, or we fail to sign.
+    pub fn respond<K: SignatureKey>(
+        message: &[u8],
+        private_key: &K::PrivateKey,
+    ) -> Result<(Self, Vec<u8>, Vec<u8>), NoiseError> {
+        let mut handshake = build_handshake(false);
+        handshake.read_message(message, &mut [])?;
+        let mut reply = vec![0; MAX_HANDSHAKE_MESSAGE];
+        let length = handshake.write_message(&[], &mut reply)?;
+        reply.truncate(length);
+        let split = Split::new(handshake);
+
+        let signature = sign_handshake::<K>(private_key, RESPONDER_LABEL, &split.hash)?;
+        let payload = encrypt(&split.responder, 0, &signature);
+        Ok((Self { split }, reply, payload))
+    }
+
+    /// Read the last message of the handshake, checking that `initiator` signed it, and return
+    /// the session from the initiator.
+    ///
+    /// # Errors
+    /// If the message is invalid, or was not signed by `initiator`.
+    pub fn finish<K: SignatureKey>(
+        self,
+        payload: &[u8],
+        initiator: &K,
+    ) -> Result<RecvSession, NoiseError> {
+        let signature = decrypt(&self.split.initiator, 0, payload)?;
+        verify_handshake(initiator, INITIATOR_LABEL, &self.split.hash, &signature)?;
+        // Nonce 0 carried the signature, so it can't be replayed as a message
+        Ok(RecvSession {
+            cipher: self.split.initiator,
+            highest: Some(0),
+            received: 1,
+        })
+    }
+}
+
+/// The sending end of a session.
+#[derive(Clone)]
+pub struct SendSession {
+    /// The cipher messages are encrypted with
+    cipher: ChaCha20Poly1305,
+    /// Nonce of the next message
+    nonce: u64,
+}
+
+impl SendSession {
+    /// Encrypt `plaintext`, returning its nonce and its ciphertext.
+    pub fn encrypt(&mut self, plaintext: &[u8]) -> (u64, Vec<u8>) {
+        let nonce = self.nonce;
+        self.nonce += 1;
+        (nonce, encrypt(&self.cipher, nonce, plaintext))
+    }
+}
+
+/// The receiving end of a session.
+#[derive(Clone)]
+pub struct RecvSession {
+    /// The cipher messages are encrypted with
+    cipher: ChaCha20Poly1305,
+    /// The highest nonce received, if any
+    highest: Option<u64>,
+    /// Which of the `REPLAY_WINDOW` nonces up to the highest one were received, the highest one
+    /// being the lowest bit
+    received: u64,
+}
+
+impl RecvSession {
+    /// Decrypt the message with `nonce` and `ciphertext`.
+    ///
+    /// # Errors
+    /// If the message was tampered with, or was received before.
+    pub fn decrypt(&mut self, nonce: u64, ciphertext: &[u8]) -> Result<Vec<u8>, NoiseError> {
+        // Check for replays first, but only record the nonce of an authentic message
+        let offset = self.highest.map(|highest| highest.checked_sub(nonce));
+        if let Some(Some(offset)) = offset {
+            if offset >= REPLAY_WINDOW || self.received & (1 << offset) != 0 {
+                return Err(NoiseError::Replay);
+            }
+        }
+
+        let plaintext = decrypt(&self.cipher, nonce, ciphertext)?;
+        match (self.highest, offset) {
+            (Some(_), Some(Some(offset))) => self.received |= 1 << offset,
+            (Some(highest), _) => {
+                let shift = nonce - highest;
+                self.received = if shift >= REPLAY_WINDOW {
+                    1
+                } else {
+                    (self.received << shift) | 1
+                };
+                self.highest = Some(nonce);
+            }
+            (None, _) => {
+                self.received = 1;
+                self.highest = Some(nonce);
+            }
+        }
+        Ok(plaintext)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::signature_key::BLSPubKey;
+
+    /// Run a handshake between the keys of seeds `initiator` and `responder`, as `responder`
+    /// expects to see them.
+    fn handshake(
+        initiator: u64,
+        responder: u64,
+        expected_responder: u64,
+    ) -> Result<(SendSession, RecvSession), NoiseError> {
+        let (initiator_key, initiator_private) =
+            BLSPubKey::generated_from_seed_indexed([0; 32], initiator);
+        let (_, responder_private) = BLSPubKey::generated_from_seed_indexed([0; 32], responder);
+        let expected_responder =
+            BLSPubKey::generated_from_seed_indexed([0; 32], expected_responder).0;
+
+        let (initiator, hello) = Initiator::new();
+        let (responder, reply, payload) =
+            Responder::respond::<BLSPubKey>(&hello, &responder_private)?;
+        let (finish, send) =
+            initiator.finish(&reply, &payload, &expected_responder, &initiator_private)?;
+        let recv = responder.finish(&finish, &initiator_key)?;
+        Ok((send, recv))
+    }
+
+    #[test]
+    fn messages_round_trip() {
+        let (mut send, mut recv) = handshake(1, 2, 2).unwrap();
+
+        let (nonce, ciphertext) = send.encrypt(b"vote");
+        assert!(!ciphertext.windows(4).any(|window| window == b"vote"));
+        assert_eq!(recv.decrypt(nonce, &ciphertext).unwrap(), b"vote");
+    }
+
+    #[test]
+    fn handshake_rejects_impostor() {
+        // A relay which answers in place of the responder can't sign as it
+        assert!(matches!(handshake(1, 3, 2), Err(NoiseError::Signature)));
+    }
+
+    #[test]
+    fn session_rejects_tampering_and_replays() {
+        let (mut send, mut recv) = handshake(1, 2, 2).unwrap();
+        let first = send.encrypt(b"first");
+        let second = send.encrypt(b"second");
+
+        let mut tampered = second.1.clone();
+        tampered[0] ^= 1;
+        assert!(matches!(
+            recv.decrypt(second.0, &tampered),
+            Err(NoiseError::Authentication)
+        ));
+
+        // Out of order is fine, but only once
+        assert_eq!(recv.decrypt(second.0, &second.1).unwrap(), b"second");
+        assert_eq!(recv.decrypt(first.0, &first.1).unwrap(), b"first");
+        assert!(matches!(
+            recv.decrypt(first.0, &first.1),
+            Err(NoiseError::Replay)
+        ));
+        assert!(matches!(
+            recv.decrypt(second.0, &second.1),
+            Err(NoiseError::Replay)
+        ));
+    }
+}
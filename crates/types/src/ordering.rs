@@ -0,0 +1,430 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Building blocks for running HotShot as an ordering-only sequencer.
+//!
+//! In this mode there is no application state: transactions are opaque byte strings, a block is
+//! an ordered list of them, and consensus only checks that a proposed block extends its parent.
+//! Size is enforced by [`BlockLimits`](crate::block_limits::BlockLimits) and availability by the
+//! DA certificate, as for any other block. Downstream consumers, typically a rollup, read the
+//! ordered payloads from `Decide` events with [`ordered_blocks`].
+//!
+//! To use it, define a [`NodeType`] with
+//! `BlockPayload = OrderedPayload`, `BlockHeader = OrderingHeader`,
+//! `ValidatedState = OrderingState`, `InstanceState = OrderingInstanceState`,
+//! `Transaction = OpaqueTransaction` and `View = ViewNumber`.
+
+use std::{fmt::Display, sync::Arc};
+
+use async_trait::async_trait;
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use vbs::version::Version;
+
+use crate::{
+    data::{BlockError, Leaf2, ViewNumber},
+    event::LeafInfo,
+    hlc::HybridTimestamp,
+    traits::{
+        block_contents::{BlockHeader, BuilderFee, EncodeBytes, Transaction},
+        node_implementation::NodeType,
+        states::{InstanceState, StateDelta, ValidatedState},
+        BlockPayload,
+    },
+    utils::BuilderCommitment,
+    vid::{VidCommitment, VidCommon},
+};
+
+/// An opaque transaction, ordered but never interpreted.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OpaqueTransaction(pub Vec<u8>);
+
+impl Committable for OpaqueTransaction {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Opaque Txn Comm")
+            .var_size_bytes(&self.0)
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "OPAQUE_TXN".to_string()
+    }
+}
+
+impl Transaction for OpaqueTransaction {
+    fn minimum_block_size(&self) -> u64 {
+        // Length prefix plus the bytes themselves
+        self.0.len() as u64 + 4
+    }
+}
+
+/// Metadata of an [`OrderedPayload`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderedMetadata {
+    /// Number of transactions in the payload
+    pub num_transactions: u64,
+}
+
+impl EncodeBytes for OrderedMetadata {
+    fn encode(&self) -> Arc<[u8]> {
+        Arc::new([])
+    }
+}
+
+/// An ordered list of opaque transactions.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderedPayload {
+    /// The transactions, in order
+    pub transactions: Vec<OpaqueTransaction>,
+}
+
+impl Display for OrderedPayload {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "OrderedPayload #txns={}", self.transactions.len())
+    }
+}
+
+impl EncodeBytes for OrderedPayload {
+    /// Each transaction is encoded as its length, as a little-endian `u32`, followed by its bytes.
+    fn encode(&self) -> Arc<[u8]> {
+        let mut encoded = Vec::new();
+        for transaction in &self.transactions {
+            let len = u32::try_from(transaction.0.len()).expect("transaction is too large");
+            encoded.extend_from_slice(&len.to_le_bytes());
+            encoded.extend_from_slice(&transaction.0);
+        }
+        encoded.into()
+    }
+}
+
+impl OrderedPayload {
+    /// Decode a payload produced by [`EncodeBytes::encode`]. A truncated final transaction is
+    /// dropped.
+    #[must_use]
+    pub fn decode(encoded_transactions: &[u8]) -> Self {
+        let mut transactions = Vec::new();
+        let mut rest = encoded_transactions;
+        while let Some((len, tail)) = rest.split_first_chunk::<4>() {
+            let len = u32::from_le_bytes(*len) as usize;
+            if tail.len() < len {
+                tracing::warn!("Ordered payload ends with a truncated transaction");
+                break;
+            }
+            let (transaction, tail) = tail.split_at(len);
+            transactions.push(OpaqueTransaction(transaction.to_vec()));
+            rest = tail;
+        }
+
+        Self { transactions }
+    }
+}
+
+#[async_trait]
+impl<TYPES: NodeType> BlockPayload<TYPES> for OrderedPayload {
+    type Error = BlockError;
+    type Instance = OrderingInstanceState;
+    type Transaction = OpaqueTransaction;
+    type ValidatedState = OrderingState;
+    type Metadata = OrderedMetadata;
+
+    async fn from_transactions(
+        transactions: impl IntoIterator<Item = Self::Transaction> + Send,
+        _validated_state: &Self::ValidatedState,
+        _instance_state: &Self::Instance,
+    ) -> Result<(Self, Self::Metadata), Self::Error> {
+        let transactions: Vec<_> = transactions.into_iter().collect();
+        let metadata = OrderedMetadata {
+            num_transactions: transactions.len() as u64,
+        };
+
+        Ok((Self { transactions }, metadata))
+    }
+
+    fn from_bytes(encoded_transactions: &[u8], _metadata: &Self::Metadata) -> Self {
+        Self::decode(encoded_transactions)
+    }
+
+    fn empty() -> (Self, Self::Metadata) {
+        (Self::default(), OrderedMetadata::default())
+    }
+
+    fn builder_commitment(&self, _metadata: &Self::Metadata) -> BuilderCommitment {
+        let mut digest = Sha256::new();
+        digest.update(self.encode());
+        BuilderCommitment::from_raw_digest(digest.finalize())
+    }
+
+    fn transactions<'a>(
+        &'a self,
+        _metadata: &'a Self::Metadata,
+    ) -> impl 'a + Iterator<Item = Self::Transaction> {
+        self.transactions.iter().cloned()
+    }
+}
+
+/// Header of an ordered block.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderingHeader {
+    /// Height of the block
+    pub block_number: u64,
+    /// VID commitment to the payload
+    pub payload_commitment: VidCommitment,
+    /// Commitment builders sign the payload with
+    pub builder_commitment: BuilderCommitment,
+    /// Payload metadata
+    pub metadata: OrderedMetadata,
+    /// Creation time, later than the parent's
+    pub timestamp: HybridTimestamp,
+}
+
+impl OrderingHeader {
+    /// A header for a block extending `parent_leaf`.
+    fn extending<TYPES: NodeType<BlockHeader = Self>>(
+        parent_leaf: &Leaf2<TYPES>,
+        payload_commitment: VidCommitment,
+        builder_commitment: BuilderCommitment,
+        metadata: OrderedMetadata,
+    ) -> Self {
+        let parent = parent_leaf.block_header();
+
+        Self {
+            block_number: parent.block_number + 1,
+            payload_commitment,
+            builder_commitment,
+            metadata,
+            // Never go back in time, even if our clock is behind the parent's proposer.
+            timestamp: HybridTimestamp::successor(parent.timestamp),
+        }
+    }
+}
+
+impl<TYPES> BlockHeader<TYPES> for OrderingHeader
+where
+    TYPES: NodeType<
+        BlockHeader = Self,
+        BlockPayload = OrderedPayload,
+        ValidatedState = OrderingState,
+        InstanceState = OrderingInstanceState,
+    >,
+{
+    type Error = std::convert::Infallible;
+
+    async fn new_legacy(
+        _parent_state: &TYPES::ValidatedState,
+        _instance_state: &OrderingInstanceState,
+        parent_leaf: &Leaf2<TYPES>,
+        payload_commitment: VidCommitment,
+        builder_commitment: BuilderCommitment,
+        metadata: OrderedMetadata,
+        _builder_fee: BuilderFee<TYPES>,
+        _vid_common: VidCommon,
+        _version: Version,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self::extending(
+            parent_leaf,
+            payload_commitment,
+            builder_commitment,
+            metadata,
+        ))
+    }
+
+    async fn new_marketplace(
+        _parent_state: &TYPES::ValidatedState,
+        _instance_state: &OrderingInstanceState,
+        parent_leaf: &Leaf2<TYPES>,
+        payload_commitment: VidCommitment,
+        builder_commitment: BuilderCommitment,
+        metadata: OrderedMetadata,
+        _builder_fee: Vec<BuilderFee<TYPES>>,
+        _view_number: u64,
+        _vid_common: VidCommon,
+        _auction_results: Option<TYPES::AuctionResult>,
+        _version: Version,
+    ) -> Result<Self, Self::Error> {
+        Ok(Self::extending(
+            parent_leaf,
+            payload_commitment,
+            builder_commitment,
+            metadata,
+        ))
+    }
+
+    fn genesis(
+        _instance_state: &OrderingInstanceState,
+        payload_commitment: VidCommitment,
+        builder_commitment: BuilderCommitment,
+        metadata: OrderedMetadata,
+    ) -> Self {
+        Self {
+            block_number: 0,
+            payload_commitment,
+            builder_commitment,
+            metadata,
+            timestamp: HybridTimestamp::default(),
+        }
+    }
+
+    fn block_number(&self) -> u64 {
+        self.block_number
+    }
+
+    fn payload_commitment(&self) -> VidCommitment {
+        self.payload_commitment
+    }
+
+    fn metadata(&self) -> &OrderedMetadata {
+        &self.metadata
+    }
+
+    fn builder_commitment(&self) -> BuilderCommitment {
+        self.builder_commitment.clone()
+    }
+
+    fn get_auction_results(&self) -> Option<TYPES::AuctionResult> {
+        None
+    }
+
+    fn timestamp(&self) -> Option<HybridTimestamp> {
+        Some(self.timestamp)
+    }
+}
+
+impl Committable for OrderingHeader {
+    fn commit(&self) -> Commitment<Self> {
+        RawCommitmentBuilder::new("Ordering Header Comm")
+            .u64_field("block number", self.block_number)
+            .constant_str("payload commitment")
+            .fixed_size_bytes(self.payload_commitment.as_ref().as_ref())
+            .u64_field("num transactions", self.metadata.num_transactions)
+            .u64_field("timestamp", self.timestamp.physical())
+            .u64_field("timestamp counter", self.timestamp.logical().into())
+            .finalize()
+    }
+
+    fn tag() -> String {
+        "ORDERING_HEADER".to_string()
+    }
+}
+
+/// Instance state of an ordering-only node, which has none.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OrderingInstanceState;
+
+impl InstanceState for OrderingInstanceState {}
+
+/// State delta of an ordering-only node, which is always empty.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderingDelta;
+
+impl StateDelta for OrderingDelta {}
+
+/// Validated state of an ordering-only node: only the height of the chain.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderingState {
+    /// Height of the last applied block
+    pub block_height: u64,
+}
+
+impl<TYPES: NodeType> ValidatedState<TYPES> for OrderingState {
+    type Error = BlockError;
+    type Instance = OrderingInstanceState;
+    type Delta = OrderingDelta;
+    type Time = ViewNumber;
+
+    async fn validate_and_apply_header(
+        &self,
+        _instance: &Self::Instance,
+        parent_leaf: &Leaf2<TYPES>,
+        proposed_header: &TYPES::BlockHeader,
+        _vid_common: VidCommon,
+        _version: Version,
+        _view_number: u64,
+    ) -> Result<(Self, Self::Delta), Self::Error> {
+        // There is no state to apply the payload to, so a block is valid as long as it extends
+        // its parent.
+        let expected = parent_leaf.height() + 1;
+        if proposed_header.block_number() != expected {
+            return Err(BlockError::InvalidBlockHeader(format!(
+                "block number {} does not extend parent at height {}",
+                proposed_header.block_number(),
+                parent_leaf.height()
+            )));
+        }
+
+        Ok((
+            Self {
+                block_height: expected,
+            },
+            OrderingDelta,
+        ))
+    }
+
+    fn from_header(block_header: &TYPES::BlockHeader) -> Self {
+        Self {
+            block_height: block_header.block_number(),
+        }
+    }
+
+    fn genesis(_instance: &Self::Instance) -> (Self, Self::Delta) {
+        (Self::default(), OrderingDelta)
+    }
+
+    fn on_commit(&self) {}
+}
+
+/// A decided block, as seen by a consumer of the ordering.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OrderedBlock {
+    /// Height of the block
+    pub height: u64,
+    /// View the block was proposed in
+    pub view: u64,
+    /// Ordered transactions, or `None` if this node does not have the payload
+    pub transactions: Option<Vec<OpaqueTransaction>>,
+}
+
+/// The blocks of a decided leaf chain, oldest first.
+#[must_use]
+pub fn ordered_blocks<TYPES>(leaf_chain: &[LeafInfo<TYPES>]) -> Vec<OrderedBlock>
+where
+    TYPES: NodeType<BlockPayload = OrderedPayload>,
+{
+    leaf_chain
+        .iter()
+        .rev()
+        .map(|info| OrderedBlock {
+            height: info.leaf.height(),
+            view: *info.leaf.view_number(),
+            transactions: info
+                .leaf
+                .block_payload()
+                .map(|payload| payload.transactions),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ordered_payload_round_trips() {
+        let payload = OrderedPayload {
+            transactions: vec![
+                OpaqueTransaction(vec![1, 2, 3]),
+                OpaqueTransaction(vec![]),
+                OpaqueTransaction(vec![4; 300]),
+            ],
+        };
+        let encoded = payload.encode();
+        assert_eq!(OrderedPayload::decode(&encoded), payload);
+
+        // A truncated transaction is dropped
+        let truncated = OrderedPayload::decode(&encoded[..encoded.len() - 1]);
+        assert_eq!(truncated.transactions, payload.transactions[..2]);
+    }
+}
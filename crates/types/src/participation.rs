@@ -0,0 +1,272 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Participation of validators in quorum certificates, and jailing of those who persistently
+//! miss them.
+//!
+//! A jailed validator keeps its stake and its vote, but is passed over for leadership, so that an
+//! offline node does not cost the network a timeout every time its turn comes.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use committable::Committable;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    data::Leaf2,
+    simple_certificate::QuorumCertificate2,
+    traits::{
+        block_contents::BlockHeader,
+        election::Membership,
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+        storage::{Storage, StoredValue},
+    },
+    utils::epoch_from_block_number,
+    vote::HasViewNumber,
+};
+
+/// When validators which miss certificates are kept from leading, and for how long.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JailConfig {
+    /// Percentage of an epoch's decided certificates a validator must sign to stay eligible to
+    /// lead. Zero disables jailing.
+    pub liveness_threshold_percent: u64,
+    /// Number of epochs a validator stays jailed for each epoch it falls below the threshold
+    pub penalty_epochs: u64,
+}
+
+impl Default for JailConfig {
+    fn default() -> Self {
+        Self {
+            liveness_threshold_percent: 0,
+            penalty_epochs: 1,
+        }
+    }
+}
+
+/// The keys whose signatures are aggregated in `qc`, which was formed against `stake_table`.
+#[must_use]
+pub fn qc_signers<TYPES: NodeType>(
+    qc: &QuorumCertificate2<TYPES>,
+    stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+) -> Vec<TYPES::SignatureKey> {
+    let Some(signatures) = qc.signatures.as_ref() else {
+        return vec![];
+    };
+    let (_, signers) = TYPES::SignatureKey::sig_proof(signatures);
+
+    stake_table
+        .iter()
+        .zip(signers.iter())
+        .filter(|(_, signed)| **signed)
+        .map(|(entry, _)| TYPES::SignatureKey::public_key(entry))
+        .collect()
+}
+
+/// Certificates signed in one epoch.
+#[derive(Clone, Debug)]
+struct EpochParticipation<TYPES: NodeType> {
+    /// Number of certificates recorded
+    certificates: u64,
+    /// Number of those certificates each validator signed
+    signed: BTreeMap<TYPES::SignatureKey, u64>,
+}
+
+/// Certificates signed by each validator, per epoch.
+///
+/// Decided leaves are recorded one at a time in chain order with
+/// [`ParticipationTracker::record_decided`], and the validators to jail are settled at the last
+/// leaf of each epoch, so every node judges a validator by the same certificates however its
+/// decides were batched. A restarted node rebuilds its participation from the proposals it stored
+/// with [`ParticipationTracker::restore`].
+#[derive(Clone, Debug)]
+pub struct ParticipationTracker<TYPES: NodeType> {
+    /// Participation in each epoch
+    epochs: BTreeMap<TYPES::Epoch, EpochParticipation<TYPES>>,
+    /// The view of the last decided leaf recorded
+    last_decided: Option<TYPES::View>,
+}
+
+impl<TYPES: NodeType> Default for ParticipationTracker<TYPES> {
+    fn default() -> Self {
+        Self {
+            epochs: BTreeMap::new(),
+            last_decided: None,
+        }
+    }
+}
+
+impl<TYPES: NodeType> ParticipationTracker<TYPES> {
+    /// Record a certificate of `epoch` signed by `signers`.
+    pub fn record(
+        &mut self,
+        epoch: TYPES::Epoch,
+        signers: impl IntoIterator<Item = TYPES::SignatureKey>,
+    ) {
+        let participation = self
+            .epochs
+            .entry(epoch)
+            .or_insert_with(|| EpochParticipation {
+                certificates: 0,
+                signed: BTreeMap::new(),
+            });
+        participation.certificates += 1;
+        for signer in signers {
+            *participation.signed.entry(signer).or_default() += 1;
+        }
+    }
+
+    /// The members of the `epoch` committee of `membership` which signed less than
+    /// `threshold_percent` of the certificates recorded for `epoch`.
+    ///
+    /// Nobody is offline in an epoch without recorded certificates, since there is nothing to
+    /// judge them by.
+    #[must_use]
+    pub fn offline(
+        &self,
+        epoch: TYPES::Epoch,
+        membership: &TYPES::Membership,
+        threshold_percent: u64,
+    ) -> BTreeSet<TYPES::SignatureKey> {
+        let Some(participation) = self.epochs.get(&epoch) else {
+            return BTreeSet::new();
+        };
+
+        membership
+            .stake_table(epoch)
+            .iter()
+            .map(TYPES::SignatureKey::public_key)
+            .filter(|key| {
+                let signed = participation.signed.get(key).copied().unwrap_or(0);
+                signed.saturating_mul(100)
+                    < threshold_percent.saturating_mul(participation.certificates)
+            })
+            .collect()
+    }
+
+    /// The validators to keep from leading in `epoch`: those offline in any of the
+    /// `penalty_epochs` epochs which ended at least one epoch before it.
+    ///
+    /// The gap of an epoch leaves time for the last certificates of an epoch to be decided before
+    /// the jail they lead to takes effect.
+    #[must_use]
+    pub fn jailed(
+        &self,
+        epoch: TYPES::Epoch,
+        membership: &TYPES::Membership,
+        config: JailConfig,
+    ) -> BTreeSet<TYPES::SignatureKey> {
+        if config.liveness_threshold_percent == 0 {
+            return BTreeSet::new();
+        }
+
+        (2..config.penalty_epochs.saturating_add(2))
+            .filter_map(|back| epoch.u64().checked_sub(back))
+            .map(TYPES::Epoch::new)
+            .flat_map(|judged| self.offline(judged, membership, config.liveness_threshold_percent))
+            .collect()
+    }
+
+    /// Forget the participation of epochs before `epoch`.
+    pub fn prune_before(&mut self, epoch: TYPES::Epoch) {
+        self.epochs = self.epochs.split_off(&epoch);
+    }
+
+    /// Record who signed the QC carried by the decided leaf `leaf`, and once `leaf` is the last
+    /// block of an epoch, jail in `membership` the validators which were offline, two epochs
+    /// later, like DRB results.
+    ///
+    /// Leaves must come in chain order. A leaf no newer than the last one recorded is skipped, so
+    /// the jails settled at an epoch's last leaf depend on the certificates decided up to it and
+    /// nothing else.
+    pub fn record_decided(
+        &mut self,
+        leaf: &Leaf2<TYPES>,
+        membership: &TYPES::Membership,
+        config: JailConfig,
+        epoch_height: u64,
+    ) {
+        if epoch_height == 0 || config.liveness_threshold_percent == 0 {
+            return;
+        }
+        let view = leaf.view_number();
+        if self.last_decided.is_some_and(|last| view <= last) {
+            return;
+        }
+        self.last_decided = Some(view);
+
+        let qc = leaf.justify_qc();
+        let signers = qc_signers(&qc, &membership.stake_table(qc.data.epoch));
+        self.record(qc.data.epoch, signers);
+
+        let block_number = leaf.block_header().block_number();
+        if block_number % epoch_height != 0 {
+            return;
+        }
+        let ended_epoch = epoch_from_block_number(block_number, epoch_height);
+        let jailed_epoch = TYPES::Epoch::new(ended_epoch + 2);
+        let jailed = self.jailed(jailed_epoch, membership, config);
+        if !jailed.is_empty() {
+            tracing::info!(
+                "Jailing {} validators in epoch {jailed_epoch}",
+                jailed.len()
+            );
+        }
+        membership.set_jailed(jailed_epoch, jailed);
+
+        // The next epoch to end only looks back as far as its penalty window
+        self.prune_before(TYPES::Epoch::new(
+            (ended_epoch + 2).saturating_sub(config.penalty_epochs),
+        ));
+    }
+
+    /// Rebuild the participation of the decided chain ending at `anchor` from the proposals in
+    /// `storage`, jailing in `membership` as was done when each epoch's last leaf was decided.
+    ///
+    /// The chain is followed back from `anchor` for as long as `storage` holds the proposals, so
+    /// a node whose storage pruned proposals still in the penalty window undercounts them.
+    ///
+    /// # Errors
+    /// If `storage` cannot read back the proposals it stores.
+    pub async fn restore(
+        storage: &impl Storage<TYPES>,
+        anchor: &Leaf2<TYPES>,
+        membership: &TYPES::Membership,
+        config: JailConfig,
+        epoch_height: u64,
+    ) -> anyhow::Result<Self> {
+        let mut tracker = Self::default();
+        if epoch_height == 0 || config.liveness_threshold_percent == 0 {
+            return Ok(tracker);
+        }
+
+        let mut leaves: HashMap<_, _> = storage
+            .load_before(anchor.view_number() + 1)
+            .await?
+            .into_iter()
+            .filter_map(|value| match value {
+                StoredValue::Proposal(proposal) => {
+                    Some(Leaf2::from_quorum_proposal(&proposal.data))
+                }
+                StoredValue::VidShare(_) | StoredValue::HighQc(_) => None,
+            })
+            .map(|leaf| (leaf.commit(), leaf))
+            .collect();
+
+        let mut chain = vec![anchor.clone()];
+        let mut parent = anchor.parent_commitment();
+        while let Some(leaf) = leaves.remove(&parent) {
+            parent = leaf.parent_commitment();
+            chain.push(leaf);
+        }
+        for leaf in chain.iter().rev() {
+            tracker.record_decided(leaf, membership, config, epoch_height);
+        }
+
+        Ok(tracker)
+    }
+}
@@ -0,0 +1,479 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Streaming of large block payloads to the DA committee.
+//!
+//! A DA proposal carries the whole payload in a single message, so nothing about it can be checked
+//! until the last byte has arrived, and a large block holds up everything queued behind it on the
+//! leader's connections. When streaming is enabled, the leader instead sends a signed
+//! [`DaProposalHeader`] followed by the payload in [`PayloadChunk`]s. The header lists the hash of
+//! every chunk, so a DA member checks the leader and the declared size as soon as the header
+//! arrives, checks each chunk as soon as it arrives, and rebuilds the DA proposal once it has them
+//! all. The payload is hashed as its chunks arrive, so the leader's signature of the payload is
+//! checked, and the DA vote cast, as soon as the last chunk lands.
+
+use std::{marker::PhantomData, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    data::DaProposal2,
+    message::Proposal,
+    traits::{
+        block_contents::{BlockPayload, EncodeBytes},
+        node_implementation::{ConsensusTime, NodeType},
+        signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+
+/// Default size of a payload chunk, in bytes.
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Settings for streaming DA proposal payloads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PayloadStreamConfig {
+    /// Whether we stream the payloads of the DA proposals we send. Streamed payloads are always
+    /// accepted, whatever this is set to.
+    #[serde(default)]
+    pub enabled: bool,
+    /// Size of each chunk, in bytes. Payloads no larger than this are sent in one message.
+    #[serde(default = "default_chunk_size")]
+    pub chunk_size: usize,
+}
+
+/// Default value of [`PayloadStreamConfig::chunk_size`], for serde.
+fn default_chunk_size() -> usize {
+    DEFAULT_CHUNK_SIZE
+}
+
+impl Default for PayloadStreamConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+        }
+    }
+}
+
+impl PayloadStreamConfig {
+    /// Whether a payload of `size` bytes should be streamed.
+    #[must_use]
+    pub fn should_stream(&self, size: usize) -> bool {
+        self.enabled && size > self.chunk_size
+    }
+}
+
+/// SHA-256 hash of a payload chunk.
+pub type ChunkHash = [u8; 32];
+
+/// Hash a payload chunk.
+#[must_use]
+pub fn chunk_hash(bytes: &[u8]) -> ChunkHash {
+    Sha256::digest(bytes).into()
+}
+
+/// The part of a DA proposal which is sent ahead of its streamed payload.
+#[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(bound = "TYPES: NodeType")]
+pub struct DaProposalHeader<TYPES: NodeType> {
+    /// Metadata of the block to be applied.
+    pub metadata: <TYPES::BlockPayload as BlockPayload<TYPES>>::Metadata,
+    /// View this proposal applies to
+    pub view_number: TYPES::View,
+    /// Epoch this proposal applies to
+    pub epoch: TYPES::Epoch,
+    /// The leader's signature of the whole payload, as it would sign the equivalent [`DaProposal2`]
+    pub payload_signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+    /// Size of the encoded payload, in bytes
+    pub payload_size: u64,
+    /// Hashes of the chunks of the payload, in order
+    pub chunk_hashes: Vec<ChunkHash>,
+}
+
+impl<TYPES: NodeType> DaProposalHeader<TYPES> {
+    /// Split a signed DA proposal into a header and the chunks of its payload.
+    ///
+    /// The header still has to be signed, over [`DaProposalHeader::digest`].
+    #[must_use]
+    pub fn split(
+        proposal: &Proposal<TYPES, DaProposal2<TYPES>>,
+        chunk_size: usize,
+    ) -> (Self, Vec<PayloadChunk<TYPES>>) {
+        let view_number = proposal.data.view_number;
+        let chunks: Vec<_> = proposal
+            .data
+            .encoded_transactions
+            .chunks(chunk_size.max(1))
+            .zip(0..)
+            .map(|(bytes, index)| PayloadChunk {
+                view_number,
+                index,
+                bytes: bytes.to_vec(),
+            })
+            .collect();
+
+        let header = Self {
+            metadata: proposal.data.metadata.clone(),
+            view_number,
+            epoch: proposal.data.epoch,
+            payload_signature: proposal.signature.clone(),
+            payload_size: proposal.data.encoded_transactions.len() as u64,
+            chunk_hashes: chunks
+                .iter()
+                .map(|chunk| chunk_hash(&chunk.bytes))
+                .collect(),
+        };
+
+        (header, chunks)
+    }
+
+    /// The hash the leader signs to send this header.
+    ///
+    /// It covers everything but the payload signature, which is checked against the payload once
+    /// it has been rebuilt.
+    #[must_use]
+    pub fn digest(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"DA proposal header");
+        hasher.update(self.view_number.u64().to_le_bytes());
+        hasher.update(self.epoch.u64().to_le_bytes());
+        hasher.update(self.metadata.encode());
+        hasher.update(self.payload_size.to_le_bytes());
+        for hash in &self.chunk_hashes {
+            hasher.update(hash);
+        }
+
+        hasher.finalize().into()
+    }
+
+    /// Start collecting the chunks of the payload.
+    ///
+    /// # Errors
+    /// If the chunk hashes can't describe a payload of the declared size.
+    pub fn assembler(&self) -> Result<PayloadAssembler, PayloadStreamError> {
+        PayloadAssembler::new(self.chunk_hashes.clone(), self.payload_size)
+    }
+
+    /// Rebuild the DA proposal from this header and its rebuilt payload.
+    #[must_use]
+    pub fn into_proposal(self, payload: Vec<u8>) -> Proposal<TYPES, DaProposal2<TYPES>> {
+        Proposal {
+            data: DaProposal2 {
+                encoded_transactions: Arc::from(payload),
+                metadata: self.metadata,
+                view_number: self.view_number,
+                epoch: self.epoch,
+            },
+            signature: self.payload_signature,
+            _pd: PhantomData,
+        }
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for DaProposalHeader<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
+/// A piece of a streamed payload.
+#[derive(derive_more::Debug, Serialize, Deserialize, Clone, Eq, PartialEq, Hash)]
+#[serde(bound = "TYPES: NodeType")]
+pub struct PayloadChunk<TYPES: NodeType> {
+    /// View of the DA proposal whose payload this is part of
+    pub view_number: TYPES::View,
+    /// Position of this chunk in the payload
+    pub index: u32,
+    /// The bytes of the payload in this chunk
+    #[debug(skip)]
+    pub bytes: Vec<u8>,
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for PayloadChunk<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
+/// Reasons a streamed payload can't be rebuilt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum PayloadStreamError {
+    /// The header lists no chunks for a non-empty payload, or more chunks than there are bytes
+    #[error("{chunks} chunks can't make up a payload of {size} bytes")]
+    BadChunkCount {
+        /// Number of chunks listed in the header
+        chunks: usize,
+        /// Declared size of the payload
+        size: u64,
+    },
+    /// The chunk is not one of those listed in the header
+    #[error("Unexpected chunk {index}")]
+    UnexpectedChunk {
+        /// Index of the chunk
+        index: u32,
+    },
+    /// The chunk does not have the hash listed in the header
+    #[error("Chunk {index} does not match its hash")]
+    HashMismatch {
+        /// Index of the chunk
+        index: u32,
+    },
+    /// The rebuilt payload is not the declared size
+    #[error("Payload is {actual} bytes but was declared as {expected} bytes")]
+    SizeMismatch {
+        /// Declared size of the payload
+        expected: u64,
+        /// Size of the rebuilt payload
+        actual: u64,
+    },
+    /// Some chunks have not been received yet
+    #[error("{missing} chunks are still missing")]
+    Incomplete {
+        /// Number of chunks not received yet
+        missing: usize,
+    },
+}
+
+/// Collects the chunks of a streamed payload, checking each against its hash as it arrives.
+///
+/// The payload is also hashed as a whole while it arrives, a chunk at a time as soon as the chunks
+/// before it are in, so that the leader's signature of the payload can be checked the moment the
+/// last chunk lands.
+#[derive(Debug, Clone)]
+pub struct PayloadAssembler {
+    /// Expected hash of each chunk
+    chunk_hashes: Vec<ChunkHash>,
+    /// The chunks received so far
+    chunks: Vec<Option<Vec<u8>>>,
+    /// Number of chunks received so far
+    received: usize,
+    /// Declared size of the payload
+    payload_size: u64,
+    /// Hash of the chunks before `hashed_chunks`, all of which have been received
+    payload_hasher: Sha256,
+    /// Number of chunks at the start of the payload which have been hashed
+    hashed_chunks: usize,
+    /// Size of the chunks which have been hashed, in bytes
+    hashed_bytes: u64,
+}
+
+impl PayloadAssembler {
+    /// Start collecting a payload of `payload_size` bytes made of chunks with `chunk_hashes`.
+    ///
+    /// # Errors
+    /// If the number of chunks can't describe a payload of that size.
+    pub fn new(
+        chunk_hashes: Vec<ChunkHash>,
+        payload_size: u64,
+    ) -> Result<Self, PayloadStreamError> {
+        let count = chunk_hashes.len();
+        if (count == 0 && payload_size > 0) || count as u64 > payload_size.max(1) {
+            return Err(PayloadStreamError::BadChunkCount {
+                chunks: count,
+                size: payload_size,
+            });
+        }
+
+        Ok(Self {
+            chunk_hashes,
+            chunks: vec![None; count],
+            received: 0,
+            payload_size,
+            payload_hasher: Sha256::new(),
+            hashed_chunks: 0,
+            hashed_bytes: 0,
+        })
+    }
+
+    /// Add a chunk, once it has been checked against its hash. Chunks received twice are ignored.
+    ///
+    /// # Errors
+    /// If the chunk is not expected or does not match its hash, or if the chunks received so far
+    /// already make the payload larger than declared.
+    pub fn insert(&mut self, index: u32, bytes: Vec<u8>) -> Result<(), PayloadStreamError> {
+        let position = usize::try_from(index).unwrap_or(usize::MAX);
+        let (Some(expected), Some(slot)) = (
+            self.chunk_hashes.get(position),
+            self.chunks.get_mut(position),
+        ) else {
+            return Err(PayloadStreamError::UnexpectedChunk { index });
+        };
+        if slot.is_some() {
+            return Ok(());
+        }
+        if chunk_hash(&bytes) != *expected {
+            return Err(PayloadStreamError::HashMismatch { index });
+        }
+
+        *slot = Some(bytes);
+        self.received += 1;
+        self.hash_received_prefix()
+    }
+
+    /// Hash the chunks which follow those hashed so far without a gap.
+    ///
+    /// # Errors
+    /// If they make the payload larger than declared.
+    fn hash_received_prefix(&mut self) -> Result<(), PayloadStreamError> {
+        while let Some(Some(bytes)) = self.chunks.get(self.hashed_chunks) {
+            self.hashed_bytes += bytes.len() as u64;
+            if self.hashed_bytes > self.payload_size {
+                return Err(PayloadStreamError::SizeMismatch {
+                    expected: self.payload_size,
+                    actual: self.hashed_bytes,
+                });
+            }
+            self.payload_hasher.update(bytes);
+            self.hashed_chunks += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Whether every chunk has been received.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.received == self.chunks.len()
+    }
+
+    /// Join the chunks into the payload.
+    ///
+    /// # Errors
+    /// If chunks are missing, or the payload is not the declared size.
+    pub fn finish(self) -> Result<Vec<u8>, PayloadStreamError> {
+        self.finish_with_hash().map(|(payload, _)| payload)
+    }
+
+    /// Join the chunks into the payload, along with its SHA-256 hash, which is what the leader
+    /// signs.
+    ///
+    /// # Errors
+    /// If chunks are missing, or the payload is not the declared size.
+    pub fn finish_with_hash(self) -> Result<(Vec<u8>, [u8; 32]), PayloadStreamError> {
+        if !self.is_complete() {
+            return Err(PayloadStreamError::Incomplete {
+                missing: self.chunks.len() - self.received,
+            });
+        }
+        if self.hashed_bytes != self.payload_size {
+            return Err(PayloadStreamError::SizeMismatch {
+                expected: self.payload_size,
+                actual: self.hashed_bytes,
+            });
+        }
+
+        let payload_hash = self.payload_hasher.finalize().into();
+        let payload: Vec<u8> = self.chunks.into_iter().flatten().flatten().collect();
+
+        Ok((payload, payload_hash))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Split `payload` into chunks of `chunk_size` bytes, with their hashes.
+    fn split(payload: &[u8], chunk_size: usize) -> (Vec<ChunkHash>, Vec<Vec<u8>>) {
+        let chunks: Vec<Vec<u8>> = payload.chunks(chunk_size).map(<[u8]>::to_vec).collect();
+        (
+            chunks.iter().map(|chunk| chunk_hash(chunk)).collect(),
+            chunks,
+        )
+    }
+
+    #[test]
+    fn payload_is_rebuilt_from_chunks_in_any_order() {
+        let payload: Vec<u8> = (0..=255).cycle().take(1000).collect();
+        let (hashes, chunks) = split(&payload, 64);
+        let mut assembler = PayloadAssembler::new(hashes, payload.len() as u64).unwrap();
+
+        let mut indexed: Vec<_> = (0..).zip(&chunks).collect();
+        indexed.reverse();
+        for (index, bytes) in indexed {
+            assert!(!assembler.is_complete());
+            assembler.insert(index, bytes.clone()).unwrap();
+        }
+        // A repeated chunk changes nothing
+        assembler.insert(0, chunks[0].clone()).unwrap();
+
+        assert!(assembler.is_complete());
+        assert_eq!(
+            assembler.finish_with_hash().unwrap(),
+            (payload.clone(), Sha256::digest(&payload).into())
+        );
+    }
+
+    #[test]
+    fn oversized_payload_is_rejected_before_it_is_complete() {
+        let payload = vec![3u8; 100];
+        let (hashes, chunks) = split(&payload, 30);
+        let mut assembler = PayloadAssembler::new(hashes, 50).unwrap();
+
+        assembler.insert(0, chunks[0].clone()).unwrap();
+        // The last chunk can't be hashed yet, so its size is not known to be wrong
+        assembler.insert(3, chunks[3].clone()).unwrap();
+        assert_eq!(
+            assembler.insert(1, chunks[1].clone()),
+            Err(PayloadStreamError::SizeMismatch {
+                expected: 50,
+                actual: 60
+            })
+        );
+    }
+
+    #[test]
+    fn bad_chunks_are_rejected() {
+        let payload = vec![7u8; 100];
+        let (hashes, chunks) = split(&payload, 40);
+        let mut assembler = PayloadAssembler::new(hashes, payload.len() as u64).unwrap();
+
+        assert_eq!(
+            assembler.insert(3, chunks[0].clone()),
+            Err(PayloadStreamError::UnexpectedChunk { index: 3 })
+        );
+        assert_eq!(
+            assembler.insert(2, chunks[1].clone()),
+            Err(PayloadStreamError::HashMismatch { index: 2 })
+        );
+
+        assembler.insert(0, chunks[0].clone()).unwrap();
+        assert_eq!(
+            assembler.clone().finish(),
+            Err(PayloadStreamError::Incomplete { missing: 2 })
+        );
+    }
+
+    #[test]
+    fn declared_size_is_enforced() {
+        let payload = vec![1u8; 10];
+        let (hashes, chunks) = split(&payload, 4);
+
+        assert_eq!(
+            PayloadAssembler::new(Vec::new(), 10).unwrap_err(),
+            PayloadStreamError::BadChunkCount {
+                chunks: 0,
+                size: 10
+            }
+        );
+        assert!(PayloadAssembler::new(hashes.clone(), 2).is_err());
+
+        let mut assembler = PayloadAssembler::new(hashes, 11).unwrap();
+        for (index, bytes) in (0..).zip(chunks) {
+            assembler.insert(index, bytes).unwrap();
+        }
+        assert_eq!(
+            assembler.finish(),
+            Err(PayloadStreamError::SizeMismatch {
+                expected: 11,
+                actual: 10
+            })
+        );
+    }
+}
@@ -0,0 +1,194 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Replay protection of signed network messages.
+//!
+//! Every [`SignedEnvelope`](crate::message::SignedEnvelope) carries a nonce, a hybrid timestamp
+//! which strictly increases across the envelopes a node seals, and which is signed along with the
+//! message. Receivers remember the nonces they saw from each sender within a window around their
+//! own clock, and reject envelopes whose nonce they saw before or which fall outside the window, so
+//! recorded traffic cannot be injected again once it was delivered.
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    hash::Hash,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
+
+use thiserror::Error;
+
+use crate::hlc::{wall_clock_nanos, HybridTimestamp};
+
+/// Default time around our clock within which nonces are accepted.
+pub const DEFAULT_REPLAY_WINDOW: Duration = Duration::from_secs(30);
+
+/// Number of recorded nonces after which senders with no nonce left in the window are forgotten.
+const SWEEP_INTERVAL: usize = 4096;
+
+/// The last nonce sealed by this process.
+static LAST_NONCE: Mutex<HybridTimestamp> = Mutex::new(HybridTimestamp::new(0, 0));
+
+/// A fresh nonce, later than any nonce this process sealed before.
+#[must_use]
+pub fn next_nonce() -> HybridTimestamp {
+    let mut last = LAST_NONCE.lock().unwrap_or_else(PoisonError::into_inner);
+    *last = HybridTimestamp::successor(*last);
+    *last
+}
+
+/// Reasons to reject the nonce of an envelope.
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ReplayError {
+    /// The nonce was seen before from the same sender
+    #[error("Replayed nonce {0}")]
+    Replayed(HybridTimestamp),
+    /// The nonce is too old to tell whether it was seen before
+    #[error("Nonce {0} is older than the replay window")]
+    Stale(HybridTimestamp),
+    /// The nonce is further ahead of our clock than the window
+    #[error("Nonce {0} is ahead of the replay window")]
+    Future(HybridTimestamp),
+}
+
+/// The nonces seen from each sender within the replay window.
+#[derive(Debug)]
+pub struct ReplayGuard<K> {
+    /// Time around our clock within which nonces are accepted, in nanoseconds
+    window: u64,
+    /// Nonces seen from each sender, no older than the window
+    seen: HashMap<K, BTreeSet<HybridTimestamp>>,
+    /// Number of nonces recorded since senders were last swept
+    since_sweep: usize,
+}
+
+impl<K: Hash + Eq + Clone> ReplayGuard<K> {
+    /// A guard accepting nonces within `window` of our clock.
+    #[must_use]
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window: u64::try_from(window.as_nanos()).unwrap_or(u64::MAX),
+            seen: HashMap::new(),
+            since_sweep: 0,
+        }
+    }
+
+    /// Check `nonce` from `sender` without recording it, which is cheap enough to do before the
+    /// envelope's signature is verified.
+    ///
+    /// # Errors
+    /// Errors if the nonce was seen before, or falls outside the window.
+    pub fn check(&self, sender: &K, nonce: HybridTimestamp) -> Result<(), ReplayError> {
+        self.check_at(sender, nonce, wall_clock_nanos())
+    }
+
+    /// Record `nonce` from `sender`, once its envelope's signature checked out.
+    pub fn record(&mut self, sender: &K, nonce: HybridTimestamp) {
+        self.record_at(sender, nonce, wall_clock_nanos());
+    }
+
+    /// [`Self::check`], given our clock reads `now` unix nanoseconds.
+    ///
+    /// # Errors
+    /// Errors if the nonce was seen before, or falls outside the window.
+    pub fn check_at(
+        &self,
+        sender: &K,
+        nonce: HybridTimestamp,
+        now: u64,
+    ) -> Result<(), ReplayError> {
+        if nonce.physical().saturating_add(self.window) < now {
+            return Err(ReplayError::Stale(nonce));
+        }
+        if nonce.physical() > now.saturating_add(self.window) {
+            return Err(ReplayError::Future(nonce));
+        }
+        if self
+            .seen
+            .get(sender)
+            .is_some_and(|nonces| nonces.contains(&nonce))
+        {
+            return Err(ReplayError::Replayed(nonce));
+        }
+        Ok(())
+    }
+
+    /// [`Self::record`], given our clock reads `now` unix nanoseconds.
+    pub fn record_at(&mut self, sender: &K, nonce: HybridTimestamp, now: u64) {
+        let oldest = HybridTimestamp::new(now.saturating_sub(self.window), 0);
+        let nonces = self.seen.entry(sender.clone()).or_default();
+        nonces.insert(nonce);
+        *nonces = nonces.split_off(&oldest);
+
+        self.since_sweep += 1;
+        if self.since_sweep >= SWEEP_INTERVAL {
+            self.since_sweep = 0;
+            self.seen.retain(|_, nonces| {
+                *nonces = nonces.split_off(&oldest);
+                !nonces.is_empty()
+            });
+        }
+    }
+}
+
+impl<K: Hash + Eq + Clone> Default for ReplayGuard<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_REPLAY_WINDOW)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn nonces_increase() {
+        let first = next_nonce();
+        let second = next_nonce();
+        assert!(second > first);
+    }
+
+    #[test]
+    fn replays_are_rejected() {
+        let second = 1_000_000_000;
+        let now = 1000 * second;
+        let mut guard = ReplayGuard::new(Duration::from_secs(30));
+        let nonce = HybridTimestamp::new(now, 0);
+
+        assert_eq!(guard.check_at(&0u8, nonce, now), Ok(()));
+        guard.record_at(&0u8, nonce, now);
+        assert_eq!(
+            guard.check_at(&0u8, nonce, now),
+            Err(ReplayError::Replayed(nonce))
+        );
+
+        // Another sender may use the same nonce, and the same sender any other one
+        assert_eq!(guard.check_at(&1u8, nonce, now), Ok(()));
+        let earlier = HybridTimestamp::new(now - second, 0);
+        assert_eq!(guard.check_at(&0u8, earlier, now), Ok(()));
+
+        // Nonces outside the window are rejected, since they could have been forgotten
+        let stale = HybridTimestamp::new(now - 31 * second, 0);
+        assert_eq!(
+            guard.check_at(&0u8, stale, now),
+            Err(ReplayError::Stale(stale))
+        );
+        let future = HybridTimestamp::new(now + 31 * second, 0);
+        assert_eq!(
+            guard.check_at(&0u8, future, now),
+            Err(ReplayError::Future(future))
+        );
+
+        // Once its nonce leaves the window, a replay is rejected as stale instead
+        let later = now + 40 * second;
+        guard.record_at(&0u8, HybridTimestamp::new(later, 0), later);
+        assert!(!guard.seen[&0u8].contains(&nonce));
+        assert_eq!(
+            guard.check_at(&0u8, nonce, later),
+            Err(ReplayError::Stale(nonce))
+        );
+    }
+}
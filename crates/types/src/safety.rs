@@ -0,0 +1,379 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Detection of safety violations, and the handlers they are reported to.
+//!
+//! Conflicting QCs, equivocating votes and decided leaves which do not extend the decided chain can
+//! only happen if more than a third of the stake is faulty, or if this node has a bug. Either way
+//! an operator needs to know right away, so they are routed to a [`SafetyAlertHandler`].
+//!
+//! Conflicting QCs can also be packaged as a [`ForkProof`], which anyone holding the stake table
+//! can check, for monitoring, slashing and fraud proofs.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Debug,
+    num::NonZeroU64,
+    sync::Arc,
+};
+
+use committable::{Commitment, Committable};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    consensus::LeafMap,
+    data::Leaf2,
+    message::UpgradeLock,
+    simple_certificate::QuorumCertificate2,
+    simple_vote::QuorumVote2,
+    traits::{
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::{Certificate, HasViewNumber, Vote},
+};
+
+/// Something which must never happen if fewer than a third of the stake is faulty.
+#[derive(Clone, Debug)]
+pub enum SafetyViolation<TYPES: NodeType> {
+    /// Two valid QCs for different leaves in the same view
+    ConflictingQcs {
+        /// The view of both QCs
+        view: TYPES::View,
+        /// The QC we saw first
+        first: QuorumCertificate2<TYPES>,
+        /// The conflicting QC
+        second: QuorumCertificate2<TYPES>,
+    },
+    /// A node signed votes for two different leaves in the same view
+    ConflictingVotes {
+        /// The view of both votes
+        view: TYPES::View,
+        /// The node which signed both votes
+        voter: TYPES::SignatureKey,
+        /// The vote we saw first
+        first: QuorumVote2<TYPES>,
+        /// The conflicting vote
+        second: QuorumVote2<TYPES>,
+    },
+    /// A newly decided leaf does not extend the decided chain
+    DecideDoesNotChain {
+        /// View of the leaf which does not chain
+        view: TYPES::View,
+        /// Commitment of the leaf it should extend
+        expected_parent: Commitment<Leaf2<TYPES>>,
+        /// Commitment of the leaf it does extend
+        actual_parent: Commitment<Leaf2<TYPES>>,
+    },
+}
+
+impl<TYPES: NodeType> SafetyViolation<TYPES> {
+    /// The proof of this violation which can be handed to others, if there is one
+    #[must_use]
+    pub fn fork_proof(&self) -> Option<ForkProof<TYPES>> {
+        match self {
+            Self::ConflictingQcs { first, second, .. } => Some(ForkProof::Equivocation {
+                first: first.clone(),
+                second: second.clone(),
+            }),
+            Self::ConflictingVotes { .. } | Self::DecideDoesNotChain { .. } => None,
+        }
+    }
+}
+
+/// Proof that two QCs conflict, which anyone holding the stake table of their epoch can check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound = "")]
+pub enum ForkProof<TYPES: NodeType> {
+    /// Two QCs for different leaves in the same view, which takes more than a third of the stake
+    /// voting twice
+    Equivocation {
+        /// One of the QCs
+        first: QuorumCertificate2<TYPES>,
+        /// The other QC
+        second: QuorumCertificate2<TYPES>,
+    },
+    /// Two QCs in different views, where the leaf of the higher one does not descend from the leaf
+    /// of the lower one.
+    ///
+    /// Leaders may legitimately abandon a certified leaf which was not decided, so this is only a
+    /// fault if the lower leaf was decided, which the verifier has to know from elsewhere, e.g.
+    /// from its own decided chain.
+    Divergence {
+        /// The QC in the lower view
+        lower: QuorumCertificate2<TYPES>,
+        /// The QC in the higher view
+        higher: QuorumCertificate2<TYPES>,
+        /// The leaf of the higher QC followed by its ancestors, down to the first one in a view
+        /// no higher than the lower QC's
+        branch: Vec<Leaf2<TYPES>>,
+    },
+}
+
+/// Reasons a [`ForkProof`] does not hold
+#[derive(Clone, Debug, PartialEq, Eq, Error)]
+pub enum ForkProofError<TYPES: NodeType> {
+    /// A QC of the proof is not signed by a quorum of the stake table
+    #[error("QC for view {0} is not valid")]
+    InvalidQc(TYPES::View),
+    /// The QCs do not conflict
+    #[error("QCs for views {0} and {1} do not conflict")]
+    NoConflict(TYPES::View, TYPES::View),
+    /// The branch does not lead from the higher QC's leaf past the lower QC's view
+    #[error("Branch of the leaf certified in view {0} is malformed")]
+    MalformedBranch(TYPES::View),
+}
+
+impl<TYPES: NodeType> ForkProof<TYPES> {
+    /// The proof that `first` and `second` conflict, if they do.
+    ///
+    /// QCs in the same view conflict if they certify different leaves. For QCs in different
+    /// views, the leaf of the higher one is followed back through `leaves` past the view of the
+    /// lower one. If it does not lead to the lower leaf they conflict, as far as they can; see
+    /// [`ForkProof::Divergence`]. If `leaves` misses an ancestor we cannot tell, and return `None`.
+    #[must_use]
+    pub fn find(
+        first: &QuorumCertificate2<TYPES>,
+        second: &QuorumCertificate2<TYPES>,
+        leaves: &LeafMap<TYPES>,
+    ) -> Option<Self> {
+        if first.view_number() == second.view_number() {
+            return (first.data.leaf_commit != second.data.leaf_commit).then(|| {
+                Self::Equivocation {
+                    first: first.clone(),
+                    second: second.clone(),
+                }
+            });
+        }
+
+        let (lower, higher) = if first.view_number() < second.view_number() {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        let mut branch = Vec::new();
+        let mut commitment = higher.data.leaf_commit;
+        loop {
+            if commitment == lower.data.leaf_commit {
+                return None;
+            }
+            let leaf = leaves.get(&commitment)?;
+            branch.push((**leaf).clone());
+            if leaf.view_number() <= lower.view_number() {
+                return Some(Self::Divergence {
+                    lower: lower.clone(),
+                    higher: higher.clone(),
+                    branch,
+                });
+            }
+            commitment = leaf.parent_commitment();
+        }
+    }
+
+    /// Check that the QCs of the proof are valid for `stake_table` and `threshold`, and conflict.
+    ///
+    /// # Errors
+    /// If a QC is invalid, or the proof does not show that they conflict.
+    pub async fn verify<V: Versions>(
+        &self,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+        threshold: NonZeroU64,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<(), ForkProofError<TYPES>> {
+        let (first, second) = match self {
+            Self::Equivocation { first, second } => (first, second),
+            Self::Divergence { lower, higher, .. } => (lower, higher),
+        };
+        for qc in [first, second] {
+            if !qc.is_valid_cert(stake_table, threshold, upgrade_lock).await {
+                return Err(ForkProofError::InvalidQc(qc.view_number()));
+            }
+        }
+
+        let no_conflict = ForkProofError::NoConflict(first.view_number(), second.view_number());
+        match self {
+            Self::Equivocation { first, second } => {
+                if first.view_number() != second.view_number()
+                    || first.data.leaf_commit == second.data.leaf_commit
+                {
+                    return Err(no_conflict);
+                }
+            }
+            Self::Divergence {
+                lower,
+                higher,
+                branch,
+            } => {
+                if lower.view_number() >= higher.view_number() {
+                    return Err(no_conflict);
+                }
+                Self::check_branch(lower, higher, branch)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Check that `branch` leads from the leaf of `higher` past the view of `lower`, without
+    /// passing through the leaf of `lower`.
+    fn check_branch(
+        lower: &QuorumCertificate2<TYPES>,
+        higher: &QuorumCertificate2<TYPES>,
+        branch: &[Leaf2<TYPES>],
+    ) -> Result<(), ForkProofError<TYPES>> {
+        let malformed = ForkProofError::MalformedBranch(higher.view_number());
+        let (Some(first), Some(last)) = (branch.first(), branch.last()) else {
+            return Err(malformed);
+        };
+        if first.commit() != higher.data.leaf_commit || last.view_number() > lower.view_number() {
+            return Err(malformed);
+        }
+        for pair in branch.windows(2) {
+            if pair[0].parent_commitment() != pair[1].commit()
+                || pair[0].view_number() <= pair[1].view_number()
+            {
+                return Err(malformed);
+            }
+        }
+        if branch
+            .iter()
+            .any(|leaf| leaf.commit() == lower.data.leaf_commit)
+        {
+            return Err(ForkProofError::NoConflict(
+                lower.view_number(),
+                higher.view_number(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// Receives the safety violations detected by a node.
+///
+/// Handlers are called while consensus state is locked, so they should hand anything slow (e.g.
+/// paging someone) off to another task.
+pub trait SafetyAlertHandler<TYPES: NodeType>: Debug + Send + Sync + 'static {
+    /// Report a safety violation.
+    fn alert(&self, violation: SafetyViolation<TYPES>);
+}
+
+/// The default handler, which logs violations at error level.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LoggingSafetyAlertHandler;
+
+impl<TYPES: NodeType> SafetyAlertHandler<TYPES> for LoggingSafetyAlertHandler {
+    fn alert(&self, violation: SafetyViolation<TYPES>) {
+        tracing::error!("SAFETY VIOLATION: {violation:?}");
+    }
+}
+
+/// Keeps the QCs and votes of undecided views, and checks new ones against them.
+#[derive(Clone, Debug)]
+pub struct SafetyMonitor<TYPES: NodeType> {
+    /// Where violations are reported
+    handler: Arc<dyn SafetyAlertHandler<TYPES>>,
+    /// The first QC seen in each view
+    qcs: BTreeMap<TYPES::View, QuorumCertificate2<TYPES>>,
+    /// The first vote seen from each node in each view
+    votes: BTreeMap<TYPES::View, HashMap<TYPES::SignatureKey, QuorumVote2<TYPES>>>,
+}
+
+impl<TYPES: NodeType> Default for SafetyMonitor<TYPES> {
+    fn default() -> Self {
+        Self {
+            handler: Arc::new(LoggingSafetyAlertHandler),
+            qcs: BTreeMap::new(),
+            votes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<TYPES: NodeType> SafetyMonitor<TYPES> {
+    /// Report violations to `handler` from now on.
+    pub fn set_handler(&mut self, handler: Arc<dyn SafetyAlertHandler<TYPES>>) {
+        self.handler = handler;
+    }
+
+    /// Check a valid QC against the other QCs of its view.
+    pub fn check_qc(&mut self, qc: &QuorumCertificate2<TYPES>) {
+        let view = qc.view_number();
+        let Some(first) = self.qcs.get(&view) else {
+            self.qcs.insert(view, qc.clone());
+            return;
+        };
+        if first.data.leaf_commit != qc.data.leaf_commit {
+            self.handler.alert(SafetyViolation::ConflictingQcs {
+                view,
+                first: first.clone(),
+                second: qc.clone(),
+            });
+        }
+    }
+
+    /// Check a vote against the other votes of its sender in its view.
+    ///
+    /// Signatures are only verified when a conflict is found, so that forged votes cannot raise
+    /// false alarms without costing a verification for every vote.
+    pub fn check_vote(&mut self, vote: &QuorumVote2<TYPES>) {
+        let view = vote.view_number();
+        let voter = vote.signing_key();
+        let votes = self.votes.entry(view).or_default();
+        let Some(first) = votes.get(&voter) else {
+            votes.insert(voter, vote.clone());
+            return;
+        };
+        if first.data.leaf_commit == vote.data.leaf_commit {
+            return;
+        }
+
+        let is_signed = |vote: &QuorumVote2<TYPES>| {
+            voter.validate(&vote.signature(), vote.data_commitment().as_ref())
+        };
+        if !is_signed(vote) {
+            return;
+        }
+        if !is_signed(first) {
+            // The vote we kept was forged; keep the genuine one instead
+            votes.insert(voter, vote.clone());
+            return;
+        }
+        self.handler.alert(SafetyViolation::ConflictingVotes {
+            view,
+            voter,
+            first: first.clone(),
+            second: vote.clone(),
+        });
+    }
+
+    /// Check that a newly decided chain, newest leaf first, extends the `previous` decided leaf.
+    pub fn check_decided_chain<'a>(
+        &self,
+        previous: &Leaf2<TYPES>,
+        chain: impl DoubleEndedIterator<Item = &'a Leaf2<TYPES>>,
+    ) {
+        let mut expected_parent = previous.commit();
+        for leaf in chain.rev() {
+            let actual_parent = leaf.parent_commitment();
+            if actual_parent != expected_parent {
+                self.handler.alert(SafetyViolation::DecideDoesNotChain {
+                    view: leaf.view_number(),
+                    expected_parent,
+                    actual_parent,
+                });
+            }
+            expected_parent = leaf.commit();
+        }
+    }
+
+    /// Forget the QCs and votes of views before `view`.
+    pub fn prune(&mut self, view: TYPES::View) {
+        self.qcs = self.qcs.split_off(&view);
+        self.votes = self.votes.split_off(&view);
+    }
+}
@@ -7,6 +7,7 @@
 //! Implementations of the simple certificate type.  Used for Quorum, DA, and Timeout Certificates
 
 use std::{
+    borrow::Cow,
     fmt::{self, Debug, Display, Formatter},
     hash::Hash,
     marker::PhantomData,
@@ -24,10 +25,10 @@ use crate::{
     data::serialize_signature2,
     message::UpgradeLock,
     simple_vote::{
-        DaData, DaData2, QuorumData, QuorumData2, QuorumMarker, TimeoutData, TimeoutData2,
-        UpgradeProposalData, VersionedVoteData, ViewSyncCommitData, ViewSyncCommitData2,
-        ViewSyncFinalizeData, ViewSyncFinalizeData2, ViewSyncPreCommitData, ViewSyncPreCommitData2,
-        Voteable,
+        BatchData, DaData, DaData2, FallbackData, QuorumData, QuorumData2, QuorumMarker,
+        TimeoutData, TimeoutData2, UpgradeProposalData, VersionedVoteData, ViewSyncCommitData,
+        ViewSyncCommitData2, ViewSyncFinalizeData, ViewSyncFinalizeData2, ViewSyncPreCommitData,
+        ViewSyncPreCommitData2, Voteable,
     },
     traits::{
         election::Membership,
@@ -166,7 +167,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData>
     }
     async fn is_valid_cert<V: Versions>(
         &self,
-        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
         threshold: NonZeroU64,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
@@ -174,7 +175,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData>
             return true;
         }
         let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
+            stake_table.to_vec(),
             U256::from(u64::from(threshold)),
         );
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
@@ -199,7 +200,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData>
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]> {
         membership.da_stake_table(epoch)
     }
     /// Proxy's to `Membership.da_total_nodes`
@@ -254,7 +255,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData2<TY
     }
     async fn is_valid_cert<V: Versions>(
         &self,
-        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
         threshold: NonZeroU64,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
@@ -262,7 +263,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData2<TY
             return true;
         }
         let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
+            stake_table.to_vec(),
             U256::from(u64::from(threshold)),
         );
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
@@ -287,7 +288,7 @@ impl<TYPES: NodeType, THRESHOLD: Threshold<TYPES>> Certificate<TYPES, DaData2<TY
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]> {
         membership.da_stake_table(epoch)
     }
     /// Proxy's to `Membership.da_total_nodes`
@@ -345,7 +346,7 @@ impl<
     }
     async fn is_valid_cert<V: Versions>(
         &self,
-        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
         threshold: NonZeroU64,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> bool {
@@ -353,7 +354,7 @@ impl<
             return true;
         }
         let real_qc_pp = <TYPES::SignatureKey as SignatureKey>::public_parameter(
-            stake_table,
+            stake_table.to_vec(),
             U256::from(u64::from(threshold)),
         );
         let Ok(commit) = self.data_commitment(upgrade_lock).await else {
@@ -383,7 +384,7 @@ impl<
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry> {
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]> {
         membership.stake_table(epoch)
     }
 
@@ -458,7 +459,7 @@ impl<TYPES: NodeType> UpgradeCertificate<TYPES> {
         if let Some(ref cert) = upgrade_certificate {
             ensure!(
                 cert.is_valid_cert(
-                    quorum_membership.stake_table(epoch),
+                    &quorum_membership.stake_table(epoch),
                     quorum_membership.upgrade_threshold(epoch),
                     upgrade_lock
                 )
@@ -766,3 +767,10 @@ pub type ViewSyncFinalizeCertificate2<TYPES> =
 /// Type alias for a `UpgradeCertificate`, which is a `SimpleCertificate` of `UpgradeProposalData`
 pub type UpgradeCertificate<TYPES> =
     SimpleCertificate<TYPES, UpgradeProposalData<TYPES>, UpgradeThreshold>;
+/// Type alias for a `BatchCertificate`, which shows that at least one honest node holds a batch
+/// of the DAG mempool
+pub type BatchCertificate<TYPES> = SimpleCertificate<TYPES, BatchData<TYPES>, OneHonestThreshold>;
+/// Type alias for a `FallbackCertificate`, whose aggregated signature is the coin which elects a
+/// leader in the coin fallback
+pub type FallbackCertificate<TYPES> =
+    SimpleCertificate<TYPES, FallbackData<TYPES>, SuccessThreshold>;
@@ -11,14 +11,16 @@ use std::{fmt::Debug, hash::Hash, marker::PhantomData};
 use committable::{Commitment, Committable};
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use utils::anytrace::*;
-use vbs::version::Version;
+use vbs::version::{StaticVersionType, Version};
 
 use crate::{
+    dag_mempool::TransactionBatch,
     data::{Leaf, Leaf2},
     message::UpgradeLock,
     traits::{
         node_implementation::{ConsensusTime, NodeType, Versions},
         signature_key::SignatureKey,
+        signer::Signer,
     },
     vid::VidCommitment,
     vote::{HasViewNumber, Vote},
@@ -58,6 +60,31 @@ pub struct DaData2<TYPES: NodeType> {
     pub epoch: TYPES::Epoch,
 }
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+/// Data used for a vote that we hold a batch of the DAG mempool.
+#[serde(bound(deserialize = ""))]
+pub struct BatchData<TYPES: NodeType> {
+    /// Commitment to the batch
+    pub batch_commit: Commitment<TransactionBatch<TYPES>>,
+    /// Epoch number
+    pub epoch: TYPES::Epoch,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+/// Data used for a vote to elect the next leader by the coin fallback.
+pub struct FallbackData<TYPES: NodeType> {
+    /// The view the fallback round runs in
+    pub round: TYPES::View,
+    /// Epoch number
+    pub epoch: TYPES::Epoch,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+/// Data used for a replica's report of its highest QC to the leader of the view it enters.
+pub struct HighQcData<TYPES: NodeType> {
+    /// View of the highest QC the replica holds
+    pub high_qc_view: TYPES::View,
+    /// Epoch number
+    pub epoch: TYPES::Epoch,
+}
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
 /// Data used for a timeout vote.
 pub struct TimeoutData<TYPES: NodeType> {
     /// View the timeout is for
@@ -195,6 +222,8 @@ impl<T: NodeType> QuorumMarker for ViewSyncPreCommitData2<T> {}
 impl<T: NodeType> QuorumMarker for ViewSyncCommitData2<T> {}
 impl<T: NodeType> QuorumMarker for ViewSyncFinalizeData2<T> {}
 impl<T: NodeType + DeserializeOwned> QuorumMarker for UpgradeProposalData<T> {}
+impl<T: NodeType> QuorumMarker for BatchData<T> {}
+impl<T: NodeType> QuorumMarker for FallbackData<T> {}
 
 /// A simple yes vote over some votable type.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -266,6 +295,35 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES> + 'static> SimpleVote<TYPES, DATA> {
             view_number: view,
         })
     }
+
+    /// Creates a vote signed by `signer`, which may be remote
+    /// # Errors
+    /// If the signer fails to sign the data
+    pub async fn create_signed_vote_with_signer<V: Versions>(
+        data: DATA,
+        view: TYPES::View,
+        signer: &dyn Signer<TYPES::SignatureKey>,
+        upgrade_lock: &UpgradeLock<TYPES, V>,
+    ) -> Result<Self> {
+        let commit = VersionedVoteData::new(data.clone(), view, upgrade_lock)
+            .await?
+            .commit();
+
+        let signature = (
+            signer.public_key().clone(),
+            signer
+                .sign(commit.as_ref())
+                .await
+                .wrap()
+                .context(error!("Failed to sign vote"))?,
+        );
+
+        Ok(Self {
+            signature,
+            data,
+            view_number: view,
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
@@ -280,6 +338,9 @@ pub struct VersionedVoteData<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions
     /// version applied to the view number
     version: Version,
 
+    /// chain the vote is cast on
+    chain_id: u64,
+
     /// phantom data
     _pd: PhantomData<V>,
 }
@@ -301,6 +362,7 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> VersionedVoteData<TYPE
             data,
             view,
             version,
+            chain_id: upgrade_lock.chain_id,
             _pd: PhantomData,
         })
     }
@@ -319,6 +381,7 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> VersionedVoteData<TYPE
             data,
             view,
             version,
+            chain_id: upgrade_lock.chain_id,
             _pd: PhantomData,
         }
     }
@@ -327,11 +390,19 @@ impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> VersionedVoteData<TYPE
 impl<TYPES: NodeType, DATA: Voteable<TYPES>, V: Versions> Committable
     for VersionedVoteData<TYPES, DATA, V>
 {
+    /// From the epochs version on, the commitment is also bound to the chain, so that a key
+    /// which validates on several chains cannot have its vote replayed from one to another.
+    /// Within a chain the view fixes the epoch, so binding the view binds the epoch too.
     fn commit(&self) -> Commitment<Self> {
-        committable::RawCommitmentBuilder::new("Vote")
+        let builder = committable::RawCommitmentBuilder::new("Vote")
             .var_size_bytes(self.data.commit().as_ref())
-            .u64(*self.view)
-            .finalize()
+            .u64(*self.view);
+
+        if self.version < V::Epochs::VERSION {
+            return builder.finalize();
+        }
+
+        builder.u64_field("chain id", self.chain_id).finalize()
     }
 }
 
@@ -395,6 +466,42 @@ impl<TYPES: NodeType> Committable for DaData2<TYPES> {
     }
 }
 
+impl<TYPES: NodeType> Committable for BatchData<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        let BatchData {
+            batch_commit,
+            epoch: _,
+        } = self;
+
+        committable::RawCommitmentBuilder::new("Batch data")
+            .var_size_bytes(batch_commit.as_ref())
+            .finalize()
+    }
+}
+
+impl<TYPES: NodeType> Committable for FallbackData<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        let FallbackData { round, epoch: _ } = self;
+
+        committable::RawCommitmentBuilder::new("Fallback data")
+            .u64(**round)
+            .finalize()
+    }
+}
+
+impl<TYPES: NodeType> Committable for HighQcData<TYPES> {
+    fn commit(&self) -> Commitment<Self> {
+        let HighQcData {
+            high_qc_view,
+            epoch: _,
+        } = self;
+
+        committable::RawCommitmentBuilder::new("High QC data")
+            .u64(**high_qc_view)
+            .finalize()
+    }
+}
+
 impl<TYPES: NodeType> Committable for UpgradeProposalData<TYPES> {
     fn commit(&self) -> Commitment<Self> {
         let builder = committable::RawCommitmentBuilder::new("Upgrade data");
@@ -518,6 +625,9 @@ macro_rules! impl_has_epoch {
 impl_has_epoch!(
     QuorumData2<TYPES>,
     DaData2<TYPES>,
+    BatchData<TYPES>,
+    FallbackData<TYPES>,
+    HighQcData<TYPES>,
     TimeoutData2<TYPES>,
     ViewSyncPreCommitData2<TYPES>,
     ViewSyncCommitData2<TYPES>,
@@ -770,6 +880,81 @@ impl<TYPES: NodeType> ViewSyncFinalizeVote2<TYPES> {
     }
 }
 
+/// A quorum vote as it is sent over the network, with its signer given by position in the stake
+/// table of the vote's epoch rather than by its full public key.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Hash, Eq)]
+#[serde(bound(deserialize = ""))]
+pub struct CompactQuorumVote<TYPES: NodeType> {
+    /// The view this vote was cast for
+    pub view_number: TYPES::View,
+    /// Commitment to the leaf being voted on
+    pub leaf_commit: Commitment<Leaf2<TYPES>>,
+    /// Epoch of the leaf
+    pub epoch: TYPES::Epoch,
+    /// Position of the signer in the stake table
+    pub signer: u32,
+    /// The signer's signature share
+    pub signature: <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType,
+}
+
+impl<TYPES: NodeType> CompactQuorumVote<TYPES> {
+    /// Compact `vote`, whose signer has to be a member of `stake_table`.
+    ///
+    /// # Errors
+    /// If the signer is not in the stake table
+    pub fn compact(
+        vote: &QuorumVote2<TYPES>,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+    ) -> Result<Self> {
+        let signer = stake_table
+            .iter()
+            .position(|entry| TYPES::SignatureKey::public_key(entry) == vote.signature.0)
+            .context(warn!("The signer of the vote is not in the stake table"))?;
+
+        Ok(Self {
+            view_number: vote.view_number,
+            leaf_commit: vote.data.leaf_commit,
+            epoch: vote.data.epoch,
+            signer: u32::try_from(signer)
+                .wrap()
+                .context(error!("Stake table too large for a compact vote"))?,
+            signature: vote.signature.1.clone(),
+        })
+    }
+
+    /// Restore the full vote, looking its signer up in `stake_table`.
+    ///
+    /// # Errors
+    /// If the signer is not in the stake table
+    pub fn expand(
+        self,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+    ) -> Result<QuorumVote2<TYPES>> {
+        let entry = usize::try_from(self.signer)
+            .ok()
+            .and_then(|signer| stake_table.get(signer))
+            .context(warn!(
+                "Compact vote signer {} is not in the stake table",
+                self.signer
+            ))?;
+
+        Ok(SimpleVote {
+            signature: (TYPES::SignatureKey::public_key(entry), self.signature),
+            data: QuorumData2 {
+                leaf_commit: self.leaf_commit,
+                epoch: self.epoch,
+            },
+            view_number: self.view_number,
+        })
+    }
+}
+
+impl<TYPES: NodeType> HasViewNumber<TYPES> for CompactQuorumVote<TYPES> {
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
+
 // Type aliases for simple use of all the main votes.  We should never see `SimpleVote` outside this file
 
 /// Quorum vote Alias
@@ -805,3 +990,12 @@ pub type ViewSyncCommitVote2<TYPES> = SimpleVote<TYPES, ViewSyncCommitData2<TYPE
 pub type UpgradeVote<TYPES> = SimpleVote<TYPES, UpgradeProposalData<TYPES>>;
 /// Upgrade proposal 2 vote
 pub type UpgradeVote2<TYPES> = SimpleVote<TYPES, UpgradeData2<TYPES>>;
+
+/// DAG mempool batch availability vote type alias
+pub type BatchVote<TYPES> = SimpleVote<TYPES, BatchData<TYPES>>;
+
+/// Fallback coin vote type alias
+pub type FallbackVote<TYPES> = SimpleVote<TYPES, FallbackData<TYPES>>;
+
+/// Signed report of a replica's highest QC type alias
+pub type HighQcVote<TYPES> = SimpleVote<TYPES, HighQcData<TYPES>>;
@@ -6,10 +6,18 @@
 
 //! Types and structs related to the stake table
 
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+};
+
 use primitive_types::U256;
 use serde::{Deserialize, Serialize};
 
-use crate::traits::signature_key::{SignatureKey, StakeTableEntryType};
+use crate::{
+    traits::signature_key::{SignatureKey, StakeTableEntryType},
+    PeerConfig,
+};
 
 /// Stake table entry
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Hash, Eq)]
@@ -40,4 +48,240 @@ impl<K: SignatureKey> StakeTableEntry<K> {
     }
 }
 
+/// How the weight of a stake grows with the number of epochs it has been bonded for.
+///
+/// Every curve starts at the bonded amount and reaches at most `max_bonus_percent` more after
+/// `ramp_epochs`, so long-term validators gain influence without an unbounded advantage.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum BondingCurve {
+    /// A stake weighs its amount, however long it has been bonded.
+    #[default]
+    Flat,
+    /// The bonus grows linearly with the bonded epochs.
+    Linear {
+        /// Epochs after which the full bonus applies
+        ramp_epochs: u64,
+        /// Bonus at the end of the ramp, as a percentage of the amount
+        max_bonus_percent: u64,
+    },
+    /// The bonus grows with the square root of the bonded epochs, so the first epochs of bonding
+    /// count for the most.
+    Sqrt {
+        /// Epochs after which the full bonus applies
+        ramp_epochs: u64,
+        /// Bonus at the end of the ramp, as a percentage of the amount
+        max_bonus_percent: u64,
+    },
+}
+
+impl BondingCurve {
+    /// The effective weight of `amount` once it has been bonded for `bonded_epochs` epochs.
+    #[must_use]
+    pub fn effective_weight(&self, amount: U256, bonded_epochs: u64) -> U256 {
+        let (ramp_epochs, max_bonus_percent) = match *self {
+            Self::Flat => return amount,
+            Self::Linear {
+                ramp_epochs,
+                max_bonus_percent,
+            }
+            | Self::Sqrt {
+                ramp_epochs,
+                max_bonus_percent,
+            } => (ramp_epochs, max_bonus_percent),
+        };
+        let max_bonus = amount.saturating_mul(U256::from(max_bonus_percent)) / 100;
+        if bonded_epochs >= ramp_epochs {
+            return amount.saturating_add(max_bonus);
+        }
+
+        // How far along the ramp we are, in epochs
+        let progress = match self {
+            Self::Sqrt { .. } => {
+                // sqrt(bonded / ramp) * ramp == sqrt(bonded * ramp)
+                (U256::from(bonded_epochs) * U256::from(ramp_epochs)).integer_sqrt()
+            }
+            _ => U256::from(bonded_epochs),
+        };
+        amount.saturating_add(max_bonus.saturating_mul(progress) / U256::from(ramp_epochs))
+    }
+}
+
+/// Attributes of a node besides its stake, used to spread leadership.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct NodeMetadata {
+    /// The region the node runs in
+    #[serde(default)]
+    pub region: Option<String>,
+    /// The operator who runs the node
+    #[serde(default)]
+    pub operator: Option<String>,
+}
+
+/// An attribute which consecutive leaders must not share, so that one region or operator going
+/// down does not stall many views in a row.
+///
+/// The constraint is applied by ordering the stake table, which round-robin elections follow.
+/// Elections which draw leaders at random are not constrained.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LeaderConstraint {
+    /// Leaders take turns in stake table order.
+    #[default]
+    None,
+    /// Consecutive leaders run in different regions.
+    DistinctRegion,
+    /// Consecutive leaders are run by different operators.
+    DistinctOperator,
+}
+
+impl LeaderConstraint {
+    /// The value of the constrained attribute in `metadata`, if it is set.
+    fn attribute(self, metadata: &NodeMetadata) -> Option<&str> {
+        match self {
+            Self::None => None,
+            Self::DistinctRegion => metadata.region.as_deref(),
+            Self::DistinctOperator => metadata.operator.as_deref(),
+        }
+    }
+
+    /// Order `peers` so that no two consecutive peers share the constrained attribute, counting
+    /// the last and the first as consecutive since leaders wrap around.
+    ///
+    /// The order depends only on the order and metadata of `peers`, so every node arrives at the
+    /// same one. Peers without the attribute set may neighbour anyone. The constraint can be met
+    /// unless more than half of the peers share a value, in which case some of them neighbour
+    /// each other.
+    #[must_use]
+    pub fn arrange<KEY: SignatureKey>(self, peers: Vec<PeerConfig<KEY>>) -> Vec<PeerConfig<KEY>> {
+        if self == Self::None {
+            return peers;
+        }
+
+        // Peers grouped by attribute, in order of first appearance
+        let mut groups: Vec<VecDeque<PeerConfig<KEY>>> = Vec::new();
+        let mut group_of: BTreeMap<String, usize> = BTreeMap::new();
+        for peer in peers {
+            let Some(attribute) = self.attribute(&peer.metadata).map(str::to_owned) else {
+                groups.push(VecDeque::from([peer]));
+                continue;
+            };
+            let group = *group_of.entry(attribute).or_insert_with(|| {
+                groups.push(VecDeque::new());
+                groups.len() - 1
+            });
+            groups[group].push_back(peer);
+        }
+
+        let total = groups.iter().map(VecDeque::len).sum();
+        let mut arranged = Vec::with_capacity(total);
+        let mut first = None;
+        let mut last = None;
+        while arranged.len() < total {
+            // Take from the fullest group other than the last one. On a tie, prefer the first
+            // group taken from, so it does not end up closing the cycle next to itself.
+            let Some(next) = (0..groups.len())
+                .filter(|&group| !groups[group].is_empty() && Some(group) != last)
+                .max_by_key(|&group| (groups[group].len(), Some(group) == first, Reverse(group)))
+                .or(last)
+            else {
+                break;
+            };
+            arranged.extend(groups[next].pop_front());
+            first = first.or(Some(next));
+            last = Some(next);
+        }
+
+        arranged
+    }
+}
+
 // TODO(Chengyu): add stake table snapshot here
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{signature_key::BLSPubKey, ValidatorConfig};
+
+    #[test]
+    fn flat_curve_ignores_bonding_time() {
+        let amount = U256::from(1000);
+        assert_eq!(BondingCurve::Flat.effective_weight(amount, 0), amount);
+        assert_eq!(
+            BondingCurve::Flat.effective_weight(amount, u64::MAX),
+            amount
+        );
+    }
+
+    #[test]
+    fn bonus_grows_along_the_ramp() {
+        let amount = U256::from(1000);
+        let linear = BondingCurve::Linear {
+            ramp_epochs: 100,
+            max_bonus_percent: 50,
+        };
+        assert_eq!(linear.effective_weight(amount, 0), amount);
+        assert_eq!(linear.effective_weight(amount, 50), U256::from(1250));
+        assert_eq!(linear.effective_weight(amount, 100), U256::from(1500));
+        assert_eq!(linear.effective_weight(amount, 1000), U256::from(1500));
+
+        let sqrt = BondingCurve::Sqrt {
+            ramp_epochs: 100,
+            max_bonus_percent: 50,
+        };
+        assert_eq!(sqrt.effective_weight(amount, 0), amount);
+        assert_eq!(sqrt.effective_weight(amount, 25), U256::from(1250));
+        assert_eq!(sqrt.effective_weight(amount, 100), U256::from(1500));
+    }
+
+    #[test]
+    fn empty_ramp_gives_the_full_bonus() {
+        let curve = BondingCurve::Linear {
+            ramp_epochs: 0,
+            max_bonus_percent: 100,
+        };
+        assert_eq!(curve.effective_weight(U256::from(7), 0), U256::from(14));
+    }
+
+    /// Peers running in `regions`, one each.
+    fn peers_in(regions: &[&str]) -> Vec<PeerConfig<BLSPubKey>> {
+        (0..)
+            .zip(regions)
+            .map(|(i, region)| PeerConfig {
+                metadata: NodeMetadata {
+                    region: Some((*region).to_string()),
+                    operator: None,
+                },
+                ..ValidatorConfig::generated_from_seed_indexed([0u8; 32], i, 1, true)
+                    .public_config()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn consecutive_leaders_differ_in_region() {
+        let peers = peers_in(&["a", "a", "a", "a", "a", "a", "b", "b", "b", "c", "c", "c"]);
+        let arranged = LeaderConstraint::DistinctRegion.arrange(peers.clone());
+
+        assert_eq!(arranged.len(), peers.len());
+        assert!(peers.iter().all(|peer| arranged.contains(peer)));
+        for (i, peer) in arranged.iter().enumerate() {
+            let next = &arranged[(i + 1) % arranged.len()];
+            assert_ne!(peer.metadata.region, next.metadata.region);
+        }
+        assert_eq!(
+            LeaderConstraint::DistinctRegion.arrange(peers.clone()),
+            arranged
+        );
+    }
+
+    #[test]
+    fn unset_attributes_leave_the_order_alone() {
+        let peers = peers_in(&["a", "a", "b"]);
+        assert_eq!(LeaderConstraint::None.arrange(peers.clone()), peers);
+        assert_eq!(
+            LeaderConstraint::DistinctOperator.arrange(peers.clone()),
+            peers
+        );
+    }
+}
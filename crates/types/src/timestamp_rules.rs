@@ -0,0 +1,206 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Rules for validating the timestamp a leader puts in a proposed block header.
+//!
+//! The rules are only checked on quorum proposals. A DA proposal carries the payload but not the
+//! header, and a DA vote only attests that the payload is available, not that its block is valid.
+//! DA members validate the header, timestamp included, as replicas when the quorum proposal
+//! arrives, and nobody votes for a leaf before that.
+
+use std::time::Duration;
+
+use thiserror::Error;
+
+use crate::hlc::{wall_clock_nanos, HybridTimestamp};
+
+/// Default tolerance for a proposal timestamp ahead of our local clock.
+const DEFAULT_MAX_DRIFT: Duration = Duration::from_secs(12);
+
+/// How timestamp rule violations are treated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TimestampMode {
+    /// Reject proposals which violate the rules.
+    #[default]
+    Strict,
+    /// Only log violations. Intended for tests, where node clocks are not meaningful.
+    Lenient,
+}
+
+/// Timestamp validation rules for proposals.
+///
+/// A proposed block's timestamp must be later than its parent's, and must not be further ahead of
+/// our local clock than `max_drift`. Timestamps in the past are only bounded by the parent, so
+/// that replicas catching up on old proposals don't reject them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct TimestampRules {
+    /// Whether violations reject the proposal
+    #[serde(default)]
+    pub mode: TimestampMode,
+    /// How far ahead of our local clock a proposal timestamp may be
+    #[serde(default = "default_max_drift")]
+    pub max_drift: Duration,
+}
+
+/// Default value of [`TimestampRules::max_drift`], for serde.
+fn default_max_drift() -> Duration {
+    DEFAULT_MAX_DRIFT
+}
+
+impl Default for TimestampRules {
+    fn default() -> Self {
+        Self {
+            mode: TimestampMode::default(),
+            max_drift: DEFAULT_MAX_DRIFT,
+        }
+    }
+}
+
+/// Ways a proposal timestamp can violate the [`TimestampRules`]. Physical times are unix
+/// nanoseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TimestampError {
+    /// The proposal is not later than its parent
+    #[error("Proposal timestamp {proposed} is not later than the parent timestamp {parent}")]
+    NotMonotonic {
+        /// Timestamp of the parent block
+        parent: HybridTimestamp,
+        /// Timestamp of the proposed block
+        proposed: HybridTimestamp,
+    },
+    /// The proposal is too far ahead of our clock
+    #[error("Proposal timestamp {proposed} is more than {max_drift:?} ahead of local time {now}")]
+    TooFarAhead {
+        /// Timestamp of the proposed block
+        proposed: HybridTimestamp,
+        /// Our local time
+        now: u64,
+        /// Configured drift tolerance
+        max_drift: Duration,
+    },
+}
+
+impl TimestampRules {
+    /// Rules which never reject a proposal.
+    #[must_use]
+    pub fn lenient() -> Self {
+        Self {
+            mode: TimestampMode::Lenient,
+            ..Self::default()
+        }
+    }
+
+    /// Check the proposed timestamp against the parent timestamp and the given local time, in
+    /// unix nanoseconds.
+    ///
+    /// # Errors
+    /// If the timestamp violates the rules, regardless of the mode.
+    pub fn check_at(
+        &self,
+        parent: HybridTimestamp,
+        proposed: HybridTimestamp,
+        now: u64,
+    ) -> Result<(), TimestampError> {
+        if proposed <= parent {
+            return Err(TimestampError::NotMonotonic { parent, proposed });
+        }
+
+        if proposed.ahead_of(now) > self.max_drift {
+            return Err(TimestampError::TooFarAhead {
+                proposed,
+                now,
+                max_drift: self.max_drift,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Validate a proposal timestamp against its parent, using the local clock.
+    ///
+    /// Headers without a timestamp are always accepted. In lenient mode violations are logged
+    /// and accepted.
+    ///
+    /// # Errors
+    /// If the timestamp violates the rules in strict mode.
+    pub fn validate(
+        &self,
+        parent: Option<HybridTimestamp>,
+        proposed: Option<HybridTimestamp>,
+    ) -> Result<(), TimestampError> {
+        let (Some(parent), Some(proposed)) = (parent, proposed) else {
+            return Ok(());
+        };
+
+        match (
+            self.check_at(parent, proposed, wall_clock_nanos()),
+            self.mode,
+        ) {
+            (Err(e), TimestampMode::Lenient) => {
+                tracing::warn!("Ignoring invalid proposal timestamp in lenient mode: {e}");
+                Ok(())
+            }
+            (result, _) => result,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Nanoseconds in a second
+    const SECOND: u64 = 1_000_000_000;
+
+    /// The timestamp `seconds` into the unix epoch, with counter `logical`
+    fn at(seconds: u64, logical: u32) -> HybridTimestamp {
+        HybridTimestamp::new(seconds * SECOND, logical)
+    }
+
+    #[test]
+    fn monotonic() {
+        let rules = TimestampRules::default();
+        assert!(rules.check_at(at(10, 0), at(10, 1), 10 * SECOND).is_ok());
+        assert!(rules.check_at(at(10, 5), at(11, 0), 11 * SECOND).is_ok());
+        assert_eq!(
+            rules.check_at(at(10, 0), at(9, 0), 10 * SECOND),
+            Err(TimestampError::NotMonotonic {
+                parent: at(10, 0),
+                proposed: at(9, 0)
+            })
+        );
+        // A block may not reuse its parent's timestamp
+        assert!(rules.check_at(at(10, 1), at(10, 1), 10 * SECOND).is_err());
+    }
+
+    #[test]
+    fn drift_window() {
+        let rules = TimestampRules {
+            mode: TimestampMode::Strict,
+            max_drift: Duration::from_secs(5),
+        };
+        assert!(rules.check_at(at(0, 0), at(105, 0), 100 * SECOND).is_ok());
+        assert!(rules.check_at(at(0, 0), at(106, 0), 100 * SECOND).is_err());
+        // Old proposals are fine, as long as they extend their parent.
+        assert!(rules.check_at(at(0, 0), at(1, 0), 100 * SECOND).is_ok());
+    }
+
+    #[test]
+    fn lenient_and_missing_timestamps() {
+        assert!(TimestampRules::lenient()
+            .validate(Some(at(10, 0)), Some(at(0, 0)))
+            .is_ok());
+        assert!(TimestampRules::default()
+            .validate(Some(at(10, 0)), Some(at(0, 0)))
+            .is_err());
+        assert!(TimestampRules::default()
+            .validate(None, Some(at(0, 0)))
+            .is_ok());
+        assert!(TimestampRules::default()
+            .validate(Some(at(10, 0)), None)
+            .is_ok());
+    }
+}
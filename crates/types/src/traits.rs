@@ -5,8 +5,11 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! Common traits for the `HotShot` protocol
+pub mod application;
+pub mod archive;
 pub mod auction_results_provider;
 pub mod block_contents;
+pub mod commitment;
 pub mod consensus_api;
 pub mod election;
 pub mod metrics;
@@ -14,6 +17,7 @@ pub mod network;
 pub mod node_implementation;
 pub mod qc;
 pub mod signature_key;
+pub mod signer;
 pub mod stake_table;
 pub mod states;
 pub mod storage;
@@ -0,0 +1,89 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! An application interface modeled on ABCI.
+//!
+//! This provides the [`Application`] trait, for porting application logic written against
+//! Tendermint's ABCI. HotShot splits the work of an ABCI application between several parts, so
+//! each hook is called from the place its ABCI counterpart would run:
+//!
+//! | ABCI              | HotShot                                              |
+//! |-------------------|------------------------------------------------------|
+//! | `CheckTx`         | Before a transaction is submitted to the network     |
+//! | `PrepareProposal` | `BlockPayload::from_transactions`, in the builder    |
+//! | `ProcessProposal` | `ValidatedState::validate_and_apply_header`          |
+//! | `FinalizeBlock`   | For every leaf in a `Decide` event, oldest first     |
+//!
+//! `check_tx` and `finalize_block` are driven by HotShot itself, while `prepare_proposal` and
+//! `process_proposal` are meant to be called from the application's block and state types.
+
+use std::error::Error;
+
+use async_trait::async_trait;
+
+use super::{block_contents::BlockHeader, node_implementation::NodeType, BlockPayload};
+use crate::data::Leaf2;
+
+/// Application logic, following the ABCI request flow.
+#[async_trait]
+pub trait Application<TYPES: NodeType>: Send + Sync + 'static {
+    /// The reason a transaction or proposal is rejected
+    type Error: Error + Send + Sync + 'static;
+
+    /// Decide whether a transaction is admitted to the network. Rejected transactions are never
+    /// submitted.
+    ///
+    /// # Errors
+    /// If the transaction should be rejected.
+    async fn check_tx(&self, transaction: &TYPES::Transaction) -> Result<(), Self::Error>;
+
+    /// Select and order the transactions of a new block. The default keeps all of them, in order.
+    async fn prepare_proposal(
+        &self,
+        transactions: Vec<TYPES::Transaction>,
+    ) -> Vec<TYPES::Transaction> {
+        transactions
+    }
+
+    /// Decide whether a proposed header is a valid extension of `parent`. The default accepts
+    /// every header.
+    ///
+    /// # Errors
+    /// If the proposal should not be voted for.
+    async fn process_proposal(
+        &self,
+        _parent: &Leaf2<TYPES>,
+        _header: &TYPES::BlockHeader,
+    ) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    /// Apply the transactions of a decided block.
+    ///
+    /// Called exactly once for every decided leaf this node learns about, in block order. A leaf
+    /// whose payload this node never saw is finalized with no transactions.
+    ///
+    /// # Errors
+    /// If the block could not be applied. The error is logged, and finalization continues with
+    /// the next block.
+    async fn finalize_block(
+        &self,
+        leaf: &Leaf2<TYPES>,
+        transactions: Vec<TYPES::Transaction>,
+    ) -> Result<(), Self::Error>;
+}
+
+/// The transactions of a decided leaf, or none if we don't have its payload.
+#[must_use]
+pub fn leaf_transactions<TYPES: NodeType>(leaf: &Leaf2<TYPES>) -> Vec<TYPES::Transaction> {
+    leaf.block_payload()
+        .map(|payload| {
+            payload
+                .transactions(leaf.block_header().metadata())
+                .collect()
+        })
+        .unwrap_or_default()
+}
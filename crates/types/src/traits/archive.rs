@@ -0,0 +1,268 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Storage for the full history of the chain, kept by archival nodes.
+//!
+//! This module provides the [`ArchiveStorage`] trait. Unlike [`Storage`](super::storage::Storage),
+//! which only holds what a validator needs to recover, an archive is never pruned, so
+//! [`verify_storage`] can check the whole history it holds.
+
+use std::ops::Range;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use committable::{Commitment, Committable};
+use futures::stream::{self, BoxStream, StreamExt};
+use thiserror::Error;
+
+use super::{
+    election::Membership,
+    node_implementation::{NodeType, Versions},
+};
+use crate::{
+    data::{Leaf2, QuorumProposal2},
+    message::{Proposal, UpgradeLock},
+    vote::{Certificate, HasViewNumber},
+};
+
+/// Abstraction for storing every decided leaf and the proposals that led to them.
+#[async_trait]
+pub trait ArchiveStorage<TYPES: NodeType>: Send + Sync + 'static {
+    /// Add a decided leaf, with its payload if we have it. Leaves are appended in height order.
+    async fn append_leaf(&self, leaf: Leaf2<TYPES>) -> Result<()>;
+
+    /// Add a validated quorum proposal, signed by its leader.
+    async fn append_proposal(
+        &self,
+        proposal: Proposal<TYPES, QuorumProposal2<TYPES>>,
+    ) -> Result<()>;
+
+    /// The decided leaf at `height`.
+    async fn leaf(&self, height: u64) -> Result<Option<Leaf2<TYPES>>>;
+
+    /// The decided leaves at `heights` which are in the archive, in height order. Backends should
+    /// read them in one go; the default looks them up one by one.
+    async fn leaves(&self, heights: Range<u64>) -> Result<Vec<Leaf2<TYPES>>> {
+        let mut leaves = Vec::new();
+        for height in heights {
+            if let Some(leaf) = self.leaf(height).await? {
+                leaves.push(leaf);
+            }
+        }
+        Ok(leaves)
+    }
+
+    /// The decided leaf proposed in `view`.
+    async fn leaf_by_view(&self, view: TYPES::View) -> Result<Option<Leaf2<TYPES>>>;
+
+    /// The decided leaf with the given commitment.
+    async fn leaf_by_commitment(
+        &self,
+        commitment: Commitment<Leaf2<TYPES>>,
+    ) -> Result<Option<Leaf2<TYPES>>>;
+
+    /// Height of the decided leaf which included the transaction with the given commitment.
+    async fn transaction_height(
+        &self,
+        commitment: Commitment<TYPES::Transaction>,
+    ) -> Result<Option<u64>>;
+
+    /// The proposal we saw for `view`, if any.
+    async fn proposal(
+        &self,
+        view: TYPES::View,
+    ) -> Result<Option<Proposal<TYPES, QuorumProposal2<TYPES>>>>;
+
+    /// Height of the latest archived leaf, or `None` if the archive is empty.
+    async fn latest_height(&self) -> Result<Option<u64>>;
+
+    /// The ancestors of the leaf with the given commitment, newest first, read from the archive
+    /// [`ANCESTOR_BATCH`] leaves at a time.
+    ///
+    /// The walk follows parent commitments, so it ends at the oldest ancestor before a gap in the
+    /// archive, or right away if the archive does not have the leaf.
+    fn ancestors_of(
+        &self,
+        leaf_commitment: Commitment<Leaf2<TYPES>>,
+    ) -> BoxStream<'_, Result<Leaf2<TYPES>>> {
+        let walk = Ancestors {
+            next: leaf_commitment,
+            height: None,
+            batch: Vec::new(),
+        };
+        stream::try_unfold(walk, move |mut walk| async move {
+            let height = match walk.height {
+                Some(height) => height,
+                None => {
+                    // Start from the leaf itself, which is not one of its ancestors
+                    let Some(leaf) = self.leaf_by_commitment(walk.next).await? else {
+                        return Ok(None);
+                    };
+                    walk.next = leaf.parent_commitment();
+                    leaf.height()
+                }
+            };
+
+            if walk.batch.is_empty() && height > 0 {
+                walk.batch = self
+                    .leaves(height.saturating_sub(ANCESTOR_BATCH)..height)
+                    .await?;
+            }
+            // The batch is in height order, so the parent is its last leaf unless the archive has
+            // a gap there
+            match walk.batch.pop() {
+                Some(leaf) if leaf.commit() == walk.next => {
+                    walk.next = leaf.parent_commitment();
+                    walk.height = Some(leaf.height());
+                    Ok(Some((leaf, walk)))
+                }
+                _ => Ok(None),
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Number of leaves read at a time by [`ArchiveStorage::ancestors_of`]
+pub const ANCESTOR_BATCH: u64 = 64;
+
+/// Where an [`ArchiveStorage::ancestors_of`] walk is.
+struct Ancestors<TYPES: NodeType> {
+    /// Commitment of the next ancestor
+    next: Commitment<Leaf2<TYPES>>,
+    /// Height of the last leaf visited, or `None` before the walk has found its starting leaf
+    height: Option<u64>,
+    /// Leaves read but not visited yet, in height order
+    batch: Vec<Leaf2<TYPES>>,
+}
+
+/// Number of leaves read at a time by [`verify_storage`]
+const VERIFY_BATCH: u64 = 256;
+
+/// A corruption found by [`verify_storage`].
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum Corruption {
+    /// A leaf does not extend the leaf stored at the height before it
+    #[error("Leaf at height {height} does not extend the previous leaf")]
+    BrokenChain {
+        /// Height of the offending leaf
+        height: u64,
+    },
+    /// The QC of a leaf does not sign the leaf's parent
+    #[error("QC of the leaf at height {height} does not sign its parent")]
+    QcMismatch {
+        /// Height of the offending leaf
+        height: u64,
+    },
+    /// The QC of a leaf is not valid for the stake table of its epoch
+    #[error("QC for view {view}, of the leaf at height {height}, is not validly signed")]
+    InvalidQc {
+        /// Height of the offending leaf
+        height: u64,
+        /// View of the QC
+        view: u64,
+    },
+    /// Looking a leaf up by its view or commitment does not find it
+    #[error("Leaf at height {height} is not found by its view or commitment")]
+    BrokenIndex {
+        /// Height of the offending leaf
+        height: u64,
+    },
+    /// The proposal stored for the view of a leaf proposes another leaf
+    #[error("Proposal for view {view} does not propose the leaf at height {height}")]
+    ProposalMismatch {
+        /// Height of the offending leaf
+        height: u64,
+        /// View of the proposal
+        view: u64,
+    },
+}
+
+/// Check every leaf in `archive`: that it extends the leaf before it, that its QC signs its parent
+/// and is valid for the stake table `memberships` has for the QC's epoch, that it is indexed by
+/// its view and commitment, and that the proposal stored for its view proposes it.
+///
+/// Returns the corruptions found, in height order. This reads the whole archive, so it is meant
+/// to be run on startup or on demand rather than on every decide.
+///
+/// # Errors
+/// If the archive cannot be read.
+pub async fn verify_storage<TYPES, V, A>(
+    archive: &A,
+    memberships: &TYPES::Membership,
+    upgrade_lock: &UpgradeLock<TYPES, V>,
+) -> Result<Vec<Corruption>>
+where
+    TYPES: NodeType,
+    V: Versions,
+    A: ArchiveStorage<TYPES> + ?Sized,
+{
+    let mut corruptions = Vec::new();
+    let Some(latest) = archive.latest_height().await? else {
+        return Ok(corruptions);
+    };
+
+    let mut previous = None;
+    let mut from = 0;
+    while from <= latest {
+        let to = from
+            .saturating_add(VERIFY_BATCH)
+            .min(latest.saturating_add(1));
+        for leaf in archive.leaves(from..to).await? {
+            let height = leaf.height();
+            let commitment = leaf.commit();
+            let view = leaf.view_number();
+
+            if let Some((previous_height, previous_commitment)) = previous {
+                if previous_height + 1 == height && leaf.parent_commitment() != previous_commitment
+                {
+                    corruptions.push(Corruption::BrokenChain { height });
+                }
+            }
+
+            let qc = leaf.justify_qc();
+            if qc.data.leaf_commit != leaf.parent_commitment() {
+                corruptions.push(Corruption::QcMismatch { height });
+            }
+            let epoch = qc.data.epoch;
+            if !qc
+                .is_valid_cert(
+                    &memberships.stake_table(epoch),
+                    memberships.success_threshold(epoch),
+                    upgrade_lock,
+                )
+                .await
+            {
+                corruptions.push(Corruption::InvalidQc {
+                    height,
+                    view: *qc.view_number(),
+                });
+            }
+
+            let by_view = archive.leaf_by_view(view).await?;
+            let by_commitment = archive.leaf_by_commitment(commitment).await?;
+            if by_view.map(|leaf| leaf.commit()) != Some(commitment)
+                || by_commitment.map(|leaf| leaf.height()) != Some(height)
+            {
+                corruptions.push(Corruption::BrokenIndex { height });
+            }
+
+            if let Some(proposal) = archive.proposal(view).await? {
+                if Leaf2::from_quorum_proposal(&proposal.data).commit() != commitment {
+                    corruptions.push(Corruption::ProposalMismatch {
+                        height,
+                        view: *view,
+                    });
+                }
+            }
+
+            previous = Some((height, commitment));
+        }
+        from = to;
+    }
+
+    Ok(corruptions)
+}
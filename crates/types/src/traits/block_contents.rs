@@ -26,6 +26,7 @@ use vbs::version::Version;
 use super::signature_key::BuilderSignatureKey;
 use crate::{
     data::Leaf2,
+    hlc::HybridTimestamp,
     traits::{node_implementation::NodeType, states::InstanceState, ValidatedState},
     utils::BuilderCommitment,
     vid::{vid_scheme, VidCommitment, VidCommon, VidSchemeType},
@@ -46,6 +47,13 @@ pub trait Transaction:
     /// Since each new namespace adds overhead
     /// just ignore this parameter by default and use it when needed
     fn minimum_block_size(&self) -> u64;
+
+    /// Application-defined gas consumed by this transaction, counted against
+    /// [`BlockLimits::max_gas`](crate::block_limits::BlockLimits::max_gas).
+    /// Defaults to zero for applications without a notion of gas.
+    fn gas(&self) -> u64 {
+        0
+    }
 }
 
 /// Abstraction over the full contents of a block
@@ -248,4 +256,9 @@ pub trait BlockHeader<TYPES: NodeType>:
 
     /// Get the results of the auction for this Header. Only used in post-marketplace versions
     fn get_auction_results(&self) -> Option<TYPES::AuctionResult>;
+
+    /// Get the timestamp of the block, if the header carries one.
+    fn timestamp(&self) -> Option<HybridTimestamp> {
+        None
+    }
 }
@@ -0,0 +1,50 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Contains the [`CommitmentScheme`] trait, which decides how leaves and block headers are
+//! committed to.
+//!
+//! Every commitment to a leaf or block header goes through the scheme of the deployment's
+//! [`NodeType`], so a deployment which needs different commitments, such as SNARK-friendly ones
+//! which are cheap to verify in a circuit, swaps the scheme rather than the types.
+
+use std::fmt::Debug;
+
+use committable::{Commitment, Committable, RawCommitmentBuilder};
+
+use crate::{data::Leaf2, traits::node_implementation::NodeType};
+
+/// How leaves and block headers are committed to.
+///
+/// Both methods default to the Keccak-based commitments of [`committable`], so a scheme only
+/// overrides what it changes. A scheme which overrides [`CommitmentScheme::block_header`] changes
+/// leaf commitments as well, since a leaf commits to its block header.
+pub trait CommitmentScheme<TYPES: NodeType>:
+    Clone + Copy + Debug + Default + Send + Sync + 'static
+{
+    /// Commit to `leaf`
+    fn leaf(leaf: &Leaf2<TYPES>) -> Commitment<Leaf2<TYPES>> {
+        RawCommitmentBuilder::new("leaf commitment")
+            .u64_field("view number", *leaf.view_number())
+            .field("parent leaf commitment", leaf.parent_commitment())
+            .field("block header", Self::block_header(leaf.block_header()))
+            .field("justify qc", leaf.justify_qc().commit())
+            .optional("upgrade certificate", &leaf.upgrade_certificate())
+            .optional("epoch checkpoint", &leaf.epoch_checkpoint().cloned())
+            .finalize()
+    }
+
+    /// Commit to a block `header`
+    fn block_header(header: &TYPES::BlockHeader) -> Commitment<TYPES::BlockHeader> {
+        header.commit()
+    }
+}
+
+/// The Keccak-based commitments of [`committable`], which `HotShot` has always used
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeccakCommitments;
+
+impl<TYPES: NodeType> CommitmentScheme<TYPES> for KeccakCommitments {}
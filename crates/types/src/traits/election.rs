@@ -5,17 +5,19 @@
 // along with the HotShot repository. If not, see <https://mit-license.org/>.
 
 //! The election trait, used to decide which node is the leader and determine if a vote is valid.
-use std::{collections::BTreeSet, fmt::Debug, num::NonZeroU64};
+use std::{borrow::Cow, collections::BTreeSet, fmt::Debug, num::NonZeroU64};
 
 use utils::anytrace::Result;
 
 use super::node_implementation::NodeType;
-use crate::{traits::signature_key::SignatureKey, PeerConfig};
+use crate::{
+    drb::DrbResult, error::ConsensusError, traits::signature_key::SignatureKey, PeerConfig,
+};
 
 /// A protocol for determining membership in and participating in a committee.
 pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
     /// The error type returned by methods like `lookup_leader`.
-    type Error: std::fmt::Display;
+    type Error: std::fmt::Display + Into<ConsensusError<TYPES>>;
 
     /// Create a committee
     fn new(
@@ -26,16 +28,19 @@ pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
     ) -> Self;
 
     /// Get all participants in the committee (including their stake) for a specific epoch
+    ///
+    /// Implementations that keep the table around should borrow it rather than copy it, since this
+    /// is called for every certificate and vote.
     fn stake_table(
         &self,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>;
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]>;
 
     /// Get all participants in the committee (including their stake) for a specific epoch
     fn da_stake_table(
         &self,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>;
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]>;
 
     /// Get all participants in the committee for a specific view for a specific epoch
     fn committee_members(
@@ -90,9 +95,24 @@ pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
     fn leader(&self, view: TYPES::View, epoch: TYPES::Epoch) -> Result<TYPES::SignatureKey> {
         use utils::anytrace::*;
 
-        self.lookup_leader(view, epoch).wrap().context(info!(
-            "Failed to get leader for view {view} in epoch {epoch}"
-        ))
+        self.try_leader(view, epoch)
+            .map_err(Error::from)
+            .context(info!(
+                "Failed to get leader for view {view} in epoch {epoch}"
+            ))
+    }
+
+    /// The leader of the committee for view `view_number` in `epoch`, for callers which act on
+    /// the kind of failure.
+    ///
+    /// # Errors
+    /// Returns an error if the leader cannot be calculated.
+    fn try_leader(
+        &self,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> std::result::Result<TYPES::SignatureKey, ConsensusError<TYPES>> {
+        self.lookup_leader(view, epoch).map_err(Into::into)
     }
 
     /// The leader of the committee for view `view_number` in `epoch`.
@@ -108,6 +128,23 @@ pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
         epoch: TYPES::Epoch,
     ) -> std::result::Result<TYPES::SignatureKey, Self::Error>;
 
+    /// Provide the DRB result for `epoch`, for memberships which use it to pick leaders.
+    ///
+    /// Memberships are shared between tasks, so one which keeps the result needs interior
+    /// mutability. The default ignores it.
+    fn add_drb_result(&self, _epoch: TYPES::Epoch, _drb_result: DrbResult) {}
+
+    /// Keep `jailed` from leading in `epoch`, for memberships which support jailing.
+    ///
+    /// Jailed validators keep their stake and vote as usual. The default ignores the jail.
+    fn set_jailed(&self, _epoch: TYPES::Epoch, _jailed: BTreeSet<TYPES::SignatureKey>) {}
+
+    /// Have `leader` lead `view` in place of the usual rotation, for memberships which support
+    /// the coin fallback.
+    ///
+    /// The election is kept for the view whatever its epoch. The default ignores it.
+    fn set_fallback_leader(&self, _view: TYPES::View, _leader: TYPES::SignatureKey) {}
+
     /// Returns the number of total nodes in the committee in an epoch `epoch`
     fn total_nodes(&self, epoch: TYPES::Epoch) -> usize;
 
@@ -126,3 +163,20 @@ pub trait Membership<TYPES: NodeType>: Clone + Debug + Send + Sync {
     /// Returns the threshold required to upgrade the network protocol
     fn upgrade_threshold(&self, epoch: TYPES::Epoch) -> NonZeroU64;
 }
+
+/// A source of stake tables, such as a static config, a watcher of an L1 stake contract or a
+/// governance module.
+///
+/// Elections built on a provider query it once per epoch, so a deployment can read stake from
+/// elsewhere without writing its own `Membership`.
+pub trait StakeTableProvider<TYPES: NodeType>: Debug + Send + Sync {
+    /// The quorum and DA stake tables in effect during `epoch`, or `None` if they are not known
+    /// yet, e.g. because the L1 block which fixes them is not finalized.
+    fn stake_tables(
+        &self,
+        epoch: TYPES::Epoch,
+    ) -> Option<(
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+        Vec<PeerConfig<TYPES::SignatureKey>>,
+    )>;
+}
@@ -12,7 +12,11 @@
 //! - [`Histogram`]: stores multiple float values based for a graph (example usage: CPU %)
 //! - text: stores a constant string in the collected metrics
 
-use std::fmt::Debug;
+use std::{
+    collections::HashMap,
+    fmt::Debug,
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
 
 use dyn_clone::DynClone;
 
@@ -233,168 +237,191 @@ dyn_clone::clone_trait_object!(Gauge);
 dyn_clone::clone_trait_object!(Counter);
 dyn_clone::clone_trait_object!(Histogram);
 
-#[cfg(test)]
-mod test {
-    use std::{
-        collections::HashMap,
-        sync::{Arc, Mutex},
-    };
+/// Metrics which record their values in memory, so that they can be read back, e.g. at the end of
+/// a test.
+///
+/// Clones and subgroups share their values. The value of a metric is looked up by its full name:
+/// the names of its subgroups and its own, joined with `-`, as in `node-0-number_of_timeouts`.
+/// The labels of a metric in a family are appended to its name the same way.
+#[derive(Debug, Clone, Default)]
+pub struct RecordingMetrics {
+    /// Full name of the subgroup or metric
+    prefix: String,
+    /// Values of all the metrics
+    values: Arc<Mutex<RecordedValues>>,
+}
 
-    use super::*;
+impl RecordingMetrics {
+    /// The value of the counter `name`, which is 0 if it was never incremented.
+    #[must_use]
+    pub fn counter(&self, name: &str) -> usize {
+        self.values()
+            .counters
+            .get(name)
+            .copied()
+            .unwrap_or_default()
+    }
 
-    #[derive(Debug, Clone)]
-    struct TestMetrics {
-        prefix: String,
-        values: Arc<Mutex<Inner>>,
-    }
-
-    impl TestMetrics {
-        fn sub(&self, name: String) -> Self {
-            let prefix = if self.prefix.is_empty() {
-                name
-            } else {
-                format!("{}-{name}", self.prefix)
-            };
-            Self {
-                prefix,
-                values: Arc::clone(&self.values),
-            }
-        }
+    /// The value of the gauge `name`, if it was ever set.
+    #[must_use]
+    pub fn gauge(&self, name: &str) -> Option<usize> {
+        self.values().gauges.get(name).copied()
+    }
 
-        fn family(&self, labels: Vec<String>) -> Self {
-            let mut curr = self.clone();
-            for label in labels {
-                curr = curr.sub(label);
-            }
-            curr
-        }
+    /// The points added to the histogram `name`.
+    #[must_use]
+    pub fn histogram(&self, name: &str) -> Vec<f64> {
+        self.values()
+            .histograms
+            .get(name)
+            .cloned()
+            .unwrap_or_default()
     }
 
-    impl Metrics for TestMetrics {
-        fn create_counter(
-            &self,
-            name: String,
-            _unit_label: Option<String>,
-        ) -> Box<dyn super::Counter> {
-            Box::new(self.sub(name))
-        }
+    /// Lock the values. A recorder which panicked while holding them left them consistent, as
+    /// every update is a single step.
+    fn values(&self) -> MutexGuard<'_, RecordedValues> {
+        self.values.lock().unwrap_or_else(PoisonError::into_inner)
+    }
 
-        fn create_gauge(&self, name: String, _unit_label: Option<String>) -> Box<dyn super::Gauge> {
-            Box::new(self.sub(name))
+    /// The subgroup or metric `name` of this one.
+    fn sub(&self, name: String) -> Self {
+        let prefix = if self.prefix.is_empty() {
+            name
+        } else {
+            format!("{}-{name}", self.prefix)
+        };
+        Self {
+            prefix,
+            values: Arc::clone(&self.values),
         }
+    }
 
-        fn create_histogram(
-            &self,
-            name: String,
-            _unit_label: Option<String>,
-        ) -> Box<dyn super::Histogram> {
-            Box::new(self.sub(name))
+    /// The metric of a family with the given label values.
+    fn family(&self, labels: Vec<String>) -> Self {
+        let mut curr = self.clone();
+        for label in labels {
+            curr = curr.sub(label);
         }
+        curr
+    }
+}
 
-        fn create_text(&self, name: String) {
-            self.create_gauge(name, None).set(1);
-        }
+impl Metrics for RecordingMetrics {
+    fn create_counter(&self, name: String, _unit_label: Option<String>) -> Box<dyn Counter> {
+        Box::new(self.sub(name))
+    }
 
-        fn counter_family(&self, name: String, _: Vec<String>) -> Box<dyn CounterFamily> {
-            Box::new(self.sub(name))
-        }
+    fn create_gauge(&self, name: String, _unit_label: Option<String>) -> Box<dyn Gauge> {
+        Box::new(self.sub(name))
+    }
 
-        fn gauge_family(&self, name: String, _: Vec<String>) -> Box<dyn GaugeFamily> {
-            Box::new(self.sub(name))
-        }
+    fn create_histogram(&self, name: String, _unit_label: Option<String>) -> Box<dyn Histogram> {
+        Box::new(self.sub(name))
+    }
 
-        fn histogram_family(&self, name: String, _: Vec<String>) -> Box<dyn HistogramFamily> {
-            Box::new(self.sub(name))
-        }
+    fn create_text(&self, name: String) {
+        self.create_gauge(name, None).set(1);
+    }
 
-        fn text_family(&self, name: String, _: Vec<String>) -> Box<dyn TextFamily> {
-            Box::new(self.sub(name))
-        }
+    fn counter_family(&self, name: String, _: Vec<String>) -> Box<dyn CounterFamily> {
+        Box::new(self.sub(name))
+    }
 
-        fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
-            Box::new(self.sub(subgroup_name))
-        }
+    fn gauge_family(&self, name: String, _: Vec<String>) -> Box<dyn GaugeFamily> {
+        Box::new(self.sub(name))
     }
 
-    impl Counter for TestMetrics {
-        fn add(&self, amount: usize) {
-            *self
-                .values
-                .lock()
-                .unwrap()
-                .counters
-                .entry(self.prefix.clone())
-                .or_default() += amount;
-        }
+    fn histogram_family(&self, name: String, _: Vec<String>) -> Box<dyn HistogramFamily> {
+        Box::new(self.sub(name))
     }
 
-    impl Gauge for TestMetrics {
-        fn set(&self, amount: usize) {
-            *self
-                .values
-                .lock()
-                .unwrap()
-                .gauges
-                .entry(self.prefix.clone())
-                .or_default() = amount;
-        }
-        fn update(&self, delta: i64) {
-            let mut values = self.values.lock().unwrap();
-            let value = values.gauges.entry(self.prefix.clone()).or_default();
-            let signed_value = i64::try_from(*value).unwrap_or(i64::MAX);
-            *value = usize::try_from(signed_value + delta).unwrap_or(0);
-        }
+    fn text_family(&self, name: String, _: Vec<String>) -> Box<dyn TextFamily> {
+        Box::new(self.sub(name))
     }
 
-    impl Histogram for TestMetrics {
-        fn add_point(&self, point: f64) {
-            self.values
-                .lock()
-                .unwrap()
-                .histograms
-                .entry(self.prefix.clone())
-                .or_default()
-                .push(point);
-        }
+    fn subgroup(&self, subgroup_name: String) -> Box<dyn Metrics> {
+        Box::new(self.sub(subgroup_name))
     }
+}
 
-    impl MetricsFamily<Box<dyn Counter>> for TestMetrics {
-        fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
-            Box::new(self.family(labels))
-        }
+impl Counter for RecordingMetrics {
+    fn add(&self, amount: usize) {
+        *self
+            .values()
+            .counters
+            .entry(self.prefix.clone())
+            .or_default() += amount;
     }
+}
 
-    impl MetricsFamily<Box<dyn Gauge>> for TestMetrics {
-        fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
-            Box::new(self.family(labels))
-        }
+impl Gauge for RecordingMetrics {
+    fn set(&self, amount: usize) {
+        *self.values().gauges.entry(self.prefix.clone()).or_default() = amount;
     }
+    fn update(&self, delta: i64) {
+        let mut values = self.values();
+        let value = values.gauges.entry(self.prefix.clone()).or_default();
+        let signed_value = i64::try_from(*value).unwrap_or(i64::MAX);
+        *value = usize::try_from(signed_value + delta).unwrap_or(0);
+    }
+}
 
-    impl MetricsFamily<Box<dyn Histogram>> for TestMetrics {
-        fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
-            Box::new(self.family(labels))
-        }
+impl Histogram for RecordingMetrics {
+    fn add_point(&self, point: f64) {
+        self.values()
+            .histograms
+            .entry(self.prefix.clone())
+            .or_default()
+            .push(point);
     }
+}
 
-    impl MetricsFamily<()> for TestMetrics {
-        fn create(&self, labels: Vec<String>) {
-            self.family(labels).set(1);
-        }
+impl MetricsFamily<Box<dyn Counter>> for RecordingMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Counter> {
+        Box::new(self.family(labels))
     }
+}
+
+impl MetricsFamily<Box<dyn Gauge>> for RecordingMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Gauge> {
+        Box::new(self.family(labels))
+    }
+}
+
+impl MetricsFamily<Box<dyn Histogram>> for RecordingMetrics {
+    fn create(&self, labels: Vec<String>) -> Box<dyn Histogram> {
+        Box::new(self.family(labels))
+    }
+}
 
-    #[derive(Default, Debug)]
-    struct Inner {
-        counters: HashMap<String, usize>,
-        gauges: HashMap<String, usize>,
-        histograms: HashMap<String, Vec<f64>>,
+impl MetricsFamily<()> for RecordingMetrics {
+    fn create(&self, labels: Vec<String>) {
+        self.family(labels).set(1);
     }
+}
+
+/// Values recorded by [`RecordingMetrics`], by full name.
+#[derive(Default, Debug)]
+struct RecordedValues {
+    /// Counters
+    counters: HashMap<String, usize>,
+    /// Gauges
+    gauges: HashMap<String, usize>,
+    /// Points of histograms
+    histograms: HashMap<String, Vec<f64>>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
 
     #[test]
     fn test() {
         let values = Arc::default();
         // This is all scoped so all the arcs should go out of scope
         {
-            let metrics: Box<dyn Metrics> = Box::new(TestMetrics {
+            let metrics: Box<dyn Metrics> = Box::new(RecordingMetrics {
                 prefix: String::new(),
                 values: Arc::clone(&values),
             });
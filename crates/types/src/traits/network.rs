@@ -8,6 +8,8 @@
 //!
 //! Contains types and traits used by `HotShot` to abstract over network access
 
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
 use std::{
     collections::HashMap,
     fmt::{Debug, Display},
@@ -18,6 +20,7 @@ use std::{
 };
 
 use async_trait::async_trait;
+use bytes::Bytes;
 use dyn_clone::DynClone;
 use futures::{future::join_all, Future};
 use rand::{
@@ -26,10 +29,16 @@ use rand::{
 };
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::{sync::mpsc::error::TrySendError, time::sleep};
+use tokio::sync::mpsc::error::TrySendError;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::sleep;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 use super::{node_implementation::NodeType, signature_key::SignatureKey};
-use crate::{data::ViewNumber, message::SequencingMessage, BoxSyncFuture};
+use crate::{
+    data::ViewNumber, gossip_fanout::MessageClass, message::SequencingMessage, BoxSyncFuture,
+};
 
 /// Centralized server specific errors
 #[derive(Debug, Error, Serialize, Deserialize)]
@@ -169,11 +178,32 @@ pub enum BroadcastDelay {
     View(u64),
 }
 
+/// What a network knows about its connection to a peer.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct PeerStatus<K: SignatureKey> {
+    /// The peer's staking key, if we know which peer it is
+    pub key: Option<K>,
+    /// The peer's identifier on the network, e.g. its libp2p peer ID
+    pub peer_id: String,
+    /// Whether we are connected to the peer
+    pub connected: bool,
+    /// Time since we last received anything from the peer
+    pub last_seen: Option<Duration>,
+    /// Round-trip time of the latest request the peer answered
+    pub rtt: Option<Duration>,
+    /// Number of failed connections and messages to or from the peer
+    pub failures: u64,
+}
+
 #[async_trait]
 /// represents a networking implmentration
 /// exposes low level API for interacting with a network
 /// intended to be implemented for libp2p, the centralized server,
 /// and memory network
+///
+/// Messages are passed around as [`Bytes`], so that sending one to many peers, or over several
+/// networks, shares a single buffer instead of copying it each time.
 pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'static {
     /// Pauses the underlying network
     fn pause(&self);
@@ -194,7 +224,7 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     /// blocking
     async fn broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         topic: Topic,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError>;
@@ -203,17 +233,14 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     /// blocking
     async fn da_broadcast_message(
         &self,
-        message: Vec<u8>,
+        message: Bytes,
         recipients: Vec<K>,
         broadcast_delay: BroadcastDelay,
     ) -> Result<(), NetworkError>;
 
     /// send messages with vid shares to its recipients
     /// blocking
-    async fn vid_broadcast_message(
-        &self,
-        messages: HashMap<K, Vec<u8>>,
-    ) -> Result<(), NetworkError> {
+    async fn vid_broadcast_message(&self, messages: HashMap<K, Bytes>) -> Result<(), NetworkError> {
         let future_results = messages
             .into_iter()
             .map(|(recipient_key, message)| self.direct_message(message, recipient_key));
@@ -236,13 +263,13 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
 
     /// Sends a direct message to a specific node
     /// blocking
-    async fn direct_message(&self, message: Vec<u8>, recipient: K) -> Result<(), NetworkError>;
+    async fn direct_message(&self, message: Bytes, recipient: K) -> Result<(), NetworkError>;
 
     /// Receive one or many messages from the underlying network.
     ///
     /// # Errors
     /// If there is a network-related failure.
-    async fn recv_message(&self) -> Result<Vec<u8>, NetworkError>;
+    async fn recv_message(&self) -> Result<Bytes, NetworkError>;
 
     /// queues lookup of a node
     ///
@@ -272,6 +299,25 @@ pub trait ConnectedNetwork<K: SignatureKey + 'static>: Clone + Send + Sync + 'st
     fn is_primary_down(&self) -> bool {
         false
     }
+
+    /// Number of peers we are directly connected to.
+    ///
+    /// Returns `None` for networks which cannot tell, e.g. because all traffic goes through a
+    /// broker.
+    async fn connected_peers(&self) -> Option<usize> {
+        None
+    }
+
+    /// The status of every peer we have dealt with.
+    ///
+    /// Returns `None` for networks which do not track peers.
+    async fn peer_status(&self) -> Option<Vec<PeerStatus<K>>> {
+        None
+    }
+
+    /// Report that a message of `class` reached us `latency` after we entered its view, so that
+    /// networks which tune their gossip can adapt to it.
+    fn observe_propagation(&self, _class: MessageClass, _latency: Duration) {}
 }
 
 /// A channel generator for types that need asynchronous execution
@@ -330,7 +376,7 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
     }
 
     /// scramble the packet
-    fn scramble(&self, msg: Vec<u8>) -> Vec<u8> {
+    fn scramble(&self, msg: Bytes) -> Bytes {
         msg
     }
 
@@ -350,8 +396,8 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
     /// then return a future that does the sending and delaying
     fn chaos_send_msg(
         &self,
-        msg: Vec<u8>,
-        send_fn: Arc<dyn Send + Sync + 'static + Fn(Vec<u8>) -> BoxSyncFuture<'static, ()>>,
+        msg: Bytes,
+        send_fn: Arc<dyn Send + Sync + 'static + Fn(Bytes) -> BoxSyncFuture<'static, ()>>,
     ) -> BoxSyncFuture<'static, ()> {
         let sample_keep = self.sample_keep();
         let delay = self.sample_delay();
@@ -363,7 +409,12 @@ pub trait NetworkReliability: Debug + Sync + std::marker::Send + DynClone + 'sta
         }
         let closure = async move {
             if sample_keep {
+                // Timers need a runtime, which we don't have on wasm. Messages are still dropped,
+                // scrambled and repeated there, just not delayed.
+                #[cfg(not(target_arch = "wasm32"))]
                 sleep(delay).await;
+                #[cfg(target_arch = "wasm32")]
+                let _ = delay;
                 for msg in msgs {
                     send_fn(msg).await;
                 }
@@ -449,7 +500,7 @@ pub struct PartiallySynchronousNetwork {
     /// time when GST occurs
     pub gst: std::time::Duration,
     /// when the network was started
-    pub start: std::time::Instant,
+    pub start: Instant,
 }
 
 impl NetworkReliability for PartiallySynchronousNetwork {
@@ -491,7 +542,7 @@ impl Default for PartiallySynchronousNetwork {
             synchronous: SynchronousNetwork::default(),
             asynchronous: AsynchronousNetwork::default(),
             gst: std::time::Duration::new(0, 0),
-            start: std::time::Instant::now(),
+            start: Instant::now(),
         }
     }
 }
@@ -538,7 +589,7 @@ impl PartiallySynchronousNetwork {
             asynchronous,
             synchronous,
             gst,
-            start: std::time::Instant::now(),
+            start: Instant::now(),
         }
     }
 }
@@ -26,6 +26,7 @@ use vbs::version::StaticVersionType;
 use super::{
     auction_results_provider::AuctionResultsProvider,
     block_contents::{BlockHeader, TestableBlock, Transaction},
+    commitment::CommitmentScheme,
     network::{
         AsyncGenerator, ConnectedNetwork, NetworkReliability, TestableNetworkingImplementation,
     },
@@ -254,6 +255,9 @@ pub trait NodeType:
 
     /// The type builder uses to sign its messages
     type BuilderSignatureKey: BuilderSignatureKey;
+
+    /// How leaves and block headers are committed to
+    type CommitmentScheme: CommitmentScheme<Self>;
 }
 
 /// Version information for HotShot
@@ -172,6 +172,45 @@ pub trait SignatureKey:
     fn genesis_proposer_pk() -> Self;
 }
 
+/// The identity of the leader that proposed a leaf or block.
+///
+/// Wraps the typed public key so proposers can't be confused with other keys.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""), transparent)]
+pub struct ProposerId<KEY: SignatureKey>(KEY);
+
+impl<KEY: SignatureKey> ProposerId<KEY> {
+    /// Create a proposer id from the proposer's public key.
+    #[must_use]
+    pub fn new(key: KEY) -> Self {
+        Self(key)
+    }
+
+    /// The proposer's public key.
+    #[must_use]
+    pub fn key(&self) -> &KEY {
+        &self.0
+    }
+
+    /// Consume the id, returning the proposer's public key.
+    #[must_use]
+    pub fn into_key(self) -> KEY {
+        self.0
+    }
+}
+
+impl<KEY: SignatureKey> From<KEY> for ProposerId<KEY> {
+    fn from(key: KEY) -> Self {
+        Self(key)
+    }
+}
+
+impl<KEY: SignatureKey> Display for ProposerId<KEY> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
 /// Builder Signature Key trait with minimal requirements
 pub trait BuilderSignatureKey:
     Send
@@ -0,0 +1,112 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Signing with consensus keys.
+//!
+//! Votes, proposals, message envelopes, data requests and builder requests are all signed through
+//! a [`Signer`], so the private key can live outside the node process, e.g. in an HSM or a signing
+//! service. Signing may then be slow or fail, and callers treat a failure like any other reason not
+//! to vote, propose or send in a view. [`LocalSigner`] signs with a key held in memory; a node
+//! configured with a [`RemoteSignerConfig`] asks a signing service instead.
+
+use std::{fmt::Debug, time::Duration};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use url::Url;
+
+use super::signature_key::SignatureKey;
+
+/// Reasons a signer could not sign.
+#[derive(Clone, Debug, Error, PartialEq, Eq)]
+pub enum SignerError {
+    /// The key failed to sign the data
+    #[error("Failed to sign: {0}")]
+    Failed(String),
+    /// The signer could not be reached, or did not answer in time
+    #[error("Signer unavailable: {0}")]
+    Unavailable(String),
+    /// The signer answered with a signature which does not verify against its public key
+    #[error("Signer returned an invalid signature")]
+    InvalidSignature,
+}
+
+/// Signs data with the private key of a consensus key pair.
+#[async_trait]
+pub trait Signer<K: SignatureKey>: Debug + Send + Sync + 'static {
+    /// The public key signatures verify against.
+    fn public_key(&self) -> &K;
+
+    /// Sign `data` with the private key of [`Self::public_key`].
+    ///
+    /// # Errors
+    /// Fails if the signer cannot sign, now or at all.
+    async fn sign(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, SignerError>;
+}
+
+/// Where and how to reach a remote signing service.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RemoteSignerConfig {
+    /// Base URL of the service. Signing requests are posted to `{url}/sign`.
+    pub url: Url,
+    /// How long to wait for a signature before trying again
+    pub timeout: Duration,
+    /// Number of attempts before signing fails
+    pub max_attempts: u32,
+}
+
+impl RemoteSignerConfig {
+    /// Default settings for the service at `url`.
+    #[must_use]
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            timeout: Duration::from_millis(500),
+            max_attempts: 2,
+        }
+    }
+}
+
+/// A [`Signer`] holding the private key in memory.
+#[derive(Clone)]
+pub struct LocalSigner<K: SignatureKey> {
+    /// Our public key
+    public_key: K,
+    /// Our private key
+    private_key: K::PrivateKey,
+}
+
+impl<K: SignatureKey> LocalSigner<K> {
+    /// A signer for the key pair `(public_key, private_key)`.
+    #[must_use]
+    pub fn new(public_key: K, private_key: K::PrivateKey) -> Self {
+        Self {
+            public_key,
+            private_key,
+        }
+    }
+}
+
+impl<K: SignatureKey> Debug for LocalSigner<K> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Keep the private key out of logs
+        f.debug_struct("LocalSigner")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl<K: SignatureKey + 'static> Signer<K> for LocalSigner<K> {
+    fn public_key(&self) -> &K {
+        &self.public_key
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<K::PureAssembledSignatureType, SignerError> {
+        K::sign(&self.private_key, data).map_err(|err| SignerError::Failed(err.to_string()))
+    }
+}
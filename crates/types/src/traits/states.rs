@@ -18,6 +18,7 @@ use vbs::version::Version;
 use super::block_contents::TestableBlock;
 use crate::{
     data::Leaf2,
+    event::RejectedTransaction,
     traits::{
         node_implementation::{ConsensusTime, NodeType},
         BlockPayload,
@@ -87,6 +88,38 @@ pub trait ValidatedState<TYPES: NodeType>:
 
     /// Gets called to notify the persistence backend that this state has been committed
     fn on_commit(&self);
+
+    /// Transactions that were rejected when the block of `view_number` was applied to produce
+    /// this state and `delta`, along with the reason for each rejection.
+    ///
+    /// These are reported to clients in `Decide` events. The default reports none.
+    fn rejected_transactions(
+        &self,
+        _view_number: TYPES::View,
+        _delta: &Self::Delta,
+    ) -> Vec<RejectedTransaction<TYPES>> {
+        Vec::new()
+    }
+}
+
+/// A [`ValidatedState`] whose commitment can be carried forward by the deltas of blocks.
+///
+/// Recommitting to a large state after every block means rehashing all of it. With this
+/// extension, the commitment to each state is computed from the commitment to its parent and the
+/// delta between them instead, at a cost that grows with the delta rather than with the state.
+pub trait IncrementalCommitment<TYPES: NodeType>: ValidatedState<TYPES> {
+    /// Commitment to a state
+    type Commitment: Clone + Debug + PartialEq + Eq + Send + Sync;
+
+    /// Commit to the whole state, for a state without a parent such as genesis or one rebuilt
+    /// for catchup.
+    fn commitment(&self) -> Self::Commitment;
+
+    /// The commitment to the state which `delta` leads to from the state committed to by
+    /// `parent`, or `None` if `delta` does not apply to that state.
+    ///
+    /// This must agree with [`IncrementalCommitment::commitment`] of the resulting state.
+    fn append(parent: &Self::Commitment, delta: &Self::Delta) -> Option<Self::Commitment>;
 }
 
 /// extra functions required on state to be usable by hotshot-testing
@@ -11,13 +11,13 @@
 
 use std::collections::BTreeMap;
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use async_trait::async_trait;
 use jf_vid::VidScheme;
 
 use super::node_implementation::NodeType;
 use crate::{
-    consensus::{CommitmentMap, View},
+    consensus::{CommitmentMap, LeafMap, View},
     data::{
         DaProposal, DaProposal2, Leaf, Leaf2, QuorumProposal, QuorumProposal2, VidDisperseShare,
         VidDisperseShare2,
@@ -25,9 +25,99 @@ use crate::{
     event::HotShotAction,
     message::Proposal,
     simple_certificate::{QuorumCertificate, QuorumCertificate2, UpgradeCertificate},
+    utils::LeafCommitment,
     vid::VidSchemeType,
 };
 
+/// Everything a replica persists for a view before voting in it, written together by
+/// [`Storage::write_view`].
+#[derive(Clone, Debug)]
+pub struct ViewWrites<TYPES: NodeType> {
+    /// The quorum proposal we are voting for.
+    pub proposal: Option<Proposal<TYPES, QuorumProposal2<TYPES>>>,
+    /// The undecided leaves and state after applying the proposal.
+    pub undecided_state: Option<(LeafMap<TYPES>, BTreeMap<TYPES::View, View<TYPES>>)>,
+    /// Our VID share of the proposed block, only present if we vote.
+    pub vid_share: Option<Proposal<TYPES, VidDisperseShare2<TYPES>>>,
+    /// A new high QC seen in this view.
+    pub high_qc: Option<QuorumCertificate2<TYPES>>,
+}
+
+impl<TYPES: NodeType> Default for ViewWrites<TYPES> {
+    fn default() -> Self {
+        Self {
+            proposal: None,
+            undecided_state: None,
+            vid_share: None,
+            high_qc: None,
+        }
+    }
+}
+
+/// A key written by [`Storage::write_view`].
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StorageKey<TYPES: NodeType> {
+    /// The quorum proposal of a view
+    Proposal(TYPES::View),
+    /// The VID share of a view for a recipient
+    VidShare(TYPES::View, TYPES::SignatureKey),
+    /// The high QC
+    HighQc,
+}
+
+/// A value overwritten by [`Storage::write_view`].
+#[derive(Clone, Debug)]
+pub enum StoredValue<TYPES: NodeType> {
+    /// A quorum proposal
+    Proposal(Proposal<TYPES, QuorumProposal2<TYPES>>),
+    /// A VID share
+    VidShare(Proposal<TYPES, VidDisperseShare2<TYPES>>),
+    /// The high QC
+    HighQc(QuorumCertificate2<TYPES>),
+}
+
+impl<TYPES: NodeType> StoredValue<TYPES> {
+    /// The key the value is stored under
+    #[must_use]
+    pub fn key(&self) -> StorageKey<TYPES> {
+        match self {
+            Self::Proposal(proposal) => StorageKey::Proposal(proposal.data.view_number),
+            Self::VidShare(share) => {
+                StorageKey::VidShare(share.data.view_number, share.data.recipient_key.clone())
+            }
+            Self::HighQc(_) => StorageKey::HighQc,
+        }
+    }
+}
+
+/// What it takes to revert a view applied speculatively, recorded by backends which support
+/// [`Storage::revert_to`].
+#[derive(Clone, Debug)]
+pub struct ViewUndo<TYPES: NodeType> {
+    /// The view
+    pub view: TYPES::View,
+    /// The leaf the view's proposal extends, whose state is the latest one again once the view
+    /// is reverted
+    pub parent: LeafCommitment<TYPES>,
+    /// Keys which the view's writes added
+    pub added: Vec<StorageKey<TYPES>>,
+    /// Values which the view's writes overwrote
+    pub removed: Vec<StoredValue<TYPES>>,
+}
+
+impl<TYPES: NodeType> ViewUndo<TYPES> {
+    /// Start the record of the view `proposal` was applied in.
+    #[must_use]
+    pub fn new(proposal: &Proposal<TYPES, QuorumProposal2<TYPES>>) -> Self {
+        Self {
+            view: proposal.data.view_number,
+            parent: proposal.data.justify_qc.data.leaf_commit,
+            added: Vec::new(),
+            removed: Vec::new(),
+        }
+    }
+}
+
 /// Abstraction for storing a variety of consensus payload datum.
 #[async_trait]
 pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
@@ -75,7 +165,7 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
     /// and the undecided state.
     async fn update_undecided_state2(
         &self,
-        leaves: CommitmentMap<Leaf2<TYPES>>,
+        leaves: LeafMap<TYPES>,
         state: BTreeMap<TYPES::View, View<TYPES>>,
     ) -> Result<()>;
     /// Upgrade the current decided upgrade certificate in storage.
@@ -91,4 +181,51 @@ pub trait Storage<TYPES: NodeType>: Send + Sync + Clone {
             Proposal<TYPES, QuorumProposal<TYPES>>,
         ) -> Proposal<TYPES, QuorumProposal2<TYPES>>,
     ) -> Result<()>;
+    /// Write everything buffered for a view as one operation. Backends should apply the batch
+    /// atomically with a single sync; the default just applies each write in turn.
+    async fn write_view(&self, writes: ViewWrites<TYPES>) -> Result<()> {
+        if let Some(proposal) = &writes.proposal {
+            self.append_proposal2(proposal).await?;
+        }
+        if let Some((leaves, state)) = writes.undecided_state {
+            self.update_undecided_state2(leaves, state).await?;
+        }
+        if let Some(vid_share) = &writes.vid_share {
+            self.append_vid2(vid_share).await?;
+        }
+        if let Some(high_qc) = writes.high_qc {
+            self.update_high_qc2(high_qc).await?;
+        }
+        Ok(())
+    }
+    /// Total bytes used by the storage backend, if it can tell. This is checked on every write, so
+    /// it should be cheap.
+    async fn storage_size(&self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+    /// Delete what is stored for views before `view`, which are all decided. Backends which do
+    /// not prune ignore this.
+    async fn prune(&self, _view: TYPES::View) -> Result<()> {
+        Ok(())
+    }
+    /// Undo the writes of every view after `view` applied with [`Storage::write_view`], newest
+    /// first, for when a conflicting commit means they will never be decided. Returns the undo
+    /// records of the reverted views, newest first, so the caller can roll its own state back to
+    /// their parents.
+    ///
+    /// Backends keep undo records for the views they have not pruned yet. Those which keep none
+    /// fail rather than leave speculative writes behind.
+    async fn revert_to(&self, _view: TYPES::View) -> Result<Vec<ViewUndo<TYPES>>> {
+        bail!("This storage backend cannot revert views")
+    }
+    /// Read back the value stored under `key`. Backends which cannot read back what they store
+    /// fail, so they cannot be used where reads are needed, such as a tier of
+    /// `TieredStorage`.
+    async fn load(&self, _key: &StorageKey<TYPES>) -> Result<Option<StoredValue<TYPES>>> {
+        bail!("This storage backend cannot read back what it stores")
+    }
+    /// Read back the proposals and VID shares stored for views before `view`, in view order.
+    async fn load_before(&self, _view: TYPES::View) -> Result<Vec<StoredValue<TYPES>>> {
+        bail!("This storage backend cannot read back what it stores")
+    }
 }
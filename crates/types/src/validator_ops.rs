@@ -0,0 +1,388 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Validator lifecycle operations: registering, updating stake, rotating keys and exiting.
+//!
+//! Operations are special transactions. A validator signs a [`ValidatorOperation`] with its
+//! consensus key, and the resulting [`SignedOperation`] is carried by an ordinary transaction of
+//! the application, see [`OperationTransaction`]. Every node applies the decided operations to a
+//! [`ValidatorSet`] in chain order, so all nodes agree on the stake table of each epoch.
+//!
+//! An operation decided in epoch `e` takes effect in epoch `e + ACTIVATION_DELAY_EPOCHS`, since
+//! the stake table of epoch `e + 1` is already in use by the time `e` ends.
+
+use std::collections::BTreeMap;
+
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    light_client::StateVerKey,
+    traits::{
+        signature_key::{SignatureKey, StakeTableEntryType},
+        signer::{Signer, SignerError},
+    },
+    utils::epoch_from_block_number,
+    PeerConfig,
+};
+
+/// Prefix of the bytes of every operation, so operations are told apart from other transactions
+/// and their signatures from signatures over anything else.
+pub const OPERATION_TAG: &[u8] = b"HOTSHOT_VALIDATOR_OPERATION_V1";
+
+/// Number of epochs after the epoch it is decided in that an operation takes effect.
+pub const ACTIVATION_DELAY_EPOCHS: u64 = 2;
+
+/// The epoch in which an operation decided at block `height` takes effect.
+#[must_use]
+pub fn effective_epoch(height: u64, epoch_height: u64) -> u64 {
+    epoch_from_block_number(height, epoch_height) + ACTIVATION_DELAY_EPOCHS
+}
+
+/// A change to the stake table requested by a validator.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub enum ValidatorOperation<K: SignatureKey> {
+    /// Join the stake table as `peer`
+    Register {
+        /// The stake-table entry of the new validator
+        peer: PeerConfig<K>,
+    },
+    /// Change the stake of a validator
+    UpdateStake {
+        /// The validator
+        key: K,
+        /// Its new stake, which must not be zero
+        stake: u64,
+    },
+    /// Replace the keys of a validator, keeping its stake
+    RotateKey {
+        /// The current consensus key of the validator
+        key: K,
+        /// Its new consensus key, which must co-sign the operation
+        new_key: K,
+        /// Its new state verification key
+        new_state_ver_key: StateVerKey,
+    },
+    /// Leave the stake table
+    Exit {
+        /// The validator
+        key: K,
+    },
+}
+
+impl<K: SignatureKey> ValidatorOperation<K> {
+    /// The consensus key of the validator the operation is for, which must sign it.
+    #[must_use]
+    pub fn key(&self) -> K {
+        match self {
+            Self::Register { peer } => K::public_key(&peer.stake_table_entry),
+            Self::UpdateStake { key, .. } | Self::RotateKey { key, .. } | Self::Exit { key } => {
+                key.clone()
+            }
+        }
+    }
+
+    /// The new consensus key of a key rotation.
+    #[must_use]
+    pub fn new_key(&self) -> Option<&K> {
+        match self {
+            Self::RotateKey { new_key, .. } => Some(new_key),
+            _ => None,
+        }
+    }
+}
+
+/// Transactions which can carry a [`SignedOperation`].
+pub trait OperationTransaction: Sized {
+    /// A transaction carrying `bytes`, or `None` if the transaction cannot carry them, e.g.
+    /// because they are too long.
+    fn from_operation_bytes(bytes: Vec<u8>) -> Option<Self>;
+
+    /// The bytes the transaction carries, which may be an operation.
+    fn operation_bytes(&self) -> &[u8];
+}
+
+/// A [`ValidatorOperation`], signed by the validator it is for.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(deserialize = ""))]
+pub struct SignedOperation<K: SignatureKey> {
+    /// The operation
+    pub operation: ValidatorOperation<K>,
+    /// The chain the operation is for, so it cannot be replayed on another chain
+    pub chain_id: u64,
+    /// The number of operations of the validator before this one, so it cannot be replayed
+    pub nonce: u64,
+    /// Signature of [`ValidatorOperation::key`]
+    pub signature: K::PureAssembledSignatureType,
+    /// Signature of the new key of a key rotation, proving the validator holds it
+    pub new_key_signature: Option<K::PureAssembledSignatureType>,
+}
+
+impl<K: SignatureKey + 'static> SignedOperation<K> {
+    /// Sign `operation` with `signer`, and with `new_key_signer` for a key rotation.
+    ///
+    /// # Errors
+    /// If a signer does not hold the key it has to sign with, or fails to sign.
+    pub async fn sign(
+        operation: ValidatorOperation<K>,
+        chain_id: u64,
+        nonce: u64,
+        signer: &dyn Signer<K>,
+        new_key_signer: Option<&dyn Signer<K>>,
+    ) -> Result<Self, SignerError> {
+        if *signer.public_key() != operation.key() {
+            return Err(SignerError::Failed(format!(
+                "the operation must be signed by {}",
+                operation.key()
+            )));
+        }
+        let digest = Self::digest(&operation, chain_id, nonce);
+        let signature = signer.sign(&digest).await?;
+        let new_key_signature = match (operation.new_key(), new_key_signer) {
+            (None, _) => None,
+            (Some(new_key), Some(new_key_signer)) if new_key_signer.public_key() == new_key => {
+                Some(new_key_signer.sign(&digest).await?)
+            }
+            (Some(new_key), _) => {
+                return Err(SignerError::Failed(format!(
+                    "a key rotation must be co-signed by the new key {new_key}"
+                )));
+            }
+        };
+
+        Ok(Self {
+            operation,
+            chain_id,
+            nonce,
+            signature,
+            new_key_signature,
+        })
+    }
+
+    /// The digest the validator signs.
+    ///
+    /// # Panics
+    /// If the operation fails to serialize, which it can't.
+    fn digest(operation: &ValidatorOperation<K>, chain_id: u64, nonce: u64) -> Vec<u8> {
+        let operation = bincode::serialize(operation).expect("Validator operations serialize");
+        Sha256::new()
+            .chain_update(OPERATION_TAG)
+            .chain_update(chain_id.to_le_bytes())
+            .chain_update(nonce.to_le_bytes())
+            .chain_update(operation)
+            .finalize()
+            .to_vec()
+    }
+
+    /// Whether the operation is signed by its validator, and by the new key of a key rotation.
+    #[must_use]
+    pub fn is_signed(&self) -> bool {
+        let digest = Self::digest(&self.operation, self.chain_id, self.nonce);
+        let new_key_signed = match (self.operation.new_key(), &self.new_key_signature) {
+            (None, _) => true,
+            (Some(new_key), Some(signature)) => new_key.validate(signature, &digest),
+            (Some(_), None) => false,
+        };
+        new_key_signed && self.operation.key().validate(&self.signature, &digest)
+    }
+
+    /// The bytes of the operation, as carried by a transaction.
+    ///
+    /// # Panics
+    /// If the operation fails to serialize, which it can't.
+    #[must_use]
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = OPERATION_TAG.to_vec();
+        bincode::serialize_into(&mut bytes, self).expect("Validator operations serialize");
+        bytes
+    }
+
+    /// The operation in `bytes`, or `None` if they are not an operation.
+    #[must_use]
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        bincode::deserialize(bytes.strip_prefix(OPERATION_TAG)?).ok()
+    }
+
+    /// A transaction carrying the operation, or `None` if it does not fit in one.
+    #[must_use]
+    pub fn to_transaction<T: OperationTransaction>(&self) -> Option<T> {
+        T::from_operation_bytes(self.encode())
+    }
+
+    /// The operation carried by `transaction`, if it carries one.
+    #[must_use]
+    pub fn from_transaction<T: OperationTransaction>(transaction: &T) -> Option<Self> {
+        Self::decode(transaction.operation_bytes())
+    }
+}
+
+/// Reasons an operation is not applied.
+#[derive(Clone, Debug, Error, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OperationError {
+    /// The operation is for another chain
+    #[error("Operation is for chain {got}, not {expected}")]
+    WrongChain {
+        /// Our chain
+        expected: u64,
+        /// The chain of the operation
+        got: u64,
+    },
+    /// A signature is missing or does not verify
+    #[error("Operation is not signed by its validator")]
+    InvalidSignature,
+    /// The nonce is not the number of previous operations of the validator
+    #[error("Expected nonce {expected}, got {got}")]
+    WrongNonce {
+        /// The nonce the next operation of the validator must have
+        expected: u64,
+        /// The nonce of the operation
+        got: u64,
+    },
+    /// A validator registers while it is in the stake table
+    #[error("Validator is already registered")]
+    AlreadyRegistered,
+    /// The operation is for a validator which is not in the stake table
+    #[error("Validator is not registered")]
+    NotRegistered,
+    /// A key rotation is to a key which is or was in use
+    #[error("The new key is or was already in use")]
+    KeyInUse,
+    /// The operation would leave a validator in the stake table without stake
+    #[error("Stake must not be zero, exit instead")]
+    ZeroStake,
+}
+
+/// The validators of a chain, and the nonce of each key's next operation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidatorSet<K: SignatureKey> {
+    /// The chain operations must be for
+    chain_id: u64,
+    /// The stake-table entry of each validator
+    validators: BTreeMap<K, PeerConfig<K>>,
+    /// The nonce of the next operation of each key ever used, including those of validators which
+    /// exited or rotated their key, so their old operations cannot be replayed
+    nonces: BTreeMap<K, u64>,
+}
+
+impl<K: SignatureKey + 'static> ValidatorSet<K> {
+    /// The validators of `stake_table`, on the chain `chain_id`.
+    #[must_use]
+    pub fn new(chain_id: u64, stake_table: Vec<PeerConfig<K>>) -> Self {
+        let validators: BTreeMap<_, _> = stake_table
+            .into_iter()
+            .map(|peer| (K::public_key(&peer.stake_table_entry), peer))
+            .collect();
+        Self {
+            chain_id,
+            nonces: validators.keys().map(|key| (key.clone(), 0)).collect(),
+            validators,
+        }
+    }
+
+    /// The stake table, in key order.
+    #[must_use]
+    pub fn stake_table(&self) -> Vec<PeerConfig<K>> {
+        self.validators.values().cloned().collect()
+    }
+
+    /// The stake-table entry of the validator with `key`, if it is registered.
+    #[must_use]
+    pub fn validator(&self, key: &K) -> Option<&PeerConfig<K>> {
+        self.validators.get(key)
+    }
+
+    /// The nonce the next operation signed by `key` must have.
+    #[must_use]
+    pub fn next_nonce(&self, key: &K) -> u64 {
+        self.nonces.get(key).copied().unwrap_or_default()
+    }
+
+    /// Apply `operation`, which takes effect in `epoch`.
+    ///
+    /// # Errors
+    /// If the operation is invalid, in which case the set is unchanged.
+    pub fn apply(
+        &mut self,
+        operation: &SignedOperation<K>,
+        epoch: u64,
+    ) -> Result<(), OperationError> {
+        if operation.chain_id != self.chain_id {
+            return Err(OperationError::WrongChain {
+                expected: self.chain_id,
+                got: operation.chain_id,
+            });
+        }
+        if !operation.is_signed() {
+            return Err(OperationError::InvalidSignature);
+        }
+        let key = operation.operation.key();
+        let expected = self.next_nonce(&key);
+        if operation.nonce != expected {
+            return Err(OperationError::WrongNonce {
+                expected,
+                got: operation.nonce,
+            });
+        }
+
+        match &operation.operation {
+            ValidatorOperation::Register { peer } => {
+                if self.validators.contains_key(&key) {
+                    return Err(OperationError::AlreadyRegistered);
+                }
+                if peer.stake_table_entry.stake() == U256::zero() {
+                    return Err(OperationError::ZeroStake);
+                }
+                let peer = PeerConfig {
+                    bonded_since: epoch,
+                    ..peer.clone()
+                };
+                self.validators.insert(key.clone(), peer);
+            }
+            ValidatorOperation::UpdateStake { stake, .. } => {
+                if *stake == 0 {
+                    return Err(OperationError::ZeroStake);
+                }
+                let peer = self
+                    .validators
+                    .get_mut(&key)
+                    .ok_or(OperationError::NotRegistered)?;
+                peer.stake_table_entry = key.stake_table_entry(*stake);
+            }
+            ValidatorOperation::RotateKey {
+                new_key,
+                new_state_ver_key,
+                ..
+            } => {
+                if self.nonces.contains_key(new_key) {
+                    return Err(OperationError::KeyInUse);
+                }
+                let peer = self
+                    .validators
+                    .remove(&key)
+                    .ok_or(OperationError::NotRegistered)?;
+                let stake = u64::try_from(peer.stake_table_entry.stake()).unwrap_or(u64::MAX);
+                let peer = PeerConfig {
+                    stake_table_entry: new_key.stake_table_entry(stake),
+                    state_ver_key: new_state_ver_key.clone(),
+                    ..peer
+                };
+                self.validators.insert(new_key.clone(), peer);
+                self.nonces.insert(new_key.clone(), expected + 1);
+            }
+            ValidatorOperation::Exit { .. } => {
+                self.validators
+                    .remove(&key)
+                    .ok_or(OperationError::NotRegistered)?;
+            }
+        }
+        self.nonces.insert(key, expected + 1);
+
+        Ok(())
+    }
+}
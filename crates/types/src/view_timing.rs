@@ -0,0 +1,197 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Timestamps of the phases of each view, used to measure where the time in a view goes, and the
+//! pacing of proposals within a view.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::{collections::BTreeMap, time::Duration};
+
+use serde::{Deserialize, Serialize};
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+/// A point in the life of a view.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ViewPhase {
+    /// We entered the view
+    Start,
+    /// We received a valid proposal for the view
+    ProposalReceived,
+    /// We voted on the proposal
+    VoteSent,
+    /// We formed a QC for the proposal, as the next leader
+    QcFormed,
+    /// The proposal of the view was decided
+    Decided,
+}
+
+impl ViewPhase {
+    /// Number of phases
+    const COUNT: usize = 5;
+
+    /// Index of this phase in [`PhaseTimes`]
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// When each phase of a view happened.
+type PhaseTimes = [Option<Instant>; ViewPhase::COUNT];
+
+/// Phase timestamps of the views which have not been decided yet.
+#[derive(Clone, Debug)]
+pub struct ViewTimeline<VIEW: Ord> {
+    /// Phase timestamps, by view
+    views: BTreeMap<VIEW, PhaseTimes>,
+}
+
+impl<VIEW: Ord> Default for ViewTimeline<VIEW> {
+    fn default() -> Self {
+        Self {
+            views: BTreeMap::new(),
+        }
+    }
+}
+
+impl<VIEW: Ord + Copy> ViewTimeline<VIEW> {
+    /// Record that `view` reached `phase` at `now`.
+    ///
+    /// Returns how long after the first recorded phase of the view this happened, or `None` if
+    /// this is the first phase recorded for the view or the phase was already recorded. Phases can
+    /// arrive out of order, e.g. a proposal may be received before we enter its view.
+    pub fn record(&mut self, view: VIEW, phase: ViewPhase, now: Instant) -> Option<Duration> {
+        let times = self.views.entry(view).or_default();
+        if times[phase.index()].is_some() {
+            return None;
+        }
+        let first = times.iter().flatten().min().copied();
+        times[phase.index()] = Some(now);
+
+        first.map(|first| now.saturating_duration_since(first))
+    }
+
+    /// Whether `view` reached `phase`.
+    #[must_use]
+    pub fn reached(&self, view: VIEW, phase: ViewPhase) -> bool {
+        self.views
+            .get(&view)
+            .is_some_and(|times| times[phase.index()].is_some())
+    }
+
+    /// When `view` reached `phase`, if it did.
+    #[must_use]
+    pub fn time_of(&self, view: VIEW, phase: ViewPhase) -> Option<Instant> {
+        self.views.get(&view).and_then(|times| times[phase.index()])
+    }
+
+    /// Forget the views before `view`.
+    pub fn prune(&mut self, view: VIEW) {
+        self.views = self.views.split_off(&view);
+    }
+
+    /// Number of views with recorded phases.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.views.len()
+    }
+
+    /// Whether no phases are recorded.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+}
+
+/// When a leader sends its proposal.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Pacing {
+    /// Propose as soon as the QC and the block for the view are ready, so views move at the speed
+    /// of the network.
+    #[default]
+    Responsive,
+    /// Propose no earlier than `interval` after the leader started on the view, so that views
+    /// take the same time whichever node leads them and fast leaders cannot crowd out
+    /// transactions which take longer to reach them. Should be well below the view timeout.
+    FixedInterval {
+        /// Least time between the start of a view and its proposal
+        interval: Duration,
+    },
+}
+
+impl Pacing {
+    /// How much longer to hold a proposal whose view started `elapsed` ago.
+    #[must_use]
+    pub fn proposal_delay(self, elapsed: Duration) -> Duration {
+        match self {
+            Self::Responsive => Duration::ZERO,
+            Self::FixedInterval { interval } => interval.saturating_sub(elapsed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn phases_are_measured_from_the_first_one() {
+        let mut timeline = ViewTimeline::default();
+        let start = Instant::now();
+
+        assert_eq!(
+            timeline.record(1u64, ViewPhase::ProposalReceived, start),
+            None
+        );
+        assert_eq!(
+            timeline.record(1, ViewPhase::Start, start + Duration::from_millis(5)),
+            Some(Duration::from_millis(5))
+        );
+        assert_eq!(
+            timeline.record(1, ViewPhase::VoteSent, start + Duration::from_millis(20)),
+            Some(Duration::from_millis(20))
+        );
+        // Repeated phases are only recorded once
+        assert_eq!(
+            timeline.record(1, ViewPhase::VoteSent, start + Duration::from_millis(30)),
+            None
+        );
+    }
+
+    #[test]
+    fn prune_forgets_older_views() {
+        let mut timeline = ViewTimeline::default();
+        let now = Instant::now();
+        for view in 0u64..10 {
+            timeline.record(view, ViewPhase::Start, now);
+        }
+
+        timeline.prune(7);
+        assert_eq!(timeline.len(), 3);
+    }
+
+    #[test]
+    fn fixed_interval_holds_proposals_until_the_interval_is_over() {
+        let pacing = Pacing::FixedInterval {
+            interval: Duration::from_millis(500),
+        };
+
+        assert_eq!(
+            pacing.proposal_delay(Duration::from_millis(200)),
+            Duration::from_millis(300)
+        );
+        assert_eq!(
+            pacing.proposal_delay(Duration::from_millis(800)),
+            Duration::ZERO
+        );
+        assert_eq!(
+            Pacing::Responsive.proposal_delay(Duration::ZERO),
+            Duration::ZERO
+        );
+    }
+}
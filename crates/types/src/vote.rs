@@ -7,6 +7,7 @@
 //! Vote, Accumulator, and Certificate Types
 
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, HashMap},
     marker::PhantomData,
     num::NonZeroU64,
@@ -28,6 +29,7 @@ use crate::{
         node_implementation::{NodeType, Versions},
         signature_key::{SignatureKey, StakeTableEntryType},
     },
+    vote_aggregation::PartialCertificate,
 };
 
 /// A simple vote that has a signer and commitment to the data voted on.
@@ -75,7 +77,7 @@ pub trait Certificate<TYPES: NodeType, T>: HasViewNumber<TYPES> {
     /// Checks if the cert is valid in the given epoch
     fn is_valid_cert<V: Versions>(
         &self,
-        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
         threshold: NonZeroU64,
         upgrade_lock: &UpgradeLock<TYPES, V>,
     ) -> impl std::future::Future<Output = bool>;
@@ -90,7 +92,7 @@ pub trait Certificate<TYPES: NodeType, T>: HasViewNumber<TYPES> {
     fn stake_table<MEMBERSHIP: Membership<TYPES>>(
         membership: &MEMBERSHIP,
         epoch: TYPES::Epoch,
-    ) -> Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>;
+    ) -> Cow<'_, [<TYPES::SignatureKey as SignatureKey>::StakeTableEntry]>;
 
     /// Get Total Nodes from Membership implementation.
     fn total_nodes<MEMBERSHIP: Membership<TYPES>>(
@@ -156,6 +158,34 @@ impl<
         V: Versions,
     > VoteAccumulator<TYPES, VOTE, CERT, V>
 {
+    /// An accumulator without any votes, for votes of the versions in `upgrade_lock`.
+    #[must_use]
+    pub fn new(upgrade_lock: UpgradeLock<TYPES, V>) -> Self {
+        Self {
+            vote_outcomes: HashMap::new(),
+            signers: HashMap::new(),
+            phantom: PhantomData,
+            upgrade_lock,
+        }
+    }
+
+    /// Add a vote, returning the accumulator if more votes are needed, or the certificate once
+    /// the votes exceed the threshold.
+    ///
+    /// This is [`Self::accumulate`] for callers outside the consensus tasks, such as aggregators
+    /// and relays, which assemble certificates from the votes they observe.
+    pub async fn append(
+        mut self,
+        vote: &VOTE,
+        membership: &TYPES::Membership,
+        epoch: TYPES::Epoch,
+    ) -> Either<Self, CERT> {
+        match self.accumulate(vote, membership, epoch).await {
+            Either::Left(()) => Either::Left(self),
+            Either::Right(cert) => Either::Right(cert),
+        }
+    }
+
     /// Add a vote to the total accumulated votes for the given epoch.
     /// Returns the accumulator or the certificate if we
     /// have accumulated enough votes to exceed the threshold for creating a certificate.
@@ -186,6 +216,22 @@ impl<
             return Either::Left(());
         }
 
+        self.accumulate_verified(vote, vote_commitment, membership, epoch)
+    }
+
+    /// Add a vote whose signature has already been checked against `vote_commitment`, which must
+    /// be the commitment to its versioned vote data.
+    ///
+    /// This lets the caller check signatures elsewhere, off the task collecting the votes.
+    pub fn accumulate_verified(
+        &mut self,
+        vote: &VOTE,
+        vote_commitment: Commitment<VersionedVoteData<TYPES, VOTE::Commitment, V>>,
+        membership: &TYPES::Membership,
+        epoch: TYPES::Epoch,
+    ) -> Either<(), CERT> {
+        let key = vote.signing_key();
+
         let Some(stake_table_entry) = CERT::stake_table_entry(membership, &key, epoch) else {
             return Either::Left(());
         };
@@ -224,29 +270,119 @@ impl<
         total_vote_map.insert(key, (vote.signature(), vote_commitment));
 
         if *total_stake_casted >= CERT::threshold(membership, epoch).into() {
-            // Assemble QC
-            let real_qc_pp: <<TYPES as NodeType>::SignatureKey as SignatureKey>::QcParams =
-                <TYPES::SignatureKey as SignatureKey>::public_parameter(
-                    stake_table,
-                    U256::from(CERT::threshold(membership, epoch)),
-                );
-
-            let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(
-                &real_qc_pp,
-                signers.as_bitslice(),
-                &sig_list[..],
-            );
-
-            let cert = CERT::create_signed_certificate::<V>(
+            return Either::Right(Self::assemble_certificate(
+                stake_table.into_owned(),
+                signers,
+                sig_list,
                 vote_commitment,
                 vote.date().clone(),
-                real_qc_sig,
                 vote.view_number(),
+                membership,
+                epoch,
+            ));
+        }
+        Either::Left(())
+    }
+
+    /// Add the votes of a partial certificate whose signatures have already been checked against
+    /// `vote_commitment`, which must be the commitment to its versioned vote data.
+    ///
+    /// Signers which have been counted already are skipped. The signatures were only checked as
+    /// an aggregate, which says nothing of a subset of them, so if any signer is skipped the
+    /// signatures of the others are checked one by one, and none are counted if any is invalid.
+    pub fn accumulate_partial(
+        &mut self,
+        partial: &PartialCertificate<TYPES, VOTE::Commitment>,
+        vote_commitment: Commitment<VersionedVoteData<TYPES, VOTE::Commitment, V>>,
+        membership: &TYPES::Membership,
+        epoch: TYPES::Epoch,
+    ) -> Either<(), CERT> {
+        let stake_table = CERT::stake_table(membership, epoch);
+        if partial.signers.len() != stake_table.len()
+            || partial.signers.count_ones() != partial.signatures.len()
+        {
+            return Either::Left(());
+        }
+
+        let (total_stake_casted, total_vote_map) = self
+            .vote_outcomes
+            .entry(vote_commitment)
+            .or_insert_with(|| (U256::from(0), BTreeMap::new()));
+        let (signers, sig_list) = self
+            .signers
+            .entry(vote_commitment)
+            .or_insert((bitvec![0; CERT::total_nodes(membership, epoch)], Vec::new()));
+        let fresh: Vec<_> = partial
+            .signers
+            .iter_ones()
+            .zip(&partial.signatures)
+            .filter(|(index, _)| signers.get(*index).as_deref() == Some(&false))
+            .collect();
+        if fresh.is_empty() {
+            return Either::Left(());
+        }
+        if fresh.len() < partial.signatures.len()
+            && !fresh.iter().all(|(index, signature)| {
+                TYPES::SignatureKey::public_key(&stake_table[*index])
+                    .validate(signature, vote_commitment.as_ref())
+            })
+        {
+            error!("Partial certificate has an invalid vote");
+            return Either::Left(());
+        }
+
+        for (index, signature) in fresh {
+            let entry = &stake_table[index];
+            signers.set(index, true);
+            sig_list.push(signature.clone());
+            *total_stake_casted += entry.stake();
+            total_vote_map.insert(
+                TYPES::SignatureKey::public_key(entry),
+                (signature.clone(), vote_commitment),
             );
-            return Either::Right(cert);
+        }
+
+        if *total_stake_casted >= CERT::threshold(membership, epoch).into() {
+            return Either::Right(Self::assemble_certificate(
+                stake_table.into_owned(),
+                signers,
+                sig_list,
+                vote_commitment,
+                partial.data.clone(),
+                partial.view_number,
+                membership,
+                epoch,
+            ));
         }
         Either::Left(())
     }
+
+    /// Assemble the certificate of the votes of `signers` for `data`.
+    #[allow(clippy::too_many_arguments)]
+    fn assemble_certificate(
+        stake_table: Vec<<TYPES::SignatureKey as SignatureKey>::StakeTableEntry>,
+        signers: &BitVec,
+        sig_list: &[<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType],
+        vote_commitment: Commitment<VersionedVoteData<TYPES, VOTE::Commitment, V>>,
+        data: VOTE::Commitment,
+        view: TYPES::View,
+        membership: &TYPES::Membership,
+        epoch: TYPES::Epoch,
+    ) -> CERT {
+        let real_qc_pp: <<TYPES as NodeType>::SignatureKey as SignatureKey>::QcParams =
+            <TYPES::SignatureKey as SignatureKey>::public_parameter(
+                stake_table,
+                U256::from(CERT::threshold(membership, epoch)),
+            );
+
+        let real_qc_sig = <TYPES::SignatureKey as SignatureKey>::assemble(
+            &real_qc_pp,
+            signers.as_bitslice(),
+            sig_list,
+        );
+
+        CERT::create_signed_certificate::<V>(vote_commitment, data, real_qc_sig, view)
+    }
 }
 
 /// Mapping of commitments to vote tokens by key.
@@ -0,0 +1,203 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Aggregation of quorum votes by a few nodes on behalf of the leader.
+//!
+//! Every quorum vote normally goes to the leader of the next view, which checks each signature
+//! before counting it, so the leader's work grows with the size of the committee. When aggregation
+//! is enabled, each voter instead sends its vote to one of a few aggregators, which rotate with the
+//! view. An aggregator checks the votes of the voters assigned to it and forwards them to the leader
+//! in a [`PartialCertificate`], whose signatures the leader checks at once as an aggregate. The
+//! leader merges the partial certificates it receives, like votes, until they reach the threshold.
+//!
+//! Aggregators only relay votes. A faulty one can withhold the votes of its voters, which costs the
+//! view but never safety, and the aggregators change with every view.
+
+use std::{collections::BTreeMap, num::NonZeroUsize, time::Duration};
+
+use bitvec::{bitvec, slice::BitSlice, vec::BitVec};
+use committable::Commitment;
+use primitive_types::U256;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    simple_vote::{QuorumData2, VersionedVoteData, Voteable},
+    traits::{
+        election::Membership,
+        node_implementation::{NodeType, Versions},
+        signature_key::SignatureKey,
+    },
+    vote::HasViewNumber,
+};
+
+/// How quorum votes are aggregated before they reach the leader.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AggregationConfig {
+    /// Whether votes are sent to aggregators instead of the leader
+    pub enabled: bool,
+    /// Number of aggregators in each view
+    pub aggregators: NonZeroUsize,
+    /// How long an aggregator waits for the rest of its voters after the first vote of a view,
+    /// before it forwards the votes it has
+    pub flush_delay: Duration,
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            aggregators: NonZeroUsize::new(4).unwrap(),
+            flush_delay: Duration::from_millis(100),
+        }
+    }
+}
+
+impl AggregationConfig {
+    /// Position in the stake table of each aggregator of the votes of `view`.
+    ///
+    /// The aggregators are consecutive members of the stake table, starting from an offset which
+    /// moves by one every view.
+    fn aggregator_indices(&self, view: u64, total_nodes: usize) -> Vec<usize> {
+        let count = self.aggregators.get().min(total_nodes);
+        (0..count as u64)
+            .map(|slot| usize::try_from((view + slot) % total_nodes as u64).unwrap_or(0))
+            .collect()
+    }
+
+    /// The node which `voter` sends its vote for `view` to, or `None` if aggregation is disabled
+    /// or `voter` has no stake.
+    #[must_use]
+    pub fn aggregator_for<TYPES: NodeType>(
+        &self,
+        voter: &TYPES::SignatureKey,
+        membership: &TYPES::Membership,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> Option<TYPES::SignatureKey> {
+        if !self.enabled {
+            return None;
+        }
+        let stake_table = membership.stake_table(epoch);
+        let voter = stake_table
+            .iter()
+            .position(|entry| TYPES::SignatureKey::public_key(entry) == *voter)?;
+        let aggregators = self.aggregator_indices(*view, stake_table.len());
+
+        Some(TYPES::SignatureKey::public_key(
+            &stake_table[aggregators[voter % aggregators.len()]],
+        ))
+    }
+
+    /// Number of voters which send their votes for `view` to `aggregator`.
+    #[must_use]
+    pub fn voters_of<TYPES: NodeType>(
+        &self,
+        aggregator: &TYPES::SignatureKey,
+        membership: &TYPES::Membership,
+        view: TYPES::View,
+        epoch: TYPES::Epoch,
+    ) -> usize {
+        let stake_table = membership.stake_table(epoch);
+        let aggregators = self.aggregator_indices(*view, stake_table.len());
+        let Some(slot) = aggregators
+            .iter()
+            .position(|index| TYPES::SignatureKey::public_key(&stake_table[*index]) == *aggregator)
+        else {
+            return 0;
+        };
+
+        (0..stake_table.len())
+            .filter(|voter| voter % aggregators.len() == slot)
+            .count()
+    }
+}
+
+/// Votes on the same data, checked by an aggregator and forwarded to the leader together.
+#[derive(Serialize, Deserialize, Eq, Hash, PartialEq, Debug, Clone)]
+pub struct PartialCertificate<TYPES: NodeType, VOTEABLE: Voteable<TYPES>> {
+    /// The data the votes are for
+    pub data: VOTEABLE,
+    /// The view the votes are for
+    pub view_number: TYPES::View,
+    /// Which members of the stake table voted
+    pub signers: BitVec,
+    /// The signatures of the signers, in the order of the stake table
+    pub signatures: Vec<<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType>,
+}
+
+/// Quorum votes forwarded by an aggregator
+pub type PartialQuorumCertificate<TYPES> = PartialCertificate<TYPES, QuorumData2<TYPES>>;
+
+impl<TYPES: NodeType, VOTEABLE: Voteable<TYPES>> PartialCertificate<TYPES, VOTEABLE> {
+    /// Combine the signatures of the votes for `data` in `view`, keyed by the position of their
+    /// signers in a stake table of `total_nodes` members.
+    #[must_use]
+    pub fn new(
+        data: VOTEABLE,
+        view_number: TYPES::View,
+        total_nodes: usize,
+        votes: &BTreeMap<usize, <TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType>,
+    ) -> Self {
+        let mut signers = bitvec![0; total_nodes];
+        for index in votes.keys() {
+            signers.set(*index, true);
+        }
+
+        Self {
+            data,
+            view_number,
+            signers,
+            signatures: votes.values().cloned().collect(),
+        }
+    }
+
+    /// Whether every signature is its signer's signature of `vote_commitment`, checked at once as
+    /// an aggregate.
+    #[must_use]
+    pub fn is_valid<V: Versions>(
+        &self,
+        stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+        vote_commitment: Commitment<VersionedVoteData<TYPES, VOTEABLE, V>>,
+    ) -> bool {
+        signatures_are_valid::<TYPES>(
+            stake_table,
+            &self.signers,
+            &self.signatures,
+            vote_commitment.as_ref(),
+        )
+    }
+}
+
+/// Whether `signatures` are the signatures of `data` by the members of `stake_table` marked in
+/// `signers`, in order, checked at once as an aggregate.
+#[must_use]
+pub fn signatures_are_valid<TYPES: NodeType>(
+    stake_table: &[<TYPES::SignatureKey as SignatureKey>::StakeTableEntry],
+    signers: &BitSlice,
+    signatures: &[<TYPES::SignatureKey as SignatureKey>::PureAssembledSignatureType],
+    data: &[u8],
+) -> bool {
+    if signatures.is_empty()
+        || signers.len() != stake_table.len()
+        || signers.count_ones() != signatures.len()
+    {
+        return false;
+    }
+
+    // Any number of signers will do, only the signatures are checked here
+    let qc_pp =
+        <TYPES::SignatureKey as SignatureKey>::public_parameter(stake_table.to_vec(), U256::zero());
+    let qc = <TYPES::SignatureKey as SignatureKey>::assemble(&qc_pp, signers, signatures);
+    <TYPES::SignatureKey as SignatureKey>::check(&qc_pp, data, &qc)
+}
+
+impl<TYPES: NodeType, VOTEABLE: Voteable<TYPES>> HasViewNumber<TYPES>
+    for PartialCertificate<TYPES, VOTEABLE>
+{
+    fn view_number(&self) -> TYPES::View {
+        self.view_number
+    }
+}
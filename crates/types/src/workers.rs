@@ -0,0 +1,51 @@
+// Copyright (c) 2021-2024 Espresso Systems (espressosys.com)
+// This file is part of the HotShot repository.
+
+// You should have received a copy of the MIT License
+// along with the HotShot repository. If not, see <https://mit-license.org/>.
+
+//! Configuration of the worker threads that run heavyweight work away from the consensus tasks.
+//!
+//! Validating a proposed block header against the parent state is up to the application, and may
+//! take a long time. It runs on its own threads so that it cannot hold up the threads that handle
+//! votes, timeouts and network messages. Checking the signatures on votes and proposals is cheap
+//! one at a time but adds up on a busy leader, so it gets a pool of its own too, where the checks
+//! for different tasks can run in parallel.
+
+use serde::{Deserialize, Serialize};
+
+/// Default number of threads that validate and apply block headers.
+const DEFAULT_STATE_THREADS: usize = 2;
+
+/// Default number of threads that check signatures.
+const DEFAULT_VERIFICATION_THREADS: usize = 4;
+
+/// Sizes of the worker pools of a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkerConfig {
+    /// Number of threads that validate proposed block headers and apply them to the state
+    #[serde(default = "default_state_threads")]
+    pub state_threads: usize,
+    /// Number of threads that check the signatures on votes and proposals
+    #[serde(default = "default_verification_threads")]
+    pub verification_threads: usize,
+}
+
+/// Default value of [`WorkerConfig::state_threads`], for serde.
+fn default_state_threads() -> usize {
+    DEFAULT_STATE_THREADS
+}
+
+/// Default value of [`WorkerConfig::verification_threads`], for serde.
+fn default_verification_threads() -> usize {
+    DEFAULT_VERIFICATION_THREADS
+}
+
+impl Default for WorkerConfig {
+    fn default() -> Self {
+        Self {
+            state_threads: default_state_threads(),
+            verification_threads: default_verification_threads(),
+        }
+    }
+}